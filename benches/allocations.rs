@@ -0,0 +1,106 @@
+//! Counts the allocations made while parsing ways and relations
+//!
+//! Compare `cargo bench --bench allocations` with
+//! `cargo bench --bench allocations --features smallvec`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use osm_types::{opl, xml, Element, Id, NodeId, Relation, Way, WayId};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const WAYS: usize = 100_000;
+
+/// Untagged ways of 2 to 9 nodes and turn restrictions, like most of a planet
+fn elements() -> Vec<Element> {
+    let ways = (1..=WAYS as i64).map(|id| {
+        let nodes = (0..2 + id % 8).map(|node| Id(id * 10 + node));
+        Element::Way(
+            Way::builder(Id(id))
+                .nodes(nodes)
+                .build()
+                .expect("way is valid"),
+        )
+    });
+    let relations = (1..=WAYS as i64 / 10).map(|id| {
+        let relation = Relation::builder(Id(id))
+            .member(WayId(1), "from")
+            .member(NodeId(2), "via")
+            .member(WayId(3), "to")
+            .tag("type", "restriction")
+            .build();
+        Element::Relation(relation.expect("relation is valid"))
+    });
+    ways.chain(relations).collect()
+}
+
+/// Parses the input and prints the allocations made
+fn count(format: &str, parse: impl FnOnce() -> usize) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let elements = parse();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!(
+        "{format}: {elements} elements, {allocations} allocations ({:.2} per element) in {:?}",
+        allocations as f64 / elements as f64,
+        start.elapsed(),
+    );
+}
+
+fn main() {
+    println!(
+        "smallvec {}",
+        if cfg!(feature = "smallvec") {
+            "on"
+        } else {
+            "off"
+        }
+    );
+    let elements = elements();
+
+    let mut writer = xml::Writer::new(vec![]);
+    for element in &elements {
+        writer.write(element).expect("writing to memory succeeds");
+    }
+    writer.finish().expect("writing to memory succeeds");
+    let xml = writer.into_inner();
+    count("xml", || {
+        let mut count = 0;
+        for element in xml::Reader::new(xml.as_slice()) {
+            element.expect("document is valid");
+            count += 1;
+        }
+        count
+    });
+
+    let lines = elements.iter().map(opl::to_string).collect::<Vec<_>>();
+    count("opl", || {
+        for line in &lines {
+            opl::parse(line).expect("line is valid");
+        }
+        lines.len()
+    });
+}
@@ -0,0 +1,398 @@
+//! Access restrictions for transport modes
+//!
+//! <https://wiki.openstreetmap.org/wiki/Key:access>
+
+use crate::{Element, Tags, Way};
+
+/// Means of transport that an access tag can restrict
+///
+/// Modes form a hierarchy rooted at [TransportMode::All] (the `access` key), where
+/// more specific modes inherit restrictions from their parent unless tagged otherwise.
+///
+/// <https://wiki.openstreetmap.org/wiki/Key:access#Transport_mode_restrictions>
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransportMode {
+    All,
+    Foot,
+    Horse,
+    Vehicle,
+    Bicycle,
+    Carriage,
+    MotorVehicle,
+    Motorcycle,
+    Moped,
+    Mofa,
+    Motorcar,
+    Motorhome,
+    Goods,
+    Hgv,
+    Agricultural,
+    Psv,
+    Bus,
+    Taxi,
+}
+
+impl TransportMode {
+    /// Key used to tag restrictions for this mode
+    pub fn key(self) -> &'static str {
+        match self {
+            TransportMode::All => "access",
+            TransportMode::Foot => "foot",
+            TransportMode::Horse => "horse",
+            TransportMode::Vehicle => "vehicle",
+            TransportMode::Bicycle => "bicycle",
+            TransportMode::Carriage => "carriage",
+            TransportMode::MotorVehicle => "motor_vehicle",
+            TransportMode::Motorcycle => "motorcycle",
+            TransportMode::Moped => "moped",
+            TransportMode::Mofa => "mofa",
+            TransportMode::Motorcar => "motorcar",
+            TransportMode::Motorhome => "motorhome",
+            TransportMode::Goods => "goods",
+            TransportMode::Hgv => "hgv",
+            TransportMode::Agricultural => "agricultural",
+            TransportMode::Psv => "psv",
+            TransportMode::Bus => "bus",
+            TransportMode::Taxi => "taxi",
+        }
+    }
+
+    /// Mode tagged with `key`, the inverse of [TransportMode::key]
+    pub fn from_key(key: &str) -> Option<Self> {
+        use TransportMode::*;
+        [
+            All,
+            Foot,
+            Horse,
+            Vehicle,
+            Bicycle,
+            Carriage,
+            MotorVehicle,
+            Motorcycle,
+            Moped,
+            Mofa,
+            Motorcar,
+            Motorhome,
+            Goods,
+            Hgv,
+            Agricultural,
+            Psv,
+            Bus,
+            Taxi,
+        ]
+        .into_iter()
+        .find(|mode| mode.key() == key)
+    }
+
+    /// Next more general mode in the hierarchy
+    pub fn parent(self) -> Option<TransportMode> {
+        use TransportMode::*;
+        match self {
+            All => None,
+            Foot | Horse | Vehicle => Some(All),
+            Bicycle | Carriage | MotorVehicle => Some(Vehicle),
+            Motorcycle | Moped | Mofa | Motorcar | Motorhome | Goods | Hgv | Agricultural | Psv => {
+                Some(MotorVehicle)
+            }
+            Bus | Taxi => Some(Psv),
+        }
+    }
+
+    /// This mode followed by each of its ancestors, ending with [TransportMode::All]
+    pub fn hierarchy(self) -> impl Iterator<Item = TransportMode> {
+        std::iter::successors(Some(self), |mode| mode.parent())
+    }
+}
+
+/// Value of an access tag
+///
+/// <https://wiki.openstreetmap.org/wiki/Key:access#List_of_possible_values>
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AccessValue {
+    Yes,
+    No,
+    Private,
+    Permissive,
+    Permit,
+    Destination,
+    Delivery,
+    Customers,
+    Designated,
+    UseSidepath,
+    Dismount,
+    Agricultural,
+    Forestry,
+    Discouraged,
+    /// Any value not listed above
+    Unknown,
+}
+
+impl AccessValue {
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "yes" => AccessValue::Yes,
+            "no" => AccessValue::No,
+            "private" => AccessValue::Private,
+            "permissive" => AccessValue::Permissive,
+            "permit" => AccessValue::Permit,
+            "destination" => AccessValue::Destination,
+            "delivery" => AccessValue::Delivery,
+            "customers" => AccessValue::Customers,
+            "designated" => AccessValue::Designated,
+            "use_sidepath" => AccessValue::UseSidepath,
+            "dismount" => AccessValue::Dismount,
+            "agricultural" => AccessValue::Agricultural,
+            "forestry" => AccessValue::Forestry,
+            "discouraged" => AccessValue::Discouraged,
+            _ => AccessValue::Unknown,
+        }
+    }
+
+    /// Whether the general public may use the way in this mode
+    ///
+    /// Values that only allow specific groups (e.g. [AccessValue::Private]) and values
+    /// that require leaving the mode (e.g. [AccessValue::Dismount]) are not considered allowed.
+    pub fn is_allowed(self) -> bool {
+        matches!(
+            self,
+            AccessValue::Yes
+                | AccessValue::Permissive
+                | AccessValue::Permit
+                | AccessValue::Destination
+                | AccessValue::Delivery
+                | AccessValue::Customers
+                | AccessValue::Designated
+                | AccessValue::Discouraged
+        )
+    }
+}
+
+/// Outcome of resolving access for a [TransportMode]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Access<'a> {
+    /// Key of the tag that governs access
+    ///
+    /// For implied access, this is the `highway` key.
+    pub key: &'a str,
+    pub value: AccessValue,
+    /// Whether the value was derived from the `highway` type rather than tagged explicitly
+    pub implied: bool,
+}
+
+impl Access<'_> {
+    pub fn is_allowed(&self) -> bool {
+        self.value.is_allowed()
+    }
+}
+
+/// Finds the tag governing access for `mode`
+///
+/// Explicit tags are checked from most to least specific mode. If none apply, the
+/// [worldwide defaults](https://wiki.openstreetmap.org/wiki/OSM_tags_for_routing/Access_restrictions#Worldwide)
+/// for the `highway` type are used instead. Returns [None] if access cannot be determined.
+pub fn resolve(tags: &Tags, mode: TransportMode) -> Option<Access<'_>> {
+    resolve_explicit(tags, mode).or_else(|| resolve_implied(tags, mode))
+}
+
+/// Like [resolve], but only considers explicitly tagged restrictions
+pub fn resolve_explicit(tags: &Tags, mode: TransportMode) -> Option<Access<'_>> {
+    mode.hierarchy().find_map(|m| {
+        tags.get_key_value(m.key()).map(|(key, value)| Access {
+            key: key.as_str(),
+            value: AccessValue::parse(value),
+            implied: false,
+        })
+    })
+}
+
+fn resolve_implied(tags: &Tags, mode: TransportMode) -> Option<Access<'_>> {
+    let (key, highway) = tags.get_key_value("highway")?;
+    let defaults = implied_defaults(highway)?;
+    mode.hierarchy().find_map(|m| {
+        defaults
+            .iter()
+            .find(|(default_mode, _)| *default_mode == m)
+            .map(|(_, value)| Access {
+                key: key.as_str(),
+                value: *value,
+                implied: true,
+            })
+    })
+}
+
+/// Defaults per `highway` value, each ending in a catch-all for [TransportMode::All]
+fn implied_defaults(highway: &str) -> Option<&'static [(TransportMode, AccessValue)]> {
+    use AccessValue::{Designated, No, Yes};
+    use TransportMode::*;
+    Some(match highway {
+        "motorway" | "motorway_link" => &[
+            (Foot, No),
+            (Horse, No),
+            (Bicycle, No),
+            (Carriage, No),
+            (Moped, No),
+            (Mofa, No),
+            (TransportMode::Agricultural, No),
+            (All, Yes),
+        ],
+        "trunk" | "trunk_link" | "primary" | "primary_link" | "secondary" | "secondary_link"
+        | "tertiary" | "tertiary_link" | "unclassified" | "residential" | "living_street"
+        | "service" | "road" | "track" => &[(All, Yes)],
+        "pedestrian" => &[(Foot, Designated), (All, No)],
+        "footway" => &[(Foot, Designated), (All, No)],
+        "steps" => &[(Foot, Yes), (All, No)],
+        "path" => &[(Foot, Yes), (Horse, Yes), (Bicycle, Yes), (All, No)],
+        "bridleway" => &[(Horse, Designated), (All, No)],
+        "cycleway" => &[(Bicycle, Designated), (All, No)],
+        "busway" => &[(Bus, Designated), (All, No)],
+        _ => return None,
+    })
+}
+
+impl Way {
+    /// Finds the tag governing access for `mode` on this way
+    ///
+    /// See [resolve] for details.
+    pub fn access(&self, mode: TransportMode) -> Option<Access<'_>> {
+        resolve(&self.tags, mode)
+    }
+}
+
+impl Element {
+    /// Finds the tag governing access for `mode` on this element
+    ///
+    /// See [resolve] for details.
+    pub fn access(&self, mode: TransportMode) -> Option<Access<'_>> {
+        resolve(self.tags(), mode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TagString;
+
+    fn tags(pairs: &[(&str, &str)]) -> Tags {
+        pairs
+            .iter()
+            .map(|&(key, value)| (TagString::from_ref(key), TagString::from_ref(value)))
+            .collect()
+    }
+
+    fn value(tags: &Tags, mode: TransportMode) -> Option<(&str, AccessValue, bool)> {
+        resolve(tags, mode).map(|access| (access.key, access.value, access.implied))
+    }
+
+    #[test]
+    fn motor_vehicle_applies_to_its_children() {
+        let tags = tags(&[("highway", "residential"), ("motor_vehicle", "destination")]);
+        for mode in [
+            TransportMode::Motorcar,
+            TransportMode::Hgv,
+            TransportMode::Goods,
+            TransportMode::Motorhome,
+            TransportMode::Bus,
+        ] {
+            assert_eq!(
+                value(&tags, mode),
+                Some(("motor_vehicle", AccessValue::Destination, false)),
+                "{mode:?}"
+            );
+        }
+        assert_eq!(
+            value(&tags, TransportMode::Bicycle),
+            Some(("highway", AccessValue::Yes, true))
+        );
+    }
+
+    #[test]
+    fn specific_modes_override_general_ones() {
+        let tags = tags(&[
+            ("highway", "service"),
+            ("access", "private"),
+            ("vehicle", "no"),
+            ("bicycle", "yes"),
+        ]);
+        assert_eq!(
+            value(&tags, TransportMode::Bicycle),
+            Some(("bicycle", AccessValue::Yes, false))
+        );
+        assert_eq!(
+            value(&tags, TransportMode::Motorcar),
+            Some(("vehicle", AccessValue::No, false))
+        );
+        assert_eq!(
+            value(&tags, TransportMode::Foot),
+            Some(("access", AccessValue::Private, false))
+        );
+        assert!(!resolve(&tags, TransportMode::Foot).unwrap().is_allowed());
+    }
+
+    #[test]
+    fn conditional_restrictions_are_not_applied() {
+        let tags = tags(&[
+            ("highway", "residential"),
+            ("motor_vehicle", "no"),
+            ("motor_vehicle:conditional", "yes @ (Mo-Fr 06:00-10:00)"),
+        ]);
+        assert_eq!(
+            value(&tags, TransportMode::Motorcar),
+            Some(("motor_vehicle", AccessValue::No, false))
+        );
+    }
+
+    #[test]
+    fn each_level_of_the_hierarchy() {
+        let modes = [
+            (TransportMode::Taxi, "taxi"),
+            (TransportMode::Psv, "psv"),
+            (TransportMode::MotorVehicle, "motor_vehicle"),
+            (TransportMode::Vehicle, "vehicle"),
+            (TransportMode::All, "access"),
+        ];
+        for (i, &(_, key)) in modes.iter().enumerate() {
+            let tagged: Vec<_> = modes[i..].iter().map(|&(_, key)| (key, "no")).collect();
+            assert_eq!(
+                value(&tags(&tagged), TransportMode::Taxi),
+                Some((key, AccessValue::No, false))
+            );
+        }
+        assert_eq!(
+            TransportMode::Taxi.hierarchy().collect::<Vec<_>>(),
+            modes.map(|(mode, _)| mode)
+        );
+    }
+
+    #[test]
+    fn unknown_values_and_highways() {
+        let tags_with_unknown = tags(&[("highway", "footway"), ("foot", "sometimes")]);
+        let access = resolve(&tags_with_unknown, TransportMode::Foot).unwrap();
+        assert_eq!(access.value, AccessValue::Unknown);
+        assert!(!access.is_allowed());
+
+        assert_eq!(
+            value(&tags(&[("highway", "footway")]), TransportMode::Horse),
+            Some(("highway", AccessValue::No, true))
+        );
+        assert_eq!(
+            value(&tags(&[("highway", "motorway")]), TransportMode::Bicycle),
+            Some(("highway", AccessValue::No, true))
+        );
+        assert_eq!(
+            value(&tags(&[("highway", "motorway")]), TransportMode::Hgv),
+            Some(("highway", AccessValue::Yes, true))
+        );
+        assert_eq!(
+            value(&tags(&[("highway", "proposed")]), TransportMode::Foot),
+            None
+        );
+        assert_eq!(value(&tags(&[]), TransportMode::Foot), None);
+        assert_eq!(TransportMode::from_key("spaceship"), None);
+        assert_eq!(
+            TransportMode::from_key("motorhome"),
+            Some(TransportMode::Motorhome)
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Addresses from `addr:*` tags
+//!
+//! <https://wiki.openstreetmap.org/wiki/Key:addr:*>
+//!
+//! Besides the addresses of single elements, [interpolate] spreads the house numbers of an
+//! [interpolation way](https://wiki.openstreetmap.org/wiki/Addresses#Interpolation) over its
+//! length, between the nodes on it that carry numbers.
+
+use std::str::FromStr;
+
+use crate::geom::{distance, interpolate as interpolate_point, length, LatLon, MissingNode};
+use crate::tags::values::ParseValueError;
+use crate::tags::Tags;
+use crate::{Id, Node, TagString, Way};
+
+/// Parts of an address, borrowed from the tags
+#[derive(Debug, PartialEq, Eq, Clone, Default, Hash)]
+pub struct Address<'a> {
+    pub housenumber: Option<&'a str>,
+    /// Name of a house without a number, `addr:housename`
+    pub housename: Option<&'a str>,
+    /// Flat or suite within the building, `addr:unit`
+    pub unit: Option<&'a str>,
+    pub street: Option<&'a str>,
+    /// Hamlet or square used instead of a street, `addr:place`
+    pub place: Option<&'a str>,
+    pub city: Option<&'a str>,
+    pub postcode: Option<&'a str>,
+    pub state: Option<&'a str>,
+    /// Usually an ISO 3166-1 alpha-2 code, `addr:country`
+    pub country: Option<&'a str>,
+    /// Scheme of an interpolation way, `addr:interpolation`
+    pub interpolation: Option<Interpolation>,
+}
+
+impl<'a> Address<'a> {
+    /// Address of the tags, [None] if there are no `addr:*` tags this knows
+    pub fn from_tags(tags: &'a Tags) -> Option<Self> {
+        let get = |key: &str| tags.get(key).map(TagString::as_str);
+        let address = Self {
+            housenumber: get("addr:housenumber"),
+            housename: get("addr:housename"),
+            unit: get("addr:unit"),
+            street: get("addr:street"),
+            place: get("addr:place"),
+            city: get("addr:city"),
+            postcode: get("addr:postcode"),
+            state: get("addr:state"),
+            country: get("addr:country"),
+            interpolation: tags.parse("addr:interpolation"),
+        };
+        (address != Self::default()).then_some(address)
+    }
+
+    /// Whether a house number or name can be found on a street or place
+    pub fn is_complete(&self) -> bool {
+        (self.housenumber.is_some() || self.housename.is_some())
+            && (self.street.is_some() || self.place.is_some())
+    }
+}
+
+/// Value of `addr:interpolation`
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Interpolation {
+    Odd,
+    Even,
+    All,
+    /// Letters after the same number, e.g. `10a` to `10d`
+    Alphabetic,
+    /// Every nth number
+    Step(u32),
+}
+
+impl FromStr for Interpolation {
+    type Err = ParseValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.trim() {
+            "odd" => Interpolation::Odd,
+            "even" => Interpolation::Even,
+            "all" => Interpolation::All,
+            "alphabetic" => Interpolation::Alphabetic,
+            s => match s.parse() {
+                Ok(step) if step > 0 => Interpolation::Step(step),
+                _ => return Err(ParseValueError),
+            },
+        })
+    }
+}
+
+impl Interpolation {
+    /// House numbers strictly between `from` and `to`, counting down if `to` is smaller
+    ///
+    /// Returns [None] if the numbers do not fit the scheme, e.g. an even number for
+    /// [Interpolation::Odd] or letters for numeric schemes.
+    pub fn numbers(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        let (from, to) = (from.trim(), to.trim());
+        if let Interpolation::Alphabetic = self {
+            return letters(from, to);
+        }
+        let (from, to) = (from.parse::<u64>().ok()?, to.parse::<u64>().ok()?);
+        let step = match self {
+            Interpolation::Odd | Interpolation::Even => {
+                let odd = matches!(self, Interpolation::Odd);
+                if (from % 2 == 1) != odd || (to % 2 == 1) != odd {
+                    return None;
+                }
+                2
+            }
+            Interpolation::All => 1,
+            Interpolation::Step(step) => u64::from(*step),
+            Interpolation::Alphabetic => return None,
+        };
+        let numbers: Vec<u64> = if from <= to {
+            (from..to).step_by(step as usize).skip(1).collect()
+        } else {
+            (to + 1..=from)
+                .rev()
+                .step_by(step as usize)
+                .skip(1)
+                .collect()
+        };
+        Some(
+            numbers
+                .into_iter()
+                .map(|number| number.to_string())
+                .collect(),
+        )
+    }
+}
+
+/// Numbers for [Interpolation::Alphabetic], with the same number before the letters
+fn letters(from: &str, to: &str) -> Option<Vec<String>> {
+    fn split(number: &str) -> Option<(&str, u8)> {
+        let (prefix, letter) = number.split_at(number.len().checked_sub(1)?);
+        let letter = letter.chars().next()?;
+        letter
+            .is_ascii_alphabetic()
+            .then_some((prefix, letter as u8))
+    }
+    let ((prefix, from), (to_prefix, to)) = (split(from)?, split(to)?);
+    if prefix != to_prefix || prefix.is_empty() {
+        return None;
+    }
+    let letters: Vec<u8> = if from <= to {
+        (from + 1..to).collect()
+    } else {
+        (to + 1..from).rev().collect()
+    };
+    Some(
+        letters
+            .into_iter()
+            .map(|letter| format!("{prefix}{}", letter as char))
+            .collect(),
+    )
+}
+
+/// Point `offset` meters along a line, the last point if the line is shorter
+fn along(line: &[LatLon], mut offset: f64) -> Option<LatLon> {
+    for pair in line.windows(2) {
+        let length = distance(pair[0], pair[1]);
+        if offset <= length && length > 0. {
+            return Some(interpolate_point(pair[0], pair[1], offset / length));
+        }
+        offset -= length;
+    }
+    line.last().copied()
+}
+
+/// Interpolated house numbers of an interpolation way, evenly spaced between the nodes of the
+/// way that have `addr:housenumber`
+///
+/// The numbers of those nodes are not repeated. Sections whose numbers do not fit the scheme
+/// are skipped, and ways without `addr:interpolation` give none.
+pub fn interpolate<'a>(
+    way: &Way,
+    mut node: impl FnMut(Id) -> Option<&'a Node>,
+) -> Result<Vec<(LatLon, String)>, MissingNode> {
+    let Some(interpolation) = way.tags.parse::<Interpolation>("addr:interpolation") else {
+        return Ok(vec![]);
+    };
+    let nodes = way
+        .refs
+        .iter()
+        .enumerate()
+        .map(|(index, &id)| {
+            node(id).ok_or(MissingNode {
+                way: way.id,
+                node: id,
+                index,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let anchors: Vec<(usize, &str)> = nodes
+        .iter()
+        .enumerate()
+        .filter_map(|(index, node)| Some((index, node.tags.get("addr:housenumber")?.as_str())))
+        .collect();
+    let mut addresses = vec![];
+    for pair in anchors.windows(2) {
+        let ((start, from), (end, to)) = (pair[0], pair[1]);
+        let Some(numbers) = interpolation.numbers(from, to) else {
+            continue;
+        };
+        let line: Vec<_> = nodes[start..=end]
+            .iter()
+            .map(|node| node.lat_lon())
+            .collect();
+        let spacing = length(&line) / (numbers.len() + 1) as f64;
+        for (i, number) in numbers.into_iter().enumerate() {
+            if let Some(point) = along(&line, spacing * (i + 1) as f64) {
+                addresses.push((point, number));
+            }
+        }
+    }
+    Ok(addresses)
+}
+
+impl Tags {
+    /// See [Address::from_tags]
+    pub fn address(&self) -> Option<Address<'_>> {
+        Address::from_tags(self)
+    }
+}
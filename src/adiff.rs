@@ -0,0 +1,179 @@
+//! [Augmented diffs](https://wiki.openstreetmap.org/wiki/Overpass_API/Augmented_Diffs) of the
+//! Overpass API
+//!
+//! Unlike [osmChange](crate::change), an augmented diff holds the state of each element both
+//! before and after a change, in `action` envelopes such as
+//! `<action type="modify"><old>…</old><new>…</new></action>`. [Reader] streams them as
+//! [AugmentedChange]s. Geometry added by `out geom` is ignored, as in [crate::xml].
+
+use std::io::BufRead;
+
+use chrono::{DateTime, Utc};
+
+use crate::change::{Action, Change};
+use crate::error::{Error, Result};
+use crate::xml::{self, TagKind, Tokenizer};
+use crate::{timestamp_from_utc, Element, Timestamp};
+
+/// Change of an element with its states around it
+///
+/// Creations only have a new state. Deletions have the deleted element as the old state and
+/// usually a new state with `visible="false"` that records who deleted it.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AugmentedChange {
+    pub action: Action,
+    pub old: Option<Element>,
+    pub new: Option<Element>,
+}
+
+impl AugmentedChange {
+    /// New state, or the old one for deletions without it
+    pub fn element(&self) -> Option<&Element> {
+        self.new.as_ref().or(self.old.as_ref())
+    }
+
+    /// Change as in osmChange, with the state of [AugmentedChange::element]
+    pub fn into_change(self) -> Option<Change> {
+        Some(Change {
+            action: self.action,
+            element: self.new.or(self.old)?,
+        })
+    }
+}
+
+/// Part of an `action` the next element belongs to
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Slot {
+    Old,
+    New,
+}
+
+/// Reader of the [AugmentedChange]s of an augmented diff
+#[derive(Debug)]
+pub struct Reader<R> {
+    tokenizer: Tokenizer<R>,
+    generator: Option<String>,
+    osm_base: Option<Timestamp>,
+    done: bool,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            tokenizer: Tokenizer::new(input),
+            generator: None,
+            osm_base: None,
+            done: false,
+        }
+    }
+}
+
+impl<R> Reader<R> {
+    /// Program that wrote the diff, which is available once the first change was read
+    pub fn generator(&self) -> Option<&str> {
+        self.generator.as_deref()
+    }
+
+    /// Time of the data the diff was computed from, which is available once the first change
+    /// was read
+    pub fn osm_base(&self) -> Option<Timestamp> {
+        self.osm_base
+    }
+
+    pub fn into_inner(self) -> R {
+        self.tokenizer.input
+    }
+}
+
+impl<R: BufRead> Reader<R> {
+    fn read_change(&mut self) -> Result<Option<AugmentedChange>> {
+        let mut action = None;
+        let mut slot = None;
+        let (mut old, mut new) = (None, None);
+        while let Some(tag) = self.tokenizer.next_tag()? {
+            let offset = Some(self.tokenizer.tag_offset);
+            match (tag.kind, tag.name.as_str()) {
+                (TagKind::Start, "action") => {
+                    action = Some(match tag.attribute("type") {
+                        Some("create") => Action::Create,
+                        Some("modify") => Action::Modify,
+                        Some("delete") => Action::Delete,
+                        Some(_) => return Err(Error::decode(offset, "invalid type attribute")),
+                        None => return Err(Error::decode(offset, "missing type attribute")),
+                    });
+                }
+                (TagKind::End, "action") => {
+                    let Some(action) = action.take() else {
+                        continue;
+                    };
+                    let missing = match action {
+                        Action::Create => new.is_none(),
+                        Action::Modify => old.is_none() || new.is_none(),
+                        Action::Delete => old.is_none(),
+                    };
+                    if missing {
+                        return Err(Error::decode(
+                            offset,
+                            format!("missing state in {} action", action.as_str()),
+                        ));
+                    }
+                    return Ok(Some(AugmentedChange {
+                        action,
+                        old: old.take(),
+                        new: new.take(),
+                    }));
+                }
+                (TagKind::Start, "old") => slot = Some(Slot::Old),
+                (TagKind::Start, "new") => slot = Some(Slot::New),
+                (TagKind::End, "old" | "new") => slot = None,
+                (TagKind::End, _) => {}
+                (_, "node" | "way" | "relation") => {
+                    let Some(action) = action else {
+                        return Err(Error::decode(offset, "element outside of action"));
+                    };
+                    // New states of deletions may lack coordinates
+                    let deleted = action == Action::Delete && slot != Some(Slot::Old);
+                    let element = xml::read_element(&mut self.tokenizer, &tag, deleted)??;
+                    match slot {
+                        Some(Slot::Old) => old = Some(element),
+                        Some(Slot::New) | None => new = Some(element),
+                    }
+                }
+                (_, "osm" | "osmAugmentedDiff") => {
+                    self.generator = tag.attribute("generator").map(str::to_string);
+                }
+                (_, "meta") => {
+                    self.osm_base = tag
+                        .attribute("osm_base")
+                        .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+                        .map(|time| timestamp_from_utc(time.with_timezone(&Utc)));
+                }
+                _ => {}
+            }
+        }
+        if action.is_some() {
+            return Err(Error::decode(
+                Some(self.tokenizer.offset),
+                "unexpected end of file",
+            ));
+        }
+        Ok(None)
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<AugmentedChange>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let change = self.read_change().transpose();
+        if !matches!(change, Some(Ok(_))) {
+            self.done = true;
+        }
+        change
+    }
+}
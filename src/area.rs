@@ -0,0 +1,245 @@
+//! Polygons of closed ways and multipolygon relations as one type, as in
+//! [osmium](https://osmcode.org/osmium-concepts/#areas)
+//!
+//! An [Area] has an id of its own derived from the element it was assembled from, so that the
+//! areas of a way and a relation with the same id stay apart: twice the id for ways, and twice
+//! the id plus one for relations, keeping the sign. [GeomElement] pairs areas with the nodes
+//! and ways that are points and lines.
+
+use crate::error::ElementContext;
+use crate::geom::{signed_area, LatLon};
+use crate::multipolygon::{
+    assemble, locate_in_polygons, AssemblyError, MultiPolygon, PointLocation, Polygon,
+};
+use crate::scalar::{Scalar, ScalarExt};
+use crate::store::ElementStore;
+use crate::{Element, Id, Info, MemberType, Node, Relation, Tags, Way};
+
+/// Polygons with the tags of the way or relation they were assembled from
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub struct Area {
+    /// Id of the area, see [area_id]
+    pub id: Id,
+    pub tags: Tags,
+    pub info: Option<Info>,
+    /// Outer rings with their holes, one for a way
+    pub polygons: Vec<Polygon>,
+}
+
+/// Id of the area of a way or relation
+///
+/// Returns [None] for nodes, which have no area, and for ids whose double does not fit in an
+/// [i64].
+pub fn area_id(ty: MemberType, id: Id) -> Option<Id> {
+    let offset = match ty {
+        MemberType::Way => 0,
+        MemberType::Relation => 1,
+        MemberType::Node => return None,
+    };
+    let area = id.0.checked_abs()?.checked_mul(2)?.checked_add(offset)?;
+    Some(Id(if id.0 < 0 { -area } else { area }))
+}
+
+impl Area {
+    /// Area of a closed way, with the ring turned counterclockwise
+    ///
+    /// Tags are not checked, see [Way::is_area].
+    pub fn from_way<'a>(
+        way: &Way,
+        node: impl FnMut(Id) -> Option<&'a Node>,
+    ) -> Result<Self, AssemblyError> {
+        let id = area_id(MemberType::Way, way.id).ok_or(AssemblyError::IdTooLarge(way.id))?;
+        let mut ring = way.resolve(node).map_err(AssemblyError::MissingNode)?;
+        let Some(&last) = way.refs.last() else {
+            return Err(AssemblyError::NoWays);
+        };
+        if !way.is_closed() {
+            return Err(AssemblyError::OpenRing { node: last });
+        }
+        if way.refs.len() < 4 {
+            return Err(AssemblyError::DegenerateRing { node: last });
+        }
+        let planar: Vec<_> = ring
+            .iter()
+            .map(|&(lat, lon)| (ScalarExt::as_f64(lat), ScalarExt::as_f64(lon)))
+            .collect();
+        if signed_area(&planar) < 0. {
+            ring.reverse();
+        }
+        Ok(Self {
+            id,
+            tags: way.tags.clone(),
+            info: way.info.clone(),
+            polygons: vec![Polygon {
+                outer: ring,
+                inners: vec![],
+            }],
+        })
+    }
+
+    /// Area of a multipolygon relation, see [assemble]
+    ///
+    /// Tags are not checked, see [Relation::is_area].
+    pub fn from_relation<'a>(
+        relation: &Relation,
+        way: impl FnMut(Id) -> Option<&'a Way>,
+        node: impl FnMut(Id) -> Option<LatLon>,
+    ) -> Result<Self, AssemblyError> {
+        let id = area_id(MemberType::Relation, relation.id)
+            .ok_or(AssemblyError::IdTooLarge(relation.id))?;
+        let MultiPolygon { polygons } = assemble(relation, way, node)?;
+        Ok(Self {
+            id,
+            tags: relation.tags.clone(),
+            info: relation.info.clone(),
+            polygons,
+        })
+    }
+
+    /// Whether the area was assembled from a way rather than a relation
+    pub fn is_from_way(&self) -> bool {
+        self.id.0 % 2 == 0
+    }
+
+    /// Type and id of the element the area was assembled from
+    pub fn source(&self) -> ElementContext {
+        let id = Id(self.id.0 / 2);
+        let ty = if self.is_from_way() {
+            MemberType::Way
+        } else {
+            MemberType::Relation
+        };
+        ElementContext { ty, id }
+    }
+
+    pub fn multipolygon(&self) -> MultiPolygon {
+        MultiPolygon {
+            polygons: self.polygons.clone(),
+        }
+    }
+
+    /// See [MultiPolygon::locate]
+    pub fn locate(&self, lat: Scalar, lon: Scalar) -> PointLocation {
+        locate_in_polygons(&self.polygons, lat, lon)
+    }
+
+    /// Whether the point is inside or on the boundary, see [MultiPolygon::contains]
+    pub fn contains(&self, lat: Scalar, lon: Scalar) -> bool {
+        self.locate(lat, lon) != PointLocation::Outside
+    }
+}
+
+/// Node as a point, way as a line, or [Area]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub enum GeomElement {
+    Node(Node),
+    Way(Way),
+    Area(Area),
+}
+
+impl GeomElement {
+    pub fn tags(&self) -> &Tags {
+        match self {
+            GeomElement::Node(node) => &node.tags,
+            GeomElement::Way(way) => &way.tags,
+            GeomElement::Area(area) => &area.tags,
+        }
+    }
+}
+
+impl ElementStore {
+    /// Area of a way or relation that is one by [Way::is_area] or [Relation::is_area],
+    /// resolving members and nodes from the store
+    ///
+    /// Returns [None] for other elements.
+    pub fn assemble_area(&self, element: &Element) -> Option<Result<Area, AssemblyError>> {
+        match element {
+            Element::Way(way) if way.is_area() => Some(Area::from_way(way, |id| self.get_node(id))),
+            Element::Relation(relation) if relation.is_area() => Some(Area::from_relation(
+                relation,
+                |id| self.get_way(id),
+                |id| self.get_node(id).map(Node::lat_lon),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Element by its geometry, with ways that are areas and multipolygons as [Area]s
+    ///
+    /// Returns [None] for relations that are not areas.
+    pub fn geom_element(&self, element: &Element) -> Option<Result<GeomElement, AssemblyError>> {
+        match (element, self.assemble_area(element)) {
+            (_, Some(area)) => Some(area.map(GeomElement::Area)),
+            (Element::Node(node), None) => Some(Ok(GeomElement::Node(node.clone()))),
+            (Element::Way(way), None) => Some(Ok(GeomElement::Way(way.clone()))),
+            (Element::Relation(_), None) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn area_ids() {
+        assert_eq!(area_id(MemberType::Way, Id(1)), Some(Id(2)));
+        assert_eq!(area_id(MemberType::Relation, Id(1)), Some(Id(3)));
+        assert_eq!(area_id(MemberType::Way, Id(-1)), Some(Id(-2)));
+        assert_eq!(area_id(MemberType::Relation, Id(-1)), Some(Id(-3)));
+        assert_eq!(area_id(MemberType::Node, Id(1)), None);
+    }
+
+    #[test]
+    fn area_ids_at_the_limits() {
+        let max = i64::MAX / 2;
+        assert_eq!(area_id(MemberType::Way, Id(max)), Some(Id(i64::MAX - 1)));
+        assert_eq!(area_id(MemberType::Relation, Id(max)), Some(Id(i64::MAX)));
+        assert_eq!(area_id(MemberType::Relation, Id(-max)), Some(Id(-i64::MAX)));
+        assert_eq!(area_id(MemberType::Way, Id(max + 1)), None);
+        assert_eq!(area_id(MemberType::Way, Id(i64::MAX)), None);
+        assert_eq!(area_id(MemberType::Way, Id(i64::MIN)), None);
+        assert_eq!(area_id(MemberType::Relation, Id(i64::MIN)), None);
+    }
+
+    #[test]
+    fn ways_with_large_ids_are_errors() {
+        let way = Way::builder(Id(i64::MAX))
+            .nodes([1, 2, 3, 1].map(Id))
+            .build()
+            .unwrap();
+        assert_eq!(
+            Area::from_way(&way, |_| None),
+            Err(AssemblyError::IdTooLarge(Id(i64::MAX)))
+        );
+    }
+
+    #[test]
+    fn source_is_the_element() {
+        let way = Way::builder(Id(-7))
+            .nodes([1, 2, 3, 1].map(Id))
+            .build()
+            .unwrap();
+        let nodes: Vec<_> = [(1, 0, 0), (2, 0, 1), (3, 1, 1)]
+            .map(|(id, lat, lon)| {
+                Node::builder(Id(id))
+                    .lat(Scalar::from_int(lat))
+                    .lon(Scalar::from_int(lon))
+                    .build()
+                    .unwrap()
+            })
+            .into();
+        let area = Area::from_way(&way, |id| nodes.iter().find(|node| node.id == id)).unwrap();
+        assert_eq!(area.id, Id(-14));
+        assert!(area.is_from_way());
+        assert_eq!(
+            area.source(),
+            ElementContext {
+                ty: MemberType::Way,
+                id: Id(-7)
+            }
+        );
+    }
+}
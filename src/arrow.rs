@@ -0,0 +1,464 @@
+//! Columnar batches of elements in the memory layout of [Apache Arrow](https://arrow.apache.org/docs/format/Columnar.html)
+//!
+//! [Batches] splits elements into a [NodeBatch], [WayBatch], and [RelationBatch], each with a
+//! column per field as described by its `schema`. Columns hold the buffers of the Arrow format,
+//! e.g. offsets and bytes for strings or a validity bitmap for nullable values, so they can be
+//! wrapped into arrays and record batches by an Arrow implementation without copying, e.g. to
+//! write Parquet files with [arrow-rs](https://docs.rs/arrow). Tags are a map of strings to
+//! strings, way nodes a list of ids, and members a list of structs.
+
+use crate::scalar::ScalarExt;
+use crate::{timestamp_to_utc, Element, Info, MemberType, Node, Relation, Tags, Way};
+
+/// Type of a column, named as in Arrow
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum DataType {
+    Int8,
+    Int32,
+    Int64,
+    Float64,
+    Boolean,
+    Utf8,
+    /// Seconds since the Unix epoch, in UTC
+    TimestampSecond,
+    List(Box<Field>),
+    /// Map with entries of `key` and `value`
+    Map(Box<Field>, Box<Field>),
+    Struct(Vec<Field>),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Field {
+    pub name: &'static str,
+    pub data_type: DataType,
+    pub nullable: bool,
+}
+
+impl Field {
+    pub fn new(name: &'static str, data_type: DataType, nullable: bool) -> Self {
+        Self {
+            name,
+            data_type,
+            nullable,
+        }
+    }
+}
+
+/// Bit per value that is set if the value is not null, least significant bit first
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Bitmap {
+    pub bits: Vec<u8>,
+    pub len: usize,
+}
+
+impl Bitmap {
+    pub fn push(&mut self, set: bool) {
+        if self.len.is_multiple_of(8) {
+            self.bits.push(0);
+        }
+        if set {
+            self.bits[self.len / 8] |= 1 << (self.len % 8);
+        }
+        self.len += 1;
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        index < self.len && self.bits[index / 8] & (1 << (index % 8)) != 0
+    }
+
+    /// Number of unset bits
+    pub fn unset_count(&self) -> usize {
+        self.len
+            - self
+                .bits
+                .iter()
+                .map(|byte| byte.count_ones() as usize)
+                .sum::<usize>()
+    }
+}
+
+/// Column of fixed size values, with zero for nulls
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct PrimitiveColumn<T> {
+    pub values: Vec<T>,
+    pub validity: Bitmap,
+}
+
+impl<T: Default> PrimitiveColumn<T> {
+    pub fn push(&mut self, value: Option<T>) {
+        self.validity.push(value.is_some());
+        self.values.push(value.unwrap_or_default());
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+/// Column of strings, where string `i` is `data[offsets[i]..offsets[i + 1]]`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct StringColumn {
+    pub offsets: Vec<i32>,
+    pub data: Vec<u8>,
+    pub validity: Bitmap,
+}
+
+impl Default for StringColumn {
+    fn default() -> Self {
+        Self {
+            offsets: vec![0],
+            data: vec![],
+            validity: Bitmap::default(),
+        }
+    }
+}
+
+impl StringColumn {
+    pub fn push(&mut self, value: Option<&str>) {
+        self.validity.push(value.is_some());
+        self.data
+            .extend_from_slice(value.unwrap_or_default().as_bytes());
+        self.offsets.push(offset(self.data.len()));
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        if !self.validity.get(index) {
+            return None;
+        }
+        let range = self.offsets[index] as usize..self.offsets[index + 1] as usize;
+        std::str::from_utf8(&self.data[range]).ok()
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Offset in a buffer, which Arrow limits to 32 bits for strings, lists, and maps
+fn offset(len: usize) -> i32 {
+    i32::try_from(len).expect("column exceeds the 2 GiB limit of Arrow offsets")
+}
+
+/// Column of tags, where the tags of row `i` are entries `offsets[i]..offsets[i + 1]`
+///
+/// Entries of a row are sorted by key.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MapColumn {
+    pub offsets: Vec<i32>,
+    pub keys: StringColumn,
+    pub values: StringColumn,
+}
+
+impl Default for MapColumn {
+    fn default() -> Self {
+        Self {
+            offsets: vec![0],
+            keys: StringColumn::default(),
+            values: StringColumn::default(),
+        }
+    }
+}
+
+impl MapColumn {
+    pub fn push(&mut self, tags: &Tags) {
+        for (key, value) in tags.iter_sorted() {
+            self.keys.push(Some(key));
+            self.values.push(Some(value));
+        }
+        self.offsets.push(offset(self.keys.len()));
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Column of lists of 64-bit integers, where the list of row `i` is
+/// `values[offsets[i]..offsets[i + 1]]`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ListColumn {
+    pub offsets: Vec<i32>,
+    pub values: Vec<i64>,
+}
+
+impl Default for ListColumn {
+    fn default() -> Self {
+        Self {
+            offsets: vec![0],
+            values: vec![],
+        }
+    }
+}
+
+/// Column of lists of members, with a child column for each field of the member struct
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MemberColumn {
+    pub offsets: Vec<i32>,
+    /// 0 for nodes, 1 for ways, 2 for relations
+    pub types: Vec<i8>,
+    pub refs: Vec<i64>,
+    pub roles: StringColumn,
+}
+
+impl Default for MemberColumn {
+    fn default() -> Self {
+        Self {
+            offsets: vec![0],
+            types: vec![],
+            refs: vec![],
+            roles: StringColumn::default(),
+        }
+    }
+}
+
+/// Columns of [Info], null for elements without it
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct InfoColumns {
+    pub version: PrimitiveColumn<i32>,
+    pub timestamp: PrimitiveColumn<i64>,
+    pub changeset: PrimitiveColumn<i64>,
+    pub uid: PrimitiveColumn<i32>,
+    pub user: StringColumn,
+    /// A byte per value, which Arrow packs into a bit
+    pub visible: PrimitiveColumn<bool>,
+}
+
+impl InfoColumns {
+    fn push(&mut self, info: Option<&Info>) {
+        self.version.push(info.map(|info| info.version));
+        self.timestamp.push(
+            info.and_then(|info| info.timestamp)
+                .map(|timestamp| timestamp_to_utc(timestamp).timestamp()),
+        );
+        self.changeset.push(info.and_then(|info| info.changeset));
+        self.uid.push(info.and_then(|info| info.uid));
+        self.user.push(info.and_then(|info| info.user.as_deref()));
+        self.visible.push(info.and_then(|info| info.visible));
+    }
+
+    fn fields() -> [Field; 6] {
+        [
+            Field::new("version", DataType::Int32, true),
+            Field::new("timestamp", DataType::TimestampSecond, true),
+            Field::new("changeset", DataType::Int64, true),
+            Field::new("uid", DataType::Int32, true),
+            Field::new("user", DataType::Utf8, true),
+            Field::new("visible", DataType::Boolean, true),
+        ]
+    }
+}
+
+fn tags_field() -> Field {
+    Field::new(
+        "tags",
+        DataType::Map(
+            Box::new(Field::new("key", DataType::Utf8, false)),
+            Box::new(Field::new("value", DataType::Utf8, false)),
+        ),
+        false,
+    )
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct NodeBatch {
+    pub id: Vec<i64>,
+    pub lat: Vec<f64>,
+    pub lon: Vec<f64>,
+    pub tags: MapColumn,
+    pub info: InfoColumns,
+}
+
+impl NodeBatch {
+    /// Fields of the columns in order: `id`, `lat`, `lon`, `tags`, and those of [InfoColumns]
+    pub fn schema() -> Vec<Field> {
+        let mut fields = vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("lat", DataType::Float64, false),
+            Field::new("lon", DataType::Float64, false),
+            tags_field(),
+        ];
+        fields.extend(InfoColumns::fields());
+        fields
+    }
+
+    pub fn push(&mut self, node: &Node) {
+        self.id.push(node.id.0);
+        self.lat.push(node.lat.as_f64());
+        self.lon.push(node.lon.as_f64());
+        self.tags.push(&node.tags);
+        self.info.push(node.info.as_ref());
+    }
+
+    pub fn len(&self) -> usize {
+        self.id.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.id.is_empty()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct WayBatch {
+    pub id: Vec<i64>,
+    pub nodes: ListColumn,
+    pub tags: MapColumn,
+    pub info: InfoColumns,
+}
+
+impl WayBatch {
+    /// Fields of the columns in order: `id`, `nodes`, `tags`, and those of [InfoColumns]
+    pub fn schema() -> Vec<Field> {
+        let mut fields = vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new(
+                "nodes",
+                DataType::List(Box::new(Field::new("item", DataType::Int64, false))),
+                false,
+            ),
+            tags_field(),
+        ];
+        fields.extend(InfoColumns::fields());
+        fields
+    }
+
+    pub fn push(&mut self, way: &Way) {
+        self.id.push(way.id.0);
+        self.nodes.values.extend(way.refs.iter().map(|id| id.0));
+        self.nodes.offsets.push(offset(self.nodes.values.len()));
+        self.tags.push(&way.tags);
+        self.info.push(way.info.as_ref());
+    }
+
+    pub fn len(&self) -> usize {
+        self.id.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.id.is_empty()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct RelationBatch {
+    pub id: Vec<i64>,
+    pub members: MemberColumn,
+    pub tags: MapColumn,
+    pub info: InfoColumns,
+}
+
+impl RelationBatch {
+    /// Fields of the columns in order: `id`, `members`, `tags`, and those of [InfoColumns]
+    pub fn schema() -> Vec<Field> {
+        let member = DataType::Struct(vec![
+            Field::new("type", DataType::Int8, false),
+            Field::new("ref", DataType::Int64, false),
+            Field::new("role", DataType::Utf8, true),
+        ]);
+        let mut fields = vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new(
+                "members",
+                DataType::List(Box::new(Field::new("item", member, false))),
+                false,
+            ),
+            tags_field(),
+        ];
+        fields.extend(InfoColumns::fields());
+        fields
+    }
+
+    pub fn push(&mut self, relation: &Relation) {
+        self.id.push(relation.id.0);
+        for member in &relation.members {
+            self.members.types.push(match member.ty {
+                MemberType::Node => 0,
+                MemberType::Way => 1,
+                MemberType::Relation => 2,
+            });
+            self.members.refs.push(member.id.0);
+            self.members.roles.push(member.role.as_deref());
+        }
+        self.members.offsets.push(offset(self.members.refs.len()));
+        self.tags.push(&relation.tags);
+        self.info.push(relation.info.as_ref());
+    }
+
+    pub fn len(&self) -> usize {
+        self.id.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.id.is_empty()
+    }
+}
+
+/// Batch of each element type
+///
+/// Batches grow without limit, so large inputs should be split, e.g. with [Iterator::take]
+/// or by moving the batches out once [Batches::len] reaches a row group size.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Batches {
+    pub nodes: NodeBatch,
+    pub ways: WayBatch,
+    pub relations: RelationBatch,
+}
+
+impl Batches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, element: &Element) {
+        match element {
+            Element::Node(node) => self.nodes.push(node),
+            Element::Way(way) => self.ways.push(way),
+            Element::Relation(relation) => self.relations.push(relation),
+        }
+    }
+
+    /// Number of rows of all batches
+    pub fn len(&self) -> usize {
+        self.nodes.len() + self.ways.len() + self.relations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<'a> Extend<&'a Element> for Batches {
+    fn extend<I: IntoIterator<Item = &'a Element>>(&mut self, elements: I) {
+        for element in elements {
+            self.push(element);
+        }
+    }
+}
+
+impl Extend<Element> for Batches {
+    fn extend<I: IntoIterator<Item = Element>>(&mut self, elements: I) {
+        for element in elements {
+            self.push(&element);
+        }
+    }
+}
+
+impl FromIterator<Element> for Batches {
+    fn from_iter<I: IntoIterator<Item = Element>>(elements: I) -> Self {
+        let mut batches = Self::new();
+        batches.extend(elements);
+        batches
+    }
+}
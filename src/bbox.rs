@@ -0,0 +1,387 @@
+//! Bounding boxes in latitude and longitude
+//!
+//! <https://wiki.openstreetmap.org/wiki/Bounding_Box>
+
+use fnv::FnvHashSet as HashSet;
+
+use crate::geom::{LatLon, EARTH_RADIUS};
+use crate::scalar::{Scalar, ScalarExt};
+use crate::store::ElementStore;
+use crate::{Element, Id, MemberType, Node, Relation, Way};
+
+/// Area between two latitudes and two longitudes
+///
+/// A box crossing the antimeridian (±180° longitude) has [Bbox::min_lon] greater than
+/// [Bbox::max_lon], following [RFC 7946](https://datatracker.ietf.org/doc/html/rfc7946#section-5.2).
+/// For example, a box around Fiji spans from 177° to -178°.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "decimal", derive(Eq, Hash))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bbox {
+    pub min_lat: Scalar,
+    /// Western edge
+    pub min_lon: Scalar,
+    pub max_lat: Scalar,
+    /// Eastern edge
+    pub max_lon: Scalar,
+}
+
+impl Bbox {
+    pub fn new(min_lat: Scalar, min_lon: Scalar, max_lat: Scalar, max_lon: Scalar) -> Self {
+        Self {
+            min_lat,
+            min_lon,
+            max_lat,
+            max_lon,
+        }
+    }
+
+    /// Box around a single coordinate
+    pub fn point(lat: Scalar, lon: Scalar) -> Self {
+        Self::new(lat, lon, lat, lon)
+    }
+
+    /// Smallest box around the `(lat, lon)` coordinates, [None] if there are none
+    ///
+    /// The box never crosses the antimeridian, even if the coordinates lie on both sides of
+    /// it.
+    pub fn of_points(points: impl IntoIterator<Item = LatLon>) -> Option<Bbox> {
+        let mut points = points.into_iter();
+        let (lat, lon) = points.next()?;
+        let mut bbox = Bbox::point(lat, lon);
+        for (lat, lon) in points {
+            bbox.min_lat = bbox.min_lat.min(lat);
+            bbox.min_lon = bbox.min_lon.min(lon);
+            bbox.max_lat = bbox.max_lat.max(lat);
+            bbox.max_lon = bbox.max_lon.max(lon);
+        }
+        Some(bbox)
+    }
+
+    /// Smallest box around the nodes, as with [Bbox::of_points]
+    pub fn of_nodes<'a>(nodes: impl IntoIterator<Item = &'a Node>) -> Option<Bbox> {
+        Bbox::of_points(nodes.into_iter().map(Node::lat_lon))
+    }
+
+    /// Grows the box to include the coordinate
+    ///
+    /// Of the two ways to reach a longitude outside the box, moving the western edge west or
+    /// the eastern edge east, the box takes the narrower one, so it may come to cross the
+    /// antimeridian.
+    pub fn extend(&mut self, lat: Scalar, lon: Scalar) {
+        self.min_lat = self.min_lat.min(lat);
+        self.max_lat = self.max_lat.max(lat);
+        if self.contains_lon(lon) {
+            return;
+        }
+        let west = eastward(lon, self.min_lon);
+        let east = eastward(self.max_lon, lon);
+        if west < east {
+            self.min_lon = lon;
+        } else if east < west {
+            self.max_lon = lon;
+        } else {
+            self.min_lon = self.min_lon.min(lon);
+            self.max_lon = self.max_lon.max(lon);
+        }
+    }
+
+    /// Smallest box around both boxes
+    ///
+    /// Either box may cross the antimeridian, and so may the union if that makes it
+    /// narrower. Boxes that together go around the globe give a box from -180° to 180°.
+    pub fn union(&self, other: &Bbox) -> Bbox {
+        let min_lat = self.min_lat.min(other.min_lat);
+        let max_lat = self.max_lat.max(other.max_lat);
+        // The union starts at the western edge of one box and ends at the eastern edge of one
+        let candidates = [
+            (
+                self.min_lon.min(other.min_lon),
+                self.max_lon.max(other.max_lon),
+            ),
+            (self.min_lon, self.max_lon),
+            (self.min_lon, other.max_lon),
+            (other.min_lon, other.max_lon),
+            (other.min_lon, self.max_lon),
+        ];
+        let mut union: Option<Bbox> = None;
+        for (min_lon, max_lon) in candidates {
+            let candidate = Bbox::new(min_lat, min_lon, max_lat, max_lon);
+            let narrower = union.is_none_or(|union| candidate.width() < union.width());
+            if narrower && candidate.spans(self) && candidate.spans(other) {
+                union = Some(candidate);
+            }
+        }
+        union.unwrap_or_else(|| {
+            Bbox::new(
+                min_lat,
+                Scalar::from_int(-180),
+                max_lat,
+                Scalar::from_int(180),
+            )
+        })
+    }
+
+    /// Whether the longitudes of `other` lie within those of the box
+    fn spans(&self, other: &Bbox) -> bool {
+        let width = self.width();
+        width >= Scalar::from_int(360)
+            || eastward(self.min_lon, other.min_lon) + other.width() <= width
+    }
+
+    pub fn crosses_antimeridian(&self) -> bool {
+        self.min_lon > self.max_lon
+    }
+
+    /// Splits a box crossing the antimeridian into its eastern and western parts
+    ///
+    /// Returns the box itself and [None] if it does not cross.
+    pub fn parts(&self) -> (Bbox, Option<Bbox>) {
+        if self.crosses_antimeridian() {
+            (
+                Bbox {
+                    max_lon: Scalar::from_int(180),
+                    ..*self
+                },
+                Some(Bbox {
+                    min_lon: Scalar::from_int(-180),
+                    ..*self
+                }),
+            )
+        } else {
+            (*self, None)
+        }
+    }
+
+    /// Extent in degrees of longitude
+    pub fn width(&self) -> Scalar {
+        if self.crosses_antimeridian() {
+            Scalar::from_int(360) - (self.min_lon - self.max_lon)
+        } else {
+            self.max_lon - self.min_lon
+        }
+    }
+
+    /// Extent in degrees of latitude
+    pub fn height(&self) -> Scalar {
+        self.max_lat - self.min_lat
+    }
+
+    /// Area on the sphere of [EARTH_RADIUS] in square meters
+    pub fn area(&self) -> f64 {
+        let (south, north) = (self.min_lat.as_f64(), self.max_lat.as_f64());
+        let band = north.to_radians().sin() - south.to_radians().sin();
+        EARTH_RADIUS * EARTH_RADIUS * band * self.width().as_f64().to_radians()
+    }
+
+    /// Whether the coordinate lies inside or on the edge of the box
+    pub fn contains(&self, lat: Scalar, lon: Scalar) -> bool {
+        lat >= self.min_lat && lat <= self.max_lat && self.contains_lon(lon)
+    }
+
+    fn contains_lon(&self, lon: Scalar) -> bool {
+        if self.crosses_antimeridian() {
+            lon >= self.min_lon || lon <= self.max_lon
+        } else {
+            lon >= self.min_lon && lon <= self.max_lon
+        }
+    }
+
+    pub fn contains_node(&self, node: &Node) -> bool {
+        self.contains(node.lat, node.lon)
+    }
+
+    /// Whether the boxes share at least one coordinate
+    pub fn intersects(&self, other: &Bbox) -> bool {
+        if self.min_lat > other.max_lat || other.min_lat > self.max_lat {
+            return false;
+        }
+        let (a, a_west) = self.parts();
+        let (b, b_west) = other.parts();
+        let overlaps = |a: &Bbox, b: &Bbox| a.min_lon <= b.max_lon && b.min_lon <= a.max_lon;
+        [Some(a), a_west]
+            .iter()
+            .flatten()
+            .any(|a| [Some(b), b_west].iter().flatten().any(|b| overlaps(a, b)))
+    }
+}
+
+/// Degrees of longitude from `from` east to `to`
+fn eastward(from: Scalar, to: Scalar) -> Scalar {
+    let degrees = to - from;
+    if degrees < Scalar::from_int(0) {
+        degrees + Scalar::from_int(360)
+    } else {
+        degrees
+    }
+}
+
+impl Way {
+    /// Box around the nodes found by `node`, skipping missing ones
+    ///
+    /// Returns [None] if no node is found.
+    pub fn bbox(&self, node: impl FnMut(Id) -> Option<LatLon>) -> Option<Bbox> {
+        Bbox::of_points(self.refs.iter().copied().filter_map(node))
+    }
+}
+
+impl Relation {
+    /// Box around the members found in `store`, including those of nested relations
+    ///
+    /// Missing members are skipped, and each relation is only visited once so that cycles
+    /// end. Returns [None] if no node is found.
+    pub fn bbox(&self, store: &ElementStore) -> Option<Bbox> {
+        let mut visited = HashSet::default();
+        visited.insert(self.id);
+        let mut pending = vec![self];
+        let mut bbox: Option<Bbox> = None;
+        while let Some(relation) = pending.pop() {
+            for member in &relation.members {
+                let part = match member.ty {
+                    MemberType::Node => store
+                        .get_node(member.id)
+                        .map(|node| Bbox::point(node.lat, node.lon)),
+                    MemberType::Way => store
+                        .get_way(member.id)
+                        .and_then(|way| way.bbox(|id| store.get_node(id).map(Node::lat_lon))),
+                    MemberType::Relation => {
+                        if visited.insert(member.id) {
+                            pending.extend(store.get_relation(member.id));
+                        }
+                        None
+                    }
+                };
+                if let Some(part) = part {
+                    bbox = Some(bbox.map_or(part, |bbox| bbox.union(&part)));
+                }
+            }
+        }
+        bbox
+    }
+}
+
+impl ElementStore {
+    /// Box around an element, resolving ways and relations from the store
+    pub fn bbox(&self, element: &Element) -> Option<Bbox> {
+        match element {
+            Element::Node(node) => Some(Bbox::point(node.lat, node.lon)),
+            Element::Way(way) => way.bbox(|id| self.get_node(id).map(Node::lat_lon)),
+            Element::Relation(relation) => relation.bbox(self),
+        }
+    }
+}
+
+/// Splits a line of `(lat, lon)` coordinates wherever it crosses the antimeridian
+///
+/// A segment is considered to cross if its longitudes differ by more than 180°, since the
+/// shorter way around is assumed. The crossing point is interpolated and added to both sides,
+/// at 180° on the eastern side and -180° on the western side.
+pub fn split_at_antimeridian(line: &[(Scalar, Scalar)]) -> Vec<Vec<(Scalar, Scalar)>> {
+    let mut parts = vec![];
+    let mut current = vec![];
+    for pair in line.windows(2) {
+        let ((lat1, lon1), (lat2, lon2)) = (pair[0], pair[1]);
+        if current.is_empty() {
+            current.push((lat1, lon1));
+        }
+        if (lon2 - lon1).abs() > Scalar::from_int(180) {
+            let (edge, unwrapped_lon2) = if lon1 > lon2 {
+                (Scalar::from_int(180), lon2 + Scalar::from_int(360))
+            } else {
+                (Scalar::from_int(-180), lon2 - Scalar::from_int(360))
+            };
+            let t = (edge - lon1) / (unwrapped_lon2 - lon1);
+            let lat = lat1 + t * (lat2 - lat1);
+            if current.last() != Some(&(lat, edge)) {
+                current.push((lat, edge));
+            }
+            parts.push(std::mem::take(&mut current));
+            current.push((lat, -edge));
+        }
+        if current.last() != Some(&(lat2, lon2)) {
+            current.push((lat2, lon2));
+        }
+    }
+    if current.is_empty() {
+        current.extend(line.first());
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bbox(min_lat: i64, min_lon: i64, max_lat: i64, max_lon: i64) -> Bbox {
+        Bbox::new(
+            Scalar::from_int(min_lat),
+            Scalar::from_int(min_lon),
+            Scalar::from_int(max_lat),
+            Scalar::from_int(max_lon),
+        )
+    }
+
+    #[test]
+    fn extend_crossing_box() {
+        let mut fiji = bbox(-20, 177, -15, -178);
+        fiji.extend(Scalar::from_int(-16), Scalar::from_int(179));
+        assert_eq!(fiji, bbox(-20, 177, -15, -178));
+        fiji.extend(Scalar::from_int(-21), Scalar::from_int(-175));
+        assert_eq!(fiji, bbox(-21, 177, -15, -175));
+        fiji.extend(Scalar::from_int(-16), Scalar::from_int(170));
+        assert_eq!(fiji, bbox(-21, 170, -15, -175));
+    }
+
+    #[test]
+    fn extend_across_antimeridian() {
+        let mut bbox_ = bbox(0, 170, 10, 175);
+        bbox_.extend(Scalar::from_int(5), Scalar::from_int(-175));
+        assert_eq!(bbox_, bbox(0, 170, 10, -175));
+        let mut bbox_ = bbox(0, -10, 10, 10);
+        bbox_.extend(Scalar::from_int(5), Scalar::from_int(20));
+        assert_eq!(bbox_, bbox(0, -10, 10, 20));
+        bbox_.extend(Scalar::from_int(5), Scalar::from_int(-170));
+        assert_eq!(bbox_, bbox(0, -170, 10, 20));
+    }
+
+    #[test]
+    fn of_points_does_not_cross() {
+        let points = [170, -175].map(|lon| (Scalar::from_int(0), Scalar::from_int(lon)));
+        assert_eq!(Bbox::of_points(points), Some(bbox(0, -175, 0, 170)));
+    }
+
+    #[test]
+    fn union_crossing_boxes() {
+        let fiji = bbox(-20, 177, -15, -178);
+        assert_eq!(
+            fiji.union(&bbox(-10, 175, -5, 178)),
+            bbox(-20, 175, -5, -178)
+        );
+        assert_eq!(
+            fiji.union(&bbox(-10, -179, -5, -170)),
+            bbox(-20, 177, -5, -170)
+        );
+        assert_eq!(
+            bbox(0, 170, 1, 175).union(&bbox(0, -175, 1, -170)),
+            bbox(0, 170, 1, -170)
+        );
+        let inside = bbox(-19, 179, -16, -179);
+        assert_eq!(fiji.union(&inside), fiji);
+        assert_eq!(inside.union(&fiji), fiji);
+        assert_eq!(
+            bbox(0, -10, 1, 10).union(&bbox(0, 20, 1, 30)),
+            bbox(0, -10, 1, 30)
+        );
+        assert_eq!(
+            fiji.union(&bbox(-20, -170, -15, 170)),
+            bbox(-20, -170, -15, -178)
+        );
+        assert_eq!(
+            fiji.union(&bbox(-20, -179, -15, 178)),
+            bbox(-20, -180, -15, 180)
+        );
+    }
+}
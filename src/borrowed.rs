@@ -0,0 +1,198 @@
+//! Elements whose strings borrow from where they were read
+//!
+//! [ElementRef] and the views it holds mirror the core types, but keep tags and roles as
+//! [Cow]s, so that a parser only allocates for strings it has to unescape.
+//! [opl::parse_borrowed](crate::opl::parse_borrowed) yields them without copying the line,
+//! e.g. to filter a file and only convert the elements that are kept with
+//! [ElementRef::into_owned]. Every [Element] can also be viewed as an [ElementRef].
+
+use std::borrow::Cow;
+
+use crate::scalar::Scalar;
+use crate::{
+    Element, ElementId, Id, Info, Member, MemberType, Node, Refs, Relation, TagString, Tags, Way,
+};
+
+/// Key and value of a tag
+pub type TagRef<'a> = (Cow<'a, str>, Cow<'a, str>);
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub enum ElementRef<'a> {
+    Node(NodeRef<'a>),
+    Way(WayRef<'a>),
+    Relation(RelationRef<'a>),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub struct NodeRef<'a> {
+    pub id: Id,
+    /// Tags in the order they were read
+    pub tags: Vec<TagRef<'a>>,
+    pub info: Option<Info>,
+    pub lat: Scalar,
+    pub lon: Scalar,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct WayRef<'a> {
+    pub id: Id,
+    pub tags: Vec<TagRef<'a>>,
+    pub info: Option<Info>,
+    pub refs: Refs,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RelationRef<'a> {
+    pub id: Id,
+    pub tags: Vec<TagRef<'a>>,
+    pub info: Option<Info>,
+    pub members: Vec<MemberRef<'a>>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct MemberRef<'a> {
+    pub id: Id,
+    pub ty: MemberType,
+    pub role: Option<Cow<'a, str>>,
+}
+
+impl ElementRef<'_> {
+    pub fn id(&self) -> Id {
+        match self {
+            ElementRef::Node(node) => node.id,
+            ElementRef::Way(way) => way.id,
+            ElementRef::Relation(relation) => relation.id,
+        }
+    }
+
+    pub fn member_type(&self) -> MemberType {
+        match self {
+            ElementRef::Node(_) => MemberType::Node,
+            ElementRef::Way(_) => MemberType::Way,
+            ElementRef::Relation(_) => MemberType::Relation,
+        }
+    }
+
+    pub fn element_id(&self) -> ElementId {
+        ElementId::new(self.member_type(), self.id())
+    }
+
+    pub fn tags(&self) -> &[TagRef<'_>] {
+        match self {
+            ElementRef::Node(node) => &node.tags,
+            ElementRef::Way(way) => &way.tags,
+            ElementRef::Relation(relation) => &relation.tags,
+        }
+    }
+
+    /// Value of the first tag with the key
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags()
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_ref())
+    }
+
+    pub fn info(&self) -> Option<&Info> {
+        match self {
+            ElementRef::Node(node) => node.info.as_ref(),
+            ElementRef::Way(way) => way.info.as_ref(),
+            ElementRef::Relation(relation) => relation.info.as_ref(),
+        }
+    }
+
+    /// Copies the strings into an [Element], keeping the last of repeated keys
+    pub fn into_owned(self) -> Element {
+        match self {
+            ElementRef::Node(node) => Element::Node(Node {
+                id: node.id,
+                tags: owned_tags(node.tags),
+                info: node.info,
+                lat: node.lat,
+                lon: node.lon,
+            }),
+            ElementRef::Way(way) => Element::Way(Way {
+                id: way.id,
+                tags: owned_tags(way.tags),
+                info: way.info,
+                refs: way.refs,
+            }),
+            ElementRef::Relation(relation) => Element::Relation(Relation {
+                id: relation.id,
+                tags: owned_tags(relation.tags),
+                info: relation.info,
+                members: relation
+                    .members
+                    .into_iter()
+                    .map(|member| Member {
+                        id: member.id,
+                        ty: member.ty,
+                        role: member.role.map(owned_string),
+                    })
+                    .collect(),
+            }),
+        }
+    }
+}
+
+fn owned_tags(tags: Vec<TagRef<'_>>) -> Tags {
+    tags.into_iter()
+        .map(|(key, value)| (owned_string(key), owned_string(value)))
+        .collect()
+}
+
+fn owned_string(s: Cow<'_, str>) -> TagString {
+    match s {
+        Cow::Borrowed(s) => TagString::from_ref(s),
+        Cow::Owned(s) => TagString::from_string(s),
+    }
+}
+
+fn borrowed_tags(tags: &Tags) -> Vec<TagRef<'_>> {
+    tags.iter()
+        .map(|(key, value)| (Cow::Borrowed(key.as_str()), Cow::Borrowed(value.as_str())))
+        .collect()
+}
+
+impl<'a> From<&'a Element> for ElementRef<'a> {
+    /// View of the element, which copies way nodes but no strings
+    fn from(element: &'a Element) -> Self {
+        match element {
+            Element::Node(node) => ElementRef::Node(NodeRef {
+                id: node.id,
+                tags: borrowed_tags(&node.tags),
+                info: node.info.clone(),
+                lat: node.lat,
+                lon: node.lon,
+            }),
+            Element::Way(way) => ElementRef::Way(WayRef {
+                id: way.id,
+                tags: borrowed_tags(&way.tags),
+                info: way.info.clone(),
+                refs: way.refs.clone(),
+            }),
+            Element::Relation(relation) => ElementRef::Relation(RelationRef {
+                id: relation.id,
+                tags: borrowed_tags(&relation.tags),
+                info: relation.info.clone(),
+                members: relation
+                    .members
+                    .iter()
+                    .map(|member| MemberRef {
+                        id: member.id,
+                        ty: member.ty.clone(),
+                        role: member.role.as_deref().map(Cow::Borrowed),
+                    })
+                    .collect(),
+            }),
+        }
+    }
+}
+
+impl From<ElementRef<'_>> for Element {
+    fn from(element: ElementRef<'_>) -> Self {
+        element.into_owned()
+    }
+}
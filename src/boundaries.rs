@@ -0,0 +1,173 @@
+//! Hierarchy of [administrative boundaries](https://wiki.openstreetmap.org/wiki/Tag:boundary%3Dadministrative)
+//!
+//! A [Hierarchy] holds the assembled `boundary=administrative` relations of an extract, so
+//! that [Hierarchy::locate] can say which country, state, county, and so on a coordinate lies
+//! in, ordered by [admin_level](https://wiki.openstreetmap.org/wiki/Key:admin_level). Unlike
+//! [crate::region], boundaries keep their relation with all its tags, e.g. names and
+//! `ref:*` codes, and their exact polygons, which suits geocoding and statistics per area.
+//!
+//! Boundaries are found through a grid of cells like those of [crate::region::RegionIndex],
+//! and then tested with [MultiPolygon::locate], so points on a border shared by two
+//! boundaries are in both.
+
+use fnv::FnvHashMap as HashMap;
+
+use crate::bbox::Bbox;
+use crate::multipolygon::{AssemblyError, MultiPolygon};
+use crate::scalar::{Scalar, ScalarExt};
+use crate::store::ElementStore;
+use crate::{Id, Relation, Tags};
+
+/// Size of the cells of the index in degrees
+const CELL_SIZE: f64 = 1.;
+
+/// `admin_level` of an administrative boundary, [None] for other tags
+///
+/// Levels are numbers from 1 upwards, with 2 for countries and higher numbers for smaller
+/// subdivisions, whose meaning differs between countries.
+pub fn admin_level(tags: &Tags) -> Option<u8> {
+    if !tags.has("boundary", "administrative") {
+        return None;
+    }
+    tags.parse::<u8>("admin_level").filter(|&level| level > 0)
+}
+
+/// Administrative boundary with its polygons
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub struct Boundary {
+    pub relation: Relation,
+    pub admin_level: u8,
+    pub multipolygon: MultiPolygon,
+    pub bbox: Bbox,
+}
+
+impl Boundary {
+    /// Boundary of a relation with an [admin_level] and its assembled polygons, [None] for
+    /// other relations or without polygons
+    pub fn new(relation: Relation, multipolygon: MultiPolygon) -> Option<Self> {
+        let admin_level = admin_level(&relation.tags)?;
+        let bbox = Bbox::of_points(
+            multipolygon
+                .polygons
+                .iter()
+                .flat_map(|polygon| polygon.outer.iter().copied()),
+        )?;
+        Some(Self {
+            relation,
+            admin_level,
+            multipolygon,
+            bbox,
+        })
+    }
+
+    /// Whether the point is inside or on the boundary
+    pub fn contains(&self, lat: Scalar, lon: Scalar) -> bool {
+        self.bbox.contains(lat, lon) && self.multipolygon.contains(lat, lon)
+    }
+}
+
+/// [Boundary]s indexed by a grid of cells for fast lookups
+#[derive(Debug, Clone, Default)]
+pub struct Hierarchy {
+    boundaries: Vec<Boundary>,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl Hierarchy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hierarchy of the administrative boundaries of the store, with the relations that
+    /// failed to assemble, e.g. because an extract cut them off
+    pub fn from_store(store: &ElementStore) -> (Self, Vec<(Id, AssemblyError)>) {
+        let mut hierarchy = Self::new();
+        let mut failed = vec![];
+        for relation in store.relations() {
+            if admin_level(&relation.tags).is_none() {
+                continue;
+            }
+            match store.assemble_multipolygon(relation) {
+                Ok(multipolygon) => {
+                    hierarchy.insert(relation.clone(), multipolygon);
+                }
+                Err(err) => failed.push((relation.id, err)),
+            }
+        }
+        (hierarchy, failed)
+    }
+
+    /// Adds the relation as a [Boundary], returning false if it is not one
+    pub fn insert(&mut self, relation: Relation, multipolygon: MultiPolygon) -> bool {
+        let Some(boundary) = Boundary::new(relation, multipolygon) else {
+            return false;
+        };
+        let index = self.boundaries.len();
+        let bbox = &boundary.bbox;
+        let min_cell = cell(bbox.min_lat.as_f64(), bbox.min_lon.as_f64());
+        let max_cell = cell(bbox.max_lat.as_f64(), bbox.max_lon.as_f64());
+        for row in min_cell.0..=max_cell.0 {
+            for column in min_cell.1..=max_cell.1 {
+                self.cells.entry((row, column)).or_default().push(index);
+            }
+        }
+        self.boundaries.push(boundary);
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.boundaries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.boundaries.is_empty()
+    }
+
+    pub fn boundaries(&self) -> impl Iterator<Item = &Boundary> {
+        self.boundaries.iter()
+    }
+
+    /// Boundaries containing the coordinate, from the lowest `admin_level` to the highest
+    ///
+    /// Boundaries of the same level, e.g. of disputed areas, are ordered from the largest
+    /// bounding box to the smallest.
+    pub fn boundaries_at(&self, lat: Scalar, lon: Scalar) -> Vec<&Boundary> {
+        let mut boundaries: Vec<&Boundary> = self
+            .cells
+            .get(&cell(lat.as_f64(), lon.as_f64()))
+            .into_iter()
+            .flatten()
+            .map(|&index| &self.boundaries[index])
+            .filter(|boundary| boundary.contains(lat, lon))
+            .collect();
+        boundaries.sort_by(|a, b| {
+            (a.admin_level.cmp(&b.admin_level)).then(b.bbox.area().total_cmp(&a.bbox.area()))
+        });
+        boundaries
+    }
+
+    /// Relations of the [Hierarchy::boundaries_at] the coordinate, e.g. of the country first
+    pub fn locate(&self, lat: Scalar, lon: Scalar) -> Vec<&Relation> {
+        self.boundaries_at(lat, lon)
+            .into_iter()
+            .map(|boundary| &boundary.relation)
+            .collect()
+    }
+
+    /// Boundary of the highest `admin_level` containing the coordinate, optionally at most
+    /// `max_level`, e.g. 8 for the municipality
+    pub fn innermost(&self, lat: Scalar, lon: Scalar, max_level: Option<u8>) -> Option<&Boundary> {
+        self.boundaries_at(lat, lon)
+            .into_iter()
+            .rev()
+            .find(|boundary| max_level.is_none_or(|max| boundary.admin_level <= max))
+    }
+}
+
+fn cell(lat: f64, lon: f64) -> (i32, i32) {
+    (
+        (lat / CELL_SIZE).floor() as i32,
+        (lon / CELL_SIZE).floor() as i32,
+    )
+}
@@ -0,0 +1,151 @@
+//! Builders for [Node], [Way], and [Relation]
+//!
+//! Each starts from an id with no tags and no [Info], and [validates](crate::validate) the
+//! element on `build`.
+
+use crate::error::{Error, Result};
+use crate::scalar::{Scalar, ScalarExt};
+use crate::validate::ValidationError;
+use crate::{
+    ElementId, Id, Info, Member, MemberType, Members, Node, Refs, Relation, TagString, Tags, Way,
+};
+
+impl Node {
+    /// Starts building a node at latitude and longitude 0
+    pub fn builder(id: impl Into<Id>) -> NodeBuilder {
+        NodeBuilder(Node {
+            id: id.into(),
+            tags: Tags::new(),
+            info: None,
+            lat: Scalar::from_int(0),
+            lon: Scalar::from_int(0),
+        })
+    }
+}
+
+impl Way {
+    pub fn builder(id: impl Into<Id>) -> WayBuilder {
+        WayBuilder(Way {
+            id: id.into(),
+            tags: Tags::new(),
+            info: None,
+            refs: Refs::new(),
+        })
+    }
+}
+
+impl Relation {
+    pub fn builder(id: impl Into<Id>) -> RelationBuilder {
+        RelationBuilder(Relation {
+            id: id.into(),
+            tags: Tags::new(),
+            info: None,
+            members: Members::new(),
+        })
+    }
+}
+
+/// Builder returned by [Node::builder]
+#[derive(Debug, PartialEq, Clone)]
+pub struct NodeBuilder(Node);
+
+impl NodeBuilder {
+    pub fn lat(mut self, lat: Scalar) -> Self {
+        self.0.lat = lat;
+        self
+    }
+
+    pub fn lon(mut self, lon: Scalar) -> Self {
+        self.0.lon = lon;
+        self
+    }
+
+    pub fn tag(mut self, key: impl Into<TagString>, value: impl Into<TagString>) -> Self {
+        self.0.tags.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn info(mut self, info: Info) -> Self {
+        self.0.info = Some(info);
+        self
+    }
+
+    /// Fails with the first error of [Node::validate]
+    pub fn build(self) -> Result<Node> {
+        check(self.0.validate(), MemberType::Node, self.0.id)?;
+        Ok(self.0)
+    }
+}
+
+/// Builder returned by [Way::builder]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct WayBuilder(Way);
+
+impl WayBuilder {
+    pub fn node(mut self, id: impl Into<Id>) -> Self {
+        self.0.refs.push(id.into());
+        self
+    }
+
+    pub fn nodes<I: Into<Id>>(mut self, ids: impl IntoIterator<Item = I>) -> Self {
+        self.0.refs.extend(ids.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn tag(mut self, key: impl Into<TagString>, value: impl Into<TagString>) -> Self {
+        self.0.tags.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn info(mut self, info: Info) -> Self {
+        self.0.info = Some(info);
+        self
+    }
+
+    /// Fails with the first error of [Way::validate]
+    pub fn build(self) -> Result<Way> {
+        check(self.0.validate(), MemberType::Way, self.0.id)?;
+        Ok(self.0)
+    }
+}
+
+/// Builder returned by [Relation::builder]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RelationBuilder(Relation);
+
+impl RelationBuilder {
+    /// Adds a member, without a role if `role` is empty
+    pub fn member(mut self, id: impl Into<ElementId>, role: impl Into<TagString>) -> Self {
+        let id = id.into();
+        let role = role.into();
+        self.0.members.push(Member {
+            id: id.id(),
+            ty: id.member_type(),
+            role: (!role.is_empty()).then_some(role),
+        });
+        self
+    }
+
+    pub fn tag(mut self, key: impl Into<TagString>, value: impl Into<TagString>) -> Self {
+        self.0.tags.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn info(mut self, info: Info) -> Self {
+        self.0.info = Some(info);
+        self
+    }
+
+    /// Fails with the first error of [Relation::validate]
+    pub fn build(self) -> Result<Relation> {
+        check(self.0.validate(), MemberType::Relation, self.0.id)?;
+        Ok(self.0)
+    }
+}
+
+fn check(errors: Vec<ValidationError>, ty: MemberType, id: Id) -> Result<()> {
+    match errors.into_iter().next() {
+        Some(err) => Err(Error::from(err).with_element(ty, id)),
+        None => Ok(()),
+    }
+}
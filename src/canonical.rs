@@ -0,0 +1,123 @@
+//! Canonical form of elements, for comparisons and reproducible output
+//!
+//! Editors and converters write the same content in slightly different ways: tags with stray
+//! whitespace, members with an empty role or none, or coordinates with trailing zeros such as
+//! `1.50`. [Element::canonicalize] rewrites these into one form, so that equal content
+//! compares, hashes, and prints the same. [Element::eq_ignoring_info] further leaves out the
+//! [Info](crate::Info) of each version.
+
+use crate::scalar::ScalarExt;
+use crate::tags::TagMap;
+use crate::{Element, Member, Node, Relation, TagString, Tags, Way};
+
+fn trimmed(s: &TagString) -> TagString {
+    let trimmed = s.trim();
+    if trimmed.len() == s.len() {
+        s.clone()
+    } else {
+        TagString::from_ref(trimmed)
+    }
+}
+
+impl Tags {
+    /// Trims whitespace around keys and values and removes tags left with an empty key or value
+    ///
+    /// The map is rebuilt in key order, so that canonical tags with the same content also
+    /// iterate in the same order. Of keys that only differ in whitespace, the one first in key
+    /// order is kept, which is the one that was already trimmed if any.
+    pub fn canonicalize(&mut self) {
+        let mut map = TagMap::with_capacity_and_hasher(self.len(), Default::default());
+        for (key, value) in self.iter_sorted() {
+            let (key, value) = (trimmed(key), trimmed(value));
+            if !key.is_empty() && !value.is_empty() {
+                map.entry(key).or_insert(value);
+            }
+        }
+        *self = map.into();
+    }
+}
+
+impl Member {
+    /// Trims whitespace around the role, and replaces an empty role with [None]
+    pub fn canonicalize(&mut self) {
+        self.role = self
+            .role
+            .as_ref()
+            .map(trimmed)
+            .filter(|role| !role.is_empty());
+    }
+}
+
+impl Node {
+    /// Canonical tags, see [Tags::canonicalize], and coordinates without trailing zeros
+    pub fn canonicalize(&mut self) {
+        self.tags.canonicalize();
+        self.lat = self.lat.normalized();
+        self.lon = self.lon.normalized();
+    }
+
+    /// Whether both have the same id, tags, and location, whatever their [Node::info]
+    pub fn eq_ignoring_info(&self, other: &Node) -> bool {
+        self.id == other.id
+            && self.lat == other.lat
+            && self.lon == other.lon
+            && self.tags == other.tags
+    }
+}
+
+impl Way {
+    /// Canonical tags, see [Tags::canonicalize]
+    pub fn canonicalize(&mut self) {
+        self.tags.canonicalize();
+    }
+
+    /// Whether both have the same id, tags, and nodes, whatever their [Way::info]
+    pub fn eq_ignoring_info(&self, other: &Way) -> bool {
+        self.id == other.id && self.refs == other.refs && self.tags == other.tags
+    }
+}
+
+impl Relation {
+    /// Canonical tags and roles, see [Tags::canonicalize] and [Member::canonicalize]
+    pub fn canonicalize(&mut self) {
+        self.tags.canonicalize();
+        self.members.iter_mut().for_each(Member::canonicalize);
+    }
+
+    /// Whether both have the same id, tags, and members, whatever their [Relation::info]
+    pub fn eq_ignoring_info(&self, other: &Relation) -> bool {
+        self.id == other.id && self.members == other.members && self.tags == other.tags
+    }
+}
+
+impl Element {
+    /// Rewrites the element into its canonical form, leaving [Element::info] as is
+    pub fn canonicalize(&mut self) {
+        match self {
+            Element::Node(node) => node.canonicalize(),
+            Element::Way(way) => way.canonicalize(),
+            Element::Relation(relation) => relation.canonicalize(),
+        }
+    }
+
+    /// Canonical form of a copy of the element, see [Element::canonicalize]
+    pub fn canonical(&self) -> Element {
+        let mut element = self.clone();
+        element.canonicalize();
+        element
+    }
+
+    /// Whether both are the same type and have the same content, whatever their
+    /// [Element::info]
+    ///
+    /// Content is compared as is, so canonicalize both first to also ignore differences in
+    /// whitespace and roles.
+    pub fn eq_ignoring_info(&self, other: &Element) -> bool {
+        match (self, other) {
+            (Element::Node(a), Element::Node(b)) => a.eq_ignoring_info(b),
+            (Element::Way(a), Element::Way(b)) => a.eq_ignoring_info(b),
+            (Element::Relation(a), Element::Relation(b)) => a.eq_ignoring_info(b),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! Classification of points of interest into categories for search and display
+//!
+//! <https://wiki.openstreetmap.org/wiki/Points_of_interest>
+
+use fnv::FnvHashMap as HashMap;
+
+use crate::TagString;
+
+/// Category of a point of interest
+///
+/// Categories form a two-level hierarchy: specific categories like [Category::Cafe]
+/// belong to a general category like [Category::Food].
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Category {
+    Food,
+    Restaurant,
+    FastFood,
+    Cafe,
+    Bar,
+
+    Health,
+    Hospital,
+    Clinic,
+    Pharmacy,
+
+    Education,
+    School,
+    University,
+    Kindergarten,
+    Library,
+
+    Shopping,
+    Supermarket,
+    Clothing,
+
+    Accommodation,
+    Hotel,
+    Camping,
+
+    Transport,
+    Fuel,
+    Parking,
+    ChargingStation,
+
+    Finance,
+    Bank,
+    Atm,
+
+    Leisure,
+    Park,
+    Sports,
+    Entertainment,
+
+    Tourism,
+    Attraction,
+    Museum,
+
+    PublicService,
+    Religion,
+    Office,
+}
+
+impl Category {
+    /// General category that this category belongs to, if it is specific
+    pub fn parent(self) -> Option<Category> {
+        use Category::*;
+        match self {
+            Restaurant | FastFood | Cafe | Bar => Some(Food),
+            Hospital | Clinic | Pharmacy => Some(Health),
+            School | University | Kindergarten | Library => Some(Education),
+            Supermarket | Clothing => Some(Shopping),
+            Hotel | Camping => Some(Accommodation),
+            Fuel | Parking | ChargingStation => Some(Transport),
+            Bank | Atm => Some(Finance),
+            Park | Sports | Entertainment => Some(Leisure),
+            Attraction | Museum => Some(Tourism),
+            Food | Health | Education | Shopping | Accommodation | Transport | Finance
+            | Leisure | Tourism | PublicService | Religion | Office => None,
+        }
+    }
+
+    /// The general category of this category, or itself if it is general
+    pub fn root(self) -> Category {
+        self.parent().unwrap_or(self)
+    }
+}
+
+/// Assigns a [Category] to elements with a matching tag
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rule {
+    pub key: TagString,
+    /// Value the tag must have, or [None] to match any value
+    pub value: Option<TagString>,
+    pub category: Category,
+}
+
+impl Rule {
+    pub fn new(key: impl Into<TagString>, value: Option<&str>, category: Category) -> Self {
+        Self {
+            key: key.into(),
+            value: value.map(TagString::from_ref),
+            category,
+        }
+    }
+
+    pub fn matches(&self, tags: &HashMap<TagString, TagString>) -> bool {
+        match (tags.get(&self.key), &self.value) {
+            (Some(actual), Some(expected)) => actual == expected,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+/// Ordered table of [Rule]s where the first matching rule wins
+///
+/// [Classifier::default] covers common values of the `amenity`, `shop`, `tourism`,
+/// `leisure`, `office`, and `healthcare` keys.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Classifier {
+    pub rules: Vec<Rule>,
+}
+
+impl Classifier {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self { rules }
+    }
+
+    pub fn classify(&self, tags: &HashMap<TagString, TagString>) -> Option<Category> {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(tags))
+            .map(|rule| rule.category)
+    }
+}
+
+impl Default for Classifier {
+    fn default() -> Self {
+        use Category::*;
+        const SPECIFIC: &[(&str, &[&str], Category)] = &[
+            ("amenity", &["restaurant"], Restaurant),
+            ("amenity", &["fast_food", "food_court"], FastFood),
+            ("amenity", &["cafe", "ice_cream"], Cafe),
+            ("amenity", &["bar", "pub", "biergarten"], Bar),
+            ("amenity", &["hospital"], Hospital),
+            ("amenity", &["clinic", "doctors", "dentist"], Clinic),
+            ("amenity", &["pharmacy"], Pharmacy),
+            ("healthcare", &["pharmacy"], Pharmacy),
+            ("amenity", &["school"], School),
+            ("amenity", &["university", "college"], University),
+            ("amenity", &["kindergarten", "childcare"], Kindergarten),
+            ("amenity", &["library"], Library),
+            (
+                "shop",
+                &["supermarket", "convenience", "grocery"],
+                Supermarket,
+            ),
+            (
+                "shop",
+                &["clothes", "shoes", "fashion_accessories"],
+                Clothing,
+            ),
+            (
+                "tourism",
+                &["hotel", "motel", "guest_house", "hostel", "apartment"],
+                Hotel,
+            ),
+            ("tourism", &["camp_site", "caravan_site"], Camping),
+            ("amenity", &["fuel"], Fuel),
+            (
+                "amenity",
+                &["parking", "bicycle_parking", "motorcycle_parking"],
+                Parking,
+            ),
+            ("amenity", &["charging_station"], ChargingStation),
+            ("amenity", &["bank", "bureau_de_change"], Bank),
+            ("amenity", &["atm"], Atm),
+            (
+                "leisure",
+                &["park", "garden", "playground", "nature_reserve"],
+                Park,
+            ),
+            (
+                "leisure",
+                &[
+                    "sports_centre",
+                    "pitch",
+                    "fitness_centre",
+                    "stadium",
+                    "swimming_pool",
+                ],
+                Sports,
+            ),
+            (
+                "amenity",
+                &["cinema", "theatre", "nightclub", "arts_centre"],
+                Entertainment,
+            ),
+            ("tourism", &["museum", "gallery"], Museum),
+            (
+                "tourism",
+                &["attraction", "viewpoint", "zoo", "theme_park"],
+                Attraction,
+            ),
+            (
+                "amenity",
+                &[
+                    "townhall",
+                    "police",
+                    "fire_station",
+                    "post_office",
+                    "courthouse",
+                ],
+                PublicService,
+            ),
+            ("amenity", &["place_of_worship", "monastery"], Religion),
+        ];
+        const GENERAL: &[(&str, Category)] = &[
+            ("healthcare", Health),
+            ("shop", Shopping),
+            ("office", Office),
+            ("tourism", Tourism),
+            ("leisure", Leisure),
+        ];
+
+        let specific = SPECIFIC.iter().flat_map(|(key, values, category)| {
+            values
+                .iter()
+                .map(|value| Rule::new(TagString::from_static(key), Some(value), *category))
+        });
+        let general = GENERAL
+            .iter()
+            .map(|(key, category)| Rule::new(TagString::from_static(key), None, *category));
+        Self::new(specific.chain(general).collect())
+    }
+}
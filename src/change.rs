@@ -0,0 +1,569 @@
+//! Changes to elements, as in [osmChange](https://wiki.openstreetmap.org/wiki/OsmChange) files
+//!
+//! [apply_sorted] merges a sorted stream of changes into a sorted stream of elements, e.g. a
+//! planet file and a replication diff, holding only one element of each in memory.
+//! [OsmChange] reads and writes whole documents, and [Reader] streams their changes.
+
+use std::cmp::Ordering;
+use std::io::{BufRead, Write};
+
+use crate::error::{ElementContext, Error};
+use crate::feed::{Buffer, Decode};
+use crate::parse::{ParseOptions, Recovery};
+use crate::pipeline::Source;
+use crate::xml::{self, Scanner, TagKind, Tokenizer};
+use crate::Element;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Action {
+    Create,
+    Modify,
+    Delete,
+}
+
+/// New state of an element along with how it changed
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Change {
+    pub action: Action,
+    /// Element after the change, which for deletions only needs its id and version
+    pub element: Element,
+}
+
+/// Reason a change does not fit the element it applies to
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum ConflictKind {
+    /// Element to modify or delete is not in the base
+    MissingBase,
+    /// Element to create is already in the base
+    AlreadyExists,
+    /// Version of the change is not newer than the version in the base
+    Stale,
+}
+
+/// Conflict passed to the callback of [apply_sorted]
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub struct Conflict<'a> {
+    pub kind: ConflictKind,
+    pub change: &'a Change,
+    /// Element in the base with the same type and id, if any
+    pub base: Option<&'a Element>,
+}
+
+impl Conflict<'_> {
+    fn message(&self) -> String {
+        let action = self.change.action.as_str();
+        match self.kind {
+            ConflictKind::MissingBase => format!("cannot {action}, element does not exist"),
+            ConflictKind::AlreadyExists => format!("cannot {action}, element already exists"),
+            ConflictKind::Stale => format!(
+                "cannot {action} version {} over version {}",
+                version(&self.change.element).unwrap_or_default(),
+                self.base.and_then(version).unwrap_or_default()
+            ),
+        }
+    }
+}
+
+/// What [apply_sorted] does about a [Conflict]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Resolution {
+    /// Keeps the base as it is
+    Skip,
+    /// Applies the change anyway, e.g. replacing an existing element on creation
+    Apply,
+    /// Stops with [Error::Conflict]
+    Abort,
+}
+
+/// Applies `changes` to `base`, calling `on_conflict` to resolve conflicts
+///
+/// Both inputs must be sorted by type (nodes, ways, then relations) and then by id, as
+/// planet files and replication diffs are, and the output is sorted the same way. When
+/// several changes apply to the same element, only the last one is applied. Unsorted input
+/// stops with [Error::Validation].
+pub fn apply_sorted<S, C, F>(base: S, changes: C, on_conflict: F) -> ApplySorted<S, C, F>
+where
+    S: Source,
+    S::Error: Into<Error>,
+    C: Iterator<Item = Result<Change, Error>>,
+    F: FnMut(&Conflict) -> Resolution,
+{
+    ApplySorted {
+        base,
+        changes,
+        on_conflict,
+        next_base: None,
+        next_change: None,
+        lookahead: None,
+        last_base: None,
+        last_change: None,
+        done: false,
+    }
+}
+
+/// Iterator returned by [apply_sorted]
+#[derive(Debug)]
+pub struct ApplySorted<S, C, F> {
+    base: S,
+    changes: C,
+    on_conflict: F,
+    next_base: Option<Element>,
+    next_change: Option<Change>,
+    /// Change read ahead to find later changes to the same element
+    lookahead: Option<Change>,
+    last_base: Option<(u8, i64)>,
+    last_change: Option<(u8, i64)>,
+    done: bool,
+}
+
+fn key(element: &Element) -> (u8, i64) {
+    let rank = match element {
+        Element::Node(_) => 0,
+        Element::Way(_) => 1,
+        Element::Relation(_) => 2,
+    };
+    (rank, element.id().0)
+}
+
+fn version(element: &Element) -> Option<i32> {
+    element.info().map(|info| info.version)
+}
+
+fn context(element: &Element) -> ElementContext {
+    ElementContext {
+        ty: element.member_type(),
+        id: element.id(),
+    }
+}
+
+fn check_order(last: &mut Option<(u8, i64)>, element: &Element, input: &str) -> Result<(), Error> {
+    let key = key(element);
+    if last.is_some_and(|last| last > key) {
+        return Err(Error::Validation {
+            element: Some(context(element)),
+            message: format!("{input} is not sorted"),
+        });
+    }
+    *last = Some(key);
+    Ok(())
+}
+
+impl<S, C, F> ApplySorted<S, C, F>
+where
+    S: Source,
+    S::Error: Into<Error>,
+    C: Iterator<Item = Result<Change, Error>>,
+    F: FnMut(&Conflict) -> Resolution,
+{
+    fn fill_base(&mut self) -> Result<(), Error> {
+        if self.next_base.is_none() {
+            if let Some(element) = self.base.read() {
+                let element = element.map_err(Into::into)?;
+                check_order(&mut self.last_base, &element, "base")?;
+                self.next_base = Some(element);
+            }
+        }
+        Ok(())
+    }
+
+    fn read_change(&mut self) -> Result<Option<Change>, Error> {
+        if let Some(change) = self.lookahead.take() {
+            return Ok(Some(change));
+        }
+        let change = self.changes.next().transpose()?;
+        if let Some(change) = &change {
+            check_order(&mut self.last_change, &change.element, "changes")?;
+        }
+        Ok(change)
+    }
+
+    /// Next change, after skipping to the last of several changes to the same element
+    fn peek_change(&mut self) -> Result<Option<&Change>, Error> {
+        if self.next_change.is_none() {
+            let Some(mut current) = self.read_change()? else {
+                return Ok(None);
+            };
+            while let Some(change) = self.read_change()? {
+                if key(&change.element) == key(&current.element) {
+                    current = change;
+                } else {
+                    self.lookahead = Some(change);
+                    break;
+                }
+            }
+            self.next_change = Some(current);
+        }
+        Ok(self.next_change.as_ref())
+    }
+
+    /// Resolves a conflict, returning whether to apply the change
+    fn resolve(&mut self, conflict: Conflict) -> Result<bool, Error> {
+        let resolution = (self.on_conflict)(&conflict);
+        #[cfg(feature = "tracing")]
+        tracing::debug!(kind = ?conflict.kind, ?resolution, "conflict");
+        match resolution {
+            Resolution::Skip => Ok(false),
+            Resolution::Apply => Ok(true),
+            Resolution::Abort => Err(Error::Conflict {
+                element: context(&conflict.change.element),
+                message: conflict.message(),
+            }),
+        }
+    }
+
+    fn step(&mut self) -> Result<Option<Element>, Error> {
+        loop {
+            self.fill_base()?;
+            let change = self.peek_change()?.map(|change| key(&change.element));
+            let order = match (self.next_base.as_ref().map(key), change) {
+                (None, None) => return Ok(None),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(base), Some(change)) => base.cmp(&change),
+            };
+            if order == Ordering::Less {
+                return Ok(self.next_base.take());
+            }
+            let change = self.next_change.take().expect("change was peeked");
+            let base = if order == Ordering::Equal {
+                self.next_base.take()
+            } else {
+                None
+            };
+
+            let kind = match (&base, change.action) {
+                (None, Action::Create) => None,
+                (None, _) => Some(ConflictKind::MissingBase),
+                (Some(_), Action::Create) => Some(ConflictKind::AlreadyExists),
+                (Some(base), _) => match (version(base), version(&change.element)) {
+                    (Some(old), Some(new)) if new <= old => Some(ConflictKind::Stale),
+                    _ => None,
+                },
+            };
+            let apply = match kind {
+                None => true,
+                Some(kind) => self.resolve(Conflict {
+                    kind,
+                    change: &change,
+                    base: base.as_ref(),
+                })?,
+            };
+
+            match (apply, change.action) {
+                (true, Action::Delete) => {}
+                (true, _) => return Ok(Some(change.element)),
+                (false, _) => {
+                    if base.is_some() {
+                        return Ok(base);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<S, C, F> Iterator for ApplySorted<S, C, F>
+where
+    S: Source,
+    S::Error: Into<Error>,
+    C: Iterator<Item = Result<Change, Error>>,
+    F: FnMut(&Conflict) -> Resolution,
+{
+    type Item = Result<Element, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.step().transpose();
+        if !matches!(next, Some(Ok(_))) {
+            self.done = true;
+        }
+        next
+    }
+}
+
+impl Action {
+    /// Name of the section of an osmChange document with changes of this action
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Action::Create => "create",
+            Action::Modify => "modify",
+            Action::Delete => "delete",
+        }
+    }
+}
+
+/// [osmChange](https://wiki.openstreetmap.org/wiki/OsmChange) document, e.g. a
+/// [replication diff](https://wiki.openstreetmap.org/wiki/Planet.osm/diffs) or an upload
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub struct OsmChange {
+    /// Version of the format, `0.6` for current files
+    pub version: Option<String>,
+    /// Program that wrote the document
+    pub generator: Option<String>,
+    /// Changes in the order of the document, which matters for uploads
+    pub changes: Vec<Change>,
+}
+
+impl OsmChange {
+    /// Empty document of version `0.6` from [xml::DEFAULT_GENERATOR]
+    pub fn new() -> Self {
+        Self {
+            version: Some("0.6".to_string()),
+            generator: Some(xml::DEFAULT_GENERATOR.to_string()),
+            changes: vec![],
+        }
+    }
+
+    /// Reads a whole document, see [Reader] to stream large ones
+    pub fn read(input: impl BufRead) -> Result<Self, Error> {
+        let mut reader = Reader::new(input);
+        let changes = reader.by_ref().collect::<Result<_, _>>()?;
+        Ok(Self {
+            version: reader.version,
+            generator: reader.generator,
+            changes,
+        })
+    }
+
+    /// Writes the document, grouping consecutive changes with the same action
+    ///
+    /// Deleted elements are written in full, although readers only need their ids and versions.
+    pub fn write(&self, output: impl Write) -> Result<(), Error> {
+        self.write_with(output, false)
+    }
+
+    /// Writes the document, with `if-unused` on delete sections for uploads
+    pub(crate) fn write_with(&self, mut output: impl Write, if_unused: bool) -> Result<(), Error> {
+        let mut buf = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<osmChange");
+        if let Some(version) = &self.version {
+            xml::attribute(&mut buf, "version", version);
+        }
+        if let Some(generator) = &self.generator {
+            xml::attribute(&mut buf, "generator", generator);
+        }
+        buf.push_str(">\n");
+        for group in self.changes.chunk_by(|a, b| a.action == b.action) {
+            let action = group[0].action.as_str();
+            buf.push_str(&format!(" <{action}"));
+            if if_unused && group[0].action == Action::Delete {
+                xml::attribute(&mut buf, "if-unused", true);
+            }
+            buf.push_str(">\n");
+            for change in group {
+                xml::write_element(&mut buf, &change.element, 2);
+            }
+            buf.push_str(&format!(" </{action}>\n"));
+            output.write_all(buf.as_bytes())?;
+            buf.clear();
+        }
+        buf.push_str("</osmChange>\n");
+        output.write_all(buf.as_bytes())?;
+        output.flush()?;
+        Ok(())
+    }
+}
+
+impl FromIterator<Change> for OsmChange {
+    fn from_iter<T: IntoIterator<Item = Change>>(iter: T) -> Self {
+        Self {
+            changes: iter.into_iter().collect(),
+            ..Self::new()
+        }
+    }
+}
+
+/// Reader of the [Change]s in an osmChange document, e.g. for [apply_sorted]
+///
+/// Attributes of `delete` sections, such as `if-unused` in uploads, are ignored. Malformed
+/// elements end reading unless [ParseOptions] say otherwise.
+#[derive(Debug)]
+pub struct Reader<R> {
+    tokenizer: Tokenizer<R>,
+    action: Option<Action>,
+    version: Option<String>,
+    generator: Option<String>,
+    recovery: Recovery,
+    done: bool,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            tokenizer: Tokenizer::new(input),
+            action: None,
+            version: None,
+            generator: None,
+            recovery: Recovery::default(),
+            done: false,
+        }
+    }
+}
+
+impl<R> Reader<R> {
+    /// Sets how malformed elements are handled
+    ///
+    /// Deleted elements are not checked by [ParseOptions::strict], as they only need their
+    /// ids and versions.
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.recovery.options = options;
+        self
+    }
+
+    /// Errors of the malformed elements skipped so far with [ErrorPolicy::Warn]
+    ///
+    /// [ErrorPolicy::Warn]: crate::parse::ErrorPolicy::Warn
+    pub fn warnings(&self) -> &[Error] {
+        &self.recovery.warnings
+    }
+
+    /// Takes the [Reader::warnings], e.g. to report them while reading a large diff
+    pub fn take_warnings(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.recovery.warnings)
+    }
+
+    /// Version of the format, which is available once the first change was read
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Program that wrote the document, which is available once the first change was read
+    pub fn generator(&self) -> Option<&str> {
+        self.generator.as_deref()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.tokenizer.input
+    }
+}
+
+impl<R: BufRead> Reader<R> {
+    fn read_change(&mut self) -> Result<Option<Change>, Error> {
+        while let Some(tag) = self.tokenizer.next_tag_or_malformed()? {
+            let line = self.tokenizer.tag_line;
+            let tag = match tag {
+                Ok(tag) => tag,
+                Err(err) => {
+                    self.recovery.skip(err.at_line(line))?;
+                    continue;
+                }
+            };
+            let action = match tag.name.as_str() {
+                "create" => Some(Action::Create),
+                "modify" => Some(Action::Modify),
+                "delete" => Some(Action::Delete),
+                _ => None,
+            };
+            match (tag.kind, tag.name.as_str()) {
+                (TagKind::Start, _) if action.is_some() => self.action = action,
+                (TagKind::End, _) if action.is_some() => self.action = None,
+                (TagKind::End, _) => {}
+                (_, "node" | "way" | "relation") => {
+                    let offset = Some(self.tokenizer.tag_offset);
+                    let deleted = self.action == Some(Action::Delete);
+                    let element = xml::read_element(&mut self.tokenizer, &tag, deleted)?;
+                    let Some(action) = self.action else {
+                        let message = "element outside of create, modify, or delete";
+                        self.recovery
+                            .skip(Error::decode(offset, message).at_line(line))?;
+                        continue;
+                    };
+                    let element = element.and_then(|element| match action {
+                        Action::Delete => Ok(element),
+                        _ => self.recovery.check(&element).map(|()| element),
+                    });
+                    match element {
+                        Ok(element) => return Ok(Some(Change { action, element })),
+                        Err(err) => self.recovery.skip(err.at_line(line))?,
+                    }
+                }
+                (_, "osmChange") => {
+                    self.version = tag.attribute("version").map(str::to_string);
+                    self.generator = tag.attribute("generator").map(str::to_string);
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<Change, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let change = self
+            .read_change()
+            .map_err(|err| err.at_line(self.tokenizer.tag_line))
+            .transpose();
+        if !matches!(change, Some(Ok(_))) {
+            self.done = true;
+        }
+        change
+    }
+}
+
+/// [Decode]r of an osmChange document pushed in chunks, e.g. a diff being downloaded, which
+/// yields the same as a [Reader]
+#[derive(Debug)]
+pub struct Decoder {
+    reader: Reader<Buffer>,
+    scanner: Scanner,
+    ended: bool,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self {
+            reader: Reader::new(Buffer::default()),
+            scanner: Scanner::default(),
+            ended: false,
+        }
+    }
+
+    /// Version of the format, which is available once the first change was decoded
+    pub fn version(&self) -> Option<&str> {
+        self.reader.version()
+    }
+
+    /// Program that wrote the document, which is available once the first change was decoded
+    pub fn generator(&self) -> Option<&str> {
+        self.reader.generator()
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decode for Decoder {
+    type Item = Change;
+
+    fn push(&mut self, data: &[u8]) {
+        let buffer = &mut self.reader.tokenizer.input;
+        buffer.push(data);
+        self.scanner.scan(buffer);
+    }
+
+    fn end(&mut self) {
+        self.ended = true;
+    }
+
+    fn decode(&mut self) -> Option<Result<Change, Error>> {
+        if !self.ended {
+            self.scanner.ready = self.scanner.ready.checked_sub(1)?;
+        }
+        self.reader.next()
+    }
+}
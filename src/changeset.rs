@@ -0,0 +1,428 @@
+//! [Changesets](https://wiki.openstreetmap.org/wiki/Changeset), the groups of edits that
+//! element versions belong to
+//!
+//! [Reader] streams changesets from the XML of the API, e.g. `api/0.6/changesets`, or from the
+//! [changeset dumps](https://planet.openstreetmap.org/planet/changesets-latest.osm.bz2).
+//! With the `serde` feature, [Changeset] has the schema of the JSON API and [Document] is the
+//! body of a response.
+
+use std::io::BufRead;
+
+use crate::bbox::Bbox;
+use crate::change::{Action, OsmChange};
+use crate::error::{Error, Result};
+use crate::scalar::{Scalar, ScalarExt};
+use crate::xml::{optional, required, TagKind, Tokenizer, XmlTag};
+use crate::{Element, TagString, Tags, Timestamp};
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Changeset {
+    pub id: i64,
+    /// Time the changeset was opened, which the API always gives
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            with = "crate::json::timestamp"
+        )
+    )]
+    pub created_at: Option<Timestamp>,
+    /// Time the changeset was closed, or will be closed automatically if still open
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            with = "crate::json::timestamp"
+        )
+    )]
+    pub closed_at: Option<Timestamp>,
+    pub open: bool,
+    /// Display name of the user, [None] along with `uid` for anonymous edits
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub user: Option<TagString>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub uid: Option<i32>,
+    /// Extent of the edits, [None] if the changeset is empty
+    #[cfg_attr(feature = "serde", serde(flatten, with = "bounds"))]
+    pub bbox: Option<Bbox>,
+    /// Number of edits
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub changes_count: Option<u32>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub comments_count: u32,
+    /// Tags such as `comment`, `source`, and `created_by`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tags: Tags,
+    /// Discussion, which the API only includes when asked to
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub comments: Vec<Comment>,
+}
+
+impl Changeset {
+    /// Description of the edits given by the user
+    ///
+    /// <https://wiki.openstreetmap.org/wiki/Key:comment>
+    pub fn comment(&self) -> Option<&str> {
+        self.tags.get("comment").map(TagString::as_str)
+    }
+
+    /// Name of the program from `created_by`, without its version, e.g. `JOSM` for
+    /// `JOSM/1.5 (19017 en)` or `iD` for `iD 2.27.3`
+    ///
+    /// <https://wiki.openstreetmap.org/wiki/Key:created_by>
+    pub fn editor(&self) -> Option<&str> {
+        self.tags
+            .get("created_by")
+            .map(|created_by| editor_name(created_by))
+    }
+
+    /// [ChangesetStats] of the edits, with the editor of this changeset
+    pub fn stats(&self, change: &OsmChange) -> ChangesetStats {
+        let mut stats = ChangesetStats::from_change(change);
+        if let Some(editor) = self.editor() {
+            stats.editor = Some(TagString::from_ref(editor));
+        }
+        stats
+    }
+}
+
+/// Part of `created_by` up to a `/` or to a space before the version
+fn editor_name(created_by: &str) -> &str {
+    let end = created_by
+        .char_indices()
+        .find(|&(index, c)| {
+            c == '/'
+                || (c == ' '
+                    && created_by[index + 1..].starts_with(|next: char| next.is_ascii_digit()))
+        })
+        .map_or(created_by.len(), |(index, _)| index);
+    created_by[..end].trim()
+}
+
+/// Counts and extent of the edits in an [OsmChange], like those that review tools such as
+/// [OSMCha](https://osmcha.org) show for a changeset
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChangesetStats {
+    pub num_creates: usize,
+    pub num_modifies: usize,
+    pub num_deletes: usize,
+    /// Box around the created and modified nodes, [None] if there are none
+    ///
+    /// Deleted nodes are left out since deletions need not give coordinates, and ways and
+    /// relations since their nodes may not be part of the change.
+    pub bbox: Option<Bbox>,
+    /// See [Changeset::editor], or else the generator of the [OsmChange]
+    pub editor: Option<TagString>,
+}
+
+impl ChangesetStats {
+    pub fn from_change(change: &OsmChange) -> Self {
+        let mut stats = Self {
+            editor: change
+                .generator
+                .as_deref()
+                .map(|generator| TagString::from_ref(editor_name(generator))),
+            ..Self::default()
+        };
+        for change in &change.changes {
+            match change.action {
+                Action::Create => stats.num_creates += 1,
+                Action::Modify => stats.num_modifies += 1,
+                Action::Delete => {
+                    stats.num_deletes += 1;
+                    continue;
+                }
+            }
+            if let Element::Node(node) = &change.element {
+                match &mut stats.bbox {
+                    Some(bbox) => bbox.extend(node.lat, node.lon),
+                    None => stats.bbox = Some(Bbox::point(node.lat, node.lon)),
+                }
+            }
+        }
+        stats
+    }
+
+    pub fn num_changes(&self) -> usize {
+        self.num_creates + self.num_modifies + self.num_deletes
+    }
+
+    /// [Bbox::area] of the edits in square meters, 0 without a box
+    pub fn bbox_area(&self) -> f64 {
+        self.bbox.as_ref().map_or(0., Bbox::area)
+    }
+}
+
+/// [Comment], named like [crate::note::NoteComment]
+pub type ChangesetComment = Comment;
+
+/// Comment in the discussion of a [Changeset]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Comment {
+    /// Only given by newer versions of the API
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub id: Option<i64>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            with = "crate::json::timestamp"
+        )
+    )]
+    pub date: Option<Timestamp>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub uid: Option<i32>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub user: Option<TagString>,
+    pub text: String,
+}
+
+/// Body of a JSON response, with `changeset` for a single changeset and `changesets` for a
+/// query
+#[cfg(feature = "serde")]
+#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub struct Document {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changeset: Option<Changeset>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changesets: Vec<Changeset>,
+}
+
+#[cfg(feature = "serde")]
+impl Document {
+    pub fn into_changesets(self) -> impl Iterator<Item = Changeset> {
+        self.changeset.into_iter().chain(self.changesets)
+    }
+}
+
+/// Reads the `changeset`s of an XML document
+#[derive(Debug)]
+pub struct Reader<R> {
+    tokenizer: Tokenizer<R>,
+    done: bool,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            tokenizer: Tokenizer::new(input),
+            done: false,
+        }
+    }
+}
+
+impl<R> Reader<R> {
+    pub fn into_inner(self) -> R {
+        self.tokenizer.input
+    }
+}
+
+impl<R: BufRead> Reader<R> {
+    fn read_changeset(&mut self) -> Result<Option<Changeset>> {
+        while let Some(tag) = self.tokenizer.next_tag()? {
+            if tag.name != "changeset" || tag.kind == TagKind::End {
+                continue;
+            }
+            let offset = self.tokenizer.tag_offset;
+            let error = |message: String| Error::decode(Some(offset), message);
+            let mut changeset = start_changeset(&tag).map_err(error)?;
+            if tag.kind == TagKind::Start {
+                self.read_children(&mut changeset)?;
+            }
+            return Ok(Some(changeset));
+        }
+        Ok(None)
+    }
+
+    /// Reads `tag`s and the `discussion` up to the end of the changeset
+    fn read_children(&mut self, changeset: &mut Changeset) -> Result<()> {
+        let mut comment = None;
+        while let Some(tag) = self.tokenizer.next_tag()? {
+            let error = |message: &str| Error::decode(Some(self.tokenizer.tag_offset), message);
+            match (tag.kind, tag.name.as_str()) {
+                (TagKind::End, "changeset") => return Ok(()),
+                (_, "tag") => {
+                    let key = tag
+                        .attribute("k")
+                        .ok_or_else(|| error("missing k attribute"))?;
+                    let value = tag
+                        .attribute("v")
+                        .ok_or_else(|| error("missing v attribute"))?;
+                    changeset
+                        .tags
+                        .insert(TagString::from_ref(key), TagString::from_ref(value));
+                }
+                (TagKind::Start | TagKind::Empty, "comment") => {
+                    comment = Some(start_comment(&tag).map_err(|message| error(&message))?);
+                    if tag.kind == TagKind::Empty {
+                        changeset.comments.extend(comment.take());
+                    }
+                }
+                (TagKind::End, "comment") => changeset.comments.extend(comment.take()),
+                (TagKind::End, "text") => {
+                    if let Some(comment) = &mut comment {
+                        comment.text = self.tokenizer.text()?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err(Error::decode(
+            Some(self.tokenizer.offset),
+            "unexpected end of file",
+        ))
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<Changeset>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let changeset = self.read_changeset().transpose();
+        if !matches!(changeset, Some(Ok(_))) {
+            self.done = true;
+        }
+        changeset
+    }
+}
+
+/// Changeset from the attributes of a `changeset`
+fn start_changeset(tag: &XmlTag) -> std::result::Result<Changeset, String> {
+    let coordinate = |name| {
+        tag.attribute(name)
+            .map(|value| {
+                Scalar::parse_decimal(value).ok_or_else(|| format!("invalid {name} attribute"))
+            })
+            .transpose()
+    };
+    let bbox = match (
+        coordinate("min_lat")?,
+        coordinate("min_lon")?,
+        coordinate("max_lat")?,
+        coordinate("max_lon")?,
+    ) {
+        (Some(min_lat), Some(min_lon), Some(max_lat), Some(max_lon)) => {
+            Some(Bbox::new(min_lat, min_lon, max_lat, max_lon))
+        }
+        _ => None,
+    };
+    Ok(Changeset {
+        id: required(tag, "id")?,
+        created_at: timestamp(tag, "created_at")?,
+        closed_at: timestamp(tag, "closed_at")?,
+        open: optional(tag, "open")?.unwrap_or_default(),
+        user: tag.attribute("user").map(TagString::from_ref),
+        uid: optional(tag, "uid")?,
+        bbox,
+        // Dumps name the count as in the database
+        changes_count: match optional(tag, "changes_count")? {
+            Some(count) => Some(count),
+            None => optional(tag, "num_changes")?,
+        },
+        comments_count: optional(tag, "comments_count")?.unwrap_or_default(),
+        tags: Tags::new(),
+        comments: vec![],
+    })
+}
+
+fn start_comment(tag: &XmlTag) -> std::result::Result<Comment, String> {
+    Ok(Comment {
+        id: optional(tag, "id")?,
+        date: timestamp(tag, "date")?,
+        uid: optional(tag, "uid")?,
+        user: tag.attribute("user").map(TagString::from_ref),
+        text: String::new(),
+    })
+}
+
+fn timestamp(tag: &XmlTag, name: &str) -> std::result::Result<Option<Timestamp>, String> {
+    tag.attribute(name)
+        .map(|value| {
+            crate::parse_timestamp(value).ok_or_else(|| format!("invalid {name} attribute"))
+        })
+        .transpose()
+}
+
+/// Bounds as the `min_lat`, `min_lon`, `max_lat`, and `max_lon` fields of the changeset
+#[cfg(feature = "serde")]
+mod bounds {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Bounds {
+        #[serde(with = "crate::json::coordinate")]
+        min_lat: Scalar,
+        #[serde(with = "crate::json::coordinate")]
+        min_lon: Scalar,
+        #[serde(with = "crate::json::coordinate")]
+        max_lat: Scalar,
+        #[serde(with = "crate::json::coordinate")]
+        max_lon: Scalar,
+    }
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Bbox>,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        value
+            .map(|bbox| Bounds {
+                min_lat: bbox.min_lat,
+                min_lon: bbox.min_lon,
+                max_lat: bbox.max_lat,
+                max_lon: bbox.max_lon,
+            })
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Option<Bbox>, D::Error> {
+        Ok(Option::<Bounds>::deserialize(deserializer)?.map(|bounds| {
+            Bbox::new(
+                bounds.min_lat,
+                bounds.min_lon,
+                bounds.max_lat,
+                bounds.max_lon,
+            )
+        }))
+    }
+}
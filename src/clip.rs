@@ -0,0 +1,241 @@
+//! Regional extracts of an [ElementStore], with the strategies of
+//! [osmium extract](https://docs.osmcode.org/osmium/latest/osmium-extract.html)
+//!
+//! Elements are never cut: a way that leaves the region is either kept whole, with or without
+//! the nodes outside, or left out. The [Strategy] decides which references may dangle in the
+//! extract, so that consumers know what they can resolve.
+
+use fnv::FnvHashSet as HashSet;
+
+use crate::bbox::Bbox;
+use crate::geom::ring_contains;
+use crate::multipolygon::MultiPolygon;
+use crate::scalar::{Scalar, ScalarExt};
+use crate::store::ElementStore;
+use crate::{Element, Id, MemberType, Relation};
+
+/// Region to extract
+#[derive(Debug, PartialEq, Clone)]
+pub enum ClipRegion {
+    Bbox(Bbox),
+    /// Polygons with holes, which must not cross the antimeridian
+    Polygon(MultiPolygon),
+}
+
+/// Which elements an extract keeps besides the nodes inside the region
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Strategy {
+    /// Ways with a node inside, without their nodes outside, and relations with a node or way
+    /// member in the extract
+    ///
+    /// Ways and relations keep all their references, so references to elements outside
+    /// dangle.
+    Simple,
+    /// Like [Strategy::Simple], but with all nodes of the ways kept, and with the relations
+    /// that have a relation in the extract as a member
+    #[default]
+    CompleteWays,
+    /// Like [Strategy::CompleteWays], but with all member ways of multipolygons in the extract
+    /// and their nodes, so that the polygons can be assembled
+    Smart,
+}
+
+/// [ClipRegion] with its coordinates ready for point tests, see [ClipRegion::prepare]
+#[derive(Debug, Clone)]
+pub struct PreparedRegion<'a>(Prepared<'a>);
+
+#[derive(Debug, Clone)]
+enum Prepared<'a> {
+    Bbox(&'a Bbox),
+    Polygon {
+        /// Rings of each polygon, outer ring first
+        polygons: Vec<Vec<Vec<(f64, f64)>>>,
+        /// Minimum and maximum latitude and longitude
+        bounds: [f64; 4],
+    },
+}
+
+impl PreparedRegion<'_> {
+    /// Whether the coordinate lies inside the region, or on the edge of a box
+    pub fn contains(&self, lat: Scalar, lon: Scalar) -> bool {
+        let (polygons, bounds) = match &self.0 {
+            Prepared::Bbox(bbox) => return bbox.contains(lat, lon),
+            Prepared::Polygon { polygons, bounds } => (polygons, bounds),
+        };
+        let (lat, lon) = (lat.as_f64(), lon.as_f64());
+        let [min_lat, min_lon, max_lat, max_lon] = *bounds;
+        if lat < min_lat || lat > max_lat || lon < min_lon || lon > max_lon {
+            return false;
+        }
+        polygons.iter().any(|rings| {
+            ring_contains(&rings[0], lat, lon)
+                && !rings[1..].iter().any(|ring| ring_contains(ring, lat, lon))
+        })
+    }
+}
+
+impl ClipRegion {
+    /// Region converted for testing many points, which [ClipRegion::contains] does on each call
+    pub fn prepare(&self) -> PreparedRegion<'_> {
+        PreparedRegion(match self {
+            ClipRegion::Bbox(bbox) => Prepared::Bbox(bbox),
+            ClipRegion::Polygon(multipolygon) => {
+                let mut bounds = [f64::MAX, f64::MAX, f64::MIN, f64::MIN];
+                let polygons = multipolygon
+                    .to_rings()
+                    .into_iter()
+                    .map(|rings| {
+                        rings
+                            .into_iter()
+                            .filter(|ring| !ring.is_empty())
+                            .map(|ring| {
+                                ring.into_iter()
+                                    .map(|(lat, lon)| {
+                                        let (lat, lon) = (lat.as_f64(), lon.as_f64());
+                                        bounds = [
+                                            bounds[0].min(lat),
+                                            bounds[1].min(lon),
+                                            bounds[2].max(lat),
+                                            bounds[3].max(lon),
+                                        ];
+                                        (lat, lon)
+                                    })
+                                    .collect()
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .filter(|rings| !rings.is_empty())
+                    .collect();
+                Prepared::Polygon { polygons, bounds }
+            }
+        })
+    }
+
+    /// Whether the coordinate lies inside the region, or on the edge of a box
+    ///
+    /// Polygons are converted on each call, so [ClipRegion::prepare] them to test many points.
+    pub fn contains(&self, lat: Scalar, lon: Scalar) -> bool {
+        self.prepare().contains(lat, lon)
+    }
+}
+
+/// Elements of `store` in `region`, chosen by `strategy`
+pub fn extract(store: &ElementStore, region: &ClipRegion, strategy: Strategy) -> ElementStore {
+    let region = region.prepare();
+    let inside: HashSet<Id> = store
+        .nodes()
+        .filter(|node| region.contains(node.lat, node.lon))
+        .map(|node| node.id)
+        .collect();
+    let mut nodes = inside.clone();
+    let mut ways: HashSet<Id> = HashSet::default();
+    for way in store.ways() {
+        if way.refs.iter().any(|id| inside.contains(id)) {
+            ways.insert(way.id);
+            if strategy != Strategy::Simple {
+                nodes.extend(way.refs.iter().copied());
+            }
+        }
+    }
+
+    let has_member = |relation: &Relation, ty: MemberType, ids: &HashSet<Id>| {
+        relation
+            .members
+            .iter()
+            .any(|member| member.ty == ty && ids.contains(&member.id))
+    };
+    let mut relations: HashSet<Id> = store
+        .relations()
+        .filter(|relation| {
+            has_member(relation, MemberType::Node, &nodes)
+                || has_member(relation, MemberType::Way, &ways)
+        })
+        .map(|relation| relation.id)
+        .collect();
+    if strategy != Strategy::Simple {
+        // Parents of relations in the extract, at any depth
+        loop {
+            let parents: Vec<Id> = store
+                .relations()
+                .filter(|relation| {
+                    !relations.contains(&relation.id)
+                        && has_member(relation, MemberType::Relation, &relations)
+                })
+                .map(|relation| relation.id)
+                .collect();
+            if parents.is_empty() {
+                break;
+            }
+            relations.extend(parents);
+        }
+    }
+    if strategy == Strategy::Smart {
+        for relation in relations.iter().filter_map(|&id| store.get_relation(id)) {
+            if !relation.is_multipolygon() {
+                continue;
+            }
+            for member in &relation.members {
+                match member.ty {
+                    MemberType::Node => {
+                        nodes.insert(member.id);
+                    }
+                    MemberType::Way => {
+                        if let Some(way) = store.get_way(member.id) {
+                            ways.insert(way.id);
+                            nodes.extend(way.refs.iter().copied());
+                        }
+                    }
+                    MemberType::Relation => {}
+                }
+            }
+        }
+    }
+
+    let mut extract = ElementStore::new();
+    let nodes = nodes.into_iter().filter_map(|id| store.get_node(id));
+    extract.extend(nodes.cloned().map(Element::Node));
+    let ways = ways.into_iter().filter_map(|id| store.get_way(id));
+    extract.extend(ways.cloned().map(Element::Way));
+    let relations = relations
+        .into_iter()
+        .filter_map(|id| store.get_relation(id));
+    extract.extend(relations.cloned().map(Element::Relation));
+    extract
+}
+
+impl ElementStore {
+    /// See [extract]
+    pub fn extract(&self, region: &ClipRegion, strategy: Strategy) -> ElementStore {
+        extract(self, region, strategy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geom::LatLon;
+    use crate::multipolygon::Polygon;
+
+    fn square(min: i64, max: i64) -> Vec<LatLon> {
+        [(min, min), (min, max), (max, max), (max, min), (min, min)]
+            .map(|(lat, lon)| (Scalar::from_int(lat), Scalar::from_int(lon)))
+            .to_vec()
+    }
+
+    #[test]
+    fn prepared_polygon_contains() {
+        let region = ClipRegion::Polygon(MultiPolygon {
+            polygons: vec![Polygon {
+                outer: square(0, 10),
+                inners: vec![square(4, 6)],
+            }],
+        });
+        let prepared = region.prepare();
+        for (lat, lon, inside) in [(2, 2, true), (5, 5, false), (11, 5, false), (-1, 5, false)] {
+            let (lat, lon) = (Scalar::from_int(lat), Scalar::from_int(lon));
+            assert_eq!(prepared.contains(lat, lon), inside);
+            assert_eq!(region.contains(lat, lon), inside);
+        }
+    }
+}
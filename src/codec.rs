@@ -0,0 +1,932 @@
+//! Compact binary cache of sorted elements
+//!
+//! Tools that preprocess OSM data, e.g. filtering a planet file once and reading the result
+//! many times, need an intermediate format that is small, fast to decode, and can be opened
+//! at any element. [Writer] takes elements sorted by type and id, as in planet files, and
+//! delta codes them in blocks of [DEFAULT_BLOCK_ELEMENTS]:
+//!
+//! - each block starts with a table of the strings of its tags, roles, and users, which
+//!   elements refer to by index
+//! - ids, node refs, and member ids are differences to the previous one in the block
+//! - coordinates are differences in nanodegrees, and versions, timestamps in whole seconds,
+//!   changesets, and uids are differences to those of the previous element with them
+//!
+//! Blocks are independent, so [Cache] finds an element by reading a single block through
+//! the index at the end of the file, while [Reader] streams all of them in order.
+//!
+//! A file is the magic bytes `OSMCACHE` and the [FORMAT_VERSION] byte, then each block as a
+//! varint length and its data, a zero length, the index, and finally the offset of the index
+//! as a little-endian `u64` followed by `OSMI`. Numbers are varints as in protocol buffers,
+//! signed ones zigzag coded. Changes to the layout increment [FORMAT_VERSION].
+
+use std::io::{BufRead, Read, Seek, SeekFrom, Write};
+
+use chrono::DateTime;
+use fnv::FnvHashMap as HashMap;
+
+use crate::coordinate::Coordinate;
+use crate::error::{ElementContext, Error, Result};
+use crate::id::ElementId;
+use crate::pipeline::Sink;
+use crate::{Element, Id, Info, Member, MemberType, Node, Relation, TagString, Tags, Way};
+
+/// Version of the layout, which readers check
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Elements in one block unless set with [Writer::block_elements]
+pub const DEFAULT_BLOCK_ELEMENTS: usize = 8000;
+
+const MAGIC: &[u8; 8] = b"OSMCACHE";
+const INDEX_MAGIC: &[u8; 4] = b"OSMI";
+/// Longest block that is read
+const MAX_BLOCK_SIZE: u64 = 256 * 1024 * 1024;
+
+const HAS_INFO: u8 = 1;
+const HAS_TIMESTAMP: u8 = 1 << 1;
+const HAS_CHANGESET: u8 = 1 << 2;
+const HAS_UID: u8 = 1 << 3;
+const HAS_USER: u8 = 1 << 4;
+const HAS_VISIBLE: u8 = 1 << 5;
+const VISIBLE: u8 = 1 << 6;
+
+fn type_code(ty: &MemberType) -> u8 {
+    match ty {
+        MemberType::Node => 0,
+        MemberType::Way => 1,
+        MemberType::Relation => 2,
+    }
+}
+
+fn member_type(code: u8) -> Option<MemberType> {
+    match code {
+        0 => Some(MemberType::Node),
+        1 => Some(MemberType::Way),
+        2 => Some(MemberType::Relation),
+        _ => None,
+    }
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn put_signed(buf: &mut Vec<u8>, value: i64) {
+    put_varint(buf, ((value << 1) ^ (value >> 63)) as u64);
+}
+
+/// Values that elements of a block are coded relative to
+#[derive(Debug, Default)]
+struct Deltas {
+    id: i64,
+    version: i64,
+    timestamp: i64,
+    changeset: i64,
+    uid: i64,
+    lat: i64,
+    lon: i64,
+    /// Node, way, and relation references
+    refs: [i64; 3],
+}
+
+impl Deltas {
+    fn delta(previous: &mut i64, value: i64) -> i64 {
+        let delta = value.wrapping_sub(*previous);
+        *previous = value;
+        delta
+    }
+
+    fn undelta(previous: &mut i64, delta: i64) -> i64 {
+        *previous = previous.wrapping_add(delta);
+        *previous
+    }
+}
+
+/// Block being encoded
+#[derive(Debug, Default)]
+struct BlockBuilder {
+    strings: HashMap<TagString, u64>,
+    table: Vec<TagString>,
+    data: Vec<u8>,
+    deltas: Deltas,
+    len: usize,
+    first: Option<ElementId>,
+}
+
+impl BlockBuilder {
+    fn string(&mut self, s: &TagString) -> u64 {
+        if let Some(&index) = self.strings.get(s) {
+            return index;
+        }
+        let index = self.table.len() as u64;
+        self.strings.insert(s.clone(), index);
+        self.table.push(s.clone());
+        index
+    }
+
+    fn add(&mut self, element: &Element) -> std::result::Result<(), &'static str> {
+        let coordinate = match element {
+            Element::Node(node) => Some(
+                node.coordinate()
+                    .ok_or("coordinate is out of range or finer than a nanodegree")?,
+            ),
+            _ => None,
+        };
+        self.first.get_or_insert_with(|| element.element_id());
+        self.data.push(type_code(&element.member_type()));
+        let id = Deltas::delta(&mut self.deltas.id, element.id().0);
+        put_signed(&mut self.data, id);
+        self.info(element.info());
+        self.tags(element.tags());
+        match element {
+            Element::Node(_) => {
+                let coordinate = coordinate.unwrap_or_default();
+                let lat = Deltas::delta(&mut self.deltas.lat, coordinate.lat);
+                let lon = Deltas::delta(&mut self.deltas.lon, coordinate.lon);
+                put_signed(&mut self.data, lat);
+                put_signed(&mut self.data, lon);
+            }
+            Element::Way(way) => {
+                put_varint(&mut self.data, way.refs.len() as u64);
+                for id in &way.refs {
+                    let delta = Deltas::delta(&mut self.deltas.refs[0], id.0);
+                    put_signed(&mut self.data, delta);
+                }
+            }
+            Element::Relation(relation) => {
+                put_varint(&mut self.data, relation.members.len() as u64);
+                for member in &relation.members {
+                    let code = type_code(&member.ty);
+                    self.data.push(code);
+                    let delta = Deltas::delta(&mut self.deltas.refs[code as usize], member.id.0);
+                    put_signed(&mut self.data, delta);
+                    let role = member.role.as_ref().map_or(0, |role| self.string(role) + 1);
+                    put_varint(&mut self.data, role);
+                }
+            }
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    fn info(&mut self, info: Option<&Info>) {
+        let Some(info) = info else {
+            self.data.push(0);
+            return;
+        };
+        let mut flags = HAS_INFO;
+        for (present, flag) in [
+            (info.timestamp.is_some(), HAS_TIMESTAMP),
+            (info.changeset.is_some(), HAS_CHANGESET),
+            (info.uid.is_some(), HAS_UID),
+            (info.user.is_some(), HAS_USER),
+            (info.visible.is_some(), HAS_VISIBLE),
+            (info.visible == Some(true), VISIBLE),
+        ] {
+            if present {
+                flags |= flag;
+            }
+        }
+        self.data.push(flags);
+        let version = Deltas::delta(&mut self.deltas.version, info.version.into());
+        put_signed(&mut self.data, version);
+        if let Some(timestamp) = info.timestamp {
+            let seconds = crate::timestamp_to_utc(timestamp).timestamp();
+            let delta = Deltas::delta(&mut self.deltas.timestamp, seconds);
+            put_signed(&mut self.data, delta);
+        }
+        if let Some(changeset) = info.changeset {
+            let delta = Deltas::delta(&mut self.deltas.changeset, changeset);
+            put_signed(&mut self.data, delta);
+        }
+        if let Some(uid) = info.uid {
+            let delta = Deltas::delta(&mut self.deltas.uid, uid.into());
+            put_signed(&mut self.data, delta);
+        }
+        if let Some(user) = &info.user {
+            let index = self.string(user);
+            put_varint(&mut self.data, index);
+        }
+    }
+
+    fn tags(&mut self, tags: &Tags) {
+        put_varint(&mut self.data, tags.len() as u64);
+        for (key, value) in tags.iter_sorted() {
+            let key = self.string(key);
+            let value = self.string(value);
+            put_varint(&mut self.data, key);
+            put_varint(&mut self.data, value);
+        }
+    }
+
+    /// String table, element count, and elements
+    fn encode(self) -> Vec<u8> {
+        let mut block = Vec::with_capacity(self.data.len() + self.table.len() * 8 + 16);
+        put_varint(&mut block, self.table.len() as u64);
+        for s in &self.table {
+            put_varint(&mut block, s.len() as u64);
+            block.extend_from_slice(s.as_bytes());
+        }
+        put_varint(&mut block, self.len as u64);
+        block.extend_from_slice(&self.data);
+        block
+    }
+}
+
+/// Block in the index of a file
+#[derive(Debug, Clone, Copy)]
+struct BlockEntry {
+    first: ElementId,
+    /// Position of the length of the block
+    offset: u64,
+    elements: u64,
+}
+
+/// Writer of [Element]s in the format of this module
+///
+/// Elements must come sorted by type (nodes, ways, then relations) and then by id, e.g.
+/// from [ElementStore::iter_sorted](crate::store::ElementStore::iter_sorted). Versions of the
+/// same element may follow each other, as in history files.
+#[derive(Debug)]
+pub struct Writer<W> {
+    output: W,
+    offset: u64,
+    block_elements: usize,
+    block: BlockBuilder,
+    index: Vec<BlockEntry>,
+    last: Option<ElementId>,
+    started: bool,
+    finished: bool,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(output: W) -> Self {
+        Self {
+            output,
+            offset: 0,
+            block_elements: DEFAULT_BLOCK_ELEMENTS,
+            block: BlockBuilder::default(),
+            index: vec![],
+            last: None,
+            started: false,
+            finished: false,
+        }
+    }
+
+    /// Most elements in one block, at least 1
+    ///
+    /// Smaller blocks make [Cache::get] faster and the file larger.
+    pub fn block_elements(mut self, block_elements: usize) -> Self {
+        self.block_elements = block_elements.max(1);
+        self
+    }
+
+    pub fn write(&mut self, element: &Element) -> Result<()> {
+        self.start()?;
+        let id = element.element_id();
+        let error = |message: &str| Error::Validation {
+            element: Some(ElementContext::from(id)),
+            message: message.to_string(),
+        };
+        if self.last.is_some_and(|last| id < last) {
+            return Err(error("elements are not sorted by type and id"));
+        }
+        self.block.add(element).map_err(error)?;
+        self.last = Some(id);
+        if self.block.len >= self.block_elements {
+            self.flush_block()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the last block and the index
+    pub fn finish(&mut self) -> Result<()> {
+        self.start()?;
+        self.flush_block()?;
+        let mut index = vec![0];
+        put_varint(&mut index, self.index.len() as u64);
+        let (mut id, mut offset) = (0, 0);
+        for entry in &self.index {
+            index.push(type_code(&entry.first.member_type()));
+            put_signed(&mut index, Deltas::delta(&mut id, entry.first.id().0));
+            put_varint(&mut index, entry.offset - offset);
+            put_varint(&mut index, entry.elements);
+            offset = entry.offset;
+        }
+        // The index starts after the zero length ending the blocks
+        let index_offset = self.offset + 1;
+        index.extend_from_slice(&index_offset.to_le_bytes());
+        index.extend_from_slice(INDEX_MAGIC);
+        self.output.write_all(&index)?;
+        self.output.flush()?;
+        self.offset += index.len() as u64;
+        self.finished = true;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    fn start(&mut self) -> Result<()> {
+        if self.finished {
+            return Err(Error::Validation {
+                element: None,
+                message: "writer is already finished".to_string(),
+            });
+        }
+        if !self.started {
+            self.output.write_all(MAGIC)?;
+            self.output.write_all(&[FORMAT_VERSION])?;
+            self.offset = MAGIC.len() as u64 + 1;
+            self.started = true;
+        }
+        Ok(())
+    }
+
+    fn flush_block(&mut self) -> Result<()> {
+        let Some(first) = self.block.first else {
+            return Ok(());
+        };
+        let elements = self.block.len as u64;
+        let block = std::mem::take(&mut self.block).encode();
+        let mut len = vec![];
+        put_varint(&mut len, block.len() as u64);
+        self.output.write_all(&len)?;
+        self.output.write_all(&block)?;
+        self.index.push(BlockEntry {
+            first,
+            offset: self.offset,
+            elements,
+        });
+        self.offset += (len.len() + block.len()) as u64;
+        Ok(())
+    }
+}
+
+impl<W: Write> Sink for Writer<W> {
+    type Error = Error;
+
+    fn write(&mut self, element: Element) -> Result<()> {
+        Writer::write(self, &element)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        Writer::finish(self)
+    }
+}
+
+/// Data of a block or of the index being decoded
+struct Decoder<'a> {
+    data: &'a [u8],
+    /// Position of the data in the file
+    offset: u64,
+}
+
+impl Decoder<'_> {
+    fn error(&self, message: &str) -> Error {
+        Error::decode(Some(self.offset), message)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        let (&byte, rest) = self
+            .data
+            .split_first()
+            .ok_or_else(|| self.error("unexpected end of block"))?;
+        self.data = rest;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(self.error("varint is too long"))
+    }
+
+    fn signed(&mut self) -> Result<i64> {
+        let value = self.varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    /// Length of a sequence, which each item takes at least a byte of
+    fn len(&mut self) -> Result<usize> {
+        let len = self.varint()?;
+        if len > self.data.len() as u64 {
+            return Err(self.error("sequence is longer than the block"));
+        }
+        Ok(len as usize)
+    }
+
+    fn member_type(&mut self) -> Result<MemberType> {
+        let code = self.byte()?;
+        member_type(code).ok_or_else(|| self.error("invalid element type"))
+    }
+}
+
+/// Decodes the elements of a block that starts at `offset`
+fn decode_block(data: &[u8], offset: u64) -> Result<Vec<Element>> {
+    let mut decoder = Decoder { data, offset };
+    let mut table = Vec::with_capacity(decoder.len()?);
+    for _ in 0..table.capacity() {
+        let len = decoder.len()?;
+        let (s, rest) = decoder.data.split_at(len);
+        let s = std::str::from_utf8(s).map_err(|_| decoder.error("string is not UTF-8"))?;
+        table.push(TagString::from_ref(s));
+        decoder.data = rest;
+        decoder.offset += len as u64;
+    }
+    let string = |decoder: &Decoder, index: u64| {
+        table
+            .get(index as usize)
+            .cloned()
+            .ok_or_else(|| decoder.error("string index is out of range"))
+    };
+
+    let mut deltas = Deltas::default();
+    let mut elements = Vec::with_capacity(decoder.len()?);
+    for _ in 0..elements.capacity() {
+        let ty = decoder.member_type()?;
+        let id = Id(Deltas::undelta(&mut deltas.id, decoder.signed()?));
+
+        let flags = decoder.byte()?;
+        let info = if flags & HAS_INFO != 0 {
+            let version = Deltas::undelta(&mut deltas.version, decoder.signed()?);
+            let mut info = Info {
+                version: version
+                    .try_into()
+                    .map_err(|_| decoder.error("version is out of range"))?,
+                timestamp: None,
+                changeset: None,
+                uid: None,
+                user: None,
+                visible: (flags & HAS_VISIBLE != 0).then_some(flags & VISIBLE != 0),
+            };
+            if flags & HAS_TIMESTAMP != 0 {
+                let seconds = Deltas::undelta(&mut deltas.timestamp, decoder.signed()?);
+                info.timestamp =
+                    DateTime::from_timestamp(seconds, 0).map(crate::timestamp_from_utc);
+            }
+            if flags & HAS_CHANGESET != 0 {
+                info.changeset = Some(Deltas::undelta(&mut deltas.changeset, decoder.signed()?));
+            }
+            if flags & HAS_UID != 0 {
+                let uid = Deltas::undelta(&mut deltas.uid, decoder.signed()?);
+                info.uid = Some(
+                    uid.try_into()
+                        .map_err(|_| decoder.error("uid is out of range"))?,
+                );
+            }
+            if flags & HAS_USER != 0 {
+                let index = decoder.varint()?;
+                info.user = Some(string(&decoder, index)?);
+            }
+            Some(info)
+        } else {
+            None
+        };
+
+        let len = decoder.len()?;
+        let mut tags = crate::tags::TagMap::with_capacity_and_hasher(len, Default::default());
+        for _ in 0..len {
+            let (key, value) = (decoder.varint()?, decoder.varint()?);
+            tags.insert(string(&decoder, key)?, string(&decoder, value)?);
+        }
+        let tags = Tags::from(tags);
+
+        elements.push(match ty {
+            MemberType::Node => {
+                let lat = Deltas::undelta(&mut deltas.lat, decoder.signed()?);
+                let lon = Deltas::undelta(&mut deltas.lon, decoder.signed()?);
+                let (lat, lon) = Coordinate::new(lat, lon).lat_lon();
+                Element::Node(Node {
+                    id,
+                    tags,
+                    info,
+                    lat,
+                    lon,
+                })
+            }
+            MemberType::Way => {
+                let len = decoder.len()?;
+                let mut refs = Vec::with_capacity(len);
+                for _ in 0..len {
+                    refs.push(Id(Deltas::undelta(&mut deltas.refs[0], decoder.signed()?)));
+                }
+                Element::Way(Way {
+                    id,
+                    tags,
+                    info,
+                    refs: refs.into_iter().collect(),
+                })
+            }
+            MemberType::Relation => {
+                let len = decoder.len()?;
+                let mut members = Vec::with_capacity(len);
+                for _ in 0..len {
+                    let ty = decoder.member_type()?;
+                    let refs = &mut deltas.refs[type_code(&ty) as usize];
+                    let id = Id(Deltas::undelta(refs, decoder.signed()?));
+                    let role = match decoder.varint()? {
+                        0 => None,
+                        index => Some(string(&decoder, index - 1)?),
+                    };
+                    members.push(Member { id, ty, role });
+                }
+                Element::Relation(Relation {
+                    id,
+                    tags,
+                    info,
+                    members: members.into_iter().collect(),
+                })
+            }
+        });
+    }
+    Ok(elements)
+}
+
+fn read_header(input: &mut impl Read) -> Result<()> {
+    let mut header = [0; MAGIC.len() + 1];
+    input
+        .read_exact(&mut header)
+        .map_err(|_| Error::decode(Some(0), "file is too short"))?;
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(Error::decode(Some(0), "not an element cache"));
+    }
+    if header[MAGIC.len()] != FORMAT_VERSION {
+        return Err(Error::decode(
+            Some(MAGIC.len() as u64),
+            "unsupported cache version",
+        ));
+    }
+    Ok(())
+}
+
+/// Reads the length and the data of the block at `offset`, [None] at the end of the blocks
+fn read_block(input: &mut impl Read, offset: u64, buf: &mut Vec<u8>) -> Result<Option<u64>> {
+    let mut len = 0u64;
+    let mut header = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        input
+            .read_exact(&mut byte)
+            .map_err(|_| Error::decode(Some(offset), "unexpected end of file"))?;
+        header += 1;
+        len |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+    }
+    if len == 0 {
+        return Ok(None);
+    }
+    if len > MAX_BLOCK_SIZE {
+        return Err(Error::decode(Some(offset), "block is too large"));
+    }
+    buf.resize(len as usize, 0);
+    input
+        .read_exact(buf)
+        .map_err(|_| Error::decode(Some(offset), "unexpected end of file"))?;
+    Ok(Some(header))
+}
+
+/// Streaming reader of all [Element]s of a file, in order
+#[derive(Debug)]
+pub struct Reader<R> {
+    input: R,
+    offset: u64,
+    buf: Vec<u8>,
+    elements: std::vec::IntoIter<Element>,
+    started: bool,
+    done: bool,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            input,
+            offset: 0,
+            buf: vec![],
+            elements: vec![].into_iter(),
+            started: false,
+            done: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.input
+    }
+
+    /// Decodes the next block, returning false after the last one
+    fn read_next_block(&mut self) -> Result<bool> {
+        if !self.started {
+            read_header(&mut self.input)?;
+            self.offset = MAGIC.len() as u64 + 1;
+            self.started = true;
+        }
+        let Some(header) = read_block(&mut self.input, self.offset, &mut self.buf)? else {
+            return Ok(false);
+        };
+        self.elements = decode_block(&self.buf, self.offset + header)?.into_iter();
+        self.offset += header + self.buf.len() as u64;
+        Ok(true)
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<Element>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(element) = self.elements.next() {
+                return Some(Ok(element));
+            }
+            if self.done {
+                return None;
+            }
+            match self.read_next_block() {
+                Ok(true) => {}
+                Ok(false) => self.done = true,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// File opened through its index, which reads single blocks on demand
+#[derive(Debug)]
+pub struct Cache<R> {
+    input: R,
+    index: Vec<BlockEntry>,
+    buf: Vec<u8>,
+}
+
+impl<R: Read + Seek> Cache<R> {
+    /// Checks the header and reads the index, which [Writer::finish] writes at the end
+    pub fn open(mut input: R) -> Result<Self> {
+        input.seek(SeekFrom::Start(0))?;
+        read_header(&mut input)?;
+        let end = input.seek(SeekFrom::End(-12))?;
+        let mut trailer = [0; 12];
+        input.read_exact(&mut trailer)?;
+        if &trailer[8..] != INDEX_MAGIC {
+            return Err(Error::decode(Some(end), "index is missing"));
+        }
+        let offset = u64::from_le_bytes(trailer[..8].try_into().expect("8 bytes"));
+        if offset > end {
+            return Err(Error::decode(Some(end), "index offset is out of range"));
+        }
+        input.seek(SeekFrom::Start(offset))?;
+        let mut data = vec![0; (end - offset) as usize];
+        input.read_exact(&mut data)?;
+
+        let mut decoder = Decoder {
+            data: &data,
+            offset,
+        };
+        let mut index = Vec::with_capacity(decoder.len()?);
+        let (mut previous, mut block_offset) = (0, 0u64);
+        for _ in 0..index.capacity() {
+            let ty = decoder.member_type()?;
+            let id = Id(Deltas::undelta(&mut previous, decoder.signed()?));
+            block_offset += decoder.varint()?;
+            index.push(BlockEntry {
+                first: ElementId::new(ty, id),
+                offset: block_offset,
+                elements: decoder.varint()?,
+            });
+        }
+        Ok(Self {
+            input,
+            index,
+            buf: vec![],
+        })
+    }
+
+    /// Number of elements in the file
+    pub fn len(&self) -> u64 {
+        self.index.iter().map(|entry| entry.elements).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Elements of the block at `index` in the file, in order
+    pub fn read_block(&mut self, index: usize) -> Result<Vec<Element>> {
+        let Some(entry) = self.index.get(index) else {
+            return Err(Error::Validation {
+                element: None,
+                message: format!("block {index} is out of range"),
+            });
+        };
+        self.input.seek(SeekFrom::Start(entry.offset))?;
+        let header = read_block(&mut self.input, entry.offset, &mut self.buf)?
+            .ok_or_else(|| Error::decode(Some(entry.offset), "block is missing"))?;
+        decode_block(&self.buf, entry.offset + header)
+    }
+
+    /// First version of the element in the file, reading the one or two blocks that may
+    /// contain it
+    pub fn get(&mut self, id: ElementId) -> Result<Option<Element>> {
+        let start = self
+            .index
+            .partition_point(|entry| entry.first < id)
+            .saturating_sub(1);
+        let mut block = start;
+        while self.index.get(block).is_some_and(|entry| entry.first <= id) {
+            let found = self
+                .read_block(block)?
+                .into_iter()
+                .find(|element| element.element_id() == id);
+            if found.is_some() {
+                return Ok(found);
+            }
+            block += 1;
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::scalar::{Scalar, ScalarExt};
+    use crate::{NodeId, RelationId, WayId};
+
+    fn info(version: i32, visible: Option<bool>) -> Info {
+        Info {
+            version,
+            timestamp: DateTime::from_timestamp(1_600_000_000 + i64::from(version) * 60, 0)
+                .map(crate::timestamp_from_utc),
+            changeset: Some(1000 + i64::from(version)),
+            uid: (version % 2 == 0).then_some(42),
+            user: (version % 2 == 0).then(|| "mapper".into()),
+            visible,
+        }
+    }
+
+    fn node(id: i64, version: i32) -> Element {
+        Element::Node(Node {
+            id: Id(id),
+            tags: [("name", format!("node {id}"))]
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+            info: Some(info(version, None)),
+            lat: Scalar::with_scale(515_000_000 + id * 1234, 7),
+            lon: Scalar::with_scale(-1_250_000 - id * 987, 7),
+        })
+    }
+
+    /// Nodes with a history, a deleted way, and relations, sorted by type and id
+    fn elements() -> Vec<Element> {
+        let mut elements = vec![node(-3, 1)];
+        for id in 1..=5 {
+            elements.push(node(id, 1));
+        }
+        elements.push(node(5, 2));
+        let mut way = Way::builder(Id(10))
+            .nodes([1, 2, 3, 1].map(Id))
+            .tag("building", "yes")
+            .build()
+            .unwrap();
+        way.info = Some(info(1, Some(true)));
+        elements.push(Element::Way(way));
+        elements.push(Element::Way(Way {
+            id: Id(11),
+            tags: Tags::new(),
+            info: Some(info(3, Some(false))),
+            refs: Default::default(),
+        }));
+        let relation = Relation::builder(Id(20))
+            .member(WayId(10), "outer")
+            .member(NodeId(-3), "")
+            .member(RelationId(21), "subarea")
+            .tag("type", "multipolygon")
+            .build()
+            .unwrap();
+        elements.push(Element::Relation(relation));
+        elements.push(Element::Relation(
+            Relation::builder(Id(21)).build().unwrap(),
+        ));
+        elements
+    }
+
+    fn write(elements: &[Element], block_elements: usize) -> Vec<u8> {
+        let mut writer = Writer::new(vec![]).block_elements(block_elements);
+        for element in elements {
+            writer.write(element).unwrap();
+        }
+        writer.finish().unwrap();
+        writer.into_inner()
+    }
+
+    #[test]
+    fn round_trip() {
+        let elements = elements();
+        for block_elements in [1, 3, DEFAULT_BLOCK_ELEMENTS] {
+            let data = write(&elements, block_elements);
+            let read: Vec<_> = Reader::new(&data[..]).map(Result::unwrap).collect();
+            assert_eq!(read, elements, "{block_elements} elements per block");
+
+            let mut cache = Cache::open(Cursor::new(&data)).unwrap();
+            assert_eq!(cache.len(), elements.len() as u64);
+            assert_eq!(cache.block_count(), elements.len().div_ceil(block_elements));
+            let all: Vec<_> = (0..cache.block_count())
+                .flat_map(|index| cache.read_block(index).unwrap())
+                .collect();
+            assert_eq!(all, elements);
+        }
+    }
+
+    #[test]
+    fn cache_get() {
+        let elements = elements();
+        // Versions of node 5 end up in different blocks
+        let data = write(&elements, 3);
+        let mut cache = Cache::open(Cursor::new(data)).unwrap();
+        for element in &elements {
+            let found = cache.get(element.element_id()).unwrap().unwrap();
+            assert_eq!(found.element_id(), element.element_id());
+        }
+        let found = cache.get(NodeId(5).into()).unwrap().unwrap();
+        assert_eq!(found.info().unwrap().version, 1);
+        assert_eq!(cache.get(NodeId(4).into()).unwrap(), Some(node(4, 1)));
+        assert_eq!(cache.get(NodeId(6).into()).unwrap(), None);
+        assert_eq!(cache.get(WayId(1).into()).unwrap(), None);
+        assert_eq!(cache.get(RelationId(22).into()).unwrap(), None);
+        assert!(cache.read_block(cache.block_count()).is_err());
+    }
+
+    #[test]
+    fn empty_file() {
+        let data = write(&[], 10);
+        assert_eq!(Reader::new(&data[..]).count(), 0);
+        let cache = Cache::open(Cursor::new(data)).unwrap();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn unsorted_elements() {
+        let mut writer = Writer::new(vec![]);
+        writer.write(&node(2, 1)).unwrap();
+        assert!(matches!(
+            writer.write(&node(1, 1)),
+            Err(Error::Validation { .. })
+        ));
+        writer.finish().unwrap();
+        assert!(writer.write(&node(3, 1)).is_err());
+    }
+
+    #[test]
+    fn corrupt_files() {
+        let data = write(&elements(), 3);
+        let decode_error = |data: &[u8]| match Reader::new(data).find_map(Result::err) {
+            Some(Error::Decode { message, .. }) => message,
+            other => panic!("expected a decode error, got {other:?}"),
+        };
+        assert_eq!(decode_error(b"OSMCACHE"), "file is too short");
+        assert_eq!(decode_error(b"NOTACACHE"), "not an element cache");
+        let mut version = data.clone();
+        version[MAGIC.len()] = FORMAT_VERSION + 1;
+        assert_eq!(decode_error(&version), "unsupported cache version");
+
+        // Emptying the string table of the first block leaves the rest of it undecodable
+        let mut decoder = Decoder {
+            data: &data[MAGIC.len() + 1..],
+            offset: 0,
+        };
+        decoder.varint().unwrap();
+        let mut strings = data.clone();
+        strings[MAGIC.len() + 1 + decoder.offset as usize] = 0;
+        assert!(matches!(
+            Reader::new(&strings[..]).find_map(Result::err),
+            Some(Error::Decode { .. })
+        ));
+
+        let truncated = &data[..data.len() - 1];
+        assert!(matches!(
+            Cache::open(Cursor::new(truncated)),
+            Err(Error::Decode { .. })
+        ));
+    }
+}
@@ -0,0 +1,225 @@
+//! Matching of elements between two datasets, e.g. an import and the existing map
+//!
+//! <https://wiki.openstreetmap.org/wiki/Conflation>
+//!
+//! A [Matcher] pairs nodes with nodes and ways with ways that lie within a distance of each
+//! other, and scores each pair by how close they are and how similar their tags are. It only
+//! proposes [Candidate]s: deciding which to merge is left to the caller or a human reviewer.
+
+use fnv::{FnvHashMap as HashMap, FnvHashSet as HashSet};
+
+use crate::bbox::Bbox;
+use crate::geom::{densify, distance, hausdorff_distance, LatLon};
+use crate::id::ElementId;
+use crate::scalar::ScalarExt;
+use crate::store::ElementStore;
+use crate::{Element, MemberType, Node, TagString, Tags};
+
+/// Meters per degree of latitude, to widen boxes by a distance
+const METERS_PER_DEGREE: f64 = 111_320.;
+
+/// Possible match between an element of the source and one of the target
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Candidate {
+    pub source: ElementId,
+    pub target: ElementId,
+    /// Distance between points, or [hausdorff_distance] between lines, in meters
+    pub distance: f64,
+    /// See [Matcher::tag_similarity]
+    pub tag_similarity: f64,
+    /// Combined score from 0 to 1, higher for better matches
+    pub score: f64,
+}
+
+/// Settings for [Matcher::candidates]
+#[derive(Debug, PartialEq, Clone)]
+pub struct Matcher {
+    /// Farthest distance between matched nodes in meters
+    pub max_node_distance: f64,
+    /// Farthest [hausdorff_distance] between matched ways in meters
+    pub max_way_distance: f64,
+    /// Keys compared by [Matcher::tag_similarity], or every key if empty
+    pub keys: Vec<TagString>,
+    /// Share of the tags in [Candidate::score], the rest being proximity
+    pub tag_weight: f64,
+    /// Lowest [Candidate::score] returned
+    pub min_score: f64,
+}
+
+impl Default for Matcher {
+    fn default() -> Self {
+        Self {
+            max_node_distance: 50.,
+            max_way_distance: 20.,
+            keys: vec![],
+            tag_weight: 0.5,
+            min_score: 0.5,
+        }
+    }
+}
+
+impl Matcher {
+    /// Share from 0 to 1 of the compared keys that both have with the same value
+    ///
+    /// A key tagged on only one side counts against the match, and names are compared
+    /// ignoring case. Tags with none of the compared keys have a similarity of 0.
+    pub fn tag_similarity(&self, a: &Tags, b: &Tags) -> f64 {
+        let keys: HashSet<&str> = if self.keys.is_empty() {
+            a.keys().chain(b.keys()).map(TagString::as_str).collect()
+        } else {
+            self.keys
+                .iter()
+                .map(TagString::as_str)
+                .filter(|key| a.contains_key(*key) || b.contains_key(*key))
+                .collect()
+        };
+        if keys.is_empty() {
+            return 0.;
+        }
+        let same = keys
+            .iter()
+            .filter(|&&key| match (a.get(key), b.get(key)) {
+                (Some(a), Some(b)) if key == "name" || key.starts_with("name:") => {
+                    a.to_lowercase() == b.to_lowercase()
+                }
+                (Some(a), Some(b)) => a == b,
+                _ => false,
+            })
+            .count();
+        same as f64 / keys.len() as f64
+    }
+
+    /// Candidates for each node and way of `source` among those of `target`, best first
+    pub fn candidates(&self, source: &ElementStore, target: &ElementStore) -> Vec<Candidate> {
+        let nodes = Grid::new(
+            self.max_node_distance,
+            target
+                .nodes()
+                .map(|node| (Element::Node(node.clone()), node_points(node))),
+        );
+        let lines = |store: &ElementStore| -> Vec<(Element, Vec<LatLon>)> {
+            store
+                .ways()
+                .filter_map(|way| {
+                    let line = store.resolve_way(way).ok()?;
+                    Some((Element::Way(way.clone()), line))
+                })
+                .collect()
+        };
+        let ways = Grid::new(self.max_way_distance, lines(target));
+
+        let mut candidates = vec![];
+        for node in source.nodes() {
+            let points = node_points(node);
+            let node = Element::Node(node.clone());
+            for (other, other_points) in nodes.near(&points) {
+                let distance = distance(points[0], other_points[0]);
+                self.push(&mut candidates, &node, other, distance);
+            }
+        }
+        for (way, line) in lines(source) {
+            let line = densify(&line, self.max_way_distance.max(1.));
+            for (other, other_line) in ways.near(&line) {
+                let other_line = densify(other_line, self.max_way_distance.max(1.));
+                if let Some(distance) = hausdorff_distance(&line, &other_line) {
+                    self.push(&mut candidates, &way, other, distance);
+                }
+            }
+        }
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+        candidates
+    }
+
+    fn push(
+        &self,
+        candidates: &mut Vec<Candidate>,
+        source: &Element,
+        target: &Element,
+        distance: f64,
+    ) {
+        let max = match source.member_type() {
+            MemberType::Node => self.max_node_distance,
+            _ => self.max_way_distance,
+        };
+        if distance > max {
+            return;
+        }
+        let proximity = if max > 0. { 1. - distance / max } else { 1. };
+        let tag_similarity = self.tag_similarity(source.tags(), target.tags());
+        let score = self.tag_weight * tag_similarity + (1. - self.tag_weight) * proximity;
+        if score >= self.min_score {
+            candidates.push(Candidate {
+                source: source.element_id(),
+                target: target.element_id(),
+                distance,
+                tag_similarity,
+                score,
+            });
+        }
+    }
+}
+
+fn node_points(node: &Node) -> Vec<LatLon> {
+    vec![node.lat_lon()]
+}
+
+/// Elements of the target by the cells their boxes cover, with cells about as large as the
+/// distance searched
+struct Grid {
+    cell: f64,
+    distance: f64,
+    elements: Vec<(Element, Vec<LatLon>)>,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl Grid {
+    fn new(distance: f64, elements: impl IntoIterator<Item = (Element, Vec<LatLon>)>) -> Self {
+        let mut grid = Self {
+            cell: (distance / METERS_PER_DEGREE).max(1e-4),
+            distance,
+            elements: elements.into_iter().collect(),
+            cells: HashMap::default(),
+        };
+        for (index, (_, points)) in grid.elements.iter().enumerate() {
+            for cell in grid.covered(points, 0.) {
+                grid.cells.entry(cell).or_default().push(index);
+            }
+        }
+        grid
+    }
+
+    /// Cells of the box around `points`, widened by `distance` meters
+    fn covered(&self, points: &[LatLon], distance: f64) -> Vec<(i64, i64)> {
+        let Some(bbox) = Bbox::of_points(points.iter().copied()) else {
+            return vec![];
+        };
+        let (min_lat, max_lat) = (bbox.min_lat.as_f64(), bbox.max_lat.as_f64());
+        let lat_margin = distance / METERS_PER_DEGREE;
+        let widest = min_lat.abs().max(max_lat.abs()).min(89.).to_radians().cos();
+        let lon_margin = lat_margin / widest;
+        let index = |degrees: f64| (degrees / self.cell).floor() as i64;
+        let lats = index(min_lat - lat_margin)..=index(max_lat + lat_margin);
+        let lons =
+            index(bbox.min_lon.as_f64() - lon_margin)..=index(bbox.max_lon.as_f64() + lon_margin);
+        lats.flat_map(|lat| lons.clone().map(move |lon| (lat, lon)))
+            .collect()
+    }
+
+    /// Elements whose boxes may lie within the distance of `points`
+    fn near(&self, points: &[LatLon]) -> impl Iterator<Item = (&Element, &Vec<LatLon>)> {
+        let mut indexes: Vec<usize> = self
+            .covered(points, self.distance)
+            .iter()
+            .filter_map(|cell| self.cells.get(cell))
+            .flatten()
+            .copied()
+            .collect();
+        indexes.sort_unstable();
+        indexes.dedup();
+        indexes.into_iter().map(|index| {
+            let (element, points) = &self.elements[index];
+            (element, points)
+        })
+    }
+}
@@ -0,0 +1,202 @@
+//! Validation and normalization of contact information
+//!
+//! <https://wiki.openstreetmap.org/wiki/Key:contact>
+
+use fnv::FnvHashMap as HashMap;
+
+use crate::{Element, TagString};
+
+/// Kind of contact information held by a key
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ContactKind {
+    Phone,
+    Website,
+    Email,
+}
+
+impl ContactKind {
+    /// Kind of contact information expected for `key`, if any
+    pub fn of_key(key: &str) -> Option<Self> {
+        Some(match key.strip_prefix("contact:").unwrap_or(key) {
+            "phone" | "mobile" | "fax" => ContactKind::Phone,
+            "website" | "url" => ContactKind::Website,
+            "email" => ContactKind::Email,
+            _ => return None,
+        })
+    }
+}
+
+/// Normalizes a phone number to [E.164](https://en.wikipedia.org/wiki/E.164), e.g. `+44 20 7946 0000` becomes `+442079460000`
+///
+/// Numbers without an international prefix are only converted if `country_code`
+/// (e.g. `44`) is given, in which case a leading trunk prefix `0` is dropped.
+/// A trunk prefix written as `(0)` after the country code, as in `+44 (0)20 7946 0000`, is dropped too.
+/// Returns [None] if the value is not recognizable as a phone number.
+pub fn normalize_phone(value: &str, country_code: Option<&str>) -> Option<String> {
+    let value = value.trim();
+    let (international, rest) = if let Some(rest) = value.strip_prefix('+') {
+        (true, rest)
+    } else if let Some(rest) = value.strip_prefix("00") {
+        (true, rest)
+    } else {
+        (false, value)
+    };
+    let rest = if international {
+        rest.replacen("(0)", "", 1)
+    } else {
+        rest.to_string()
+    };
+    let mut digits = String::with_capacity(rest.len());
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => digits.push(c),
+            ' ' | '-' | '.' | '/' | '(' | ')' => {}
+            _ => return None,
+        }
+    }
+    if !international {
+        let country_code = country_code?;
+        digits = format!(
+            "{country_code}{}",
+            digits.strip_prefix('0').unwrap_or(&digits)
+        );
+    }
+    // E.164 allows at most 15 digits, and no country has subscriber numbers this short
+    (7..=15)
+        .contains(&digits.len())
+        .then(|| format!("+{digits}"))
+}
+
+/// Normalizes a website to an absolute `http` or `https` URL
+///
+/// A missing scheme is assumed to be `https`, and the scheme and host are lowercased.
+/// Returns [None] if the value does not look like a website.
+pub fn normalize_website(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() || value.chars().any(char::is_whitespace) {
+        return None;
+    }
+    let (scheme, rest) = match value.split_once("://") {
+        Some((scheme, rest)) => (scheme.to_ascii_lowercase(), rest),
+        None => ("https".to_string(), value),
+    };
+    if scheme != "http" && scheme != "https" {
+        return None;
+    }
+    let host_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (host, path) = rest.split_at(host_end);
+    let hostname = host.rsplit_once(':').map_or(host, |(hostname, port)| {
+        if port.bytes().all(|b| b.is_ascii_digit()) {
+            hostname
+        } else {
+            host
+        }
+    });
+    let valid_host = hostname.contains('.')
+        && !hostname.starts_with('.')
+        && !hostname.ends_with('.')
+        && hostname
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '.');
+    valid_host.then(|| format!("{scheme}://{}{path}", host.to_lowercase()))
+}
+
+/// Normalizes an email address, removing any `mailto:` prefix and lowercasing the domain
+///
+/// Returns [None] if the value does not look like an email address.
+pub fn normalize_email(value: &str) -> Option<String> {
+    let value = value.trim();
+    let value = value.strip_prefix("mailto:").unwrap_or(value);
+    let (local, domain) = value.rsplit_once('@')?;
+    let valid = !local.is_empty()
+        && !value.chars().any(char::is_whitespace)
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !domain.contains('@');
+    valid.then(|| format!("{local}@{}", domain.to_lowercase()))
+}
+
+/// Normalizes each `;`-separated value of contact information of `kind`
+///
+/// Returns [None] if any of the values is invalid.
+pub fn normalize(kind: ContactKind, value: &str, country_code: Option<&str>) -> Option<String> {
+    value
+        .split(';')
+        .map(|part| match kind {
+            ContactKind::Phone => normalize_phone(part, country_code),
+            ContactKind::Website => normalize_website(part),
+            ContactKind::Email => normalize_email(part),
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|parts| parts.join(";"))
+}
+
+/// Proposed change to a contact tag
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Suggestion<'a> {
+    pub key: &'a str,
+    pub kind: ContactKind,
+    pub value: &'a str,
+    /// Normalized value, or [None] if the value is invalid and cannot be fixed automatically
+    pub normalized: Option<String>,
+}
+
+/// Finds contact tags whose values are invalid or not in normal form
+///
+/// See [normalize_phone] for the meaning of `country_code`.
+pub fn suggestions<'a>(
+    tags: &'a HashMap<TagString, TagString>,
+    country_code: Option<&str>,
+) -> Vec<Suggestion<'a>> {
+    let mut suggestions: Vec<_> = tags
+        .iter()
+        .filter_map(|(key, value)| {
+            let kind = ContactKind::of_key(key)?;
+            let normalized = normalize(kind, value, country_code);
+            (normalized.as_deref() != Some(value.as_str())).then_some(Suggestion {
+                key,
+                kind,
+                value,
+                normalized,
+            })
+        })
+        .collect();
+    suggestions.sort_by_key(|suggestion| suggestion.key);
+    suggestions
+}
+
+impl Element {
+    /// See [suggestions]
+    pub fn contact_suggestions(&self, country_code: Option<&str>) -> Vec<Suggestion<'_>> {
+        suggestions(self.tags(), country_code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phone_numbers_are_normalized() {
+        assert_eq!(
+            normalize_phone("+44 20 7946 0000", None).as_deref(),
+            Some("+442079460000")
+        );
+        assert_eq!(
+            normalize_phone("+44 (0)20 7946 0000", None).as_deref(),
+            Some("+442079460000")
+        );
+        assert_eq!(
+            normalize_phone("0044 (0) 20-7946-0000", None).as_deref(),
+            Some("+442079460000")
+        );
+        assert_eq!(
+            normalize_phone("020 7946 0000", Some("44")).as_deref(),
+            Some("+442079460000")
+        );
+        assert_eq!(normalize_phone("020 7946 0000", None), None);
+        assert_eq!(normalize_phone("call us", Some("44")), None);
+    }
+}
@@ -0,0 +1,227 @@
+//! Compact fixed-point coordinates
+//!
+//! [Coordinate] holds a latitude and longitude as whole nanodegrees, the unit PBF files
+//! compute coordinates in. It is 16 bytes, [Copy], [Ord], and [Hash] with either [Scalar],
+//! which makes it a cheaper key for indexes and sorting than a pair of [Scalar]s. [Node] keeps
+//! [Scalar] coordinates, which convert to and from it.
+//!
+//! [CoordinateE7] holds them as [i32]s in units of 100 nanodegrees, the precision of the OSM
+//! database, in 8 bytes. It converts losslessly to [Coordinate], and back where no precision is
+//! lost. Without the default `decimal` feature, [Scalar] is an [f64] instead.
+
+use std::fmt;
+
+use crate::geom::LatLon;
+use crate::scalar::{Scalar, ScalarExt};
+use crate::Node;
+
+/// Nanodegrees in a degree
+pub const NANODEGREES_PER_DEGREE: i64 = 1_000_000_000;
+
+/// Latitude and longitude in nanodegrees
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Coordinate {
+    pub lat: i64,
+    pub lon: i64,
+}
+
+impl Coordinate {
+    pub fn new(lat: i64, lon: i64) -> Self {
+        Self { lat, lon }
+    }
+
+    /// Converts exactly, or returns [None] if a coordinate is outside of ±180° or has more
+    /// than 9 decimal places
+    ///
+    /// Without the `decimal` feature, coordinates are rounded to the nearest nanodegree instead.
+    pub fn from_lat_lon((lat, lon): LatLon) -> Option<Self> {
+        Some(Self {
+            lat: nanodegrees(lat)?,
+            lon: nanodegrees(lon)?,
+        })
+    }
+
+    /// Rounds to the nearest nanodegree, or returns [None] if a coordinate is not finite or
+    /// outside of ±180°
+    pub fn from_f64(lat: f64, lon: f64) -> Option<Self> {
+        let nanodegrees = |degrees: f64| {
+            (degrees.is_finite() && degrees.abs() <= 180.)
+                .then(|| (degrees * NANODEGREES_PER_DEGREE as f64).round() as i64)
+        };
+        Some(Self {
+            lat: nanodegrees(lat)?,
+            lon: nanodegrees(lon)?,
+        })
+    }
+
+    /// Exact with the `decimal` feature
+    pub fn lat_lon(&self) -> LatLon {
+        let degrees = |nanodegrees| Scalar::with_scale(nanodegrees, 9).normalized();
+        (degrees(self.lat), degrees(self.lon))
+    }
+
+    pub fn lat_f64(&self) -> f64 {
+        self.lat as f64 / NANODEGREES_PER_DEGREE as f64
+    }
+
+    pub fn lon_f64(&self) -> f64 {
+        self.lon as f64 / NANODEGREES_PER_DEGREE as f64
+    }
+}
+
+impl From<Coordinate> for LatLon {
+    fn from(coordinate: Coordinate) -> Self {
+        coordinate.lat_lon()
+    }
+}
+
+/// Units of [CoordinateE7] in a degree
+pub const E7_PER_DEGREE: i32 = 10_000_000;
+
+/// Nanodegrees in a unit of [CoordinateE7]
+const NANODEGREES_PER_E7: i64 = NANODEGREES_PER_DEGREE / E7_PER_DEGREE as i64;
+
+/// Latitude and longitude in units of 100 nanodegrees, as stored by the OSM database
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CoordinateE7 {
+    pub lat: i32,
+    pub lon: i32,
+}
+
+impl CoordinateE7 {
+    pub fn new(lat: i32, lon: i32) -> Self {
+        Self { lat, lon }
+    }
+
+    /// Converts exactly, or returns [None] if a coordinate is outside of ±180° or has more
+    /// than 7 decimal places
+    ///
+    /// Without the `decimal` feature, coordinates are rounded to the nearest unit instead.
+    pub fn from_lat_lon(coordinate: LatLon) -> Option<Self> {
+        let coordinate = Coordinate::from_lat_lon(coordinate)?;
+        #[cfg(feature = "decimal")]
+        return coordinate.try_into().ok();
+        #[cfg(not(feature = "decimal"))]
+        return coordinate.to_e7();
+    }
+
+    /// Rounds to the nearest unit, or returns [None] if a coordinate is not finite or outside
+    /// of ±180°
+    pub fn from_f64(lat: f64, lon: f64) -> Option<Self> {
+        let units = |degrees: f64| {
+            (degrees.is_finite() && degrees.abs() <= 180.)
+                .then(|| (degrees * E7_PER_DEGREE as f64).round() as i32)
+        };
+        Some(Self {
+            lat: units(lat)?,
+            lon: units(lon)?,
+        })
+    }
+
+    /// Exact with the `decimal` feature
+    pub fn lat_lon(&self) -> LatLon {
+        Coordinate::from(*self).lat_lon()
+    }
+
+    pub fn lat_f64(&self) -> f64 {
+        self.lat as f64 / E7_PER_DEGREE as f64
+    }
+
+    pub fn lon_f64(&self) -> f64 {
+        self.lon as f64 / E7_PER_DEGREE as f64
+    }
+}
+
+impl From<CoordinateE7> for Coordinate {
+    fn from(coordinate: CoordinateE7) -> Self {
+        Self {
+            lat: coordinate.lat as i64 * NANODEGREES_PER_E7,
+            lon: coordinate.lon as i64 * NANODEGREES_PER_E7,
+        }
+    }
+}
+
+/// Error returned when a [Coordinate] has more precision than a [CoordinateE7] holds
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PrecisionLossError;
+
+impl fmt::Display for PrecisionLossError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "coordinate has more than 7 decimal places")
+    }
+}
+
+impl std::error::Error for PrecisionLossError {}
+
+impl TryFrom<Coordinate> for CoordinateE7 {
+    type Error = PrecisionLossError;
+
+    /// Converts if both coordinates are whole multiples of 100 nanodegrees in the range of
+    /// [i32], see [Coordinate::to_e7] to round instead
+    fn try_from(coordinate: Coordinate) -> Result<Self, Self::Error> {
+        let units = |nanodegrees: i64| {
+            (nanodegrees % NANODEGREES_PER_E7 == 0)
+                .then(|| i32::try_from(nanodegrees / NANODEGREES_PER_E7).ok())
+                .flatten()
+                .ok_or(PrecisionLossError)
+        };
+        Ok(Self {
+            lat: units(coordinate.lat)?,
+            lon: units(coordinate.lon)?,
+        })
+    }
+}
+
+impl From<CoordinateE7> for LatLon {
+    fn from(coordinate: CoordinateE7) -> Self {
+        coordinate.lat_lon()
+    }
+}
+
+impl Coordinate {
+    /// Rounds to the nearest 100 nanodegrees, with halfway values going away from zero, or
+    /// returns [None] if a coordinate is outside of the range of [CoordinateE7]
+    pub fn to_e7(&self) -> Option<CoordinateE7> {
+        let units = |nanodegrees: i64| {
+            let half = NANODEGREES_PER_E7 / 2 * nanodegrees.signum();
+            i32::try_from((nanodegrees + half) / NANODEGREES_PER_E7).ok()
+        };
+        Some(CoordinateE7 {
+            lat: units(self.lat)?,
+            lon: units(self.lon)?,
+        })
+    }
+}
+
+impl Node {
+    /// See [CoordinateE7::from_lat_lon]
+    pub fn coordinate_e7(&self) -> Option<CoordinateE7> {
+        CoordinateE7::from_lat_lon(self.lat_lon())
+    }
+
+    /// See [Coordinate::from_lat_lon]
+    pub fn coordinate(&self) -> Option<Coordinate> {
+        Coordinate::from_lat_lon(self.lat_lon())
+    }
+
+    pub fn set_coordinate(&mut self, coordinate: Coordinate) {
+        (self.lat, self.lon) = coordinate.lat_lon();
+    }
+}
+
+fn nanodegrees(degrees: Scalar) -> Option<i64> {
+    if degrees.abs() > Scalar::from_int(180) {
+        return None;
+    }
+    let scaled = degrees * Scalar::from_int(NANODEGREES_PER_DEGREE);
+    // Floats rarely scale to an exact integer
+    #[cfg(not(feature = "decimal"))]
+    let scaled = scaled.round_half_away();
+    if scaled.is_integer() {
+        scaled.to_int()
+    } else {
+        None
+    }
+}
@@ -0,0 +1,211 @@
+//! Dates of varying precision as used by lifecycle and survey keys
+//!
+//! <https://wiki.openstreetmap.org/wiki/Key:start_date>
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+
+use crate::{Element, Tags};
+
+/// Calendar date that may only be known to the year or month
+///
+/// Dates are ordered by year, month, and day, with a less precise date sorting
+/// before the more precise dates it contains, and then by qualifier.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PartialDate {
+    pub year: i32,
+    /// 1 to 12
+    pub month: Option<u32>,
+    /// 1 to 31, only present if [PartialDate::month] is
+    pub day: Option<u32>,
+    pub qualifier: DateQualifier,
+}
+
+/// Uncertainty or bound attached to a [PartialDate]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DateQualifier {
+    /// Sometime before the date, e.g. `before 1920`
+    Before,
+    /// Approximately the date, e.g. `~1850`
+    Approximate,
+    #[default]
+    Exact,
+    /// Sometime after the date, e.g. `after 1920`
+    After,
+}
+
+impl PartialDate {
+    pub fn year(year: i32) -> Self {
+        Self {
+            year,
+            month: None,
+            day: None,
+            qualifier: DateQualifier::Exact,
+        }
+    }
+
+    /// Earliest day this date could refer to, ignoring the qualifier
+    pub fn first_day(&self) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(self.year, self.month.unwrap_or(1), self.day.unwrap_or(1))
+    }
+
+    /// Latest day this date could refer to, ignoring the qualifier
+    pub fn last_day(&self) -> Option<NaiveDate> {
+        match (self.month, self.day) {
+            (Some(month), Some(day)) => NaiveDate::from_ymd_opt(self.year, month, day),
+            (Some(month), None) => {
+                let (year, month) = if month == 12 {
+                    (self.year + 1, 1)
+                } else {
+                    (self.year, month + 1)
+                };
+                NaiveDate::from_ymd_opt(year, month, 1)?.pred_opt()
+            }
+            (None, _) => NaiveDate::from_ymd_opt(self.year, 12, 31),
+        }
+    }
+}
+
+impl From<NaiveDate> for PartialDate {
+    fn from(date: NaiveDate) -> Self {
+        use chrono::Datelike;
+        Self {
+            year: date.year(),
+            month: Some(date.month()),
+            day: Some(date.day()),
+            qualifier: DateQualifier::Exact,
+        }
+    }
+}
+
+impl PartialOrd for PartialDate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PartialDate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let key = |d: &Self| (d.year, d.month.unwrap_or(0), d.day.unwrap_or(0));
+        key(self)
+            .cmp(&key(other))
+            .then(self.qualifier.cmp(&other.qualifier))
+    }
+}
+
+/// Error returned when parsing a [PartialDate] fails
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseDateError;
+
+impl fmt::Display for ParseDateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid date")
+    }
+}
+
+impl std::error::Error for ParseDateError {}
+
+impl FromStr for PartialDate {
+    type Err = ParseDateError;
+
+    /// Parses `YYYY`, `YYYY-MM`, or `YYYY-MM-DD`, optionally prefixed by `~`, `before `, or `after `
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (qualifier, s) = if let Some(rest) = s.strip_prefix('~') {
+            (DateQualifier::Approximate, rest)
+        } else if let Some(rest) = s.strip_prefix("before ") {
+            (DateQualifier::Before, rest)
+        } else if let Some(rest) = s.strip_prefix("after ") {
+            (DateQualifier::After, rest)
+        } else {
+            (DateQualifier::Exact, s)
+        };
+
+        let mut parts = s.trim().splitn(3, '-');
+        let number = |part: Option<&str>, digits: usize| -> Result<Option<u32>, ParseDateError> {
+            match part {
+                None => Ok(None),
+                Some(part) if part.len() == digits && part.bytes().all(|b| b.is_ascii_digit()) => {
+                    Ok(Some(part.parse().map_err(|_| ParseDateError)?))
+                }
+                Some(_) => Err(ParseDateError),
+            }
+        };
+        let year = number(parts.next(), 4)?.ok_or(ParseDateError)? as i32;
+        let month = number(parts.next(), 2)?;
+        let day = number(parts.next(), 2)?;
+
+        let date = PartialDate {
+            year,
+            month,
+            day,
+            qualifier,
+        };
+        if !matches!(month, None | Some(1..=12)) || date.first_day().is_none() {
+            return Err(ParseDateError);
+        }
+        Ok(date)
+    }
+}
+
+impl fmt::Display for PartialDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.qualifier {
+            DateQualifier::Before => write!(f, "before ")?,
+            DateQualifier::Approximate => write!(f, "~")?,
+            DateQualifier::Exact => {}
+            DateQualifier::After => write!(f, "after ")?,
+        }
+        write!(f, "{:04}", self.year)?;
+        if let Some(month) = self.month {
+            write!(f, "-{month:02}")?;
+        }
+        if let Some(day) = self.day {
+            write!(f, "-{day:02}")?;
+        }
+        Ok(())
+    }
+}
+
+fn date_tag(tags: &Tags, key: &str) -> Option<PartialDate> {
+    tags.get(key)?.parse().ok()
+}
+
+/// Date the feature came into existence
+pub fn start_date(tags: &Tags) -> Option<PartialDate> {
+    date_tag(tags, "start_date")
+}
+
+/// Date the feature ceased to exist
+pub fn end_date(tags: &Tags) -> Option<PartialDate> {
+    date_tag(tags, "end_date")
+}
+
+/// Date the feature was last verified, preferring `check_date` over `survey:date`
+///
+/// <https://wiki.openstreetmap.org/wiki/Key:check_date>
+pub fn check_date(tags: &Tags) -> Option<PartialDate> {
+    date_tag(tags, "check_date").or_else(|| date_tag(tags, "survey:date"))
+}
+
+impl Element {
+    /// See [start_date]
+    pub fn start_date(&self) -> Option<PartialDate> {
+        start_date(self.tags())
+    }
+
+    /// See [end_date]
+    pub fn end_date(&self) -> Option<PartialDate> {
+        end_date(self.tags())
+    }
+
+    /// See [check_date]
+    pub fn check_date(&self) -> Option<PartialDate> {
+        check_date(self.tags())
+    }
+}
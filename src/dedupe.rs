@@ -0,0 +1,149 @@
+//! Content hashes of elements and merging of duplicate nodes
+//!
+//! [Element::content_hash] identifies elements by what they describe rather than by their id
+//! or version, e.g. to find the features of an import that are already mapped.
+
+use std::hash::Hasher;
+
+use fnv::{FnvHashMap as HashMap, FnvHasher};
+
+use crate::scalar::ScalarExt;
+use crate::store::ElementStore;
+use crate::{Element, Id, MemberType, Node, Relation, Tags, Way};
+
+/// FNV-1a over fields separated by a zero byte, so that e.g. `ab=c` and `a=bc` differ
+struct Digest(FnvHasher);
+
+impl Digest {
+    fn new(ty: MemberType, tags: &Tags) -> Self {
+        let mut digest = Self(FnvHasher::default());
+        digest.write(ty.as_str().as_bytes());
+        for (key, value) in tags.iter_sorted() {
+            digest.write(key.as_bytes());
+            digest.write(value.as_bytes());
+        }
+        digest
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+        self.0.write_u8(0);
+    }
+}
+
+impl Element {
+    /// Digest of the type, tags, and coordinates, refs, or members of the element
+    ///
+    /// The id and [crate::Info] are left out, and tags are hashed in the order of their keys.
+    /// The digest is [FNV-1a](https://en.wikipedia.org/wiki/Fowler%E2%80%93Noll%E2%80%93Vo_hash_function)
+    /// over a fixed encoding, so it is the same across runs and platforms and can be stored.
+    pub fn content_hash(&self) -> u64 {
+        match self {
+            Element::Node(node) => node.content_hash(),
+            Element::Way(way) => way.content_hash(),
+            Element::Relation(relation) => relation.content_hash(),
+        }
+    }
+}
+
+impl Node {
+    /// See [Element::content_hash]
+    pub fn content_hash(&self) -> u64 {
+        let mut digest = Digest::new(MemberType::Node, &self.tags);
+        digest.write(self.lat.normalized().to_string().as_bytes());
+        digest.write(self.lon.normalized().to_string().as_bytes());
+        digest.0.finish()
+    }
+}
+
+impl Way {
+    /// See [Element::content_hash]
+    pub fn content_hash(&self) -> u64 {
+        let mut digest = Digest::new(MemberType::Way, &self.tags);
+        for id in &self.refs {
+            digest.write(&id.0.to_le_bytes());
+        }
+        digest.0.finish()
+    }
+}
+
+impl Relation {
+    /// See [Element::content_hash]
+    pub fn content_hash(&self) -> u64 {
+        let mut digest = Digest::new(MemberType::Relation, &self.tags);
+        for member in &self.members {
+            digest.write(member.ty.as_str().as_bytes());
+            digest.write(&member.id.0.to_le_bytes());
+            digest.write(member.role.as_deref().unwrap_or("").as_bytes());
+        }
+        digest.0.finish()
+    }
+}
+
+/// Merges nodes with identical coordinates and tags into the one with the lowest id
+///
+/// Way refs and node members of relations are rewritten to the remaining nodes, with
+/// consecutive duplicate refs dropped. Returns the id of each removed node to the id it was
+/// merged into. Unlike [crate::snap::snap_and_merge], coordinates must be exactly equal.
+pub fn dedupe_identical_nodes(store: &mut ElementStore) -> HashMap<Id, Id> {
+    let mut nodes: Vec<&Node> = store.nodes().collect();
+    nodes.sort_by_key(|node| node.id);
+    let mut survivors: HashMap<u64, Vec<&Node>> = HashMap::default();
+    let mut merged = HashMap::default();
+    for node in nodes {
+        let hash = node.content_hash();
+        let candidates = survivors.entry(hash).or_default();
+        // Hashes may collide, so the survivor must also compare equal
+        let same = candidates.iter().find(|survivor| {
+            survivor.lat == node.lat && survivor.lon == node.lon && survivor.tags == node.tags
+        });
+        match same {
+            Some(survivor) => {
+                merged.insert(node.id, survivor.id);
+            }
+            None => candidates.push(node),
+        }
+    }
+    if merged.is_empty() {
+        return merged;
+    }
+
+    let ways: Vec<_> = store
+        .ways()
+        .filter(|way| way.refs.iter().any(|id| merged.contains_key(id)))
+        .cloned()
+        .collect();
+    for mut way in ways {
+        for id in way.refs.iter_mut() {
+            if let Some(survivor) = merged.get(id) {
+                *id = *survivor;
+            }
+        }
+        way.refs.dedup();
+        store.insert(Element::Way(way));
+    }
+    let relations: Vec<_> = store
+        .relations()
+        .filter(|relation| {
+            relation
+                .members
+                .iter()
+                .any(|member| member.ty == MemberType::Node && merged.contains_key(&member.id))
+        })
+        .cloned()
+        .collect();
+    for mut relation in relations {
+        for member in relation.members.iter_mut() {
+            if member.ty == MemberType::Node {
+                if let Some(survivor) = merged.get(&member.id) {
+                    member.id = *survivor;
+                }
+            }
+        }
+        store.insert(Element::Relation(relation));
+    }
+    for id in merged.keys() {
+        store.remove(&MemberType::Node, *id);
+    }
+    merged
+}
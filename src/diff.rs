@@ -0,0 +1,259 @@
+//! Differences between two versions of an element
+//!
+//! [element_diff] says what an edit changed, e.g. to review the versions of a changeset like
+//! [OSMCha](https://osmcha.org/) does. Way nodes and relation members are compared as
+//! sequences, so inserting a node in the middle of a way is a single [SequenceChange::Added].
+
+use crate::geom::LatLon;
+use crate::{Element, ElementId, Id, Member, TagString, Tags};
+
+/// Sequences with more pairs of elements left after removing the common start and end are
+/// compared as a whole, to bound memory
+const MAX_COMPARISONS: usize = 1 << 24;
+
+/// Changes from one version of an element to another
+///
+/// Each list is empty if that part did not change, or does not exist in the elements, or the
+/// elements are not of the same type.
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub struct ElementDiff {
+    /// Changed tags, sorted by key
+    pub tags: Vec<TagChange>,
+    /// Old and new coordinates of a node that moved
+    pub moved: Option<(LatLon, LatLon)>,
+    /// Changes to the nodes of a way
+    pub nodes: Vec<SequenceChange<Id>>,
+    /// Changes to the members of a relation, by [ElementId]
+    pub members: Vec<SequenceChange<Member>>,
+    /// Members whose role changed without being moved
+    pub roles: Vec<RoleChange>,
+}
+
+impl ElementDiff {
+    /// Whether the versions are the same, apart from [Info](crate::Info)
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && !self.is_geometry_changed() && self.roles.is_empty()
+    }
+
+    /// Whether a node moved, or the nodes of a way or the members of a relation changed
+    pub fn is_geometry_changed(&self) -> bool {
+        self.moved.is_some() || !self.nodes.is_empty() || !self.members.is_empty()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum TagChange {
+    Added {
+        key: TagString,
+        value: TagString,
+    },
+    Removed {
+        key: TagString,
+        value: TagString,
+    },
+    Changed {
+        key: TagString,
+        old: TagString,
+        new: TagString,
+    },
+}
+
+impl TagChange {
+    pub fn key(&self) -> &TagString {
+        match self {
+            TagChange::Added { key, .. }
+            | TagChange::Removed { key, .. }
+            | TagChange::Changed { key, .. } => key,
+        }
+    }
+}
+
+/// Item inserted into or removed from a sequence
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum SequenceChange<T> {
+    /// Item at `index` of the new sequence
+    Added { index: usize, item: T },
+    /// Item at `index` of the old sequence
+    Removed { index: usize, item: T },
+}
+
+/// Member of both versions of a relation, with a different role
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct RoleChange {
+    pub member: ElementId,
+    /// Index in the new relation
+    pub index: usize,
+    pub old: Option<TagString>,
+    pub new: Option<TagString>,
+}
+
+/// Compares two versions of an element
+pub fn element_diff(old: &Element, new: &Element) -> ElementDiff {
+    let mut diff = ElementDiff {
+        tags: tag_changes(old.tags(), new.tags()),
+        ..ElementDiff::default()
+    };
+    match (old, new) {
+        (Element::Node(old), Element::Node(new)) if (old.lat, old.lon) != (new.lat, new.lon) => {
+            diff.moved = Some(((old.lat, old.lon), (new.lat, new.lon)));
+        }
+        (Element::Way(old), Element::Way(new)) => {
+            diff.nodes = sequence_changes(&old.refs, &new.refs, |a, b| a == b);
+        }
+        (Element::Relation(old), Element::Relation(new)) => {
+            let same = |a: &Member, b: &Member| a.ty == b.ty && a.id == b.id;
+            diff.members = sequence_changes(&old.members, &new.members, same);
+            diff.roles = role_changes(&old.members, &new.members, &diff.members);
+        }
+        _ => {}
+    }
+    diff
+}
+
+fn tag_changes(old: &Tags, new: &Tags) -> Vec<TagChange> {
+    let mut changes = vec![];
+    for (key, value) in old.iter_sorted() {
+        match new.get(key) {
+            None => changes.push(TagChange::Removed {
+                key: key.clone(),
+                value: value.clone(),
+            }),
+            Some(new) if new != value => changes.push(TagChange::Changed {
+                key: key.clone(),
+                old: value.clone(),
+                new: new.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (key, value) in new.iter() {
+        if !old.contains_key(key) {
+            changes.push(TagChange::Added {
+                key: key.clone(),
+                value: value.clone(),
+            });
+        }
+    }
+    changes.sort_by(|a, b| a.key().cmp(b.key()));
+    changes
+}
+
+/// Members kept in place whose role differs
+fn role_changes(
+    old: &[Member],
+    new: &[Member],
+    changes: &[SequenceChange<Member>],
+) -> Vec<RoleChange> {
+    let removed = changes
+        .iter()
+        .filter_map(|change| match change {
+            SequenceChange::Removed { index, .. } => Some(*index),
+            SequenceChange::Added { .. } => None,
+        })
+        .collect::<Vec<_>>();
+    let added = changes
+        .iter()
+        .filter_map(|change| match change {
+            SequenceChange::Added { index, .. } => Some(*index),
+            SequenceChange::Removed { .. } => None,
+        })
+        .collect::<Vec<_>>();
+    // Members that are not removed or added are matched up in order
+    let kept_old = (0..old.len()).filter(|i| removed.binary_search(i).is_err());
+    let kept_new = (0..new.len()).filter(|i| added.binary_search(i).is_err());
+    kept_old
+        .zip(kept_new)
+        .filter(|&(i, j)| old[i].role != new[j].role)
+        .map(|(i, j)| RoleChange {
+            member: new[j].element_id(),
+            index: j,
+            old: old[i].role.clone(),
+            new: new[j].role.clone(),
+        })
+        .collect()
+}
+
+/// Edits turning `old` into `new`, from the longest common subsequence of the two
+///
+/// Removals come before additions, each in order of their index.
+fn sequence_changes<T: Clone>(
+    old: &[T],
+    new: &[T],
+    same: impl Fn(&T, &T) -> bool,
+) -> Vec<SequenceChange<T>> {
+    let mut old_kept = vec![false; old.len()];
+    let mut new_kept = vec![false; new.len()];
+    for (i, j) in common_subsequence(old, new, same) {
+        old_kept[i] = true;
+        new_kept[j] = true;
+    }
+    let removed = old
+        .iter()
+        .zip(old_kept)
+        .enumerate()
+        .filter(|(_, (_, kept))| !kept)
+        .map(|(index, (item, _))| SequenceChange::Removed {
+            index,
+            item: item.clone(),
+        });
+    let added = new
+        .iter()
+        .zip(new_kept)
+        .enumerate()
+        .filter(|(_, (_, kept))| !kept)
+        .map(|(index, (item, _))| SequenceChange::Added {
+            index,
+            item: item.clone(),
+        });
+    removed.chain(added).collect()
+}
+
+/// Indexes in `old` and `new` of the items of their longest common subsequence, in order
+///
+/// Only the common start and end are matched if the rest is too long to compare.
+pub(crate) fn common_subsequence<T>(
+    old: &[T],
+    new: &[T],
+    same: impl Fn(&T, &T) -> bool,
+) -> Vec<(usize, usize)> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| same(a, b)).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| same(a, b))
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+    let (n, m) = (old_middle.len(), new_middle.len());
+
+    let mut matches: Vec<_> = (0..prefix).map(|i| (i, i)).collect();
+    if n.saturating_mul(m) <= MAX_COMPARISONS {
+        // lengths[i * (m + 1) + j] is the length of the LCS of old_middle[i..] and new_middle[j..]
+        let mut lengths = vec![0u32; (n + 1) * (m + 1)];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lengths[i * (m + 1) + j] = if same(&old_middle[i], &new_middle[j]) {
+                    lengths[(i + 1) * (m + 1) + j + 1] + 1
+                } else {
+                    lengths[(i + 1) * (m + 1) + j].max(lengths[i * (m + 1) + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n && j < m {
+            if same(&old_middle[i], &new_middle[j]) {
+                matches.push((prefix + i, prefix + j));
+                i += 1;
+                j += 1;
+            } else if lengths[(i + 1) * (m + 1) + j] >= lengths[i * (m + 1) + j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+    }
+    matches.extend((0..suffix).map(|k| (old.len() - suffix + k, new.len() - suffix + k)));
+    matches
+}
@@ -0,0 +1,212 @@
+//! Degree-minute-second notation for coordinates, e.g. `48°51′24″N 2°21′08″E`
+//!
+//! <https://en.wikipedia.org/wiki/Degree_(angle)#Subdivisions>
+
+use std::fmt;
+
+use crate::scalar::{Scalar, ScalarExt};
+use crate::Node;
+
+/// Decimal places kept when converting parsed coordinates to degrees, matching the OSM API
+const PARSED_DECIMAL_PLACES: u32 = 7;
+
+/// Formats a latitude like `48°51′24″N` with `precision` decimal places of seconds
+pub fn format_lat(lat: Scalar, precision: u32) -> String {
+    format_angle(
+        lat,
+        precision,
+        if lat.is_sign_negative() { 'S' } else { 'N' },
+    )
+}
+
+/// Formats a longitude like `2°21′08″E` with `precision` decimal places of seconds
+pub fn format_lon(lon: Scalar, precision: u32) -> String {
+    format_angle(
+        lon,
+        precision,
+        if lon.is_sign_negative() { 'W' } else { 'E' },
+    )
+}
+
+fn format_angle(degrees: Scalar, precision: u32, hemisphere: char) -> String {
+    // Round once on total seconds so that e.g. 59.9999″ carries into the minutes
+    let total_seconds = (degrees.abs() * Scalar::from_int(3600)).round_places(precision);
+    let whole_degrees = (total_seconds / Scalar::from_int(3600)).floor();
+    let remainder = total_seconds - whole_degrees * Scalar::from_int(3600);
+    let minutes = (remainder / Scalar::from_int(60)).floor();
+    let seconds = remainder - minutes * Scalar::from_int(60);
+
+    let precision = precision as usize;
+    let width = if precision == 0 { 2 } else { precision + 3 };
+    format!("{whole_degrees}°{minutes:0>2}′{seconds:0>width$.precision$}″{hemisphere}")
+}
+
+/// Error returned when parsing degree-minute-second notation fails
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseDmsError;
+
+impl fmt::Display for ParseDmsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid degree-minute-second coordinates")
+    }
+}
+
+impl std::error::Error for ParseDmsError {}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Unit {
+    Degrees,
+    Minutes,
+    Seconds,
+}
+
+#[derive(Debug, Default)]
+struct Angle {
+    components: Vec<(Scalar, Unit)>,
+    negative: bool,
+    hemisphere: Option<char>,
+}
+
+impl Angle {
+    fn degrees(&self) -> Result<Scalar, ParseDmsError> {
+        let mut degrees = Scalar::ZERO;
+        let mut previous: Option<Unit> = None;
+        for (value, unit) in &self.components {
+            let in_order = match previous {
+                None => true,
+                Some(Unit::Degrees) => *unit != Unit::Degrees,
+                Some(Unit::Minutes) => *unit == Unit::Seconds,
+                Some(Unit::Seconds) => false,
+            };
+            if !in_order || (*unit != Unit::Degrees && *value >= Scalar::from_int(60)) {
+                return Err(ParseDmsError);
+            }
+            degrees += match unit {
+                Unit::Degrees => *value,
+                Unit::Minutes => *value / Scalar::from_int(60),
+                Unit::Seconds => *value / Scalar::from_int(3600),
+            };
+            previous = Some(*unit);
+        }
+        let degrees = degrees.round_places(PARSED_DECIMAL_PLACES).normalized();
+        Ok(
+            if self.negative || matches!(self.hemisphere, Some('S' | 'W')) {
+                -degrees
+            } else {
+                degrees
+            },
+        )
+    }
+}
+
+/// Parses a latitude and longitude in degree-minute-second notation
+///
+/// Accepts prime symbols or ASCII quotes for minutes and seconds, decimal fractions on
+/// any component, and hemisphere letters before or after each angle. If hemispheres are
+/// given, they determine which angle is the latitude; otherwise the latitude comes first.
+///
+/// Returns `(latitude, longitude)` in degrees, rounded to 7 decimal places.
+pub fn parse(s: &str) -> Result<(Scalar, Scalar), ParseDmsError> {
+    let mut angles: Vec<Angle> = vec![];
+    let mut current = Angle::default();
+    let mut chars = s.trim().chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '0'..='9' | '.' => {
+                let mut number = String::from(c);
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+                    number.push(c);
+                    chars.next();
+                }
+                let value: Scalar = number.parse().map_err(|_| ParseDmsError)?;
+                while chars.peek().is_some_and(|c| *c == ' ') {
+                    chars.next();
+                }
+                let symbol = match chars.peek() {
+                    Some('°' | 'º' | 'd') => Some(Unit::Degrees),
+                    Some('′' | '’' | '\'' | 'm') => Some(Unit::Minutes),
+                    Some('″' | '"' | '”' | 's') => Some(Unit::Seconds),
+                    _ => None,
+                };
+                let unit = match symbol {
+                    Some(unit) => {
+                        let apostrophe = chars.next() == Some('\'');
+                        // Two apostrophes are commonly used instead of a double quote
+                        if apostrophe && chars.next_if_eq(&'\'').is_some() {
+                            Unit::Seconds
+                        } else {
+                            unit
+                        }
+                    }
+                    // Unitless components follow on from the previous one
+                    None => match current.components.last() {
+                        None => Unit::Degrees,
+                        Some((_, Unit::Degrees)) => Unit::Minutes,
+                        Some(_) => Unit::Seconds,
+                    },
+                };
+                if unit == Unit::Degrees && !current.components.is_empty() {
+                    angles.push(std::mem::take(&mut current));
+                }
+                current.components.push((value, unit));
+            }
+            '-' if current.components.is_empty() => current.negative = true,
+            'N' | 'S' | 'E' | 'W' => {
+                if current.components.is_empty() {
+                    if current.hemisphere.replace(c).is_some() {
+                        return Err(ParseDmsError);
+                    }
+                } else if current.hemisphere.is_none() {
+                    current.hemisphere = Some(c);
+                    angles.push(std::mem::take(&mut current));
+                } else {
+                    // Prefixed hemisphere of the next angle
+                    angles.push(std::mem::take(&mut current));
+                    current.hemisphere = Some(c);
+                }
+            }
+            ',' | ';' if !current.components.is_empty() => {
+                angles.push(std::mem::take(&mut current));
+            }
+            ' ' | '\t' | ',' | ';' => {}
+            _ => return Err(ParseDmsError),
+        }
+    }
+    if !current.components.is_empty() {
+        angles.push(current);
+    } else if current.hemisphere.is_some() || current.negative {
+        return Err(ParseDmsError);
+    }
+
+    let [first, second] = <[Angle; 2]>::try_from(angles).map_err(|_| ParseDmsError)?;
+    let is_lon = |angle: &Angle| matches!(angle.hemisphere, Some('E' | 'W'));
+    let is_lat = |angle: &Angle| matches!(angle.hemisphere, Some('N' | 'S'));
+    let (lat, lon) = if is_lon(&first) || is_lat(&second) {
+        (second, first)
+    } else {
+        (first, second)
+    };
+    if is_lon(&lat) || is_lat(&lon) {
+        return Err(ParseDmsError);
+    }
+
+    let (lat, lon) = (lat.degrees()?, lon.degrees()?);
+    if lat.abs() > Scalar::from_int(90) || lon.abs() > Scalar::from_int(180) {
+        return Err(ParseDmsError);
+    }
+    Ok((lat, lon))
+}
+
+impl Node {
+    /// Formats the node's coordinates like `48°51′24″N 2°21′08″E`
+    ///
+    /// `precision` is the number of decimal places for seconds.
+    pub fn to_dms(&self, precision: u32) -> String {
+        format!(
+            "{} {}",
+            format_lat(self.lat, precision),
+            format_lon(self.lon, precision)
+        )
+    }
+}
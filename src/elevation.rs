@@ -0,0 +1,108 @@
+//! Elevation above sea level
+//!
+//! <https://wiki.openstreetmap.org/wiki/Key:ele>
+
+use std::fmt;
+use std::str::FromStr;
+
+use fnv::FnvHashMap as HashMap;
+
+use crate::locations::NodeLocation;
+use crate::scalar::{Scalar, ScalarExt};
+use crate::{Element, Node, TagString};
+
+/// Height above mean sea level
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "decimal", derive(Eq, Ord, Hash))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Elevation {
+    pub meters: Scalar,
+}
+
+impl Elevation {
+    pub fn from_meters(meters: Scalar) -> Self {
+        Self { meters }
+    }
+
+    pub fn from_feet(feet: Scalar) -> Self {
+        // International foot is defined as exactly 0.3048 meters
+        Self {
+            meters: feet * Scalar::with_scale(3048, 4),
+        }
+    }
+
+    pub fn feet(&self) -> Scalar {
+        self.meters / Scalar::with_scale(3048, 4)
+    }
+}
+
+/// Error returned when parsing an [Elevation] fails
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseElevationError;
+
+impl fmt::Display for ParseElevationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid elevation")
+    }
+}
+
+impl std::error::Error for ParseElevationError {}
+
+impl FromStr for Elevation {
+    type Err = ParseElevationError;
+
+    /// Parses a value of the `ele` key
+    ///
+    /// Values are in meters unless suffixed by `ft` or `'`, and may use `,` as the decimal separator.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (number, feet) = if let Some(number) = s.strip_suffix("ft").or(s.strip_suffix('\'')) {
+            (number, true)
+        } else {
+            (s.strip_suffix('m').unwrap_or(s), false)
+        };
+        let number = number.trim().replace(',', ".");
+        let value = Scalar::parse_decimal(&number).ok_or(ParseElevationError)?;
+        Ok(if feet {
+            Self::from_feet(value)
+        } else {
+            Self::from_meters(value)
+        })
+    }
+}
+
+impl fmt::Display for Elevation {
+    /// Formats in meters without a unit, as expected by the `ele` key
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.meters.normalized())
+    }
+}
+
+pub fn elevation(tags: &HashMap<TagString, TagString>) -> Option<Elevation> {
+    tags.get("ele")?.parse().ok()
+}
+
+impl Element {
+    /// See [elevation]
+    pub fn elevation(&self) -> Option<Elevation> {
+        elevation(self.tags())
+    }
+}
+
+/// [NodeLocation] with the node's elevation, if tagged
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "decimal", derive(Eq, Hash))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ElevatedLocation {
+    pub location: NodeLocation,
+    pub elevation: Option<Elevation>,
+}
+
+impl From<&Node> for ElevatedLocation {
+    fn from(node: &Node) -> Self {
+        Self {
+            location: node.into(),
+            elevation: elevation(&node.tags),
+        }
+    }
+}
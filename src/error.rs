@@ -0,0 +1,239 @@
+//! Error type shared by the whole crate
+//!
+//! Modules keep their own specific errors, e.g. [ParseDateError] for [std::str::FromStr],
+//! and each converts into [Error] so that `?` works across modules.
+
+use std::fmt;
+use std::io;
+
+use crate::date::ParseDateError;
+use crate::dms::ParseDmsError;
+use crate::elevation::ParseElevationError;
+use crate::geohash::DecodeGeohashError;
+use crate::geom::MissingNode;
+use crate::lanes::LaneError;
+use crate::multipolygon::AssemblyError;
+use crate::pipeline::PipelineError;
+use crate::validate::ValidationError;
+use crate::{ElementId, Id, MemberType};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Element an error relates to
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct ElementContext {
+    pub ty: MemberType,
+    pub id: Id,
+}
+
+impl fmt::Display for ElementContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.ty, self.id.0)
+    }
+}
+
+impl From<ElementId> for ElementContext {
+    fn from(id: ElementId) -> Self {
+        ElementContext {
+            ty: id.member_type(),
+            id: id.id(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// Input is malformed
+    Decode {
+        /// Position in the input in bytes, if known
+        offset: Option<u64>,
+        /// Line of the input, counted from 1, if known for text formats
+        line: Option<u64>,
+        /// Element being decoded, if known
+        element: Option<ElementContext>,
+        message: String,
+    },
+    /// Value could not be parsed, e.g. a date or an elevation
+    Parse(Box<dyn std::error::Error + Send + Sync>),
+    /// Data is well-formed but breaks a rule, e.g. a way with a single node
+    Validation {
+        element: Option<ElementContext>,
+        message: String,
+    },
+    /// Change cannot be applied to the current state of the data
+    Conflict {
+        element: ElementContext,
+        message: String,
+    },
+}
+
+impl Error {
+    pub fn decode(offset: Option<u64>, message: impl Into<String>) -> Self {
+        Error::Decode {
+            offset,
+            line: None,
+            element: None,
+            message: message.into(),
+        }
+    }
+
+    /// Attaches the line of the input if the error is a [Error::Decode] without one
+    pub fn at_line(mut self, line_number: u64) -> Self {
+        if let Error::Decode {
+            line: line @ None, ..
+        } = &mut self
+        {
+            *line = Some(line_number);
+        }
+        self
+    }
+
+    /// Attaches the element being processed if the error does not name one already
+    pub fn with_element(mut self, ty: MemberType, id: Id) -> Self {
+        match &mut self {
+            Error::Decode { element, .. } | Error::Validation { element, .. }
+                if element.is_none() =>
+            {
+                *element = Some(ElementContext { ty, id });
+            }
+            _ => {}
+        }
+        self
+    }
+
+    /// Element the error relates to, if known
+    pub fn element(&self) -> Option<&ElementContext> {
+        match self {
+            Error::Decode { element, .. } | Error::Validation { element, .. } => element.as_ref(),
+            Error::Conflict { element, .. } => Some(element),
+            Error::Io(_) | Error::Parse(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "i/o error: {err}"),
+            Error::Decode {
+                offset,
+                line,
+                element,
+                message,
+            } => {
+                write!(f, "decoding failed")?;
+                if let Some(line) = line {
+                    write!(f, " on line {line}")?;
+                }
+                if let Some(offset) = offset {
+                    write!(f, " at byte {offset}")?;
+                }
+                if let Some(element) = element {
+                    write!(f, " in {element}")?;
+                }
+                write!(f, ": {message}")
+            }
+            Error::Parse(err) => write!(f, "{err}"),
+            Error::Validation { element, message } => match element {
+                Some(element) => write!(f, "{element} is invalid: {message}"),
+                None => write!(f, "invalid data: {message}"),
+            },
+            Error::Conflict { element, message } => write!(f, "conflict on {element}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Parse(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<ParseDateError> for Error {
+    fn from(err: ParseDateError) -> Self {
+        Error::Parse(Box::new(err))
+    }
+}
+
+impl From<ParseDmsError> for Error {
+    fn from(err: ParseDmsError) -> Self {
+        Error::Parse(Box::new(err))
+    }
+}
+
+impl From<ParseElevationError> for Error {
+    fn from(err: ParseElevationError) -> Self {
+        Error::Parse(Box::new(err))
+    }
+}
+
+impl From<DecodeGeohashError> for Error {
+    fn from(err: DecodeGeohashError) -> Self {
+        Error::Parse(Box::new(err))
+    }
+}
+
+impl From<LaneError> for Error {
+    fn from(err: LaneError) -> Self {
+        Error::Validation {
+            element: None,
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<MissingNode> for Error {
+    fn from(err: MissingNode) -> Self {
+        Error::Validation {
+            element: Some(ElementContext {
+                ty: MemberType::Way,
+                id: err.way,
+            }),
+            message: format!("node {} at position {} is missing", err.node.0, err.index),
+        }
+    }
+}
+
+impl From<AssemblyError> for Error {
+    fn from(err: AssemblyError) -> Self {
+        Error::Validation {
+            element: None,
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<ValidationError> for Error {
+    fn from(err: ValidationError) -> Self {
+        Error::Validation {
+            element: None,
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<std::convert::Infallible> for Error {
+    fn from(never: std::convert::Infallible) -> Self {
+        match never {}
+    }
+}
+
+impl<S: Into<Error>, K: Into<Error>> From<PipelineError<S, K>> for Error {
+    fn from(err: PipelineError<S, K>) -> Self {
+        match err {
+            PipelineError::Source(err) => err.into(),
+            PipelineError::Sink(err) => err.into(),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Tiles to render again after applying a diff, as
+//! [osm2pgsql](https://osm2pgsql.org/doc/manual.html#expire) computes them
+//!
+//! Geometry is expired both where it was, taken from an [ElementStore] holding the state before
+//! the change, and where it is after the change. Ways are expired along their segments rather
+//! than over their whole box, so that a long diagonal road does not expire a large square.
+
+use std::ops::RangeInclusive;
+
+use fnv::{FnvHashMap as HashMap, FnvHashSet as HashSet};
+
+use crate::bbox::Bbox;
+use crate::change::{Action, OsmChange};
+use crate::geom::LatLon;
+use crate::store::ElementStore;
+use crate::tile::{Tile, MAX_ZOOM};
+use crate::{Element, Id, Member, MemberType, Node, Relation, Way};
+
+/// Tiles at the zooms of `zooms` touched by the changes, before or after them
+///
+/// Besides the changed elements, this covers ways whose nodes moved and multipolygons and
+/// other relations with such a way or a changed way as a member. `store` holds the elements
+/// before the change, e.g. to be updated with [ElementStore::apply_change] afterwards, and
+/// elements missing from it are only expired where they are after the change.
+pub fn tiles_affected(
+    change: &OsmChange,
+    store: &ElementStore,
+    zooms: RangeInclusive<u8>,
+) -> HashSet<Tile> {
+    let max_zoom = (*zooms.end()).min(MAX_ZOOM);
+    let mut expiry = Expiry {
+        store,
+        moved: HashMap::default(),
+        tiles: HashSet::default(),
+        zoom: max_zoom,
+    };
+    for change in &change.changes {
+        if let (Element::Node(node), false) = (&change.element, change.action == Action::Delete) {
+            expiry.moved.insert(node.id, node.lat_lon());
+        }
+    }
+
+    let mut changed_ways = HashSet::default();
+    for change in &change.changes {
+        let deleted = change.action == Action::Delete;
+        match &change.element {
+            Element::Node(node) => {
+                expiry.point(store.get_node(node.id).map(Node::lat_lon));
+                if !deleted {
+                    expiry.point(Some(node.lat_lon()));
+                }
+            }
+            Element::Way(way) => {
+                changed_ways.insert(way.id);
+                expiry.way_before(store.get_way(way.id));
+                if !deleted {
+                    expiry.way_after(way);
+                }
+            }
+            Element::Relation(relation) => {
+                expiry.relation(store.get_relation(relation.id));
+                if !deleted {
+                    expiry.relation(Some(relation));
+                }
+            }
+        }
+    }
+
+    // Ways that keep their nodes but not their shape, and the relations built from them
+    let touched: HashSet<Id> = store
+        .ways()
+        .filter(|way| {
+            !changed_ways.contains(&way.id)
+                && way.refs.iter().any(|id| expiry.moved.contains_key(id))
+        })
+        .map(|way| way.id)
+        .collect();
+    for way in touched.iter().filter_map(|&id| store.get_way(id)) {
+        expiry.way_before(Some(way));
+        expiry.way_after(way);
+    }
+    for relation in store.relations() {
+        let has_way = |member: &Member| {
+            member.ty == MemberType::Way
+                && (touched.contains(&member.id) || changed_ways.contains(&member.id))
+        };
+        if relation.members.iter().any(has_way) {
+            expiry.relation(Some(relation));
+        }
+    }
+
+    let mut tiles = HashSet::default();
+    for tile in expiry.tiles {
+        for zoom in zooms.clone().filter(|&zoom| zoom <= max_zoom) {
+            let shift = max_zoom - zoom;
+            tiles.insert(Tile {
+                z: zoom,
+                x: tile.x >> shift,
+                y: tile.y >> shift,
+            });
+        }
+    }
+    tiles
+}
+
+/// Tiles at the deepest zoom, expired so far
+struct Expiry<'a> {
+    store: &'a ElementStore,
+    /// Locations of nodes after the change
+    moved: HashMap<Id, LatLon>,
+    tiles: HashSet<Tile>,
+    zoom: u8,
+}
+
+impl Expiry<'_> {
+    fn point(&mut self, point: Option<LatLon>) {
+        if let Some((lat, lon)) = point {
+            self.tiles.insert(Tile::from_lat_lon(lat, lon, self.zoom));
+        }
+    }
+
+    /// Tiles along a line, skipping missing nodes
+    fn line(&mut self, points: impl Iterator<Item = Option<LatLon>>) {
+        let points: Vec<_> = points.flatten().collect();
+        if let [point] = points[..] {
+            self.point(Some(point));
+        }
+        for pair in points.windows(2) {
+            if let Some(bbox) = Bbox::of_points(pair.iter().copied()) {
+                self.tiles.extend(Tile::covering(&bbox, self.zoom));
+            }
+        }
+    }
+
+    fn way_before(&mut self, way: Option<&Way>) {
+        if let Some(way) = way {
+            let store = self.store;
+            self.line(
+                way.refs
+                    .iter()
+                    .map(|&id| store.get_node(id).map(Node::lat_lon)),
+            );
+        }
+    }
+
+    fn way_after(&mut self, way: &Way) {
+        let points: Vec<_> = way
+            .refs
+            .iter()
+            .map(|id| {
+                self.moved
+                    .get(id)
+                    .copied()
+                    .or_else(|| self.store.get_node(*id).map(Node::lat_lon))
+            })
+            .collect();
+        self.line(points.into_iter());
+    }
+
+    /// Node and way members of a relation, where they are before and after the change
+    fn relation(&mut self, relation: Option<&Relation>) {
+        let Some(relation) = relation else {
+            return;
+        };
+        for member in &relation.members {
+            match member.ty {
+                MemberType::Node => {
+                    self.point(self.store.get_node(member.id).map(Node::lat_lon));
+                    self.point(self.moved.get(&member.id).copied());
+                }
+                MemberType::Way => {
+                    let way = self.store.get_way(member.id);
+                    self.way_before(way);
+                    if let Some(way) = way {
+                        self.way_after(way);
+                    }
+                }
+                MemberType::Relation => {}
+            }
+        }
+    }
+}
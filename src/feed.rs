@@ -0,0 +1,473 @@
+//! Decoding of files that arrive in chunks, e.g. HTTP response bodies
+//!
+//! Readers pull their input from [std::io::Read], which blocks a server until the whole
+//! response arrived, or forces it to buffer the response in a temporary file first. A
+//! [Decode] is pushed bytes as they arrive instead, and decodes each element as soon as all of
+//! it was pushed, so it works with any HTTP client or runtime. [xml::Decoder] reads OSM XML,
+//! e.g. API and Overpass responses, [change::Decoder] reads osmChange, e.g. replication diffs,
+//! [pbf::Decoder] reads PBF, and with the `json` feature, [json::Decoder] reads OSM JSON.
+//!
+//! With the `stream` feature, [decode] turns a [futures_core::Stream] of chunks, such as the
+//! body of a response, into a stream of elements. With the `tokio` feature, [read] does the
+//! same for a [tokio::io::AsyncRead], e.g. a socket. Compressed input, such as `.osc.gz`
+//! diffs, has to be decompressed before it is pushed.
+//!
+//! [xml::Decoder]: crate::xml::Decoder
+//! [change::Decoder]: crate::change::Decoder
+//! [pbf::Decoder]: crate::pbf::Decoder
+//! [json::Decoder]: crate::json::Decoder
+
+use std::io::{self, BufRead, Read};
+
+#[cfg(any(feature = "stream", feature = "tokio"))]
+use crate::error::Error;
+use crate::error::Result;
+
+/// Decoder of items from input pushed in chunks
+pub trait Decode {
+    type Item;
+
+    /// Appends the next chunk of the input
+    fn push(&mut self, data: &[u8]);
+
+    /// Marks the end of the input, so that what is left of it is decoded
+    fn end(&mut self);
+
+    /// Decodes the next item, [None] if more input is needed, or after [Decode::end] once the
+    /// input is done
+    ///
+    /// After an error, the rest of the input is not decoded.
+    fn decode(&mut self) -> Option<Result<Self::Item>>;
+}
+
+/// Input pushed so far, which readers consume from the front
+#[derive(Debug, Default)]
+pub(crate) struct Buffer {
+    data: Vec<u8>,
+    position: usize,
+    /// Bytes removed from the front of `data`
+    removed: u64,
+}
+
+impl Buffer {
+    pub fn push(&mut self, data: &[u8]) {
+        // Consumed bytes are removed once they make up half of the buffer, so that removing
+        // them takes linear time overall
+        if self.position > 0 && self.position >= self.data.len() / 2 {
+            self.data.drain(..self.position);
+            self.removed += self.position as u64;
+            self.position = 0;
+        }
+        self.data.extend_from_slice(data);
+    }
+
+    /// Bytes consumed so far
+    pub fn consumed(&self) -> u64 {
+        self.removed + self.position as u64
+    }
+
+    /// Unconsumed bytes from `offset`, which is counted from the start of the input
+    pub fn unconsumed_after(&self, offset: u64) -> &[u8] {
+        let start = (offset.max(self.consumed()) - self.removed) as usize;
+        &self.data[start.min(self.data.len())..]
+    }
+}
+
+impl Read for Buffer {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl BufRead for Buffer {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(&self.data[self.position..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.position = (self.position + amt).min(self.data.len());
+    }
+}
+
+/// Decodes the chunks of `input` with `decoder`
+///
+/// Errors of the input end the stream after they are yielded.
+#[cfg(feature = "stream")]
+pub fn decode<S, D>(input: S, decoder: D) -> Decoded<S, D> {
+    Decoded {
+        input,
+        decoder,
+        ended: false,
+        failed: false,
+    }
+}
+
+/// Stream of the items decoded from a stream of chunks, see [decode]
+#[cfg(feature = "stream")]
+#[derive(Debug)]
+pub struct Decoded<S, D> {
+    input: S,
+    decoder: D,
+    ended: bool,
+    failed: bool,
+}
+
+#[cfg(feature = "stream")]
+impl<S, D> Decoded<S, D> {
+    pub fn decoder(&self) -> &D {
+        &self.decoder
+    }
+
+    pub fn into_inner(self) -> (S, D) {
+        (self.input, self.decoder)
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<S, B, E, D> futures_core::Stream for Decoded<S, D>
+where
+    S: futures_core::Stream<Item = std::result::Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: Into<Error>,
+    D: Decode + Unpin,
+{
+    type Item = Result<D::Item>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        use std::task::Poll;
+
+        let this = self.get_mut();
+        loop {
+            if this.failed {
+                return Poll::Ready(None);
+            }
+            if let Some(item) = this.decoder.decode() {
+                this.failed = item.is_err();
+                return Poll::Ready(Some(item));
+            }
+            if this.ended {
+                return Poll::Ready(None);
+            }
+            match std::pin::Pin::new(&mut this.input).poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => this.decoder.push(chunk.as_ref()),
+                Poll::Ready(Some(Err(err))) => {
+                    this.failed = true;
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+                Poll::Ready(None) => {
+                    this.decoder.end();
+                    this.ended = true;
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Bytes [AsyncReader] reads at a time
+#[cfg(feature = "tokio")]
+const READ_CHUNK: usize = 64 * 1024;
+
+/// Decodes what `input` reads with `decoder`
+///
+/// Errors of the input end the reader after they are returned.
+#[cfg(feature = "tokio")]
+pub fn read<R, D>(input: R, decoder: D) -> AsyncReader<R, D> {
+    AsyncReader {
+        input,
+        decoder,
+        chunk: vec![0; READ_CHUNK].into_boxed_slice(),
+        ended: false,
+        failed: false,
+    }
+}
+
+/// Reader of the items decoded from a [tokio::io::AsyncRead], see [read]
+///
+/// With the `stream` feature, it is also a [futures_core::Stream] of the items.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct AsyncReader<R, D> {
+    input: R,
+    decoder: D,
+    chunk: Box<[u8]>,
+    ended: bool,
+    failed: bool,
+}
+
+#[cfg(feature = "tokio")]
+impl<R, D> AsyncReader<R, D> {
+    pub fn decoder(&self) -> &D {
+        &self.decoder
+    }
+
+    pub fn into_inner(self) -> (R, D) {
+        (self.input, self.decoder)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<R, D> AsyncReader<R, D>
+where
+    R: tokio::io::AsyncRead + Unpin,
+    D: Decode,
+{
+    /// Next item, [None] once the input is done or after an error
+    pub async fn next(&mut self) -> Option<Result<D::Item>> {
+        std::future::poll_fn(|cx| self.poll_item(cx)).await
+    }
+
+    fn poll_item(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Result<D::Item>>> {
+        use std::task::Poll;
+
+        loop {
+            if self.failed {
+                return Poll::Ready(None);
+            }
+            if let Some(item) = self.decoder.decode() {
+                self.failed = item.is_err();
+                return Poll::Ready(Some(item));
+            }
+            if self.ended {
+                return Poll::Ready(None);
+            }
+            let mut buf = tokio::io::ReadBuf::new(&mut self.chunk);
+            match std::pin::Pin::new(&mut self.input).poll_read(cx, &mut buf) {
+                Poll::Ready(Ok(())) => match buf.filled().len() {
+                    0 => {
+                        self.decoder.end();
+                        self.ended = true;
+                    }
+                    len => self.decoder.push(&self.chunk[..len]),
+                },
+                Poll::Ready(Err(err)) => {
+                    self.failed = true;
+                    return Poll::Ready(Some(Err(Error::from(err))));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "tokio", feature = "stream"))]
+impl<R, D> futures_core::Stream for AsyncReader<R, D>
+where
+    R: tokio::io::AsyncRead + Unpin,
+    D: Decode + Unpin,
+{
+    type Item = Result<D::Item>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        self.get_mut().poll_item(cx)
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use std::future::Future;
+    use std::pin::{pin, Pin};
+    use std::task::{Context, Poll, Waker};
+
+    use tokio::io::{AsyncRead, ReadBuf};
+
+    use super::*;
+    #[cfg(feature = "json")]
+    use crate::json;
+    #[cfg(feature = "pbf")]
+    use crate::pbf;
+    use crate::scalar::{Scalar, ScalarExt};
+    use crate::{xml, Element, Id, Node, Relation, Way, WayId};
+
+    /// Input that is pending before each read and then reads a few bytes
+    struct Trickle<'a> {
+        data: &'a [u8],
+        pending: bool,
+    }
+
+    impl AsyncRead for Trickle<'_> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            self.pending = !self.pending;
+            if self.pending {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let len = self.data.len().min(buf.remaining()).min(7);
+            buf.put_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    fn elements() -> Vec<Element> {
+        let node = |id: i64| {
+            Node::builder(Id(id))
+                .lat(Scalar::from_int(id))
+                .lon(Scalar::from_int(-id))
+                .tag("name", format!("node {id}"))
+                .build()
+                .unwrap()
+        };
+        vec![
+            Element::Node(node(1)),
+            Element::Node(node(2)),
+            Element::Way(
+                Way::builder(Id(3))
+                    .nodes([1, 2].map(Id))
+                    .tag("highway", "path")
+                    .build()
+                    .unwrap(),
+            ),
+            Element::Relation(
+                Relation::builder(Id(4))
+                    .member(WayId(3), "outer")
+                    .build()
+                    .unwrap(),
+            ),
+        ]
+    }
+
+    fn read_all<D: Decode<Item = Element>>(data: &[u8], decoder: D) -> Vec<Element> {
+        let mut reader = read(
+            Trickle {
+                data,
+                pending: false,
+            },
+            decoder,
+        );
+        block_on(async {
+            let mut elements = vec![];
+            while let Some(element) = reader.next().await {
+                elements.push(element.unwrap());
+            }
+            elements
+        })
+    }
+
+    #[test]
+    fn read_xml() {
+        let mut writer = xml::Writer::new(vec![]);
+        for element in elements() {
+            writer.write(&element).unwrap();
+        }
+        writer.finish().unwrap();
+        let data = writer.into_inner();
+        assert_eq!(read_all(&data, xml::Decoder::new()), elements());
+    }
+
+    #[test]
+    #[cfg(feature = "pbf")]
+    fn read_pbf() {
+        let mut writer = pbf::Writer::new(vec![]).block_elements(3);
+        for element in elements() {
+            writer.write(&element).unwrap();
+        }
+        writer.finish().unwrap();
+        let data = writer.into_inner();
+        assert_eq!(read_all(&data, pbf::Decoder::new()), elements());
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn read_json() {
+        let mut writer = json::Writer::new(vec![]);
+        for element in elements() {
+            writer.write(&element).unwrap();
+        }
+        writer.finish().unwrap();
+        let data = writer.into_inner();
+        assert_eq!(read_all(&data, json::Decoder::new()), elements());
+    }
+
+    #[test]
+    fn write_xml() {
+        let mut writer = xml::AsyncWriter::new(vec![]);
+        let data = block_on(async {
+            for element in elements() {
+                writer.write(&element).await.unwrap();
+            }
+            writer.finish().await.unwrap();
+            writer.into_inner()
+        });
+        let mut expected = xml::Writer::new(vec![]);
+        for element in elements() {
+            expected.write(&element).unwrap();
+        }
+        expected.finish().unwrap();
+        assert_eq!(data, expected.into_inner());
+    }
+
+    #[test]
+    #[cfg(feature = "pbf")]
+    fn write_pbf() {
+        let mut writer = pbf::AsyncWriter::new(vec![]).block_elements(2);
+        let data = block_on(async {
+            for element in elements() {
+                writer.write(&element).await.unwrap();
+            }
+            writer.finish().await.unwrap();
+            writer.into_inner()
+        });
+        assert_eq!(read_all(&data, pbf::Decoder::new()), elements());
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn write_json() {
+        let mut writer = json::AsyncWriter::new(vec![]);
+        let data = block_on(async {
+            for element in elements() {
+                writer.write(&element).await.unwrap();
+            }
+            writer.finish().await.unwrap();
+            writer.into_inner()
+        });
+        assert_eq!(read_all(&data, json::Decoder::new()), elements());
+    }
+
+    #[test]
+    #[cfg(feature = "pbf")]
+    fn read_truncated() {
+        let mut writer = pbf::Writer::new(vec![]);
+        for element in elements() {
+            writer.write(&element).unwrap();
+        }
+        writer.finish().unwrap();
+        let data = writer.into_inner();
+        let mut reader = read(&data[..data.len() - 1], pbf::Decoder::new());
+        block_on(async {
+            assert!(matches!(
+                reader.next().await,
+                Some(Err(Error::Decode { .. }))
+            ));
+            assert!(reader.next().await.is_none());
+        });
+    }
+}
@@ -0,0 +1,208 @@
+//! Batched downloads of elements by id from the OSM API
+//!
+//! The multi fetch calls, e.g. `/nodes?nodes=1,2,3`, return many elements at once but reject
+//! URLs that are too long. [Batcher] splits a large set of ids into requests that fit, and
+//! [merge] collects the parsed responses into an [ElementStore].
+//!
+//! <https://wiki.openstreetmap.org/wiki/API_v0.6#Multi_fetch:_GET_/api/0.6/[nodes|ways|relations]?#parameters>
+
+use crate::store::ElementStore;
+use crate::{Element, Id, MemberType};
+
+/// Address of the API on the main OSM server
+pub const DEFAULT_API_BASE: &str = "https://api.openstreetmap.org/api/0.6";
+
+/// Ids per request by default
+///
+/// The API does not document a limit on ids, but responses for many more take long enough to
+/// risk timeouts.
+pub const DEFAULT_MAX_IDS: usize = 700;
+
+/// URL length by default, below the 8 KiB that common web servers accept for a request line
+pub const DEFAULT_MAX_URL_LEN: usize = 8000;
+
+/// Splits ids into [Batch]es within limits on ids and URL length
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Batcher {
+    base: String,
+    max_ids: usize,
+    max_url_len: usize,
+}
+
+impl Default for Batcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Batcher {
+    /// Batches for [DEFAULT_API_BASE] with [DEFAULT_MAX_IDS] and [DEFAULT_MAX_URL_LEN]
+    pub fn new() -> Self {
+        Self {
+            base: DEFAULT_API_BASE.to_string(),
+            max_ids: DEFAULT_MAX_IDS,
+            max_url_len: DEFAULT_MAX_URL_LEN,
+        }
+    }
+
+    /// API the URLs are for, e.g. `https://master.apis.dev.openstreetmap.org/api/0.6`
+    pub fn base(mut self, base: &str) -> Self {
+        self.base = base.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Most ids in one batch, at least 1
+    pub fn max_ids(mut self, max_ids: usize) -> Self {
+        self.max_ids = max_ids.max(1);
+        self
+    }
+
+    /// Longest URL of a batch
+    ///
+    /// A batch always holds at least one id, even if its URL is longer than this.
+    pub fn max_url_len(mut self, max_url_len: usize) -> Self {
+        self.max_url_len = max_url_len;
+        self
+    }
+
+    /// Batches of one type, with ids sorted and duplicates removed
+    pub fn batches(&self, ty: &MemberType, ids: impl IntoIterator<Item = Id>) -> Vec<Batch> {
+        let mut ids: Vec<_> = ids.into_iter().collect();
+        ids.sort_unstable();
+        ids.dedup();
+
+        let mut batches = vec![];
+        let mut batch = Batch::new(ty.clone());
+        let empty_len = batch.url_at(&self.base).len();
+        let mut url_len = empty_len;
+        for id in ids {
+            let digits = id.0.to_string().len();
+            if !batch.ids.is_empty() {
+                if batch.ids.len() < self.max_ids && url_len + 1 + digits <= self.max_url_len {
+                    // Separating comma
+                    url_len += 1;
+                } else {
+                    batches.push(std::mem::replace(&mut batch, Batch::new(ty.clone())));
+                    url_len = empty_len;
+                }
+            }
+            url_len += digits;
+            batch.ids.push(id);
+        }
+        if !batch.ids.is_empty() {
+            batches.push(batch);
+        }
+        batches
+    }
+
+    /// Batches of nodes, then ways, then relations
+    pub fn batches_of<'a>(
+        &self,
+        objects: impl IntoIterator<Item = (&'a MemberType, Id)>,
+    ) -> Vec<Batch> {
+        let (mut nodes, mut ways, mut relations) = (vec![], vec![], vec![]);
+        for (ty, id) in objects {
+            match ty {
+                MemberType::Node => nodes.push(id),
+                MemberType::Way => ways.push(id),
+                MemberType::Relation => relations.push(id),
+            }
+        }
+        let mut batches = self.batches(&MemberType::Node, nodes);
+        batches.extend(self.batches(&MemberType::Way, ways));
+        batches.extend(self.batches(&MemberType::Relation, relations));
+        batches
+    }
+
+    /// URL of a batch on the configured API
+    pub fn url(&self, batch: &Batch) -> String {
+        batch.url_at(&self.base)
+    }
+}
+
+/// Ids of one type fetched with a single request
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Batch {
+    pub ty: MemberType,
+    pub ids: Vec<Id>,
+}
+
+impl Batch {
+    pub fn new(ty: MemberType) -> Self {
+        Self { ty, ids: vec![] }
+    }
+
+    /// Path and query relative to the API, e.g. `/nodes?nodes=1,2,3`
+    pub fn path(&self) -> String {
+        let plural = match self.ty {
+            MemberType::Node => "nodes",
+            MemberType::Way => "ways",
+            MemberType::Relation => "relations",
+        };
+        let ids = self
+            .ids
+            .iter()
+            .map(|id| id.0.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("/{plural}?{plural}={ids}")
+    }
+
+    /// URL of the batch on the API at `base`, e.g. [DEFAULT_API_BASE]
+    pub fn url_at(&self, base: &str) -> String {
+        format!("{}{}", base.trim_end_matches('/'), self.path())
+    }
+
+    /// Halves the batch, or returns [None] for a single id
+    ///
+    /// The API answers `404 Not Found` for the whole batch if any element never existed, so
+    /// retrying the halves narrows down the missing ids.
+    pub fn split(mut self) -> Option<(Batch, Batch)> {
+        if self.ids.len() < 2 {
+            return None;
+        }
+        let second = self.ids.split_off(self.ids.len() / 2);
+        let ty = self.ty.clone();
+        Some((self, Batch { ty, ids: second }))
+    }
+
+    /// Ids of the batch that are not in the store, e.g. after merging its response
+    pub fn missing(&self, store: &ElementStore) -> Vec<Id> {
+        self.ids
+            .iter()
+            .copied()
+            .filter(|id| match self.ty {
+                MemberType::Node => store.get_node(*id).is_none(),
+                MemberType::Way => store.get_way(*id).is_none(),
+                MemberType::Relation => store.get_relation(*id).is_none(),
+            })
+            .collect()
+    }
+}
+
+/// Inserts elements of a response into the store, returning how many were new or newer
+///
+/// An element already in the store with the same or a later version is kept, so responses
+/// can be merged in any order.
+pub fn merge(store: &mut ElementStore, elements: impl IntoIterator<Item = Element>) -> usize {
+    let mut merged = 0;
+    for element in elements {
+        let id = element.id();
+        let existing = match element {
+            Element::Node(_) => store.get_node(id).map(|node| node.info.as_ref()),
+            Element::Way(_) => store.get_way(id).map(|way| way.info.as_ref()),
+            Element::Relation(_) => store
+                .get_relation(id)
+                .map(|relation| relation.info.as_ref()),
+        };
+        let version = |info: Option<&crate::Info>| info.map(|info| info.version);
+        if let Some(existing) = existing {
+            if version(existing) >= version(element.info()) {
+                continue;
+            }
+        }
+        store.insert(element);
+        merged += 1;
+    }
+    merged
+}
@@ -0,0 +1,245 @@
+//! C ABI for consuming elements from C and C++
+//!
+//! Elements are passed across the boundary as opaque `OsmElement*` handles owned by the caller,
+//! which must be released with [osm_element_free]. Strings are not NUL-terminated and are
+//! passed as an [OsmStr] pointing into the element, valid until the element is modified or freed.
+//!
+//! Only elements are exposed so far; the crate does not yet have stores or readers to wrap.
+//!
+//! To build a shared library, run `cargo rustc --release --features ffi --crate-type cdylib`.
+//!
+//! # Safety
+//!
+//! Unless stated otherwise, every pointer argument must be non-null, properly aligned, and
+//! point to a live value of the expected type. Element handles must originate from this module.
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::{c_int, c_void};
+
+use crate::scalar::{Scalar, ScalarExt};
+use crate::{Element, Id, MemberType, Node, TagString};
+
+/// Borrowed UTF-8 string
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct OsmStr {
+    pub ptr: *const u8,
+    pub len: usize,
+}
+
+impl OsmStr {
+    const EMPTY: OsmStr = OsmStr {
+        ptr: std::ptr::null(),
+        len: 0,
+    };
+
+    fn new(s: &str) -> Self {
+        Self {
+            ptr: s.as_ptr(),
+            len: s.len(),
+        }
+    }
+
+    unsafe fn as_str<'a>(self) -> Option<&'a str> {
+        if self.ptr.is_null() {
+            return None;
+        }
+        std::str::from_utf8(std::slice::from_raw_parts(self.ptr, self.len)).ok()
+    }
+}
+
+pub const OSM_NODE: c_int = 0;
+pub const OSM_WAY: c_int = 1;
+pub const OSM_RELATION: c_int = 2;
+
+fn member_type_to_c(ty: &MemberType) -> c_int {
+    match ty {
+        MemberType::Node => OSM_NODE,
+        MemberType::Way => OSM_WAY,
+        MemberType::Relation => OSM_RELATION,
+    }
+}
+
+/// Member of a relation as returned by [osm_relation_member]
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct OsmMember {
+    pub id: i64,
+    /// One of `OSM_NODE`, `OSM_WAY`, or `OSM_RELATION`
+    pub ty: c_int,
+    /// Empty with a null pointer if the member has no role
+    pub role: OsmStr,
+}
+
+/// Creates an untagged node, returning null if a coordinate is not finite
+#[no_mangle]
+pub extern "C" fn osm_node_new(id: i64, lat: f64, lon: f64) -> *mut Element {
+    let (Some(lat), Some(lon)) = (Scalar::try_from_f64(lat), Scalar::try_from_f64(lon)) else {
+        return std::ptr::null_mut();
+    };
+    Box::into_raw(Box::new(Element::Node(Node {
+        id: Id(id),
+        tags: Default::default(),
+        info: None,
+        lat: lat.round_places(7).normalized(),
+        lon: lon.round_places(7).normalized(),
+    })))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn osm_element_clone(element: *const Element) -> *mut Element {
+    Box::into_raw(Box::new((*element).clone()))
+}
+
+/// Releases an element, doing nothing if it is null
+#[no_mangle]
+pub unsafe extern "C" fn osm_element_free(element: *mut Element) {
+    if !element.is_null() {
+        drop(Box::from_raw(element));
+    }
+}
+
+/// One of `OSM_NODE`, `OSM_WAY`, or `OSM_RELATION`
+#[no_mangle]
+pub unsafe extern "C" fn osm_element_type(element: *const Element) -> c_int {
+    match &*element {
+        Element::Node(_) => OSM_NODE,
+        Element::Way(_) => OSM_WAY,
+        Element::Relation(_) => OSM_RELATION,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn osm_element_id(element: *const Element) -> i64 {
+    (*element).id().0
+}
+
+/// Version of the element, or -1 if it has no [crate::Info]
+#[no_mangle]
+pub unsafe extern "C" fn osm_element_version(element: *const Element) -> i32 {
+    (*element).info().map_or(-1, |info| info.version)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn osm_element_tag_count(element: *const Element) -> usize {
+    (*element).tags().len()
+}
+
+/// Looks up the value of a tag, writing it to `value`
+///
+/// Returns false and leaves `value` untouched if the tag is absent or `key` is not valid UTF-8.
+#[no_mangle]
+pub unsafe extern "C" fn osm_element_tag(
+    element: *const Element,
+    key: OsmStr,
+    value: *mut OsmStr,
+) -> bool {
+    match key.as_str().and_then(|key| (*element).tags().get(key)) {
+        Some(found) => {
+            *value = OsmStr::new(found);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Calls `callback` with each tag of the element in unspecified order
+#[no_mangle]
+pub unsafe extern "C" fn osm_element_for_each_tag(
+    element: *const Element,
+    callback: extern "C" fn(key: OsmStr, value: OsmStr, user_data: *mut c_void),
+    user_data: *mut c_void,
+) {
+    for (key, value) in (*element).tags() {
+        callback(OsmStr::new(key), OsmStr::new(value), user_data);
+    }
+}
+
+/// Sets a tag, returning false if `key` or `value` is not valid UTF-8
+#[no_mangle]
+pub unsafe extern "C" fn osm_element_set_tag(
+    element: *mut Element,
+    key: OsmStr,
+    value: OsmStr,
+) -> bool {
+    match (key.as_str(), value.as_str()) {
+        (Some(key), Some(value)) => {
+            (*element)
+                .tags_mut()
+                .insert(TagString::from_ref(key), TagString::from_ref(value));
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Latitude of a node, or NaN for other elements
+#[no_mangle]
+pub unsafe extern "C" fn osm_node_lat(element: *const Element) -> f64 {
+    (*element)
+        .as_node()
+        .map(|node| node.lat.as_f64())
+        .unwrap_or(f64::NAN)
+}
+
+/// Longitude of a node, or NaN for other elements
+#[no_mangle]
+pub unsafe extern "C" fn osm_node_lon(element: *const Element) -> f64 {
+    (*element)
+        .as_node()
+        .map(|node| node.lon.as_f64())
+        .unwrap_or(f64::NAN)
+}
+
+/// Number of node references of a way, or 0 for other elements
+#[no_mangle]
+pub unsafe extern "C" fn osm_way_ref_count(element: *const Element) -> usize {
+    (*element).as_way().map_or(0, |way| way.refs.len())
+}
+
+/// Node reference at `index`, writing it to `id`
+///
+/// Returns false if the element is not a way or `index` is out of bounds.
+#[no_mangle]
+pub unsafe extern "C" fn osm_way_ref(element: *const Element, index: usize, id: *mut i64) -> bool {
+    match (*element).as_way().and_then(|way| way.refs.get(index)) {
+        Some(node) => {
+            *id = node.0;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Number of members of a relation, or 0 for other elements
+#[no_mangle]
+pub unsafe extern "C" fn osm_relation_member_count(element: *const Element) -> usize {
+    (*element)
+        .as_relation()
+        .map_or(0, |relation| relation.members.len())
+}
+
+/// Member at `index`, writing it to `member`
+///
+/// Returns false if the element is not a relation or `index` is out of bounds.
+#[no_mangle]
+pub unsafe extern "C" fn osm_relation_member(
+    element: *const Element,
+    index: usize,
+    member: *mut OsmMember,
+) -> bool {
+    match (*element)
+        .as_relation()
+        .and_then(|relation| relation.members.get(index))
+    {
+        Some(found) => {
+            *member = OsmMember {
+                id: found.id.0,
+                ty: member_type_to_c(&found.ty),
+                role: found.role.as_deref().map_or(OsmStr::EMPTY, OsmStr::new),
+            };
+            true
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,635 @@
+//! Tag filters in the style of
+//! [osmium tags-filter](https://docs.osmcode.org/osmium/latest/osmium-tags-filter.html)
+//!
+//! A [Filter] is built from conditions on element types and tags, combined with
+//! [Filter::and], [Filter::or], and `!`. [Filter::parse] reads the expressions of osmium, e.g.
+//! `w/highway=primary,secondary`, which are:
+//!
+//! - An optional list of types followed by `/`, from `n`, `w`, and `r`
+//! - A key, which must be present
+//! - Optionally `=` or `!=` and a comma-separated list of values that the value must (not) be
+//! - Or `~` or `!~` and a [Regex] that the value must (not) match, e.g. `name~^Main `
+//!
+//! See [osmfilter](crate::osmfilter) for the syntax of osmfilter.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::Result;
+use crate::store::ElementStore;
+use crate::{Element, MemberType, TagString, Tags};
+
+/// Error returned when parsing a [Filter] or a [Regex] fails
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ParseFilterError {
+    EmptyKey,
+    /// Type before `/` other than `n`, `w`, or `r`
+    UnknownType(char),
+    /// Regular expression that cannot be compiled, with the reason
+    InvalidRegex {
+        pattern: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ParseFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseFilterError::EmptyKey => write!(f, "empty key in filter"),
+            ParseFilterError::UnknownType(ty) => write!(f, "unknown type {ty:?} in filter"),
+            ParseFilterError::InvalidRegex { pattern, reason } => {
+                write!(f, "invalid regular expression {pattern:?}: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseFilterError {}
+
+impl From<ParseFilterError> for crate::Error {
+    fn from(err: ParseFilterError) -> Self {
+        crate::Error::Parse(Box::new(err))
+    }
+}
+
+/// Condition on the type and tags of an element
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Filter {
+    /// Element of the type
+    Type(MemberType),
+    /// Element with the key
+    Key(TagString),
+    /// Element with the key and value
+    Tag(TagString, TagString),
+    /// Element with the key and a value matching the expression
+    Regex(TagString, Regex),
+    /// Element matching every filter, or any element if there are none
+    And(Vec<Filter>),
+    /// Element matching at least one filter
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    pub fn of_type(ty: MemberType) -> Self {
+        Filter::Type(ty)
+    }
+
+    pub fn key(key: impl Into<TagString>) -> Self {
+        Filter::Key(key.into())
+    }
+
+    pub fn tag(key: impl Into<TagString>, value: impl Into<TagString>) -> Self {
+        Filter::Tag(key.into(), value.into())
+    }
+
+    /// Fails if the pattern is not a valid [Regex]
+    pub fn regex(
+        key: impl Into<TagString>,
+        pattern: &str,
+    ) -> std::result::Result<Self, ParseFilterError> {
+        Ok(Filter::Regex(key.into(), Regex::new(pattern)?))
+    }
+
+    pub fn and(self, other: Filter) -> Self {
+        match self {
+            Filter::And(mut filters) => {
+                filters.push(other);
+                Filter::And(filters)
+            }
+            filter => Filter::And(vec![filter, other]),
+        }
+    }
+
+    pub fn or(self, other: Filter) -> Self {
+        match self {
+            Filter::Or(mut filters) => {
+                filters.push(other);
+                Filter::Or(filters)
+            }
+            filter => Filter::Or(vec![filter, other]),
+        }
+    }
+
+    /// Parses an osmium expression, see the [module](self) for the syntax
+    pub fn parse(s: &str) -> std::result::Result<Self, ParseFilterError> {
+        let (types, condition) = match s.split_once('/') {
+            Some((types, condition)) if !types.contains(['=', '~']) => (Some(types), condition),
+            _ => (None, s),
+        };
+        let op =
+            condition
+                .find(['=', '~'])
+                .map(|index| match condition[..index].strip_suffix('!') {
+                    Some(key) => (key, true, &condition[index..]),
+                    None => (&condition[..index], false, &condition[index..]),
+                });
+        let (key, negated, value) = op.unwrap_or((condition, false, ""));
+        if key.is_empty() {
+            return Err(ParseFilterError::EmptyKey);
+        }
+        let key = TagString::from_ref(key);
+        let mut filter = match value.split_at_checked(1) {
+            Some(("~", pattern)) => Filter::regex(key.clone(), pattern)?,
+            Some((_, values)) => {
+                let mut values = values
+                    .split(',')
+                    .map(|value| Filter::tag(key.clone(), TagString::from_ref(value)));
+                match (values.next(), values.next()) {
+                    (Some(first), None) => first,
+                    (first, second) => {
+                        Filter::Or(first.into_iter().chain(second).chain(values).collect())
+                    }
+                }
+            }
+            None => Filter::key(key.clone()),
+        };
+        if negated {
+            filter = Filter::Key(key).and(!filter);
+        }
+        if let Some(types) = types {
+            let mut types: Vec<_> = types
+                .chars()
+                .map(|ty| match ty {
+                    'n' => Ok(Filter::Type(MemberType::Node)),
+                    'w' => Ok(Filter::Type(MemberType::Way)),
+                    'r' => Ok(Filter::Type(MemberType::Relation)),
+                    other => Err(ParseFilterError::UnknownType(other)),
+                })
+                .collect::<std::result::Result<_, _>>()?;
+            let types = match types.len() {
+                1 => types.remove(0),
+                _ => Filter::Or(types),
+            };
+            filter = types.and(filter);
+        }
+        Ok(filter)
+    }
+
+    pub fn matches(&self, element: &Element) -> bool {
+        self.matches_parts(&element.member_type(), element.tags())
+    }
+
+    fn matches_parts(&self, ty: &MemberType, tags: &Tags) -> bool {
+        match self {
+            Filter::Type(expected) => expected == ty,
+            Filter::Key(key) => tags.contains_key(key),
+            Filter::Tag(key, value) => tags.has(key, value),
+            Filter::Regex(key, regex) => tags.get(key).is_some_and(|value| regex.is_match(value)),
+            Filter::And(filters) => filters.iter().all(|filter| filter.matches_parts(ty, tags)),
+            Filter::Or(filters) => filters.iter().any(|filter| filter.matches_parts(ty, tags)),
+            Filter::Not(filter) => !filter.matches_parts(ty, tags),
+        }
+    }
+}
+
+impl std::ops::Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Self::Output {
+        match self {
+            Filter::Not(filter) => *filter,
+            filter => Filter::Not(Box::new(filter)),
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = ParseFilterError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Elements that match the filter, passing errors through
+pub fn filter_elements<'a, I>(
+    elements: I,
+    filter: &'a Filter,
+) -> impl Iterator<Item = Result<Element>> + 'a
+where
+    I: IntoIterator<Item = Result<Element>>,
+    I::IntoIter: 'a,
+{
+    elements.into_iter().filter(move |element| match element {
+        Ok(element) => filter.matches(element),
+        Err(_) => true,
+    })
+}
+
+impl ElementStore {
+    /// Store with copies of the elements that match the filter
+    ///
+    /// Ways and relations keep references to elements that do not match, so only the matches
+    /// themselves are copied.
+    pub fn filter(&self, filter: &Filter) -> ElementStore {
+        let nodes = self
+            .nodes()
+            .filter(|node| filter.matches_parts(&MemberType::Node, &node.tags))
+            .cloned()
+            .map(Element::Node);
+        let ways = self
+            .ways()
+            .filter(|way| filter.matches_parts(&MemberType::Way, &way.tags))
+            .cloned()
+            .map(Element::Way);
+        let relations = self
+            .relations()
+            .filter(|relation| filter.matches_parts(&MemberType::Relation, &relation.tags))
+            .cloned()
+            .map(Element::Relation);
+        nodes.chain(ways).chain(relations).collect()
+    }
+}
+
+/// Regular expression for matching tag values
+///
+/// Supported are the common subset of regular expression syntaxes: `.`, classes like `[a-z]`
+/// and `[^0-9]`, `\d`, `\w`, and `\s`, anchors `^` and `$`, groups with `|`, and the
+/// quantifiers `*`, `+`, `?`, and `{n,m}`. Other characters match themselves, or are
+/// escaped with a backslash.
+/// Values match if the expression matches any part of them, and matching backtracks, which
+/// is fast for the short values of tags.
+#[derive(Clone)]
+pub struct Regex {
+    pattern: String,
+    alternatives: Vec<Vec<Node>>,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Any,
+    /// Inclusive ranges of characters
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+    Start,
+    End,
+    Group(Vec<Vec<Node>>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> std::result::Result<Self, ParseFilterError> {
+        let error = |reason: &str| ParseFilterError::InvalidRegex {
+            pattern: pattern.to_string(),
+            reason: reason.to_string(),
+        };
+        let mut chars = pattern.chars().peekable();
+        let alternatives = parse_alternatives(&mut chars).map_err(error)?;
+        if chars.next().is_some() {
+            return Err(error("unmatched )"));
+        }
+        Ok(Self {
+            pattern: pattern.to_string(),
+            alternatives,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn is_match(&self, value: &str) -> bool {
+        let chars = value.chars().collect::<Vec<_>>();
+        let group = [Node::Group(self.alternatives.clone())];
+        (0..=chars.len()).any(|start| match_nodes(&group, &chars, start, &mut |_| true))
+    }
+}
+
+impl PartialEq for Regex {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl Eq for Regex {}
+
+impl fmt::Debug for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Regex").field(&self.pattern).finish()
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+/// Sequences separated by `|`, up to the end or a `)`
+fn parse_alternatives(chars: &mut Chars) -> std::result::Result<Vec<Vec<Node>>, &'static str> {
+    let mut alternatives = vec![vec![]];
+    while let Some(&c) = chars.peek() {
+        if c == ')' {
+            break;
+        }
+        chars.next();
+        let node = match c {
+            '|' => {
+                alternatives.push(vec![]);
+                continue;
+            }
+            '(' => {
+                let group = parse_alternatives(chars)?;
+                if chars.next() != Some(')') {
+                    return Err("unmatched (");
+                }
+                Node::Group(group)
+            }
+            '[' => parse_class(chars)?,
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '\\' => escape(chars.next().ok_or("trailing backslash")?),
+            '*' | '+' | '?' | '{' => return Err("quantifier without an expression"),
+            c => Node::Char(c),
+        };
+        let node = parse_quantifier(chars, node)?;
+        alternatives
+            .last_mut()
+            .expect("there is always an alternative")
+            .push(node);
+    }
+    Ok(alternatives)
+}
+
+fn parse_quantifier(chars: &mut Chars, node: Node) -> std::result::Result<Node, &'static str> {
+    let (min, max) = match chars.peek() {
+        Some('*') => (0, None),
+        Some('+') => (1, None),
+        Some('?') => (0, Some(1)),
+        Some('{') => {
+            chars.next();
+            let mut bounds = String::new();
+            loop {
+                match chars.next() {
+                    Some('}') => break,
+                    Some(c) => bounds.push(c),
+                    None => return Err("unmatched {"),
+                }
+            }
+            let number = |s: &str| s.trim().parse::<usize>().map_err(|_| "invalid repetition");
+            let (min, max) = match bounds.split_once(',') {
+                Some((min, "")) => (number(min)?, None),
+                Some((min, max)) => (number(min)?, Some(number(max)?)),
+                None => (number(&bounds)?, Some(number(&bounds)?)),
+            };
+            if max.is_some_and(|max| max < min) {
+                return Err("invalid repetition");
+            }
+            return parse_quantifier(chars, repeat(node, min, max));
+        }
+        _ => return Ok(node),
+    };
+    chars.next();
+    parse_quantifier(chars, repeat(node, min, max))
+}
+
+fn repeat(node: Node, min: usize, max: Option<usize>) -> Node {
+    Node::Repeat {
+        node: Box::new(node),
+        min,
+        max,
+    }
+}
+
+/// Ranges of `\d`, `\w`, and `\s`
+fn escape_ranges(c: char) -> Option<Vec<(char, char)>> {
+    Some(match c.to_ascii_lowercase() {
+        'd' => vec![('0', '9')],
+        'w' => vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')],
+        's' => vec![(' ', ' '), ('\t', '\r')],
+        _ => return None,
+    })
+}
+
+fn escape(c: char) -> Node {
+    match escape_ranges(c) {
+        Some(ranges) => Node::Class {
+            ranges,
+            negated: c.is_ascii_uppercase(),
+        },
+        None => Node::Char(c),
+    }
+}
+
+/// Class after its `[`
+fn parse_class(chars: &mut Chars) -> std::result::Result<Node, &'static str> {
+    let negated = chars.next_if_eq(&'^').is_some();
+    let mut ranges = vec![];
+    let mut first = true;
+    loop {
+        let c = match chars.next() {
+            Some(']') if !first => break,
+            Some('\\') => {
+                let c = chars.next().ok_or("trailing backslash")?;
+                match escape_ranges(c) {
+                    Some(class) if c.is_ascii_lowercase() => {
+                        ranges.extend(class);
+                        first = false;
+                        continue;
+                    }
+                    Some(_) => return Err("negated escape in class"),
+                    None => c,
+                }
+            }
+            Some(c) => c,
+            None => return Err("unmatched ["),
+        };
+        first = false;
+        let end = match (chars.peek().copied(), chars.clone().nth(1)) {
+            (Some('-'), Some(end)) if end != ']' => {
+                chars.next();
+                chars.next();
+                end
+            }
+            _ => c,
+        };
+        if end < c {
+            return Err("invalid range in class");
+        }
+        ranges.push((c, end));
+    }
+    Ok(Node::Class { ranges, negated })
+}
+
+/// Whether `nodes` match `text` from `pos` such that `then` accepts the end of the match
+fn match_nodes(
+    nodes: &[Node],
+    text: &[char],
+    pos: usize,
+    then: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    let Some((node, rest)) = nodes.split_first() else {
+        return then(pos);
+    };
+    match node {
+        Node::Start => pos == 0 && match_nodes(rest, text, pos, then),
+        Node::End => pos == text.len() && match_nodes(rest, text, pos, then),
+        Node::Group(alternatives) => alternatives.iter().any(|alternative| {
+            match_nodes(alternative, text, pos, &mut |end| {
+                match_nodes(rest, text, end, then)
+            })
+        }),
+        Node::Repeat { node, min, max } => {
+            match_repeat((node, *min, *max), rest, text, pos, 0, then)
+        }
+        Node::Char(_) | Node::Any | Node::Class { .. } => {
+            let matches = text.get(pos).is_some_and(|&c| match node {
+                Node::Char(expected) => c == *expected,
+                Node::Class { ranges, negated } => {
+                    ranges
+                        .iter()
+                        .any(|&(start, end)| (start..=end).contains(&c))
+                        != *negated
+                }
+                _ => true,
+            });
+            matches && match_nodes(rest, text, pos + 1, then)
+        }
+    }
+}
+
+/// Matches a repeated node greedily after `count` repetitions, and then `rest`
+fn match_repeat(
+    repeat @ (node, min, max): (&Node, usize, Option<usize>),
+    rest: &[Node],
+    text: &[char],
+    pos: usize,
+    count: usize,
+    then: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    if max.is_none_or(|max| count < max) {
+        let more = match_nodes(std::slice::from_ref(node), text, pos, &mut |end| {
+            // Repetitions that match nothing would never end
+            (end != pos || count < min) && match_repeat(repeat, rest, text, end, count + 1, then)
+        });
+        if more {
+            return true;
+        }
+    }
+    count >= min && match_nodes(rest, text, pos, then)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Id, Node, Way};
+
+    fn node(tags: &[(&str, &str)]) -> Element {
+        let mut builder = Node::builder(Id(1));
+        for &(key, value) in tags {
+            builder = builder.tag(TagString::from_ref(key), TagString::from_ref(value));
+        }
+        Element::Node(builder.build().unwrap())
+    }
+
+    fn matches(filter: &str, tags: &[(&str, &str)]) -> bool {
+        Filter::parse(filter).unwrap().matches(&node(tags))
+    }
+
+    #[test]
+    fn expressions() {
+        assert!(matches("highway", &[("highway", "path")]));
+        assert!(matches(
+            "highway=primary,secondary",
+            &[("highway", "secondary")]
+        ));
+        assert!(!matches(
+            "highway=primary,secondary",
+            &[("highway", "tertiary")]
+        ));
+        assert!(matches("highway!=primary", &[("highway", "tertiary")]));
+        // Negated conditions still need the key
+        assert!(!matches("highway!=primary", &[]));
+        assert!(matches("name~^Main ", &[("name", "Main Street")]));
+        assert!(!matches("name!~^Main ", &[("name", "Main Street")]));
+        assert!(matches("name~a/b", &[("name", "a/b")]));
+        assert!(matches("n/amenity", &[("amenity", "bench")]));
+        assert!(!matches("wr/amenity", &[("amenity", "bench")]));
+    }
+
+    #[test]
+    fn types_apply_to_all_values() {
+        let filter = Filter::parse("w/highway=primary,secondary").unwrap();
+        let way = Way::builder(Id(2))
+            .nodes([1, 2].map(Id))
+            .tag("highway", "secondary")
+            .build()
+            .unwrap();
+        assert!(filter.matches(&Element::Way(way)));
+        assert!(!filter.matches(&node(&[("highway", "secondary")])));
+    }
+
+    #[test]
+    fn combinators() {
+        let filter = Filter::key("amenity")
+            .and(Filter::tag("cuisine", "pizza"))
+            .or(Filter::key("shop"));
+        assert!(filter.matches(&node(&[("amenity", "restaurant"), ("cuisine", "pizza")])));
+        assert!(filter.matches(&node(&[("shop", "bakery")])));
+        assert!(!filter.matches(&node(&[("amenity", "restaurant")])));
+        assert!((!filter.clone()).matches(&node(&[])));
+        assert_eq!(!!filter.clone(), filter);
+        assert!(Filter::And(vec![]).matches(&node(&[])));
+        assert!(!Filter::Or(vec![]).matches(&node(&[])));
+    }
+
+    #[test]
+    fn regex_precedence() {
+        let is_match = |pattern: &str, value: &str| Regex::new(pattern).unwrap().is_match(value);
+        // Alternation binds loosest, so the anchors belong to one alternative each
+        assert!(is_match("^a|b$", "ax"));
+        assert!(is_match("^a|b$", "xb"));
+        assert!(!is_match("^(a|b)$", "ab"));
+        // Quantifiers bind to the previous character or group
+        assert!(is_match("^ab+$", "abbb"));
+        assert!(!is_match("^ab+$", "abab"));
+        assert!(is_match("^(ab)+$", "abab"));
+        assert!(is_match("^a{2,3}$", "aaa"));
+        assert!(!is_match("^a{2,3}$", "aaaa"));
+        assert!(is_match("^[^0-9]\\d\\s?$", "a1"));
+        assert!(is_match("^\\w+-\\W$", "A_1-+"));
+        assert!(is_match("^[a\\-z]+$", "a-z"));
+        assert!(is_match("^\\.$", "."));
+        assert!(!is_match("^\\.$", "x"));
+    }
+
+    #[test]
+    fn errors() {
+        let parse = |s: &str| Filter::parse(s).err();
+        assert_eq!(parse("=primary"), Some(ParseFilterError::EmptyKey));
+        assert_eq!(parse("w/"), Some(ParseFilterError::EmptyKey));
+        assert_eq!(parse("x/highway"), Some(ParseFilterError::UnknownType('x')));
+        for (pattern, reason) in [
+            ("(a", "unmatched ("),
+            ("a)", "unmatched )"),
+            ("[a", "unmatched ["),
+            ("a{2", "unmatched {"),
+            ("*a", "quantifier without an expression"),
+            ("a{3,2}", "invalid repetition"),
+            ("a{x}", "invalid repetition"),
+            ("[z-a]", "invalid range in class"),
+            ("[\\D]", "negated escape in class"),
+            ("a\\", "trailing backslash"),
+        ] {
+            assert_eq!(
+                parse(&format!("name~{pattern}")),
+                Some(ParseFilterError::InvalidRegex {
+                    pattern: pattern.to_string(),
+                    reason: reason.to_string(),
+                }),
+                "{pattern}"
+            );
+        }
+    }
+}
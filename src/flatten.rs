@@ -0,0 +1,170 @@
+//! Flat records of elements for CSV files and dataframes
+//!
+//! A [Flattener] turns each element into one record with a fixed set of [Column]s, e.g. the
+//! id, a few tag keys, and the geometry as [WKT](https://en.wikipedia.org/wiki/Well-known_text_representation_of_geometry).
+
+use std::io::{self, Write};
+
+use crate::locations::NodeLocations;
+use crate::scalar::ScalarExt;
+use crate::{Element, TagString};
+
+/// Field of a flattened record
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Column {
+    Id,
+    /// `node`, `way`, or `relation`
+    Type,
+    Version,
+    /// In ISO 8601 format, e.g. `2024-01-31T12:00:00Z`
+    Timestamp,
+    Changeset,
+    User,
+    /// Value of the tag with this key
+    Tag(TagString),
+    /// `POINT` for nodes and `LINESTRING` for ways, in WKT
+    ///
+    /// Relations and ways with a missing node location have no geometry.
+    Geometry,
+}
+
+impl Column {
+    pub fn tag(key: &str) -> Self {
+        Column::Tag(TagString::from_ref(key))
+    }
+
+    /// Name of the column in a header, which is the key for tags
+    pub fn name(&self) -> &str {
+        match self {
+            Column::Id => "id",
+            Column::Type => "type",
+            Column::Version => "version",
+            Column::Timestamp => "timestamp",
+            Column::Changeset => "changeset",
+            Column::User => "user",
+            Column::Tag(key) => key,
+            Column::Geometry => "geometry",
+        }
+    }
+}
+
+/// Converts elements into records with the same columns
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Flattener {
+    columns: Vec<Column>,
+}
+
+impl Flattener {
+    pub fn new(columns: Vec<Column>) -> Self {
+        Self { columns }
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    pub fn header(&self) -> Vec<&str> {
+        self.columns.iter().map(Column::name).collect()
+    }
+
+    /// Values of the element in the order of the columns, with [None] for missing values
+    ///
+    /// Node locations are only needed for the geometry of ways.
+    pub fn flatten(
+        &self,
+        element: &Element,
+        locations: Option<&NodeLocations>,
+    ) -> Vec<Option<String>> {
+        let info = element.info();
+        self.columns
+            .iter()
+            .map(|column| match column {
+                Column::Id => Some(element.id().0.to_string()),
+                Column::Type => Some(
+                    match element {
+                        Element::Node(_) => "node",
+                        Element::Way(_) => "way",
+                        Element::Relation(_) => "relation",
+                    }
+                    .to_string(),
+                ),
+                Column::Version => info.map(|info| info.version.to_string()),
+                Column::Timestamp => info
+                    .and_then(|info| info.timestamp)
+                    .map(|timestamp| crate::format_timestamp(timestamp).to_string()),
+                Column::Changeset => info
+                    .and_then(|info| info.changeset)
+                    .map(|changeset| changeset.to_string()),
+                Column::User => info
+                    .and_then(|info| info.user.as_ref())
+                    .map(|user| user.to_string()),
+                Column::Tag(key) => element.tags().get(key).map(|value| value.to_string()),
+                Column::Geometry => wkt(element, locations),
+            })
+            .collect()
+    }
+
+    /// Writes the header as a CSV line
+    pub fn write_csv_header<W: Write>(&self, writer: W) -> io::Result<()> {
+        write_csv_line(writer, self.header().into_iter().map(Some))
+    }
+
+    /// Writes the element as a CSV line
+    pub fn write_csv<W: Write>(
+        &self,
+        writer: W,
+        element: &Element,
+        locations: Option<&NodeLocations>,
+    ) -> io::Result<()> {
+        let record = self.flatten(element, locations);
+        write_csv_line(writer, record.iter().map(Option::as_deref))
+    }
+}
+
+/// Writes fields following [RFC 4180](https://datatracker.ietf.org/doc/html/rfc4180), with
+/// missing values as empty fields
+fn write_csv_line<'a, W: Write>(
+    mut writer: W,
+    fields: impl Iterator<Item = Option<&'a str>>,
+) -> io::Result<()> {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+        let field = field.unwrap_or_default();
+        if field.contains([',', '"', '\n', '\r']) {
+            write!(writer, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(field.as_bytes())?;
+        }
+    }
+    writer.write_all(b"\r\n")
+}
+
+fn wkt(element: &Element, locations: Option<&NodeLocations>) -> Option<String> {
+    match element {
+        Element::Node(node) => Some(format!(
+            "POINT({} {})",
+            node.lon.normalized(),
+            node.lat.normalized()
+        )),
+        Element::Way(way) => {
+            let locations = locations?;
+            let points = way
+                .refs
+                .iter()
+                .map(|id| {
+                    let location = locations.get(*id)?;
+                    Some(format!(
+                        "{} {}",
+                        location.lon.normalized(),
+                        location.lat.normalized()
+                    ))
+                })
+                .collect::<Option<Vec<_>>>()?;
+            (points.len() >= 2).then(|| format!("LINESTRING({})", points.join(",")))
+        }
+        Element::Relation(_) => None,
+    }
+}
@@ -0,0 +1,58 @@
+//! Conversions to [geo_types], for use with the [georust](https://georust.org) crates
+//!
+//! Coordinates become `x` for longitude and `y` for latitude, in [f64].
+
+use geo_types::{Coord, LineString, Point};
+
+use crate::geom::{LatLon, MissingNode};
+use crate::multipolygon::{MultiPolygon, Polygon};
+use crate::scalar::ScalarExt;
+use crate::{Id, Node, Way};
+
+pub fn coord((lat, lon): LatLon) -> Coord<f64> {
+    Coord {
+        x: lon.as_f64(),
+        y: lat.as_f64(),
+    }
+}
+
+/// Line through the coordinates, e.g. those of [Way::resolve]
+pub fn line_string(coordinates: &[LatLon]) -> LineString<f64> {
+    coordinates.iter().copied().map(coord).collect()
+}
+
+impl From<&Node> for Point<f64> {
+    fn from(node: &Node) -> Self {
+        Point(coord(node.lat_lon()))
+    }
+}
+
+impl From<&Polygon> for geo_types::Polygon<f64> {
+    fn from(polygon: &Polygon) -> Self {
+        geo_types::Polygon::new(
+            line_string(&polygon.outer),
+            polygon
+                .inners
+                .iter()
+                .map(|inner| line_string(inner))
+                .collect(),
+        )
+    }
+}
+
+impl From<&MultiPolygon> for geo_types::MultiPolygon<f64> {
+    fn from(multipolygon: &MultiPolygon) -> Self {
+        geo_types::MultiPolygon(multipolygon.polygons.iter().map(Into::into).collect())
+    }
+}
+
+impl Way {
+    /// See [Way::resolve]
+    pub fn to_line_string<'a>(
+        &self,
+        node: impl FnMut(Id) -> Option<&'a Node>,
+    ) -> Result<LineString<f64>, MissingNode> {
+        self.resolve(node)
+            .map(|coordinates| line_string(&coordinates))
+    }
+}
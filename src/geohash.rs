@@ -0,0 +1,153 @@
+//! Geohash encoding of coordinates
+//!
+//! Nearby coordinates usually share a geohash prefix, which makes geohashes a cheap key for spatial bucketing.
+//!
+//! <https://en.wikipedia.org/wiki/Geohash>
+
+use std::fmt;
+
+use crate::scalar::{Scalar, ScalarExt};
+use crate::Node;
+
+const ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Maximum length of a geohash, with cells smaller than 4cm
+pub const MAX_PRECISION: usize = 12;
+
+/// Error returned when decoding an invalid geohash
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DecodeGeohashError;
+
+impl fmt::Display for DecodeGeohashError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid geohash")
+    }
+}
+
+impl std::error::Error for DecodeGeohashError {}
+
+/// Number of bits used for (latitude, longitude) in a geohash of `precision` characters
+///
+/// Bits alternate starting with longitude.
+fn bits(precision: usize) -> (u32, u32) {
+    let total = 5 * precision as u32;
+    (total / 2, total.div_ceil(2))
+}
+
+/// Encodes a coordinate as a geohash of `precision` characters
+///
+/// `precision` is clamped to the range 1 to [MAX_PRECISION].
+pub fn encode(lat: Scalar, lon: Scalar, precision: usize) -> String {
+    let precision = precision.clamp(1, MAX_PRECISION);
+    let (lat_bits, lon_bits) = bits(precision);
+
+    // Exact arithmetic with the `decimal` feature (rather than bisecting floats) puts coordinates on a cell boundary in the right cell
+    let index = |degrees: Scalar, bound: i64, bits: u32| {
+        let cells = 1u64 << bits;
+        let scaled = (degrees + Scalar::from_int(bound)) * Scalar::from_int(cells as i64)
+            / Scalar::from_int(2 * bound);
+        scaled
+            .floor()
+            .to_int()
+            .unwrap_or(0)
+            .clamp(0, cells as i64 - 1) as u64
+    };
+    let lat_index = index(
+        lat.clamp(Scalar::from_int(-90), Scalar::from_int(90)),
+        90,
+        lat_bits,
+    );
+    let lon_index = index(
+        lon.clamp(Scalar::from_int(-180), Scalar::from_int(180)),
+        180,
+        lon_bits,
+    );
+
+    let mut hash = String::with_capacity(precision);
+    let (mut lat_remaining, mut lon_remaining) = (lat_bits, lon_bits);
+    let mut char_index = 0;
+    for bit in 0..5 * precision {
+        let value = if bit % 2 == 0 {
+            lon_remaining -= 1;
+            (lon_index >> lon_remaining) & 1
+        } else {
+            lat_remaining -= 1;
+            (lat_index >> lat_remaining) & 1
+        };
+        char_index = (char_index << 1) | value as usize;
+        if bit % 5 == 4 {
+            hash.push(ALPHABET[char_index] as char);
+            char_index = 0;
+        }
+    }
+    hash
+}
+
+/// Area covered by a geohash
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "decimal", derive(Eq, Hash))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cell {
+    /// Latitude of the center
+    pub lat: Scalar,
+    /// Longitude of the center
+    pub lon: Scalar,
+    /// Half the height of the cell in degrees
+    pub lat_error: Scalar,
+    /// Half the width of the cell in degrees
+    pub lon_error: Scalar,
+}
+
+/// Decodes a geohash into the cell it covers
+///
+/// Case is ignored.
+pub fn decode(hash: &str) -> Result<Cell, DecodeGeohashError> {
+    if hash.is_empty() || hash.len() > MAX_PRECISION {
+        return Err(DecodeGeohashError);
+    }
+    let (mut lat_index, mut lon_index) = (0u64, 0u64);
+    let mut bit = 0;
+    for c in hash.bytes() {
+        let value = ALPHABET
+            .iter()
+            .position(|a| *a == c.to_ascii_lowercase())
+            .ok_or(DecodeGeohashError)?;
+        for shift in (0..5).rev() {
+            let value = ((value >> shift) & 1) as u64;
+            if bit % 2 == 0 {
+                lon_index = (lon_index << 1) | value;
+            } else {
+                lat_index = (lat_index << 1) | value;
+            }
+            bit += 1;
+        }
+    }
+
+    let (lat_bits, lon_bits) = bits(hash.len());
+    let center = |index: u64, bound: i64, bits: u32| {
+        let cell_size = Scalar::from_int(2 * bound) / Scalar::from_int(1 << bits);
+        let center = Scalar::from_int(-bound)
+            + cell_size * (Scalar::from_int(index as i64) + Scalar::with_scale(5, 1));
+        (
+            center.normalized(),
+            (cell_size / Scalar::from_int(2)).normalized(),
+        )
+    };
+    let (lat, lat_error) = center(lat_index, 90, lat_bits);
+    let (lon, lon_error) = center(lon_index, 180, lon_bits);
+    Ok(Cell {
+        lat,
+        lon,
+        lat_error,
+        lon_error,
+    })
+}
+
+impl Node {
+    /// Geohash of the node's coordinates with `precision` characters
+    ///
+    /// See [encode] for details.
+    pub fn geohash(&self, precision: usize) -> String {
+        encode(self.lat, self.lon, precision)
+    }
+}
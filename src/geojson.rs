@@ -0,0 +1,183 @@
+//! [GeoJSON](https://datatracker.ietf.org/doc/html/rfc7946) features
+//!
+//! Nodes become points, closed ways with area tags become polygons and other ways lines, and
+//! multipolygon relations become multipolygons. Lines that cross the antimeridian are split
+//! into multilines there. Tags are the properties of each feature.
+//! Ways and relations are resolved through lookups, e.g. an [ElementStore] with [feature].
+
+use serde::{Deserialize, Serialize};
+
+use crate::bbox::split_at_antimeridian;
+use crate::geom::{signed_area, LatLon};
+use crate::multipolygon::assemble;
+use crate::scalar::ScalarExt;
+use crate::store::ElementStore;
+use crate::{Element, ElementId, Id, Node, Relation, Tags, Way};
+
+/// Longitude and latitude, in the order of GeoJSON
+pub type Position = [f64; 2];
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "coordinates")]
+pub enum Geometry {
+    Point(Position),
+    LineString(Vec<Position>),
+    /// Line that crosses the antimeridian, split there as RFC 7946 recommends
+    MultiLineString(Vec<Vec<Position>>),
+    /// Counterclockwise outer ring followed by clockwise holes
+    Polygon(Vec<Vec<Position>>),
+    MultiPolygon(Vec<Vec<Vec<Position>>>),
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub struct Feature {
+    /// Type and id of the element, e.g. `way/123`
+    pub id: String,
+    pub geometry: Geometry,
+    pub properties: Tags,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub struct FeatureCollection {
+    pub features: Vec<Feature>,
+}
+
+impl FromIterator<Feature> for FeatureCollection {
+    fn from_iter<T: IntoIterator<Item = Feature>>(iter: T) -> Self {
+        Self {
+            features: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Node {
+    pub fn to_feature(&self) -> Feature {
+        Feature {
+            id: ElementId::from(self.node_id()).to_string(),
+            geometry: Geometry::Point(position(self.lat_lon())),
+            properties: self.tags.clone(),
+        }
+    }
+}
+
+impl Way {
+    /// Line or polygon with coordinates from `node`
+    ///
+    /// Returns [None] if a node is missing or the way has fewer than two nodes.
+    pub fn to_feature(&self, node: impl FnMut(Id) -> Option<LatLon>) -> Option<Feature> {
+        let line = self
+            .refs
+            .iter()
+            .copied()
+            .map(node)
+            .collect::<Option<Vec<_>>>()?;
+        if line.len() < 2 {
+            return None;
+        }
+        let geometry = if self.is_area() {
+            Geometry::Polygon(vec![ring(&line, true)])
+        } else {
+            let mut lines: Vec<Vec<_>> = split_at_antimeridian(&line)
+                .into_iter()
+                .map(|line| line.into_iter().map(position).collect())
+                .collect();
+            match lines.len() {
+                1 => Geometry::LineString(lines.remove(0)),
+                _ => Geometry::MultiLineString(lines),
+            }
+        };
+        Some(Feature {
+            id: ElementId::from(self.way_id()).to_string(),
+            geometry,
+            properties: self.tags.clone(),
+        })
+    }
+}
+
+impl Relation {
+    /// Multipolygon with ways from `way` and coordinates from `node`
+    ///
+    /// Returns [None] unless the relation [is a multipolygon](Relation::is_multipolygon)
+    /// whose ways are all found and form closed rings.
+    pub fn to_feature<'a>(
+        &self,
+        way: impl FnMut(Id) -> Option<&'a Way>,
+        node: impl FnMut(Id) -> Option<LatLon>,
+    ) -> Option<Feature> {
+        if !self.is_multipolygon() {
+            return None;
+        }
+        let polygons = assemble(self, way, node)
+            .ok()?
+            .polygons
+            .into_iter()
+            .map(|polygon| {
+                let mut rings = vec![ring(&polygon.outer, true)];
+                rings.extend(polygon.inners.iter().map(|inner| ring(inner, false)));
+                rings
+            })
+            .collect();
+        Some(Feature {
+            id: ElementId::from(self.relation_id()).to_string(),
+            geometry: Geometry::MultiPolygon(polygons),
+            properties: self.tags.clone(),
+        })
+    }
+}
+
+/// Feature of an element, resolving ways and relations from `store`
+pub fn feature(element: &Element, store: &ElementStore) -> Option<Feature> {
+    let node = |id| store.get_node(id).map(Node::lat_lon);
+    match element {
+        Element::Node(node) => Some(node.to_feature()),
+        Element::Way(way) => way.to_feature(node),
+        Element::Relation(relation) => relation.to_feature(|id| store.get_way(id), node),
+    }
+}
+
+fn position((lat, lon): LatLon) -> Position {
+    [lon.as_f64(), lat.as_f64()]
+}
+
+/// Ring oriented as GeoJSON requires, counterclockwise if `outer`
+fn ring(ring: &[LatLon], outer: bool) -> Vec<Position> {
+    let mut positions: Vec<_> = ring.iter().copied().map(position).collect();
+    let planar: Vec<_> = positions.iter().map(|&[lon, lat]| (lat, lon)).collect();
+    if (signed_area(&planar) > 0.) != outer {
+        positions.reverse();
+    }
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scalar::Scalar;
+
+    #[test]
+    fn lines_across_the_antimeridian_are_split() {
+        let location = |id: Id| {
+            let (lat, lon) = [(-17, 178), (-18, -178), (-19, -175)][id.0 as usize - 1];
+            Some((Scalar::from_int(lat), Scalar::from_int(lon)))
+        };
+        let way = Way::builder(Id(1))
+            .nodes([1, 2, 3].map(Id))
+            .tag("route", "ferry")
+            .build()
+            .unwrap();
+        let Geometry::MultiLineString(lines) = way.to_feature(location).unwrap().geometry else {
+            panic!("expected a multiline");
+        };
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], [[178., -17.], [180., -17.5]]);
+        assert_eq!(lines[1], [[-180., -17.5], [-178., -18.], [-175., -19.]]);
+
+        let way = Way::builder(Id(2)).nodes([3, 2].map(Id)).build().unwrap();
+        assert_eq!(
+            way.to_feature(location).unwrap().geometry,
+            Geometry::LineString(vec![[-175., -19.], [-178., -18.]])
+        );
+    }
+}
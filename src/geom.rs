@@ -0,0 +1,724 @@
+//! Geometric calculations on the surface of the earth
+//!
+//! Calculations treat the earth as a sphere and are done in [f64], except for the `geodesic_`
+//! functions, which use the WGS 84 ellipsoid.
+
+use std::fmt;
+
+use crate::bbox::split_at_antimeridian;
+use crate::scalar::{Scalar, ScalarExt};
+use crate::store::ElementStore;
+use crate::{Id, Node, Relation, Way};
+
+/// Latitude and longitude in degrees
+pub type LatLon = (Scalar, Scalar);
+
+/// Node of a [Way] that could not be found, returned by [Way::resolve]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct MissingNode {
+    pub way: Id,
+    pub node: Id,
+    /// Position of the node in [Way::refs]
+    pub index: usize,
+}
+
+impl fmt::Display for MissingNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "node {} at position {} of way {} is missing",
+            self.node.0, self.index, self.way.0
+        )
+    }
+}
+
+impl std::error::Error for MissingNode {}
+
+/// [Mean radius](https://en.wikipedia.org/wiki/Earth_radius#Mean_radius) of the earth in meters
+pub const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Decimal places kept for calculated coordinates, matching the OSM API
+const DECIMAL_PLACES: u32 = 7;
+
+pub(crate) fn to_radians((lat, lon): LatLon) -> (f64, f64) {
+    (lat.as_f64().to_radians(), lon.as_f64().to_radians())
+}
+
+pub(crate) fn from_radians(lat: f64, lon: f64) -> LatLon {
+    let degrees = |radians: f64| {
+        Scalar::try_from_f64(radians.to_degrees())
+            .unwrap_or_default()
+            .round_places(DECIMAL_PLACES)
+            .normalized()
+    };
+    (degrees(lat), degrees(lon))
+}
+
+/// Keys that make a closed way a polygon, except for the values that stay lines
+///
+/// Based on the rules of common renderers and editors, e.g. `natural=coastline` is a line
+/// even when it closes around an island.
+pub const AREA_KEYS: &[(&str, &[&str])] = &[
+    (
+        "aeroway",
+        &["jet_bridge", "parking_position", "runway", "taxiway"],
+    ),
+    ("amenity", &[]),
+    ("area:highway", &[]),
+    ("boundary", &[]),
+    ("building", &[]),
+    ("building:part", &[]),
+    ("craft", &[]),
+    ("historic", &[]),
+    ("indoor", &["corridor", "wall"]),
+    ("landuse", &[]),
+    ("leisure", &["slipway", "track"]),
+    (
+        "man_made",
+        &[
+            "breakwater",
+            "cutline",
+            "dyke",
+            "embankment",
+            "groyne",
+            "pipeline",
+        ],
+    ),
+    ("military", &[]),
+    (
+        "natural",
+        &[
+            "arete",
+            "cliff",
+            "coastline",
+            "earth_bank",
+            "ridge",
+            "tree_row",
+            "valley",
+        ],
+    ),
+    ("office", &[]),
+    ("place", &[]),
+    ("shop", &[]),
+    ("tourism", &[]),
+    ("water", &[]),
+];
+
+/// Keys that keep a closed way a line, except for the values that are polygons
+///
+/// Closed `highway` and `barrier` ways, e.g. a roundabout or a fence, are lines unless tagged
+/// `area=yes`.
+pub const AREA_VALUES: &[(&str, &[&str])] = &[
+    ("highway", &["platform", "rest_area", "services"]),
+    (
+        "power",
+        &["generator", "plant", "substation", "transformer"],
+    ),
+    ("public_transport", &["platform", "station"]),
+    (
+        "railway",
+        &["platform", "roundhouse", "station", "turntable"],
+    ),
+    ("waterway", &["boatyard", "dam", "dock", "riverbank"]),
+];
+
+/// Whether tags make a closed way a polygon, see [Way::is_area]
+fn implies_area(tags: &crate::Tags) -> bool {
+    let value = |key: &str| tags.get(key).map(|value| value.as_str());
+    match value("area") {
+        Some("yes") => return true,
+        Some("no") => return false,
+        _ => {}
+    }
+    let area_key = AREA_KEYS.iter().any(|(key, lines)| {
+        value(key).is_some_and(|value| value != "no" && !lines.contains(&value))
+    });
+    area_key
+        || AREA_VALUES
+            .iter()
+            .any(|(key, areas)| value(key).is_some_and(|value| areas.contains(&value)))
+}
+
+/// Even-odd test of a point against a ring, treating coordinates as planar
+///
+/// Rings of fewer than 3 coordinates contain no point.
+pub(crate) fn ring_contains(ring: &[(f64, f64)], lat: f64, lon: f64) -> bool {
+    if ring.len() < 3 {
+        return false;
+    }
+    let mut inside = false;
+    let mut previous = ring[ring.len() - 1];
+    for &current in ring {
+        let ((lat_a, lon_a), (lat_b, lon_b)) = (previous, current);
+        if (lat_a > lat) != (lat_b > lat)
+            && lon < lon_a + (lat - lat_a) / (lat_b - lat_a) * (lon_b - lon_a)
+        {
+            inside = !inside;
+        }
+        previous = current;
+    }
+    inside
+}
+
+/// Planar area of a ring in square degrees, positive if counterclockwise
+///
+/// The ring is closed whether or not it repeats its first coordinate.
+pub(crate) fn signed_area(ring: &[(f64, f64)]) -> f64 {
+    let Some(&last) = ring.last() else {
+        return 0.;
+    };
+    let mut previous = last;
+    let mut twice = 0.;
+    for &current in ring {
+        let ((lat_a, lon_a), (lat_b, lon_b)) = (previous, current);
+        twice += lon_a * lat_b - lon_b * lat_a;
+        previous = current;
+    }
+    twice / 2.
+}
+
+/// [Haversine](https://en.wikipedia.org/wiki/Haversine_formula) distance between two coordinates in meters
+pub fn distance(a: LatLon, b: LatLon) -> f64 {
+    let (lat1, lon1) = to_radians(a);
+    let (lat2, lon2) = to_radians(b);
+    let h = ((lat2 - lat1) / 2.).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.).sin().powi(2);
+    2. * EARTH_RADIUS * h.sqrt().min(1.).asin()
+}
+
+/// Semi-major axis of the [WGS 84](https://en.wikipedia.org/wiki/World_Geodetic_System#WGS84) ellipsoid in meters
+const WGS84_A: f64 = 6_378_137.;
+/// Flattening of the WGS 84 ellipsoid
+const WGS84_F: f64 = 1. / 298.257_223_563;
+
+/// Distance between two coordinates in meters on the WGS 84 ellipsoid, with
+/// [Vincenty's formulae](https://en.wikipedia.org/wiki/Vincenty%27s_formulae)
+///
+/// This is accurate to within a millimeter, where [distance] can be off by about 0.5%. For
+/// nearly antipodal points the iteration may not converge, and [distance] is returned instead.
+pub fn geodesic_distance(a: LatLon, b: LatLon) -> f64 {
+    let (lat1, lon1) = to_radians(a);
+    let (lat2, lon2) = to_radians(b);
+    let semi_minor = WGS84_A * (1. - WGS84_F);
+    let u1 = ((1. - WGS84_F) * lat1.tan()).atan();
+    let u2 = ((1. - WGS84_F) * lat2.tan()).atan();
+    let (sin_u1, cos_u1) = u1.sin_cos();
+    let (sin_u2, cos_u2) = u2.sin_cos();
+    let delta_lon = lon2 - lon1;
+    let mut lambda = delta_lon;
+    for _ in 0..200 {
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+        let sin_sigma = ((cos_u2 * sin_lambda).powi(2)
+            + (cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda).powi(2))
+        .sqrt();
+        if sin_sigma == 0. {
+            return 0.;
+        }
+        let cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+        let sigma = sin_sigma.atan2(cos_sigma);
+        let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+        let cos_sq_alpha = 1. - sin_alpha.powi(2);
+        // Lines along the equator have no central latitude
+        let cos_2sigma_m = if cos_sq_alpha == 0. {
+            0.
+        } else {
+            cos_sigma - 2. * sin_u1 * sin_u2 / cos_sq_alpha
+        };
+        let c = WGS84_F / 16. * cos_sq_alpha * (4. + WGS84_F * (4. - 3. * cos_sq_alpha));
+        let previous = lambda;
+        lambda = delta_lon
+            + (1. - c)
+                * WGS84_F
+                * sin_alpha
+                * (sigma
+                    + c * sin_sigma
+                        * (cos_2sigma_m + c * cos_sigma * (-1. + 2. * cos_2sigma_m.powi(2))));
+        if (lambda - previous).abs() < 1e-12 {
+            let u_sq = cos_sq_alpha * (WGS84_A.powi(2) - semi_minor.powi(2)) / semi_minor.powi(2);
+            let k = u_sq / 1024.;
+            let big_a = 1. + k / 16. * (4096. + u_sq * (-768. + u_sq * (320. - 175. * u_sq)));
+            let big_b = k * (256. + u_sq * (-128. + u_sq * (74. - 47. * u_sq)));
+            let delta_sigma = big_b
+                * sin_sigma
+                * (cos_2sigma_m
+                    + big_b / 4.
+                        * (cos_sigma * (-1. + 2. * cos_2sigma_m.powi(2))
+                            - big_b / 6.
+                                * cos_2sigma_m
+                                * (-3. + 4. * sin_sigma.powi(2))
+                                * (-3. + 4. * cos_2sigma_m.powi(2))));
+            return semi_minor * big_a * (sigma - delta_sigma);
+        }
+    }
+    distance(a, b)
+}
+
+/// Length of a line in meters with [distance]
+pub fn length(line: &[LatLon]) -> f64 {
+    line.windows(2).map(|pair| distance(pair[0], pair[1])).sum()
+}
+
+/// Length of a line in meters with [geodesic_distance]
+pub fn geodesic_length(line: &[LatLon]) -> f64 {
+    line.windows(2)
+        .map(|pair| geodesic_distance(pair[0], pair[1]))
+        .sum()
+}
+
+/// Initial [great-circle](https://en.wikipedia.org/wiki/Great-circle_navigation) bearing from `from` to `to`
+///
+/// Returns degrees clockwise from north in the range `[0, 360)`. Following the great circle,
+/// the bearing changes along the way unless travelling along a meridian or the equator.
+pub fn bearing(from: LatLon, to: LatLon) -> f64 {
+    let (lat1, lon1) = to_radians(from);
+    let (lat2, lon2) = to_radians(to);
+    let delta_lon = lon2 - lon1;
+    let y = delta_lon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * delta_lon.cos();
+    y.atan2(x).to_degrees().rem_euclid(360.)
+}
+
+/// Coordinate reached by travelling `distance` meters from `start` along a great circle with initial `bearing` in degrees
+///
+/// Longitude is normalized to the range `[-180, 180)` and both coordinates are rounded to 7 decimal places.
+pub fn destination(start: LatLon, bearing: f64, distance: f64) -> LatLon {
+    let (lat1, lon1) = to_radians(start);
+    let bearing = bearing.to_radians();
+    let angular_distance = distance / EARTH_RADIUS;
+    let lat2 = (lat1.sin() * angular_distance.cos()
+        + lat1.cos() * angular_distance.sin() * bearing.cos())
+    .asin();
+    let lon2 = lon1
+        + (bearing.sin() * angular_distance.sin() * lat1.cos())
+            .atan2(angular_distance.cos() - lat1.sin() * lat2.sin());
+    let lon2 =
+        (lon2 + std::f64::consts::PI).rem_euclid(std::f64::consts::TAU) - std::f64::consts::PI;
+    from_radians(lat2, lon2)
+}
+
+/// Discrete [Hausdorff distance](https://en.wikipedia.org/wiki/Hausdorff_distance) between the vertices of two lines in meters
+///
+/// This is the greatest distance from a vertex of either line to the nearest vertex of the other.
+/// Lines with long segments should be densified first (see [densify]) for a meaningful result.
+/// Returns [None] if either line is empty.
+pub fn hausdorff_distance(a: &[LatLon], b: &[LatLon]) -> Option<f64> {
+    let directed = |from: &[LatLon], to: &[LatLon]| {
+        from.iter()
+            .map(|p| {
+                to.iter()
+                    .map(|q| distance(*p, *q))
+                    .fold(f64::INFINITY, f64::min)
+            })
+            .fold(0., f64::max)
+    };
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    Some(directed(a, b).max(directed(b, a)))
+}
+
+/// [Discrete Fréchet distance](https://en.wikipedia.org/wiki/Fr%C3%A9chet_distance#Discrete_Fr%C3%A9chet_distance) between two lines in meters
+///
+/// Unlike [hausdorff_distance], this takes the direction and order of vertices into account,
+/// so a line is only similar to another line that follows the same course.
+/// Returns [None] if either line is empty.
+pub fn frechet_distance(a: &[LatLon], b: &[LatLon]) -> Option<f64> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    // Coupling distances for the previous and current vertex of a against every vertex of b
+    let mut previous = vec![0f64; b.len()];
+    let mut current = vec![0f64; b.len()];
+    for (i, p) in a.iter().enumerate() {
+        for (j, q) in b.iter().enumerate() {
+            let d = distance(*p, *q);
+            current[j] = match (i, j) {
+                (0, 0) => d,
+                (0, _) => current[j - 1].max(d),
+                (_, 0) => previous[0].max(d),
+                _ => previous[j].min(previous[j - 1]).min(current[j - 1]).max(d),
+            };
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous.last().copied()
+}
+
+/// Coordinate at `fraction` of the way from `a` to `b` along their great circle
+pub fn interpolate(a: LatLon, b: LatLon, fraction: f64) -> LatLon {
+    let (lat1, lon1) = to_radians(a);
+    let (lat2, lon2) = to_radians(b);
+    let angular_distance = distance(a, b) / EARTH_RADIUS;
+    if angular_distance == 0. {
+        return a;
+    }
+    let weight_a = ((1. - fraction) * angular_distance).sin() / angular_distance.sin();
+    let weight_b = (fraction * angular_distance).sin() / angular_distance.sin();
+    let x = weight_a * lat1.cos() * lon1.cos() + weight_b * lat2.cos() * lon2.cos();
+    let y = weight_a * lat1.cos() * lon1.sin() + weight_b * lat2.cos() * lon2.sin();
+    let z = weight_a * lat1.sin() + weight_b * lat2.sin();
+    from_radians(z.atan2(x.hypot(y)), y.atan2(x))
+}
+
+/// Inserts evenly spaced points into segments longer than `max_segment_length` meters
+///
+/// Original vertices are kept, and inserted points follow the great circle between them.
+///
+/// # Panics
+///
+/// If `max_segment_length` is not positive.
+pub fn densify(line: &[LatLon], max_segment_length: f64) -> Vec<LatLon> {
+    assert!(
+        max_segment_length > 0.,
+        "maximum segment length must be positive"
+    );
+    let mut densified = Vec::with_capacity(line.len());
+    densified.extend(line.first());
+    for pair in line.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let pieces = (distance(a, b) / max_segment_length).ceil().max(1.) as usize;
+        densified.extend((1..pieces).map(|i| interpolate(a, b, i as f64 / pieces as f64)));
+        densified.push(b);
+    }
+    densified
+}
+
+/// Projects coordinates to meters east and north of `origin` on a plane tangent to it
+///
+/// Distortion stays below tolerances used for simplification over the extent of a single way.
+fn planar(origin: LatLon, line: &[LatLon]) -> Vec<(f64, f64)> {
+    let (lat0, lon0) = to_radians(origin);
+    let scale = lat0.cos();
+    line.iter()
+        .map(|&at| {
+            let (lat, lon) = to_radians(at);
+            let mut delta_lon = lon - lon0;
+            if delta_lon > std::f64::consts::PI {
+                delta_lon -= std::f64::consts::TAU;
+            } else if delta_lon < -std::f64::consts::PI {
+                delta_lon += std::f64::consts::TAU;
+            }
+            (
+                delta_lon * scale * EARTH_RADIUS,
+                (lat - lat0) * EARTH_RADIUS,
+            )
+        })
+        .collect()
+}
+
+/// Distance of `p` to the segment from `a` to `b` on the plane
+fn segment_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared == 0. {
+        0.
+    } else {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_squared).clamp(0., 1.)
+    };
+    (p.0 - a.0 - t * dx).hypot(p.1 - a.1 - t * dy)
+}
+
+/// Indexes of the vertices kept by [simplify_way], in order
+///
+/// The first and last vertex are always kept, so a ring stays closed but may collapse to
+/// fewer than 4 vertices.
+pub fn simplify_indexes(line: &[LatLon], tolerance: f64) -> Vec<usize> {
+    if line.len() <= 2 {
+        return (0..line.len()).collect();
+    }
+    let points = planar(line[0], line);
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let mut ranges = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = ranges.pop() {
+        let farthest = (first + 1..last)
+            .map(|i| (i, segment_distance(points[i], points[first], points[last])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, distance)) = farthest {
+            if distance > tolerance {
+                keep[i] = true;
+                ranges.push((first, i));
+                ranges.push((i, last));
+            }
+        }
+    }
+    (0..points.len()).filter(|&i| keep[i]).collect()
+}
+
+/// Line with the vertices within `tolerance` meters of the simplified line removed, by the
+/// [Douglas–Peucker algorithm](https://en.wikipedia.org/wiki/Ramer%E2%80%93Douglas%E2%80%93Peucker_algorithm)
+///
+/// Kept vertices are the original coordinates, see [simplify_indexes] for which ones.
+pub fn simplify_way(line: &[LatLon], tolerance: f64) -> Vec<LatLon> {
+    simplify_indexes(line, tolerance)
+        .into_iter()
+        .map(|i| line[i])
+        .collect()
+}
+
+/// Indexes of the vertices kept by [simplify_vw], in order
+///
+/// The first and last vertex are always kept, as for [simplify_indexes].
+pub fn simplify_vw_indexes(line: &[LatLon], min_area: f64) -> Vec<usize> {
+    use std::cmp::Reverse;
+    use std::collections::BinaryHeap;
+
+    if line.len() <= 2 {
+        return (0..line.len()).collect();
+    }
+    let points = planar(line[0], line);
+    let area = |a: usize, b: usize, c: usize| {
+        let ((ax, ay), (bx, by), (cx, cy)) = (points[a], points[b], points[c]);
+        ((bx - ax) * (cy - ay) - (cx - ax) * (by - ay)).abs() / 2.
+    };
+    // Neighbours of each vertex still in the line
+    let mut previous: Vec<usize> = (0..points.len()).map(|i| i.saturating_sub(1)).collect();
+    let mut next: Vec<usize> = (1..=points.len()).collect();
+    let mut removed = vec![false; points.len()];
+    // Effective areas as ordered bits, which sort like non-negative floats
+    let mut heap: BinaryHeap<Reverse<(u64, usize)>> = (1..points.len() - 1)
+        .map(|i| Reverse((area(i - 1, i, i + 1).to_bits(), i)))
+        .collect();
+    let mut current: Vec<u64> = (0..points.len())
+        .map(|i| match i {
+            0 => 0,
+            i if i == points.len() - 1 => 0,
+            i => area(i - 1, i, i + 1).to_bits(),
+        })
+        .collect();
+    while let Some(Reverse((bits, i))) = heap.pop() {
+        if removed[i] || bits != current[i] {
+            continue;
+        }
+        if f64::from_bits(bits) >= min_area {
+            break;
+        }
+        removed[i] = true;
+        let (before, after) = (previous[i], next[i]);
+        next[before] = after;
+        previous[after] = before;
+        for j in [before, after] {
+            if j != 0 && j != points.len() - 1 {
+                // An effective area never shrinks below that of a removed neighbour, so that
+                // vertices are removed in order of significance
+                let bits = area(previous[j], j, next[j])
+                    .max(f64::from_bits(bits))
+                    .to_bits();
+                current[j] = bits;
+                heap.push(Reverse((bits, j)));
+            }
+        }
+    }
+    (0..points.len()).filter(|&i| !removed[i]).collect()
+}
+
+/// Line with the vertices removed whose triangle with their neighbours is smaller than
+/// `min_area` square meters, by the
+/// [Visvalingam–Whyatt algorithm](https://en.wikipedia.org/wiki/Visvalingam%E2%80%93Whyatt_algorithm)
+///
+/// This keeps the overall shape better than [simplify_way] at coarse scales, e.g. for low
+/// zoom levels of vector tiles.
+pub fn simplify_vw(line: &[LatLon], min_area: f64) -> Vec<LatLon> {
+    simplify_vw_indexes(line, min_area)
+        .into_iter()
+        .map(|i| line[i])
+        .collect()
+}
+
+impl Node {
+    /// See [distance]
+    pub fn distance_to(&self, other: &Node) -> f64 {
+        distance(self.lat_lon(), other.lat_lon())
+    }
+
+    pub fn lat_lon(&self) -> LatLon {
+        (self.lat, self.lon)
+    }
+
+    /// See [geodesic_distance]
+    pub fn geodesic_distance_to(&self, other: &Node) -> f64 {
+        geodesic_distance(self.lat_lon(), other.lat_lon())
+    }
+
+    /// See [bearing]
+    pub fn bearing_to(&self, other: &Node) -> f64 {
+        bearing(self.lat_lon(), other.lat_lon())
+    }
+
+    /// See [destination]
+    pub fn destination(&self, bearing: f64, distance: f64) -> LatLon {
+        destination(self.lat_lon(), bearing, distance)
+    }
+}
+
+impl Way {
+    /// Whether the first and last node are the same, as for rings
+    pub fn is_closed(&self) -> bool {
+        self.refs.len() > 1 && self.refs.first() == self.refs.last()
+    }
+
+    /// Whether the way is a polygon rather than a line
+    ///
+    /// The way must be closed with at least 4 nodes. Then `area=yes` or `area=no` decides, and
+    /// otherwise tags such as `building` or `landuse` make it an area, see [AREA_KEYS] and
+    /// [AREA_VALUES].
+    pub fn is_area(&self) -> bool {
+        self.refs.len() >= 4 && self.is_closed() && implies_area(&self.tags)
+    }
+
+    /// Coordinates of the nodes in order, looking each up with `node`
+    ///
+    /// Fails at the first node that is not found.
+    pub fn resolve<'a>(
+        &self,
+        mut node: impl FnMut(Id) -> Option<&'a Node>,
+    ) -> Result<Vec<LatLon>, MissingNode> {
+        self.refs
+            .iter()
+            .enumerate()
+            .map(|(index, &id)| {
+                node(id).map(Node::lat_lon).ok_or(MissingNode {
+                    way: self.id,
+                    node: id,
+                    index,
+                })
+            })
+            .collect()
+    }
+
+    /// Like [Way::resolve], but split into lines wherever the way crosses the antimeridian
+    ///
+    /// Ways that do not cross it are a single line. See [split_at_antimeridian] for where the
+    /// lines are split.
+    pub fn resolve_split<'a>(
+        &self,
+        node: impl FnMut(Id) -> Option<&'a Node>,
+    ) -> Result<Vec<Vec<LatLon>>, MissingNode> {
+        self.resolve(node).map(|line| split_at_antimeridian(&line))
+    }
+
+    /// Length in meters with [distance], looking up nodes with `node`
+    pub fn length<'a>(&self, node: impl FnMut(Id) -> Option<&'a Node>) -> Result<f64, MissingNode> {
+        self.resolve(node).map(|line| length(&line))
+    }
+
+    /// Length in meters with [geodesic_distance], looking up nodes with `node`
+    pub fn geodesic_length<'a>(
+        &self,
+        node: impl FnMut(Id) -> Option<&'a Node>,
+    ) -> Result<f64, MissingNode> {
+        self.resolve(node).map(|line| geodesic_length(&line))
+    }
+
+    /// Copy of the way with only the refs kept by [simplify_way], looking up nodes with `node`
+    ///
+    /// The refs that remain still point to the original nodes, so the simplified way can
+    /// stand in for the way in the same dataset.
+    pub fn simplified<'a>(
+        &self,
+        node: impl FnMut(Id) -> Option<&'a Node>,
+        tolerance: f64,
+    ) -> Result<Way, MissingNode> {
+        let line = self.resolve(node)?;
+        let mut way = self.clone();
+        way.refs = simplify_indexes(&line, tolerance)
+            .into_iter()
+            .map(|i| self.refs[i])
+            .collect();
+        Ok(way)
+    }
+}
+
+impl Relation {
+    /// Whether the relation is a polygon, see [Relation::is_multipolygon]
+    pub fn is_area(&self) -> bool {
+        self.is_multipolygon()
+    }
+}
+
+impl ElementStore {
+    /// See [Way::resolve]
+    pub fn resolve_way(&self, way: &Way) -> Result<Vec<LatLon>, MissingNode> {
+        way.resolve(|id| self.get_node(id))
+    }
+
+    /// See [Way::resolve_split]
+    pub fn resolve_way_split(&self, way: &Way) -> Result<Vec<Vec<LatLon>>, MissingNode> {
+        way.resolve_split(|id| self.get_node(id))
+    }
+
+    /// See [Way::length]
+    pub fn way_length(&self, way: &Way) -> Result<f64, MissingNode> {
+        way.length(|id| self.get_node(id))
+    }
+
+    /// See [Way::simplified]
+    pub fn simplified_way(&self, way: &Way, tolerance: f64) -> Result<Way, MissingNode> {
+        way.simplified(|id| self.get_node(id), tolerance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: i64, lat: i64, lon: i64) -> Node {
+        Node::builder(Id(id))
+            .lat(Scalar::from_int(lat))
+            .lon(Scalar::from_int(lon))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn trans_pacific_ways_are_split() {
+        // From Japan across the Pacific to Alaska, and back along a line that does not cross
+        let nodes = [
+            node(1, 35, 140),
+            node(2, 40, 170),
+            node(3, 50, -170),
+            node(4, 60, -150),
+        ];
+        let node = |id: Id| nodes.iter().find(|node| node.id == id);
+        let way = Way::builder(Id(10))
+            .nodes([1, 2, 3, 4].map(Id))
+            .build()
+            .unwrap();
+        let lines = way.resolve_split(node).unwrap();
+        let int = |lat, lon| (Scalar::from_int(lat), Scalar::from_int(lon));
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0][..2], [int(35, 140), int(40, 170)]);
+        assert_eq!(lines[1][1..], [int(50, -170), int(60, -150)]);
+        let (lat, lon) = *lines[0].last().unwrap();
+        assert_eq!((lat.as_f64(), lon.as_f64()), (45., 180.));
+        assert_eq!(lines[1][0], (lat, Scalar::from_int(-180)));
+
+        let way = Way::builder(Id(11)).nodes([4, 3].map(Id)).build().unwrap();
+        assert_eq!(
+            way.resolve_split(node).unwrap(),
+            [vec![int(60, -150), int(50, -170)]]
+        );
+    }
+
+    const SQUARE: [(f64, f64); 4] = [(0., 0.), (0., 2.), (2., 2.), (2., 0.)];
+
+    #[test]
+    fn ring_contains_short_rings() {
+        assert!(ring_contains(&SQUARE, 1., 1.));
+        assert!(!ring_contains(&SQUARE, 3., 1.));
+        assert!(!ring_contains(&[], 0., 0.));
+        assert!(!ring_contains(&SQUARE[..2], 0., 1.));
+    }
+
+    #[test]
+    fn signed_area_of_open_and_closed_rings() {
+        let mut closed = SQUARE.to_vec();
+        closed.push(SQUARE[0]);
+        assert_eq!(signed_area(&SQUARE), 4.);
+        assert_eq!(signed_area(&closed), 4.);
+        closed.reverse();
+        assert_eq!(signed_area(&closed), -4.);
+        assert_eq!(signed_area(&[]), 0.);
+    }
+}
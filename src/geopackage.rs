@@ -0,0 +1,348 @@
+//! [GeoPackage](https://www.geopackage.org) export
+//!
+//! [Writer] creates a GeoPackage with `points`, `lines`, and `polygons` layers that opens
+//! directly in QGIS. Each layer has the OSM type and id of the feature, followed by one text
+//! column for each selected tag key.
+//!
+//! Ways are resolved from the nodes written before them, so elements should be written in the
+//! usual order of nodes, then ways. Relations are not written.
+
+use std::io;
+use std::path::Path;
+
+use fnv::FnvHashMap as HashMap;
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+
+use crate::pipeline::Sink;
+use crate::scalar::ScalarExt;
+use crate::{Element, Error, Id, Node, TagString, Way};
+
+/// `GPKG` in ASCII
+const APPLICATION_ID: i32 = 0x4750_4B47;
+/// Version 1.4.0
+const USER_VERSION: i32 = 10400;
+const SRS_ID: i32 = 4326;
+
+const WGS_84: &str = r#"GEOGCS["WGS 84",DATUM["WGS_1984",SPHEROID["WGS 84",6378137,298.257223563,AUTHORITY["EPSG","7030"]],AUTHORITY["EPSG","6326"]],PRIMEM["Greenwich",0,AUTHORITY["EPSG","8901"]],UNIT["degree",0.0174532925199433,AUTHORITY["EPSG","9122"]],AUTHORITY["EPSG","4326"]]"#;
+
+const SCHEMA: &str = r#"
+CREATE TABLE gpkg_spatial_ref_sys (
+    srs_name TEXT NOT NULL,
+    srs_id INTEGER PRIMARY KEY,
+    organization TEXT NOT NULL,
+    organization_coordsys_id INTEGER NOT NULL,
+    definition TEXT NOT NULL,
+    description TEXT
+);
+CREATE TABLE gpkg_contents (
+    table_name TEXT NOT NULL PRIMARY KEY,
+    data_type TEXT NOT NULL,
+    identifier TEXT UNIQUE,
+    description TEXT DEFAULT '',
+    last_change DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+    min_x DOUBLE,
+    min_y DOUBLE,
+    max_x DOUBLE,
+    max_y DOUBLE,
+    srs_id INTEGER,
+    CONSTRAINT fk_gc_r_srs_id FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys(srs_id)
+);
+CREATE TABLE gpkg_geometry_columns (
+    table_name TEXT NOT NULL,
+    column_name TEXT NOT NULL,
+    geometry_type_name TEXT NOT NULL,
+    srs_id INTEGER NOT NULL,
+    z TINYINT NOT NULL,
+    m TINYINT NOT NULL,
+    CONSTRAINT pk_geom_cols PRIMARY KEY (table_name, column_name),
+    CONSTRAINT fk_gc_tn FOREIGN KEY (table_name) REFERENCES gpkg_contents(table_name),
+    CONSTRAINT fk_gc_srs FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys(srs_id)
+);
+INSERT INTO gpkg_spatial_ref_sys VALUES
+    ('Undefined cartesian SRS', -1, 'NONE', -1, 'undefined', NULL),
+    ('Undefined geographic SRS', 0, 'NONE', 0, 'undefined', NULL);
+"#;
+
+fn sqlite(err: rusqlite::Error) -> Error {
+    Error::Io(io::Error::other(err))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Layer {
+    Points,
+    Lines,
+    Polygons,
+}
+
+impl Layer {
+    const ALL: [Layer; 3] = [Layer::Points, Layer::Lines, Layer::Polygons];
+
+    fn table(self) -> &'static str {
+        match self {
+            Layer::Points => "points",
+            Layer::Lines => "lines",
+            Layer::Polygons => "polygons",
+        }
+    }
+
+    fn geometry_type(self) -> &'static str {
+        match self {
+            Layer::Points => "POINT",
+            Layer::Lines => "LINESTRING",
+            Layer::Polygons => "POLYGON",
+        }
+    }
+}
+
+/// Extent of a layer as `(min_x, min_y, max_x, max_y)`
+type Extent = Option<(f64, f64, f64, f64)>;
+
+/// Writes elements into a new GeoPackage
+///
+/// All rows are written in a single transaction that is committed by [Writer::finish].
+pub struct Writer {
+    connection: Connection,
+    columns: Vec<String>,
+    /// Locations of all nodes written so far as `(x, y)`
+    nodes: HashMap<Id, (f64, f64)>,
+    extents: [Extent; 3],
+}
+
+impl Writer {
+    /// Creates a GeoPackage at `path` with a text column for each of `columns`
+    ///
+    /// Fails if `path` already exists, to avoid appending to an unrelated database.
+    pub fn create(path: impl AsRef<Path>, columns: &[&str]) -> Result<Self, Error> {
+        let path = path.as_ref();
+        if path.exists() {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", path.display()),
+            )));
+        }
+        Self::new(Connection::open(path).map_err(sqlite)?, columns)
+    }
+
+    /// Like [Writer::create], but with the database held in memory
+    pub fn in_memory(columns: &[&str]) -> Result<Self, Error> {
+        Self::new(Connection::open_in_memory().map_err(sqlite)?, columns)
+    }
+
+    fn new(connection: Connection, columns: &[&str]) -> Result<Self, Error> {
+        let mut unique: Vec<String> = vec![];
+        for column in columns {
+            if !matches!(*column, "fid" | "geom" | "osm_type" | "osm_id")
+                && !unique.iter().any(|existing| existing == column)
+            {
+                unique.push(column.to_string());
+            }
+        }
+        connection
+            .pragma_update(None, "application_id", APPLICATION_ID)
+            .and_then(|_| connection.pragma_update(None, "user_version", USER_VERSION))
+            .and_then(|_| connection.execute_batch(SCHEMA))
+            .and_then(|_| {
+                connection.execute(
+                    "INSERT INTO gpkg_spatial_ref_sys VALUES ('WGS 84', ?1, 'EPSG', ?1, ?2, NULL)",
+                    (SRS_ID, WGS_84),
+                )
+            })
+            .map_err(sqlite)?;
+        let attributes: String = unique
+            .iter()
+            .map(|column| format!(", {} TEXT", quote(column)))
+            .collect();
+        for layer in Layer::ALL {
+            let table = layer.table();
+            connection
+                .execute_batch(&format!(
+                    "CREATE TABLE {table} (fid INTEGER PRIMARY KEY AUTOINCREMENT, geom {}, osm_type TEXT NOT NULL, osm_id INTEGER NOT NULL{attributes});",
+                    layer.geometry_type()
+                ))
+                .and_then(|_| {
+                    connection.execute(
+                        "INSERT INTO gpkg_contents (table_name, data_type, identifier, srs_id) VALUES (?1, 'features', ?1, ?2)",
+                        (table, SRS_ID),
+                    )
+                })
+                .and_then(|_| {
+                    connection.execute(
+                        "INSERT INTO gpkg_geometry_columns VALUES (?1, 'geom', ?2, ?3, 0, 0)",
+                        (table, layer.geometry_type(), SRS_ID),
+                    )
+                })
+                .map_err(sqlite)?;
+        }
+        connection.execute_batch("BEGIN").map_err(sqlite)?;
+        Ok(Self {
+            connection,
+            columns: unique,
+            nodes: HashMap::default(),
+            extents: [None; 3],
+        })
+    }
+
+    /// Writes a feature for the element if it has a geometry
+    ///
+    /// Untagged nodes are only kept to resolve ways. Ways with a node that was not written
+    /// before are skipped.
+    pub fn write(&mut self, element: &Element) -> Result<(), Error> {
+        match element {
+            Element::Node(node) => self.write_node(node),
+            Element::Way(way) => self.write_way(way),
+            Element::Relation(_) => Ok(()),
+        }
+    }
+
+    fn write_node(&mut self, node: &Node) -> Result<(), Error> {
+        let at = (node.lon.as_f64(), node.lat.as_f64());
+        self.nodes.insert(node.id, at);
+        if node.tags.is_empty() {
+            return Ok(());
+        }
+        let mut wkb = wkb_header(Layer::Points);
+        wkb_coordinate(&mut wkb, at);
+        self.insert(Layer::Points, "node", node.id, &node.tags, &[at], wkb)
+    }
+
+    fn write_way(&mut self, way: &Way) -> Result<(), Error> {
+        let Some(line) = way
+            .refs
+            .iter()
+            .map(|id| self.nodes.get(id).copied())
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Ok(());
+        };
+        if line.len() < 2 {
+            return Ok(());
+        }
+        let layer = if way.is_area() {
+            Layer::Polygons
+        } else {
+            Layer::Lines
+        };
+        let mut wkb = wkb_header(layer);
+        if let Layer::Polygons = layer {
+            wkb.extend_from_slice(&1u32.to_le_bytes());
+        }
+        wkb.extend_from_slice(&(line.len() as u32).to_le_bytes());
+        line.iter().for_each(|at| wkb_coordinate(&mut wkb, *at));
+        self.insert(layer, "way", way.id, &way.tags, &line, wkb)
+    }
+
+    fn insert(
+        &mut self,
+        layer: Layer,
+        osm_type: &str,
+        id: Id,
+        tags: &HashMap<TagString, TagString>,
+        coordinates: &[(f64, f64)],
+        wkb: Vec<u8>,
+    ) -> Result<(), Error> {
+        let extent = &mut self.extents[layer as usize];
+        for (x, y) in coordinates {
+            let (min_x, min_y, max_x, max_y) = extent.get_or_insert((*x, *y, *x, *y));
+            *min_x = min_x.min(*x);
+            *min_y = min_y.min(*y);
+            *max_x = max_x.max(*x);
+            *max_y = max_y.max(*y);
+        }
+
+        // GeoPackage binary header: magic, version 0, little-endian without envelope, SRS id
+        let mut geometry = Vec::with_capacity(8 + wkb.len());
+        geometry.extend_from_slice(b"GP");
+        geometry.extend_from_slice(&[0, 1]);
+        geometry.extend_from_slice(&SRS_ID.to_le_bytes());
+        geometry.extend_from_slice(&wkb);
+
+        let mut values = vec![
+            Value::Blob(geometry),
+            Value::Text(osm_type.to_string()),
+            Value::Integer(id.0),
+        ];
+        values.extend(self.columns.iter().map(|column| {
+            tags.get(column.as_str())
+                .map_or(Value::Null, |value| Value::Text(value.to_string()))
+        }));
+        let names: String = self
+            .columns
+            .iter()
+            .map(|column| format!(", {}", quote(column)))
+            .collect();
+        let placeholders: String = (4..values.len() + 1).map(|i| format!(", ?{i}")).collect();
+        self.connection
+            .prepare_cached(&format!(
+                "INSERT INTO {} (geom, osm_type, osm_id{names}) VALUES (?1, ?2, ?3{placeholders})",
+                layer.table()
+            ))
+            .and_then(|mut statement| statement.execute(params_from_iter(values)))
+            .map_err(sqlite)?;
+        Ok(())
+    }
+
+    /// Records the extent of each layer and commits all rows
+    pub fn finish(&mut self) -> Result<(), Error> {
+        for layer in Layer::ALL {
+            if let Some((min_x, min_y, max_x, max_y)) = self.extents[layer as usize] {
+                self.connection
+                    .execute(
+                        "UPDATE gpkg_contents SET min_x = ?1, min_y = ?2, max_x = ?3, max_y = ?4 WHERE table_name = ?5",
+                        (min_x, min_y, max_x, max_y, layer.table()),
+                    )
+                    .map_err(sqlite)?;
+            }
+        }
+        if !self.connection.is_autocommit() {
+            self.connection.execute_batch("COMMIT").map_err(sqlite)?;
+        }
+        Ok(())
+    }
+
+    /// Underlying database, e.g. to add indexes or further tables
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+}
+
+impl Sink for Writer {
+    type Error = Error;
+
+    fn write(&mut self, element: Element) -> Result<(), Self::Error> {
+        Writer::write(self, &element)
+    }
+
+    fn finish(&mut self) -> Result<(), Self::Error> {
+        Writer::finish(self)
+    }
+}
+
+impl std::fmt::Debug for Writer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Writer")
+            .field("columns", &self.columns)
+            .field("nodes", &self.nodes.len())
+            .finish_non_exhaustive()
+    }
+}
+
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+fn wkb_header(layer: Layer) -> Vec<u8> {
+    let ty: u32 = match layer {
+        Layer::Points => 1,
+        Layer::Lines => 2,
+        Layer::Polygons => 3,
+    };
+    let mut wkb = vec![1];
+    wkb.extend_from_slice(&ty.to_le_bytes());
+    wkb
+}
+
+fn wkb_coordinate(wkb: &mut Vec<u8>, (x, y): (f64, f64)) {
+    wkb.extend_from_slice(&x.to_le_bytes());
+    wkb.extend_from_slice(&y.to_le_bytes());
+}
@@ -0,0 +1,176 @@
+//! [GPX](https://wiki.openstreetmap.org/wiki/GPX) traces, as uploaded to and downloaded from
+//! the [GPS traces](https://www.openstreetmap.org/traces) of OSM
+//!
+//! [Trace::read] reads the tracks of a GPX 1.0 or 1.1 file, and [Trace::write] writes GPX 1.1.
+//! Waypoints and routes are skipped, and the segments of several tracks are concatenated, as
+//! editors do when showing a trace behind the map data.
+
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+
+use chrono::{DateTime, Utc};
+
+use crate::elevation::Elevation;
+use crate::error::{Error, Result};
+use crate::geom::LatLon;
+use crate::scalar::{Scalar, ScalarExt};
+use crate::xml::{attribute, escape, TagKind, Tokenizer};
+use crate::{timestamp_from_utc, timestamp_to_utc, Timestamp};
+
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Trace {
+    /// Name of the first track
+    pub name: Option<String>,
+    /// Description of the first track
+    pub description: Option<String>,
+    pub segments: Vec<TrackSegment>,
+}
+
+/// Points recorded without interruption
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackSegment {
+    pub points: Vec<TrackPoint>,
+}
+
+impl TrackSegment {
+    /// Coordinates of the points in order
+    pub fn line(&self) -> Vec<LatLon> {
+        self.points
+            .iter()
+            .map(|point| (point.lat, point.lon))
+            .collect()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackPoint {
+    /// [WGS 84](https://en.wikipedia.org/wiki/World_Geodetic_System#WGS84) latitude (y)
+    pub lat: Scalar,
+    /// [WGS 84](https://en.wikipedia.org/wiki/World_Geodetic_System#WGS84) longitude (x)
+    pub lon: Scalar,
+    pub time: Option<Timestamp>,
+    pub elevation: Option<Elevation>,
+}
+
+impl Trace {
+    /// Points of all segments in order
+    pub fn points(&self) -> impl Iterator<Item = &TrackPoint> {
+        self.segments.iter().flat_map(|segment| &segment.points)
+    }
+
+    /// Reads the tracks of a GPX document
+    pub fn read<R: BufRead>(input: R) -> Result<Self> {
+        let mut tokenizer = Tokenizer::new(input);
+        let mut trace = Trace::default();
+        let mut segment = None::<TrackSegment>;
+        let mut point = None::<TrackPoint>;
+        let mut tracks = 0;
+        while let Some(tag) = tokenizer.next_tag()? {
+            let offset = tokenizer.tag_offset;
+            let error = |message: &str| Error::decode(Some(offset), message);
+            match (tag.kind, tag.name.as_str()) {
+                (TagKind::Start, "trk") => tracks += 1,
+                (TagKind::Start | TagKind::Empty, "trkseg") => {
+                    segment = Some(TrackSegment::default());
+                    if tag.kind == TagKind::Empty {
+                        trace.segments.extend(segment.take());
+                    }
+                }
+                (TagKind::End, "trkseg") => trace.segments.extend(segment.take()),
+                (TagKind::Start | TagKind::Empty, "trkpt") if segment.is_some() => {
+                    let coordinate = |name| {
+                        tag.attribute(name)
+                            .and_then(Scalar::parse_decimal)
+                            .ok_or_else(|| error(&format!("missing or invalid {name} attribute")))
+                    };
+                    point = Some(TrackPoint {
+                        lat: coordinate("lat")?,
+                        lon: coordinate("lon")?,
+                        time: None,
+                        elevation: None,
+                    });
+                    if tag.kind == TagKind::Empty {
+                        segment.as_mut().unwrap().points.extend(point.take());
+                    }
+                }
+                (TagKind::End, "trkpt") => {
+                    if let (Some(segment), Some(point)) = (&mut segment, point.take()) {
+                        segment.points.push(point);
+                    }
+                }
+                (TagKind::End, "ele") => {
+                    if let Some(point) = &mut point {
+                        let meters = Scalar::parse_decimal(tokenizer.text()?.trim())
+                            .ok_or_else(|| error("invalid ele"))?;
+                        point.elevation = Some(Elevation::from_meters(meters));
+                    }
+                }
+                (TagKind::End, "time") => {
+                    if let Some(point) = &mut point {
+                        let time = DateTime::parse_from_rfc3339(tokenizer.text()?.trim())
+                            .map_err(|_| error("invalid time"))?;
+                        point.time = Some(timestamp_from_utc(time.with_timezone(&Utc)));
+                    }
+                }
+                // Only the first track names the trace, and the names of points are skipped
+                (TagKind::End, "name") if tracks == 1 && segment.is_none() => {
+                    trace.name.get_or_insert(tokenizer.text()?);
+                }
+                (TagKind::End, "desc") if tracks == 1 && segment.is_none() => {
+                    trace.description.get_or_insert(tokenizer.text()?);
+                }
+                _ => {}
+            }
+        }
+        Ok(trace)
+    }
+
+    /// Writes the trace as a GPX 1.1 document with a single track
+    pub fn write<W: Write>(&self, mut output: W) -> Result<()> {
+        let mut buf = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        buf.push_str("<gpx version=\"1.1\" creator=\"osm-types\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n <trk>\n");
+        if let Some(name) = &self.name {
+            buf.push_str("  <name>");
+            escape(&mut buf, name);
+            buf.push_str("</name>\n");
+        }
+        if let Some(description) = &self.description {
+            buf.push_str("  <desc>");
+            escape(&mut buf, description);
+            buf.push_str("</desc>\n");
+        }
+        for segment in &self.segments {
+            buf.push_str("  <trkseg>\n");
+            for point in &segment.points {
+                buf.push_str("   <trkpt");
+                attribute(&mut buf, "lat", point.lat);
+                attribute(&mut buf, "lon", point.lon);
+                if point.elevation.is_none() && point.time.is_none() {
+                    buf.push_str("/>\n");
+                    continue;
+                }
+                buf.push_str(">\n");
+                if let Some(elevation) = point.elevation {
+                    let _ = writeln!(buf, "    <ele>{}</ele>", elevation.meters);
+                }
+                if let Some(time) = point.time {
+                    let time = timestamp_to_utc(time).format("%Y-%m-%dT%H:%M:%SZ");
+                    let _ = writeln!(buf, "    <time>{time}</time>");
+                }
+                buf.push_str("   </trkpt>\n");
+            }
+            buf.push_str("  </trkseg>\n");
+            output.write_all(buf.as_bytes())?;
+            buf.clear();
+        }
+        buf.push_str(" </trk>\n</gpx>\n");
+        output.write_all(buf.as_bytes())?;
+        Ok(())
+    }
+}
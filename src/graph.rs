@@ -0,0 +1,327 @@
+//! Routing graphs built from highways
+//!
+//! [Graph::build] splits routable ways into [Edge]s between junctions, where a junction is any
+//! node shared by two ways or visited twice by a single way. Each edge carries the access,
+//! direction, and speed for a single [TransportMode], and turn restrictions are translated into
+//! pairs of edges.
+//!
+//! <https://wiki.openstreetmap.org/wiki/OSM_tags_for_routing>
+
+use fnv::FnvHashMap as HashMap;
+
+use crate::access::{self, TransportMode};
+use crate::geom;
+use crate::locations::NodeLocations;
+use crate::oneway::{self, Direction, Directional, Oneway};
+use crate::restriction::{TurnRestriction, Via};
+use crate::scalar::ScalarExt;
+use crate::tags::values::Maxspeed;
+use crate::{Id, Relation, Way};
+
+/// Part of a way between two junctions
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Edge {
+    pub way: Id,
+    /// Nodes in the order of the way, starting and ending at a junction
+    pub nodes: Vec<Id>,
+    /// Length in meters
+    pub length: f64,
+    /// Whether the mode may travel in the order of [Edge::nodes]
+    pub forward: bool,
+    /// Whether the mode may travel against the order of [Edge::nodes]
+    pub backward: bool,
+    /// Expected speed in km/h when travelling forward, from `maxspeed` or a default for the road
+    pub forward_speed: f64,
+    pub backward_speed: f64,
+}
+
+impl Edge {
+    pub fn from(&self) -> Id {
+        self.nodes[0]
+    }
+
+    pub fn to(&self) -> Id {
+        self.nodes[self.nodes.len() - 1]
+    }
+
+    /// Whether the mode may travel along the edge in `direction`
+    pub fn permits(&self, direction: Direction) -> bool {
+        match direction {
+            Direction::Forward => self.forward,
+            Direction::Backward => self.backward,
+        }
+    }
+
+    /// Travel time in seconds, or [None] if travel is not permitted in `direction`
+    pub fn duration(&self, direction: Direction) -> Option<f64> {
+        let speed = match direction {
+            Direction::Forward => self.forward_speed,
+            Direction::Backward => self.backward_speed,
+        };
+        (self.permits(direction) && speed > 0.).then(|| self.length / (speed / 3.6))
+    }
+}
+
+/// Turn from one edge to another through their shared node
+///
+/// <https://wiki.openstreetmap.org/wiki/Relation:restriction>
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Turn {
+    /// Index into [Graph::edges]
+    pub from: usize,
+    pub via: Id,
+    /// Index into [Graph::edges]
+    pub to: usize,
+}
+
+/// Turn restrictions applying to the graph's mode
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TurnRestrictions {
+    /// Turns that may not be taken, e.g. from `restriction=no_left_turn`
+    pub prohibited: Vec<Turn>,
+    /// Turns that must be taken when arriving on the `from` edge, e.g. from `restriction=only_straight_on`
+    pub mandatory: Vec<Turn>,
+    /// Restrictions with ways as `via` members, which cannot be expressed as a single [Turn]
+    pub via_ways: Vec<Id>,
+}
+
+/// Routable network for a single [TransportMode]
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Graph {
+    pub edges: Vec<Edge>,
+    /// Indexes into [Graph::edges] of the edges at each junction
+    pub junctions: HashMap<Id, Vec<usize>>,
+    pub restrictions: TurnRestrictions,
+}
+
+impl Graph {
+    /// Builds the graph of the ways routable for `mode`
+    ///
+    /// Ways with a node missing from `locations` are skipped.
+    pub fn build<'a>(
+        mode: TransportMode,
+        ways: impl IntoIterator<Item = &'a Way>,
+        relations: impl IntoIterator<Item = &'a Relation>,
+        locations: &NodeLocations,
+    ) -> Self {
+        let routable: Vec<(&Way, Vec<geom::LatLon>)> = ways
+            .into_iter()
+            .filter(|way| way.refs.len() >= 2 && is_routable(way, mode))
+            .filter_map(|way| {
+                let coordinates = way
+                    .refs
+                    .iter()
+                    .map(|id| locations.get(*id).map(|l| (l.lat, l.lon)))
+                    .collect::<Option<Vec<_>>>()?;
+                Some((way, coordinates))
+            })
+            .collect();
+
+        let mut uses: HashMap<Id, u32> = HashMap::default();
+        for (way, _) in &routable {
+            for id in &way.refs {
+                *uses.entry(*id).or_default() += 1;
+            }
+        }
+
+        let mut graph = Graph::default();
+        let mut edges_of_way: HashMap<Id, Vec<usize>> = HashMap::default();
+        for (way, coordinates) in &routable {
+            let (forward, backward) = directions(way, mode);
+            let speeds = Directional::from_tags(&way.tags, "maxspeed");
+            let speed = |direction| {
+                speeds
+                    .get(direction)
+                    .and_then(parse_maxspeed)
+                    .map(|limit| limit.min(mode_speed(mode)))
+                    .or_else(|| default_speed(way, mode))
+                    .unwrap_or(0.)
+            };
+            let (forward_speed, backward_speed) =
+                (speed(Direction::Forward), speed(Direction::Backward));
+
+            let mut start = 0;
+            for i in 1..way.refs.len() {
+                let last = i == way.refs.len() - 1;
+                if !last && uses[&way.refs[i]] < 2 {
+                    continue;
+                }
+                let length = coordinates[start..=i]
+                    .windows(2)
+                    .map(|pair| geom::distance(pair[0], pair[1]))
+                    .sum();
+                let index = graph.edges.len();
+                graph.edges.push(Edge {
+                    way: way.id,
+                    nodes: way.refs[start..=i].to_vec(),
+                    length,
+                    forward,
+                    backward,
+                    forward_speed,
+                    backward_speed,
+                });
+                for end in [way.refs[start], way.refs[i]] {
+                    let at = graph.junctions.entry(end).or_default();
+                    if !at.contains(&index) {
+                        at.push(index);
+                    }
+                }
+                edges_of_way.entry(way.id).or_default().push(index);
+                start = i;
+            }
+        }
+
+        for relation in relations {
+            graph.add_restriction(relation, mode, &edges_of_way);
+        }
+        graph
+    }
+
+    /// Indexes into [Graph::edges] of the edges ending at `node`
+    pub fn edges_at(&self, node: Id) -> &[usize] {
+        self.junctions.get(&node).map_or(&[], Vec::as_slice)
+    }
+
+    /// Edges the mode may leave `node` on, with the junction at their other end
+    ///
+    /// An edge that starts and ends at `node`, e.g. a closed way, is listed once per permitted
+    /// direction.
+    pub fn neighbors(&self, node: Id) -> Vec<(usize, Id)> {
+        let mut neighbors = vec![];
+        for &index in self.edges_at(node) {
+            let edge = &self.edges[index];
+            if edge.from() == node && edge.forward {
+                neighbors.push((index, edge.to()));
+            }
+            if edge.to() == node && edge.backward {
+                neighbors.push((index, edge.from()));
+            }
+        }
+        neighbors
+    }
+
+    /// Edges as pairs of junctions in each permitted direction, with their travel time in
+    /// seconds, e.g. to build the graph of a network analysis library
+    pub fn directed_edges(&self) -> impl Iterator<Item = (Id, Id, usize, f64)> + '_ {
+        self.edges.iter().enumerate().flat_map(|(index, edge)| {
+            let forward = edge
+                .duration(Direction::Forward)
+                .map(|duration| (edge.from(), edge.to(), index, duration));
+            let backward = edge
+                .duration(Direction::Backward)
+                .map(|duration| (edge.to(), edge.from(), index, duration));
+            forward.into_iter().chain(backward)
+        })
+    }
+
+    fn add_restriction(
+        &mut self,
+        relation: &Relation,
+        mode: TransportMode,
+        edges_of_way: &HashMap<Id, Vec<usize>>,
+    ) {
+        let Some(Ok(restriction)) = TurnRestriction::for_mode(relation, mode) else {
+            return;
+        };
+        let via = match restriction.via {
+            Via::Node(via) => via,
+            Via::Ways(_) => {
+                self.restrictions.via_ways.push(relation.id);
+                return;
+            }
+        };
+        let touching = |way: &Id| {
+            edges_of_way
+                .get(way)
+                .into_iter()
+                .flatten()
+                .copied()
+                .filter(|&edge| self.edges[edge].from() == via || self.edges[edge].to() == via)
+                .collect::<Vec<_>>()
+        };
+        let to_edges: Vec<_> = restriction.to.iter().flat_map(touching).collect();
+        for from in restriction.from.iter().flat_map(touching) {
+            for &to in &to_edges {
+                let turn = Turn { from, via, to };
+                if restriction.kind.is_mandatory() {
+                    self.restrictions.mandatory.push(turn);
+                } else {
+                    self.restrictions.prohibited.push(turn);
+                }
+            }
+        }
+    }
+}
+
+fn is_routable(way: &Way, mode: TransportMode) -> bool {
+    way.tags.contains_key("highway")
+        && access::resolve(&way.tags, mode).is_some_and(|access| access.is_allowed())
+}
+
+/// Permitted directions, where `oneway` does not apply to pedestrians unless tagged `oneway:foot`
+fn directions(way: &Way, mode: TransportMode) -> (bool, bool) {
+    let specific = mode
+        .hierarchy()
+        .filter(|m| *m != TransportMode::All)
+        .find_map(|m| way.tags.get(format!("oneway:{}", m.key()).as_str()))
+        .and_then(|value| Oneway::parse(value));
+    let restriction = match specific {
+        Some(restriction) => restriction,
+        None if mode == TransportMode::Foot => Oneway::No,
+        None => oneway::oneway(&way.tags).unwrap_or(Oneway::No),
+    };
+    (
+        restriction.permits(Direction::Forward),
+        restriction.permits(Direction::Backward),
+    )
+}
+
+/// Parses a value of the `maxspeed` key into km/h
+///
+/// Accepts the limits of [Maxspeed], e.g. plain numbers in km/h and numbers followed by `mph` or
+/// `knots`. Returns [None] for other values, e.g. `none` or zone values such as `DE:urban`.
+///
+/// <https://wiki.openstreetmap.org/wiki/Key:maxspeed>
+pub fn parse_maxspeed(value: &str) -> Option<f64> {
+    let speed = value.parse::<Maxspeed>().ok()?.kilometers_per_hour()?;
+    Some(speed.as_f64())
+}
+
+/// Typical top speed of the mode itself in km/h
+fn mode_speed(mode: TransportMode) -> f64 {
+    match mode {
+        TransportMode::Foot => 5.,
+        TransportMode::Horse => 10.,
+        TransportMode::Bicycle => 18.,
+        TransportMode::Mofa => 25.,
+        TransportMode::Moped | TransportMode::Carriage => 45.,
+        TransportMode::Agricultural => 40.,
+        TransportMode::Hgv
+        | TransportMode::Goods
+        | TransportMode::Bus
+        | TransportMode::Motorhome => 90.,
+        _ => 130.,
+    }
+}
+
+/// Speed in km/h assumed without `maxspeed`, or [None] for unknown road types
+fn default_speed(way: &Way, mode: TransportMode) -> Option<f64> {
+    let road: f64 = match way.tags.get("highway")?.as_str() {
+        "motorway" => 120.,
+        "trunk" => 100.,
+        "primary" => 80.,
+        "secondary" => 70.,
+        "tertiary" => 60.,
+        "motorway_link" | "trunk_link" | "primary_link" | "secondary_link" | "tertiary_link" => 50.,
+        "unclassified" | "residential" | "road" => 50.,
+        "living_street" => 10.,
+        "service" | "track" | "busway" => 20.,
+        "pedestrian" | "footway" | "path" | "steps" | "bridleway" | "cycleway" => mode_speed(mode),
+        _ => return None,
+    };
+    Some(road.min(mode_speed(mode)))
+}
@@ -0,0 +1,132 @@
+//! [H3](https://h3geo.org) cell indexes
+//!
+//! Indexes are returned as the raw `u64` used by H3 bindings and analytics platforms.
+
+use std::fmt;
+
+use geo_types::{Coord, LineString, Polygon};
+use h3o::geom::{ContainmentMode, TilerBuilder};
+use h3o::{LatLng, Resolution};
+
+use crate::geom::LatLon;
+use crate::scalar::ScalarExt;
+use crate::Node;
+
+/// Finest resolution, with cells of under a square meter
+pub const MAX_RESOLUTION: u8 = 15;
+
+/// Error returned when computing H3 cells fails
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum H3Error {
+    /// Resolution is greater than [MAX_RESOLUTION]
+    Resolution(u8),
+    /// Coordinates are not finite, or a ring is not closed or has fewer than 4 coordinates
+    Geometry,
+}
+
+impl fmt::Display for H3Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            H3Error::Resolution(resolution) => write!(f, "invalid H3 resolution {resolution}"),
+            H3Error::Geometry => write!(f, "invalid geometry for H3"),
+        }
+    }
+}
+
+impl std::error::Error for H3Error {}
+
+impl From<H3Error> for crate::Error {
+    fn from(err: H3Error) -> Self {
+        crate::Error::Parse(Box::new(err))
+    }
+}
+
+fn resolution(resolution: u8) -> Result<Resolution, H3Error> {
+    Resolution::try_from(resolution).map_err(|_| H3Error::Resolution(resolution))
+}
+
+fn degrees((lat, lon): LatLon) -> (f64, f64) {
+    (lat.as_f64(), lon.as_f64())
+}
+
+/// Index of the cell at `res` containing the coordinate
+pub fn cell(at: LatLon, res: u8) -> Result<u64, H3Error> {
+    let (lat, lon) = degrees(at);
+    let at = LatLng::new(lat, lon).map_err(|_| H3Error::Geometry)?;
+    Ok(at.to_cell(resolution(res)?).into())
+}
+
+/// How cells along the edge of a polygon are treated by [polyfill]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub enum Containment {
+    /// Cells whose center lies inside, as done by H3 itself
+    #[default]
+    Center,
+    /// Cells entirely inside
+    Inside,
+    /// Cells that touch the polygon at all
+    Intersects,
+}
+
+/// Cells covering a polygon given as an outer ring followed by any inner rings
+///
+/// Rings must be closed. Returns sorted indexes without duplicates.
+pub fn polyfill(
+    rings: &[Vec<LatLon>],
+    res: u8,
+    containment: Containment,
+) -> Result<Vec<u64>, H3Error> {
+    fill([rings], res, containment)
+}
+
+/// Cells covering several polygons as accepted by [polyfill], e.g. an assembled multipolygon
+pub fn multi_polyfill(
+    polygons: &[Vec<Vec<LatLon>>],
+    res: u8,
+    containment: Containment,
+) -> Result<Vec<u64>, H3Error> {
+    fill(polygons.iter().map(Vec::as_slice), res, containment)
+}
+
+fn fill<'a>(
+    polygons: impl IntoIterator<Item = &'a [Vec<LatLon>]>,
+    res: u8,
+    containment: Containment,
+) -> Result<Vec<u64>, H3Error> {
+    let mode = match containment {
+        Containment::Center => ContainmentMode::ContainsCentroid,
+        Containment::Inside => ContainmentMode::ContainsBoundary,
+        Containment::Intersects => ContainmentMode::Covers,
+    };
+    let mut tiler = TilerBuilder::new(resolution(res)?)
+        .containment_mode(mode)
+        .build();
+    let ring = |ring: &Vec<LatLon>| {
+        LineString::new(
+            ring.iter()
+                .map(|at| {
+                    let (lat, lon) = degrees(*at);
+                    Coord { x: lon, y: lat }
+                })
+                .collect(),
+        )
+    };
+    for polygon in polygons {
+        let Some((outer, inner)) = polygon.split_first() else {
+            continue;
+        };
+        let polygon = Polygon::new(ring(outer), inner.iter().map(ring).collect());
+        tiler.add(polygon).map_err(|_| H3Error::Geometry)?;
+    }
+    let mut cells: Vec<u64> = tiler.into_coverage().map(u64::from).collect();
+    cells.sort_unstable();
+    cells.dedup();
+    Ok(cells)
+}
+
+impl Node {
+    /// See [cell]
+    pub fn h3_cell(&self, res: u8) -> Result<u64, H3Error> {
+        cell((self.lat, self.lon), res)
+    }
+}
@@ -0,0 +1,179 @@
+//! [Full history](https://wiki.openstreetmap.org/wiki/Planet.osm/full) of elements
+//!
+//! History files, e.g. `.osh.pbf`, hold every version of every element, sorted by type, id,
+//! and version. Deleted versions have `visible` set to false in their [Info](crate::Info), and
+//! deleted nodes have no coordinates.
+//! The XML, PBF, OPL, and o5m readers read them like any other file, and [histories] groups
+//! their versions into an [ElementHistory] per element.
+//! Writing the versions back out in order produces a history file again; PBF writers need a
+//! header with the `HistoricalInformation` feature to keep `visible`.
+
+use crate::error::{Error, Result};
+use crate::{Element, ElementId, Timestamp};
+
+/// Versions of a single element, ordered by version
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub struct ElementHistory {
+    versions: Vec<Element>,
+}
+
+impl ElementHistory {
+    pub fn new(element: Element) -> Self {
+        Self {
+            versions: vec![element],
+        }
+    }
+
+    /// Adds a version in order, after any equal version
+    ///
+    /// Fails if the element is a different one.
+    pub fn push(&mut self, element: Element) -> Result<()> {
+        if element.element_id() != self.element_id() {
+            return Err(Error::Validation {
+                element: Some(element.element_id().into()),
+                message: format!("not a version of {}", self.element_id()),
+            });
+        }
+        let version = version(&element);
+        let index = self
+            .versions
+            .partition_point(|existing| self::version(existing) <= version);
+        self.versions.insert(index, element);
+        Ok(())
+    }
+
+    pub fn element_id(&self) -> ElementId {
+        self.versions[0].element_id()
+    }
+
+    /// Every version, oldest first
+    pub fn versions(&self) -> &[Element] {
+        &self.versions
+    }
+
+    pub fn into_versions(self) -> Vec<Element> {
+        self.versions
+    }
+
+    /// Version with the given number, if present
+    pub fn version(&self, version: i32) -> Option<&Element> {
+        self.versions
+            .iter()
+            .find(|element| self::version(element) == version)
+    }
+
+    /// Newest version, which may be a deletion
+    pub fn latest(&self) -> &Element {
+        self.versions
+            .last()
+            .expect("history has at least one version")
+    }
+
+    /// Newest version, [None] if the element was deleted
+    pub fn live(&self) -> Option<&Element> {
+        Some(self.latest()).filter(|element| is_visible(element))
+    }
+
+    /// Whether the newest version is a deletion
+    pub fn is_deleted(&self) -> bool {
+        self.live().is_none()
+    }
+
+    /// Version that was current at `time`, [None] if the element did not exist yet or was
+    /// deleted
+    ///
+    /// Versions without a timestamp are skipped.
+    pub fn at_timestamp(&self, time: Timestamp) -> Option<&Element> {
+        self.versions
+            .iter()
+            .rfind(|element| {
+                element
+                    .info()
+                    .and_then(|info| info.timestamp)
+                    .is_some_and(|timestamp| timestamp <= time)
+            })
+            .filter(|element| is_visible(element))
+    }
+}
+
+impl IntoIterator for ElementHistory {
+    type Item = Element;
+    type IntoIter = std::vec::IntoIter<Element>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.versions.into_iter()
+    }
+}
+
+/// Version of an element, 0 if unknown
+fn version(element: &Element) -> i32 {
+    element.info().map_or(0, |info| info.version)
+}
+
+/// Whether the element is not a deletion
+fn is_visible(element: &Element) -> bool {
+    element.info().and_then(|info| info.visible) != Some(false)
+}
+
+/// Groups consecutive versions of the same element, as in history files
+///
+/// Elements that are not next to their other versions get a history of their own, so the
+/// input should be sorted, see [sort](crate::sort).
+pub fn histories<I>(elements: I) -> Histories<I::IntoIter>
+where
+    I: IntoIterator<Item = Result<Element>>,
+{
+    Histories {
+        elements: elements.into_iter(),
+        next: None,
+        done: false,
+    }
+}
+
+/// Iterator returned by [histories]
+#[derive(Debug)]
+pub struct Histories<I> {
+    elements: I,
+    /// First version of the next history
+    next: Option<Element>,
+    done: bool,
+}
+
+impl<I: Iterator<Item = Result<Element>>> Iterator for Histories<I> {
+    type Item = Result<ElementHistory>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut history = match self.next.take().map(Ok).or_else(|| self.elements.next()) {
+            Some(Ok(element)) => ElementHistory::new(element),
+            Some(Err(err)) => {
+                self.done = true;
+                return Some(Err(err));
+            }
+            None => {
+                self.done = true;
+                return None;
+            }
+        };
+        for element in self.elements.by_ref() {
+            match element {
+                Ok(element) if element.element_id() == history.element_id() => {
+                    history.versions.push(element);
+                }
+                Ok(element) => {
+                    self.next = Some(element);
+                    break;
+                }
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        history.versions.sort_by_key(version);
+        Some(Ok(history))
+    }
+}
@@ -0,0 +1,151 @@
+//! Identifiers typed by the kind of [Element](crate::Element) they refer to
+//!
+//! Core types store a plain [Id], which says nothing about whether it names a node, a way, or a
+//! relation. The typed identifiers here make that explicit so that, e.g., a way's node refs
+//! cannot be compared with relation ids by accident. Each converts to and from [Id].
+
+use core::fmt;
+
+use crate::{Element, Id, Member, MemberType, Node, Relation, Way};
+
+macro_rules! typed_id {
+    ($(#[$attr:meta])* $name:ident, $variant:ident) => {
+        $(#[$attr])*
+        #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        pub struct $name(pub i64);
+
+        impl From<Id> for $name {
+            fn from(id: Id) -> Self {
+                Self(id.0)
+            }
+        }
+
+        impl From<$name> for Id {
+            fn from(id: $name) -> Self {
+                Id(id.0)
+            }
+        }
+
+        impl From<$name> for ElementId {
+            fn from(id: $name) -> Self {
+                ElementId::$variant(id)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+    };
+}
+
+typed_id!(
+    /// Identifier of a [Node]
+    NodeId,
+    Node
+);
+typed_id!(
+    /// Identifier of a [Way]
+    WayId,
+    Way
+);
+typed_id!(
+    /// Identifier of a [Relation]
+    RelationId,
+    Relation
+);
+
+/// Identifier of any [Element], unique across types
+///
+/// Displayed as type and id, e.g. `way/123`, the form used by the website and Overpass.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ElementId {
+    Node(NodeId),
+    Way(WayId),
+    Relation(RelationId),
+}
+
+impl ElementId {
+    pub fn new(ty: MemberType, id: Id) -> Self {
+        match ty {
+            MemberType::Node => ElementId::Node(id.into()),
+            MemberType::Way => ElementId::Way(id.into()),
+            MemberType::Relation => ElementId::Relation(id.into()),
+        }
+    }
+
+    /// Untyped identifier, only unique within [ElementId::member_type]
+    pub fn id(&self) -> Id {
+        match *self {
+            ElementId::Node(NodeId(id))
+            | ElementId::Way(WayId(id))
+            | ElementId::Relation(RelationId(id)) => Id(id),
+        }
+    }
+
+    pub fn member_type(&self) -> MemberType {
+        match self {
+            ElementId::Node(_) => MemberType::Node,
+            ElementId::Way(_) => MemberType::Way,
+            ElementId::Relation(_) => MemberType::Relation,
+        }
+    }
+}
+
+impl fmt::Display for ElementId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ElementId::Node(id) => write!(f, "node/{id}"),
+            ElementId::Way(id) => write!(f, "way/{id}"),
+            ElementId::Relation(id) => write!(f, "relation/{id}"),
+        }
+    }
+}
+
+impl From<&Member> for ElementId {
+    fn from(member: &Member) -> Self {
+        ElementId::new(member.ty.clone(), member.id)
+    }
+}
+
+impl Element {
+    pub fn element_id(&self) -> ElementId {
+        match self {
+            Element::Node(node) => node.node_id().into(),
+            Element::Way(way) => way.way_id().into(),
+            Element::Relation(relation) => relation.relation_id().into(),
+        }
+    }
+}
+
+impl Node {
+    pub fn node_id(&self) -> NodeId {
+        self.id.into()
+    }
+}
+
+impl Way {
+    pub fn way_id(&self) -> WayId {
+        self.id.into()
+    }
+
+    /// [Way::refs] as the nodes they refer to
+    pub fn node_ids(&self) -> impl ExactSizeIterator<Item = NodeId> + '_ {
+        self.refs.iter().map(|&id| id.into())
+    }
+}
+
+impl Relation {
+    pub fn relation_id(&self) -> RelationId {
+        self.id.into()
+    }
+}
+
+impl Member {
+    pub fn element_id(&self) -> ElementId {
+        self.into()
+    }
+}
@@ -0,0 +1,117 @@
+//! Interning of the keys and values of tags
+//!
+//! A few strings such as `highway`, `building`, and `yes` make up most tags of a planet.
+//! [TagInterner] keeps one copy of each distinct string and hands out clones of it, which
+//! share their allocation with the `arc-str` [representation](crate::string) and with strings
+//! from [TagInterner::with_common]. Other representations store short strings inline, which
+//! already avoids an allocation for most keys and values.
+
+use fnv::FnvHashSet as HashSet;
+
+use crate::{Element, TagString, Tags};
+
+/// Keys and values common enough to intern from the start, as static strings
+const COMMON: &[&str] = &[
+    "access",
+    "addr:city",
+    "addr:housenumber",
+    "addr:postcode",
+    "addr:street",
+    "amenity",
+    "asphalt",
+    "barrier",
+    "building",
+    "created_by",
+    "footway",
+    "highway",
+    "house",
+    "landuse",
+    "layer",
+    "maxspeed",
+    "name",
+    "natural",
+    "no",
+    "oneway",
+    "power",
+    "primary",
+    "ref",
+    "residential",
+    "secondary",
+    "service",
+    "source",
+    "surface",
+    "tertiary",
+    "track",
+    "tree",
+    "unclassified",
+    "water",
+    "waterway",
+    "yes",
+];
+
+/// Set of distinct strings, see the [module](self) docs
+#[derive(Debug, Clone, Default)]
+pub struct TagInterner {
+    strings: HashSet<TagString>,
+    hits: u64,
+}
+
+impl TagInterner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Interner that already holds the most common keys and values
+    pub fn with_common() -> Self {
+        Self {
+            strings: COMMON.iter().map(|s| TagString::from_static(s)).collect(),
+            hits: 0,
+        }
+    }
+
+    /// Copy of the interned string equal to `s`, interning it if there is none
+    pub fn intern(&mut self, s: &str) -> TagString {
+        if let Some(interned) = self.strings.get(s) {
+            self.hits += 1;
+            return interned.clone();
+        }
+        let interned = TagString::from_ref(s);
+        self.strings.insert(interned.clone());
+        interned
+    }
+
+    /// Replaces the keys and values with interned copies
+    ///
+    /// Maps shared between elements are left as they are, since they are stored only once.
+    pub fn intern_tags(&mut self, tags: &mut Tags) {
+        if tags.is_empty() || tags.is_shared() {
+            return;
+        }
+        *tags = tags
+            .iter()
+            .map(|(key, value)| (self.intern(key), self.intern(value)))
+            .collect();
+    }
+
+    /// Number of distinct strings
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Number of strings that were already interned
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+}
+
+impl Element {
+    /// Replaces the keys and values of the tags with interned copies, see
+    /// [TagInterner::intern_tags]
+    pub fn intern_tags(&mut self, interner: &mut TagInterner) {
+        interner.intern_tags(self.tags_mut());
+    }
+}
@@ -0,0 +1,178 @@
+//! URLs for the [JOSM](https://josm.openstreetmap.de) remote control
+//!
+//! JOSM listens on [DEFAULT_BASE] while remote control is enabled, so a QA tool can link to a
+//! [Command::url] to open the affected elements in the editor.
+//!
+//! <https://josm.openstreetmap.de/wiki/Help/RemoteControlCommands>
+
+use std::fmt;
+
+use crate::bbox::Bbox;
+use crate::scalar::ScalarExt;
+use crate::{Element, Id, MemberType, TagString};
+
+/// Address of the remote control on the local machine
+pub const DEFAULT_BASE: &str = "http://127.0.0.1:8111";
+
+/// Error returned when a tag cannot be passed to `addtags`
+///
+/// JOSM separates tags with `|` and keys from values with the first `=`, so neither may
+/// appear in keys and `|` may not appear in values.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct InvalidTagError {
+    pub key: TagString,
+}
+
+impl fmt::Display for InvalidTagError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "tag {} cannot be passed to JOSM", self.key)
+    }
+}
+
+impl std::error::Error for InvalidTagError {}
+
+impl From<InvalidTagError> for crate::Error {
+    fn from(err: InvalidTagError) -> Self {
+        crate::Error::Parse(Box::new(err))
+    }
+}
+
+/// Remote control request, built with one of the constructors and optional parameters
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Command {
+    name: &'static str,
+    params: Vec<(&'static str, String)>,
+}
+
+impl Command {
+    /// Downloads the box and zooms to it
+    pub fn load_and_zoom(bbox: &Bbox) -> Self {
+        Self {
+            name: "load_and_zoom",
+            params: vec![
+                ("left", bbox.min_lon.normalized().to_string()),
+                ("right", bbox.max_lon.normalized().to_string()),
+                ("bottom", bbox.min_lat.normalized().to_string()),
+                ("top", bbox.max_lat.normalized().to_string()),
+            ],
+        }
+    }
+
+    /// Downloads the given elements from the API
+    pub fn load_object<'a>(objects: impl IntoIterator<Item = (&'a MemberType, Id)>) -> Self {
+        Self {
+            name: "load_object",
+            params: vec![("objects", object_list(objects))],
+        }
+    }
+
+    /// Downloads the elements, e.g. the ones reported by a validator
+    pub fn load_elements<'a>(elements: impl IntoIterator<Item = &'a Element>) -> Self {
+        Self::load_object(elements.into_iter().map(|element| {
+            let ty = match element {
+                Element::Node(_) => &MemberType::Node,
+                Element::Way(_) => &MemberType::Way,
+                Element::Relation(_) => &MemberType::Relation,
+            };
+            (ty, element.id())
+        }))
+    }
+
+    /// Selects the elements once loaded, for [Command::load_and_zoom]
+    pub fn select<'a>(mut self, objects: impl IntoIterator<Item = (&'a MemberType, Id)>) -> Self {
+        self.params.push(("select", object_list(objects)));
+        self
+    }
+
+    /// Adds the tags to the selected or loaded elements, after confirmation in JOSM
+    pub fn add_tags<'a>(
+        mut self,
+        tags: impl IntoIterator<Item = (&'a TagString, &'a TagString)>,
+    ) -> Result<Self, InvalidTagError> {
+        let mut tags: Vec<_> = tags.into_iter().collect();
+        tags.sort_unstable();
+        let mut list = String::new();
+        for (key, value) in tags {
+            if key.is_empty() || key.contains(['|', '=']) || value.contains('|') {
+                return Err(InvalidTagError { key: key.clone() });
+            }
+            if !list.is_empty() {
+                list.push('|');
+            }
+            list.push_str(key);
+            list.push('=');
+            list.push_str(value);
+        }
+        self.params.push(("addtags", list));
+        Ok(self)
+    }
+
+    /// Loads into a new layer instead of the active one
+    pub fn new_layer(mut self) -> Self {
+        self.params.push(("new_layer", "true".to_string()));
+        self
+    }
+
+    /// Also downloads the members of relations, for [Command::load_object]
+    pub fn relation_members(mut self) -> Self {
+        self.params.push(("relation_members", "true".to_string()));
+        self
+    }
+
+    /// Also downloads the ways and relations using the elements, for [Command::load_object]
+    pub fn referrers(mut self) -> Self {
+        self.params.push(("referrers", "true".to_string()));
+        self
+    }
+
+    /// URL of the command on a remote control listening at `base`, e.g. [DEFAULT_BASE]
+    pub fn url_at(&self, base: &str) -> String {
+        let mut url = format!("{}/{}", base.trim_end_matches('/'), self.name);
+        for (i, (key, value)) in self.params.iter().enumerate() {
+            url.push(if i == 0 { '?' } else { '&' });
+            url.push_str(key);
+            url.push('=');
+            encode(&mut url, value);
+        }
+        url
+    }
+
+    pub fn url(&self) -> String {
+        self.url_at(DEFAULT_BASE)
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.url())
+    }
+}
+
+/// Comma-separated list of objects, e.g. `n1,w2,r3`
+fn object_list<'a>(objects: impl IntoIterator<Item = (&'a MemberType, Id)>) -> String {
+    objects
+        .into_iter()
+        .map(|(ty, id)| {
+            let prefix = match ty {
+                MemberType::Node => 'n',
+                MemberType::Way => 'w',
+                MemberType::Relation => 'r',
+            };
+            format!("{prefix}{}", id.0)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Percent-encodes everything except unreserved characters of
+/// [RFC 3986](https://datatracker.ietf.org/doc/html/rfc3986#section-2.3)
+fn encode(url: &mut String, value: &str) {
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                url.push(byte as char)
+            }
+            _ => url.push_str(&format!("%{byte:02X}")),
+        }
+    }
+}
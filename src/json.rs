@@ -0,0 +1,1051 @@
+//! [OSM JSON](https://wiki.openstreetmap.org/wiki/OSM_JSON) as returned by the API
+//!
+//! Calls such as `api/0.6/map.json` return elements in a flat schema that differs from the
+//! serde representation of [crate::Element]. [Element] matches that schema and converts
+//! losslessly to and from the core types. [Document] is the body of a response. The Overpass
+//! API uses the same elements, see [crate::overpass].
+//!
+//! With the `json` feature, [Writer] streams elements into a document and [Decoder] decodes
+//! them from a document pushed in chunks, see [crate::feed]. With the `tokio` feature as well,
+//! [AsyncWriter] writes to a [tokio::io::AsyncWrite].
+
+#[cfg(feature = "json")]
+use std::collections::VecDeque;
+#[cfg(feature = "json")]
+use std::io::{self, Write};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::bbox::Bbox;
+use crate::error::{Error, Result};
+#[cfg(feature = "json")]
+use crate::feed::Decode;
+#[cfg(feature = "json")]
+use crate::pipeline::Sink;
+use crate::scalar::{Scalar, ScalarExt};
+use crate::{Id, Info, Member, MemberType, Tags, Timestamp};
+
+/// Decimal places of coordinates, which OSM stores as 100 nanodegrees
+const COORDINATE_PLACES: u32 = 7;
+
+/// Body of an API response
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub struct Document {
+    /// Version of the API, `0.6` for current responses
+    ///
+    /// Read from a number as well, which Overpass returns.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "version")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub copyright: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// Area of a `map` call
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "bounds")]
+    pub bounds: Option<Bbox>,
+    pub elements: Vec<Element>,
+}
+
+impl Document {
+    /// Empty document of version `0.6` from [crate::xml::DEFAULT_GENERATOR]
+    pub fn new() -> Self {
+        Self {
+            version: Some("0.6".to_string()),
+            generator: Some(crate::xml::DEFAULT_GENERATOR.to_string()),
+            copyright: None,
+            attribution: None,
+            license: None,
+            bounds: None,
+            elements: vec![],
+        }
+    }
+
+    /// Nodes, ways, and relations of the document, skipping other types
+    pub fn into_elements(self) -> impl Iterator<Item = crate::Element> {
+        self.elements
+            .into_iter()
+            .filter_map(|element| element.try_into().ok())
+    }
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FromIterator<crate::Element> for Document {
+    fn from_iter<T: IntoIterator<Item = crate::Element>>(iter: T) -> Self {
+        Self {
+            elements: iter.into_iter().map(Into::into).collect(),
+            ..Self::new()
+        }
+    }
+}
+
+/// Element of a document
+///
+/// Overpass responses may also contain areas and counts, see [Element::Other].
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Element {
+    Node(Node),
+    Way(Way),
+    Relation(Relation),
+    /// Any other type, which has no representation in [crate::Element]
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub struct Node {
+    pub id: Id,
+    #[serde(with = "coordinate")]
+    pub lat: Scalar,
+    #[serde(with = "coordinate")]
+    pub lon: Scalar,
+    #[serde(default, skip_serializing_if = "no_tags")]
+    pub tags: Tags,
+    #[serde(flatten)]
+    pub meta: Meta,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Way {
+    pub id: Id,
+    #[serde(default)]
+    pub nodes: Vec<Id>,
+    #[serde(default, skip_serializing_if = "no_tags")]
+    pub tags: Tags,
+    #[serde(flatten)]
+    pub meta: Meta,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Relation {
+    pub id: Id,
+    #[serde(default)]
+    pub members: Vec<RelationMember>,
+    #[serde(default, skip_serializing_if = "no_tags")]
+    pub tags: Tags,
+    #[serde(flatten)]
+    pub meta: Meta,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct RelationMember {
+    #[serde(rename = "type", with = "member_type")]
+    pub ty: MemberType,
+    #[serde(rename = "ref")]
+    pub id: Id,
+    /// Empty if the member has no role
+    #[serde(default)]
+    pub role: String,
+}
+
+/// Attributes returned by `out meta`, which correspond to [Info]
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+pub struct Meta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "timestamp")]
+    pub timestamp: Option<Timestamp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changeset: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<i32>,
+    /// Only returned for deleted elements of attic queries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visible: Option<bool>,
+}
+
+fn no_tags(tags: &Tags) -> bool {
+    tags.is_empty()
+}
+
+impl From<Option<Info>> for Meta {
+    fn from(info: Option<Info>) -> Self {
+        let Some(info) = info else {
+            return Self::default();
+        };
+        Self {
+            // Version 0 means unknown, as in the XML formats
+            version: (info.version != 0).then_some(info.version),
+            timestamp: info.timestamp,
+            changeset: info.changeset,
+            user: info.user.map(|user| user.to_string()),
+            uid: info.uid,
+            visible: info.visible,
+        }
+    }
+}
+
+impl From<Meta> for Option<Info> {
+    fn from(meta: Meta) -> Self {
+        if meta == Meta::default() {
+            return None;
+        }
+        Some(Info {
+            version: meta.version.unwrap_or_default(),
+            timestamp: meta.timestamp,
+            changeset: meta.changeset,
+            uid: meta.uid,
+            user: meta.user.map(Into::into),
+            visible: meta.visible,
+        })
+    }
+}
+
+impl From<crate::Element> for Element {
+    fn from(element: crate::Element) -> Self {
+        match element {
+            crate::Element::Node(node) => Element::Node(Node {
+                id: node.id,
+                lat: node.lat,
+                lon: node.lon,
+                tags: node.tags,
+                meta: node.info.into(),
+            }),
+            crate::Element::Way(way) => Element::Way(Way {
+                id: way.id,
+                nodes: way.refs.into_iter().collect(),
+                tags: way.tags,
+                meta: way.info.into(),
+            }),
+            crate::Element::Relation(relation) => Element::Relation(Relation {
+                id: relation.id,
+                members: relation
+                    .members
+                    .into_iter()
+                    .map(|member| RelationMember {
+                        ty: member.ty,
+                        id: member.id,
+                        role: member.role.as_deref().unwrap_or_default().to_string(),
+                    })
+                    .collect(),
+                tags: relation.tags,
+                meta: relation.info.into(),
+            }),
+        }
+    }
+}
+
+impl TryFrom<Element> for crate::Element {
+    type Error = Error;
+
+    /// Fails for [Element::Other]
+    fn try_from(element: Element) -> Result<Self> {
+        Ok(match element {
+            Element::Node(node) => crate::Element::Node(crate::Node {
+                id: node.id,
+                tags: node.tags,
+                info: node.meta.into(),
+                lat: node.lat,
+                lon: node.lon,
+            }),
+            Element::Way(way) => crate::Element::Way(crate::Way {
+                id: way.id,
+                tags: way.tags,
+                info: way.meta.into(),
+                refs: way.nodes.into_iter().collect(),
+            }),
+            Element::Relation(relation) => crate::Element::Relation(crate::Relation {
+                id: relation.id,
+                tags: relation.tags,
+                info: relation.meta.into(),
+                members: relation
+                    .members
+                    .into_iter()
+                    .map(|member| Member {
+                        id: member.id,
+                        ty: member.ty,
+                        role: (!member.role.is_empty()).then(|| member.role.into()),
+                    })
+                    .collect(),
+            }),
+            Element::Other => {
+                return Err(Error::Validation {
+                    element: None,
+                    message: "element is not a node, way, or relation".to_string(),
+                })
+            }
+        })
+    }
+}
+
+/// [Decode]r of an OSM JSON [Document] pushed in chunks, which yields the same as
+/// [Document::into_elements]
+///
+/// Each element is decoded once all of it was pushed, so the input buffered is about the size
+/// of the largest element. Overpass responses are read the same way, and their other members,
+/// e.g. `osm3s`, are skipped.
+#[cfg(feature = "json")]
+#[derive(Debug)]
+pub struct Decoder {
+    state: DecoderState,
+    /// Offset of the next byte pushed
+    offset: u64,
+    /// Members of the document other than its elements, without the enclosing braces
+    fields: Vec<u8>,
+    header: Document,
+    /// Key or element being scanned
+    pending: Vec<u8>,
+    /// Offset of the start of [Decoder::pending]
+    pending_offset: u64,
+    value: ValueEnd,
+    decoded: VecDeque<Result<crate::Element>>,
+}
+
+#[cfg(feature = "json")]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum DecoderState {
+    /// Before the document
+    Start,
+    /// Before the key of a member of the document, or its end if `first`
+    Key { first: bool },
+    /// Inside the key of a member
+    InKey { escape: bool },
+    /// Between the key of a member and its value
+    Colon,
+    /// Inside the value of a member other than the elements
+    Value,
+    /// After the value of a member
+    AfterValue,
+    /// Between the key of the elements and the array
+    ElementsStart,
+    /// Between elements, or before the end of the array
+    Elements,
+    /// Inside an element
+    InElement,
+    /// After the document
+    Done,
+    /// After an error, once the input is ignored
+    Failed,
+}
+
+#[cfg(feature = "json")]
+impl Decoder {
+    pub fn new() -> Self {
+        Self {
+            state: DecoderState::Start,
+            offset: 0,
+            fields: vec![],
+            header: Document {
+                version: None,
+                generator: None,
+                elements: vec![],
+                ..Document::new()
+            },
+            pending: vec![],
+            pending_offset: 0,
+            value: ValueEnd::default(),
+            decoded: VecDeque::new(),
+        }
+    }
+
+    /// Members of the document other than its elements, which are available once the first
+    /// element was decoded
+    ///
+    /// The elements of the header are always empty.
+    pub fn header(&self) -> &Document {
+        &self.header
+    }
+
+    fn fail(&mut self, offset: u64, message: impl Into<String>) {
+        self.decoded
+            .push_back(Err(Error::decode(Some(offset), message)));
+        self.state = DecoderState::Failed;
+    }
+
+    /// Parses the members seen so far into the header
+    fn parse_header(&mut self, offset: u64) {
+        let mut document = b"{".to_vec();
+        document.extend_from_slice(&self.fields);
+        if !self.fields.is_empty() {
+            document.push(b',');
+        }
+        document.extend_from_slice(b"\"elements\":[]}");
+        match serde_json::from_slice(&document) {
+            Ok(header) => self.header = header,
+            Err(err) => self.fail(offset, format!("invalid document: {err}")),
+        }
+    }
+
+    fn push_byte(&mut self, byte: u8) {
+        use DecoderState::*;
+
+        let offset = self.offset;
+        let whitespace = byte.is_ascii_whitespace();
+        match self.state {
+            Start | Key { .. } | Colon | AfterValue | ElementsStart | Elements | Done
+                if whitespace => {}
+            Start if byte == b'{' => self.state = Key { first: true },
+            Key { first: true } | AfterValue if byte == b'}' => {
+                self.parse_header(offset);
+                if self.state != Failed {
+                    self.state = Done;
+                }
+            }
+            Key { .. } if byte == b'"' => {
+                self.pending.clear();
+                self.state = InKey { escape: false };
+            }
+            InKey { escape } => {
+                if !escape && byte == b'"' {
+                    self.state = Colon;
+                } else {
+                    self.pending.push(byte);
+                    self.state = InKey {
+                        escape: !escape && byte == b'\\',
+                    };
+                }
+            }
+            Colon if byte == b':' && self.pending == b"elements" => self.state = ElementsStart,
+            Colon if byte == b':' => {
+                if !self.fields.is_empty() {
+                    self.fields.push(b',');
+                }
+                self.fields.push(b'"');
+                self.fields.append(&mut self.pending);
+                self.fields.extend_from_slice(b"\":");
+                self.value = ValueEnd::default();
+                self.state = Value;
+            }
+            Value => match self.value.push(byte) {
+                Boundary::Inside => self.fields.push(byte),
+                Boundary::Last => {
+                    self.fields.push(byte);
+                    self.state = AfterValue;
+                }
+                Boundary::After => {
+                    self.state = AfterValue;
+                    self.push_byte(byte);
+                    return;
+                }
+            },
+            AfterValue if byte == b',' => self.state = Key { first: false },
+            ElementsStart if byte == b'[' => {
+                self.parse_header(offset);
+                if self.state != Failed {
+                    self.state = Elements;
+                }
+            }
+            Elements if byte == b',' => {}
+            Elements if byte == b']' => self.state = AfterValue,
+            Elements if byte == b'{' => {
+                self.pending.clear();
+                self.pending.push(byte);
+                self.pending_offset = offset;
+                self.value = ValueEnd::default();
+                self.value.push(byte);
+                self.state = InElement;
+            }
+            InElement => {
+                self.pending.push(byte);
+                if self.value.push(byte) == Boundary::Last {
+                    self.decode_element();
+                    self.state = Elements;
+                }
+            }
+            Failed => {}
+            _ => self.fail(offset, format!("unexpected {:?}", char::from(byte))),
+        }
+        self.offset += 1;
+    }
+
+    fn decode_element(&mut self) {
+        let element = match serde_json::from_slice::<Element>(&self.pending) {
+            Ok(Element::Other) => return,
+            Ok(element) => element.try_into(),
+            Err(err) => Err(Error::decode(
+                Some(self.pending_offset),
+                format!("invalid element: {err}"),
+            )),
+        };
+        self.decoded.push_back(element);
+    }
+}
+
+#[cfg(feature = "json")]
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "json")]
+impl Decode for Decoder {
+    type Item = crate::Element;
+
+    fn push(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.push_byte(byte);
+        }
+    }
+
+    fn end(&mut self) {
+        if !matches!(self.state, DecoderState::Done | DecoderState::Failed) {
+            self.fail(self.offset, "unexpected end of document");
+        }
+    }
+
+    fn decode(&mut self) -> Option<Result<crate::Element>> {
+        self.decoded.pop_front()
+    }
+}
+
+/// Whether a byte pushed to [ValueEnd] ends the value
+#[cfg(feature = "json")]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Boundary {
+    Inside,
+    /// The byte is the last of the value
+    Last,
+    /// The value ended before the byte, which is how numbers and literals end
+    After,
+}
+
+/// Finds the end of a JSON value pushed a byte at a time, leaving its validation to serde
+#[cfg(feature = "json")]
+#[derive(Debug, Default)]
+struct ValueEnd {
+    depth: usize,
+    string: bool,
+    escape: bool,
+    scalar: bool,
+}
+
+#[cfg(feature = "json")]
+impl ValueEnd {
+    fn push(&mut self, byte: u8) -> Boundary {
+        if self.string {
+            if self.escape {
+                self.escape = false;
+            } else if byte == b'\\' {
+                self.escape = true;
+            } else if byte == b'"' {
+                self.string = false;
+                if self.depth == 0 {
+                    return Boundary::Last;
+                }
+            }
+            return Boundary::Inside;
+        }
+        if self.scalar {
+            return match byte {
+                b',' | b'}' | b']' => Boundary::After,
+                _ if byte.is_ascii_whitespace() => Boundary::After,
+                _ => Boundary::Inside,
+            };
+        }
+        match byte {
+            b'"' => self.string = true,
+            b'{' | b'[' => self.depth += 1,
+            b'}' | b']' if self.depth <= 1 => {
+                self.depth = 0;
+                return Boundary::Last;
+            }
+            b'}' | b']' => self.depth -= 1,
+            _ if byte.is_ascii_whitespace() => {}
+            _ if self.depth == 0 => self.scalar = true,
+            _ => {}
+        }
+        Boundary::Inside
+    }
+}
+
+/// Writer of [crate::Element]s in an OSM JSON [Document]
+///
+/// The members of the header are written with the first element, one element per line, and
+/// [Writer::finish] closes the document. Each element is written with a single call to the
+/// output, which should still be buffered if it is a file.
+#[cfg(feature = "json")]
+#[derive(Debug)]
+pub struct Writer<W> {
+    output: W,
+    header: Document,
+    buf: Vec<u8>,
+    state: WriterState,
+}
+
+#[cfg(feature = "json")]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum WriterState {
+    Empty,
+    Open,
+    /// After the first element
+    Separated,
+    Finished,
+}
+
+#[cfg(feature = "json")]
+impl<W: Write> Writer<W> {
+    /// Writer with the header from [Document::new]
+    pub fn new(output: W) -> Self {
+        Self::with_header(output, Document::new())
+    }
+
+    /// Writer with the members of `header` other than its elements, which are not written
+    pub fn with_header(output: W, header: Document) -> Self {
+        Self {
+            output,
+            header: Document {
+                elements: vec![],
+                ..header
+            },
+            buf: vec![],
+            state: WriterState::Empty,
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = element.id().0)))]
+    pub fn write(&mut self, element: &crate::Element) -> Result<()> {
+        self.buf.clear();
+        self.start()?;
+        if self.state == WriterState::Separated {
+            self.buf.push(b',');
+        }
+        self.buf.push(b'\n');
+        serde_json::to_writer(&mut self.buf, &Element::from(element.clone()))
+            .map_err(io::Error::from)?;
+        self.state = WriterState::Separated;
+        self.output.write_all(&self.buf)?;
+        Ok(())
+    }
+
+    /// Closes the document, writing the header first if no element was written
+    pub fn finish(&mut self) -> Result<()> {
+        self.buf.clear();
+        self.start()?;
+        self.buf.extend_from_slice(b"\n]}\n");
+        self.output.write_all(&self.buf)?;
+        self.output.flush()?;
+        self.state = WriterState::Finished;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    /// Adds the header to the buffer if not written yet
+    fn start(&mut self) -> Result<()> {
+        match self.state {
+            WriterState::Empty => {}
+            WriterState::Open | WriterState::Separated => return Ok(()),
+            WriterState::Finished => {
+                return Err(Error::Validation {
+                    element: None,
+                    message: "writer is already finished".to_string(),
+                })
+            }
+        }
+        // The elements are the last member, so the header ends with an empty array to open
+        serde_json::to_writer(&mut self.buf, &self.header).map_err(io::Error::from)?;
+        debug_assert!(self.buf.ends_with(b"[]}"));
+        self.buf.truncate(self.buf.len() - 2);
+        self.state = WriterState::Open;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "json")]
+impl<W: Write> Sink for Writer<W> {
+    type Error = Error;
+
+    fn write(&mut self, element: crate::Element) -> Result<()> {
+        Writer::write(self, &element)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        Writer::finish(self)
+    }
+}
+
+/// [Writer] to a [tokio::io::AsyncWrite], e.g. a socket
+///
+/// Each element is written to the output once it is encoded, so the output need not be
+/// buffered.
+#[cfg(all(feature = "json", feature = "tokio"))]
+#[derive(Debug)]
+pub struct AsyncWriter<W> {
+    output: W,
+    writer: Writer<Vec<u8>>,
+}
+
+#[cfg(all(feature = "json", feature = "tokio"))]
+impl<W: tokio::io::AsyncWrite + Unpin> AsyncWriter<W> {
+    /// Writer with the header from [Document::new]
+    pub fn new(output: W) -> Self {
+        Self::with_header(output, Document::new())
+    }
+
+    /// Writer with the members of `header` other than its elements, which are not written
+    pub fn with_header(output: W, header: Document) -> Self {
+        Self {
+            output,
+            writer: Writer::with_header(vec![], header),
+        }
+    }
+
+    pub async fn write(&mut self, element: &crate::Element) -> Result<()> {
+        self.writer.write(element)?;
+        self.write_encoded().await
+    }
+
+    /// Closes the document, writing the header first if no element was written
+    pub async fn finish(&mut self) -> Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        self.writer.finish()?;
+        self.write_encoded().await?;
+        self.output.flush().await?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    async fn write_encoded(&mut self) -> Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        self.output.write_all(&self.writer.output).await?;
+        self.writer.output.clear();
+        Ok(())
+    }
+}
+
+/// Coordinates as JSON numbers, rounded to the precision of OSM when read
+pub(crate) mod coordinate {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        value: &Scalar,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_f64(value.as_f64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Scalar, D::Error> {
+        let value = f64::deserialize(deserializer)?;
+        Scalar::try_from_f64(value)
+            .map(|value| value.round_places(COORDINATE_PLACES))
+            .ok_or_else(|| serde::de::Error::custom("invalid coordinate"))
+    }
+}
+
+/// Timestamps in the format of the API, e.g. `2024-01-31T12:00:00Z`
+pub(crate) mod timestamp {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Timestamp>,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.collect_str(&crate::format_timestamp(*value)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Option<Timestamp>, D::Error> {
+        let Some(value) = Option::<String>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        crate::parse_timestamp(&value)
+            .map(Some)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid timestamp {value}")))
+    }
+}
+
+/// Versions as strings, read from numbers as well
+mod version {
+    use std::fmt;
+
+    use serde::de::{self, Visitor};
+
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<String>,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        value.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Option<String>, D::Error> {
+        deserializer.deserialize_option(OptionVisitor)
+    }
+
+    struct OptionVisitor;
+
+    impl<'de> Visitor<'de> for OptionVisitor {
+        type Value = Option<String>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a version as a string or number")
+        }
+
+        fn visit_none<E: de::Error>(self) -> std::result::Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_unit<E: de::Error>(self) -> std::result::Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_some<D: Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> std::result::Result<Self::Value, D::Error> {
+            deserializer.deserialize_any(self)
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> std::result::Result<Self::Value, E> {
+            Ok(Some(value.to_string()))
+        }
+
+        fn visit_f64<E: de::Error>(self, value: f64) -> std::result::Result<Self::Value, E> {
+            Ok(Some(value.to_string()))
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> std::result::Result<Self::Value, E> {
+            Ok(Some(value.to_string()))
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> std::result::Result<Self::Value, E> {
+            Ok(Some(value.to_string()))
+        }
+    }
+}
+
+mod member_type {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        value: &MemberType,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(value.as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<MemberType, D::Error> {
+        match String::deserialize(deserializer)?.as_str() {
+            "node" => Ok(MemberType::Node),
+            "way" => Ok(MemberType::Way),
+            "relation" => Ok(MemberType::Relation),
+            other => Err(serde::de::Error::unknown_variant(
+                other,
+                &["node", "way", "relation"],
+            )),
+        }
+    }
+}
+
+/// Bounds with the attribute names of the XML format
+mod bounds {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Bounds {
+        #[serde(with = "coordinate")]
+        minlat: Scalar,
+        #[serde(with = "coordinate")]
+        minlon: Scalar,
+        #[serde(with = "coordinate")]
+        maxlat: Scalar,
+        #[serde(with = "coordinate")]
+        maxlon: Scalar,
+    }
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Bbox>,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        value
+            .map(|bbox| Bounds {
+                minlat: bbox.min_lat,
+                minlon: bbox.min_lon,
+                maxlat: bbox.max_lat,
+                maxlon: bbox.max_lon,
+            })
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Option<Bbox>, D::Error> {
+        Ok(Option::<Bounds>::deserialize(deserializer)?
+            .map(|bounds| Bbox::new(bounds.minlat, bounds.minlon, bounds.maxlat, bounds.maxlon)))
+    }
+}
+
+/// Serde adapter for [crate::Element] in this schema
+///
+/// Use it with `#[serde(with = "osm_types::json::element")]` on fields of other types.
+pub mod element {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        value: &crate::Element,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        Element::from(value.clone()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<crate::Element, D::Error> {
+        Element::deserialize(deserializer)?
+            .try_into()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use super::*;
+    use crate::{Id, WayId};
+
+    fn elements() -> Vec<crate::Element> {
+        vec![
+            crate::Element::Node(
+                crate::Node::builder(Id(1))
+                    .lat(Scalar::from_int(52))
+                    .lon(Scalar::from_int(13))
+                    .tag("name", "Quote \" and brace }")
+                    .build()
+                    .unwrap(),
+            ),
+            crate::Element::Way(
+                crate::Way::builder(Id(2))
+                    .nodes([1, 3].map(Id))
+                    .tag("highway", "path")
+                    .build()
+                    .unwrap(),
+            ),
+            crate::Element::Relation(
+                crate::Relation::builder(Id(3))
+                    .member(WayId(2), "outer")
+                    .build()
+                    .unwrap(),
+            ),
+        ]
+    }
+
+    fn decode_all(data: &[u8], chunk: usize) -> (Document, Vec<Result<crate::Element>>) {
+        let mut decoder = Decoder::new();
+        let mut decoded = vec![];
+        for data in data.chunks(chunk) {
+            decoder.push(data);
+            decoded.extend(std::iter::from_fn(|| decoder.decode()));
+        }
+        decoder.end();
+        decoded.extend(std::iter::from_fn(|| decoder.decode()));
+        (decoder.header().clone(), decoded)
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut header = Document::new();
+        header.copyright = Some("OpenStreetMap and contributors".to_string());
+        let mut writer = Writer::with_header(vec![], header.clone());
+        for element in elements() {
+            writer.write(&element).unwrap();
+        }
+        writer.finish().unwrap();
+        let data = writer.into_inner();
+
+        let document: Document = serde_json::from_slice(&data).unwrap();
+        assert_eq!(document.into_elements().collect::<Vec<_>>(), elements());
+        for chunk in [1, 5, data.len()] {
+            let (decoded_header, decoded) = decode_all(&data, chunk);
+            assert_eq!(decoded_header, header);
+            let decoded: Vec<_> = decoded
+                .into_iter()
+                .map(|element| element.unwrap())
+                .collect();
+            assert_eq!(decoded, elements());
+        }
+    }
+
+    #[test]
+    fn empty_document() {
+        let mut writer = Writer::new(vec![]);
+        writer.finish().unwrap();
+        let data = writer.into_inner();
+        let (header, decoded) = decode_all(&data, 3);
+        assert_eq!(header, Document::new());
+        assert!(decoded.is_empty());
+    }
+
+    #[test]
+    fn overpass_members_are_skipped() {
+        let data = br#"{
+            "version": 0.6,
+            "osm3s": {"timestamp_osm_base": "2024-01-31T12:00:00Z", "copyright": "[]"},
+            "elements": [
+                {"type": "area", "id": 3600000001, "tags": {"name": "]"}},
+                {"type": "node", "id": 1, "lat": 52.0, "lon": 13.0}
+            ],
+            "remark": null
+        }"#;
+        let (header, decoded) = decode_all(data, 4);
+        assert_eq!(header.version.as_deref(), Some("0.6"));
+        let decoded: Vec<_> = decoded
+            .into_iter()
+            .map(|element| element.unwrap())
+            .collect();
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].id(), Id(1));
+    }
+
+    #[test]
+    fn malformed_documents_are_errors() {
+        let (_, decoded) = decode_all(br#"{"elements": [{"type": "node", "id": "x"}]}"#, 64);
+        assert!(matches!(
+            decoded.as_slice(),
+            [Err(Error::Decode {
+                offset: Some(14),
+                ..
+            })]
+        ));
+
+        let (_, decoded) = decode_all(br#"{"elements": [{"type": "node""#, 64);
+        assert!(matches!(decoded.as_slice(), [Err(Error::Decode { .. })]));
+
+        let (_, decoded) = decode_all(br#"["elements"]"#, 64);
+        assert!(matches!(
+            decoded.as_slice(),
+            [Err(Error::Decode {
+                offset: Some(0),
+                ..
+            })]
+        ));
+    }
+
+    #[test]
+    fn write_after_finish_fails() {
+        let mut writer = Writer::new(vec![]);
+        writer.finish().unwrap();
+        assert!(writer.write(&elements()[0]).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+//! Primary feature of an element, the tag that decides what the element is
+//!
+//! <https://wiki.openstreetmap.org/wiki/Map_features>
+//!
+//! An element may carry several feature keys, e.g. a school mapped on a building outline has
+//! both `amenity=school` and `building=yes`. [ElementKind::PRIORITY] orders the keys so that
+//! the more specific feature wins: points of interest before transport, transport before
+//! land cover, and buildings, places, and boundaries last.
+
+use crate::{Element, TagString, Tags};
+
+/// Key of the primary feature of an element
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ElementKind {
+    Amenity,
+    Shop,
+    Craft,
+    Office,
+    Healthcare,
+    Tourism,
+    Historic,
+    Leisure,
+    Emergency,
+    Aeroway,
+    Aerialway,
+    Railway,
+    PublicTransport,
+    Highway,
+    Waterway,
+    ManMade,
+    Power,
+    Military,
+    Natural,
+    Landuse,
+    Building,
+    Barrier,
+    Place,
+    Boundary,
+    Route,
+}
+
+impl ElementKind {
+    /// All kinds, from the one that wins to the one that loses when an element has several
+    pub const PRIORITY: [ElementKind; 25] = {
+        use ElementKind::*;
+        [
+            Amenity,
+            Shop,
+            Craft,
+            Office,
+            Healthcare,
+            Tourism,
+            Historic,
+            Leisure,
+            Emergency,
+            Aeroway,
+            Aerialway,
+            Railway,
+            PublicTransport,
+            Highway,
+            Waterway,
+            ManMade,
+            Power,
+            Military,
+            Natural,
+            Landuse,
+            Building,
+            Barrier,
+            Place,
+            Boundary,
+            Route,
+        ]
+    };
+
+    /// Key tagged for this kind, e.g. `public_transport`
+    pub fn key(self) -> &'static str {
+        use ElementKind::*;
+        match self {
+            Amenity => "amenity",
+            Shop => "shop",
+            Craft => "craft",
+            Office => "office",
+            Healthcare => "healthcare",
+            Tourism => "tourism",
+            Historic => "historic",
+            Leisure => "leisure",
+            Emergency => "emergency",
+            Aeroway => "aeroway",
+            Aerialway => "aerialway",
+            Railway => "railway",
+            PublicTransport => "public_transport",
+            Highway => "highway",
+            Waterway => "waterway",
+            ManMade => "man_made",
+            Power => "power",
+            Military => "military",
+            Natural => "natural",
+            Landuse => "landuse",
+            Building => "building",
+            Barrier => "barrier",
+            Place => "place",
+            Boundary => "boundary",
+            Route => "route",
+        }
+    }
+
+    /// Kind of a key, [None] for keys that are not primary features
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::PRIORITY.into_iter().find(|kind| kind.key() == key)
+    }
+}
+
+impl Tags {
+    /// Key and value of the primary feature, the first of [ElementKind::PRIORITY] tagged
+    ///
+    /// Tags with the value `no`, such as `building=no`, do not count.
+    pub fn primary_feature(&self) -> Option<(&TagString, &TagString)> {
+        ElementKind::PRIORITY.into_iter().find_map(|kind| {
+            self.get_key_value(kind.key())
+                .filter(|(_, value)| value.as_str() != "no")
+        })
+    }
+
+    /// Kind of the [Tags::primary_feature]
+    pub fn kind(&self) -> Option<ElementKind> {
+        self.primary_feature()
+            .and_then(|(key, _)| ElementKind::from_key(key))
+    }
+}
+
+impl Element {
+    /// See [Tags::kind]
+    pub fn kind(&self) -> Option<ElementKind> {
+        self.tags().kind()
+    }
+}
@@ -0,0 +1,331 @@
+//! Lane counts and turn indications
+//!
+//! <https://wiki.openstreetmap.org/wiki/Key:lanes>
+//! <https://wiki.openstreetmap.org/wiki/Key:turn>
+
+use std::fmt;
+
+use crate::oneway::{self, Direction, Oneway};
+use crate::{Tags, Way};
+
+/// Indication painted on a lane, as used in `turn:lanes`
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Turn {
+    /// Lane has no indication
+    None,
+    Through,
+    Left,
+    SlightLeft,
+    SharpLeft,
+    Right,
+    SlightRight,
+    SharpRight,
+    Reverse,
+    MergeToLeft,
+    MergeToRight,
+    SlideLeft,
+    SlideRight,
+}
+
+impl Turn {
+    pub fn parse(value: &str) -> Option<Self> {
+        Some(match value {
+            "" | "none" => Turn::None,
+            "through" => Turn::Through,
+            "left" => Turn::Left,
+            "slight_left" => Turn::SlightLeft,
+            "sharp_left" => Turn::SharpLeft,
+            "right" => Turn::Right,
+            "slight_right" => Turn::SlightRight,
+            "sharp_right" => Turn::SharpRight,
+            "reverse" => Turn::Reverse,
+            "merge_to_left" => Turn::MergeToLeft,
+            "merge_to_right" => Turn::MergeToRight,
+            "slide_left" => Turn::SlideLeft,
+            "slide_right" => Turn::SlideRight,
+            _ => return None,
+        })
+    }
+}
+
+/// Parses a `turn:lanes` value into the indications of each lane, from left to right
+///
+/// Lanes are separated by `|` and the indications of a single lane by `;`.
+pub fn parse_turn_lanes(value: &str) -> Option<Vec<Vec<Turn>>> {
+    value
+        .split('|')
+        .map(|lane| lane.split(';').map(|t| Turn::parse(t.trim())).collect())
+        .collect()
+}
+
+/// Lanes carrying traffic in one direction
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DirectedLanes {
+    pub count: Option<u32>,
+    /// Indications of each lane, from left to right in the direction of travel
+    pub turns: Option<Vec<Vec<Turn>>>,
+}
+
+impl DirectedLanes {
+    /// Tagged number of lanes, or else the number of lanes with turn indications
+    pub fn number(&self) -> Option<u32> {
+        self.count
+            .or_else(|| self.turns.as_ref().map(|turns| turns.len() as u32))
+    }
+}
+
+/// Lanes of a [Way] split by direction of travel
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lanes {
+    /// Total number of lanes in both directions
+    pub total: Option<u32>,
+    pub forward: DirectedLanes,
+    pub backward: DirectedLanes,
+    /// Number of center lanes usable in both directions, e.g. for turning
+    pub both_ways: Option<u32>,
+}
+
+/// Reason lane tags could not be parsed or are inconsistent
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum LaneError {
+    /// Value of the key is not a valid count or turn indication
+    Invalid { key: &'static str },
+    /// `turn:lanes` is tagged on a way that is not oneway
+    Ambiguous { key: &'static str },
+    /// Count implied by the key differs from the tagged count
+    Mismatch {
+        key: &'static str,
+        expected: u32,
+        actual: u32,
+    },
+}
+
+impl fmt::Display for LaneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LaneError::Invalid { key } => write!(f, "invalid value for {key}"),
+            LaneError::Ambiguous { key } => {
+                write!(f, "{key} is ambiguous on a way that is not oneway")
+            }
+            LaneError::Mismatch {
+                key,
+                expected,
+                actual,
+            } => write!(f, "{key} implies {actual} lanes instead of {expected}"),
+        }
+    }
+}
+
+impl std::error::Error for LaneError {}
+
+impl Lanes {
+    /// Collects lane counts and turn indications from tags
+    ///
+    /// On oneway ways, the unsuffixed `lanes` and `turn:lanes` keys apply to the direction of travel.
+    pub fn from_tags(tags: &Tags) -> Result<Self, LaneError> {
+        let count = |key: &'static str| {
+            tags.get(key)
+                .map(|value| value.trim().parse().map_err(|_| LaneError::Invalid { key }))
+                .transpose()
+        };
+        let turns = |key: &'static str| {
+            tags.get(key)
+                .map(|value| parse_turn_lanes(value).ok_or(LaneError::Invalid { key }))
+                .transpose()
+        };
+
+        let mut lanes = Lanes {
+            total: count("lanes")?,
+            forward: DirectedLanes {
+                count: count("lanes:forward")?,
+                turns: turns("turn:lanes:forward")?,
+            },
+            backward: DirectedLanes {
+                count: count("lanes:backward")?,
+                turns: turns("turn:lanes:backward")?,
+            },
+            both_ways: count("lanes:both_ways")?,
+        };
+
+        let undirected_turns = turns("turn:lanes")?;
+        match oneway::oneway(tags) {
+            Some(Oneway::Yes(direction)) => {
+                let directed = match direction {
+                    Direction::Forward => &mut lanes.forward,
+                    Direction::Backward => &mut lanes.backward,
+                };
+                directed.count = directed.count.or(lanes.total);
+                directed.turns = directed.turns.take().or(undirected_turns);
+            }
+            _ if undirected_turns.is_some() => {
+                return Err(LaneError::Ambiguous { key: "turn:lanes" })
+            }
+            _ => {}
+        }
+        Ok(lanes)
+    }
+
+    /// Checks that directional counts add up to the total and match the turn indications
+    pub fn validate(&self) -> Result<(), LaneError> {
+        for (directed, key) in [
+            (&self.forward, "turn:lanes:forward"),
+            (&self.backward, "turn:lanes:backward"),
+        ] {
+            if let (Some(expected), Some(turns)) = (directed.count, &directed.turns) {
+                let actual = turns.len() as u32;
+                if expected != actual {
+                    return Err(LaneError::Mismatch {
+                        key,
+                        expected,
+                        actual,
+                    });
+                }
+            }
+        }
+        if let (Some(expected), Some(forward), Some(backward)) =
+            (self.total, self.forward.number(), self.backward.number())
+        {
+            let actual = forward + backward + self.both_ways.unwrap_or(0);
+            if expected != actual {
+                return Err(LaneError::Mismatch {
+                    key: "lanes:forward",
+                    expected,
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Way {
+    /// See [Lanes::from_tags]
+    pub fn lanes(&self) -> Result<Lanes, LaneError> {
+        Lanes::from_tags(&self.tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TagString;
+
+    fn tags(pairs: &[(&str, &str)]) -> Tags {
+        pairs
+            .iter()
+            .map(|&(key, value)| (TagString::from_ref(key), TagString::from_ref(value)))
+            .collect()
+    }
+
+    #[test]
+    fn counts_by_direction() {
+        let lanes = Lanes::from_tags(&tags(&[
+            ("lanes", "5"),
+            ("lanes:forward", "2"),
+            ("lanes:backward", " 2 "),
+            ("lanes:both_ways", "1"),
+        ]))
+        .unwrap();
+        assert_eq!(lanes.total, Some(5));
+        assert_eq!(lanes.forward.number(), Some(2));
+        assert_eq!(lanes.backward.number(), Some(2));
+        assert_eq!(lanes.both_ways, Some(1));
+        assert_eq!(lanes.validate(), Ok(()));
+    }
+
+    #[test]
+    fn oneway_lanes_apply_to_the_direction_of_travel() {
+        let lanes = Lanes::from_tags(&tags(&[
+            ("oneway", "-1"),
+            ("lanes", "2"),
+            ("turn:lanes", "left|through;right"),
+        ]))
+        .unwrap();
+        assert_eq!(lanes.forward, DirectedLanes::default());
+        assert_eq!(lanes.backward.count, Some(2));
+        assert_eq!(
+            lanes.backward.turns,
+            Some(vec![vec![Turn::Left], vec![Turn::Through, Turn::Right]])
+        );
+        assert_eq!(lanes.validate(), Ok(()));
+
+        let implied = Lanes::from_tags(&tags(&[("junction", "roundabout"), ("lanes", "2")]));
+        assert_eq!(implied.unwrap().forward.count, Some(2));
+    }
+
+    #[test]
+    fn turn_lanes_need_a_direction() {
+        assert_eq!(
+            Lanes::from_tags(&tags(&[("lanes", "2"), ("turn:lanes", "left|")])),
+            Err(LaneError::Ambiguous { key: "turn:lanes" })
+        );
+    }
+
+    #[test]
+    fn invalid_values() {
+        assert_eq!(
+            Lanes::from_tags(&tags(&[("lanes", "two")])),
+            Err(LaneError::Invalid { key: "lanes" })
+        );
+        assert_eq!(
+            Lanes::from_tags(&tags(&[("lanes:forward", "-1")])),
+            Err(LaneError::Invalid {
+                key: "lanes:forward"
+            })
+        );
+        assert_eq!(
+            Lanes::from_tags(&tags(&[("turn:lanes:backward", "left|sideways")])),
+            Err(LaneError::Invalid {
+                key: "turn:lanes:backward"
+            })
+        );
+    }
+
+    #[test]
+    fn empty_lanes_between_separators() {
+        assert_eq!(
+            parse_turn_lanes("left||none|"),
+            Some(vec![
+                vec![Turn::Left],
+                vec![Turn::None],
+                vec![Turn::None],
+                vec![Turn::None]
+            ])
+        );
+    }
+
+    #[test]
+    fn mismatched_counts() {
+        let lanes = Lanes::from_tags(&tags(&[
+            ("lanes:forward", "2"),
+            ("turn:lanes:forward", "left|through|right"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            lanes.validate(),
+            Err(LaneError::Mismatch {
+                key: "turn:lanes:forward",
+                expected: 2,
+                actual: 3
+            })
+        );
+
+        let lanes = Lanes::from_tags(&tags(&[
+            ("lanes", "4"),
+            ("lanes:forward", "2"),
+            ("turn:lanes:backward", "left|through|through"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            lanes.validate(),
+            Err(LaneError::Mismatch {
+                key: "lanes:forward",
+                expected: 4,
+                actual: 5
+            })
+        );
+    }
+}
@@ -1,12 +1,225 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "alloc")))]
+compile_error!("osm-types needs either the `std` or the `alloc` feature");
+#[cfg(not(any(feature = "std", feature = "decimal")))]
+compile_error!("osm-types needs the `decimal` feature without `std`, which rounds floats");
+#[cfg(not(any(
+    feature = "std",
+    feature = "arc-str",
+    feature = "box-str",
+    feature = "compact-str"
+)))]
+compile_error!("osm-types needs the `arc-str` or `box-str` feature without `std`");
+
+extern crate alloc;
+
+use core::fmt;
+
+#[cfg(not(feature = "utc"))]
 use chrono::NaiveDateTime;
-use fnv::FnvHashMap as HashMap;
-use kstring::KString;
-use rust_decimal::Decimal;
+use chrono::{DateTime, Timelike, Utc};
+
+use crate::scalar::Scalar;
+
+#[cfg(feature = "std")]
+pub mod access;
+#[cfg(feature = "std")]
+pub mod address;
+#[cfg(feature = "std")]
+pub mod adiff;
+#[cfg(feature = "std")]
+pub mod area;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "std")]
+pub mod bbox;
+#[cfg(feature = "std")]
+pub mod borrowed;
+#[cfg(feature = "std")]
+pub mod boundaries;
+#[cfg(feature = "std")]
+pub mod builder;
+#[cfg(feature = "std")]
+pub mod canonical;
+#[cfg(feature = "std")]
+pub mod category;
+#[cfg(feature = "std")]
+pub mod change;
+#[cfg(feature = "std")]
+pub mod changeset;
+#[cfg(feature = "std")]
+pub mod clip;
+#[cfg(feature = "std")]
+pub mod codec;
+#[cfg(feature = "std")]
+pub mod conflate;
+#[cfg(feature = "std")]
+pub mod contact;
+#[cfg(feature = "std")]
+pub mod coordinate;
+#[cfg(feature = "std")]
+pub mod date;
+#[cfg(feature = "std")]
+pub mod dedupe;
+#[cfg(feature = "std")]
+pub mod diff;
+#[cfg(feature = "std")]
+pub mod dms;
+#[cfg(feature = "std")]
+pub mod elevation;
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(feature = "std")]
+pub mod expire;
+#[cfg(feature = "std")]
+pub mod feed;
+#[cfg(feature = "std")]
+pub mod fetch;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod filter;
+#[cfg(feature = "std")]
+pub mod flatten;
+#[cfg(feature = "geo")]
+pub mod geo;
+#[cfg(feature = "std")]
+pub mod geohash;
+#[cfg(feature = "geojson")]
+pub mod geojson;
+#[cfg(feature = "std")]
+pub mod geom;
+#[cfg(feature = "geopackage")]
+pub mod geopackage;
+#[cfg(feature = "std")]
+pub mod gpx;
+#[cfg(feature = "std")]
+pub mod graph;
+#[cfg(feature = "h3")]
+pub mod h3;
+#[cfg(feature = "std")]
+pub mod history;
+pub mod id;
+#[cfg(feature = "std")]
+pub mod intern;
+#[cfg(feature = "std")]
+pub mod josm;
+#[cfg(all(feature = "serde", feature = "std"))]
+pub mod json;
+#[cfg(feature = "std")]
+pub mod kind;
+#[cfg(feature = "std")]
+pub mod lanes;
+#[cfg(feature = "std")]
+pub mod locations;
+#[cfg(feature = "std")]
+pub mod mapping;
+#[cfg(feature = "std")]
+pub mod memory;
+#[cfg(feature = "std")]
+pub mod merge;
+#[cfg(feature = "std")]
+pub mod multipolygon;
+#[cfg(feature = "std")]
+pub mod nominatim;
+#[cfg(feature = "std")]
+pub mod note;
+#[cfg(feature = "std")]
+pub mod o5m;
+#[cfg(feature = "std")]
+pub mod oneway;
+#[cfg(feature = "opening-hours")]
+pub mod opening_hours;
+#[cfg(feature = "std")]
+pub mod opl;
+#[cfg(feature = "std")]
+pub mod osmfilter;
+#[cfg(feature = "osmio")]
+pub mod osmio;
+#[cfg(feature = "osmpbf")]
+pub mod osmpbf;
+#[cfg(feature = "std")]
+pub mod overpass;
+#[cfg(feature = "std")]
+pub mod parse;
+#[cfg(feature = "std")]
+pub mod partition;
+#[cfg(feature = "pbf")]
+pub mod pbf;
+#[cfg(feature = "std")]
+pub mod pipeline;
+#[cfg(feature = "std")]
+pub mod placeholder;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "std")]
+pub mod progress;
+#[cfg(feature = "pyo3")]
+pub mod python;
+#[cfg(feature = "regions")]
+pub mod region;
+#[cfg(feature = "std")]
+pub mod renumber;
+#[cfg(feature = "std")]
+pub mod replication;
+#[cfg(feature = "std")]
+pub mod restriction;
+#[cfg(feature = "std")]
+pub mod role;
+#[cfg(feature = "std")]
+pub mod route;
+#[cfg(feature = "s2")]
+pub mod s2;
+pub mod scalar;
+#[cfg(feature = "std")]
+pub mod schema;
+#[cfg(feature = "std")]
+pub mod snap;
+#[cfg(feature = "std")]
+pub mod sort;
+#[cfg(feature = "rstar")]
+pub mod spatial;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod store;
+pub mod string;
+#[cfg(feature = "std")]
+pub mod summary;
+pub mod tags;
+#[cfg(feature = "std")]
+pub mod tile;
+#[cfg(feature = "std")]
+pub mod upload;
+#[cfg(feature = "std")]
+pub mod user;
+#[cfg(feature = "std")]
+pub mod validate;
+#[cfg(feature = "std")]
+pub mod vertical;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(all(feature = "serde", feature = "std"))]
+pub mod wire;
+#[cfg(feature = "wkt")]
+pub mod wkt;
+#[cfg(feature = "std")]
+pub mod xml;
+
+#[cfg(feature = "std")]
+pub use builder::{NodeBuilder, RelationBuilder, WayBuilder};
+#[cfg(feature = "std")]
+pub use error::Error;
+pub use id::{ElementId, NodeId, RelationId, WayId};
+pub use string::TagString;
+pub use tags::Tags;
 
 /// Fundamental representation of geographical features in OpenStreetMap
 ///
 /// <https://wiki.openstreetmap.org/wiki/Elements>
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Element {
     Node(Node),
@@ -23,7 +236,24 @@ impl Element {
         }
     }
 
-    pub fn tags(&self) -> &HashMap<KString, KString> {
+    /// Type of the element, as referenced by a [Member]
+    pub fn member_type(&self) -> MemberType {
+        match self {
+            Element::Node(_) => MemberType::Node,
+            Element::Way(_) => MemberType::Way,
+            Element::Relation(_) => MemberType::Relation,
+        }
+    }
+
+    pub fn tags(&self) -> &Tags {
+        match self {
+            Element::Node(Node { tags, .. })
+            | Element::Way(Way { tags, .. })
+            | Element::Relation(Relation { tags, .. }) => tags,
+        }
+    }
+
+    pub fn tags_mut(&mut self) -> &mut Tags {
         match self {
             Element::Node(Node { tags, .. })
             | Element::Way(Way { tags, .. })
@@ -75,6 +305,8 @@ impl Element {
 }
 
 /// [Element] identifier
+///
+/// Only unique within a [MemberType], see [ElementId] and the other typed identifiers.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Id(pub i64);
@@ -82,16 +314,17 @@ pub struct Id(pub i64);
 /// Single point in space
 ///
 /// <https://wiki.openstreetmap.org/wiki/Node>
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Node {
     pub id: Id,
-    pub tags: HashMap<KString, KString>,
+    pub tags: Tags,
     pub info: Option<Info>,
     /// [WGS 84](https://en.wikipedia.org/wiki/World_Geodetic_System#WGS84) latitude (y)
-    pub lat: Decimal,
+    pub lat: Scalar,
     /// [WGS 84](https://en.wikipedia.org/wiki/World_Geodetic_System#WGS84) longitude (x)
-    pub lon: Decimal,
+    pub lon: Scalar,
 }
 
 impl Node {
@@ -99,6 +332,15 @@ impl Node {
     pub fn strip_info(&mut self) {
         self.info = None;
     }
+
+    /// Whether both coordinates differ by at most `epsilon_degrees`
+    ///
+    /// Nodes that went through a lossy format round trip may no longer be exactly equal.
+    /// Only coordinates are compared. See [snap::Grid] for a comparison that can be hashed.
+    pub fn approx_eq(&self, other: &Node, epsilon_degrees: Scalar) -> bool {
+        (self.lat - other.lat).abs() <= epsilon_degrees
+            && (self.lon - other.lon).abs() <= epsilon_degrees
+    }
 }
 
 /// Ordered list of [Node]s
@@ -108,14 +350,14 @@ impl Node {
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Way {
     pub id: Id,
-    pub tags: HashMap<KString, KString>,
+    pub tags: Tags,
     pub info: Option<Info>,
 
     /// Nodes in the way
     ///
     /// In an [open way](https://wiki.openstreetmap.org/wiki/Way#Open_way_%28open_polyline%29), the first and last nodes differ.
     /// In a [closed way](https://wiki.openstreetmap.org/wiki/Way#Closed_way_%28closed_polyline%29), the first and last nodes are identical.
-    pub refs: Vec<Id>,
+    pub refs: Refs,
 }
 
 impl Way {
@@ -133,12 +375,12 @@ impl Way {
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Relation {
     pub id: Id,
-    pub tags: HashMap<KString, KString>,
+    pub tags: Tags,
     pub info: Option<Info>,
     /// There should be no more than 300 members per relation, with a hard limit of 32,000
     ///
     /// <https://wiki.openstreetmap.org/wiki/Relation#Size>
-    pub members: Vec<Member>,
+    pub members: Members,
 }
 
 impl Relation {
@@ -157,11 +399,13 @@ pub struct Member {
     /// Describes the function of this member in its relation
     ///
     /// <https://wiki.openstreetmap.org/wiki/Relation#Roles>
-    pub role: Option<KString>,
+    pub role: Option<TagString>,
 }
 
 /// Type of [Element] represented by [Member]
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+///
+/// Types are ordered as in planet files, see [sort].
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MemberType {
     Node,
@@ -169,6 +413,69 @@ pub enum MemberType {
     Relation,
 }
 
+/// Storage of [Way::refs]
+///
+/// This is a [Vec] by default, and a [SmallVec](smallvec::SmallVec) with the `smallvec`
+/// feature so that ways of up to [INLINE_REFS] nodes need no allocation of their own.
+#[cfg(not(feature = "smallvec"))]
+pub type Refs = alloc::vec::Vec<Id>;
+#[cfg(feature = "smallvec")]
+pub type Refs = smallvec::SmallVec<[Id; INLINE_REFS]>;
+
+/// Storage of [Relation::members], see [Refs]
+#[cfg(not(feature = "smallvec"))]
+pub type Members = alloc::vec::Vec<Member>;
+#[cfg(feature = "smallvec")]
+pub type Members = smallvec::SmallVec<[Member; INLINE_MEMBERS]>;
+
+/// Nodes stored inline with the `smallvec` feature, enough for most buildings
+#[cfg(feature = "smallvec")]
+pub const INLINE_REFS: usize = 8;
+/// Members stored inline with the `smallvec` feature, enough for most turn restrictions
+#[cfg(feature = "smallvec")]
+pub const INLINE_MEMBERS: usize = 3;
+
+/// Point in time, which OSM always gives in UTC
+///
+/// This is a [chrono::NaiveDateTime] in UTC by default, and a [`DateTime<Utc>`] with the `utc`
+/// feature so the time zone is part of the type.
+#[cfg(not(feature = "utc"))]
+pub type Timestamp = NaiveDateTime;
+#[cfg(feature = "utc")]
+pub type Timestamp = DateTime<Utc>;
+
+/// Converts a time in UTC to a [Timestamp], regardless of the `utc` feature
+pub(crate) fn timestamp_from_utc(time: DateTime<Utc>) -> Timestamp {
+    #[cfg(feature = "utc")]
+    return time;
+    #[cfg(not(feature = "utc"))]
+    return time.naive_utc();
+}
+
+/// Converts a [Timestamp] to a time in UTC, regardless of the `utc` feature
+pub(crate) fn timestamp_to_utc(timestamp: Timestamp) -> DateTime<Utc> {
+    #[cfg(feature = "utc")]
+    return timestamp;
+    #[cfg(not(feature = "utc"))]
+    return timestamp.and_utc();
+}
+
+/// Parses a timestamp as the API writes it, e.g. `2024-01-31T12:00:00Z`
+///
+/// Other RFC 3339 offsets are converted to UTC. Fractions of a second are dropped, since OSM
+/// keeps timestamps to the second and a [Timestamp] should round trip through
+/// [format_timestamp].
+pub fn parse_timestamp(value: &str) -> Option<Timestamp> {
+    let time = DateTime::parse_from_rfc3339(value).ok()?;
+    let time = time.with_timezone(&Utc).with_nanosecond(0)?;
+    Some(timestamp_from_utc(time))
+}
+
+/// Formats a timestamp as the API does, to the second in UTC with a `Z` suffix
+pub fn format_timestamp(timestamp: Timestamp) -> impl fmt::Display {
+    timestamp_to_utc(timestamp).format("%Y-%m-%dT%H:%M:%SZ")
+}
+
 /// Non-geographical information about a [Element]
 ///
 /// <https://wiki.openstreetmap.org/wiki/Elements#Common_attributes>
@@ -180,7 +487,7 @@ pub struct Info {
     /// Starts at 1 and incremented with each update
     pub version: i32,
     /// Time of last modification
-    pub timestamp: Option<NaiveDateTime>,
+    pub timestamp: Option<Timestamp>,
     /// Group of edits that this version belongs to
     ///
     /// <https://wiki.openstreetmap.org/wiki/Changeset>
@@ -190,10 +497,107 @@ pub struct Info {
     /// Display name of the user
     ///
     /// This will change without a version increment if the user modifies their display name.
-    pub user: Option<KString>,
+    pub user: Option<TagString>,
     /// Whether a [Element] is visible or not
     ///
     /// Assume this to be true if it is [None]. If [Some(false)], the [Element] was deleted
     /// and was returned by a history call.
     pub visible: Option<bool>,
 }
+
+impl Info {
+    /// Starts building [Info] for the first version of an element
+    pub fn builder() -> InfoBuilder {
+        InfoBuilder(Info {
+            version: 1,
+            timestamp: None,
+            changeset: None,
+            uid: None,
+            user: None,
+            visible: None,
+        })
+    }
+
+    /// Time of last modification in UTC, regardless of the `utc` feature
+    pub fn timestamp_utc(&self) -> Option<DateTime<Utc>> {
+        self.timestamp.map(timestamp_to_utc)
+    }
+
+    /// Whether the version was made by an anonymous user, see [Author::Anonymous]
+    pub fn is_anonymous(&self) -> bool {
+        self.author() == Author::Anonymous
+    }
+
+    pub fn author(&self) -> Author<'_> {
+        if self.uid.is_some() || self.user.is_some() {
+            Author::User {
+                uid: self.uid,
+                user: self.user.as_deref(),
+            }
+        } else if self.changeset.is_some() {
+            Author::Anonymous
+        } else {
+            Author::Unknown
+        }
+    }
+}
+
+/// Who made a version of an [Element], as far as [Info] tells
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Author<'a> {
+    /// Registered user, identified by id and/or display name
+    User {
+        uid: Option<i32>,
+        user: Option<&'a str>,
+    },
+    /// Edit made without an account, which was possible before API 0.6 in 2009
+    ///
+    /// Histories still contain these versions, with a changeset but no user.
+    Anonymous,
+    /// No user information, e.g. because it was removed from a public extract
+    Unknown,
+}
+
+/// Builder returned by [Info::builder]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct InfoBuilder(Info);
+
+impl InfoBuilder {
+    pub fn version(mut self, version: i32) -> Self {
+        self.0.version = version;
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.0.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn changeset(mut self, changeset: i64) -> Self {
+        self.0.changeset = Some(changeset);
+        self
+    }
+
+    /// Sets the user id and display name, which always go together in the API
+    pub fn user(mut self, uid: i32, user: impl Into<TagString>) -> Self {
+        self.0.uid = Some(uid);
+        self.0.user = Some(user.into());
+        self
+    }
+
+    /// Clears the user, for an edit made by an anonymous user
+    pub fn anonymous(mut self) -> Self {
+        self.0.uid = None;
+        self.0.user = None;
+        self
+    }
+
+    pub fn visible(mut self, visible: bool) -> Self {
+        self.0.visible = Some(visible);
+        self
+    }
+
+    pub fn build(self) -> Info {
+        self.0
+    }
+}
@@ -0,0 +1,364 @@
+//! Node coordinates without tags or metadata
+//!
+//! Resolving geometries only requires the location of each node, which is
+//! much cheaper to hold and exchange than a full [Node]. A [LocatedWay] carries the locations
+//! of its nodes along with it, so that the nodes themselves can be dropped.
+
+use std::io::{self, Read, Write};
+
+use fnv::FnvHashSet as HashSet;
+
+use crate::geom::LatLon;
+use crate::scalar::{Scalar, ScalarExt};
+use crate::store::ElementStore;
+use crate::{schema, Id, MemberType, Node, Way};
+
+/// Fixed-point scale of encoded coordinates, matching the 7 decimal places used by the OSM API
+const SCALE: u32 = 7;
+
+/// Location of a [Node]
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "decimal", derive(Eq, Hash))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeLocation {
+    pub id: Id,
+    /// [WGS 84](https://en.wikipedia.org/wiki/World_Geodetic_System#WGS84) latitude (y)
+    pub lat: Scalar,
+    /// [WGS 84](https://en.wikipedia.org/wiki/World_Geodetic_System#WGS84) longitude (x)
+    pub lon: Scalar,
+}
+
+impl NodeLocation {
+    /// Length of [NodeLocation::to_bytes]
+    pub const ENCODED_LEN: usize = 16;
+
+    /// Encodes as a little-endian id followed by coordinates in 32-bit fixed point with 7 decimal places
+    ///
+    /// Returns [None] if a coordinate has more than 7 decimal places or is out of range. Without
+    /// the `decimal` feature, coordinates are rounded to 7 decimal places instead.
+    pub fn to_bytes(&self) -> Option<[u8; Self::ENCODED_LEN]> {
+        let mut bytes = [0; Self::ENCODED_LEN];
+        bytes[..8].copy_from_slice(&self.id.0.to_le_bytes());
+        bytes[8..12].copy_from_slice(&to_fixed(self.lat)?.to_le_bytes());
+        bytes[12..].copy_from_slice(&to_fixed(self.lon)?.to_le_bytes());
+        Some(bytes)
+    }
+
+    /// Decodes the representation produced by [NodeLocation::to_bytes]
+    pub fn from_bytes(bytes: [u8; Self::ENCODED_LEN]) -> Self {
+        let degrees = |fixed: &[u8]| from_fixed(i32::from_le_bytes(fixed.try_into().unwrap()));
+        Self {
+            id: Id(i64::from_le_bytes(bytes[..8].try_into().unwrap())),
+            lat: degrees(&bytes[8..12]),
+            lon: degrees(&bytes[12..]),
+        }
+    }
+}
+
+/// Degrees in 32-bit fixed point with 7 decimal places, see [NodeLocation::to_bytes]
+pub(crate) fn to_fixed(degrees: Scalar) -> Option<i32> {
+    let scaled = degrees * Scalar::from_int(10i64.pow(SCALE));
+    // Floats rarely scale to an exact integer
+    #[cfg(not(feature = "decimal"))]
+    let scaled = scaled.round_half_away();
+    if scaled.is_integer() {
+        i32::try_from(scaled.to_int()?).ok()
+    } else {
+        None
+    }
+}
+
+pub(crate) fn from_fixed(fixed: i32) -> Scalar {
+    Scalar::with_scale(fixed.into(), SCALE).normalized()
+}
+
+impl From<&Node> for NodeLocation {
+    fn from(node: &Node) -> Self {
+        Self {
+            id: node.id,
+            lat: node.lat,
+            lon: node.lon,
+        }
+    }
+}
+
+impl Node {
+    pub fn location(&self) -> NodeLocation {
+        self.into()
+    }
+}
+
+/// Collection of [NodeLocation]s that can be looked up by id once sorted
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeLocations {
+    locations: Vec<NodeLocation>,
+    sorted: bool,
+}
+
+impl NodeLocations {
+    pub fn new() -> Self {
+        Self {
+            locations: vec![],
+            sorted: true,
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            locations: Vec::with_capacity(capacity),
+            sorted: true,
+        }
+    }
+
+    pub fn push(&mut self, location: NodeLocation) {
+        if let Some(last) = self.locations.last() {
+            self.sorted &= last.id < location.id;
+        }
+        self.locations.push(location);
+    }
+
+    /// Sorts by id, keeping only the last location pushed for each id
+    pub fn sort(&mut self) {
+        if self.sorted {
+            return;
+        }
+        // Stable sort keeps pushed order within an id
+        self.locations.sort_by_key(|location| location.id);
+        self.locations.reverse();
+        self.locations.dedup_by_key(|location| location.id);
+        self.locations.reverse();
+        self.sorted = true;
+    }
+
+    pub fn is_sorted(&self) -> bool {
+        self.sorted
+    }
+
+    /// Finds the location of the node with `id`
+    ///
+    /// Falls back to a linear scan if not sorted.
+    pub fn get(&self, id: Id) -> Option<&NodeLocation> {
+        if self.sorted {
+            self.locations
+                .binary_search_by_key(&id, |location| location.id)
+                .ok()
+                .map(|i| &self.locations[i])
+        } else {
+            self.locations
+                .iter()
+                .rev()
+                .find(|location| location.id == id)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.locations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, NodeLocation> {
+        self.locations.iter()
+    }
+
+    pub fn as_slice(&self) -> &[NodeLocation] {
+        &self.locations
+    }
+
+    /// Writes each location as [NodeLocation::to_bytes]
+    ///
+    /// Fails with [io::ErrorKind::InvalidData] if a location cannot be encoded.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(locations = self.len())))]
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for location in &self.locations {
+            let bytes = location.to_bytes().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("location of node {} cannot be encoded", location.id.0),
+                )
+            })?;
+            writer.write_all(&bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Reads locations written by [NodeLocations::write_to] until the end of `reader`
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut locations = Self::new();
+        let mut bytes = [0; NodeLocation::ENCODED_LEN];
+        loop {
+            let mut filled = 0;
+            while filled < bytes.len() {
+                match reader.read(&mut bytes[filled..]) {
+                    Ok(0) if filled == 0 => {
+                        #[cfg(feature = "tracing")]
+                        tracing::debug!(locations = locations.len(), "read node locations");
+                        return Ok(locations);
+                    }
+                    Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                    Ok(n) => filled += n,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            locations.push(NodeLocation::from_bytes(bytes));
+        }
+    }
+
+    /// Writes a [schema::Header] followed by [NodeLocations::write_to]
+    pub fn write_versioned<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        schema::Header::current(schema::Kind::NodeLocations).write_to(&mut writer)?;
+        self.write_to(writer)
+    }
+
+    /// Reads locations written by [NodeLocations::write_versioned]
+    ///
+    /// Files written by older releases must first be upgraded with [schema::Migrations].
+    pub fn read_versioned<R: Read>(mut reader: R) -> io::Result<Self> {
+        schema::Header::expect(&mut reader, schema::Kind::NodeLocations)?;
+        Self::read_from(reader)
+    }
+}
+
+impl Default for NodeLocations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FromIterator<NodeLocation> for NodeLocations {
+    fn from_iter<T: IntoIterator<Item = NodeLocation>>(iter: T) -> Self {
+        let mut locations = Self::new();
+        locations.extend(iter);
+        locations
+    }
+}
+
+impl Extend<NodeLocation> for NodeLocations {
+    fn extend<T: IntoIterator<Item = NodeLocation>>(&mut self, iter: T) {
+        for location in iter {
+            self.push(location);
+        }
+    }
+}
+
+impl IntoIterator for NodeLocations {
+    type Item = NodeLocation;
+    type IntoIter = std::vec::IntoIter<NodeLocation>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.locations.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a NodeLocations {
+    type Item = &'a NodeLocation;
+    type IntoIter = std::slice::Iter<'a, NodeLocation>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.locations.iter()
+    }
+}
+
+/// [Way] with the location of each of its nodes embedded, like the ways with locations of
+/// [osmium](https://osmcode.org/osmium-concepts/#node-locations)
+///
+/// Once every way is located, the untagged nodes that only ways refer to are no longer needed,
+/// see [ElementStore::remove_way_only_nodes].
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LocatedWay {
+    pub way: Way,
+    /// Location of each of [Way::refs] in order, [None] for nodes that were not found
+    pub locations: Vec<Option<LatLon>>,
+}
+
+impl LocatedWay {
+    /// Whether every node was found
+    pub fn is_complete(&self) -> bool {
+        self.locations.iter().all(Option::is_some)
+    }
+
+    /// Coordinates of the nodes in order, [None] if any node was not found
+    pub fn line(&self) -> Option<Vec<LatLon>> {
+        self.locations.iter().copied().collect()
+    }
+
+    /// Refs of the nodes that were not found
+    pub fn missing(&self) -> impl Iterator<Item = Id> + '_ {
+        self.way
+            .refs
+            .iter()
+            .zip(&self.locations)
+            .filter(|(_, location)| location.is_none())
+            .map(|(id, _)| *id)
+    }
+}
+
+impl Way {
+    /// Copy of the way with the location of each node, looked up with `location`
+    pub fn with_locations(&self, mut location: impl FnMut(Id) -> Option<LatLon>) -> LocatedWay {
+        LocatedWay {
+            locations: self.refs.iter().map(|&id| location(id)).collect(),
+            way: self.clone(),
+        }
+    }
+}
+
+impl NodeLocations {
+    /// See [Way::with_locations]
+    pub fn locate(&self, way: &Way) -> LocatedWay {
+        way.with_locations(|id| self.get(id).map(|location| (location.lat, location.lon)))
+    }
+}
+
+impl ElementStore {
+    /// See [Way::with_locations]
+    pub fn locate_way(&self, way: &Way) -> LocatedWay {
+        way.with_locations(|id| self.get_node(id).map(Node::lat_lon))
+    }
+
+    /// Every way of the store with its locations, in no particular order
+    pub fn located_ways(&self) -> impl Iterator<Item = LocatedWay> + '_ {
+        self.ways().map(|way| self.locate_way(way))
+    }
+
+    /// Untagged nodes that ways refer to and relations do not
+    ///
+    /// These are the nodes that only matter for their location, so they can be dropped once
+    /// the ways are located. Untagged nodes that nothing refers to are not included.
+    pub fn way_only_nodes(&self) -> Vec<Id> {
+        let members: HashSet<Id> = self
+            .relations()
+            .flat_map(|relation| &relation.members)
+            .filter(|member| member.ty == MemberType::Node)
+            .map(|member| member.id)
+            .collect();
+        let mut nodes: Vec<Id> = self
+            .ways()
+            .flat_map(|way| way.refs.iter().copied())
+            .filter(|id| !members.contains(id))
+            .filter(|&id| self.get_node(id).is_some_and(|node| node.tags.is_empty()))
+            .collect();
+        nodes.sort_unstable();
+        nodes.dedup();
+        nodes
+    }
+
+    /// Removes the [ElementStore::way_only_nodes], returning how many were removed
+    ///
+    /// Ways keep their refs, so locate them first, e.g. with [ElementStore::located_ways].
+    pub fn remove_way_only_nodes(&mut self) -> usize {
+        let nodes = self.way_only_nodes();
+        for &id in &nodes {
+            self.remove(&MemberType::Node, id);
+        }
+        nodes.len()
+    }
+}
@@ -0,0 +1,258 @@
+//! Mapping of elements to rows of database tables
+//!
+//! Modelled after the [flex output of osm2pgsql](https://osm2pgsql.org/doc/manual.html#the-flex-output):
+//! an [OutputMapping] declares its [Table]s and turns each element into zero or more [Row]s,
+//! so database loaders only need to know how to write rows.
+
+use fnv::FnvHashMap as HashMap;
+
+use crate::pipeline::Source;
+use crate::{Element, Id, MemberType, TagString};
+
+/// Kind of geometry stored in a table
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GeometryKind {
+    /// Table without geometry, e.g. for relation members
+    None,
+    Point,
+    Line,
+    Polygon,
+    /// Polygon or multipolygon, e.g. for multipolygon relations
+    MultiPolygon,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColumnType {
+    Text,
+    Integer,
+    Real,
+    Boolean,
+    /// All tags of the element, e.g. as `hstore` or `jsonb`
+    Tags,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Column {
+    pub name: TagString,
+    pub ty: ColumnType,
+}
+
+impl Column {
+    pub fn new(name: &str, ty: ColumnType) -> Self {
+        Self {
+            name: TagString::from_ref(name),
+            ty,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Table {
+    pub name: TagString,
+    pub geometry: GeometryKind,
+    pub columns: Vec<Column>,
+}
+
+impl Table {
+    pub fn new(name: &str, geometry: GeometryKind) -> Self {
+        Self {
+            name: TagString::from_ref(name),
+            geometry,
+            columns: vec![],
+        }
+    }
+
+    pub fn column(mut self, name: &str, ty: ColumnType) -> Self {
+        self.columns.push(Column::new(name, ty));
+        self
+    }
+}
+
+/// Value of a column in a [Row]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value {
+    Null,
+    Text(TagString),
+    Integer(i64),
+    Real(f64),
+    Boolean(bool),
+    Tags(HashMap<TagString, TagString>),
+}
+
+impl Value {
+    /// Converts the value of tag `key` to a column of type `ty`
+    ///
+    /// Returns [Value::Null] if the tag is absent or cannot be converted. Booleans accept
+    /// `yes`/`no`, `true`/`false`, and `1`/`0`. For [ColumnType::Tags], all tags are returned.
+    pub fn from_tag(tags: &HashMap<TagString, TagString>, key: &str, ty: ColumnType) -> Self {
+        if ty == ColumnType::Tags {
+            return Value::Tags(tags.clone());
+        }
+        let Some(value) = tags.get(key) else {
+            return Value::Null;
+        };
+        let converted = match ty {
+            ColumnType::Text => Some(Value::Text(value.clone())),
+            ColumnType::Integer => value.trim().parse().ok().map(Value::Integer),
+            ColumnType::Real => value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|real: &f64| real.is_finite())
+                .map(Value::Real),
+            ColumnType::Boolean => match value.as_str() {
+                "yes" | "true" | "1" => Some(Value::Boolean(true)),
+                "no" | "false" | "0" => Some(Value::Boolean(false)),
+                _ => None,
+            },
+            ColumnType::Tags => unreachable!(),
+        };
+        converted.unwrap_or(Value::Null)
+    }
+}
+
+/// Row produced by an [OutputMapping]
+///
+/// The geometry is not included, since it depends on node locations that the mapping does not
+/// have. Loaders build it from the element according to [Table::geometry].
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Row {
+    /// Index into [OutputMapping::tables]
+    pub table: usize,
+    pub ty: MemberType,
+    pub id: Id,
+    /// Values in the order of [Table::columns]
+    pub values: Vec<Value>,
+}
+
+/// Describes how elements are stored in database tables
+pub trait OutputMapping {
+    /// Tables written by this mapping, which must not change while mapping
+    fn tables(&self) -> &[Table];
+
+    /// Appends the rows for `element` to `rows`
+    fn map(&mut self, element: &Element, rows: &mut Vec<Row>);
+}
+
+/// Rows produced by mapping each element of a [Source], see [map_rows]
+#[derive(Debug)]
+pub struct Rows<S, M> {
+    source: S,
+    mapping: M,
+    pending: std::vec::IntoIter<Row>,
+    buffer: Vec<Row>,
+}
+
+impl<S, M: OutputMapping> Rows<S, M> {
+    pub fn mapping(&self) -> &M {
+        &self.mapping
+    }
+}
+
+impl<S: Source, M: OutputMapping> Iterator for Rows<S, M> {
+    type Item = Result<Row, S::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.pending.next() {
+                return Some(Ok(row));
+            }
+            let element = match self.source.read()? {
+                Ok(element) => element,
+                Err(err) => return Some(Err(err)),
+            };
+            self.mapping.map(&element, &mut self.buffer);
+            self.pending = std::mem::take(&mut self.buffer).into_iter();
+        }
+    }
+}
+
+/// Maps each element of `source` to rows with `mapping`
+pub fn map_rows<S: Source, M: OutputMapping>(source: S, mapping: M) -> Rows<S, M> {
+    Rows {
+        source,
+        mapping,
+        pending: vec![].into_iter(),
+        buffer: vec![],
+    }
+}
+
+/// [OutputMapping] with one table per key, e.g. a `highway` table for all elements tagged `highway`
+///
+/// Each table has a text column for its key followed by the extra columns, whose values are
+/// taken from the tags of the same name.
+#[derive(Debug, Clone)]
+pub struct KeyTables {
+    tables: Vec<Table>,
+    keys: Vec<TagString>,
+}
+
+impl KeyTables {
+    pub fn new() -> Self {
+        Self {
+            tables: vec![],
+            keys: vec![],
+        }
+    }
+
+    /// Adds a table named after `key` storing `extra` columns
+    pub fn table(mut self, key: &str, geometry: GeometryKind, extra: &[Column]) -> Self {
+        let mut table = Table::new(key, geometry).column(key, ColumnType::Text);
+        table.columns.extend_from_slice(extra);
+        self.tables.push(table);
+        self.keys.push(TagString::from_ref(key));
+        self
+    }
+}
+
+impl Default for KeyTables {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OutputMapping for KeyTables {
+    fn tables(&self) -> &[Table] {
+        &self.tables
+    }
+
+    fn map(&mut self, element: &Element, rows: &mut Vec<Row>) {
+        let ty = element.member_type();
+        let tags = element.tags();
+        for (i, (table, key)) in self.tables.iter().zip(&self.keys).enumerate() {
+            if !tags.contains_key(key) || !geometry_fits(table.geometry, element) {
+                continue;
+            }
+            rows.push(Row {
+                table: i,
+                ty: ty.clone(),
+                id: element.id(),
+                values: table
+                    .columns
+                    .iter()
+                    .map(|column| Value::from_tag(tags, &column.name, column.ty))
+                    .collect(),
+            });
+        }
+    }
+}
+
+/// Whether a table of `kind` can hold the geometry of `element`
+fn geometry_fits(kind: GeometryKind, element: &Element) -> bool {
+    matches!(
+        (kind, element),
+        (GeometryKind::None, _)
+            | (GeometryKind::Point, Element::Node(_))
+            | (GeometryKind::Line | GeometryKind::Polygon, Element::Way(_))
+            | (
+                GeometryKind::MultiPolygon,
+                Element::Way(_) | Element::Relation(_)
+            )
+    )
+}
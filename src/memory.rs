@@ -0,0 +1,145 @@
+//! Estimates of the memory that elements use
+//!
+//! Holding a planet extract in memory takes tens of gigabytes, most of it in allocations
+//! behind the elements rather than in the elements themselves: tag maps and their strings,
+//! and the nodes of ways. [Element::estimated_heap_size] estimates these allocations, and
+//! [ElementStore::stats](crate::store::ElementStore::stats) adds them up per element type.
+//!
+//! Estimates count the bytes requested from the allocator, so the memory used is somewhat
+//! higher, depending on the allocator. Strings short enough to be stored inline, which
+//! depends on the representation of [TagString], count as no bytes.
+
+use std::mem::size_of;
+
+use crate::tags::TagMap;
+use crate::{Element, Info, Member, Node, Relation, TagString, Tags, Way};
+
+/// Whether [TagString] stores a string of `len` bytes inline
+#[cfg(not(any(feature = "arc-str", feature = "box-str", feature = "compact-str")))]
+fn stored_inline(len: usize) -> bool {
+    len <= 15
+}
+#[cfg(all(
+    feature = "compact-str",
+    not(any(feature = "arc-str", feature = "box-str"))
+))]
+fn stored_inline(len: usize) -> bool {
+    len <= 24
+}
+#[cfg(any(feature = "arc-str", feature = "box-str"))]
+fn stored_inline(_len: usize) -> bool {
+    false
+}
+
+/// Estimated heap bytes of a [TagString] holding `s`
+fn string_heap_size(s: &str) -> usize {
+    if cfg!(feature = "arc-str") {
+        // Reference counts are allocated along with the string
+        2 * size_of::<usize>() + s.len()
+    } else if stored_inline(s.len()) {
+        0
+    } else {
+        s.len()
+    }
+}
+
+/// Estimated heap bytes of a shared tag map, counting its table and strings
+fn tag_map_heap_size(tags: &TagMap) -> usize {
+    if tags.is_empty() && tags.capacity() == 0 {
+        return 0;
+    }
+    let table = tags.capacity() * (size_of::<(TagString, TagString)>() + 1);
+    let strings: usize = tags
+        .iter()
+        .map(|(key, value)| string_heap_size(key) + string_heap_size(value))
+        .sum();
+    // Reference counts and the map itself live in the shared allocation
+    2 * size_of::<usize>() + size_of::<TagMap>() + table + strings
+}
+
+/// Heap bytes of the items of a [crate::Refs] or [crate::Members]
+#[cfg(not(feature = "smallvec"))]
+fn items_heap_size<T>(items: &Vec<T>) -> usize {
+    items.capacity() * size_of::<T>()
+}
+
+/// Heap bytes of the items of a [crate::Refs] or [crate::Members], which are only allocated
+/// once they no longer fit inline
+#[cfg(feature = "smallvec")]
+fn items_heap_size<A: smallvec::Array>(items: &smallvec::SmallVec<A>) -> usize {
+    match items.spilled() {
+        true => items.capacity() * size_of::<A::Item>(),
+        false => 0,
+    }
+}
+
+impl Tags {
+    /// Estimated heap bytes of the map, including those of a map shared with other [Tags]
+    pub fn estimated_heap_size(&self) -> usize {
+        tag_map_heap_size(self)
+    }
+}
+
+impl Info {
+    /// Estimated heap bytes of the user name
+    pub fn estimated_heap_size(&self) -> usize {
+        self.user.as_deref().map_or(0, string_heap_size)
+    }
+}
+
+impl Member {
+    /// Estimated heap bytes of the role
+    pub fn estimated_heap_size(&self) -> usize {
+        self.role.as_deref().map_or(0, string_heap_size)
+    }
+}
+
+impl Node {
+    /// Estimated heap bytes of the tags and info
+    pub fn estimated_heap_size(&self) -> usize {
+        self.tags.estimated_heap_size() + self.info.as_ref().map_or(0, Info::estimated_heap_size)
+    }
+}
+
+impl Way {
+    /// Estimated heap bytes of the tags, info, and node ids
+    pub fn estimated_heap_size(&self) -> usize {
+        self.tags.estimated_heap_size()
+            + self.info.as_ref().map_or(0, Info::estimated_heap_size)
+            + items_heap_size(&self.refs)
+    }
+}
+
+impl Relation {
+    /// Estimated heap bytes of the tags, info, and members with their roles
+    pub fn estimated_heap_size(&self) -> usize {
+        self.tags.estimated_heap_size()
+            + self.info.as_ref().map_or(0, Info::estimated_heap_size)
+            + items_heap_size(&self.members)
+            + self
+                .members
+                .iter()
+                .map(Member::estimated_heap_size)
+                .sum::<usize>()
+    }
+}
+
+impl Element {
+    /// Estimated bytes the element allocates on the heap, in addition to its own size
+    ///
+    /// Tag maps shared with other elements are counted in full, so the sum over many
+    /// elements overestimates the memory of deduplicated tags, unlike
+    /// [ElementStore::stats](crate::store::ElementStore::stats).
+    pub fn estimated_heap_size(&self) -> usize {
+        match self {
+            Element::Node(node) => node.estimated_heap_size(),
+            Element::Way(way) => way.estimated_heap_size(),
+            Element::Relation(relation) => relation.estimated_heap_size(),
+        }
+    }
+
+    /// Estimated bytes of the element, its own size and those it allocates
+    pub fn estimated_size(&self) -> usize {
+        size_of::<Element>() + self.estimated_heap_size()
+    }
+}
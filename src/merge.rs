@@ -0,0 +1,419 @@
+//! Merging of sorted element streams, e.g. neighboring extracts or several history files, and
+//! of concurrent edits of one element
+//!
+//! Inputs of [merge] must be in the canonical order of [crate::sort], and so is the output.
+//! [three_way] combines two versions derived from the same base version, e.g. a local edit
+//! and the version another mapper uploaded in the meantime.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
+
+use crate::diff::common_subsequence;
+use crate::error::{ElementContext, Error};
+use crate::geom::LatLon;
+use crate::pipeline::Source;
+use crate::sort::SortKey;
+use crate::{Element, ElementId, Info, Member, Node, Relation, TagString, Tags, Way};
+
+/// Merges sorted `sources` into one sorted stream
+///
+/// Of the copies of an element in several sources, only the one with the highest version is
+/// kept, or the first of them if versions are equal. See [Merge::all_versions] to keep
+/// every version instead. Unsorted input stops with [Error::Validation].
+pub fn merge<S>(sources: impl IntoIterator<Item = S>) -> Merge<S>
+where
+    S: Source,
+    S::Error: Into<Error>,
+{
+    let sources: Vec<_> = sources.into_iter().collect();
+    Merge {
+        heads: sources.iter().map(|_| None).collect(),
+        last: sources.iter().map(|_| None).collect(),
+        sources,
+        heap: BinaryHeap::new(),
+        all_versions: false,
+        started: false,
+        done: false,
+    }
+}
+
+/// Iterator returned by [merge]
+#[derive(Debug)]
+pub struct Merge<S> {
+    sources: Vec<S>,
+    /// Next element of each source
+    heads: Vec<Option<Element>>,
+    /// Keys of the heads, smallest first, ties going to the earlier source
+    heap: BinaryHeap<Reverse<(SortKey, usize)>>,
+    last: Vec<Option<SortKey>>,
+    all_versions: bool,
+    started: bool,
+    done: bool,
+}
+
+impl<S> Merge<S>
+where
+    S: Source,
+    S::Error: Into<Error>,
+{
+    /// Keeps every version of an element, e.g. to combine history files
+    ///
+    /// Copies of the same version in several sources are still only kept once.
+    pub fn all_versions(mut self) -> Self {
+        self.all_versions = true;
+        self
+    }
+
+    fn fill(&mut self, index: usize) -> Result<(), Error> {
+        let Some(element) = self.sources[index].read() else {
+            return Ok(());
+        };
+        let element = element.map_err(Into::into)?;
+        let key = element.sort_key();
+        if self.last[index].as_ref().is_some_and(|last| *last > key) {
+            return Err(Error::Validation {
+                element: Some(ElementContext {
+                    ty: key.ty,
+                    id: key.id,
+                }),
+                message: format!("input {index} is not sorted"),
+            });
+        }
+        self.last[index] = Some(key.clone());
+        self.heap.push(Reverse((key, index)));
+        self.heads[index] = Some(element);
+        Ok(())
+    }
+
+    fn take(&mut self) -> Result<Option<(SortKey, Element)>, Error> {
+        let Some(Reverse((key, index))) = self.heap.pop() else {
+            return Ok(None);
+        };
+        let element = self.heads[index]
+            .take()
+            .expect("head of a source in the heap");
+        self.fill(index)?;
+        Ok(Some((key, element)))
+    }
+
+    fn read_element(&mut self) -> Result<Option<Element>, Error> {
+        if !self.started {
+            self.started = true;
+            for index in 0..self.sources.len() {
+                self.fill(index)?;
+            }
+        }
+        let Some((mut key, mut element)) = self.take()? else {
+            return Ok(None);
+        };
+        while let Some(Reverse((next, _))) = self.heap.peek() {
+            let duplicate = if self.all_versions {
+                *next == key
+            } else {
+                next.ty == key.ty && next.id == key.id
+            };
+            if !duplicate {
+                break;
+            }
+            let (next, next_element) = self.take()?.expect("peeked element");
+            if next.version > key.version {
+                (key, element) = (next, next_element);
+            }
+        }
+        Ok(Some(element))
+    }
+}
+
+impl<S> Iterator for Merge<S>
+where
+    S: Source,
+    S::Error: Into<Error>,
+{
+    type Item = Result<Element, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let element = self.read_element().transpose();
+        if !matches!(element, Some(Ok(_))) {
+            self.done = true;
+        }
+        element
+    }
+}
+
+/// Part of an element that two versions changed differently
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub enum Conflict {
+    /// Versions are of different elements
+    Mismatch,
+    /// One version deleted the element and the other changed it
+    Deleted,
+    /// Value of a tag, [None] where the tag is absent
+    Tag {
+        key: TagString,
+        base: Option<TagString>,
+        mine: Option<TagString>,
+        theirs: Option<TagString>,
+    },
+    /// Location of a node
+    Location {
+        base: LatLon,
+        mine: LatLon,
+        theirs: LatLon,
+    },
+    /// Nodes of a way, changed in the same place
+    Nodes,
+    /// Members of a relation, changed in the same place
+    Members,
+    /// Role of a member kept by both versions
+    Role {
+        member: ElementId,
+        base: Option<TagString>,
+        mine: Option<TagString>,
+        theirs: Option<TagString>,
+    },
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Conflict::Mismatch => write!(f, "versions are of different elements"),
+            Conflict::Deleted => write!(f, "deleted in one version and changed in the other"),
+            Conflict::Tag { key, .. } => write!(f, "tag {key} changed in both versions"),
+            Conflict::Location { .. } => write!(f, "moved in both versions"),
+            Conflict::Nodes => write!(f, "nodes changed in both versions"),
+            Conflict::Members => write!(f, "members changed in both versions"),
+            Conflict::Role { member, .. } => write!(f, "role of {member} changed in both versions"),
+        }
+    }
+}
+
+/// Conflicts that keep [three_way] from merging two versions
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub struct ConflictSet {
+    pub element: ElementId,
+    pub conflicts: Vec<Conflict>,
+    /// Merge of the parts without conflicts, with those of `mine` for the others
+    pub partial: Box<Element>,
+}
+
+impl fmt::Display for ConflictSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cannot merge {}: ", self.element)?;
+        for (i, conflict) in self.conflicts.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{conflict}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConflictSet {}
+
+impl From<ConflictSet> for Error {
+    fn from(conflicts: ConflictSet) -> Self {
+        Error::Conflict {
+            element: conflicts.element.into(),
+            message: conflicts.to_string(),
+        }
+    }
+}
+
+/// Value of both changes, or [None] if they changed it differently
+fn merge_value<T: PartialEq + Clone>(base: &T, mine: &T, theirs: &T) -> Option<T> {
+    if mine == theirs || theirs == base {
+        Some(mine.clone())
+    } else if mine == base {
+        Some(theirs.clone())
+    } else {
+        None
+    }
+}
+
+fn merge_tags(base: &Tags, mine: &Tags, theirs: &Tags, conflicts: &mut Vec<Conflict>) -> Tags {
+    let mut keys: Vec<&TagString> = base
+        .keys()
+        .chain(mine.keys())
+        .chain(theirs.keys())
+        .collect();
+    keys.sort_unstable();
+    keys.dedup();
+    let mut merged = Tags::new();
+    for key in keys {
+        let (b, m, t) = (base.get(key), mine.get(key), theirs.get(key));
+        let value = merge_value(&b, &m, &t).unwrap_or_else(|| {
+            conflicts.push(Conflict::Tag {
+                key: key.clone(),
+                base: b.cloned(),
+                mine: m.cloned(),
+                theirs: t.cloned(),
+            });
+            m
+        });
+        if let Some(value) = value {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+    merged
+}
+
+/// Three-way merge of sequences, as in `diff3`, or [None] if both changed the same stretch
+///
+/// Items of `base` that both kept, by `same`, split the sequences into stretches. Each
+/// stretch is taken from the version that changed it, and `merge_item` merges the items kept
+/// in between, which may still differ in what `same` does not compare.
+fn merge_sequence<T: PartialEq + Clone>(
+    base: &[T],
+    mine: &[T],
+    theirs: &[T],
+    same: impl Fn(&T, &T) -> bool + Copy,
+    mut merge_item: impl FnMut(&T, &T, &T) -> T,
+) -> Option<Vec<T>> {
+    let mut in_mine = vec![None; base.len()];
+    for (i, j) in common_subsequence(base, mine, same) {
+        in_mine[i] = Some(j);
+    }
+    let mut in_theirs = vec![None; base.len()];
+    for (i, j) in common_subsequence(base, theirs, same) {
+        in_theirs[i] = Some(j);
+    }
+    let mut merged = Vec::with_capacity(mine.len().max(theirs.len()));
+    let (mut b, mut m, mut t) = (0, 0, 0);
+    loop {
+        let stable = (b..base.len()).find_map(|i| Some((i, in_mine[i]?, in_theirs[i]?)));
+        let (bi, mi, ti) = stable.unwrap_or((base.len(), mine.len(), theirs.len()));
+        let (base_part, mine_part, theirs_part) = (&base[b..bi], &mine[m..mi], &theirs[t..ti]);
+        merged.extend_from_slice(merge_value(&base_part, &mine_part, &theirs_part)?);
+        let Some((bi, mi, ti)) = stable else {
+            return Some(merged);
+        };
+        merged.push(merge_item(&base[bi], &mine[mi], &theirs[ti]));
+        (b, m, t) = (bi + 1, mi + 1, ti + 1);
+    }
+}
+
+fn info_mut(element: &mut Element) -> &mut Option<Info> {
+    match element {
+        Element::Node(Node { info, .. })
+        | Element::Way(Way { info, .. })
+        | Element::Relation(Relation { info, .. }) => info,
+    }
+}
+
+fn is_deleted(element: &Element) -> bool {
+    element
+        .info()
+        .is_some_and(|info| info.visible == Some(false))
+}
+
+/// Merges the changes that `mine` and `theirs` each made to `base`
+///
+/// Tags merge key by key. Way nodes and relation members merge as sequences, so that e.g.
+/// nodes inserted in different segments of a way are both kept, while changes to the same
+/// stretch conflict unless they are equal. The merge has the [Info](crate::Info) of `theirs`,
+/// which is taken to be the latest version, so that an upload of the merge names the version
+/// it replaces.
+pub fn three_way(base: &Element, mine: &Element, theirs: &Element) -> Result<Element, ConflictSet> {
+    let conflict = |conflicts: Vec<Conflict>, partial: Element| ConflictSet {
+        element: mine.element_id(),
+        conflicts,
+        partial: Box::new(partial),
+    };
+    if base.element_id() != mine.element_id() || base.element_id() != theirs.element_id() {
+        return Err(conflict(vec![Conflict::Mismatch], mine.clone()));
+    }
+    match (is_deleted(mine), is_deleted(theirs)) {
+        (false, false) => {}
+        (true, true) => return Ok(theirs.clone()),
+        (true, false) if theirs.eq_ignoring_info(base) => {
+            let mut merged = mine.clone();
+            let info = info_mut(&mut merged);
+            *info = theirs.info().cloned();
+            if let Some(info) = info {
+                info.visible = Some(false);
+            }
+            return Ok(merged);
+        }
+        (false, true) if mine.eq_ignoring_info(base) => return Ok(theirs.clone()),
+        _ => return Err(conflict(vec![Conflict::Deleted], mine.clone())),
+    }
+
+    let mut conflicts = vec![];
+    let mut merged = theirs.clone();
+    *merged.tags_mut() = merge_tags(base.tags(), mine.tags(), theirs.tags(), &mut conflicts);
+    match (base, mine, &mut merged) {
+        (Element::Node(base), Element::Node(mine), Element::Node(merged)) => {
+            let (b, m, t) = (base.lat_lon(), mine.lat_lon(), merged.lat_lon());
+            let (lat, lon) = merge_value(&b, &m, &t).unwrap_or_else(|| {
+                conflicts.push(Conflict::Location {
+                    base: b,
+                    mine: m,
+                    theirs: t,
+                });
+                m
+            });
+            (merged.lat, merged.lon) = (lat, lon);
+        }
+        (Element::Way(base), Element::Way(mine), Element::Way(merged)) => {
+            let refs = merge_sequence(
+                &base.refs,
+                &mine.refs,
+                &merged.refs,
+                |a, b| a == b,
+                |_, m, _| *m,
+            );
+            match refs {
+                Some(refs) => merged.refs = refs.into_iter().collect(),
+                None => {
+                    conflicts.push(Conflict::Nodes);
+                    merged.refs = mine.refs.clone();
+                }
+            }
+        }
+        (Element::Relation(base), Element::Relation(mine), Element::Relation(merged)) => {
+            let same = |a: &Member, b: &Member| a.ty == b.ty && a.id == b.id;
+            let mut roles = vec![];
+            let members = merge_sequence(
+                &base.members,
+                &mine.members,
+                &merged.members,
+                same,
+                |b, m, t| {
+                    let role = merge_value(&b.role, &m.role, &t.role).unwrap_or_else(|| {
+                        roles.push(Conflict::Role {
+                            member: m.element_id(),
+                            base: b.role.clone(),
+                            mine: m.role.clone(),
+                            theirs: t.role.clone(),
+                        });
+                        m.role.clone()
+                    });
+                    Member { role, ..m.clone() }
+                },
+            );
+            match members {
+                Some(members) => {
+                    conflicts.extend(roles);
+                    merged.members = members.into_iter().collect();
+                }
+                None => {
+                    conflicts.push(Conflict::Members);
+                    merged.members = mine.members.clone();
+                }
+            }
+        }
+        _ => unreachable!("element ids of the same type"),
+    }
+    if conflicts.is_empty() {
+        Ok(merged)
+    } else {
+        Err(conflict(conflicts, merged))
+    }
+}
@@ -0,0 +1,610 @@
+//! Multipolygon relations
+//!
+//! <https://wiki.openstreetmap.org/wiki/Relation:multipolygon>
+//!
+//! [assemble] turns the ways of a relation into polygons with holes, which
+//! [MultiPolygon::locate] tests points against.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use fnv::{FnvHashMap as HashMap, FnvHashSet as HashSet};
+
+use crate::geom::{ring_contains, signed_area, LatLon, MissingNode};
+use crate::scalar::{Scalar, ScalarExt};
+use crate::store::ElementStore;
+use crate::{Id, Member, MemberType, Node, Relation, TagString, Way};
+
+/// Role of a member of a multipolygon
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MultipolygonRole {
+    /// Way forming part of an outer ring
+    Outer,
+    /// Way forming part of a hole in an outer ring
+    Inner,
+    /// Empty or any other role, e.g. a typo such as `outter`
+    ///
+    /// Data consumers commonly treat unknown roles of closed ways as [MultipolygonRole::Outer].
+    Unknown,
+}
+
+impl MultipolygonRole {
+    pub fn parse(role: Option<&str>) -> Self {
+        match role {
+            Some("outer") => MultipolygonRole::Outer,
+            Some("inner") => MultipolygonRole::Inner,
+            _ => MultipolygonRole::Unknown,
+        }
+    }
+
+    /// Role of a [Member], regardless of its type
+    pub fn of(member: &Member) -> Self {
+        Self::parse(member.role.as_deref())
+    }
+}
+
+/// [Member] of a multipolygon along with its parsed role
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct MultipolygonMember<'a> {
+    pub member: &'a Member,
+    pub role: MultipolygonRole,
+}
+
+impl MultipolygonMember<'_> {
+    /// Whether the member can form a ring, which only ways with a known role can
+    pub fn is_valid(&self) -> bool {
+        self.member.ty == MemberType::Way && self.role != MultipolygonRole::Unknown
+    }
+}
+
+impl Relation {
+    /// Whether the relation is tagged `type=multipolygon` or `type=boundary`
+    ///
+    /// Boundaries use the same roles for their rings, along with additional roles like
+    /// `admin_centre` that are [MultipolygonRole::Unknown] here.
+    pub fn is_multipolygon(&self) -> bool {
+        matches!(
+            self.tags.get("type").map(TagString::as_str),
+            Some("multipolygon" | "boundary")
+        )
+    }
+
+    /// Members with their multipolygon roles, in order
+    pub fn multipolygon_members(&self) -> impl Iterator<Item = MultipolygonMember<'_>> {
+        self.members.iter().map(|member| MultipolygonMember {
+            member,
+            role: MultipolygonRole::of(member),
+        })
+    }
+
+    /// Way members with the given role, e.g. the outer ways to assemble first
+    pub fn ways_with_role(&self, role: MultipolygonRole) -> impl Iterator<Item = &Member> {
+        self.multipolygon_members()
+            .filter(move |m| m.member.ty == MemberType::Way && m.role == role)
+            .map(|m| m.member)
+    }
+}
+
+/// Outer ring with the holes inside it
+///
+/// Rings are closed, the outer ring counterclockwise and holes clockwise.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub struct Polygon {
+    pub outer: Vec<LatLon>,
+    pub inners: Vec<Vec<LatLon>>,
+}
+
+/// Polygons assembled from a multipolygon relation by [assemble]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub struct MultiPolygon {
+    pub polygons: Vec<Polygon>,
+}
+
+/// Where a point lies relative to a polygon
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum PointLocation {
+    Inside,
+    /// On an edge or a vertex of a ring
+    Boundary,
+    Outside,
+}
+
+/// Side of the line from `a` to `b` that `(lat, lon)` is on, [Ordering::Greater] to the left
+///
+/// Exact with the `decimal` feature, so that points on edges are found reliably.
+fn side(a: LatLon, b: LatLon, lat: Scalar, lon: Scalar) -> Ordering {
+    let left = (b.1 - a.1) * (lat - a.0);
+    let right = (lon - a.1) * (b.0 - a.0);
+    left.partial_cmp(&right).unwrap_or(Ordering::Equal)
+}
+
+/// [Winding number](https://en.wikipedia.org/wiki/Point_in_polygon#Winding_number_algorithm)
+/// test of a point against a ring, which holds for either orientation and for rings that
+/// touch themselves
+fn locate_in_ring(ring: &[LatLon], lat: Scalar, lon: Scalar) -> PointLocation {
+    let Some(&last) = ring.last() else {
+        return PointLocation::Outside;
+    };
+    let mut winding = 0i32;
+    let mut a = last;
+    for &b in ring {
+        let side = side(a, b, lat, lon);
+        if side == Ordering::Equal
+            && a.0.min(b.0) <= lat
+            && lat <= a.0.max(b.0)
+            && a.1.min(b.1) <= lon
+            && lon <= a.1.max(b.1)
+        {
+            return PointLocation::Boundary;
+        }
+        if a.0 <= lat {
+            if b.0 > lat && side == Ordering::Greater {
+                winding += 1;
+            }
+        } else if b.0 <= lat && side == Ordering::Less {
+            winding -= 1;
+        }
+        a = b;
+    }
+    if winding == 0 {
+        PointLocation::Outside
+    } else {
+        PointLocation::Inside
+    }
+}
+
+impl Polygon {
+    /// Where the point lies, outside if it is inside a hole
+    pub fn locate(&self, lat: Scalar, lon: Scalar) -> PointLocation {
+        match locate_in_ring(&self.outer, lat, lon) {
+            PointLocation::Inside => {}
+            location => return location,
+        }
+        for inner in &self.inners {
+            match locate_in_ring(inner, lat, lon) {
+                PointLocation::Inside => return PointLocation::Outside,
+                PointLocation::Boundary => return PointLocation::Boundary,
+                PointLocation::Outside => {}
+            }
+        }
+        PointLocation::Inside
+    }
+
+    /// Whether the point is inside or on the boundary
+    pub fn contains(&self, lat: Scalar, lon: Scalar) -> bool {
+        self.locate(lat, lon) != PointLocation::Outside
+    }
+}
+
+pub(crate) fn locate_in_polygons(polygons: &[Polygon], lat: Scalar, lon: Scalar) -> PointLocation {
+    let mut location = PointLocation::Outside;
+    for polygon in polygons {
+        match polygon.locate(lat, lon) {
+            PointLocation::Inside => return PointLocation::Inside,
+            PointLocation::Boundary => location = PointLocation::Boundary,
+            PointLocation::Outside => {}
+        }
+    }
+    location
+}
+
+impl MultiPolygon {
+    /// Where the point lies relative to the union of the polygons
+    pub fn locate(&self, lat: Scalar, lon: Scalar) -> PointLocation {
+        locate_in_polygons(&self.polygons, lat, lon)
+    }
+
+    /// Whether the point is inside or on the boundary of any of the polygons
+    ///
+    /// Points on a border shared by neighboring areas, e.g. administrative boundaries, are in
+    /// both.
+    pub fn contains(&self, lat: Scalar, lon: Scalar) -> bool {
+        self.locate(lat, lon) != PointLocation::Outside
+    }
+
+    /// Rings of each polygon, outer first, as taken by e.g. [crate::postgres::multi_polygon]
+    pub fn to_rings(&self) -> Vec<Vec<Vec<LatLon>>> {
+        self.polygons
+            .iter()
+            .map(|polygon| {
+                let mut rings = vec![polygon.outer.clone()];
+                rings.extend(polygon.inners.iter().cloned());
+                rings
+            })
+            .collect()
+    }
+}
+
+/// Reason [assemble] failed
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum AssemblyError {
+    /// Relation has no way members, or a way for [Area::from_way](crate::area::Area::from_way) has no nodes
+    NoWays,
+    MissingWay(Id),
+    MissingNode(MissingNode),
+    /// Ways end at this node without another way continuing the ring
+    OpenRing {
+        node: Id,
+    },
+    /// Ring returns to this node with fewer than three other nodes
+    DegenerateRing {
+        node: Id,
+    },
+    /// Id of the way or relation is too large for an [area id](crate::area::area_id)
+    IdTooLarge(Id),
+}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssemblyError::NoWays => write!(f, "multipolygon has no ways"),
+            AssemblyError::MissingWay(id) => write!(f, "way {} is missing", id.0),
+            AssemblyError::MissingNode(missing) => missing.fmt(f),
+            AssemblyError::OpenRing { node } => write!(f, "ring is not closed at node {}", node.0),
+            AssemblyError::DegenerateRing { node } => {
+                write!(f, "ring at node {} has fewer than three nodes", node.0)
+            }
+            AssemblyError::IdTooLarge(id) => write!(f, "id {} is too large for an area", id.0),
+        }
+    }
+}
+
+impl std::error::Error for AssemblyError {}
+
+/// Node of a ring along with its coordinates
+type Point = (Id, LatLon);
+
+/// Assembles the way members of a multipolygon into polygons
+///
+/// Ways are joined at shared end nodes, reversed as needed, into closed rings. Rings that
+/// touch at a node are split there. Roles are ignored, since they are often wrong: a ring is
+/// a hole if it lies inside an odd number of other rings and belongs to the smallest of them,
+/// so that islands in lakes become polygons of their own. Ways are resolved with `way` and
+/// coordinates with `node`.
+///
+/// Self-intersections and overlapping rings are not detected.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(relation = relation.id.0)))]
+pub fn assemble<'a>(
+    relation: &Relation,
+    mut way: impl FnMut(Id) -> Option<&'a Way>,
+    mut node: impl FnMut(Id) -> Option<LatLon>,
+) -> Result<MultiPolygon, AssemblyError> {
+    let mut seen = HashSet::default();
+    let mut ways = vec![];
+    for member in &relation.members {
+        // Ways listed twice would form degenerate rings
+        if member.ty != MemberType::Way || !seen.insert(member.id) {
+            continue;
+        }
+        let way = way(member.id).ok_or(AssemblyError::MissingWay(member.id))?;
+        let points = way
+            .refs
+            .iter()
+            .enumerate()
+            .map(|(index, &id)| {
+                let missing = MissingNode {
+                    way: way.id,
+                    node: id,
+                    index,
+                };
+                node(id)
+                    .map(|lat_lon| (id, lat_lon))
+                    .ok_or(AssemblyError::MissingNode(missing))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        ways.extend((!points.is_empty()).then_some(points));
+    }
+    if ways.is_empty() {
+        return Err(AssemblyError::NoWays);
+    }
+
+    let rings = stitch(ways)?;
+    let planar: Vec<_> = rings.iter().map(|ring| planar(ring)).collect();
+    let areas: Vec<_> = planar.iter().map(|ring| signed_area(ring).abs()).collect();
+    let ids: Vec<HashSet<Id>> = rings
+        .iter()
+        .map(|ring| ring.iter().map(|&(id, _)| id).collect())
+        .collect();
+    // Rings containing each ring, tested at a node the rings do not share
+    let containers: Vec<Vec<usize>> = (0..rings.len())
+        .map(|i| {
+            (0..rings.len())
+                .filter(|&j| {
+                    i != j
+                        && rings[i]
+                            .iter()
+                            .zip(&planar[i])
+                            .find(|((id, _), _)| !ids[j].contains(id))
+                            .is_some_and(|(_, &(lat, lon))| ring_contains(&planar[j], lat, lon))
+                })
+                .collect()
+        })
+        .collect();
+
+    let is_outer: Vec<_> = containers
+        .iter()
+        .map(|containers| containers.len().is_multiple_of(2))
+        .collect();
+
+    let mut polygons = vec![];
+    let mut polygon_of = HashMap::default();
+    for (i, ring) in rings.iter().enumerate() {
+        if is_outer[i] {
+            polygon_of.insert(i, polygons.len());
+            polygons.push(Polygon {
+                outer: oriented(ring, &planar[i], true),
+                inners: vec![],
+            });
+        }
+    }
+    for (i, ring) in rings.iter().enumerate() {
+        if !is_outer[i] {
+            let parent = containers[i]
+                .iter()
+                .copied()
+                .filter(|&j| is_outer[j])
+                .min_by(|&a, &b| areas[a].total_cmp(&areas[b]))
+                .expect("a ring inside an odd number of rings is inside an outer ring");
+            polygons[polygon_of[&parent]]
+                .inners
+                .push(oriented(ring, &planar[i], false));
+        }
+    }
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        rings = rings.len(),
+        polygons = polygons.len(),
+        "assembled multipolygon"
+    );
+    Ok(MultiPolygon { polygons })
+}
+
+impl ElementStore {
+    /// See [assemble]
+    pub fn assemble_multipolygon(
+        &self,
+        relation: &Relation,
+    ) -> Result<MultiPolygon, AssemblyError> {
+        assemble(
+            relation,
+            |id| self.get_way(id),
+            |id| self.get_node(id).map(Node::lat_lon),
+        )
+    }
+}
+
+/// Joins ways at shared end nodes into closed rings, reversing ways as needed
+///
+/// A ring that passes a node twice is split there into two rings.
+fn stitch(mut ways: Vec<Vec<Point>>) -> Result<Vec<Vec<Point>>, AssemblyError> {
+    let mut rings = vec![];
+    while let Some(first) = ways.pop() {
+        let mut ring: Vec<Point> = vec![];
+        // Position of each node in the ring
+        let mut positions = HashMap::default();
+        let mut next = first;
+        loop {
+            for point in next {
+                if ring.last().is_some_and(|last| last.0 == point.0) {
+                    continue;
+                }
+                if let Some(&start) = positions.get(&point.0) {
+                    let mut closed = ring.split_off(start);
+                    for (id, _) in &closed {
+                        positions.remove(id);
+                    }
+                    closed.push(point);
+                    if closed.len() < 4 {
+                        return Err(AssemblyError::DegenerateRing { node: point.0 });
+                    }
+                    rings.push(closed);
+                }
+                positions.insert(point.0, ring.len());
+                ring.push(point);
+            }
+            if ring.len() == 1 {
+                break;
+            }
+            let end = ring[ring.len() - 1].0;
+            let index = ways
+                .iter()
+                .position(|way| way[0].0 == end || way[way.len() - 1].0 == end)
+                .ok_or(AssemblyError::OpenRing { node: end })?;
+            let mut way = ways.swap_remove(index);
+            if way[0].0 != end {
+                way.reverse();
+            }
+            next = way;
+        }
+    }
+    Ok(rings)
+}
+
+fn planar(ring: &[Point]) -> Vec<(f64, f64)> {
+    ring.iter()
+        .map(|&(_, (lat, lon))| (lat.as_f64(), lon.as_f64()))
+        .collect()
+}
+
+/// Coordinates of a ring, counterclockwise if `outer` and clockwise otherwise
+fn oriented(ring: &[Point], planar: &[(f64, f64)], outer: bool) -> Vec<LatLon> {
+    let mut coordinates: Vec<_> = ring.iter().map(|&(_, lat_lon)| lat_lon).collect();
+    if (signed_area(planar) > 0.) != outer {
+        coordinates.reverse();
+    }
+    coordinates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Id of the node at a coordinate of whole degrees, from 0 to 99
+    fn node_id((lat, lon): (i64, i64)) -> Id {
+        Id(lat * 100 + lon + 1)
+    }
+
+    fn coordinate(id: Id) -> Option<LatLon> {
+        let (lat, lon) = ((id.0 - 1) / 100, (id.0 - 1) % 100);
+        Some((Scalar::from_int(lat), Scalar::from_int(lon)))
+    }
+
+    fn way(id: i64, points: &[(i64, i64)]) -> Way {
+        Way::builder(Id(id))
+            .nodes(points.iter().copied().map(node_id))
+            .build()
+            .unwrap()
+    }
+
+    /// Closed square ring from its south-western corner
+    fn square(id: i64, (lat, lon): (i64, i64), size: i64) -> Way {
+        let (north, east) = (lat + size, lon + size);
+        way(
+            id,
+            &[
+                (lat, lon),
+                (lat, east),
+                (north, east),
+                (north, lon),
+                (lat, lon),
+            ],
+        )
+    }
+
+    fn assemble_ways(ways: &[Way]) -> Result<MultiPolygon, AssemblyError> {
+        let mut relation = Relation::builder(Id(1)).tag("type", "multipolygon");
+        for way in ways {
+            relation = relation.member(crate::WayId(way.id.0), "outer");
+        }
+        let relation = relation.build().unwrap();
+        assemble(
+            &relation,
+            |id| ways.iter().find(|way| way.id == id),
+            coordinate,
+        )
+    }
+
+    fn area(ring: &[LatLon]) -> f64 {
+        let planar: Vec<_> = ring
+            .iter()
+            .map(|&(lat, lon)| (lat.as_f64(), lon.as_f64()))
+            .collect();
+        signed_area(&planar)
+    }
+
+    #[test]
+    fn joins_and_orients_ways() {
+        // Clockwise square, split into two ways of which the second runs backwards
+        let ways = [
+            way(10, &[(0, 0), (10, 0), (10, 10)]),
+            way(11, &[(0, 0), (0, 10), (10, 10)]),
+        ];
+        let multipolygon = assemble_ways(&ways).unwrap();
+        assert_eq!(multipolygon.polygons.len(), 1);
+        let polygon = &multipolygon.polygons[0];
+        assert_eq!(polygon.outer.len(), 5);
+        assert_eq!(polygon.outer.first(), polygon.outer.last());
+        assert_eq!(area(&polygon.outer), 100.);
+        assert!(polygon.inners.is_empty());
+    }
+
+    #[test]
+    fn nested_holes() {
+        let ways = [
+            square(10, (0, 0), 10),
+            square(11, (2, 2), 6),
+            // Island in the lake, and a pond on the island
+            square(12, (4, 4), 2),
+            way(13, &[(4, 4), (5, 4), (5, 5), (4, 5), (4, 4)]),
+        ];
+        // The pond shares a corner with the island, so it is tested at another node
+        let mut multipolygon = assemble_ways(&ways).unwrap();
+        multipolygon
+            .polygons
+            .sort_by(|a, b| area(&b.outer).total_cmp(&area(&a.outer)));
+        let [outer, island] = &multipolygon.polygons[..] else {
+            panic!("expected two polygons, got {multipolygon:?}");
+        };
+        assert_eq!(area(&outer.outer), 100.);
+        assert_eq!(outer.inners.len(), 1);
+        assert_eq!(area(&outer.inners[0]), -36.);
+        assert_eq!(area(&island.outer), 4.);
+        assert_eq!(island.inners.len(), 1);
+        assert_eq!(area(&island.inners[0]), -1.);
+
+        let (two, five) = (Scalar::from_int(2), Scalar::from_int(5));
+        let (one, three) = (Scalar::from_int(1), Scalar::from_int(3));
+        assert!(multipolygon.contains(one, one));
+        assert!(!multipolygon.contains(three, three));
+        assert!(multipolygon.contains(five, Scalar::with_scale(55, 1)));
+        assert!(!multipolygon.contains(Scalar::with_scale(45, 1), Scalar::with_scale(45, 1)));
+        assert!(multipolygon.contains(two, two));
+    }
+
+    #[test]
+    fn touching_rings_are_split() {
+        // Two squares touching at a corner, drawn as one way passing it twice
+        let figure_eight = way(
+            10,
+            &[
+                (0, 0),
+                (0, 2),
+                (2, 2),
+                (2, 4),
+                (4, 4),
+                (4, 2),
+                (2, 2),
+                (2, 0),
+                (0, 0),
+            ],
+        );
+        let multipolygon = assemble_ways(&[figure_eight]).unwrap();
+        assert_eq!(multipolygon.polygons.len(), 2);
+        for polygon in &multipolygon.polygons {
+            assert_eq!(area(&polygon.outer), 4.);
+            assert!(polygon.inners.is_empty());
+        }
+
+        // Separate ways touching at a corner
+        let multipolygon = assemble_ways(&[square(10, (0, 0), 2), square(11, (2, 2), 2)]).unwrap();
+        assert_eq!(multipolygon.polygons.len(), 2);
+        assert!(multipolygon
+            .polygons
+            .iter()
+            .all(|polygon| polygon.inners.is_empty()));
+    }
+
+    #[test]
+    fn unclosed_input() {
+        let open = way(10, &[(0, 0), (0, 10), (10, 10)]);
+        assert!(matches!(
+            assemble_ways(std::slice::from_ref(&open)),
+            Err(AssemblyError::OpenRing { .. })
+        ));
+        let gap = way(11, &[(10, 10), (10, 0), (1, 0)]);
+        assert_eq!(
+            assemble_ways(&[open, gap]),
+            Err(AssemblyError::OpenRing {
+                node: node_id((1, 0))
+            })
+        );
+        assert_eq!(
+            assemble_ways(&[way(10, &[(0, 0), (0, 1), (0, 0)])]),
+            Err(AssemblyError::DegenerateRing {
+                node: node_id((0, 0))
+            })
+        );
+        let relation = Relation::builder(Id(1))
+            .member(crate::WayId(10), "outer")
+            .build()
+            .unwrap();
+        assert_eq!(
+            assemble(&relation, |_| None, coordinate),
+            Err(AssemblyError::MissingWay(Id(10)))
+        );
+        assert_eq!(assemble_ways(&[]), Err(AssemblyError::NoWays));
+    }
+}
@@ -0,0 +1,212 @@
+//! Results of the [Nominatim](https://nominatim.org/release-docs/latest/api/Overview/) geocoder
+//!
+//! With the `serde` feature, [Place] has the schema of the `json` and `jsonv2` output formats,
+//! so `/search` responses deserialize as a `Vec<Place>` and `/reverse` responses as a
+//! [Reverse]. Nominatim writes coordinates as strings, which are kept exactly, and names the
+//! element a place was made from with `osm_type` and `osm_id`, which become an [ElementId].
+
+use crate::bbox::Bbox;
+use crate::id::ElementId;
+use crate::scalar::Scalar;
+use crate::{MemberType, TagString, Tags};
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "json::PlaceRepr", into = "json::PlaceRepr")
+)]
+pub struct Place {
+    /// Internal id of the place, which differs between Nominatim installations and imports
+    pub place_id: u64,
+    /// Element the place was made from, [None] for places from other data such as postcodes
+    pub element: Option<ElementId>,
+    pub lat: Scalar,
+    pub lon: Scalar,
+    /// Extent of the place, a box of a few meters around points
+    pub bbox: Option<Bbox>,
+    /// Full name with the address, e.g. `Brandenburger Tor, Pariser Platz, Mitte, Berlin, …`
+    pub display_name: String,
+    /// Name in the requested language, only given by `jsonv2`
+    pub name: Option<TagString>,
+    /// Key of the main tag, e.g. `tourism`, named `class` in `json` and `category` in `jsonv2`
+    pub category: TagString,
+    /// Value of the main tag, e.g. `attraction`
+    pub ty: TagString,
+    /// Rank of the place from 1 for continents to 30 for single buildings, only given by
+    /// `jsonv2`
+    pub place_rank: Option<u8>,
+    /// Relevance of the place between 0 and 1, used to order search results
+    pub importance: Option<f64>,
+    /// Kind of address part the place is in an address, e.g. `road` or `city`
+    pub address_type: Option<TagString>,
+    /// Parts of the address by kind, e.g. `road` and `postcode`, with `addressdetails=1`
+    pub address: Tags,
+    /// Further tags of the element, with `extratags=1`
+    pub extra_tags: Tags,
+    /// Tags of the names of the element, e.g. `name:de`, with `namedetails=1`
+    pub name_details: Tags,
+    /// Notice of the license of the data, e.g. `Data © OpenStreetMap contributors, ODbL 1.0. …`
+    pub licence: Option<String>,
+}
+
+impl Place {
+    /// Type of [Place::element]
+    pub fn member_type(&self) -> Option<MemberType> {
+        self.element.as_ref().map(ElementId::member_type)
+    }
+}
+
+/// Body of a `/reverse` response, an error if there is no place at the location
+#[cfg(feature = "serde")]
+#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum Reverse {
+    Place(Box<Place>),
+    Error { error: String },
+}
+
+#[cfg(feature = "serde")]
+impl Reverse {
+    /// Place found, [None] for an error
+    pub fn place(self) -> Option<Place> {
+        match self {
+            Reverse::Place(place) => Some(*place),
+            Reverse::Error { .. } => None,
+        }
+    }
+}
+
+/// Parses an `osm_type` of Nominatim, `node`, `way`, or `relation` as in search results, or
+/// `N`, `W`, or `R` as in the details and lookup endpoints
+pub fn parse_osm_type(osm_type: &str) -> Option<MemberType> {
+    match osm_type {
+        "node" | "N" => Some(MemberType::Node),
+        "way" | "W" => Some(MemberType::Way),
+        "relation" | "R" => Some(MemberType::Relation),
+        _ => None,
+    }
+}
+
+/// JSON representation of places, with coordinates as strings
+#[cfg(feature = "serde")]
+mod json {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::scalar::ScalarExt;
+    use crate::Id;
+
+    #[derive(Serialize, Deserialize)]
+    pub struct PlaceRepr {
+        place_id: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        licence: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        osm_type: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        osm_id: Option<i64>,
+        lat: String,
+        lon: String,
+        /// Minimum and maximum latitude, then minimum and maximum longitude
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        boundingbox: Option<[String; 4]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        place_rank: Option<u8>,
+        #[serde(alias = "class")]
+        category: TagString,
+        #[serde(rename = "type")]
+        ty: TagString,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        importance: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        addresstype: Option<TagString>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<TagString>,
+        display_name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        address: Option<Tags>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        extratags: Option<Tags>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        namedetails: Option<Tags>,
+    }
+
+    fn coordinate(value: &str) -> Result<Scalar, String> {
+        Scalar::parse_decimal(value).ok_or_else(|| format!("invalid coordinate {value}"))
+    }
+
+    impl TryFrom<PlaceRepr> for Place {
+        type Error = String;
+
+        fn try_from(place: PlaceRepr) -> Result<Self, Self::Error> {
+            let element = match (place.osm_type, place.osm_id) {
+                (Some(osm_type), Some(osm_id)) => {
+                    let ty = parse_osm_type(&osm_type)
+                        .ok_or_else(|| format!("invalid osm_type {osm_type}"))?;
+                    Some(ElementId::new(ty, Id(osm_id)))
+                }
+                _ => None,
+            };
+            let bbox = place
+                .boundingbox
+                .map(|[min_lat, max_lat, min_lon, max_lon]| {
+                    Ok::<_, String>(Bbox::new(
+                        coordinate(&min_lat)?,
+                        coordinate(&min_lon)?,
+                        coordinate(&max_lat)?,
+                        coordinate(&max_lon)?,
+                    ))
+                })
+                .transpose()?;
+            Ok(Place {
+                place_id: place.place_id,
+                element,
+                lat: coordinate(&place.lat)?,
+                lon: coordinate(&place.lon)?,
+                bbox,
+                display_name: place.display_name,
+                name: place.name.filter(|name| !name.is_empty()),
+                category: place.category,
+                ty: place.ty,
+                place_rank: place.place_rank,
+                importance: place.importance,
+                address_type: place.addresstype,
+                address: place.address.unwrap_or_default(),
+                extra_tags: place.extratags.unwrap_or_default(),
+                name_details: place.namedetails.unwrap_or_default(),
+                licence: place.licence,
+            })
+        }
+    }
+
+    impl From<Place> for PlaceRepr {
+        fn from(place: Place) -> Self {
+            let tags = |tags: Tags| (!tags.is_empty()).then_some(tags);
+            PlaceRepr {
+                place_id: place.place_id,
+                licence: place.licence,
+                osm_type: place
+                    .element
+                    .map(|element| element.member_type().as_str().to_string()),
+                osm_id: place.element.map(|element| element.id().0),
+                lat: place.lat.normalized().to_string(),
+                lon: place.lon.normalized().to_string(),
+                boundingbox: place.bbox.map(|bbox| {
+                    [bbox.min_lat, bbox.max_lat, bbox.min_lon, bbox.max_lon]
+                        .map(|value| value.normalized().to_string())
+                }),
+                place_rank: place.place_rank,
+                category: place.category,
+                ty: place.ty,
+                importance: place.importance,
+                addresstype: place.address_type,
+                name: place.name,
+                display_name: place.display_name,
+                address: tags(place.address),
+                extratags: tags(place.extra_tags),
+                namedetails: tags(place.name_details),
+            }
+        }
+    }
+}
@@ -0,0 +1,468 @@
+//! [Notes](https://wiki.openstreetmap.org/wiki/Notes), the reports anyone can leave on the map
+//! for mappers to look into
+//!
+//! [Reader] streams notes from the XML of the API, e.g. `api/0.6/notes?bbox=…`, or from the
+//! [notes dump](https://planet.openstreetmap.org/notes/). With the `serde` feature, [Note] has
+//! the schema of the GeoJSON API, a `Feature`, and [Document] is a `FeatureCollection`.
+
+use std::fmt;
+use std::io::BufRead;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::error::{Error, Result};
+use crate::scalar::{Scalar, ScalarExt};
+use crate::xml::{optional, TagKind, Tokenizer, XmlTag};
+use crate::{timestamp_from_utc, TagString, Timestamp};
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "json::Feature", into = "json::Feature")
+)]
+pub struct Note {
+    pub id: i64,
+    /// [WGS 84](https://en.wikipedia.org/wiki/World_Geodetic_System#WGS84) latitude (y)
+    pub lat: Scalar,
+    /// [WGS 84](https://en.wikipedia.org/wiki/World_Geodetic_System#WGS84) longitude (x)
+    pub lon: Scalar,
+    pub status: NoteStatus,
+    pub created_at: Option<Timestamp>,
+    pub closed_at: Option<Timestamp>,
+    /// Discussion in order, starting with the comment that opened the note
+    pub comments: Vec<NoteComment>,
+}
+
+impl Note {
+    /// Text of the comment that opened the note
+    pub fn description(&self) -> Option<&str> {
+        self.comments
+            .iter()
+            .find(|comment| comment.action == NoteAction::Opened)
+            .map(|comment| comment.text.as_str())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum NoteStatus {
+    Open,
+    Closed,
+    /// Hidden by a moderator, only returned to moderators
+    Hidden,
+}
+
+impl NoteStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NoteStatus::Open => "open",
+            NoteStatus::Closed => "closed",
+            NoteStatus::Hidden => "hidden",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "open" => Some(NoteStatus::Open),
+            "closed" => Some(NoteStatus::Closed),
+            "hidden" => Some(NoteStatus::Hidden),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for NoteStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What a [NoteComment] did to its note
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum NoteAction {
+    Opened,
+    Commented,
+    Closed,
+    Reopened,
+    Hidden,
+}
+
+impl NoteAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NoteAction::Opened => "opened",
+            NoteAction::Commented => "commented",
+            NoteAction::Closed => "closed",
+            NoteAction::Reopened => "reopened",
+            NoteAction::Hidden => "hidden",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "opened" => Some(NoteAction::Opened),
+            "commented" => Some(NoteAction::Commented),
+            "closed" => Some(NoteAction::Closed),
+            "reopened" => Some(NoteAction::Reopened),
+            "hidden" => Some(NoteAction::Hidden),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for NoteAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Comment in the discussion of a [Note]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NoteComment {
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none", with = "json::time")
+    )]
+    pub date: Option<Timestamp>,
+    /// [None] along with `user` for anonymous comments
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub uid: Option<i32>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub user: Option<TagString>,
+    pub action: NoteAction,
+    /// Plain text, without the HTML the API also gives
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub text: String,
+}
+
+/// Body of a GeoJSON response listing notes
+#[cfg(feature = "serde")]
+#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+#[serde(from = "json::FeatureCollection", into = "json::FeatureCollection")]
+pub struct Document {
+    pub notes: Vec<Note>,
+}
+
+/// Reads the `note`s of an XML document
+#[derive(Debug)]
+pub struct Reader<R> {
+    tokenizer: Tokenizer<R>,
+    done: bool,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            tokenizer: Tokenizer::new(input),
+            done: false,
+        }
+    }
+}
+
+impl<R> Reader<R> {
+    pub fn into_inner(self) -> R {
+        self.tokenizer.input
+    }
+}
+
+impl<R: BufRead> Reader<R> {
+    fn read_note(&mut self) -> Result<Option<Note>> {
+        while let Some(tag) = self.tokenizer.next_tag()? {
+            if tag.name != "note" || tag.kind == TagKind::End {
+                continue;
+            }
+            let offset = self.tokenizer.tag_offset;
+            let error = |message: String| Error::decode(Some(offset), message);
+            let (mut note, mut id) = start_note(&tag).map_err(error)?;
+            let dump = id.is_some();
+            if tag.kind == TagKind::Start {
+                id = self.read_children(&mut note)?.or(id);
+            }
+            note.id = id.ok_or_else(|| Error::decode(Some(offset), "missing id"))?;
+            if dump && note.closed_at.is_some() {
+                // Dumps only give the time a note was closed
+                note.status = NoteStatus::Closed;
+            }
+            return Ok(Some(note));
+        }
+        Ok(None)
+    }
+
+    /// Reads the elements and comments up to the end of the note, returning the id if given
+    /// as an element as in the API
+    fn read_children(&mut self, note: &mut Note) -> Result<Option<i64>> {
+        let mut id = None;
+        let mut comment = None::<NoteComment>;
+        // Dumps give the text of a comment as its content rather than in a `text` element
+        let mut text_content = false;
+        while let Some(tag) = self.tokenizer.next_tag()? {
+            let offset = self.tokenizer.tag_offset;
+            let error = |message: String| Error::decode(Some(offset), message);
+            if tag.kind == TagKind::Start && tag.name == "comment" {
+                text_content = tag.attribute("action").is_some();
+                comment = Some(start_comment(&tag).map_err(error)?);
+                continue;
+            }
+            if tag.kind != TagKind::End {
+                continue;
+            }
+            let text = || self.tokenizer.text();
+            let invalid = |name: &str| error(format!("invalid {name}"));
+            if tag.name == "comment" {
+                if let Some(mut comment) = comment.take() {
+                    if text_content {
+                        comment.text = text()?;
+                    }
+                    note.comments.push(comment);
+                }
+                continue;
+            }
+            match (&mut comment, tag.name.as_str()) {
+                (_, "note") => return Ok(id),
+                (Some(comment), "date") => {
+                    comment.date = Some(parse_time(&text()?).ok_or_else(|| invalid("date"))?);
+                }
+                (Some(comment), "uid") => {
+                    comment.uid = Some(text()?.parse().map_err(|_| invalid("uid"))?);
+                }
+                (Some(comment), "user") => comment.user = Some(text()?.into()),
+                (Some(comment), "action") => {
+                    comment.action =
+                        NoteAction::parse(&text()?).ok_or_else(|| invalid("action"))?;
+                }
+                (Some(comment), "text") => comment.text = text()?,
+                (None, "id") => id = Some(text()?.parse().map_err(|_| invalid("id"))?),
+                (None, "date_created") => {
+                    note.created_at =
+                        Some(parse_time(&text()?).ok_or_else(|| invalid("date_created"))?);
+                }
+                (None, "date_closed") => {
+                    note.closed_at =
+                        Some(parse_time(&text()?).ok_or_else(|| invalid("date_closed"))?);
+                }
+                (None, "status") => {
+                    note.status = NoteStatus::parse(&text()?).ok_or_else(|| invalid("status"))?;
+                }
+                _ => {}
+            }
+        }
+        Err(Error::decode(
+            Some(self.tokenizer.offset),
+            "unexpected end of file",
+        ))
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<Note>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let note = self.read_note().transpose();
+        if !matches!(note, Some(Ok(_))) {
+            self.done = true;
+        }
+        note
+    }
+}
+
+/// Note from the attributes of a `note`, and the id if given as an attribute as in dumps
+fn start_note(tag: &XmlTag) -> std::result::Result<(Note, Option<i64>), String> {
+    let coordinate = |name| {
+        tag.attribute(name)
+            .and_then(Scalar::parse_decimal)
+            .ok_or_else(|| format!("missing or invalid {name} attribute"))
+    };
+    let id = optional(tag, "id")?;
+    let note = Note {
+        id: id.unwrap_or_default(),
+        lat: coordinate("lat")?,
+        lon: coordinate("lon")?,
+        status: NoteStatus::Open,
+        created_at: time_attribute(tag, "created_at")?,
+        closed_at: time_attribute(tag, "closed_at")?,
+        comments: vec![],
+    };
+    Ok((note, id))
+}
+
+/// Comment from the attributes of a `comment` in a dump, empty in the API
+fn start_comment(tag: &XmlTag) -> std::result::Result<NoteComment, String> {
+    Ok(NoteComment {
+        date: time_attribute(tag, "timestamp")?,
+        uid: optional(tag, "uid")?,
+        user: tag.attribute("user").map(TagString::from_ref),
+        action: match tag.attribute("action") {
+            Some(action) => NoteAction::parse(action).ok_or("invalid action attribute")?,
+            None => NoteAction::Commented,
+        },
+        text: String::new(),
+    })
+}
+
+fn time_attribute(tag: &XmlTag, name: &str) -> std::result::Result<Option<Timestamp>, String> {
+    tag.attribute(name)
+        .map(|value| parse_time(value).ok_or_else(|| format!("invalid {name} attribute")))
+        .transpose()
+}
+
+/// Time as the API writes it for notes, e.g. `2024-01-31 12:00:00 UTC`, or in RFC 3339 as
+/// in dumps
+fn parse_time(value: &str) -> Option<Timestamp> {
+    let time = match NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S UTC") {
+        Ok(time) => time.and_utc(),
+        Err(_) => DateTime::parse_from_rfc3339(value)
+            .ok()?
+            .with_timezone(&Utc),
+    };
+    Some(timestamp_from_utc(time))
+}
+
+/// GeoJSON representation of notes
+#[cfg(feature = "serde")]
+mod json {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(tag = "type")]
+    pub enum Feature {
+        Feature {
+            geometry: Geometry,
+            properties: Properties,
+        },
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(tag = "type")]
+    pub enum Geometry {
+        Point { coordinates: Coordinates },
+    }
+
+    /// Longitude and latitude
+    #[derive(Serialize, Deserialize)]
+    pub struct Coordinates(
+        #[serde(with = "crate::json::coordinate")] Scalar,
+        #[serde(with = "crate::json::coordinate")] Scalar,
+    );
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Properties {
+        id: i64,
+        status: NoteStatus,
+        #[serde(default, skip_serializing_if = "Option::is_none", with = "time")]
+        date_created: Option<Timestamp>,
+        #[serde(default, skip_serializing_if = "Option::is_none", with = "time")]
+        closed_at: Option<Timestamp>,
+        #[serde(default)]
+        comments: Vec<NoteComment>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(tag = "type")]
+    pub enum FeatureCollection {
+        FeatureCollection { features: Vec<Note> },
+    }
+
+    impl From<Feature> for Note {
+        fn from(feature: Feature) -> Self {
+            let Feature::Feature {
+                geometry:
+                    Geometry::Point {
+                        coordinates: Coordinates(lon, lat),
+                    },
+                properties,
+            } = feature;
+            Note {
+                id: properties.id,
+                lat,
+                lon,
+                status: properties.status,
+                created_at: properties.date_created,
+                closed_at: properties.closed_at,
+                comments: properties.comments,
+            }
+        }
+    }
+
+    impl From<Note> for Feature {
+        fn from(note: Note) -> Self {
+            Feature::Feature {
+                geometry: Geometry::Point {
+                    coordinates: Coordinates(note.lon, note.lat),
+                },
+                properties: Properties {
+                    id: note.id,
+                    status: note.status,
+                    date_created: note.created_at,
+                    closed_at: note.closed_at,
+                    comments: note.comments,
+                },
+            }
+        }
+    }
+
+    impl From<FeatureCollection> for Document {
+        fn from(collection: FeatureCollection) -> Self {
+            let FeatureCollection::FeatureCollection { features } = collection;
+            Document { notes: features }
+        }
+    }
+
+    impl From<Document> for FeatureCollection {
+        fn from(document: Document) -> Self {
+            FeatureCollection::FeatureCollection {
+                features: document.notes,
+            }
+        }
+    }
+
+    /// Times in the format of the notes API, e.g. `2024-01-31 12:00:00 UTC`
+    pub mod time {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(
+            value: &Option<Timestamp>,
+            serializer: S,
+        ) -> std::result::Result<S::Ok, S::Error> {
+            match value {
+                Some(value) => serializer
+                    .collect_str(&crate::timestamp_to_utc(*value).format("%Y-%m-%d %H:%M:%S UTC")),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> std::result::Result<Option<Timestamp>, D::Error> {
+            Option::<String>::deserialize(deserializer)?
+                .map(|value| {
+                    parse_time(&value)
+                        .ok_or_else(|| serde::de::Error::custom("invalid note timestamp"))
+                })
+                .transpose()
+        }
+    }
+}
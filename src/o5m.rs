@@ -0,0 +1,752 @@
+//! [o5m](https://wiki.openstreetmap.org/wiki/O5m) files, as written by osmconvert
+//!
+//! o5m stores elements as datasets of varints, with ids, coordinates, and metadata coded as
+//! deltas to the previous dataset and strings referencing a table of recent strings.
+//! [Reader] decodes both o5m and o5c, the variant for changes.
+
+use std::io::BufRead;
+
+use chrono::DateTime;
+
+use crate::bbox::Bbox;
+use crate::error::{Error, Result};
+use crate::parse::{ParseOptions, Recovery};
+use crate::progress::{NoProgress, Observer};
+use crate::scalar::{Scalar, ScalarExt};
+use crate::{
+    Element, Id, Info, Member, MemberType, Members, Node, Refs, Relation, TagString, Tags,
+    Timestamp, Way,
+};
+
+/// Strings the reference table holds
+const TABLE_SIZE: usize = 15_000;
+/// Longest pair of strings, in bytes, that is added to the reference table
+const MAX_TABLE_STRING: usize = 250;
+/// Longest dataset that is read
+const MAX_DATASET_SIZE: u64 = 64 * 1024 * 1024;
+
+const NODE: u8 = 0x10;
+const WAY: u8 = 0x11;
+const RELATION: u8 = 0x12;
+const BOUNDING_BOX: u8 = 0xdb;
+const FILE_TIMESTAMP: u8 = 0xdc;
+const HEADER: u8 = 0xe0;
+const END_OF_FILE: u8 = 0xfe;
+const RESET: u8 = 0xff;
+
+/// Header datasets of a file
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub struct Header {
+    /// Whether the file is o5c, which contains changes rather than complete data
+    pub is_change: bool,
+    pub bbox: Option<Bbox>,
+    /// Time the data was extracted
+    pub timestamp: Option<Timestamp>,
+}
+
+/// Streaming reader of [Element]s in an o5m file
+///
+/// Elements without any data after their metadata, which o5c uses for deletions, are read
+/// with `visible` set to false and nodes placed at `0, 0`.
+/// Elements without a version have no [Info].
+///
+/// Malformed elements end reading unless [ParseOptions] say otherwise. As later datasets are
+/// coded relative to a malformed one, skipping it also skips those up to the next reset.
+#[derive(Debug)]
+pub struct Reader<R, O = NoProgress> {
+    input: R,
+    observer: O,
+    offset: u64,
+    header: Header,
+    state: DeltaState,
+    buf: Vec<u8>,
+    recovery: Recovery,
+    /// Whether elements are skipped until the next reset, after a malformed one
+    resync: bool,
+    done: bool,
+}
+
+/// Values that datasets are coded relative to, which a reset clears
+#[derive(Debug)]
+struct DeltaState {
+    id: i64,
+    timestamp: i64,
+    changeset: i64,
+    lat: i64,
+    lon: i64,
+    /// Node, way, and relation references
+    refs: [i64; 3],
+    /// Recent strings, which references count back from `next`
+    table: Vec<Vec<u8>>,
+    next: usize,
+}
+
+impl Default for DeltaState {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            timestamp: 0,
+            changeset: 0,
+            lat: 0,
+            lon: 0,
+            refs: [0; 3],
+            table: vec![vec![]; TABLE_SIZE],
+            next: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            input,
+            observer: NoProgress,
+            offset: 0,
+            header: Header::default(),
+            state: DeltaState::default(),
+            buf: vec![],
+            recovery: Recovery::default(),
+            resync: false,
+            done: false,
+        }
+    }
+}
+
+impl<R, O> Reader<R, O> {
+    /// Reports bytes and elements read to `observer`
+    pub fn with_observer<P: Observer>(self, observer: P) -> Reader<R, P> {
+        Reader {
+            input: self.input,
+            observer,
+            offset: self.offset,
+            header: self.header,
+            state: self.state,
+            buf: self.buf,
+            recovery: self.recovery,
+            resync: self.resync,
+            done: self.done,
+        }
+    }
+
+    /// Sets how malformed elements are handled
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.recovery.options = options;
+        self
+    }
+
+    /// Errors of the malformed elements skipped so far with [ErrorPolicy::Warn]
+    ///
+    /// [ErrorPolicy::Warn]: crate::parse::ErrorPolicy::Warn
+    pub fn warnings(&self) -> &[Error] {
+        &self.recovery.warnings
+    }
+
+    /// Takes the [Reader::warnings], e.g. to report them while reading a large file
+    pub fn take_warnings(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.recovery.warnings)
+    }
+
+    /// Header of the file, which is complete once the first element was read
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn into_inner(self) -> R {
+        self.input
+    }
+}
+
+impl<R: BufRead, O: Observer> Reader<R, O> {
+    fn read_element(&mut self) -> Result<Option<Element>> {
+        loop {
+            let offset = self.offset;
+            let Some(kind) = self.read_byte()? else {
+                return Ok(None);
+            };
+            match kind {
+                RESET => {
+                    self.state = DeltaState::default();
+                    self.resync = false;
+                    continue;
+                }
+                END_OF_FILE => return Ok(None),
+                // Datasets of these types have no length
+                0xf0..=0xff => continue,
+                _ => {}
+            }
+            let len = self.read_varint()?;
+            if len > MAX_DATASET_SIZE {
+                return Err(Error::decode(Some(offset), "dataset is too large"));
+            }
+            self.buf.resize(len as usize, 0);
+            self.input
+                .read_exact(&mut self.buf)
+                .map_err(|_| Error::decode(Some(self.offset), "unexpected end of file"))?;
+            self.offset += len;
+            self.observer.bytes_read(self.offset - offset);
+
+            let mut data = Dataset {
+                data: &self.buf,
+                state: &mut self.state,
+            };
+            let error = |message| Error::decode(Some(offset), message);
+            match kind {
+                NODE | WAY | RELATION if self.resync => {}
+                NODE | WAY | RELATION => match data.element(kind).map_err(error) {
+                    Ok(element) => match self.recovery.check(&element) {
+                        Ok(()) => return Ok(Some(element)),
+                        Err(err) => self.recovery.skip(err)?,
+                    },
+                    Err(err) => {
+                        self.recovery.skip(err)?;
+                        self.resync = true;
+                    }
+                },
+                HEADER => match &self.buf[..] {
+                    b"o5m2" => self.header.is_change = false,
+                    b"o5c2" => self.header.is_change = true,
+                    _ => return Err(error("unsupported o5m version")),
+                },
+                BOUNDING_BOX => {
+                    let mut coordinate = || data.signed().map(|value| Scalar::with_scale(value, 7));
+                    let (min_lon, min_lat) = (coordinate(), coordinate());
+                    let (max_lon, max_lat) = (coordinate(), coordinate());
+                    self.header.bbox = Some(Bbox::new(
+                        min_lat.map_err(error)?,
+                        min_lon.map_err(error)?,
+                        max_lat.map_err(error)?,
+                        max_lon.map_err(error)?,
+                    ));
+                }
+                FILE_TIMESTAMP => {
+                    let seconds = data.signed().map_err(error)?;
+                    self.header.timestamp =
+                        DateTime::from_timestamp(seconds, 0).map(crate::timestamp_from_utc);
+                }
+                // Readers should skip datasets of unknown types, e.g. jump datasets
+                _ => {}
+            }
+        }
+    }
+
+    fn read_byte(&mut self) -> Result<Option<u8>> {
+        let Some(&byte) = self.input.fill_buf()?.first() else {
+            return Ok(None);
+        };
+        self.input.consume(1);
+        self.offset += 1;
+        Ok(Some(byte))
+    }
+
+    fn read_varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self
+                .read_byte()?
+                .ok_or_else(|| Error::decode(Some(self.offset), "unexpected end of file"))?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Error::decode(Some(self.offset), "varint is too long"))
+    }
+}
+
+impl<R: BufRead, O: Observer> Iterator for Reader<R, O> {
+    type Item = Result<Element>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let element = self.read_element();
+        match &element {
+            Ok(Some(element)) => self.observer.element_read(element),
+            Ok(None) | Err(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(bytes = self.offset, "finished reading o5m");
+                self.done = true;
+            }
+        }
+        element.transpose()
+    }
+}
+
+/// Data of a single dataset being decoded
+struct Dataset<'a> {
+    data: &'a [u8],
+    state: &'a mut DeltaState,
+}
+
+impl Dataset<'_> {
+    fn element(&mut self, kind: u8) -> std::result::Result<Element, &'static str> {
+        self.state.id = self.delta(self.state.id)?;
+        let id = Id(self.state.id);
+        let mut info = self.info()?;
+        // Datasets that end after the metadata are deletions in o5c
+        let deleted = self.data.is_empty();
+        if deleted {
+            info.get_or_insert_with(unknown_info).visible = Some(false);
+        }
+
+        Ok(match kind {
+            NODE => {
+                let (lat, lon) = if deleted {
+                    (Scalar::ZERO, Scalar::ZERO)
+                } else {
+                    self.state.lon = self.delta(self.state.lon)?;
+                    self.state.lat = self.delta(self.state.lat)?;
+                    (
+                        Scalar::with_scale(self.state.lat, 7),
+                        Scalar::with_scale(self.state.lon, 7),
+                    )
+                };
+                Element::Node(Node {
+                    id,
+                    tags: self.tags()?,
+                    info,
+                    lat,
+                    lon,
+                })
+            }
+            WAY => {
+                let mut refs = Refs::new();
+                let mut section = self.section(deleted)?;
+                while !section.data.is_empty() {
+                    section.state.refs[0] = section.delta(section.state.refs[0])?;
+                    refs.push(Id(section.state.refs[0]));
+                }
+                Element::Way(Way {
+                    id,
+                    tags: self.tags()?,
+                    info,
+                    refs,
+                })
+            }
+            _ => {
+                let mut members = Members::new();
+                let mut section = self.section(deleted)?;
+                while !section.data.is_empty() {
+                    let delta = section.signed()?;
+                    let (role, _) = section.string(false)?;
+                    let (&ty, role) = role.split_first().ok_or("missing member type")?;
+                    let (ty, index) = match ty {
+                        b'0' => (MemberType::Node, 0),
+                        b'1' => (MemberType::Way, 1),
+                        b'2' => (MemberType::Relation, 2),
+                        _ => return Err("invalid member type"),
+                    };
+                    section.state.refs[index] = section.state.refs[index]
+                        .checked_add(delta)
+                        .ok_or("delta overflows")?;
+                    let role = std::str::from_utf8(role).map_err(|_| "invalid UTF-8 in role")?;
+                    members.push(Member {
+                        id: Id(section.state.refs[index]),
+                        ty,
+                        role: (!role.is_empty()).then(|| TagString::from_ref(role)),
+                    });
+                }
+                Element::Relation(Relation {
+                    id,
+                    tags: self.tags()?,
+                    info,
+                    members,
+                })
+            }
+        })
+    }
+
+    fn info(&mut self) -> std::result::Result<Option<Info>, &'static str> {
+        let version = self.unsigned()?;
+        if version == 0 {
+            return Ok(None);
+        }
+        let mut info = Info {
+            version: i32::try_from(version).map_err(|_| "invalid version")?,
+            ..unknown_info()
+        };
+        self.state.timestamp = self.delta(self.state.timestamp)?;
+        if self.state.timestamp != 0 {
+            info.timestamp =
+                DateTime::from_timestamp(self.state.timestamp, 0).map(crate::timestamp_from_utc);
+            self.state.changeset = self.delta(self.state.changeset)?;
+            info.changeset = Some(self.state.changeset).filter(|&changeset| changeset != 0);
+            let (uid, user) = self.string(true)?;
+            // A uid of 0 is its own terminator, which leaves the string empty
+            let uid = if uid.is_empty() {
+                0
+            } else {
+                varint(&mut &uid[..])?
+            };
+            info.uid = Some(i32::try_from(uid).map_err(|_| "invalid uid")?).filter(|&uid| uid != 0);
+            let user = std::str::from_utf8(&user).map_err(|_| "invalid UTF-8 in user")?;
+            info.user = (!user.is_empty()).then(|| TagString::from_ref(user));
+        }
+        Ok(Some(info))
+    }
+
+    fn tags(&mut self) -> std::result::Result<Tags, &'static str> {
+        let mut tags = Tags::new();
+        while !self.data.is_empty() {
+            let (key, value) = self.string(true)?;
+            let string = |bytes| std::str::from_utf8(bytes).map_err(|_| "invalid UTF-8 in tag");
+            tags.insert(
+                TagString::from_ref(string(&key)?),
+                TagString::from_ref(string(&value)?),
+            );
+        }
+        Ok(tags)
+    }
+
+    /// Length prefixed section of references, which shares the state of the dataset
+    ///
+    /// The section is empty for deleted elements, which have no data.
+    fn section(&mut self, deleted: bool) -> std::result::Result<Dataset<'_>, &'static str> {
+        let len = match deleted {
+            true => 0,
+            false => usize::try_from(self.unsigned()?).map_err(|_| "invalid length")?,
+        };
+        if len > self.data.len() {
+            return Err("truncated dataset");
+        }
+        let (section, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(Dataset {
+            data: section,
+            state: self.state,
+        })
+    }
+
+    /// String or pair of strings, either inline or a reference to the table
+    fn string(&mut self, pair: bool) -> std::result::Result<(Vec<u8>, Vec<u8>), &'static str> {
+        let reference = self.unsigned()?;
+        let entry = if reference == 0 {
+            let mut entry = self.inline()?.to_vec();
+            if pair {
+                entry.push(0);
+                entry.extend_from_slice(self.inline()?);
+            }
+            // Pairs count their separator, which the limit does not include
+            if entry.len() - usize::from(pair) <= MAX_TABLE_STRING {
+                let state = &mut *self.state;
+                state.table[state.next] = entry.clone();
+                state.next = (state.next + 1) % TABLE_SIZE;
+            }
+            entry
+        } else {
+            let reference = usize::try_from(reference)
+                .ok()
+                .filter(|&reference| reference <= TABLE_SIZE)
+                .ok_or("invalid string reference")?;
+            let index = (self.state.next + TABLE_SIZE - reference) % TABLE_SIZE;
+            self.state.table[index].clone()
+        };
+        Ok(match pair {
+            true => {
+                let separator = entry
+                    .iter()
+                    .position(|&byte| byte == 0)
+                    .ok_or("invalid string pair")?;
+                (entry[..separator].to_vec(), entry[separator + 1..].to_vec())
+            }
+            false => (entry, vec![]),
+        })
+    }
+
+    /// String up to its terminating zero byte
+    fn inline(&mut self) -> std::result::Result<&[u8], &'static str> {
+        let end = self
+            .data
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or("unterminated string")?;
+        let (string, rest) = self.data.split_at(end);
+        self.data = &rest[1..];
+        Ok(string)
+    }
+
+    fn unsigned(&mut self) -> std::result::Result<u64, &'static str> {
+        varint(&mut self.data)
+    }
+
+    fn signed(&mut self) -> std::result::Result<i64, &'static str> {
+        let value = self.unsigned()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    /// Adds a signed delta to the value of the previous dataset
+    fn delta(&mut self, previous: i64) -> std::result::Result<i64, &'static str> {
+        previous
+            .checked_add(self.signed()?)
+            .ok_or("delta overflows")
+    }
+}
+
+fn varint(data: &mut &[u8]) -> std::result::Result<u64, &'static str> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = data.split_first().ok_or("truncated dataset")?;
+        *data = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("varint is too long")
+}
+
+fn unknown_info() -> Info {
+    Info {
+        version: 0,
+        timestamp: None,
+        changeset: None,
+        uid: None,
+        user: None,
+        visible: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::ErrorPolicy;
+
+    fn unsigned(buf: &mut Vec<u8>, mut value: u64) {
+        while value >= 0x80 {
+            buf.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        buf.push(value as u8);
+    }
+
+    fn signed(buf: &mut Vec<u8>, value: i64) {
+        unsigned(buf, ((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    /// Inline string pair
+    fn pair(buf: &mut Vec<u8>, first: &[u8], second: &[u8]) {
+        buf.push(0);
+        buf.extend_from_slice(first);
+        buf.push(0);
+        buf.extend_from_slice(second);
+        buf.push(0);
+    }
+
+    fn dataset(file: &mut Vec<u8>, kind: u8, data: &[u8]) {
+        file.push(kind);
+        unsigned(file, data.len() as u64);
+        file.extend_from_slice(data);
+    }
+
+    /// Node dataset without metadata
+    fn node(file: &mut Vec<u8>, id: i64, lon: i64, lat: i64, tags: impl Fn(&mut Vec<u8>)) {
+        let mut data = vec![];
+        signed(&mut data, id);
+        unsigned(&mut data, 0);
+        signed(&mut data, lon);
+        signed(&mut data, lat);
+        tags(&mut data);
+        dataset(file, NODE, &data);
+    }
+
+    /// `version`, timestamp, and changeset deltas, followed by the uid and user
+    fn info(data: &mut Vec<u8>, version: u64, timestamp: i64, changeset: i64) {
+        unsigned(data, version);
+        signed(data, timestamp);
+        signed(data, changeset);
+    }
+
+    fn expected_node(id: i64, lat: i64, lon: i64, tags: &[(&str, &str)]) -> Element {
+        expected_node_with(id, lat, lon, tags, None)
+    }
+
+    fn expected_node_with(
+        id: i64,
+        lat: i64,
+        lon: i64,
+        tags: &[(&str, &str)],
+        info: Option<Info>,
+    ) -> Element {
+        Element::Node(Node {
+            id: Id(id),
+            tags: tags
+                .iter()
+                .map(|&(key, value)| (TagString::from_ref(key), TagString::from_ref(value)))
+                .collect(),
+            info,
+            lat: Scalar::with_scale(lat, 7),
+            lon: Scalar::with_scale(lon, 7),
+        })
+    }
+
+    fn read(file: &[u8], options: ParseOptions) -> (Vec<Result<Element>>, Vec<Error>, Header) {
+        let mut reader = Reader::new(file).with_options(options);
+        let elements = reader.by_ref().collect();
+        (elements, reader.take_warnings(), reader.header().clone())
+    }
+
+    fn read_all(file: &[u8]) -> Vec<Element> {
+        let (elements, _, _) = read(file, ParseOptions::default());
+        elements.into_iter().map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn string_references_and_reset() {
+        let mut file = vec![RESET];
+        dataset(&mut file, HEADER, b"o5m2");
+        node(&mut file, 1, 100, 200, |data| {
+            pair(data, b"amenity", b"cafe")
+        });
+        // Refers back to the pair of the previous node
+        node(&mut file, 1, 10, -20, |data| unsigned(data, 1));
+
+        let mut data = vec![];
+        signed(&mut data, 1);
+        info(&mut data, 2, 1_600_000_000, 5);
+        pair(&mut data, &[7], b"anna");
+        signed(&mut data, 0);
+        signed(&mut data, 0);
+        // The user is now the most recent string, the tag the one before
+        unsigned(&mut data, 2);
+        dataset(&mut file, NODE, &data);
+
+        let mut data = vec![];
+        signed(&mut data, 1);
+        info(&mut data, 1, 1, 0);
+        unsigned(&mut data, 1);
+        signed(&mut data, 0);
+        signed(&mut data, 0);
+        dataset(&mut file, NODE, &data);
+
+        file.push(RESET);
+        let mut data = vec![];
+        signed(&mut data, 10);
+        unsigned(&mut data, 0);
+        let mut refs = vec![];
+        for delta in [1, 1, 1] {
+            signed(&mut refs, delta);
+        }
+        unsigned(&mut data, refs.len() as u64);
+        data.extend_from_slice(&refs);
+        pair(&mut data, b"highway", b"path");
+        dataset(&mut file, WAY, &data);
+
+        let mut data = vec![];
+        signed(&mut data, 1);
+        unsigned(&mut data, 0);
+        let mut members = vec![];
+        signed(&mut members, 10);
+        members.extend_from_slice(b"\x001outer\x00");
+        // Node members are coded relative to the last node of the way
+        signed(&mut members, 0);
+        members.extend_from_slice(b"\x000\x00");
+        unsigned(&mut data, members.len() as u64);
+        data.extend_from_slice(&members);
+        dataset(&mut file, RELATION, &data);
+        file.push(END_OF_FILE);
+
+        let info = |version, seconds| Info {
+            version,
+            timestamp: DateTime::from_timestamp(seconds, 0).map(crate::timestamp_from_utc),
+            changeset: Some(5),
+            uid: Some(7),
+            user: Some("anna".into()),
+            visible: None,
+        };
+        let third = expected_node_with(
+            3,
+            180,
+            110,
+            &[("amenity", "cafe")],
+            Some(info(2, 1_600_000_000)),
+        );
+        let fourth = expected_node_with(4, 180, 110, &[], Some(info(1, 1_600_000_001)));
+        let way = Way::builder(Id(10))
+            .nodes([1, 2, 3].map(Id))
+            .tag("highway", "path")
+            .build()
+            .unwrap();
+        let relation = Relation::builder(Id(11))
+            .member(crate::WayId(10), "outer")
+            .member(crate::NodeId(3), "")
+            .build()
+            .unwrap();
+        assert_eq!(
+            read_all(&file),
+            [
+                expected_node(1, 200, 100, &[("amenity", "cafe")]),
+                expected_node(2, 180, 110, &[("amenity", "cafe")]),
+                third,
+                fourth,
+                Element::Way(way),
+                Element::Relation(relation),
+            ]
+        );
+    }
+
+    #[test]
+    fn reset_clears_string_table() {
+        let mut file = vec![];
+        node(&mut file, 1, 0, 0, |data| pair(data, b"amenity", b"cafe"));
+        file.push(RESET);
+        node(&mut file, 2, 0, 0, |data| unsigned(data, 1));
+        let (elements, _, _) = read(&file, ParseOptions::default());
+        assert!(elements[0].is_ok());
+        assert!(
+            matches!(&elements[1], Err(Error::Decode { message, .. }) if message == "invalid string pair")
+        );
+    }
+
+    #[test]
+    fn o5c_deletion() {
+        let mut file = vec![];
+        dataset(&mut file, HEADER, b"o5c2");
+        let mut data = vec![];
+        signed(&mut data, 5);
+        info(&mut data, 3, 1_700_000_000, 9);
+        pair(&mut data, &[7], b"anna");
+        dataset(&mut file, NODE, &data);
+        let (elements, _, header) = read(&file, ParseOptions::default());
+        assert!(header.is_change);
+        let Ok(Element::Node(node)) = &elements[0] else {
+            panic!("expected a node, got {elements:?}");
+        };
+        let info = node.info.as_ref().unwrap();
+        assert_eq!(
+            (node.id, info.version, info.visible),
+            (Id(5), 3, Some(false))
+        );
+        assert_eq!((node.lat, node.lon), (Scalar::ZERO, Scalar::ZERO));
+        assert_eq!(info.changeset, Some(9));
+    }
+
+    #[test]
+    fn delta_overflow_skips_to_reset() {
+        let mut file = vec![];
+        node(&mut file, 1, 0, 0, |_| {});
+        node(&mut file, i64::MAX, 0, 0, |_| {});
+        node(&mut file, 1, 0, 0, |_| {});
+        file.push(RESET);
+        node(&mut file, 7, 0, 0, |_| {});
+
+        let (elements, _, _) = read(&file, ParseOptions::default());
+        assert_eq!(elements.len(), 2);
+        assert!(
+            matches!(&elements[1], Err(Error::Decode { message, .. }) if message == "delta overflows")
+        );
+
+        let options = ParseOptions {
+            on_error: ErrorPolicy::Warn,
+            ..ParseOptions::default()
+        };
+        let (elements, warnings, _) = read(&file, options);
+        let ids: Vec<_> = elements
+            .into_iter()
+            .map(|element| element.unwrap().id())
+            .collect();
+        assert_eq!(ids, [Id(1), Id(7)]);
+        assert_eq!(warnings.len(), 1);
+    }
+}
@@ -0,0 +1,307 @@
+//! Oneway restrictions and direction-dependent tags
+//!
+//! <https://wiki.openstreetmap.org/wiki/Key:oneway>
+//! <https://wiki.openstreetmap.org/wiki/Forward_%26_backward,_left_%26_right>
+
+use crate::{TagString, Tags, Way};
+
+/// Direction of travel relative to the order of a [Way]'s nodes
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+impl Direction {
+    /// Suffix used by keys that only apply in this direction, e.g. `lanes:forward`
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Direction::Forward => "forward",
+            Direction::Backward => "backward",
+        }
+    }
+
+    pub fn reverse(self) -> Self {
+        match self {
+            Direction::Forward => Direction::Backward,
+            Direction::Backward => Direction::Forward,
+        }
+    }
+}
+
+/// Parsed value of the `oneway` key
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Oneway {
+    /// Travel is permitted in both directions
+    No,
+    /// Travel is only permitted in the given direction
+    ///
+    /// `oneway=-1` is represented as [Direction::Backward].
+    Yes(Direction),
+    /// Direction of travel changes over time, e.g. morning and evening rush hours
+    Reversible,
+    /// Direction of travel alternates frequently, e.g. at a single lane bridge with traffic lights
+    Alternating,
+}
+
+impl Oneway {
+    /// Parses a value of the `oneway` key
+    ///
+    /// Returns [None] for unrecognized values.
+    pub fn parse(value: &str) -> Option<Self> {
+        Some(match value {
+            "yes" | "true" | "1" => Oneway::Yes(Direction::Forward),
+            "-1" | "reverse" => Oneway::Yes(Direction::Backward),
+            "no" | "false" | "0" => Oneway::No,
+            "reversible" => Oneway::Reversible,
+            "alternating" => Oneway::Alternating,
+            _ => return None,
+        })
+    }
+
+    /// Whether travel is permitted in `direction`
+    ///
+    /// Reversible and alternating ways permit travel in both directions, just not at the same time.
+    pub fn permits(self, direction: Direction) -> bool {
+        match self {
+            Oneway::Yes(d) => d == direction,
+            Oneway::No | Oneway::Reversible | Oneway::Alternating => true,
+        }
+    }
+
+    /// Same restriction as seen from a way with reversed node order
+    pub fn reverse(self) -> Self {
+        match self {
+            Oneway::Yes(d) => Oneway::Yes(d.reverse()),
+            other => other,
+        }
+    }
+}
+
+/// Resolves the oneway restriction from tags
+///
+/// If `oneway` is not tagged, it is implied by `junction=roundabout`, `junction=circular`,
+/// and `highway=motorway`. Returns [None] if it is tagged with an unrecognized value.
+pub fn oneway(tags: &Tags) -> Option<Oneway> {
+    if let Some(value) = tags.get("oneway") {
+        return Oneway::parse(value);
+    }
+    let implied = matches!(
+        tags.get("junction").map(TagString::as_str),
+        Some("roundabout" | "circular")
+    ) || tags.get("highway").map(TagString::as_str) == Some("motorway");
+    Some(if implied {
+        Oneway::Yes(Direction::Forward)
+    } else {
+        Oneway::No
+    })
+}
+
+/// Values of a key split by direction of travel
+///
+/// e.g. `maxspeed`, `maxspeed:forward`, and `maxspeed:backward`
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Directional<'a> {
+    /// Value of the key without a direction suffix
+    pub both: Option<&'a str>,
+    pub forward: Option<&'a str>,
+    pub backward: Option<&'a str>,
+}
+
+impl<'a> Directional<'a> {
+    /// Collects the values of `key` and its suffixed variants
+    pub fn from_tags(tags: &'a Tags, key: &str) -> Self {
+        let suffixed = |direction: Direction| {
+            tags.get(format!("{key}:{}", direction.suffix()).as_str())
+                .map(TagString::as_str)
+        };
+        Self {
+            both: tags.get(key).map(TagString::as_str),
+            forward: suffixed(Direction::Forward),
+            backward: suffixed(Direction::Backward),
+        }
+    }
+
+    /// Value that applies when travelling in `direction`
+    ///
+    /// A suffixed value takes precedence over the unsuffixed one.
+    pub fn get(&self, direction: Direction) -> Option<&'a str> {
+        match direction {
+            Direction::Forward => self.forward,
+            Direction::Backward => self.backward,
+        }
+        .or(self.both)
+    }
+
+    /// Same values as seen from a way with reversed node order
+    pub fn reverse(self) -> Self {
+        Self {
+            both: self.both,
+            forward: self.backward,
+            backward: self.forward,
+        }
+    }
+}
+
+/// Swaps `forward` and `backward` components of a key, e.g. `lanes:forward` becomes `lanes:backward`
+///
+/// Returns [None] if the key has no direction component.
+pub fn reverse_key(key: &str) -> Option<String> {
+    let mut changed = false;
+    let reversed = key
+        .split(':')
+        .map(|part| match part {
+            "forward" => {
+                changed = true;
+                "backward"
+            }
+            "backward" => {
+                changed = true;
+                "forward"
+            }
+            other => other,
+        })
+        .collect::<Vec<_>>()
+        .join(":");
+    changed.then_some(reversed)
+}
+
+impl Way {
+    /// Resolves the oneway restriction of this way
+    ///
+    /// See [oneway] for details.
+    pub fn oneway(&self) -> Option<Oneway> {
+        oneway(&self.tags)
+    }
+
+    /// Collects the values of `key` and its direction-suffixed variants
+    pub fn directional(&self, key: &str) -> Directional<'_> {
+        Directional::from_tags(&self.tags, key)
+    }
+
+    /// Reverses the order of nodes, swapping `forward`/`backward` key components and `oneway=yes`/`oneway=-1`
+    ///
+    /// Other side-dependent tags (e.g. `:left`/`:right` suffixes) are not changed, and
+    /// implied restrictions (e.g. on roundabouts) are not made explicit.
+    pub fn reverse(&mut self) {
+        self.refs.reverse();
+        let tags = std::mem::take(&mut self.tags);
+        self.tags = tags
+            .into_iter()
+            .map(|(key, value)| {
+                let key = reverse_key(&key).map(TagString::from).unwrap_or(key);
+                (key, value)
+            })
+            .collect();
+        if let Some(value) = self.tags.get_mut("oneway") {
+            match Oneway::parse(value) {
+                Some(Oneway::Yes(Direction::Forward)) => *value = TagString::from_static("-1"),
+                Some(Oneway::Yes(Direction::Backward)) => *value = TagString::from_static("yes"),
+                _ => {}
+            }
+        }
+    }
+
+    /// Reverses a way tagged `oneway=-1` so that it is tagged `oneway=yes` instead
+    ///
+    /// Returns whether the way was reversed.
+    pub fn normalize_oneway(&mut self) -> bool {
+        let reversed = self.oneway() == Some(Oneway::Yes(Direction::Backward));
+        if reversed {
+            self.reverse();
+        }
+        reversed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Id;
+
+    fn tags(pairs: &[(&str, &str)]) -> Tags {
+        pairs
+            .iter()
+            .map(|&(key, value)| (TagString::from_ref(key), TagString::from_ref(value)))
+            .collect()
+    }
+
+    #[test]
+    fn tagged_values() {
+        for (value, expected) in [
+            ("yes", Some(Oneway::Yes(Direction::Forward))),
+            ("1", Some(Oneway::Yes(Direction::Forward))),
+            ("-1", Some(Oneway::Yes(Direction::Backward))),
+            ("reverse", Some(Oneway::Yes(Direction::Backward))),
+            ("no", Some(Oneway::No)),
+            ("reversible", Some(Oneway::Reversible)),
+            ("alternating", Some(Oneway::Alternating)),
+            ("sometimes", None),
+        ] {
+            assert_eq!(oneway(&tags(&[("oneway", value)])), expected, "{value}");
+        }
+    }
+
+    #[test]
+    fn reversible_permits_both_directions() {
+        for value in [Oneway::Reversible, Oneway::Alternating, Oneway::No] {
+            assert!(value.permits(Direction::Forward));
+            assert!(value.permits(Direction::Backward));
+            assert_eq!(value.reverse(), value);
+        }
+        let backward = Oneway::Yes(Direction::Backward);
+        assert!(!backward.permits(Direction::Forward));
+        assert_eq!(backward.reverse(), Oneway::Yes(Direction::Forward));
+    }
+
+    #[test]
+    fn implied_oneway() {
+        let forward = Some(Oneway::Yes(Direction::Forward));
+        assert_eq!(oneway(&tags(&[("junction", "roundabout")])), forward);
+        assert_eq!(oneway(&tags(&[("junction", "circular")])), forward);
+        assert_eq!(oneway(&tags(&[("highway", "motorway")])), forward);
+        assert_eq!(
+            oneway(&tags(&[("highway", "motorway_link")])),
+            Some(Oneway::No)
+        );
+        assert_eq!(
+            oneway(&tags(&[("highway", "residential")])),
+            Some(Oneway::No)
+        );
+        // An explicit value overrides the implied one
+        assert_eq!(
+            oneway(&tags(&[("junction", "roundabout"), ("oneway", "no")])),
+            Some(Oneway::No)
+        );
+    }
+
+    #[test]
+    fn directional_values() {
+        let tags = tags(&[("maxspeed", "50"), ("maxspeed:backward", "30")]);
+        let maxspeed = Directional::from_tags(&tags, "maxspeed");
+        assert_eq!(maxspeed.get(Direction::Forward), Some("50"));
+        assert_eq!(maxspeed.get(Direction::Backward), Some("30"));
+        assert_eq!(maxspeed.reverse().get(Direction::Forward), Some("30"));
+    }
+
+    #[test]
+    fn reversing_swaps_directions() {
+        let mut way = Way::builder(Id(1))
+            .nodes([1, 2, 3].map(Id))
+            .tag("oneway", "-1")
+            .tag("lanes:forward", "2")
+            .build()
+            .unwrap();
+        assert!(way.normalize_oneway());
+        assert_eq!(
+            way.refs.iter().map(|id| id.0).collect::<Vec<_>>(),
+            [3, 2, 1]
+        );
+        assert!(way.tags.has("oneway", "yes"));
+        assert!(way.tags.has("lanes:backward", "2"));
+        assert!(!way.normalize_oneway());
+        assert_eq!(reverse_key("name"), None);
+    }
+}
@@ -0,0 +1,549 @@
+//! Values of the [opening_hours](https://wiki.openstreetmap.org/wiki/Key:opening_hours) key
+//!
+//! [OpeningHours] covers the common subset of the
+//! [specification](https://wiki.openstreetmap.org/wiki/Key:opening_hours/specification): `24/7`,
+//! month and weekday ranges, time spans including those past midnight, the `open`, `off`,
+//! `closed`, and `unknown` states, comments, and normal (`;`), additional (`,`), and fallback
+//! (`||`) rules. Values using other selectors, e.g. week numbers, dates, or `sunset`, fail to
+//! parse. Public and school holidays (`PH`, `SH`) depend on a calendar that is not known here,
+//! so they never match.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Datelike, Days, NaiveDateTime, Timelike, Weekday};
+
+use crate::tags::Tags;
+
+/// Minutes in a day, the end of a span that lasts until midnight
+const DAY: u32 = 24 * 60;
+
+/// Error returned when parsing [OpeningHours] fails
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseOpeningHoursError {
+    /// Position in the value in bytes
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseOpeningHoursError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid opening hours at byte {}: {}",
+            self.offset, self.message
+        )
+    }
+}
+
+impl std::error::Error for ParseOpeningHoursError {}
+
+/// Rules in order, where later rules take precedence
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OpeningHours {
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum State {
+    Open,
+    Closed,
+    /// Tagged as `unknown`, e.g. `Sa unknown "by appointment"`
+    Unknown,
+}
+
+/// How a rule combines with the rules before it
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RuleKind {
+    /// Follows `;` or starts the value, and replaces the earlier rules on the days it selects
+    Normal,
+    /// Follows `,`, and adds to the earlier rules
+    Additional,
+    /// Follows `||`, and only applies on days no earlier rule selects
+    Fallback,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rule {
+    pub kind: RuleKind,
+    /// Inclusive ranges of months from 1 to 12, which may wrap around the new year, e.g.
+    /// `(11, 2)` for `Nov-Feb`; every month if empty
+    pub months: Vec<(u32, u32)>,
+    /// Inclusive ranges of weekdays, which may wrap around the week, e.g. `Fr-Mo`; every day if
+    /// empty and [Rule::holidays] is not set
+    pub weekdays: Vec<(Weekday, Weekday)>,
+    /// Whether `PH` or `SH` is selected
+    pub holidays: bool,
+    /// Spans in minutes since midnight, with ends past [DAY] for spans past midnight, e.g.
+    /// `(1320, 1560)` for `22:00-02:00`; the whole day if empty
+    pub times: Vec<(u32, u32)>,
+    pub state: State,
+    pub comment: Option<String>,
+}
+
+impl Rule {
+    fn selects_day(&self, date: NaiveDateTime) -> bool {
+        let in_range = |value: u32, (start, end): (u32, u32)| {
+            if start <= end {
+                (start..=end).contains(&value)
+            } else {
+                value >= start || value <= end
+            }
+        };
+        let month = date.month();
+        let weekday = date.weekday().num_days_from_monday();
+        (self.months.is_empty() || self.months.iter().any(|&range| in_range(month, range)))
+            && if self.weekdays.is_empty() {
+                !self.holidays
+            } else {
+                self.weekdays.iter().any(|(start, end)| {
+                    in_range(
+                        weekday,
+                        (start.num_days_from_monday(), end.num_days_from_monday()),
+                    )
+                })
+            }
+    }
+
+    /// Whether the spans contain a minute of a day the rule selects, or of the next day
+    fn covers(&self, minute: u32, from_previous_day: bool) -> bool {
+        if self.times.is_empty() {
+            return !from_previous_day;
+        }
+        let minute = if from_previous_day {
+            minute + DAY
+        } else {
+            minute
+        };
+        self.times
+            .iter()
+            .any(|&(start, end)| (start..end).contains(&minute))
+    }
+}
+
+impl OpeningHours {
+    /// State at a local time
+    ///
+    /// A normal rule that selects the day decides the whole day, unless overridden by a later
+    /// one. Spans past midnight continue into the next day.
+    pub fn state_at(&self, time: NaiveDateTime) -> State {
+        let minute = time.hour() * 60 + time.minute();
+        let previous_day = time.checked_sub_days(Days::new(1));
+        let mut state = State::Closed;
+        let mut selected = false;
+        for rule in &self.rules {
+            if rule.kind == RuleKind::Fallback && selected {
+                break;
+            }
+            let today = rule.selects_day(time);
+            let covered = (today && rule.covers(minute, false))
+                || previous_day.is_some_and(|previous| {
+                    rule.selects_day(previous) && rule.covers(minute, true)
+                });
+            if today && rule.kind != RuleKind::Additional {
+                state = State::Closed;
+            }
+            if covered {
+                state = rule.state;
+            }
+            selected |= today || covered;
+        }
+        state
+    }
+
+    pub fn is_open_at(&self, time: NaiveDateTime) -> bool {
+        self.state_at(time) == State::Open
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Token {
+    Word(String),
+    /// Minutes since midnight
+    Time(u32),
+    Comment(String),
+    Always,
+    Dash,
+    Comma,
+    Semicolon,
+    Fallback,
+}
+
+fn tokenize(value: &str) -> Result<Vec<(usize, Token)>, ParseOpeningHoursError> {
+    let error = |offset, message: &str| ParseOpeningHoursError {
+        offset,
+        message: message.to_string(),
+    };
+    let mut tokens = vec![];
+    let mut chars = value.char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        let token = match c {
+            _ if c.is_whitespace() => continue,
+            '-' => Token::Dash,
+            ',' => Token::Comma,
+            ';' => Token::Semicolon,
+            '|' if chars.next_if(|&(_, c)| c == '|').is_some() => Token::Fallback,
+            '"' => {
+                let comment: String = chars
+                    .by_ref()
+                    .map(|(_, c)| c)
+                    .take_while(|&c| c != '"')
+                    .collect();
+                Token::Comment(comment)
+            }
+            _ if c.is_ascii_digit() => {
+                let mut end = offset + 1;
+                while let Some((i, c)) =
+                    chars.next_if(|&(_, c)| c.is_ascii_digit() || c == ':' || c == '/')
+                {
+                    end = i + c.len_utf8();
+                }
+                let text = &value[offset..end];
+                if text == "24/7" {
+                    Token::Always
+                } else {
+                    let (hours, minutes) = text
+                        .split_once(':')
+                        .filter(|(hours, minutes)| hours.len() <= 2 && minutes.len() == 2)
+                        .ok_or_else(|| error(offset, "expected a time such as 08:00"))?;
+                    let (Ok(hours), Ok(minutes)) = (hours.parse::<u32>(), minutes.parse::<u32>())
+                    else {
+                        return Err(error(offset, "expected a time such as 08:00"));
+                    };
+                    if hours > 48 || minutes > 59 {
+                        return Err(error(offset, "time out of range"));
+                    }
+                    Token::Time(hours * 60 + minutes)
+                }
+            }
+            _ if c.is_alphabetic() => {
+                let mut word = String::from(c);
+                while let Some((_, c)) = chars.next_if(|&(_, c)| c.is_alphabetic()) {
+                    word.push(c);
+                }
+                Token::Word(word)
+            }
+            _ => return Err(error(offset, "unexpected character")),
+        };
+        tokens.push((offset, token));
+    }
+    Ok(tokens)
+}
+
+fn month(word: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    MONTHS
+        .iter()
+        .position(|&month| month == word)
+        .map(|i| i as u32 + 1)
+}
+
+fn weekday(word: &str) -> Option<Weekday> {
+    Some(match word {
+        "Mo" => Weekday::Mon,
+        "Tu" => Weekday::Tue,
+        "We" => Weekday::Wed,
+        "Th" => Weekday::Thu,
+        "Fr" => Weekday::Fri,
+        "Sa" => Weekday::Sat,
+        "Su" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    len: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    fn peek_at(&self, ahead: usize) -> Option<&Token> {
+        self.tokens
+            .get(self.position + ahead)
+            .map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .map(|(_, token)| token.clone());
+        self.position += 1;
+        token
+    }
+
+    fn error(&self, message: &str) -> ParseOpeningHoursError {
+        ParseOpeningHoursError {
+            offset: self
+                .tokens
+                .get(self.position)
+                .map_or(self.len, |(offset, _)| *offset),
+            message: message.to_string(),
+        }
+    }
+
+    /// Items of a comma separated list, continuing while the item after a comma is accepted
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<Option<T>, ParseOpeningHoursError>,
+    ) -> Result<Vec<T>, ParseOpeningHoursError> {
+        let mut items = vec![];
+        let Some(first) = item(self)? else {
+            return Ok(items);
+        };
+        items.push(first);
+        while self.peek() == Some(&Token::Comma) {
+            let start = self.position;
+            self.position += 1;
+            match item(self)? {
+                Some(next) => items.push(next),
+                None => {
+                    self.position = start;
+                    break;
+                }
+            }
+        }
+        Ok(items)
+    }
+
+    /// Range such as `Mo-Fr` of words accepted by `parse`
+    fn range<T: Copy>(
+        &mut self,
+        parse: fn(&str) -> Option<T>,
+    ) -> Result<Option<(T, T)>, ParseOpeningHoursError> {
+        let Some(Token::Word(word)) = self.peek() else {
+            return Ok(None);
+        };
+        let Some(start) = parse(word) else {
+            return Ok(None);
+        };
+        self.position += 1;
+        if self.peek() != Some(&Token::Dash) {
+            return Ok(Some((start, start)));
+        }
+        self.position += 1;
+        let Some(end) = self.peek().and_then(|token| match token {
+            Token::Word(word) => parse(word),
+            _ => None,
+        }) else {
+            return Err(self.error("invalid end of range"));
+        };
+        self.position += 1;
+        Ok(Some((start, end)))
+    }
+
+    fn rule(&mut self, kind: RuleKind) -> Result<Rule, ParseOpeningHoursError> {
+        let mut rule = Rule {
+            kind,
+            months: vec![],
+            weekdays: vec![],
+            holidays: false,
+            times: vec![],
+            state: State::Open,
+            comment: None,
+        };
+        let start = self.position;
+        if self.peek() == Some(&Token::Always) {
+            self.position += 1;
+        } else {
+            rule.months = self.list(|parser| parser.range(month))?;
+            let mut holidays = false;
+            rule.weekdays = self
+                .list(|parser| {
+                    if let Some(Token::Word(word)) = parser.peek() {
+                        if word == "PH" || word == "SH" {
+                            parser.position += 1;
+                            holidays = true;
+                            // Keeps the list going without selecting a weekday
+                            return Ok(Some(None));
+                        }
+                    }
+                    parser.range(weekday).map(|range| range.map(Some))
+                })?
+                .into_iter()
+                .flatten()
+                .collect();
+            rule.holidays = holidays;
+            rule.times = self.list(|parser| {
+                let Some(&Token::Time(start)) = parser.peek() else {
+                    return Ok(None);
+                };
+                if parser.peek_at(1) != Some(&Token::Dash) {
+                    return Err(parser.error("expected a time span such as 08:00-12:00"));
+                }
+                parser.position += 2;
+                let Some(&Token::Time(end)) = parser.peek() else {
+                    return Err(parser.error("expected the end of the time span"));
+                };
+                parser.position += 1;
+                // Spans ending at or before their start continue past midnight
+                let end = if end <= start { end + DAY } else { end };
+                Ok(Some((start, end)))
+            })?;
+        }
+        if let Some(Token::Word(word)) = self.peek() {
+            rule.state = match word.as_str() {
+                "open" => State::Open,
+                "off" | "closed" => State::Closed,
+                "unknown" => State::Unknown,
+                _ => return Err(self.error("unsupported selector")),
+            };
+            self.position += 1;
+        }
+        if let Some(Token::Comment(comment)) = self.peek() {
+            // A rule of only a comment, e.g. `"by appointment"`, says nothing definite
+            if self.position == start {
+                rule.state = State::Unknown;
+            }
+            rule.comment = Some(comment.clone());
+            self.position += 1;
+        }
+        Ok(rule)
+    }
+}
+
+impl FromStr for OpeningHours {
+    type Err = ParseOpeningHoursError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            position: 0,
+            len: s.len(),
+        };
+        let mut rules = vec![parser.rule(RuleKind::Normal)?];
+        while let Some(token) = parser.next() {
+            let kind = match token {
+                Token::Semicolon => RuleKind::Normal,
+                Token::Comma => RuleKind::Additional,
+                Token::Fallback => RuleKind::Fallback,
+                _ => {
+                    parser.position -= 1;
+                    return Err(parser.error("expected a rule separator"));
+                }
+            };
+            // Trailing separators are common and harmless
+            if parser.peek().is_none() {
+                break;
+            }
+            rules.push(parser.rule(kind)?);
+        }
+        Ok(Self { rules })
+    }
+}
+
+impl Tags {
+    /// Value of `opening_hours`, [None] if absent or not in the supported subset
+    pub fn opening_hours(&self) -> Option<OpeningHours> {
+        self.parse("opening_hours")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Local time on a day of the first week of 2024, which starts on a Monday
+    fn at(day: Weekday, time: &str) -> NaiveDateTime {
+        let date = format!("2024-01-0{} {time}", day.num_days_from_monday() + 1);
+        NaiveDateTime::parse_from_str(&date, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn state(value: &str, day: Weekday, time: &str) -> State {
+        value
+            .parse::<OpeningHours>()
+            .unwrap()
+            .state_at(at(day, time))
+    }
+
+    #[test]
+    fn weekdays_and_days_off() {
+        let value = "Mo-Fr 08:00-12:00; Sa off";
+        assert_eq!(state(value, Weekday::Mon, "08:00"), State::Open);
+        assert_eq!(state(value, Weekday::Fri, "11:59"), State::Open);
+        assert_eq!(state(value, Weekday::Fri, "12:00"), State::Closed);
+        assert_eq!(state(value, Weekday::Tue, "07:59"), State::Closed);
+        assert_eq!(state(value, Weekday::Sat, "10:00"), State::Closed);
+        assert_eq!(state(value, Weekday::Sun, "10:00"), State::Closed);
+    }
+
+    #[test]
+    fn spans_past_midnight() {
+        let value = "Fr-Sa 22:00-02:00";
+        assert_eq!(state(value, Weekday::Fri, "23:00"), State::Open);
+        assert_eq!(state(value, Weekday::Sat, "01:00"), State::Open);
+        assert_eq!(state(value, Weekday::Sun, "01:59"), State::Open);
+        assert_eq!(state(value, Weekday::Sun, "02:00"), State::Closed);
+        assert_eq!(state(value, Weekday::Fri, "01:00"), State::Closed);
+        assert_eq!(state(value, Weekday::Thu, "23:00"), State::Closed);
+        assert_eq!(state("Mo 18:00-26:00", Weekday::Tue, "01:00"), State::Open);
+    }
+
+    #[test]
+    fn later_rules_override() {
+        let value = "Mo-Su 08:00-18:00; We off";
+        assert_eq!(state(value, Weekday::Wed, "10:00"), State::Closed);
+        assert_eq!(state(value, Weekday::Thu, "10:00"), State::Open);
+        // A normal rule replaces the whole day, not just its spans
+        let value = "Mo-Fr 08:00-18:00; Fr 10:00-12:00";
+        assert_eq!(state(value, Weekday::Fri, "09:00"), State::Closed);
+        assert_eq!(state(value, Weekday::Fri, "11:00"), State::Open);
+        // An additional rule keeps the earlier spans
+        let value = "Mo 08:00-10:00, Mo 14:00-16:00";
+        assert_eq!(state(value, Weekday::Mon, "09:00"), State::Open);
+        assert_eq!(state(value, Weekday::Mon, "15:00"), State::Open);
+        assert_eq!(state(value, Weekday::Mon, "12:00"), State::Closed);
+        assert_eq!(state("24/7; Jan off", Weekday::Mon, "12:00"), State::Closed);
+    }
+
+    #[test]
+    fn fallback_rules() {
+        let value = "Mo-Fr 09:00-17:00 || Sa-Su 10:00-12:00";
+        assert_eq!(state(value, Weekday::Mon, "10:00"), State::Open);
+        assert_eq!(state(value, Weekday::Sat, "11:00"), State::Open);
+        // The fallback does not apply on days the first rule selects
+        let value = "Mo-Fr 09:00-17:00 || unknown \"by appointment\"";
+        assert_eq!(state(value, Weekday::Mon, "18:00"), State::Closed);
+        assert_eq!(state(value, Weekday::Sun, "10:00"), State::Unknown);
+        let hours: OpeningHours = value.parse().unwrap();
+        assert_eq!(hours.rules[1].kind, RuleKind::Fallback);
+        assert_eq!(hours.rules[1].comment.as_deref(), Some("by appointment"));
+    }
+
+    #[test]
+    fn holidays_never_match() {
+        let value = "Mo-Sa 09:00-18:00; PH off";
+        assert_eq!(state(value, Weekday::Mon, "10:00"), State::Open);
+        assert_eq!(
+            state("PH 10:00-12:00", Weekday::Mon, "11:00"),
+            State::Closed
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let error = |value: &str| value.parse::<OpeningHours>().unwrap_err();
+        assert_eq!(error("Mo-Fr sunrise-sunset").offset, 6);
+        assert_eq!(error("Mo-Fr 08:00-12:00 Sa").offset, 18);
+        assert_eq!(error("Mo-Fr 49:00-50:00").message, "time out of range");
+        assert_eq!(error("Mo-Fr 8-12").message, "expected a time such as 08:00");
+        assert_eq!(error("Mo- 08:00-12:00").offset, 4);
+        assert_eq!(error("Mo 08:00").offset, 3);
+        assert_eq!(error("Mo 08:00-").offset, 9);
+        assert_eq!(error("Mo 08:00-Tu").offset, 9);
+        assert_eq!(error("Mo-Xy 08:00-12:00").offset, 3);
+        assert_eq!(error("Mo @ 08:00-12:00").offset, 3);
+        assert!("Mo-Fr 08:00-12:00;".parse::<OpeningHours>().is_ok());
+    }
+}
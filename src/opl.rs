@@ -0,0 +1,459 @@
+//! [OPL](https://osmcode.org/opl-file-format/), the line based format of osmium
+//!
+//! Each element is one line of space separated fields, e.g.
+//! `n123 v1 dV c456 t2024-01-31T12:00:00Z i789 uname Tamenity=cafe x13.4 y52.5`, which makes
+//! files easy to grep and diff. [parse] and [to_string] convert single lines, while [Reader]
+//! and [Writer] handle files. [parse_borrowed] reads a line without copying its strings.
+
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+
+use crate::borrowed::{ElementRef, MemberRef, NodeRef, RelationRef, WayRef};
+use crate::error::{Error, Result};
+use crate::parse::{ParseOptions, Recovery};
+use crate::pipeline::Sink;
+use crate::progress::{NoProgress, Observer};
+use crate::scalar::{Scalar, ScalarExt};
+use crate::{Element, Id, Info, MemberType, Refs, TagString};
+
+/// Parses a line into an element
+///
+/// [Info] is filled if any of its fields is present. Changesets and uids of 0 and empty
+/// timestamps and users are read as unknown, as osmium writes them. Deleted nodes without
+/// coordinates are placed at `0, 0`.
+pub fn parse(line: &str) -> Result<Element> {
+    parse_borrowed(line).map(ElementRef::into_owned)
+}
+
+/// Parses a line into an element that borrows its tags and roles from the line, unless they
+/// are escaped
+pub fn parse_borrowed(line: &str) -> Result<ElementRef<'_>> {
+    parse_at(line, None)
+}
+
+fn parse_at(line: &str, offset: Option<u64>) -> Result<ElementRef<'_>> {
+    let error = |message: String| Error::decode(offset, message);
+    let mut fields = line.split(' ').filter(|field| !field.is_empty());
+    let first = fields
+        .next()
+        .ok_or_else(|| error("empty line".to_string()))?;
+    let (ty, id) = first.split_at(first.chars().next().map_or(0, char::len_utf8));
+    let ty = match ty {
+        "n" => MemberType::Node,
+        "w" => MemberType::Way,
+        "r" => MemberType::Relation,
+        _ => return Err(error(format!("unsupported object type {ty:?}"))),
+    };
+    let id = Id(id.parse().map_err(|_| error("invalid id".to_string()))?);
+    let error = |message: String| Error::decode(offset, message).with_element(ty.clone(), id);
+
+    let mut info = None::<Info>;
+    let mut tags = vec![];
+    let (mut lat, mut lon) = (None, None);
+    let mut refs = Refs::new();
+    let mut members = vec![];
+    for field in fields {
+        let (key, value) = field.split_at(field.chars().next().map_or(0, char::len_utf8));
+        let invalid = || error(format!("invalid {key} field"));
+        match key {
+            "v" => {
+                info.get_or_insert_with(unknown_info).version =
+                    value.parse().map_err(|_| invalid())?
+            }
+            "d" => {
+                info.get_or_insert_with(unknown_info).visible = match value {
+                    "V" => None,
+                    "D" => Some(false),
+                    _ => return Err(invalid()),
+                }
+            }
+            "c" => {
+                info.get_or_insert_with(unknown_info).changeset =
+                    Some(value.parse().map_err(|_| invalid())?).filter(|&c| c != 0)
+            }
+            "t" => {
+                info.get_or_insert_with(unknown_info).timestamp = match value {
+                    "" => None,
+                    value => Some(crate::parse_timestamp(value).ok_or_else(invalid)?),
+                }
+            }
+            "i" => {
+                info.get_or_insert_with(unknown_info).uid =
+                    Some(value.parse().map_err(|_| invalid())?).filter(|&uid| uid != 0)
+            }
+            "u" => {
+                let user = unescape(value).ok_or_else(invalid)?;
+                info.get_or_insert_with(unknown_info).user =
+                    (!user.is_empty()).then(|| TagString::from_ref(&user));
+            }
+            "T" => {
+                for tag in value.split(',').filter(|tag| !tag.is_empty()) {
+                    let (key, value) = tag.split_once('=').ok_or_else(invalid)?;
+                    tags.push((
+                        unescape(key).ok_or_else(invalid)?,
+                        unescape(value).ok_or_else(invalid)?,
+                    ));
+                }
+            }
+            "x" => lon = coordinate(value).ok_or_else(invalid)?,
+            "y" => lat = coordinate(value).ok_or_else(invalid)?,
+            "N" => {
+                for node in value.split(',').filter(|node| !node.is_empty()) {
+                    let id = node.strip_prefix('n').ok_or_else(invalid)?;
+                    refs.push(Id(id.parse().map_err(|_| invalid())?));
+                }
+            }
+            "M" => {
+                for member in value.split(',').filter(|member| !member.is_empty()) {
+                    let (reference, role) = member.split_once('@').ok_or_else(invalid)?;
+                    let (ty, id) = reference.split_at(reference.len().min(1));
+                    let ty = match ty {
+                        "n" => MemberType::Node,
+                        "w" => MemberType::Way,
+                        "r" => MemberType::Relation,
+                        _ => return Err(invalid()),
+                    };
+                    let role = unescape(role).ok_or_else(invalid)?;
+                    members.push(MemberRef {
+                        id: Id(id.parse().map_err(|_| invalid())?),
+                        ty,
+                        role: (!role.is_empty()).then_some(role),
+                    });
+                }
+            }
+            _ => return Err(error(format!("unknown field {key:?}"))),
+        }
+    }
+
+    Ok(match ty {
+        MemberType::Node => {
+            let deleted = info.as_ref().and_then(|info| info.visible) == Some(false);
+            let (lat, lon) = match (lat, lon) {
+                (Some(lat), Some(lon)) => (lat, lon),
+                _ if deleted => (Scalar::ZERO, Scalar::ZERO),
+                _ => return Err(error("missing coordinates".to_string())),
+            };
+            ElementRef::Node(NodeRef {
+                id,
+                tags,
+                info,
+                lat,
+                lon,
+            })
+        }
+        MemberType::Way => ElementRef::Way(WayRef {
+            id,
+            tags,
+            info,
+            refs,
+        }),
+        MemberType::Relation => ElementRef::Relation(RelationRef {
+            id,
+            tags,
+            info,
+            members,
+        }),
+    })
+}
+
+/// Info with a version of 0 before its fields are read
+fn unknown_info() -> Info {
+    Info {
+        version: 0,
+        timestamp: None,
+        changeset: None,
+        uid: None,
+        user: None,
+        visible: None,
+    }
+}
+
+/// Coordinate of an `x` or `y` field, which is empty for deleted nodes
+fn coordinate(value: &str) -> Option<Option<Scalar>> {
+    match value {
+        "" => Some(None),
+        value => Scalar::parse_decimal(value).map(Some),
+    }
+}
+
+/// Formats an element as a line, without the line break
+///
+/// Tags are sorted, and [Info] fields are only written if the element has [Info]. Deleted
+/// nodes are written without coordinates.
+pub fn to_string(element: &Element) -> String {
+    let mut buf = String::new();
+    write_element(&mut buf, element);
+    buf
+}
+
+fn write_element(buf: &mut String, element: &Element) {
+    let ty = match element {
+        Element::Node(_) => 'n',
+        Element::Way(_) => 'w',
+        Element::Relation(_) => 'r',
+    };
+    let _ = write!(buf, "{ty}{}", element.id().0);
+    let info = element.info();
+    if let Some(info) = info {
+        let deleted = if info.visible == Some(false) {
+            'D'
+        } else {
+            'V'
+        };
+        let _ = write!(
+            buf,
+            " v{} d{deleted} c{} t",
+            info.version,
+            info.changeset.unwrap_or_default()
+        );
+        if let Some(timestamp) = info.timestamp {
+            let _ = write!(buf, "{}", crate::format_timestamp(timestamp));
+        }
+        let _ = write!(buf, " i{} u", info.uid.unwrap_or_default());
+        escape(buf, info.user.as_deref().unwrap_or_default());
+    }
+
+    buf.push_str(" T");
+    for (i, (key, value)) in element.tags().iter_sorted().enumerate() {
+        if i > 0 {
+            buf.push(',');
+        }
+        escape(buf, key);
+        buf.push('=');
+        escape(buf, value);
+    }
+
+    match element {
+        Element::Node(node) => {
+            if info.and_then(|info| info.visible) == Some(false) {
+                buf.push_str(" x y");
+            } else {
+                let _ = write!(
+                    buf,
+                    " x{} y{}",
+                    node.lon.normalized(),
+                    node.lat.normalized()
+                );
+            }
+        }
+        Element::Way(way) => {
+            buf.push_str(" N");
+            for (i, id) in way.refs.iter().enumerate() {
+                let separator = if i > 0 { "," } else { "" };
+                let _ = write!(buf, "{separator}n{}", id.0);
+            }
+        }
+        Element::Relation(relation) => {
+            buf.push_str(" M");
+            for (i, member) in relation.members.iter().enumerate() {
+                let ty = match member.ty {
+                    MemberType::Node => 'n',
+                    MemberType::Way => 'w',
+                    MemberType::Relation => 'r',
+                };
+                let separator = if i > 0 { "," } else { "" };
+                let _ = write!(buf, "{separator}{ty}{}@", member.id.0);
+                escape(buf, member.role.as_deref().unwrap_or_default());
+            }
+        }
+    }
+}
+
+/// Escapes characters as `%hex%` the way osmium does, which includes all characters outside
+/// of Latin, Greek, Cyrillic, Armenian, and Hebrew
+fn escape(buf: &mut String, value: &str) {
+    for c in value.chars() {
+        match u32::from(c) {
+            0x21..=0x24
+            | 0x26..=0x2b
+            | 0x2d..=0x3c
+            | 0x3e..=0x3f
+            | 0x41..=0x7e
+            | 0xa1..=0xac
+            | 0xae..=0x5ff => buf.push(c),
+            code => {
+                let _ = write!(buf, "%{code:x}%");
+            }
+        }
+    }
+}
+
+fn unescape(value: &str) -> Option<Cow<'_, str>> {
+    if !value.contains('%') {
+        return Some(Cow::Borrowed(value));
+    }
+    let mut unescaped = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some((text, escaped)) = rest.split_once('%') {
+        unescaped.push_str(text);
+        let (code, remaining) = escaped.split_once('%')?;
+        unescaped.push(char::from_u32(u32::from_str_radix(code, 16).ok()?)?);
+        rest = remaining;
+    }
+    unescaped.push_str(rest);
+    Some(Cow::Owned(unescaped))
+}
+
+/// Streaming reader of [Element]s in OPL, one per line
+///
+/// Empty lines and comments starting with `#` are skipped, as are changesets. Malformed lines
+/// end reading unless [ParseOptions] say otherwise.
+#[derive(Debug)]
+pub struct Reader<R, O = NoProgress> {
+    input: R,
+    observer: O,
+    line: Vec<u8>,
+    offset: u64,
+    /// Lines read so far
+    lines: u64,
+    recovery: Recovery,
+    done: bool,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            input,
+            observer: NoProgress,
+            line: vec![],
+            offset: 0,
+            lines: 0,
+            recovery: Recovery::default(),
+            done: false,
+        }
+    }
+}
+
+impl<R, O> Reader<R, O> {
+    /// Reports bytes and elements read to `observer`
+    pub fn with_observer<P: Observer>(self, observer: P) -> Reader<R, P> {
+        Reader {
+            input: self.input,
+            observer,
+            line: self.line,
+            offset: self.offset,
+            lines: self.lines,
+            recovery: self.recovery,
+            done: self.done,
+        }
+    }
+
+    /// Sets how malformed lines are handled
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.recovery.options = options;
+        self
+    }
+
+    /// Errors of the malformed lines skipped so far with [ErrorPolicy::Warn]
+    ///
+    /// [ErrorPolicy::Warn]: crate::parse::ErrorPolicy::Warn
+    pub fn warnings(&self) -> &[Error] {
+        &self.recovery.warnings
+    }
+
+    /// Takes the [Reader::warnings], e.g. to report them while reading a large file
+    pub fn take_warnings(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.recovery.warnings)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.input
+    }
+}
+
+impl<R: BufRead, O: Observer> Reader<R, O> {
+    fn read_element(&mut self) -> Result<Option<Element>> {
+        loop {
+            self.line.clear();
+            let read = self.input.read_until(b'\n', &mut self.line)?;
+            if read == 0 {
+                return Ok(None);
+            }
+            let offset = self.offset;
+            self.offset += read as u64;
+            self.lines += 1;
+            self.observer.bytes_read(read as u64);
+            let element = std::str::from_utf8(&self.line)
+                .map_err(|_| Error::decode(Some(offset), "invalid UTF-8"))
+                .and_then(|line| {
+                    let line = line.trim_end_matches(['\n', '\r']);
+                    if line.is_empty() || line.starts_with(['#', 'c']) {
+                        return Ok(None);
+                    }
+                    let element = parse_at(line, Some(offset))?.into_owned();
+                    self.recovery.check(&element)?;
+                    Ok(Some(element))
+                });
+            match element {
+                Ok(Some(element)) => return Ok(Some(element)),
+                Ok(None) => {}
+                Err(err) => self.recovery.skip(err.at_line(self.lines))?,
+            }
+        }
+    }
+}
+
+impl<R: BufRead, O: Observer> Iterator for Reader<R, O> {
+    type Item = Result<Element>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let element = self.read_element();
+        match &element {
+            Ok(Some(element)) => self.observer.element_read(element),
+            Ok(None) | Err(_) => self.done = true,
+        }
+        element.transpose()
+    }
+}
+
+/// Writer of [Element]s in OPL
+///
+/// Each element is written with a single call to the output, which should still be buffered
+/// if it is a file.
+#[derive(Debug)]
+pub struct Writer<W> {
+    output: W,
+    buf: String,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(output: W) -> Self {
+        Self {
+            output,
+            buf: String::new(),
+        }
+    }
+
+    pub fn write(&mut self, element: &Element) -> Result<()> {
+        self.buf.clear();
+        write_element(&mut self.buf, element);
+        self.buf.push('\n');
+        self.output.write_all(self.buf.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> Result<()> {
+        self.output.flush()?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+}
+
+impl<W: Write> Sink for Writer<W> {
+    type Error = Error;
+
+    fn write(&mut self, element: Element) -> Result<()> {
+        Writer::write(self, &element)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        Writer::finish(self)
+    }
+}
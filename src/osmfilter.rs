@@ -0,0 +1,626 @@
+//! Element filters in the syntax of [osmfilter](https://wiki.openstreetmap.org/wiki/Osmfilter)
+//!
+//! A [Filter] is compiled from `--keep=` and `--drop=` arguments, e.g.
+//! `--keep="highway=primary =secondary and name=" --drop="access=private"`.
+//!
+//! Supported are:
+//! - Conditions `key=value`, `key!=value`, and numeric comparisons `<`, `<=`, `>`, `>=`
+//! - `key=` for any value and `key!=` for a missing key
+//! - Alternative values for the previous key, e.g. `amenity=bar =pub`
+//! - Wildcards at the start or end of keys and values, e.g. `name:*=` or `name=*Street`
+//! - Metadata conditions `@id`, `@version`, `@changeset`, `@uid`, and `@user`
+//! - `and`, `or`, and parentheses, where `and` binds tighter and adjacent conditions are
+//!   combined with `or`
+//! - Backslash escapes for spaces and special characters, e.g. `name=Main\ Street`
+
+use std::fmt;
+
+use crate::{Element, TagString};
+
+/// Error returned when compiling a [Filter] fails
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ParseFilterError {
+    /// Expression ended where a condition was expected
+    UnexpectedEnd,
+    /// Token cannot be used at this position, e.g. an `or` without a left-hand side
+    Unexpected(String),
+    /// A parenthesis is not matched
+    UnbalancedParenthesis,
+    /// Argument is not a `--keep` or `--drop` option
+    UnknownOption(String),
+}
+
+impl fmt::Display for ParseFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseFilterError::UnexpectedEnd => write!(f, "unexpected end of filter"),
+            ParseFilterError::Unexpected(token) => write!(f, "unexpected {token:?} in filter"),
+            ParseFilterError::UnbalancedParenthesis => {
+                write!(f, "unbalanced parenthesis in filter")
+            }
+            ParseFilterError::UnknownOption(option) => {
+                write!(f, "unknown filter option {option:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseFilterError {}
+
+impl From<ParseFilterError> for crate::Error {
+    fn from(err: ParseFilterError) -> Self {
+        crate::Error::Parse(Box::new(err))
+    }
+}
+
+/// Character with whether it was preceded by a backslash
+type Escaped = (char, bool);
+
+/// Text with an optional `*` wildcard at either end
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+struct Pattern {
+    text: TagString,
+    any_prefix: bool,
+    any_suffix: bool,
+}
+
+impl Pattern {
+    fn new(chars: &[Escaped]) -> Self {
+        let any_prefix = matches!(chars.first(), Some(('*', false)));
+        let rest = if any_prefix { &chars[1..] } else { chars };
+        let any_suffix = matches!(rest.last(), Some(('*', false)));
+        let rest = if any_suffix {
+            &rest[..rest.len() - 1]
+        } else {
+            rest
+        };
+        Self {
+            text: rest.iter().map(|(c, _)| c).collect::<String>().into(),
+            any_prefix,
+            any_suffix,
+        }
+    }
+
+    fn matches(&self, s: &str) -> bool {
+        match (self.any_prefix, self.any_suffix) {
+            (false, false) => s == self.text.as_str(),
+            (true, false) => s.ends_with(self.text.as_str()),
+            (false, true) => s.starts_with(self.text.as_str()),
+            (true, true) => s.contains(self.text.as_str()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+enum Meta {
+    Id,
+    Version,
+    Changeset,
+    Uid,
+    User,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+enum Key {
+    Tag(Pattern),
+    Meta(Meta),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+struct Condition {
+    key: Key,
+    op: Op,
+    /// Empty for any value with [Op::Eq] or a missing key with [Op::Ne]
+    values: Vec<Pattern>,
+}
+
+impl Condition {
+    fn matches(&self, element: &Element) -> bool {
+        match &self.key {
+            Key::Tag(key) => {
+                let mut values = element
+                    .tags()
+                    .iter()
+                    .filter(|(k, _)| key.matches(k))
+                    .map(|(_, v)| v.as_str())
+                    .peekable();
+                match self.op {
+                    Op::Ne if self.values.is_empty() => values.peek().is_none(),
+                    Op::Ne => values.all(|value| !self.value_matches(value)),
+                    _ => values.any(|value| self.value_matches(value)),
+                }
+            }
+            Key::Meta(meta) => {
+                let info = element.info();
+                let value = match meta {
+                    Meta::Id => Some(element.id().0.to_string()),
+                    Meta::Version => info.map(|info| info.version.to_string()),
+                    Meta::Changeset => info
+                        .and_then(|info| info.changeset)
+                        .map(|changeset| changeset.to_string()),
+                    Meta::Uid => info.and_then(|info| info.uid).map(|uid| uid.to_string()),
+                    Meta::User => info
+                        .and_then(|info| info.user.as_ref())
+                        .map(|user| user.to_string()),
+                };
+                match (value, self.op) {
+                    (Some(value), Op::Ne) => !self.value_matches(&value),
+                    (Some(value), _) => self.value_matches(&value),
+                    (None, op) => op == Op::Ne,
+                }
+            }
+        }
+    }
+
+    fn value_matches(&self, value: &str) -> bool {
+        if self.values.is_empty() {
+            return true;
+        }
+        let compare = |ordering: fn(f64, f64) -> bool| {
+            let Ok(value) = value.trim().parse::<f64>() else {
+                return false;
+            };
+            self.values
+                .iter()
+                .filter_map(|pattern| pattern.text.trim().parse::<f64>().ok())
+                .any(|limit| ordering(value, limit))
+        };
+        match self.op {
+            Op::Eq | Op::Ne => self.values.iter().any(|pattern| pattern.matches(value)),
+            Op::Lt => compare(|a, b| a < b),
+            Op::Le => compare(|a, b| a <= b),
+            Op::Gt => compare(|a, b| a > b),
+            Op::Ge => compare(|a, b| a >= b),
+        }
+    }
+}
+
+/// Compiled boolean expression of conditions
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Expr(Node);
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+enum Node {
+    /// Empty expression, which matches nothing
+    Nothing,
+    Condition(Condition),
+    And(Vec<Node>),
+    Or(Vec<Node>),
+}
+
+impl Node {
+    fn matches(&self, element: &Element) -> bool {
+        match self {
+            Node::Nothing => false,
+            Node::Condition(condition) => condition.matches(element),
+            Node::And(nodes) => nodes.iter().all(|node| node.matches(element)),
+            Node::Or(nodes) => nodes.iter().any(|node| node.matches(element)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Token {
+    And,
+    Or,
+    Open,
+    Close,
+    /// Condition or alternative value, with escaped characters marked
+    Word(Vec<Escaped>),
+}
+
+fn tokenize(s: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut word = vec![];
+    let mut chars = s.chars();
+    let finish = |word: &mut Vec<Escaped>, tokens: &mut Vec<Token>| {
+        if word.is_empty() {
+            return;
+        }
+        let text: String = word.iter().map(|(c, _)| c).collect();
+        tokens.push(match (text.as_str(), word.iter().any(|(_, e)| *e)) {
+            ("and", false) => Token::And,
+            ("or", false) => Token::Or,
+            _ => Token::Word(std::mem::take(word)),
+        });
+        word.clear();
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => word.extend(chars.next().map(|c| (c, true))),
+            '(' | ')' => {
+                finish(&mut word, &mut tokens);
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            c if c.is_whitespace() => finish(&mut word, &mut tokens),
+            c => word.push((c, false)),
+        }
+    }
+    finish(&mut word, &mut tokens);
+    tokens
+}
+
+/// Splits a word into key, operator, and value at the first unescaped operator
+fn split_condition(word: &[Escaped]) -> Option<(&[Escaped], Op, &[Escaped])> {
+    let i = word
+        .iter()
+        .position(|(c, escaped)| !escaped && matches!(c, '=' | '!' | '<' | '>'))?;
+    let next = word
+        .get(i + 1)
+        .filter(|(_, escaped)| !escaped)
+        .map(|(c, _)| *c);
+    let (op, len) = match (word[i].0, next) {
+        ('=', _) => (Op::Eq, 1),
+        ('!', Some('=')) => (Op::Ne, 2),
+        ('<', Some('=')) => (Op::Le, 2),
+        ('<', _) => (Op::Lt, 1),
+        ('>', Some('=')) => (Op::Ge, 2),
+        ('>', _) => (Op::Gt, 1),
+        _ => return None,
+    };
+    Some((&word[..i], op, &word[i + len..]))
+}
+
+struct Parser {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    /// Alternatives separated by `or` or nothing at all
+    fn or(&mut self) -> Result<Node, ParseFilterError> {
+        let mut alternatives = vec![self.and()?];
+        loop {
+            match self.tokens.peek() {
+                Some(Token::Or) => {
+                    self.tokens.next();
+                    alternatives.push(self.and()?);
+                }
+                Some(Token::Word(_) | Token::Open) => alternatives.push(self.and()?),
+                _ => break,
+            }
+        }
+        Ok(if alternatives.len() == 1 {
+            alternatives.pop().unwrap()
+        } else {
+            Node::Or(alternatives)
+        })
+    }
+
+    fn and(&mut self) -> Result<Node, ParseFilterError> {
+        let mut all = vec![self.primary()?];
+        while self.tokens.peek() == Some(&Token::And) {
+            self.tokens.next();
+            all.push(self.primary()?);
+        }
+        Ok(if all.len() == 1 {
+            all.pop().unwrap()
+        } else {
+            Node::And(all)
+        })
+    }
+
+    fn primary(&mut self) -> Result<Node, ParseFilterError> {
+        match self.tokens.next() {
+            Some(Token::Open) => {
+                let inner = self.or()?;
+                match self.tokens.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => Err(ParseFilterError::UnbalancedParenthesis),
+                }
+            }
+            Some(Token::Word(word)) => self.condition(&word),
+            Some(Token::Close) => Err(ParseFilterError::UnbalancedParenthesis),
+            Some(token) => Err(ParseFilterError::Unexpected(describe(&token))),
+            None => Err(ParseFilterError::UnexpectedEnd),
+        }
+    }
+
+    fn condition(&mut self, word: &[Escaped]) -> Result<Node, ParseFilterError> {
+        let unexpected = || ParseFilterError::Unexpected(word.iter().map(|(c, _)| c).collect());
+        let (key, op, value) = split_condition(word).ok_or_else(unexpected)?;
+        if key.is_empty() {
+            return Err(unexpected());
+        }
+        let key_text: String = key.iter().map(|(c, _)| c).collect();
+        let key = match key_text.as_str() {
+            "@id" => Key::Meta(Meta::Id),
+            "@version" => Key::Meta(Meta::Version),
+            "@changeset" => Key::Meta(Meta::Changeset),
+            "@uid" => Key::Meta(Meta::Uid),
+            "@user" => Key::Meta(Meta::User),
+            _ => Key::Tag(Pattern::new(key)),
+        };
+        let mut values = vec![];
+        if !value.is_empty() {
+            values.push(Pattern::new(value));
+        }
+        // Alternative values for the same key, e.g. `=secondary` after `highway=primary`
+        while let Some(Token::Word(next)) = self.tokens.peek() {
+            match split_condition(next) {
+                Some((k, next_op, value)) if k.is_empty() && next_op == op && !value.is_empty() => {
+                    values.push(Pattern::new(value));
+                    self.tokens.next();
+                }
+                _ => break,
+            }
+        }
+        Ok(Node::Condition(Condition { key, op, values }))
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::And => "and".to_string(),
+        Token::Or => "or".to_string(),
+        Token::Open => "(".to_string(),
+        Token::Close => ")".to_string(),
+        Token::Word(word) => word.iter().map(|(c, _)| c).collect(),
+    }
+}
+
+impl Expr {
+    /// Compiles an expression, where an empty expression matches nothing
+    pub fn parse(s: &str) -> Result<Self, ParseFilterError> {
+        let tokens = tokenize(s);
+        if tokens.is_empty() {
+            return Ok(Expr(Node::Nothing));
+        }
+        let mut parser = Parser {
+            tokens: tokens.into_iter().peekable(),
+        };
+        let node = parser.or()?;
+        match parser.tokens.next() {
+            None => Ok(Expr(node)),
+            Some(Token::Close) => Err(ParseFilterError::UnbalancedParenthesis),
+            Some(token) => Err(ParseFilterError::Unexpected(describe(&token))),
+        }
+    }
+
+    pub fn matches(&self, element: &Element) -> bool {
+        self.0.matches(element)
+    }
+}
+
+impl std::str::FromStr for Expr {
+    type Err = ParseFilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+fn type_index(element: &Element) -> usize {
+    match element {
+        Element::Node(_) => 1,
+        Element::Way(_) => 2,
+        Element::Relation(_) => 3,
+    }
+}
+
+/// Compiled set of keep and drop rules
+///
+/// An element is kept if it matches any keep rule that applies to its type, or if there is
+/// none, and it matches no drop rule. Rules apply to all types or to nodes, ways, or relations
+/// only, like `--keep` and `--keep-ways`. As in osmfilter, `--keep=` with an empty expression
+/// drops elements that no other keep rule retains.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Filter {
+    /// Indexed by all types, nodes, ways, and relations
+    keep: [Vec<Expr>; 4],
+    drop: [Vec<Expr>; 4],
+}
+
+/// Element types a rule applies to
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Scope {
+    All,
+    Nodes,
+    Ways,
+    Relations,
+}
+
+impl Filter {
+    /// Filter that keeps every element
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn keep(mut self, scope: Scope, expr: Expr) -> Self {
+        self.keep[scope as usize].push(expr);
+        self
+    }
+
+    pub fn drop(mut self, scope: Scope, expr: Expr) -> Self {
+        self.drop[scope as usize].push(expr);
+        self
+    }
+
+    /// Compiles osmfilter options such as `--keep=...` and `--drop-relations=...`
+    ///
+    /// Values may be given in the same argument after `=` or as the next argument. Quotes
+    /// around values are removed, since in osmfilter invocations they are usually meant for
+    /// the shell.
+    pub fn from_args<I, S>(args: I) -> Result<Self, ParseFilterError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut filter = Self::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let arg = arg.as_ref();
+            let (option, value) = match arg.split_once('=') {
+                Some((option, value)) => (option, value.to_string()),
+                None => match args.next() {
+                    Some(value) => (arg, value.as_ref().to_string()),
+                    None => return Err(ParseFilterError::UnknownOption(arg.to_string())),
+                },
+            };
+            let value = ['"', '\'']
+                .iter()
+                .find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote))
+                .unwrap_or(&value);
+            let (keep, scope) = match option {
+                "--keep" => (true, Scope::All),
+                "--keep-nodes" => (true, Scope::Nodes),
+                "--keep-ways" => (true, Scope::Ways),
+                "--keep-relations" => (true, Scope::Relations),
+                "--drop" => (false, Scope::All),
+                "--drop-nodes" => (false, Scope::Nodes),
+                "--drop-ways" => (false, Scope::Ways),
+                "--drop-relations" => (false, Scope::Relations),
+                _ => return Err(ParseFilterError::UnknownOption(option.to_string())),
+            };
+            let expr = Expr::parse(value)?;
+            filter = if keep {
+                filter.keep(scope, expr)
+            } else {
+                filter.drop(scope, expr)
+            };
+        }
+        Ok(filter)
+    }
+
+    pub fn matches(&self, element: &Element) -> bool {
+        let ty = type_index(element);
+        let applicable = |rules: &[Vec<Expr>; 4]| {
+            let [all, ..] = rules;
+            all.iter()
+                .chain(&rules[ty])
+                .any(|expr| expr.matches(element))
+        };
+        let has_keep = !self.keep[Scope::All as usize].is_empty() || !self.keep[ty].is_empty();
+        (!has_keep || applicable(&self.keep)) && !applicable(&self.drop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Id, Info, Node, Way};
+
+    fn node(tags: &[(&str, &str)]) -> Element {
+        let mut builder =
+            Node::builder(Id(1)).info(Info::builder().version(3).user(7, "mapper").build());
+        for &(key, value) in tags {
+            builder = builder.tag(TagString::from_ref(key), TagString::from_ref(value));
+        }
+        Element::Node(builder.build().unwrap())
+    }
+
+    fn matches(expr: &str, tags: &[(&str, &str)]) -> bool {
+        Expr::parse(expr).unwrap().matches(&node(tags))
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let expr = "amenity=pub or shop=bakery and name=";
+        assert!(matches(expr, &[("amenity", "pub")]));
+        assert!(matches(expr, &[("shop", "bakery"), ("name", "Crumbs")]));
+        assert!(!matches(expr, &[("shop", "bakery")]));
+        // Adjacent conditions are alternatives, like with `or`
+        let expr = "amenity=pub shop=bakery and name=";
+        assert!(matches(expr, &[("amenity", "pub")]));
+        assert!(!matches(expr, &[("shop", "bakery")]));
+    }
+
+    #[test]
+    fn parentheses_group() {
+        let expr = "(amenity=pub or shop=bakery) and name=";
+        assert!(!matches(expr, &[("amenity", "pub")]));
+        assert!(matches(expr, &[("amenity", "pub"), ("name", "The Crown")]));
+        assert!(matches("((highway=primary))", &[("highway", "primary")]));
+    }
+
+    #[test]
+    fn conditions() {
+        assert!(matches(
+            "highway=primary =secondary",
+            &[("highway", "secondary")]
+        ));
+        assert!(!matches(
+            "highway=primary =secondary",
+            &[("highway", "tertiary")]
+        ));
+        assert!(matches("name:*=", &[("name:de", "Ort")]));
+        assert!(matches("name=*Street", &[("name", "Main Street")]));
+        assert!(matches("name=Main\\ Street", &[("name", "Main Street")]));
+        assert!(matches("access!=private", &[]));
+        assert!(!matches("access!=private", &[("access", "private")]));
+        assert!(matches("access!=", &[]));
+        assert!(!matches("access!=", &[("access", "yes")]));
+        assert!(matches("lanes>=2", &[("lanes", "2")]));
+        assert!(!matches("lanes>2", &[("lanes", "2")]));
+        assert!(!matches("lanes<2", &[("lanes", "many")]));
+        assert!(matches("@version>2 and @user=mapper", &[]));
+        assert!(!matches("@changeset=1", &[]));
+        assert!(matches("@changeset!=1", &[]));
+        assert!(!matches("", &[("highway", "primary")]));
+    }
+
+    #[test]
+    fn errors() {
+        let parse = |s: &str| Expr::parse(s).err();
+        assert_eq!(
+            parse("and"),
+            Some(ParseFilterError::Unexpected("and".to_string()))
+        );
+        assert_eq!(
+            parse("highway=primary or"),
+            Some(ParseFilterError::UnexpectedEnd)
+        );
+        assert_eq!(
+            parse("(highway=primary"),
+            Some(ParseFilterError::UnbalancedParenthesis)
+        );
+        assert_eq!(
+            parse("highway=primary)"),
+            Some(ParseFilterError::UnbalancedParenthesis)
+        );
+        assert_eq!(
+            parse("highway"),
+            Some(ParseFilterError::Unexpected("highway".to_string()))
+        );
+        assert_eq!(
+            parse("=primary"),
+            Some(ParseFilterError::Unexpected("=primary".to_string()))
+        );
+        assert_eq!(
+            Filter::from_args(["--keep-areas=building="]).err(),
+            Some(ParseFilterError::UnknownOption("--keep-areas".to_string()))
+        );
+        assert_eq!(
+            Filter::from_args(["--keep"]).err(),
+            Some(ParseFilterError::UnknownOption("--keep".to_string()))
+        );
+    }
+
+    #[test]
+    fn keep_and_drop() {
+        let filter =
+            Filter::from_args(["--keep-ways=\"highway=\"", "--drop", "access=private"]).unwrap();
+        let way = |tags: &[(&str, &str)]| {
+            let mut builder = Way::builder(Id(2)).nodes([1, 2].map(Id));
+            for &(key, value) in tags {
+                builder = builder.tag(TagString::from_ref(key), TagString::from_ref(value));
+            }
+            Element::Way(builder.build().unwrap())
+        };
+        assert!(filter.matches(&way(&[("highway", "path")])));
+        assert!(!filter.matches(&way(&[("building", "yes")])));
+        assert!(!filter.matches(&way(&[("highway", "path"), ("access", "private")])));
+        // The keep rule only applies to ways
+        assert!(filter.matches(&node(&[("amenity", "bench")])));
+        assert!(!filter.matches(&node(&[("access", "private")])));
+    }
+}
@@ -0,0 +1,250 @@
+//! Conversions to and from the objects of the [osmio](https://docs.rs/osmio) readers and writers
+//!
+//! The `*_from_osmio` functions take any of osmio's object types through its traits, e.g. the
+//! [ArcOSMObj](::osmio::obj_types::ArcOSMObj) of its PBF reader. The [StringOSMObj] types also
+//! convert with [From] and [TryFrom], and are what elements of this crate convert into for
+//! osmio's writers.
+//!
+//! osmio keeps coordinates in 100 nanodegrees like OSM, and versions, changesets, and user ids
+//! as unsigned 32-bit numbers, so values that do not fit are left out when writing.
+
+use ::osmio::obj_types::{
+    StringNode, StringNodeBuilder, StringOSMObj, StringRelation, StringRelationBuilder, StringWay,
+    StringWayBuilder,
+};
+use ::osmio::{Lat, Lon, OSMObj, OSMObjBase, OSMObjectType, TimestampFormat};
+use chrono::DateTime;
+
+use crate::coordinate::CoordinateE7;
+use crate::error::{ElementContext, Error, Result};
+use crate::{Element, Id, Info, Member, MemberType, Node, Relation, TagString, Tags, Way};
+
+fn tags_from_osmio(object: &impl OSMObjBase) -> Tags {
+    object
+        .tags()
+        .map(|(key, value)| (TagString::from_ref(key), TagString::from_ref(value)))
+        .collect()
+}
+
+/// [Info] of the object, [None] if it has no metadata at all
+fn info_from_osmio(object: &impl OSMObjBase) -> Option<Info> {
+    let timestamp = object
+        .timestamp()
+        .as_ref()
+        .and_then(|timestamp| DateTime::from_timestamp(timestamp.to_epoch_number(), 0))
+        .map(crate::timestamp_from_utc);
+    let info = Info {
+        version: object
+            .version()
+            .and_then(|version| i32::try_from(version).ok())
+            .unwrap_or_default(),
+        timestamp,
+        changeset: object.changeset_id().map(i64::from),
+        uid: object.uid().and_then(|uid| i32::try_from(uid).ok()),
+        user: object.user().map(TagString::from_ref),
+        visible: object.deleted().then_some(false),
+    };
+    let empty = info.version == 0
+        && info.timestamp.is_none()
+        && info.changeset.is_none()
+        && info.uid.is_none()
+        && info.user.is_none()
+        && info.visible.is_none();
+    (!empty).then_some(info)
+}
+
+impl From<&OSMObjectType> for MemberType {
+    fn from(ty: &OSMObjectType) -> Self {
+        match ty {
+            OSMObjectType::Node => MemberType::Node,
+            OSMObjectType::Way => MemberType::Way,
+            OSMObjectType::Relation => MemberType::Relation,
+        }
+    }
+}
+
+impl From<&MemberType> for OSMObjectType {
+    fn from(ty: &MemberType) -> Self {
+        match ty {
+            MemberType::Node => OSMObjectType::Node,
+            MemberType::Way => OSMObjectType::Way,
+            MemberType::Relation => OSMObjectType::Relation,
+        }
+    }
+}
+
+/// Node of any of osmio's node types
+///
+/// Fails for nodes without a location, such as deleted nodes of a change file.
+pub fn node_from_osmio(node: &impl ::osmio::Node) -> Result<Node> {
+    let Some((lat, lon)) = node.lat_lon() else {
+        return Err(Error::Validation {
+            element: Some(ElementContext {
+                ty: MemberType::Node,
+                id: Id(node.id()),
+            }),
+            message: "node has no location".to_string(),
+        });
+    };
+    let (lat, lon) = CoordinateE7::new(lat.inner(), lon.inner()).lat_lon();
+    Ok(Node {
+        id: Id(node.id()),
+        tags: tags_from_osmio(node),
+        info: info_from_osmio(node),
+        lat,
+        lon,
+    })
+}
+
+/// Way of any of osmio's way types
+pub fn way_from_osmio(way: &impl ::osmio::Way) -> Way {
+    Way {
+        id: Id(way.id()),
+        tags: tags_from_osmio(way),
+        info: info_from_osmio(way),
+        refs: way.nodes().iter().copied().map(Id).collect(),
+    }
+}
+
+/// Relation of any of osmio's relation types
+pub fn relation_from_osmio(relation: &impl ::osmio::Relation) -> Relation {
+    Relation {
+        id: Id(relation.id()),
+        tags: tags_from_osmio(relation),
+        info: info_from_osmio(relation),
+        members: relation
+            .members()
+            .map(|(ty, id, role)| Member {
+                id: Id(id),
+                ty: (&ty).into(),
+                role: Some(role)
+                    .filter(|role| !role.is_empty())
+                    .map(TagString::from_ref),
+            })
+            .collect(),
+    }
+}
+
+/// Element of any of osmio's object types, see [node_from_osmio]
+pub fn element_from_osmio(object: &impl OSMObj) -> Result<Element> {
+    if let Some(node) = object.as_node() {
+        node_from_osmio(node).map(Element::Node)
+    } else if let Some(way) = object.as_way() {
+        Ok(Element::Way(way_from_osmio(way)))
+    } else if let Some(relation) = object.as_relation() {
+        Ok(Element::Relation(relation_from_osmio(relation)))
+    } else {
+        unreachable!("osmio objects are nodes, ways, or relations")
+    }
+}
+
+impl TryFrom<&StringNode> for Node {
+    type Error = Error;
+
+    fn try_from(node: &StringNode) -> Result<Self> {
+        node_from_osmio(node)
+    }
+}
+
+impl From<&StringWay> for Way {
+    fn from(way: &StringWay) -> Self {
+        way_from_osmio(way)
+    }
+}
+
+impl From<&StringRelation> for Relation {
+    fn from(relation: &StringRelation) -> Self {
+        relation_from_osmio(relation)
+    }
+}
+
+impl TryFrom<&StringOSMObj> for Element {
+    type Error = Error;
+
+    fn try_from(object: &StringOSMObj) -> Result<Self> {
+        element_from_osmio(object)
+    }
+}
+
+fn set_base(object: &mut impl OSMObjBase, tags: &Tags, info: Option<&Info>) {
+    for (key, value) in tags.iter_sorted() {
+        object.set_tag(key, value.as_str());
+    }
+    let Some(info) = info else {
+        return;
+    };
+    object.set_version(
+        u32::try_from(info.version)
+            .ok()
+            .filter(|&version| version > 0),
+    );
+    object.set_deleted(info.visible == Some(false));
+    object.set_changeset_id(
+        info.changeset
+            .and_then(|changeset| changeset.try_into().ok()),
+    );
+    object.set_timestamp(info.timestamp.map(|timestamp| {
+        TimestampFormat::EpochNumber(crate::timestamp_to_utc(timestamp).timestamp())
+    }));
+    object.set_uid(info.uid.and_then(|uid| uid.try_into().ok()));
+    object.set_user(info.user.as_deref());
+}
+
+impl From<&Node> for StringNode {
+    fn from(node: &Node) -> Self {
+        let mut object = StringNodeBuilder::default()
+            ._id(node.id.0)
+            .build()
+            .expect("only the id is required");
+        set_base(&mut object, &node.tags, node.info.as_ref());
+        let location = node
+            .coordinate()
+            .and_then(|coordinate| coordinate.to_e7())
+            .map(|coordinate| {
+                (
+                    Lat::from_inner(coordinate.lat),
+                    Lon::from_inner(coordinate.lon),
+                )
+            });
+        ::osmio::Node::set_lat_lon_direct(&mut object, location);
+        object
+    }
+}
+
+impl From<&Way> for StringWay {
+    fn from(way: &Way) -> Self {
+        let mut object = StringWayBuilder::default()
+            ._id(way.id.0)
+            .build()
+            .expect("only the id is required");
+        set_base(&mut object, &way.tags, way.info.as_ref());
+        ::osmio::Way::set_nodes(&mut object, way.refs.iter().map(|id| id.0));
+        object
+    }
+}
+
+impl From<&Relation> for StringRelation {
+    fn from(relation: &Relation) -> Self {
+        let mut object = StringRelationBuilder::default()
+            ._id(relation.id.0)
+            .build()
+            .expect("only the id is required");
+        set_base(&mut object, &relation.tags, relation.info.as_ref());
+        let members = relation.members.iter().map(|member| {
+            let role = member.role.as_deref().unwrap_or("");
+            (OSMObjectType::from(&member.ty), member.id.0, role)
+        });
+        ::osmio::Relation::set_members(&mut object, members);
+        object
+    }
+}
+
+impl From<&Element> for StringOSMObj {
+    fn from(element: &Element) -> Self {
+        match element {
+            Element::Node(node) => StringOSMObj::Node(node.into()),
+            Element::Way(way) => StringOSMObj::Way(way.into()),
+            Element::Relation(relation) => StringOSMObj::Relation(relation.into()),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! Conversions from the elements of the [osmpbf](https://docs.rs/osmpbf) reader
+//!
+//! Elements borrow from the blocks of a file in osmpbf, so these conversions copy them into
+//! the owned types of this crate, e.g. inside `ElementReader::for_each`. Coordinates are
+//! taken from their nanodegrees and are exact. Strings that are not valid UTF-8 are skipped
+//! as osmpbf does for tags.
+//!
+//! osmpbf reports elements without a `visible` flag as visible, so [Info::visible] is only
+//! set for deleted elements of history files.
+
+use ::osmpbf::{DenseNode, DenseNodeInfo, RelMemberType};
+
+use chrono::DateTime;
+
+use crate::coordinate::Coordinate;
+use crate::{Element, Id, Info, Member, MemberType, Node, Relation, TagString, Tags, Way};
+
+fn tags<'a>(tags: impl Iterator<Item = (&'a str, &'a str)>) -> Tags {
+    tags.map(|(key, value)| (TagString::from_ref(key), TagString::from_ref(value)))
+        .collect()
+}
+
+fn info(info: &::osmpbf::Info) -> Info {
+    Info {
+        version: info.version().unwrap_or_default().max(0),
+        timestamp: info
+            .milli_timestamp()
+            .and_then(DateTime::from_timestamp_millis)
+            .map(crate::timestamp_from_utc),
+        changeset: info.changeset().filter(|&changeset| changeset != 0),
+        uid: info.uid().filter(|&uid| uid != 0),
+        user: info
+            .user()
+            .and_then(Result::ok)
+            .filter(|user| !user.is_empty())
+            .map(TagString::from_ref),
+        visible: info.deleted().then_some(false),
+    }
+}
+
+fn dense_info(info: &DenseNodeInfo) -> Info {
+    Info {
+        version: info.version().max(0),
+        timestamp: DateTime::from_timestamp_millis(info.milli_timestamp())
+            .map(crate::timestamp_from_utc),
+        changeset: Some(info.changeset()).filter(|&changeset| changeset != 0),
+        uid: Some(info.uid()).filter(|&uid| uid != 0),
+        user: info
+            .user()
+            .ok()
+            .filter(|user| !user.is_empty())
+            .map(TagString::from_ref),
+        visible: info.deleted().then_some(false),
+    }
+}
+
+impl From<&::osmpbf::Node<'_>> for Node {
+    fn from(node: &::osmpbf::Node<'_>) -> Self {
+        let (lat, lon) = Coordinate::new(node.nano_lat(), node.nano_lon()).lat_lon();
+        Node {
+            id: Id(node.id()),
+            tags: tags(node.tags()),
+            info: Some(info(&node.info())),
+            lat,
+            lon,
+        }
+    }
+}
+
+impl From<&DenseNode<'_>> for Node {
+    fn from(node: &DenseNode<'_>) -> Self {
+        let (lat, lon) = Coordinate::new(node.nano_lat(), node.nano_lon()).lat_lon();
+        Node {
+            id: Id(node.id()),
+            tags: tags(node.tags()),
+            info: node.info().map(dense_info),
+            lat,
+            lon,
+        }
+    }
+}
+
+impl From<&::osmpbf::Way<'_>> for Way {
+    fn from(way: &::osmpbf::Way<'_>) -> Self {
+        Way {
+            id: Id(way.id()),
+            tags: tags(way.tags()),
+            info: Some(info(&way.info())),
+            refs: way.refs().map(Id).collect(),
+        }
+    }
+}
+
+impl From<RelMemberType> for MemberType {
+    fn from(ty: RelMemberType) -> Self {
+        match ty {
+            RelMemberType::Node => MemberType::Node,
+            RelMemberType::Way => MemberType::Way,
+            RelMemberType::Relation => MemberType::Relation,
+        }
+    }
+}
+
+impl From<&::osmpbf::Relation<'_>> for Relation {
+    fn from(relation: &::osmpbf::Relation<'_>) -> Self {
+        Relation {
+            id: Id(relation.id()),
+            tags: tags(relation.tags()),
+            info: Some(info(&relation.info())),
+            members: relation
+                .members()
+                .map(|member| Member {
+                    id: Id(member.member_id),
+                    role: member
+                        .role()
+                        .ok()
+                        .filter(|role| !role.is_empty())
+                        .map(TagString::from_ref),
+                    ty: member.member_type.into(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<&::osmpbf::Element<'_>> for Element {
+    fn from(element: &::osmpbf::Element<'_>) -> Self {
+        match element {
+            ::osmpbf::Element::Node(node) => Element::Node(node.into()),
+            ::osmpbf::Element::DenseNode(node) => Element::Node(node.into()),
+            ::osmpbf::Element::Way(way) => Element::Way(way.into()),
+            ::osmpbf::Element::Relation(relation) => Element::Relation(relation.into()),
+        }
+    }
+}
+
+impl From<::osmpbf::Element<'_>> for Element {
+    fn from(element: ::osmpbf::Element<'_>) -> Self {
+        Element::from(&element)
+    }
+}
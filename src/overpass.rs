@@ -0,0 +1,333 @@
+//! [Overpass API](https://wiki.openstreetmap.org/wiki/Overpass_API) queries and JSON responses
+//!
+//! [QueryBuilder] writes [Overpass QL](https://wiki.openstreetmap.org/wiki/Overpass_API/Overpass_QL)
+//! queries with `[out:json]`. With the `serde` feature, their [Response] holds the elements of
+//! [OSM JSON](crate::json), which convert losslessly to and from the core types. Geometry
+//! added by `out geom` or `out center` is ignored.
+
+use std::fmt::{self, Write as _};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::bbox::Bbox;
+#[cfg(feature = "serde")]
+pub use crate::json::{Element, Meta, Node, Relation, RelationMember, Way};
+use crate::scalar::ScalarExt;
+#[cfg(feature = "serde")]
+use crate::Timestamp;
+use crate::{Id, MemberType};
+
+/// Body of a response
+#[cfg(feature = "serde")]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Response {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub osm3s: Option<Osm3s>,
+    pub elements: Vec<Element>,
+    /// Message about a runtime error, e.g. a timeout, after which `elements` is incomplete
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remark: Option<String>,
+}
+
+/// About the database that answered
+#[cfg(feature = "serde")]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Osm3s {
+    /// Time of the last diff applied to the database
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "crate::json::timestamp"
+    )]
+    pub timestamp_osm_base: Option<Timestamp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub copyright: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+impl Response {
+    /// Nodes, ways, and relations of the response, skipping other types
+    pub fn into_elements(self) -> impl Iterator<Item = crate::Element> {
+        self.elements
+            .into_iter()
+            .filter_map(|element| element.try_into().ok())
+    }
+}
+
+/// Query of the elements matching any of its statements
+///
+/// The query is written as a union of the statements, followed by its [Recurse] and [Verbosity].
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub struct QueryBuilder {
+    timeout: Option<u32>,
+    bbox: Option<Bbox>,
+    statements: Vec<Statement>,
+    recurse: Option<Recurse>,
+    verbosity: Verbosity,
+}
+
+impl Default for QueryBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QueryBuilder {
+    /// Query of nothing, with the default timeout of the server and [Verbosity::Body]
+    pub fn new() -> Self {
+        Self {
+            timeout: None,
+            bbox: None,
+            statements: vec![],
+            recurse: None,
+            verbosity: Verbosity::Body,
+        }
+    }
+
+    /// Maximum run time in seconds, after which the server stops the query
+    pub fn timeout(mut self, seconds: u32) -> Self {
+        self.timeout = Some(seconds);
+        self
+    }
+
+    /// Limits every statement to the box, which must not cross the antimeridian
+    pub fn bbox(mut self, bbox: Bbox) -> Self {
+        self.bbox = Some(bbox);
+        self
+    }
+
+    pub fn statement(mut self, statement: Statement) -> Self {
+        self.statements.push(statement);
+        self
+    }
+
+    /// Adds elements related to the matches, e.g. the nodes of ways
+    pub fn recurse(mut self, recurse: Recurse) -> Self {
+        self.recurse = Some(recurse);
+        self
+    }
+
+    pub fn verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Query in Overpass QL, to send as the `data` of a request to `/api/interpreter`
+    pub fn build(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for QueryBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("[out:json]")?;
+        if let Some(timeout) = self.timeout {
+            write!(f, "[timeout:{timeout}]")?;
+        }
+        if let Some(bbox) = &self.bbox {
+            write!(f, "[bbox:{}]", bbox_filter(bbox))?;
+        }
+        f.write_str(";\n(\n")?;
+        for statement in &self.statements {
+            writeln!(f, "  {statement};")?;
+        }
+        f.write_str(");\n")?;
+        if let Some(recurse) = self.recurse {
+            writeln!(f, "(._;{};);", recurse.as_str())?;
+        }
+        writeln!(f, "out {};", self.verbosity.as_str())
+    }
+}
+
+/// Elements of a type matching all filters
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Statement {
+    ty: Option<MemberType>,
+    filters: String,
+}
+
+impl Statement {
+    pub fn nodes() -> Self {
+        Self::of(Some(MemberType::Node))
+    }
+
+    pub fn ways() -> Self {
+        Self::of(Some(MemberType::Way))
+    }
+
+    pub fn relations() -> Self {
+        Self::of(Some(MemberType::Relation))
+    }
+
+    /// Nodes, ways, and relations
+    pub fn elements() -> Self {
+        Self::of(None)
+    }
+
+    fn of(ty: Option<MemberType>) -> Self {
+        Self {
+            ty,
+            filters: String::new(),
+        }
+    }
+
+    /// Elements with the key
+    pub fn key(self, key: &str) -> Self {
+        self.filter(format_args!("[{}]", quote(key)))
+    }
+
+    /// Elements without the key
+    pub fn no_key(self, key: &str) -> Self {
+        self.filter(format_args!("[!{}]", quote(key)))
+    }
+
+    pub fn tag(self, key: &str, value: &str) -> Self {
+        self.filter(format_args!("[{}={}]", quote(key), quote(value)))
+    }
+
+    /// Elements without the key or with another value
+    pub fn not_tag(self, key: &str, value: &str) -> Self {
+        self.filter(format_args!("[{}!={}]", quote(key), quote(value)))
+    }
+
+    /// Elements with a value of the key matching a regular expression
+    pub fn tag_regex(self, key: &str, regex: &str) -> Self {
+        self.filter(format_args!("[{}~{}]", quote(key), quote(regex)))
+    }
+
+    /// Elements with one of the values
+    pub fn tag_any<'a>(self, key: &str, values: impl IntoIterator<Item = &'a str>) -> Self {
+        let regex = values
+            .into_iter()
+            .map(escape_regex)
+            .collect::<Vec<_>>()
+            .join("|");
+        self.tag_regex(key, &format!("^({regex})$"))
+    }
+
+    /// Elements in the box, which must not cross the antimeridian
+    pub fn bbox(self, bbox: &Bbox) -> Self {
+        self.filter(format_args!("({})", bbox_filter(bbox)))
+    }
+
+    /// Element with the id
+    pub fn id(self, id: impl Into<Id>) -> Self {
+        self.filter(format_args!("({})", id.into().0))
+    }
+
+    /// Elements within `meters` of the point
+    pub fn around(self, meters: f64, lat: f64, lon: f64) -> Self {
+        self.filter(format_args!("(around:{meters},{lat},{lon})"))
+    }
+
+    fn filter(mut self, filter: fmt::Arguments) -> Self {
+        let _ = self.filters.write_fmt(filter);
+        self
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ty = self.ty.as_ref().map_or("nwr", MemberType::as_str);
+        write!(f, "{ty}{}", self.filters)
+    }
+}
+
+/// Elements added to the matches by a [recursion](https://wiki.openstreetmap.org/wiki/Overpass_API/Overpass_QL#Recurse_.28n.2C_w.2C_r.2C_bn.2C_bw.2C_br.29)
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Recurse {
+    /// Nodes of ways and members of relations, `>`
+    Down,
+    /// [Recurse::Down] repeated through relations that are members, `>>`
+    DownAll,
+    /// Ways containing nodes and relations containing members, `<`
+    Up,
+    /// [Recurse::Up] repeated through relations of relations, `<<`
+    UpAll,
+}
+
+impl Recurse {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Recurse::Down => ">",
+            Recurse::DownAll => ">>",
+            Recurse::Up => "<",
+            Recurse::UpAll => "<<",
+        }
+    }
+}
+
+/// Detail of the output elements
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Verbosity {
+    /// Only ids
+    Ids,
+    /// Ids, coordinates, way nodes, and members, without tags
+    Skel,
+    /// [Verbosity::Skel] with tags
+    Body,
+    /// Only ids and tags
+    Tags,
+    /// [Verbosity::Body] with [Info](crate::Info)
+    Meta,
+}
+
+impl Verbosity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Verbosity::Ids => "ids",
+            Verbosity::Skel => "skel",
+            Verbosity::Body => "body",
+            Verbosity::Tags => "tags",
+            Verbosity::Meta => "meta",
+        }
+    }
+}
+
+/// Box as `south,west,north,east`
+fn bbox_filter(bbox: &Bbox) -> String {
+    format!(
+        "{},{},{},{}",
+        bbox.min_lat.normalized(),
+        bbox.min_lon.normalized(),
+        bbox.max_lat.normalized(),
+        bbox.max_lon.normalized()
+    )
+}
+
+/// String literal of QL
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Value as a regular expression that matches it literally
+fn escape_regex(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,204 @@
+//! Handling of malformed input by the format readers
+//!
+//! Real-world files contain junk: a node with an unparsable coordinate, a tag without a
+//! value, a corrupt block. By default the readers stop at the first such element with an
+//! [Error::Decode] naming where it is, by byte offset and for text formats by line, and which
+//! element it is if known. With [ErrorPolicy::Warn] or [ErrorPolicy::Skip], they skip the
+//! malformed element and go on with the next one instead. [ParseOptions::strict] additionally
+//! rejects elements that decode fine but break the rules of [crate::validate], so that they
+//! fail or are skipped the same way.
+//!
+//! Errors that leave a reader unable to find the next element, e.g. i/o errors or the end of
+//! the input in the middle of an element, stop it regardless of the policy.
+
+use crate::error::{Error, Result};
+use crate::Element;
+
+/// What readers do with a malformed element
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ErrorPolicy {
+    /// Stops reading at the element, returning its error
+    #[default]
+    Fail,
+    /// Skips the element, keeping its error as a warning of the reader
+    Warn,
+    /// Skips the element without keeping its error
+    Skip,
+}
+
+/// How readers treat malformed input, see [crate::parse]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct ParseOptions {
+    /// Whether elements that break the rules of [crate::validate] are malformed
+    ///
+    /// Deleted elements, which keep no more than their id in many files, are not checked.
+    pub strict: bool,
+    pub on_error: ErrorPolicy,
+}
+
+impl ParseOptions {
+    /// Options that reject invalid elements and stop at the first malformed one
+    pub fn strict() -> Self {
+        Self {
+            strict: true,
+            on_error: ErrorPolicy::Fail,
+        }
+    }
+
+    /// Options that skip malformed elements, keeping warnings about them
+    pub fn lenient() -> Self {
+        Self {
+            strict: false,
+            on_error: ErrorPolicy::Warn,
+        }
+    }
+}
+
+/// [ParseOptions] of a reader along with the warnings it kept
+#[derive(Debug, Default)]
+pub(crate) struct Recovery {
+    pub options: ParseOptions,
+    pub warnings: Vec<Error>,
+}
+
+impl Recovery {
+    /// Checks an element read, unless it is deleted or the options are not strict
+    pub fn check(&self, element: &Element) -> Result<()> {
+        if !self.options.strict || element.info().and_then(|info| info.visible) == Some(false) {
+            return Ok(());
+        }
+        let errors = match element {
+            Element::Node(node) => node.validate(),
+            Element::Way(way) => way.validate(),
+            Element::Relation(relation) => relation.validate(),
+        };
+        match errors.into_iter().next() {
+            Some(err) => Err(Error::from(err).with_element(element.member_type(), element.id())),
+            None => Ok(()),
+        }
+    }
+
+    /// Handles the error of a malformed element, returning it if reading has to stop
+    pub fn skip(&mut self, err: Error) -> Result<()> {
+        match self.options.on_error {
+            ErrorPolicy::Fail => return Err(err),
+            ErrorPolicy::Warn => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(%err, "skipped malformed element");
+                self.warnings.push(err);
+            }
+            ErrorPolicy::Skip => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ElementContext;
+    use crate::{opl, xml, Id, MemberType};
+
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6">
+ <node id="1" lat="1" lon="2"/>
+ <node id="2" lat="north" lon="2"/>
+ <node id="3" lat="91" lon="2"/>
+ <node id="4" version="2" visible="false" lat="91" lon="2"/>
+ <way id="5">
+  <nd ref="1"/>
+  <nd ref="3"/>
+ </way>
+</osm>
+"#;
+
+    const OPL: &str = "n1 x2 y1
+n2 x2 ynorth
+n3 x2 y91
+n4 v2 dD x2 y91
+w5 Nn1,n3
+";
+
+    type Read = (Vec<Result<Element>>, Vec<Error>);
+
+    fn read_xml(options: ParseOptions) -> Read {
+        let mut reader = xml::Reader::new(XML.as_bytes()).with_options(options);
+        (reader.by_ref().collect(), reader.take_warnings())
+    }
+
+    fn read_opl(options: ParseOptions) -> Read {
+        let mut reader = opl::Reader::new(OPL.as_bytes()).with_options(options);
+        (reader.by_ref().collect(), reader.take_warnings())
+    }
+
+    fn ids(elements: &[Result<Element>]) -> Vec<i64> {
+        elements
+            .iter()
+            .map(|element| element.as_ref().unwrap().id().0)
+            .collect()
+    }
+
+    fn options(strict: bool, on_error: ErrorPolicy) -> ParseOptions {
+        ParseOptions { strict, on_error }
+    }
+
+    #[test]
+    fn fail_stops_at_malformed_element() {
+        for (read, line) in [(read_xml as fn(_) -> Read, 4), (read_opl, 2)] {
+            let (elements, warnings) = read(ParseOptions::default());
+            assert_eq!(elements.len(), 2);
+            assert_eq!(elements[0].as_ref().unwrap().id(), Id(1));
+            let Err(Error::Decode { line: found, .. }) = &elements[1] else {
+                panic!("expected a decode error, got {:?}", elements[1]);
+            };
+            assert_eq!(*found, Some(line));
+            assert!(warnings.is_empty());
+        }
+    }
+
+    #[test]
+    fn lenient_skips_malformed_elements() {
+        for read in [read_xml as fn(_) -> Read, read_opl] {
+            let (elements, warnings) = read(ParseOptions::lenient());
+            assert_eq!(ids(&elements), [1, 3, 4, 5]);
+            assert_eq!(warnings.len(), 1);
+            assert!(matches!(warnings[0], Error::Decode { .. }));
+
+            let (elements, warnings) = read(options(false, ErrorPolicy::Skip));
+            assert_eq!(ids(&elements), [1, 3, 4, 5]);
+            assert!(warnings.is_empty());
+        }
+    }
+
+    #[test]
+    fn strict_rejects_invalid_elements() {
+        for read in [read_xml as fn(_) -> Read, read_opl] {
+            // Node 4 is deleted, so its latitude is not checked
+            let (elements, warnings) = read(options(true, ErrorPolicy::Warn));
+            assert_eq!(ids(&elements), [1, 4, 5]);
+            assert_eq!(warnings.len(), 2);
+            let Error::Validation { element, .. } = &warnings[1] else {
+                panic!("expected a validation error, got {:?}", warnings[1]);
+            };
+            assert_eq!(
+                element,
+                &Some(ElementContext {
+                    ty: MemberType::Node,
+                    id: Id(3)
+                })
+            );
+
+            let (elements, _) = read(ParseOptions::strict());
+            assert!(matches!(elements.last(), Some(Err(Error::Decode { .. }))));
+        }
+    }
+
+    #[test]
+    fn strict_fails_on_invalid_element() {
+        let xml = XML.replace(r#" <node id="2" lat="north" lon="2"/>"#, "");
+        let mut reader = xml::Reader::new(xml.as_bytes()).with_options(ParseOptions::strict());
+        assert!(reader.next().unwrap().is_ok());
+        assert!(matches!(reader.next(), Some(Err(Error::Validation { .. }))));
+        assert!(reader.next().is_none());
+    }
+}
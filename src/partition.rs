@@ -0,0 +1,105 @@
+//! Grouping of element versions by the [changeset](crate::changeset) that made them
+//!
+//! Tools that review edits, such as vandalism detection or contribution statistics, look at
+//! each changeset on its own. [by_changeset] splits a stream of versions, e.g. of a history
+//! file or an augmented diff, by [Info::changeset](crate::Info::changeset), and [extents]
+//! finds where on the map each changeset edited.
+
+use fnv::FnvHashMap as HashMap;
+
+use crate::bbox::Bbox;
+use crate::geom::LatLon;
+use crate::scalar::{Scalar, ScalarExt};
+use crate::{Element, Id};
+
+/// Elements of each changeset, in the order given
+///
+/// Elements without a changeset, such as those of files stripped of metadata, are left out.
+pub fn by_changeset(elements: impl IntoIterator<Item = Element>) -> HashMap<i64, Vec<Element>> {
+    let mut changesets: HashMap<i64, Vec<Element>> = HashMap::default();
+    for element in elements {
+        if let Some(changeset) = element.info().and_then(|info| info.changeset) {
+            changesets.entry(changeset).or_default().push(element);
+        }
+    }
+    changesets
+}
+
+/// Where on the map a changeset edited
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ChangesetExtent {
+    /// Box around the edited locations, see [Bbox::extend]
+    pub bbox: Bbox,
+    /// Mean of the edited locations
+    pub centroid: LatLon,
+    /// Number of locations, counting a node once for each edited way it is a part of
+    pub locations: usize,
+}
+
+#[derive(Default)]
+struct Accumulator {
+    bbox: Option<Bbox>,
+    lat: f64,
+    lon: f64,
+    locations: usize,
+}
+
+impl Accumulator {
+    fn add(&mut self, (lat, lon): LatLon) {
+        match &mut self.bbox {
+            Some(bbox) => bbox.extend(lat, lon),
+            None => self.bbox = Some(Bbox::point(lat, lon)),
+        }
+        self.lat += lat.as_f64();
+        self.lon += lon.as_f64();
+        self.locations += 1;
+    }
+
+    fn finish(self) -> Option<ChangesetExtent> {
+        let bbox = self.bbox?;
+        let n = self.locations as f64;
+        let centroid = (
+            Scalar::try_from_f64(self.lat / n).unwrap_or(bbox.min_lat),
+            Scalar::try_from_f64(self.lon / n).unwrap_or(bbox.min_lon),
+        );
+        Some(ChangesetExtent {
+            bbox,
+            centroid,
+            locations: self.locations,
+        })
+    }
+}
+
+/// [ChangesetExtent] of each changeset of the elements
+///
+/// Nodes count with their own location and ways with the locations of their nodes, looked up
+/// with `node`. Relations are left out, as are changesets without any location, e.g. those
+/// that only deleted or only edited relations.
+pub fn extents<'a>(
+    elements: impl IntoIterator<Item = &'a Element>,
+    mut node: impl FnMut(Id) -> Option<LatLon>,
+) -> HashMap<i64, ChangesetExtent> {
+    let mut accumulators: HashMap<i64, Accumulator> = HashMap::default();
+    for element in elements {
+        let Some(changeset) = element.info().and_then(|info| info.changeset) else {
+            continue;
+        };
+        match element {
+            Element::Node(n) if n.info.as_ref().and_then(|info| info.visible) != Some(false) => {
+                accumulators.entry(changeset).or_default().add(n.lat_lon());
+            }
+            Element::Way(way) => {
+                let accumulator = accumulators.entry(changeset).or_default();
+                way.refs
+                    .iter()
+                    .filter_map(|id| node(*id))
+                    .for_each(|at| accumulator.add(at));
+            }
+            _ => {}
+        }
+    }
+    accumulators
+        .into_iter()
+        .filter_map(|(changeset, accumulator)| Some((changeset, accumulator.finish()?)))
+        .collect()
+}
@@ -0,0 +1,1743 @@
+//! [PBF](https://wiki.openstreetmap.org/wiki/PBF_Format) files, e.g. `planet.osm.pbf`
+//!
+//! [Reader] streams [Element]s from a file, optionally decoding on several threads with
+//! [Reader::par_iter], and [decode_block] decodes a single decompressed `PrimitiveBlock` for
+//! callers that read blobs themselves. Blobs compressed with zlib, using the pure Rust
+//! [miniz_oxide], or stored raw are supported, which covers the files written by common tools. [Writer] writes
+//! files, e.g. filtered extracts. With the `tokio` feature, [AsyncWriter] writes to a
+//! [tokio::io::AsyncWrite], and [crate::feed::read] reads from a [tokio::io::AsyncRead] with a
+//! [Decoder].
+
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Read, Write};
+use std::sync::{mpsc, Arc, Mutex};
+use std::{thread, vec};
+
+use chrono::DateTime;
+use fnv::FnvHashMap as HashMap;
+use miniz_oxide::deflate::compress_to_vec_zlib;
+use miniz_oxide::inflate::TINFLStatus;
+
+use crate::bbox::Bbox;
+use crate::error::{ElementContext, Error, Result};
+use crate::feed::{Buffer, Decode};
+use crate::parse::{ParseOptions, Recovery};
+use crate::pipeline::Sink;
+use crate::progress::{NoProgress, Observer};
+use crate::scalar::{Scalar, ScalarExt};
+use crate::{
+    timestamp_to_utc, Element, Id, Info, Member, MemberType, Node, Relation, TagString, Tags,
+    Timestamp, Way,
+};
+
+mod proto;
+
+use proto::{encode_zigzag, put_bytes, put_packed, put_sint, put_uint, zigzag, Fields};
+
+/// Largest `BlobHeader` allowed by the format
+pub const MAX_BLOB_HEADER_SIZE: usize = 64 * 1024;
+
+/// Largest `Blob`, compressed or not, allowed by the format
+pub const MAX_BLOB_SIZE: usize = 32 * 1024 * 1024;
+
+/// Required features that [Reader] understands
+pub const SUPPORTED_FEATURES: [&str; 3] = ["OsmSchema-V0.6", "DenseNodes", "HistoricalInformation"];
+
+/// Contents of the `OSMHeader` blob
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub struct Header {
+    pub bbox: Option<Bbox>,
+    /// Features a reader must support to read the file, e.g. `DenseNodes`
+    pub required_features: Vec<String>,
+    /// Features a reader may use, e.g. `Sort.Type_then_ID`
+    pub optional_features: Vec<String>,
+    pub writing_program: Option<String>,
+    pub source: Option<String>,
+    /// Time of the last [replication](https://wiki.openstreetmap.org/wiki/Planet.osm/diffs)
+    /// diff applied to the data
+    pub replication_timestamp: Option<Timestamp>,
+    pub replication_sequence_number: Option<i64>,
+    pub replication_base_url: Option<String>,
+}
+
+/// Streaming reader of [Element]s in a PBF file
+///
+/// Elements are read block by block in the order of the file. Zero uids, changesets, and
+/// timestamps, which writers use for missing values, are read as [None], as are empty user
+/// names. Files requiring features other than [SUPPORTED_FEATURES] are rejected.
+///
+/// Malformed blocks end reading unless [ParseOptions] say otherwise, in which case the
+/// elements of a block that fails to decode are skipped along with it.
+#[derive(Debug)]
+pub struct Reader<R, O = NoProgress> {
+    input: R,
+    observer: O,
+    offset: u64,
+    header: Option<Header>,
+    elements: vec::IntoIter<Element>,
+    recovery: Recovery,
+    done: bool,
+}
+
+impl<R: Read> Reader<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            input,
+            observer: NoProgress,
+            offset: 0,
+            header: None,
+            elements: vec![].into_iter(),
+            recovery: Recovery::default(),
+            done: false,
+        }
+    }
+}
+
+impl<R, O> Reader<R, O> {
+    /// Reports bytes, blocks, and elements read to `observer`
+    pub fn with_observer<P: Observer>(self, observer: P) -> Reader<R, P> {
+        Reader {
+            input: self.input,
+            observer,
+            offset: self.offset,
+            header: self.header,
+            elements: self.elements,
+            recovery: self.recovery,
+            done: self.done,
+        }
+    }
+
+    /// Sets how malformed blocks and elements are handled
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.recovery.options = options;
+        self
+    }
+
+    /// Header of the file, which is available once the first element was read
+    pub fn header(&self) -> Option<&Header> {
+        self.header.as_ref()
+    }
+
+    /// Errors of the malformed blocks and elements skipped so far with [ErrorPolicy::Warn]
+    ///
+    /// [ErrorPolicy::Warn]: crate::parse::ErrorPolicy::Warn
+    pub fn warnings(&self) -> &[Error] {
+        &self.recovery.warnings
+    }
+
+    /// Takes the [Reader::warnings], e.g. to report them while reading a large file
+    pub fn take_warnings(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.recovery.warnings)
+    }
+
+    /// Next element of the current block that passes [Recovery::check]
+    fn next_checked(&mut self) -> Option<Result<Element>> {
+        for element in self.elements.by_ref() {
+            match self.recovery.check(&element) {
+                Ok(()) => return Some(Ok(element)),
+                Err(err) => {
+                    if let Err(err) = self.recovery.skip(err) {
+                        return Some(Err(err));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    pub fn into_inner(self) -> R {
+        self.input
+    }
+}
+
+impl<R: Read, O: Observer> Reader<R, O> {
+    /// Reads blobs up to the next data block, returning false at the end of the file
+    fn read_block(&mut self) -> Result<bool> {
+        while let Some((offset, kind, blob)) = self.read_blob()? {
+            let data = decode_blob(&blob).map_err(|message| Error::decode(Some(offset), message));
+            match kind.as_str() {
+                "OSMHeader" => self.header = Some(check_header(offset, &data?)?),
+                "OSMData" => {
+                    let elements = data.and_then(|data| {
+                        Block::decode(&data).map_err(|message| Error::decode(Some(offset), message))
+                    });
+                    match elements {
+                        Ok(elements) => {
+                            self.elements = elements.into_iter();
+                            self.observer.block_decoded();
+                            return Ok(true);
+                        }
+                        Err(err) => self.recovery.skip(err)?,
+                    }
+                }
+                // Readers should skip blobs of unknown types
+                _ => {}
+            }
+        }
+        Ok(false)
+    }
+
+    /// Reads the next blob, returning its offset, type, and still compressed `Blob`
+    fn read_blob(&mut self) -> Result<Option<(u64, String, Vec<u8>)>> {
+        let offset = self.offset;
+        let mut len = [0; 4];
+        let read = read_full(&mut self.input, &mut len)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        self.offset += read as u64;
+        let header_len = u32::from_be_bytes(len) as usize;
+        if read < len.len() || header_len > MAX_BLOB_HEADER_SIZE {
+            return Err(Error::decode(Some(offset), "invalid blob header length"));
+        }
+        let header = self.read_exact(header_len)?;
+        let (kind, data_len) = blob_header(offset, &header)?;
+        let blob = self.read_exact(data_len)?;
+        self.observer.bytes_read(self.offset - offset);
+        Ok(Some((offset, kind, blob)))
+    }
+
+    fn read_exact(&mut self, len: usize) -> Result<Vec<u8>> {
+        let mut buf = vec![0; len];
+        if read_full(&mut self.input, &mut buf)? < len {
+            return Err(Error::decode(Some(self.offset), "unexpected end of file"));
+        }
+        self.offset += len as u64;
+        Ok(buf)
+    }
+}
+
+/// Type and size of the `Blob` of a `BlobHeader`
+fn blob_header(offset: u64, header: &[u8]) -> Result<(String, usize)> {
+    let mut kind = None;
+    let mut data_len = None;
+    for field in Fields(header) {
+        let (number, value) = field.map_err(|message| Error::decode(Some(offset), message))?;
+        match number {
+            1 => kind = value.string().ok().map(str::to_string),
+            3 => data_len = value.varint().ok(),
+            _ => {}
+        }
+    }
+    let (Some(kind), Some(data_len)) = (kind, data_len) else {
+        return Err(Error::decode(Some(offset), "incomplete blob header"));
+    };
+    if data_len as usize > MAX_BLOB_SIZE {
+        return Err(Error::decode(Some(offset), "blob is too large"));
+    }
+    Ok((kind, data_len as usize))
+}
+
+/// Decodes the data of an `OSMHeader` blob, rejecting unsupported required features
+fn check_header(offset: u64, data: &[u8]) -> Result<Header> {
+    let header = header(data).map_err(|message| Error::decode(Some(offset), message))?;
+    if let Some(feature) = header
+        .required_features
+        .iter()
+        .find(|feature| !SUPPORTED_FEATURES.contains(&feature.as_str()))
+    {
+        return Err(Error::decode(
+            Some(offset),
+            format!("unsupported required feature {feature}"),
+        ));
+    }
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        writing_program = ?header.writing_program,
+        "read pbf header"
+    );
+    Ok(header)
+}
+
+/// Reads until `buf` is full or the input ends, returning the bytes read
+fn read_full(input: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match input.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(read)
+}
+
+impl<R: Read, O: Observer> Iterator for Reader<R, O> {
+    type Item = Result<Element>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.next_checked() {
+                Some(Ok(element)) => {
+                    self.observer.element_read(&element);
+                    return Some(Ok(element));
+                }
+                Some(Err(err)) => {
+                    self.done = true;
+                    self.elements = vec![].into_iter();
+                    return Some(Err(err));
+                }
+                None => {}
+            }
+            if self.done {
+                return None;
+            }
+            match self.read_block() {
+                Ok(true) => {}
+                Ok(false) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(bytes = self.offset, "finished reading pbf");
+                    self.done = true;
+                }
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read, O: Observer> Reader<R, O> {
+    /// Decompresses and decodes blocks on `threads` worker threads, or one per available core
+    /// for 0, while yielding elements in the order of the file
+    ///
+    /// Blobs are still read on the calling thread, which also receives the progress. At most
+    /// two blocks per thread are decoded ahead of the elements yielded.
+    pub fn par_iter(self, threads: usize) -> ParallelReader<R, O> {
+        let threads = match threads {
+            0 => thread::available_parallelism().map_or(1, usize::from),
+            threads => threads,
+        };
+        let (jobs, job_receiver) = mpsc::channel::<(u64, u64, Vec<u8>)>();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        for _ in 0..threads {
+            let jobs = Arc::clone(&job_receiver);
+            let results = result_sender.clone();
+            thread::spawn(move || loop {
+                // Released before decoding so that the other workers can take jobs meanwhile
+                let job = jobs.lock().map(|jobs| jobs.recv());
+                let Ok(Ok((sequence, offset, blob))) = job else {
+                    return;
+                };
+                let elements = decode_blob(&blob)
+                    .and_then(|data| Block::decode(&data))
+                    .map_err(|message| Error::decode(Some(offset), message));
+                if results.send((sequence, elements)).is_err() {
+                    return;
+                }
+            });
+        }
+        ParallelReader {
+            reader: self,
+            jobs,
+            results,
+            pending: BTreeMap::new(),
+            read_error: None,
+            sent: 0,
+            yielded: 0,
+            ahead: threads * 2,
+            input_done: false,
+        }
+    }
+}
+
+/// [Reader] decoding blocks in parallel, created by [Reader::par_iter]
+///
+/// The worker threads stop once it is dropped.
+#[derive(Debug)]
+pub struct ParallelReader<R, O = NoProgress> {
+    reader: Reader<R, O>,
+    /// Sequence number, offset, and compressed `Blob` of data blocks to decode
+    jobs: mpsc::Sender<(u64, u64, Vec<u8>)>,
+    results: mpsc::Receiver<(u64, Result<Vec<Element>>)>,
+    /// Blocks decoded before those ahead of them in the file, and errors while reading
+    pending: BTreeMap<u64, Result<Vec<Element>>>,
+    /// Sequence number of the error that ended reading, which cannot be skipped
+    read_error: Option<u64>,
+    sent: u64,
+    yielded: u64,
+    ahead: usize,
+    input_done: bool,
+}
+
+impl<R, O> ParallelReader<R, O> {
+    /// See [Reader::header]
+    pub fn header(&self) -> Option<&Header> {
+        self.reader.header()
+    }
+}
+
+impl<R: Read, O: Observer> ParallelReader<R, O> {
+    /// Reads blobs until enough blocks are being decoded, queuing errors in file order
+    fn fill(&mut self) {
+        while !self.input_done && self.sent - self.yielded < self.ahead as u64 {
+            let queued = match self.reader.read_blob() {
+                Ok(Some((offset, kind, blob))) => match kind.as_str() {
+                    // Decoded right away, as elements in later blocks depend on it
+                    "OSMHeader" => decode_blob(&blob)
+                        .map_err(|message| Error::decode(Some(offset), message))
+                        .and_then(|data| check_header(offset, &data))
+                        .map(|header| self.reader.header = Some(header)),
+                    "OSMData" => {
+                        // The receivers only hang up when the workers panicked
+                        let _ = self.jobs.send((self.sent, offset, blob));
+                        self.sent += 1;
+                        Ok(())
+                    }
+                    _ => Ok(()),
+                },
+                Ok(None) => {
+                    self.input_done = true;
+                    Ok(())
+                }
+                Err(err) => Err(err),
+            };
+            if let Err(err) = queued {
+                self.pending.insert(self.sent, Err(err));
+                self.read_error = Some(self.sent);
+                self.sent += 1;
+                self.input_done = true;
+            }
+        }
+    }
+}
+
+impl<R: Read, O: Observer> Iterator for ParallelReader<R, O> {
+    type Item = Result<Element>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.reader.next_checked() {
+                Some(Ok(element)) => {
+                    self.reader.observer.element_read(&element);
+                    return Some(Ok(element));
+                }
+                Some(Err(err)) => {
+                    self.reader.done = true;
+                    self.reader.elements = vec![].into_iter();
+                    return Some(Err(err));
+                }
+                None => {}
+            }
+            if self.reader.done {
+                return None;
+            }
+            self.fill();
+            if self.yielded == self.sent {
+                self.reader.done = true;
+                return None;
+            }
+            let block = loop {
+                if let Some(block) = self.pending.remove(&self.yielded) {
+                    break block;
+                }
+                match self.results.recv() {
+                    Ok((sequence, block)) => {
+                        self.pending.insert(sequence, block);
+                    }
+                    Err(_) => break Err(Error::decode(None, "decoding thread panicked")),
+                }
+            };
+            let sequence = self.yielded;
+            self.yielded += 1;
+            let skipped = match block {
+                Ok(elements) => {
+                    self.reader.elements = elements.into_iter();
+                    self.reader.observer.block_decoded();
+                    Ok(())
+                }
+                Err(err) if self.read_error == Some(sequence) => Err(err),
+                // Blocks that failed to decode are skipped like those of a [Reader]
+                Err(err) => self.reader.recovery.skip(err),
+            };
+            if let Err(err) = skipped {
+                self.reader.done = true;
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+/// [Decode]r of a PBF file pushed in chunks, which yields the same as a [Reader]
+///
+/// Each blob is decoded once all of it was pushed, so up to [MAX_BLOB_SIZE] of input is
+/// buffered.
+#[derive(Debug, Default)]
+pub struct Decoder {
+    buffer: Buffer,
+    header: Option<Header>,
+    elements: vec::IntoIter<Element>,
+    ended: bool,
+    done: bool,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Header of the file, which is available once the first element was decoded
+    pub fn header(&self) -> Option<&Header> {
+        self.header.as_ref()
+    }
+
+    /// Decodes the next blob if all of it was pushed, returning whether it did
+    fn decode_blob(&mut self) -> Result<bool> {
+        let offset = self.buffer.consumed();
+        let data = self.buffer.fill_buf()?;
+        let Some((len, kind, blob)) = split_blob(offset, data)? else {
+            if !self.ended {
+                return Ok(false);
+            }
+            self.done = true;
+            return match data.is_empty() {
+                true => Ok(false),
+                false => Err(Error::decode(Some(offset), "unexpected end of file")),
+            };
+        };
+        let data = decode_blob(blob).map_err(|message| Error::decode(Some(offset), message))?;
+        match kind.as_str() {
+            "OSMHeader" => self.header = Some(check_header(offset, &data)?),
+            "OSMData" => {
+                self.elements = Block::decode(&data)
+                    .map_err(|message| Error::decode(Some(offset), message))?
+                    .into_iter();
+            }
+            _ => {}
+        }
+        self.buffer.consume(len);
+        Ok(true)
+    }
+}
+
+impl Decode for Decoder {
+    type Item = Element;
+
+    fn push(&mut self, data: &[u8]) {
+        self.buffer.push(data);
+    }
+
+    fn end(&mut self) {
+        self.ended = true;
+    }
+
+    fn decode(&mut self) -> Option<Result<Element>> {
+        loop {
+            if let Some(element) = self.elements.next() {
+                return Some(Ok(element));
+            }
+            if self.done {
+                return None;
+            }
+            match self.decode_blob() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// Splits the next blob off the input, returning its length with the header, its type, and
+/// the still compressed `Blob`, or [None] if the input ends before it does
+fn split_blob(offset: u64, data: &[u8]) -> Result<Option<(usize, String, &[u8])>> {
+    let Some(len) = data.first_chunk::<4>() else {
+        return Ok(None);
+    };
+    let header_len = u32::from_be_bytes(*len) as usize;
+    if header_len > MAX_BLOB_HEADER_SIZE {
+        return Err(Error::decode(Some(offset), "invalid blob header length"));
+    }
+    let Some(header) = data.get(4..4 + header_len) else {
+        return Ok(None);
+    };
+    let (kind, data_len) = blob_header(offset, header)?;
+    let end = 4 + header_len + data_len;
+    Ok(data.get(4 + header_len..end).map(|blob| (end, kind, blob)))
+}
+
+/// Decompressed data of a `Blob`
+fn decode_blob(blob: &[u8]) -> proto::Result<Vec<u8>> {
+    let mut raw_size = None;
+    let mut data = None;
+    for field in Fields(blob) {
+        let (number, value) = field?;
+        match number {
+            1 => data = Some((false, value.bytes()?)),
+            2 => raw_size = Some(value.varint()? as usize),
+            3 => data = Some((true, value.bytes()?)),
+            4 => return Err("lzma compressed blobs are not supported"),
+            5 => return Err("bzip2 compressed blobs are not supported"),
+            6 => return Err("lz4 compressed blobs are not supported"),
+            7 => return Err("zstd compressed blobs are not supported"),
+            _ => {}
+        }
+    }
+    match data {
+        Some((false, raw)) => Ok(raw.to_vec()),
+        Some((true, compressed)) => {
+            let max_size = raw_size.unwrap_or(MAX_BLOB_SIZE).min(MAX_BLOB_SIZE);
+            let data =
+                miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(compressed, max_size)
+                    .map_err(|err| match err.status {
+                        TINFLStatus::HasMoreOutput => "decompressed data is too large",
+                        TINFLStatus::FailedCannotMakeProgress => "truncated zlib stream",
+                        TINFLStatus::Adler32Mismatch => "zlib checksum mismatch",
+                        _ => "invalid zlib stream",
+                    })?;
+            match raw_size {
+                Some(raw_size) if raw_size != data.len() => Err("blob has the wrong raw size"),
+                _ => Ok(data),
+            }
+        }
+        None => Err("blob has no data"),
+    }
+}
+
+/// Decodes the data of an `OSMHeader` blob after decompression
+pub fn decode_header(data: &[u8]) -> Result<Header> {
+    header(data).map_err(|message| Error::decode(None, message))
+}
+
+fn header(data: &[u8]) -> proto::Result<Header> {
+    let mut header = Header::default();
+    for field in Fields(data) {
+        let (number, value) = field?;
+        match number {
+            1 => {
+                let mut bbox = [0i64; 4];
+                for field in Fields(value.bytes()?) {
+                    let (number, value) = field?;
+                    if let 1..=4 = number {
+                        bbox[number as usize - 1] = zigzag(value.varint()?);
+                    }
+                }
+                let [left, right, top, bottom] =
+                    bbox.map(|nano| Scalar::with_scale(nano, 9).normalized());
+                header.bbox = Some(Bbox::new(bottom, left, top, right));
+            }
+            4 => header.required_features.push(value.string()?.to_string()),
+            5 => header.optional_features.push(value.string()?.to_string()),
+            16 => header.writing_program = Some(value.string()?.to_string()),
+            17 => header.source = Some(value.string()?.to_string()),
+            32 => {
+                let seconds = value.varint()? as i64;
+                header.replication_timestamp =
+                    DateTime::from_timestamp(seconds, 0).map(crate::timestamp_from_utc);
+            }
+            33 => header.replication_sequence_number = Some(value.varint()? as i64),
+            34 => header.replication_base_url = Some(value.string()?.to_string()),
+            _ => {}
+        }
+    }
+    Ok(header)
+}
+
+/// Decodes the elements of an `OSMData` blob after decompression
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(len = data.len())))]
+pub fn decode_block(data: &[u8]) -> Result<Vec<Element>> {
+    Block::decode(data).map_err(|message| Error::decode(None, message))
+}
+
+/// `PrimitiveBlock` with its string table and scales
+struct Block<'a> {
+    strings: Vec<&'a str>,
+    granularity: i64,
+    lat_offset: i64,
+    lon_offset: i64,
+    date_granularity: i64,
+}
+
+impl<'a> Block<'a> {
+    fn decode(data: &'a [u8]) -> proto::Result<Vec<Element>> {
+        let mut block = Block {
+            strings: vec![],
+            granularity: 100,
+            lat_offset: 0,
+            lon_offset: 0,
+            date_granularity: 1000,
+        };
+        let mut groups = vec![];
+        for field in Fields(data) {
+            let (number, value) = field?;
+            match number {
+                1 => {
+                    for field in Fields(value.bytes()?) {
+                        let (number, value) = field?;
+                        if number == 1 {
+                            block.strings.push(value.string()?);
+                        }
+                    }
+                }
+                2 => groups.push(value.bytes()?),
+                17 => block.granularity = value.varint()? as i64,
+                18 => block.date_granularity = value.varint()? as i64,
+                19 => block.lat_offset = value.varint()? as i64,
+                20 => block.lon_offset = value.varint()? as i64,
+                _ => {}
+            }
+        }
+
+        let mut elements = vec![];
+        for group in groups {
+            for field in Fields(group) {
+                let (number, value) = field?;
+                match number {
+                    1 => elements.push(Element::Node(block.node(value.bytes()?)?)),
+                    2 => block.dense_nodes(value.bytes()?, &mut elements)?,
+                    3 => elements.push(Element::Way(block.way(value.bytes()?)?)),
+                    4 => elements.push(Element::Relation(block.relation(value.bytes()?)?)),
+                    _ => {}
+                }
+            }
+        }
+        Ok(elements)
+    }
+
+    fn string(&self, index: u64) -> proto::Result<&'a str> {
+        self.strings
+            .get(index as usize)
+            .copied()
+            .ok_or("string index out of range")
+    }
+
+    fn coordinate(&self, offset: i64, value: i64) -> proto::Result<Scalar> {
+        let nano = self
+            .granularity
+            .checked_mul(value)
+            .and_then(|value| value.checked_add(offset))
+            .ok_or("coordinate overflows")?;
+        Ok(Scalar::with_scale(nano, 9).normalized())
+    }
+
+    fn timestamp(&self, value: i64) -> proto::Result<Option<Timestamp>> {
+        if value == 0 {
+            return Ok(None);
+        }
+        let millis = value
+            .checked_mul(self.date_granularity)
+            .ok_or("timestamp overflows")?;
+        Ok(DateTime::from_timestamp_millis(millis).map(crate::timestamp_from_utc))
+    }
+
+    fn tags(&self, keys: &[u64], values: &[u64]) -> proto::Result<Tags> {
+        if keys.len() != values.len() {
+            return Err("keys and values differ in length");
+        }
+        keys.iter()
+            .zip(values)
+            .map(|(&key, &value)| {
+                Ok((
+                    TagString::from_ref(self.string(key)?),
+                    TagString::from_ref(self.string(value)?),
+                ))
+            })
+            .collect()
+    }
+
+    fn info(&self, data: &[u8]) -> proto::Result<Info> {
+        let mut info = RawInfo::default();
+        for field in Fields(data) {
+            let (number, value) = field?;
+            match number {
+                1 => info.version = value.varint()? as i32,
+                2 => info.timestamp = value.varint()? as i64,
+                3 => info.changeset = value.varint()? as i64,
+                4 => info.uid = value.varint()? as i32,
+                5 => info.user_sid = value.varint()?,
+                6 => info.visible = Some(value.varint()? != 0),
+                _ => {}
+            }
+        }
+        self.convert_info(info)
+    }
+
+    fn convert_info(&self, info: RawInfo) -> proto::Result<Info> {
+        Ok(Info {
+            version: info.version.max(0),
+            timestamp: self.timestamp(info.timestamp)?,
+            changeset: (info.changeset != 0).then_some(info.changeset),
+            uid: (info.uid != 0).then_some(info.uid),
+            user: Some(self.string(info.user_sid)?)
+                .filter(|user| !user.is_empty())
+                .map(TagString::from_ref),
+            visible: info.visible,
+        })
+    }
+
+    fn node(&self, data: &[u8]) -> proto::Result<Node> {
+        let (mut id, mut lat, mut lon, mut info) = (0, 0, 0, None);
+        let (mut keys, mut values) = (vec![], vec![]);
+        for field in Fields(data) {
+            let (number, value) = field?;
+            match number {
+                1 => id = zigzag(value.varint()?),
+                2 => value.varints_into(&mut keys)?,
+                3 => value.varints_into(&mut values)?,
+                4 => info = Some(self.info(value.bytes()?)?),
+                8 => lat = zigzag(value.varint()?),
+                9 => lon = zigzag(value.varint()?),
+                _ => {}
+            }
+        }
+        Ok(Node {
+            id: Id(id),
+            tags: self.tags(&keys, &values)?,
+            info,
+            lat: self.coordinate(self.lat_offset, lat)?,
+            lon: self.coordinate(self.lon_offset, lon)?,
+        })
+    }
+
+    fn dense_nodes(&self, data: &[u8], elements: &mut Vec<Element>) -> proto::Result<()> {
+        let (mut ids, mut lats, mut lons, mut keys_values) = (vec![], vec![], vec![], vec![]);
+        let mut info = None;
+        for field in Fields(data) {
+            let (number, value) = field?;
+            match number {
+                1 => value.varints_into(&mut ids)?,
+                5 => info = Some(DenseInfo::decode(value.bytes()?)?),
+                8 => value.varints_into(&mut lats)?,
+                9 => value.varints_into(&mut lons)?,
+                10 => value.varints_into(&mut keys_values)?,
+                _ => {}
+            }
+        }
+        if lats.len() != ids.len() || lons.len() != ids.len() {
+            return Err("dense node fields differ in length");
+        }
+        if let Some(info) = &info {
+            if !info.matches(ids.len()) {
+                return Err("dense info fields differ in length");
+            }
+        }
+
+        let mut keys_values = keys_values.into_iter();
+        let (mut id, mut lat, mut lon) = (0i64, 0i64, 0i64);
+        let mut raw = RawInfo::default();
+        elements.reserve(ids.len());
+        for i in 0..ids.len() {
+            id = add_delta(id, ids[i])?;
+            lat = add_delta(lat, lats[i])?;
+            lon = add_delta(lon, lons[i])?;
+
+            let mut tags = Tags::new();
+            while let Some(key) = keys_values.next() {
+                if key == 0 {
+                    break;
+                }
+                let value = keys_values.next().ok_or("dense tags end with a key")?;
+                tags.insert(
+                    TagString::from_ref(self.string(key)?),
+                    TagString::from_ref(self.string(value)?),
+                );
+            }
+
+            let info = match &info {
+                Some(dense) => {
+                    dense.next(i, &mut raw)?;
+                    Some(self.convert_info(raw.clone())?)
+                }
+                None => None,
+            };
+            elements.push(Element::Node(Node {
+                id: Id(id),
+                tags,
+                info,
+                lat: self.coordinate(self.lat_offset, lat)?,
+                lon: self.coordinate(self.lon_offset, lon)?,
+            }));
+        }
+        Ok(())
+    }
+
+    fn way(&self, data: &[u8]) -> proto::Result<Way> {
+        let (mut id, mut info) = (0, None);
+        let (mut keys, mut values, mut refs) = (vec![], vec![], vec![]);
+        for field in Fields(data) {
+            let (number, value) = field?;
+            match number {
+                1 => id = value.varint()? as i64,
+                2 => value.varints_into(&mut keys)?,
+                3 => value.varints_into(&mut values)?,
+                4 => info = Some(self.info(value.bytes()?)?),
+                8 => value.varints_into(&mut refs)?,
+                _ => {}
+            }
+        }
+        Ok(Way {
+            id: Id(id),
+            tags: self.tags(&keys, &values)?,
+            info,
+            refs: deltas(&refs)
+                .map(|id| id.map(Id))
+                .collect::<proto::Result<_>>()?,
+        })
+    }
+
+    fn relation(&self, data: &[u8]) -> proto::Result<Relation> {
+        let (mut id, mut info) = (0, None);
+        let (mut keys, mut values) = (vec![], vec![]);
+        let (mut roles, mut ids, mut types) = (vec![], vec![], vec![]);
+        for field in Fields(data) {
+            let (number, value) = field?;
+            match number {
+                1 => id = value.varint()? as i64,
+                2 => value.varints_into(&mut keys)?,
+                3 => value.varints_into(&mut values)?,
+                4 => info = Some(self.info(value.bytes()?)?),
+                8 => value.varints_into(&mut roles)?,
+                9 => value.varints_into(&mut ids)?,
+                10 => value.varints_into(&mut types)?,
+                _ => {}
+            }
+        }
+        if roles.len() != ids.len() || types.len() != ids.len() {
+            return Err("relation member fields differ in length");
+        }
+        let members = deltas(&ids)
+            .zip(roles.iter().zip(&types))
+            .map(|(id, (&role, &ty))| {
+                let id = id?;
+                let ty = match ty {
+                    0 => MemberType::Node,
+                    1 => MemberType::Way,
+                    2 => MemberType::Relation,
+                    _ => return Err("invalid member type"),
+                };
+                let role = self.string(role)?;
+                Ok(Member {
+                    id: Id(id),
+                    ty,
+                    role: (!role.is_empty()).then(|| TagString::from_ref(role)),
+                })
+            })
+            .collect::<proto::Result<_>>()?;
+        Ok(Relation {
+            id: Id(id),
+            tags: self.tags(&keys, &values)?,
+            info,
+            members,
+        })
+    }
+}
+
+/// Adds a delta coded `sint64` value to the sum of those before it
+fn add_delta(sum: i64, value: u64) -> proto::Result<i64> {
+    sum.checked_add(zigzag(value)).ok_or("delta overflows")
+}
+
+/// Sums of delta coded `sint64` values
+fn deltas(values: &[u64]) -> impl Iterator<Item = proto::Result<i64>> + '_ {
+    values.iter().scan(0i64, |sum, &value| {
+        Some(add_delta(*sum, value).inspect(|&next| *sum = next))
+    })
+}
+
+/// `Info` of the format before conversion to [Info]
+#[derive(Debug, Clone)]
+struct RawInfo {
+    version: i32,
+    timestamp: i64,
+    changeset: i64,
+    uid: i32,
+    user_sid: u64,
+    visible: Option<bool>,
+}
+
+impl Default for RawInfo {
+    fn default() -> Self {
+        Self {
+            version: -1,
+            timestamp: 0,
+            changeset: 0,
+            uid: 0,
+            user_sid: 0,
+            visible: None,
+        }
+    }
+}
+
+/// Columns of `DenseInfo`, all delta coded except versions and visibility
+#[derive(Debug, Default)]
+struct DenseInfo {
+    versions: Vec<u64>,
+    timestamps: Vec<u64>,
+    changesets: Vec<u64>,
+    uids: Vec<u64>,
+    user_sids: Vec<u64>,
+    visible: Vec<u64>,
+}
+
+impl DenseInfo {
+    fn decode(data: &[u8]) -> proto::Result<Self> {
+        let mut info = Self::default();
+        for field in Fields(data) {
+            let (number, value) = field?;
+            match number {
+                1 => value.varints_into(&mut info.versions)?,
+                2 => value.varints_into(&mut info.timestamps)?,
+                3 => value.varints_into(&mut info.changesets)?,
+                4 => value.varints_into(&mut info.uids)?,
+                5 => value.varints_into(&mut info.user_sids)?,
+                6 => value.varints_into(&mut info.visible)?,
+                _ => {}
+            }
+        }
+        Ok(info)
+    }
+
+    /// Whether every column is empty or has a value per node
+    fn matches(&self, len: usize) -> bool {
+        [
+            &self.versions,
+            &self.timestamps,
+            &self.changesets,
+            &self.uids,
+            &self.user_sids,
+            &self.visible,
+        ]
+        .iter()
+        .all(|column| column.is_empty() || column.len() == len)
+    }
+
+    /// Advances `raw` from node `i - 1` to node `i`
+    fn next(&self, i: usize, raw: &mut RawInfo) -> proto::Result<()> {
+        let delta = |sum: i64, column: &[u64]| match column.get(i) {
+            Some(&value) => add_delta(sum, value),
+            None => Ok(sum),
+        };
+        raw.version = self.versions.get(i).map_or(-1, |&value| value as i32);
+        raw.timestamp = delta(raw.timestamp, &self.timestamps)?;
+        raw.changeset = delta(raw.changeset, &self.changesets)?;
+        raw.uid = i32::try_from(delta(raw.uid.into(), &self.uids)?).map_err(|_| "uid overflows")?;
+        raw.user_sid = u64::try_from(delta(raw.user_sid as i64, &self.user_sids)?)
+            .map_err(|_| "string index out of range")?;
+        raw.visible = self.visible.get(i).map(|&value| value != 0);
+        Ok(())
+    }
+}
+
+/// Most elements [Writer] puts into one block by default, as common tools do
+pub const DEFAULT_BLOCK_ELEMENTS: usize = 8000;
+
+/// zlib level of [Compression::Zlib], the default of zlib and common tools
+const ZLIB_LEVEL: u8 = 6;
+
+/// Compression of the blobs written by [Writer]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Compression {
+    /// Uncompressed, which is fastest but makes files about twice as large
+    Raw,
+    #[default]
+    Zlib,
+}
+
+impl Header {
+    /// Header requiring `OsmSchema-V0.6` and `DenseNodes`, written by
+    /// [crate::xml::DEFAULT_GENERATOR]
+    pub fn new() -> Self {
+        Self {
+            required_features: vec!["OsmSchema-V0.6".to_string(), "DenseNodes".to_string()],
+            writing_program: Some(crate::xml::DEFAULT_GENERATOR.to_string()),
+            ..Self::default()
+        }
+    }
+}
+
+/// Writer of [Element]s in a PBF file
+///
+/// Elements are collected into blocks of [DEFAULT_BLOCK_ELEMENTS], each with its own string
+/// table. Nodes are written as `DenseNodes` with delta coded ids and coordinates at the
+/// default granularity of 100 nanodegrees. Visibility is only written if the header requires
+/// `HistoricalInformation`. Elements should be sorted by type and id, see
+/// [crate::change::apply_sorted], for files that other tools read efficiently.
+#[derive(Debug)]
+pub struct Writer<W> {
+    output: W,
+    /// Header until it is written
+    header: Option<Header>,
+    historical: bool,
+    compression: Compression,
+    block_elements: usize,
+    block: BlockBuilder,
+    finished: bool,
+}
+
+impl<W: Write> Writer<W> {
+    /// Writer with the header from [Header::new]
+    pub fn new(output: W) -> Self {
+        Self::with_header(output, Header::new())
+    }
+
+    pub fn with_header(output: W, header: Header) -> Self {
+        Self {
+            output,
+            historical: header
+                .required_features
+                .iter()
+                .any(|feature| feature == "HistoricalInformation"),
+            header: Some(header),
+            compression: Compression::default(),
+            block_elements: DEFAULT_BLOCK_ELEMENTS,
+            block: BlockBuilder::default(),
+            finished: false,
+        }
+    }
+
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Most elements in one block, at least 1
+    pub fn block_elements(mut self, block_elements: usize) -> Self {
+        self.block_elements = block_elements.max(1);
+        self
+    }
+
+    pub fn write(&mut self, element: &Element) -> Result<()> {
+        self.start()?;
+        self.block
+            .add(element, self.historical)
+            .map_err(|message| Error::Validation {
+                element: Some(ElementContext {
+                    ty: element.member_type(),
+                    id: element.id(),
+                }),
+                message: message.to_string(),
+            })?;
+        if self.block.len >= self.block_elements {
+            self.flush_block()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the last block, and the header if no element was written
+    pub fn finish(&mut self) -> Result<()> {
+        self.start()?;
+        self.flush_block()?;
+        self.output.flush()?;
+        self.finished = true;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    fn start(&mut self) -> Result<()> {
+        if self.finished {
+            return Err(Error::Validation {
+                element: None,
+                message: "writer is already finished".to_string(),
+            });
+        }
+        if let Some(header) = self.header.take() {
+            self.write_blob("OSMHeader", &encode_header(&header))?;
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(elements = self.block.len)))]
+    fn flush_block(&mut self) -> Result<()> {
+        if self.block.len == 0 {
+            return Ok(());
+        }
+        let block = std::mem::take(&mut self.block).encode();
+        self.write_blob("OSMData", &block)
+    }
+
+    fn write_blob(&mut self, kind: &str, data: &[u8]) -> Result<()> {
+        if data.len() > MAX_BLOB_SIZE {
+            return Err(Error::Validation {
+                element: None,
+                message: format!("block of {} bytes is too large", data.len()),
+            });
+        }
+        let mut blob = vec![];
+        match self.compression {
+            Compression::Raw => put_bytes(&mut blob, 1, data),
+            Compression::Zlib => {
+                put_uint(&mut blob, 2, data.len() as u64);
+                put_bytes(&mut blob, 3, &compress_to_vec_zlib(data, ZLIB_LEVEL));
+            }
+        }
+        let mut header = vec![];
+        put_bytes(&mut header, 1, kind.as_bytes());
+        put_uint(&mut header, 3, blob.len() as u64);
+
+        self.output
+            .write_all(&(header.len() as u32).to_be_bytes())?;
+        self.output.write_all(&header)?;
+        self.output.write_all(&blob)?;
+        Ok(())
+    }
+}
+
+impl<W: Write> Sink for Writer<W> {
+    type Error = Error;
+
+    fn write(&mut self, element: Element) -> Result<()> {
+        Writer::write(self, &element)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        Writer::finish(self)
+    }
+}
+
+/// [Writer] to a [tokio::io::AsyncWrite], e.g. a socket
+///
+/// Blocks are written to the output once they are encoded, so the output need not be
+/// buffered.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct AsyncWriter<W> {
+    output: W,
+    writer: Writer<Vec<u8>>,
+}
+
+#[cfg(feature = "tokio")]
+impl<W: tokio::io::AsyncWrite + Unpin> AsyncWriter<W> {
+    /// Writer with the header from [Header::new]
+    pub fn new(output: W) -> Self {
+        Self::with_header(output, Header::new())
+    }
+
+    pub fn with_header(output: W, header: Header) -> Self {
+        Self {
+            output,
+            writer: Writer::with_header(vec![], header),
+        }
+    }
+
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.writer = self.writer.compression(compression);
+        self
+    }
+
+    /// Most elements in one block, at least 1
+    pub fn block_elements(mut self, block_elements: usize) -> Self {
+        self.writer = self.writer.block_elements(block_elements);
+        self
+    }
+
+    pub async fn write(&mut self, element: &Element) -> Result<()> {
+        self.writer.write(element)?;
+        self.write_encoded().await
+    }
+
+    /// Writes the last block, and the header if no element was written
+    pub async fn finish(&mut self) -> Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        self.writer.finish()?;
+        self.write_encoded().await?;
+        self.output.flush().await?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    async fn write_encoded(&mut self) -> Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        self.output.write_all(&self.writer.output).await?;
+        self.writer.output.clear();
+        Ok(())
+    }
+}
+
+fn encode_header(header: &Header) -> Vec<u8> {
+    let mut buf = vec![];
+    if let Some(bbox) = &header.bbox {
+        let nano = |value: Scalar| {
+            (value * Scalar::from_int(1_000_000_000))
+                .round_half_away()
+                .to_int()
+                .unwrap_or_default()
+        };
+        let mut message = vec![];
+        put_sint(&mut message, 1, nano(bbox.min_lon));
+        put_sint(&mut message, 2, nano(bbox.max_lon));
+        put_sint(&mut message, 3, nano(bbox.max_lat));
+        put_sint(&mut message, 4, nano(bbox.min_lat));
+        put_bytes(&mut buf, 1, &message);
+    }
+    for feature in &header.required_features {
+        put_bytes(&mut buf, 4, feature.as_bytes());
+    }
+    for feature in &header.optional_features {
+        put_bytes(&mut buf, 5, feature.as_bytes());
+    }
+    if let Some(program) = &header.writing_program {
+        put_bytes(&mut buf, 16, program.as_bytes());
+    }
+    if let Some(source) = &header.source {
+        put_bytes(&mut buf, 17, source.as_bytes());
+    }
+    if let Some(timestamp) = header.replication_timestamp {
+        put_uint(&mut buf, 32, timestamp_to_utc(timestamp).timestamp() as u64);
+    }
+    if let Some(sequence_number) = header.replication_sequence_number {
+        put_uint(&mut buf, 33, sequence_number as u64);
+    }
+    if let Some(base_url) = &header.replication_base_url {
+        put_bytes(&mut buf, 34, base_url.as_bytes());
+    }
+    buf
+}
+
+/// `PrimitiveBlock` being collected by [Writer]
+#[derive(Debug, Default)]
+struct BlockBuilder {
+    strings: Vec<TagString>,
+    indices: HashMap<TagString, u64>,
+    /// Finished `PrimitiveGroup`s
+    groups: Vec<Vec<u8>>,
+    group: GroupBuilder,
+    len: usize,
+}
+
+/// `PrimitiveGroup`, which holds elements of one kind
+#[derive(Debug, Default)]
+enum GroupBuilder {
+    #[default]
+    Empty,
+    Dense(DenseBuilder),
+    /// Encoded `Way` fields
+    Ways(Vec<u8>),
+    /// Encoded `Relation` fields
+    Relations(Vec<u8>),
+}
+
+/// Columns of `DenseNodes` before delta coding
+#[derive(Debug, Default)]
+struct DenseBuilder {
+    ids: Vec<i64>,
+    lats: Vec<i64>,
+    lons: Vec<i64>,
+    keys_values: Vec<u64>,
+    /// Info of every node or none
+    info: Option<Vec<RawInfo>>,
+}
+
+impl BlockBuilder {
+    fn string(&mut self, value: &str) -> u64 {
+        if self.strings.is_empty() {
+            // Index 0 separates the tags of dense nodes, so it holds no string in use
+            self.strings.push(TagString::from_static(""));
+        }
+        if let Some(&index) = self.indices.get(value) {
+            return index;
+        }
+        let index = self.strings.len() as u64;
+        let value = TagString::from_ref(value);
+        self.strings.push(value.clone());
+        self.indices.insert(value, index);
+        index
+    }
+
+    fn add(
+        &mut self,
+        element: &Element,
+        historical: bool,
+    ) -> std::result::Result<(), &'static str> {
+        let info = element.info().map(|info| self.raw_info(info, historical));
+        match element {
+            Element::Node(node) => {
+                let lat = coordinate(node.lat).ok_or("latitude is out of range")?;
+                let lon = coordinate(node.lon).ok_or("longitude is out of range")?;
+                let same_kind = matches!(&self.group, GroupBuilder::Dense(dense) if dense.info.is_some() == info.is_some());
+                if !same_kind {
+                    self.finish_group();
+                    self.group = GroupBuilder::Dense(DenseBuilder {
+                        info: info.is_some().then(Vec::new),
+                        ..DenseBuilder::default()
+                    });
+                }
+                let mut keys_values = vec![];
+                for (key, value) in node.tags.iter() {
+                    keys_values.push(self.string(key));
+                    keys_values.push(self.string(value));
+                }
+                let GroupBuilder::Dense(dense) = &mut self.group else {
+                    unreachable!("group was just started");
+                };
+                dense.ids.push(node.id.0);
+                dense.lats.push(lat);
+                dense.lons.push(lon);
+                dense.keys_values.extend(keys_values);
+                dense.keys_values.push(0);
+                if let (Some(columns), Some(info)) = (&mut dense.info, info) {
+                    columns.push(info);
+                }
+            }
+            Element::Way(way) => {
+                let mut message = self.common(element, info);
+                put_packed(
+                    &mut message,
+                    8,
+                    delta_encode(way.refs.iter().map(|id| id.0)),
+                );
+                if !matches!(self.group, GroupBuilder::Ways(_)) {
+                    self.finish_group();
+                    self.group = GroupBuilder::Ways(vec![]);
+                }
+                if let GroupBuilder::Ways(group) = &mut self.group {
+                    put_bytes(group, 3, &message);
+                }
+            }
+            Element::Relation(relation) => {
+                let mut message = self.common(element, info);
+                let roles: Vec<_> = relation
+                    .members
+                    .iter()
+                    .map(|member| self.string(member.role.as_deref().unwrap_or_default()))
+                    .collect();
+                put_packed(&mut message, 8, roles);
+                put_packed(
+                    &mut message,
+                    9,
+                    delta_encode(relation.members.iter().map(|member| member.id.0)),
+                );
+                put_packed(
+                    &mut message,
+                    10,
+                    relation.members.iter().map(|member| match member.ty {
+                        MemberType::Node => 0,
+                        MemberType::Way => 1,
+                        MemberType::Relation => 2,
+                    }),
+                );
+                if !matches!(self.group, GroupBuilder::Relations(_)) {
+                    self.finish_group();
+                    self.group = GroupBuilder::Relations(vec![]);
+                }
+                if let GroupBuilder::Relations(group) = &mut self.group {
+                    put_bytes(group, 4, &message);
+                }
+            }
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    fn raw_info(&mut self, info: &Info, historical: bool) -> RawInfo {
+        RawInfo {
+            version: info.version,
+            // Seconds at the default date granularity of 1000 milliseconds
+            timestamp: info
+                .timestamp_utc()
+                .map_or(0, |timestamp| timestamp.timestamp()),
+            changeset: info.changeset.unwrap_or_default(),
+            uid: info.uid.unwrap_or_default(),
+            user_sid: self.string(info.user.as_deref().unwrap_or_default()),
+            visible: historical.then(|| info.visible.unwrap_or(true)),
+        }
+    }
+
+    /// Id, tags, and info of a way or relation
+    fn common(&mut self, element: &Element, info: Option<RawInfo>) -> Vec<u8> {
+        let mut message = vec![];
+        put_uint(&mut message, 1, element.id().0 as u64);
+        let (keys, values): (Vec<_>, Vec<_>) = element
+            .tags()
+            .iter()
+            .map(|(key, value)| (self.string(key), self.string(value)))
+            .unzip();
+        put_packed(&mut message, 2, keys);
+        put_packed(&mut message, 3, values);
+        if let Some(info) = info {
+            let mut encoded = vec![];
+            put_uint(&mut encoded, 1, info.version as i64 as u64);
+            put_uint(&mut encoded, 2, info.timestamp as u64);
+            put_uint(&mut encoded, 3, info.changeset as u64);
+            put_uint(&mut encoded, 4, info.uid as i64 as u64);
+            put_uint(&mut encoded, 5, info.user_sid);
+            if let Some(visible) = info.visible {
+                put_uint(&mut encoded, 6, visible.into());
+            }
+            put_bytes(&mut message, 4, &encoded);
+        }
+        message
+    }
+
+    fn finish_group(&mut self) {
+        let mut group = vec![];
+        match std::mem::take(&mut self.group) {
+            GroupBuilder::Empty => return,
+            GroupBuilder::Dense(dense) => {
+                let mut message = vec![];
+                put_packed(&mut message, 1, delta_encode(dense.ids));
+                if let Some(info) = &dense.info {
+                    let mut encoded = vec![];
+                    let column = |get: fn(&RawInfo) -> i64| info.iter().map(get);
+                    put_packed(
+                        &mut encoded,
+                        1,
+                        column(|info| info.version.into()).map(|version| version as u64),
+                    );
+                    put_packed(&mut encoded, 2, delta_encode(column(|info| info.timestamp)));
+                    put_packed(&mut encoded, 3, delta_encode(column(|info| info.changeset)));
+                    put_packed(
+                        &mut encoded,
+                        4,
+                        delta_encode(column(|info| info.uid.into())),
+                    );
+                    put_packed(
+                        &mut encoded,
+                        5,
+                        delta_encode(column(|info| info.user_sid as i64)),
+                    );
+                    if info.iter().any(|info| info.visible.is_some()) {
+                        put_packed(
+                            &mut encoded,
+                            6,
+                            info.iter().map(|info| info.visible.unwrap_or(true).into()),
+                        );
+                    }
+                    put_bytes(&mut message, 5, &encoded);
+                }
+                put_packed(&mut message, 8, delta_encode(dense.lats));
+                put_packed(&mut message, 9, delta_encode(dense.lons));
+                // All nodes without tags may omit the column
+                if dense.keys_values.iter().any(|&index| index != 0) {
+                    put_packed(&mut message, 10, dense.keys_values);
+                }
+                put_bytes(&mut group, 2, &message);
+            }
+            GroupBuilder::Ways(ways) => group = ways,
+            GroupBuilder::Relations(relations) => group = relations,
+        }
+        self.groups.push(group);
+    }
+
+    fn encode(mut self) -> Vec<u8> {
+        self.finish_group();
+        let mut table = vec![];
+        for string in &self.strings {
+            put_bytes(&mut table, 1, string.as_bytes());
+        }
+        let mut block = vec![];
+        put_bytes(&mut block, 1, &table);
+        for group in &self.groups {
+            put_bytes(&mut block, 2, group);
+        }
+        block
+    }
+}
+
+/// Coordinate in units of the default granularity of 100 nanodegrees
+fn coordinate(value: Scalar) -> Option<i64> {
+    (value * Scalar::from_int(10_000_000))
+        .round_half_away()
+        .to_int()
+}
+
+/// Differences to the previous value as `sint64`
+fn delta_encode(values: impl IntoIterator<Item = i64>) -> impl Iterator<Item = u64> {
+    values.into_iter().scan(0i64, |previous, value| {
+        let delta = value.wrapping_sub(*previous);
+        *previous = value;
+        Some(encode_zigzag(delta))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WayId;
+
+    fn info(version: i32, seconds: i64) -> Info {
+        let timestamp = DateTime::from_timestamp(seconds, 0).unwrap();
+        Info::builder()
+            .version(version)
+            .timestamp(crate::timestamp_from_utc(timestamp))
+            .changeset(100 + seconds)
+            .user(7, "mapper")
+            .build()
+    }
+
+    fn elements(with_info: bool) -> Vec<Element> {
+        let info = |version, seconds| with_info.then(|| info(version, seconds));
+        let node = |id: i64, lat: i64, lon: i64| Node {
+            id: Id(id),
+            tags: Tags::new(),
+            info: info(id as i32, 1_600_000_000 + id),
+            lat: Scalar::with_scale(lat, 7).normalized(),
+            lon: Scalar::with_scale(lon, 7).normalized(),
+        };
+        let mut tagged = node(2, 515_007_000, -1_246_000);
+        tagged.tags.insert("amenity".into(), "cafe".into());
+        let mut way = Way::builder(Id(10))
+            .nodes([1, 2, 3].map(Id))
+            .tag("highway", "footway")
+            .build()
+            .unwrap();
+        way.info = info(3, 1_700_000_000);
+        let mut relation = Relation::builder(Id(20))
+            .member(crate::NodeId(1), "stop")
+            .member(WayId(10), "")
+            .tag("type", "route")
+            .build()
+            .unwrap();
+        relation.info = info(1, 1_700_000_100);
+        vec![
+            Element::Node(node(1, 515_000_000, -1_250_000)),
+            Element::Node(tagged),
+            Element::Node(node(3, -335_000_000, 1_800_000_000)),
+            Element::Way(way),
+            Element::Relation(relation),
+        ]
+    }
+
+    fn write(elements: &[Element], compression: Compression, block_elements: usize) -> Vec<u8> {
+        let mut writer = Writer::new(vec![])
+            .compression(compression)
+            .block_elements(block_elements);
+        for element in elements {
+            writer.write(element).unwrap();
+        }
+        writer.finish().unwrap();
+        writer.into_inner()
+    }
+
+    #[test]
+    fn round_trip() {
+        for compression in [Compression::Raw, Compression::Zlib] {
+            for with_info in [true, false] {
+                for block_elements in [1, 2, DEFAULT_BLOCK_ELEMENTS] {
+                    let elements = elements(with_info);
+                    let data = write(&elements, compression, block_elements);
+                    let mut reader = Reader::new(&data[..]);
+                    let read: Vec<_> = reader.by_ref().map(Result::unwrap).collect();
+                    assert_eq!(
+                        read, elements,
+                        "{compression:?} {with_info} {block_elements}"
+                    );
+                    let header = reader.header().unwrap();
+                    assert!(header.required_features.iter().any(|f| f == "DenseNodes"));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dense_nodes_in_one_group() {
+        let elements = elements(true);
+        let data = write(&elements, Compression::Raw, DEFAULT_BLOCK_ELEMENTS);
+        let mut input = &data[..];
+        let mut blocks = vec![];
+        while !input.is_empty() {
+            let header_len = u32::from_be_bytes(input[..4].try_into().unwrap()) as usize;
+            let (kind, blob_len) = blob_header(0, &input[4..4 + header_len]).unwrap();
+            let blob = &input[4 + header_len..4 + header_len + blob_len];
+            if kind == "OSMData" {
+                blocks.push(decode_blob(blob).unwrap());
+            }
+            input = &input[4 + header_len + blob_len..];
+        }
+        assert_eq!(blocks.len(), 1);
+        let groups: Vec<Vec<u32>> = Fields(&blocks[0])
+            .filter_map(|field| {
+                let (number, value) = field.unwrap();
+                (number == 2).then(|| {
+                    Fields(value.bytes().unwrap())
+                        .map(|field| field.unwrap().0)
+                        .collect()
+                })
+            })
+            .collect();
+        assert!(groups.iter().flatten().any(|&number| number == 2));
+        assert!(!groups.iter().flatten().any(|&number| number == 1));
+        assert_eq!(decode_block(&blocks[0]).unwrap(), elements);
+    }
+
+    #[test]
+    fn corrupt_blocks_are_skipped_when_lenient() {
+        let elements = elements(true);
+        let mut data = write(&elements, Compression::Zlib, 2);
+        // Breaks the checksum at the end of the second data blob, the one of node 3 and way 10
+        let mut offset = 0;
+        let mut data_blobs = 0;
+        while data_blobs < 2 {
+            let header_len = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap());
+            let header = &data[offset + 4..offset + 4 + header_len as usize];
+            let (kind, blob_len) = blob_header(0, header).unwrap();
+            offset += 4 + header_len as usize + blob_len;
+            data_blobs += usize::from(kind == "OSMData");
+        }
+        data[offset - 1] ^= 0xff;
+
+        let read: Vec<_> = Reader::new(&data[..]).collect();
+        assert_eq!(read.len(), 3);
+        assert!(matches!(read[2], Err(Error::Decode { .. })));
+
+        let mut reader = Reader::new(&data[..]).with_options(ParseOptions::lenient());
+        let ids: Vec<_> = reader
+            .by_ref()
+            .map(|element| element.unwrap().id())
+            .collect();
+        assert_eq!(ids, [Id(1), Id(2), Id(20)]);
+        assert_eq!(reader.warnings().len(), 1);
+    }
+
+    /// `PrimitiveBlock` of one group of dense nodes with the given columns
+    fn dense_block(ids: &[i64], lats: &[i64], fields: &[(u32, u64)]) -> Vec<u8> {
+        let mut strings = vec![];
+        put_bytes(&mut strings, 1, b"");
+        let mut dense = vec![];
+        put_packed(&mut dense, 1, ids.iter().map(|&id| encode_zigzag(id)));
+        put_packed(&mut dense, 8, lats.iter().map(|&lat| encode_zigzag(lat)));
+        put_packed(&mut dense, 9, lats.iter().map(|_| 0));
+        let mut group = vec![];
+        put_bytes(&mut group, 2, &dense);
+        let mut block = vec![];
+        put_bytes(&mut block, 1, &strings);
+        put_bytes(&mut block, 2, &group);
+        for &(number, value) in fields {
+            put_uint(&mut block, number, value);
+        }
+        block
+    }
+
+    fn decode_error(data: &[u8]) -> String {
+        match decode_block(data) {
+            Err(Error::Decode { message, .. }) => message,
+            other => panic!("expected a decode error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn overflows_are_errors() {
+        assert_eq!(
+            decode_block(&dense_block(&[1, 1], &[10, -20], &[]))
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            decode_error(&dense_block(&[i64::MAX, 1], &[0, 0], &[])),
+            "delta overflows"
+        );
+        assert_eq!(
+            decode_error(&dense_block(&[1], &[i64::MAX], &[])),
+            "coordinate overflows"
+        );
+        assert_eq!(
+            decode_error(&dense_block(&[1], &[2], &[(17, u64::MAX >> 1)])),
+            "coordinate overflows"
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Reading the [protobuf wire format](https://protobuf.dev/programming-guides/encoding/)
+
+pub(super) type Result<T> = std::result::Result<T, &'static str>;
+
+/// Value of a field, by wire type
+///
+/// Values of fixed size are skipped, since no message of the format uses them.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Value<'a> {
+    Varint(u64),
+    Fixed64,
+    Bytes(&'a [u8]),
+    Fixed32,
+}
+
+impl<'a> Value<'a> {
+    pub fn varint(self) -> Result<u64> {
+        match self {
+            Value::Varint(value) => Ok(value),
+            _ => Err("expected varint field"),
+        }
+    }
+
+    pub fn bytes(self) -> Result<&'a [u8]> {
+        match self {
+            Value::Bytes(bytes) => Ok(bytes),
+            _ => Err("expected length-delimited field"),
+        }
+    }
+
+    pub fn string(self) -> Result<&'a str> {
+        std::str::from_utf8(self.bytes()?).map_err(|_| "invalid UTF-8 in string field")
+    }
+
+    /// Appends a repeated varint field, which may be packed or not
+    pub fn varints_into(self, values: &mut Vec<u64>) -> Result<()> {
+        match self {
+            Value::Varint(value) => values.push(value),
+            Value::Bytes(mut bytes) => {
+                while !bytes.is_empty() {
+                    values.push(varint(&mut bytes)?);
+                }
+            }
+            _ => return Err("expected varint field"),
+        }
+        Ok(())
+    }
+}
+
+/// Fields of a message in the order they are encoded
+pub(super) struct Fields<'a>(pub &'a [u8]);
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<(u32, Value<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.is_empty() {
+            return None;
+        }
+        Some(self.field())
+    }
+}
+
+impl<'a> Fields<'a> {
+    fn field(&mut self) -> Result<(u32, Value<'a>)> {
+        let key = varint(&mut self.0)?;
+        let number = u32::try_from(key >> 3).map_err(|_| "invalid field number")?;
+        let value = match key & 7 {
+            0 => Value::Varint(varint(&mut self.0)?),
+            1 => {
+                self.take(8)?;
+                Value::Fixed64
+            }
+            2 => {
+                let len = usize::try_from(varint(&mut self.0)?).map_err(|_| "invalid length")?;
+                Value::Bytes(self.take(len)?)
+            }
+            5 => {
+                self.take(4)?;
+                Value::Fixed32
+            }
+            _ => return Err("unsupported wire type"),
+        };
+        Ok((number, value))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.0.len() {
+            return Err("truncated message");
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+}
+
+pub(super) fn varint(data: &mut &[u8]) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = data.split_first().ok_or("truncated varint")?;
+        *data = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("varint is too long")
+}
+
+/// Decodes `sint32` and `sint64`
+pub(super) fn zigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+/// Encodes `sint32` and `sint64`
+pub(super) fn encode_zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+pub(super) fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Appends a varint field
+pub(super) fn put_uint(buf: &mut Vec<u8>, number: u32, value: u64) {
+    put_varint(buf, u64::from(number) << 3);
+    put_varint(buf, value);
+}
+
+/// Appends a `sint32` or `sint64` field
+pub(super) fn put_sint(buf: &mut Vec<u8>, number: u32, value: i64) {
+    put_uint(buf, number, encode_zigzag(value));
+}
+
+/// Appends a length-delimited field
+pub(super) fn put_bytes(buf: &mut Vec<u8>, number: u32, bytes: &[u8]) {
+    put_varint(buf, u64::from(number) << 3 | 2);
+    put_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+/// Appends a packed repeated varint field, omitting it if empty
+pub(super) fn put_packed(buf: &mut Vec<u8>, number: u32, values: impl IntoIterator<Item = u64>) {
+    let mut packed = vec![];
+    for value in values {
+        put_varint(&mut packed, value);
+    }
+    if !packed.is_empty() {
+        put_bytes(buf, number, &packed);
+    }
+}
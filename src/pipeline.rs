@@ -0,0 +1,292 @@
+//! Composable processing of element streams
+//!
+//! A [Pipeline] pulls [Element]s from a [Source], passes them through [Transform]s, and
+//! pushes the results into a [Sink]. Elements are processed one at a time, so a slow sink
+//! naturally slows down reading instead of elements piling up in memory.
+//!
+//! Any iterator of results is a [Source], so readers compose as in
+//! `reader.into_pipeline().filter(f).strip_info().write(sink)`. With the `stream` feature, a
+//! [Pipeline] is also a [futures_core::Stream].
+
+use std::convert::Infallible;
+use std::fmt;
+
+use fnv::FnvHashMap as HashMap;
+
+use crate::{Element, TagString};
+
+/// Producer of elements, e.g. a file reader
+pub trait Source {
+    type Error;
+
+    /// Reads the next element, or [None] at the end of the stream
+    fn read(&mut self) -> Option<Result<Element, Self::Error>>;
+}
+
+impl<I, E> Source for I
+where
+    I: Iterator<Item = Result<Element, E>>,
+{
+    type Error = E;
+
+    fn read(&mut self) -> Option<Result<Element, Self::Error>> {
+        self.next()
+    }
+}
+
+/// Starts a [Pipeline] from any [Source]
+pub trait IntoPipeline: Source + Sized {
+    fn into_pipeline(self) -> Pipeline<Self, Identity> {
+        Pipeline::new(self)
+    }
+}
+
+impl<S: Source> IntoPipeline for S {}
+
+/// Step that modifies or drops elements
+pub trait Transform {
+    /// Returns the transformed element, or [None] to drop it
+    fn apply(&mut self, element: Element) -> Option<Element>;
+}
+
+impl<F> Transform for F
+where
+    F: FnMut(Element) -> Option<Element>,
+{
+    fn apply(&mut self, element: Element) -> Option<Element> {
+        self(element)
+    }
+}
+
+/// Applies `A` and then `B`
+#[derive(Debug, Clone)]
+pub struct Chain<A, B>(pub A, pub B);
+
+impl<A: Transform, B: Transform> Transform for Chain<A, B> {
+    fn apply(&mut self, element: Element) -> Option<Element> {
+        self.0
+            .apply(element)
+            .and_then(|element| self.1.apply(element))
+    }
+}
+
+/// Leaves elements unchanged
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Identity;
+
+impl Transform for Identity {
+    fn apply(&mut self, element: Element) -> Option<Element> {
+        Some(element)
+    }
+}
+
+/// Consumer of elements, e.g. a file writer
+pub trait Sink {
+    type Error;
+
+    fn write(&mut self, element: Element) -> Result<(), Self::Error>;
+
+    /// Called once after the last element, e.g. to write a footer or flush buffers
+    fn finish(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl Sink for Vec<Element> {
+    type Error = Infallible;
+
+    fn write(&mut self, element: Element) -> Result<(), Self::Error> {
+        self.push(element);
+        Ok(())
+    }
+}
+
+/// [Sink] that calls a closure for each element
+#[derive(Debug, Clone)]
+pub struct ForEach<F>(pub F);
+
+impl<F, E> Sink for ForEach<F>
+where
+    F: FnMut(Element) -> Result<(), E>,
+{
+    type Error = E;
+
+    fn write(&mut self, element: Element) -> Result<(), Self::Error> {
+        (self.0)(element)
+    }
+}
+
+/// Error from either end of a [Pipeline]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PipelineError<S, K> {
+    Source(S),
+    Sink(K),
+}
+
+impl<S: fmt::Display, K: fmt::Display> fmt::Display for PipelineError<S, K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PipelineError::Source(err) => write!(f, "reading failed: {err}"),
+            PipelineError::Sink(err) => write!(f, "writing failed: {err}"),
+        }
+    }
+}
+
+impl<S, K> std::error::Error for PipelineError<S, K>
+where
+    S: std::error::Error + 'static,
+    K: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PipelineError::Source(err) => Some(err),
+            PipelineError::Sink(err) => Some(err),
+        }
+    }
+}
+
+/// [Source] followed by a [Transform]
+///
+/// Built with [Pipeline::new] and the chainable methods below, and run with [Pipeline::write].
+#[derive(Debug, Clone)]
+pub struct Pipeline<S, T> {
+    source: S,
+    transform: T,
+}
+
+impl<S: Source> Pipeline<S, Identity> {
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            transform: Identity,
+        }
+    }
+}
+
+impl<S: Source, T: Transform> Pipeline<S, T> {
+    /// Adds a transform after the existing ones
+    pub fn then<U: Transform>(self, transform: U) -> Pipeline<S, Chain<T, U>> {
+        Pipeline {
+            source: self.source,
+            transform: Chain(self.transform, transform),
+        }
+    }
+
+    /// Keeps only elements for which `predicate` returns true
+    pub fn filter<P>(self, mut predicate: P) -> Pipeline<S, Chain<T, impl Transform>>
+    where
+        P: FnMut(&Element) -> bool,
+    {
+        self.then(move |element: Element| predicate(&element).then_some(element))
+    }
+
+    pub fn map<F>(self, mut f: F) -> Pipeline<S, Chain<T, impl Transform>>
+    where
+        F: FnMut(Element) -> Element,
+    {
+        self.then(move |element| Some(f(element)))
+    }
+
+    /// Modifies the tags of each element
+    pub fn map_tags<F>(self, mut f: F) -> Pipeline<S, Chain<T, impl Transform>>
+    where
+        F: FnMut(&mut HashMap<TagString, TagString>),
+    {
+        self.map(move |mut element| {
+            f(element.tags_mut());
+            element
+        })
+    }
+
+    /// Removes [crate::Info] from each element
+    pub fn strip_info(self) -> Pipeline<S, Chain<T, impl Transform>> {
+        self.map(|mut element| {
+            element.strip_info();
+            element
+        })
+    }
+
+    /// Runs the pipeline until the source is exhausted or an error occurs
+    ///
+    /// Returns the number of elements written. The sink is only finished if all elements were read and written.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn write<K: Sink>(mut self, mut sink: K) -> Result<u64, PipelineError<S::Error, K::Error>> {
+        let mut written = 0;
+        #[cfg(feature = "tracing")]
+        let mut read = 0u64;
+        while let Some(element) = self.source.read() {
+            let element = element.map_err(PipelineError::Source)?;
+            #[cfg(feature = "tracing")]
+            {
+                read += 1;
+            }
+            if let Some(element) = self.transform.apply(element) {
+                sink.write(element).map_err(PipelineError::Sink)?;
+                written += 1;
+            }
+        }
+        sink.finish().map_err(PipelineError::Sink)?;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(read, written, "pipeline finished");
+        Ok(written)
+    }
+
+    /// Collects the output into a [Vec]
+    pub fn into_vec(self) -> Result<Vec<Element>, S::Error> {
+        let mut elements = vec![];
+        self.write(&mut elements).map_err(|err| match err {
+            PipelineError::Source(err) => err,
+            PipelineError::Sink(never) => match never {},
+        })?;
+        Ok(elements)
+    }
+}
+
+impl<S: Source, T: Transform> Iterator for Pipeline<S, T> {
+    type Item = Result<Element, S::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.source.read()? {
+                Ok(element) => {
+                    if let Some(element) = self.transform.apply(element) {
+                        return Some(Ok(element));
+                    }
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+/// Yields the elements of [Iterator::next] when polled
+///
+/// Reading is not asynchronous, so a source doing blocking I/O blocks the task polling it.
+/// Input that arrives asynchronously, e.g. a response body, is decoded with [crate::feed].
+#[cfg(feature = "stream")]
+impl<S, T> futures_core::Stream for Pipeline<S, T>
+where
+    S: Source + Unpin,
+    T: Transform + Unpin,
+{
+    type Item = Result<Element, S::Error>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        std::task::Poll::Ready(self.get_mut().next())
+    }
+}
+
+impl<K: Sink + ?Sized> Sink for &mut K {
+    type Error = K::Error;
+
+    fn write(&mut self, element: Element) -> Result<(), Self::Error> {
+        (**self).write(element)
+    }
+
+    fn finish(&mut self) -> Result<(), Self::Error> {
+        (**self).finish()
+    }
+}
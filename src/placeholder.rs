@@ -0,0 +1,111 @@
+//! Placeholder ids of elements that have not been uploaded yet
+//!
+//! Editors give new elements negative ids, which are replaced with the ids assigned by the
+//! server once the [changes are uploaded](https://wiki.openstreetmap.org/wiki/API_v0.6#Diff_upload:_POST_/api/0.6/changeset/#id/upload).
+//! Ids are never reused, so an allocator only counts down.
+
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+
+use crate::{Element, ElementId, Id};
+
+impl Id {
+    /// Whether the id is negative, which the server never assigns
+    pub fn is_placeholder(&self) -> bool {
+        self.0 < 0
+    }
+}
+
+impl ElementId {
+    pub fn is_placeholder(&self) -> bool {
+        self.id().is_placeholder()
+    }
+}
+
+/// Hands out decreasing placeholder ids, starting at -1
+///
+/// A single allocator serves every [MemberType](crate::MemberType) like in JOSM, so that
+/// placeholders are unique even without their type.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PlaceholderAllocator {
+    next: i64,
+}
+
+impl Default for PlaceholderAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlaceholderAllocator {
+    pub fn new() -> Self {
+        Self { next: -1 }
+    }
+
+    /// Allocator that continues below every placeholder of the elements, e.g. those of a
+    /// document saved before upload
+    pub fn after<'a>(elements: impl IntoIterator<Item = &'a Element>) -> Self {
+        let mut allocator = Self::new();
+        for element in elements {
+            allocator.reserve(element.id());
+            match element {
+                Element::Node(_) => {}
+                Element::Way(way) => way.refs.iter().for_each(|&id| allocator.reserve(id)),
+                Element::Relation(relation) => relation
+                    .members
+                    .iter()
+                    .for_each(|member| allocator.reserve(member.id)),
+            }
+        }
+        allocator
+    }
+
+    /// Ensures that `id` is never handed out
+    pub fn reserve(&mut self, id: Id) {
+        if id.0 <= self.next {
+            self.next = id.0 - 1;
+        }
+    }
+
+    /// Next unused placeholder
+    pub fn next_id(&mut self) -> Id {
+        let id = Id(self.next);
+        self.next -= 1;
+        id
+    }
+}
+
+/// Rewrites placeholders to the ids in `ids`, including the refs of ways and the members of
+/// relations
+///
+/// Ids missing from `ids` are left as they are.
+pub fn remap_ids<'a, S: BuildHasher>(
+    elements: impl IntoIterator<Item = &'a mut Element>,
+    ids: &HashMap<ElementId, Id, S>,
+) {
+    let remap = |id: &mut Id, element: ElementId| {
+        if let Some(&new) = ids.get(&element).filter(|_| id.is_placeholder()) {
+            *id = new;
+        }
+    };
+    for element in elements {
+        let element_id = element.element_id();
+        match element {
+            Element::Node(node) => remap(&mut node.id, element_id),
+            Element::Way(way) => {
+                remap(&mut way.id, element_id);
+                for id in &mut way.refs {
+                    let node = ElementId::Node((*id).into());
+                    remap(id, node);
+                }
+            }
+            Element::Relation(relation) => {
+                remap(&mut relation.id, element_id);
+                for member in &mut relation.members {
+                    let element = member.element_id();
+                    remap(&mut member.id, element);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,399 @@
+//! Encoding for bulk loading into PostgreSQL with PostGIS
+//!
+//! Geometries are encoded as [EWKB](https://postgis.net/docs/using_postgis_dbmanagement.html#EWKB_EWKT)
+//! in WGS 84 (SRID 4326), which `COPY` accepts as hex in text format. Tags are encoded as
+//! `hstore` or `jsonb` literals, which [parse_hstore] and [parse_json] read back. [slim] reads
+//! and writes the tables of osm2pgsql in slim mode.
+
+use std::fmt::Write;
+
+use fnv::FnvHashMap as HashMap;
+
+use crate::geom::LatLon;
+use crate::scalar::ScalarExt;
+use crate::{Node, TagString, Tags};
+
+pub mod slim;
+
+/// Spatial reference id of WGS 84, used by OSM
+pub const SRID: u32 = 4326;
+
+const SRID_FLAG: u32 = 0x2000_0000;
+
+const POINT: u32 = 1;
+const LINE_STRING: u32 = 2;
+const POLYGON: u32 = 3;
+const MULTI_POLYGON: u32 = 6;
+
+/// Starts a little-endian geometry, with the SRID only on the outermost one
+fn header(bytes: &mut Vec<u8>, ty: u32, with_srid: bool) {
+    bytes.push(1);
+    if with_srid {
+        bytes.extend_from_slice(&(ty | SRID_FLAG).to_le_bytes());
+        bytes.extend_from_slice(&SRID.to_le_bytes());
+    } else {
+        bytes.extend_from_slice(&ty.to_le_bytes());
+    }
+}
+
+fn coordinate(bytes: &mut Vec<u8>, (lat, lon): LatLon) {
+    // WKB orders coordinates as x, y
+    bytes.extend_from_slice(&lon.as_f64().to_le_bytes());
+    bytes.extend_from_slice(&lat.as_f64().to_le_bytes());
+}
+
+fn count(bytes: &mut Vec<u8>, n: usize) {
+    bytes.extend_from_slice(&(n as u32).to_le_bytes());
+}
+
+fn rings(bytes: &mut Vec<u8>, rings: &[Vec<LatLon>]) {
+    count(bytes, rings.len());
+    for ring in rings {
+        count(bytes, ring.len());
+        ring.iter().for_each(|c| coordinate(bytes, *c));
+    }
+}
+
+pub fn point(at: LatLon) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(25);
+    header(&mut bytes, POINT, true);
+    coordinate(&mut bytes, at);
+    bytes
+}
+
+/// Line through the resolved coordinates of a way
+pub fn line_string(line: &[LatLon]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(13 + line.len() * 16);
+    header(&mut bytes, LINE_STRING, true);
+    count(&mut bytes, line.len());
+    line.iter().for_each(|c| coordinate(&mut bytes, *c));
+    bytes
+}
+
+/// Polygon from an outer ring followed by any inner rings
+///
+/// Rings must be closed, as PostGIS rejects open rings.
+pub fn polygon(polygon: &[Vec<LatLon>]) -> Vec<u8> {
+    let mut bytes = vec![];
+    header(&mut bytes, POLYGON, true);
+    rings(&mut bytes, polygon);
+    bytes
+}
+
+/// Multipolygon from polygons as accepted by [polygon]
+pub fn multi_polygon(polygons: &[Vec<Vec<LatLon>>]) -> Vec<u8> {
+    let mut bytes = vec![];
+    header(&mut bytes, MULTI_POLYGON, true);
+    count(&mut bytes, polygons.len());
+    for polygon in polygons {
+        header(&mut bytes, POLYGON, false);
+        rings(&mut bytes, polygon);
+    }
+    bytes
+}
+
+/// Lowercase hex, as expected for geometries by `COPY` in text format
+pub fn hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(hex, "{byte:02x}");
+    }
+    hex
+}
+
+impl Node {
+    /// See [point]
+    pub fn to_ewkb(&self) -> Vec<u8> {
+        point((self.lat, self.lon))
+    }
+}
+
+fn sorted(tags: &HashMap<TagString, TagString>) -> Vec<(&TagString, &TagString)> {
+    let mut tags: Vec<_> = tags.iter().collect();
+    tags.sort_unstable();
+    tags
+}
+
+/// Tags as an `hstore` literal, e.g. `"amenity"=>"cafe", "name"=>"Sam's"`
+///
+/// Keys are sorted so that the output is stable.
+pub fn hstore(tags: &HashMap<TagString, TagString>) -> String {
+    let quote = |out: &mut String, s: &str| {
+        out.push('"');
+        for c in s.chars() {
+            if c == '"' || c == '\\' {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out.push('"');
+    };
+    let mut out = String::new();
+    for (i, (key, value)) in sorted(tags).into_iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        quote(&mut out, key);
+        out.push_str("=>");
+        quote(&mut out, value);
+    }
+    out
+}
+
+/// Tags as a JSON object for a `jsonb` column, e.g. `{"amenity":"cafe"}`
+///
+/// Keys are sorted so that the output is stable.
+pub fn json(tags: &HashMap<TagString, TagString>) -> String {
+    let mut out = String::from("{");
+    for (i, (key, value)) in sorted(tags).into_iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        json_string(&mut out, key);
+        out.push(':');
+        json_string(&mut out, value);
+    }
+    out.push('}');
+    out
+}
+
+fn json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if u32::from(c) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Reads an `hstore` literal as written by [hstore] or PostgreSQL
+///
+/// Keys with a `NULL` value are skipped. Returns [None] if the literal is malformed.
+pub fn parse_hstore(literal: &str) -> Option<Tags> {
+    let mut reader = Reader(literal);
+    let mut tags = Tags::new();
+    reader.skip_whitespace();
+    while !reader.0.is_empty() {
+        let key = reader.hstore_string()?;
+        reader.skip_whitespace();
+        reader.expect("=>")?;
+        reader.skip_whitespace();
+        if reader.0.starts_with("NULL") {
+            reader.0 = &reader.0[4..];
+        } else {
+            let value = reader.hstore_string()?;
+            tags.insert(key.into(), value.into());
+        }
+        reader.skip_whitespace();
+        if reader.expect(",").is_some() {
+            reader.skip_whitespace();
+        } else if !reader.0.is_empty() {
+            return None;
+        }
+    }
+    Some(tags)
+}
+
+/// Reads a JSON object of strings as written by [json] or PostgreSQL for `jsonb`
+///
+/// Returns [None] if the object is malformed or has values that are not strings.
+pub fn parse_json(object: &str) -> Option<Tags> {
+    let mut reader = Reader(object);
+    let mut tags = Tags::new();
+    reader.json_object(|reader, key| {
+        tags.insert(key.into(), reader.json_string()?.into());
+        Some(())
+    })?;
+    reader.end()?;
+    Some(tags)
+}
+
+/// Four hex digits of a `\u` escape
+fn hex_unit(chars: &mut impl Iterator<Item = (usize, char)>) -> Option<u32> {
+    let digits: String = chars.take(4).map(|(_, c)| c).collect();
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(&digits, 16).ok()
+}
+
+/// Cursor over a literal
+struct Reader<'a>(&'a str);
+
+impl Reader<'_> {
+    fn skip_whitespace(&mut self) {
+        self.0 = self.0.trim_start();
+    }
+
+    fn expect(&mut self, token: &str) -> Option<()> {
+        self.0 = self.0.strip_prefix(token)?;
+        Some(())
+    }
+
+    /// Succeeds if only whitespace is left
+    fn end(&mut self) -> Option<()> {
+        self.skip_whitespace();
+        self.0.is_empty().then_some(())
+    }
+
+    fn hstore_string(&mut self) -> Option<String> {
+        self.expect("\"")?;
+        let mut out = String::new();
+        let mut chars = self.0.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.0 = &self.0[i + 1..];
+                    return Some(out);
+                }
+                '\\' => out.push(chars.next()?.1),
+                c => out.push(c),
+            }
+        }
+        None
+    }
+
+    fn json_string(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        self.expect("\"")?;
+        let mut out = String::new();
+        let mut chars = self.0.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.0 = &self.0[i + 1..];
+                    return Some(out);
+                }
+                '\\' => {
+                    let escaped = match chars.next()?.1 {
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => {
+                            let unit = hex_unit(&mut chars)?;
+                            if (0xd800..0xdc00).contains(&unit) {
+                                // High surrogate followed by an escaped low one
+                                if chars.next()?.1 != '\\' || chars.next()?.1 != 'u' {
+                                    return None;
+                                }
+                                let low = hex_unit(&mut chars)
+                                    .filter(|low| (0xdc00..0xe000).contains(low))?;
+                                char::from_u32(0x10000 + ((unit - 0xd800) << 10) + (low - 0xdc00))?
+                            } else {
+                                char::from_u32(unit)?
+                            }
+                        }
+                        c => c,
+                    };
+                    out.push(escaped);
+                }
+                c => out.push(c),
+            }
+        }
+        None
+    }
+
+    fn json_integer(&mut self) -> Option<i64> {
+        self.skip_whitespace();
+        let end = self
+            .0
+            .find(|c: char| !(c.is_ascii_digit() || c == '-'))
+            .unwrap_or(self.0.len());
+        let value = self.0[..end].parse().ok()?;
+        self.0 = &self.0[end..];
+        Some(value)
+    }
+
+    /// Reads an array, calling `item` to read each item
+    fn json_array(&mut self, mut item: impl FnMut(&mut Self) -> Option<()>) -> Option<()> {
+        self.skip_whitespace();
+        self.expect("[")?;
+        self.skip_whitespace();
+        if self.expect("]").is_some() {
+            return Some(());
+        }
+        loop {
+            item(self)?;
+            self.skip_whitespace();
+            if self.expect("]").is_some() {
+                return Some(());
+            }
+            self.expect(",")?;
+        }
+    }
+
+    /// Reads an object, calling `field` to read the value of each key
+    fn json_object(
+        &mut self,
+        mut field: impl FnMut(&mut Self, String) -> Option<()>,
+    ) -> Option<()> {
+        self.skip_whitespace();
+        self.expect("{")?;
+        self.skip_whitespace();
+        if self.expect("}").is_some() {
+            return Some(());
+        }
+        loop {
+            let key = self.json_string()?;
+            self.skip_whitespace();
+            self.expect(":")?;
+            field(self, key)?;
+            self.skip_whitespace();
+            if self.expect("}").is_some() {
+                return Some(());
+            }
+            self.expect(",")?;
+        }
+    }
+}
+
+/// Reverses [copy_escape] and the escapes PostgreSQL writes with `COPY` in text format
+///
+/// `\N`, which stands for `NULL`, has to be checked for first.
+pub fn copy_unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('b') => out.push('\u{8}'),
+            Some('f') => out.push('\u{c}'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('v') => out.push('\u{b}'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Escapes a value for a column of `COPY` in text format
+pub fn copy_escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
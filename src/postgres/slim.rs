@@ -0,0 +1,339 @@
+//! Middle tables of [osm2pgsql](https://osm2pgsql.org/doc/manual.html#the-middle) in slim mode
+//!
+//! In slim mode, osm2pgsql keeps the imported elements in `planet_osm_nodes`,
+//! `planet_osm_ways`, and `planet_osm_rels` to update geometries from diffs. [schema] creates
+//! these tables in the format of osm2pgsql 1.9 and later, and the row functions convert
+//! elements to and from lines of `COPY` in text format, e.g. `COPY planet_osm_ways FROM STDIN`
+//! to fill a database or `COPY planet_osm_ways TO STDOUT` to read one.
+//!
+//! Coordinates are 32-bit integers in fixed point with 7 decimal places, and tags and members
+//! are `jsonb`. With `attributes`, as written by osm2pgsql with `--extra-attributes`, rows also
+//! hold the timestamp, version, changeset, and uid of each element. User names are kept in a
+//! separate table, which is not covered here. Untagged nodes usually live in a flatnodes file
+//! instead, see [DenseNodeStore](crate::store::DenseNodeStore).
+
+use std::fmt::Write as _;
+use std::str::FromStr;
+
+use chrono::DateTime;
+
+use super::{copy_escape, copy_unescape, json, json_string, parse_json, Reader};
+use crate::error::{Error, Result};
+use crate::locations::{from_fixed, to_fixed};
+use crate::{timestamp_from_utc, Id, Info, Member, MemberType, Node, Refs, Relation, Tags, Way};
+
+/// Prefix of the tables osm2pgsql creates by default
+pub const DEFAULT_PREFIX: &str = "planet_osm";
+
+/// Columns of the attributes, placed after the coordinates of nodes and before the other data
+const ATTRIBUTES: &str =
+    "created timestamp with time zone, version int4, changeset_id int4, user_id int4, ";
+
+/// Statements creating the tables `{prefix}_nodes`, `{prefix}_ways`, and `{prefix}_rels`
+pub fn schema(prefix: &str, attributes: bool) -> String {
+    let attributes = if attributes { ATTRIBUTES } else { "" };
+    format!(
+        "CREATE TABLE {prefix}_nodes (id int8 PRIMARY KEY NOT NULL, lat int4 NOT NULL, \
+         lon int4 NOT NULL, {attributes}tags jsonb);\n\
+         CREATE TABLE {prefix}_ways (id int8 PRIMARY KEY NOT NULL, {attributes}\
+         nodes int8[] NOT NULL, tags jsonb);\n\
+         CREATE TABLE {prefix}_rels (id int8 PRIMARY KEY NOT NULL, {attributes}\
+         members jsonb NOT NULL, tags jsonb);\n"
+    )
+}
+
+/// Row of `{prefix}_nodes`, [None] if a coordinate does not fit in 32-bit fixed point
+pub fn node_row(node: &Node, attributes: bool) -> Option<String> {
+    let mut row = format!(
+        "{}\t{}\t{}\t",
+        node.id.0,
+        to_fixed(node.lat)?,
+        to_fixed(node.lon)?
+    );
+    if attributes {
+        attribute_columns(&mut row, node.info.as_ref());
+    }
+    tags_column(&mut row, &node.tags);
+    Some(row)
+}
+
+/// Row of `{prefix}_ways`
+pub fn way_row(way: &Way, attributes: bool) -> String {
+    let mut row = format!("{}\t", way.id.0);
+    if attributes {
+        attribute_columns(&mut row, way.info.as_ref());
+    }
+    row.push('{');
+    for (i, id) in way.refs.iter().enumerate() {
+        if i > 0 {
+            row.push(',');
+        }
+        let _ = write!(row, "{}", id.0);
+    }
+    row.push_str("}\t");
+    tags_column(&mut row, &way.tags);
+    row
+}
+
+/// Row of `{prefix}_rels`, with members such as `{"type":"W","ref":1,"role":"outer"}`
+pub fn relation_row(relation: &Relation, attributes: bool) -> String {
+    let mut row = format!("{}\t", relation.id.0);
+    if attributes {
+        attribute_columns(&mut row, relation.info.as_ref());
+    }
+    let mut members = String::from("[");
+    for (i, member) in relation.members.iter().enumerate() {
+        if i > 0 {
+            members.push(',');
+        }
+        let _ = write!(
+            members,
+            "{{\"type\":\"{}\",\"ref\":{},\"role\":",
+            type_letter(&member.ty),
+            member.id.0
+        );
+        json_string(&mut members, member.role.as_deref().unwrap_or(""));
+        members.push('}');
+    }
+    members.push(']');
+    row.push_str(&copy_escape(&members));
+    row.push('\t');
+    tags_column(&mut row, &relation.tags);
+    row
+}
+
+fn type_letter(ty: &MemberType) -> char {
+    match ty {
+        MemberType::Node => 'N',
+        MemberType::Way => 'W',
+        MemberType::Relation => 'R',
+    }
+}
+
+fn attribute_columns(row: &mut String, info: Option<&Info>) {
+    let null = |row: &mut String| row.push_str("\\N\t");
+    match info.and_then(Info::timestamp_utc) {
+        Some(time) => {
+            let _ = write!(row, "{}\t", time.format("%Y-%m-%d %H:%M:%S+00"));
+        }
+        None => null(row),
+    }
+    match info {
+        Some(info) => {
+            let _ = write!(row, "{}\t", info.version);
+        }
+        None => null(row),
+    }
+    for value in [
+        info.and_then(|info| info.changeset),
+        info.and_then(|info| info.uid.map(i64::from)),
+    ] {
+        match value {
+            Some(value) => {
+                let _ = write!(row, "{value}\t");
+            }
+            None => null(row),
+        }
+    }
+}
+
+/// Tags as the last column, `NULL` if there are none
+fn tags_column(row: &mut String, tags: &Tags) {
+    if tags.is_empty() {
+        row.push_str("\\N");
+    } else {
+        row.push_str(&copy_escape(&json(tags)));
+    }
+}
+
+/// Splits a row into columns, [None] for `NULL`
+fn columns(row: &str) -> impl Iterator<Item = Option<String>> + '_ {
+    row.trim_end_matches(['\r', '\n'])
+        .split('\t')
+        .map(|column| (column != "\\N").then(|| copy_unescape(column)))
+}
+
+/// Reads the id of a row, and passes its other columns and an error constructor to `rest`
+fn read_row<T>(
+    row: &str,
+    ty: MemberType,
+    rest: impl FnOnce(Id, &mut dyn Iterator<Item = Option<String>>, &dyn Fn(&str) -> Error) -> Result<T>,
+) -> Result<T> {
+    let mut columns = columns(row);
+    let id = columns
+        .next()
+        .flatten()
+        .and_then(|id| id.parse().ok())
+        .map(Id)
+        .ok_or_else(|| Error::decode(None, "invalid id column".to_string()))?;
+    let error =
+        |message: &str| Error::decode(None, message.to_string()).with_element(ty.clone(), id);
+    let element = rest(id, &mut columns, &error)?;
+    if columns.next().is_some() {
+        return Err(error("too many columns"));
+    }
+    Ok(element)
+}
+
+fn read_attributes(
+    columns: &mut dyn Iterator<Item = Option<String>>,
+    error: &dyn Fn(&str) -> Error,
+) -> Result<Option<Info>> {
+    let mut next = || {
+        columns
+            .next()
+            .ok_or_else(|| error("missing attribute columns"))
+    };
+    let created = next()?;
+    let version = next()?;
+    let changeset = next()?;
+    let uid = next()?;
+    if created.is_none() && version.is_none() && changeset.is_none() && uid.is_none() {
+        return Ok(None);
+    }
+    let timestamp = created
+        .map(|created| {
+            DateTime::parse_from_str(&created, "%Y-%m-%d %H:%M:%S%.f%#z")
+                .map(|time| timestamp_from_utc(time.to_utc()))
+                .map_err(|_| error("invalid created column"))
+        })
+        .transpose()?;
+    Ok(Some(Info {
+        version: number(version, "version", error)?.unwrap_or(0),
+        timestamp,
+        changeset: number(changeset, "changeset_id", error)?,
+        uid: number(uid, "user_id", error)?,
+        user: None,
+        visible: None,
+    }))
+}
+
+fn number<T: FromStr>(
+    column: Option<String>,
+    name: &str,
+    error: &dyn Fn(&str) -> Error,
+) -> Result<Option<T>> {
+    column
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| error(&format!("invalid {name} column")))
+        })
+        .transpose()
+}
+
+fn read_tags(
+    columns: &mut dyn Iterator<Item = Option<String>>,
+    error: &dyn Fn(&str) -> Error,
+) -> Result<Tags> {
+    match columns.next() {
+        Some(Some(tags)) => parse_json(&tags).ok_or_else(|| error("invalid tags column")),
+        Some(None) => Ok(Tags::new()),
+        None => Err(error("missing tags column")),
+    }
+}
+
+/// Reads a row of `{prefix}_nodes`, e.g. from `COPY planet_osm_nodes TO STDOUT`
+pub fn parse_node_row(row: &str, attributes: bool) -> Result<Node> {
+    read_row(row, MemberType::Node, |id, columns, error| {
+        let mut coordinate = |name: &str| {
+            columns
+                .next()
+                .flatten()
+                .and_then(|value| value.parse().ok())
+                .map(from_fixed)
+                .ok_or_else(|| error(&format!("invalid {name} column")))
+        };
+        let lat = coordinate("lat")?;
+        let lon = coordinate("lon")?;
+        let info = if attributes {
+            read_attributes(columns, error)?
+        } else {
+            None
+        };
+        Ok(Node {
+            id,
+            tags: read_tags(columns, error)?,
+            info,
+            lat,
+            lon,
+        })
+    })
+}
+
+/// Reads a row of `{prefix}_ways`
+pub fn parse_way_row(row: &str, attributes: bool) -> Result<Way> {
+    read_row(row, MemberType::Way, |id, columns, error| {
+        let info = if attributes {
+            read_attributes(columns, error)?
+        } else {
+            None
+        };
+        let invalid = || error("invalid nodes column");
+        let nodes = columns.next().flatten().ok_or_else(invalid)?;
+        let nodes = nodes
+            .strip_prefix('{')
+            .and_then(|nodes| nodes.strip_suffix('}'))
+            .ok_or_else(invalid)?;
+        let refs = nodes
+            .split(',')
+            .filter(|id| !id.is_empty())
+            .map(|id| id.trim().parse().map(Id).map_err(|_| invalid()))
+            .collect::<Result<Refs>>()?;
+        Ok(Way {
+            id,
+            tags: read_tags(columns, error)?,
+            info,
+            refs,
+        })
+    })
+}
+
+/// Reads a row of `{prefix}_rels`
+pub fn parse_relation_row(row: &str, attributes: bool) -> Result<Relation> {
+    read_row(row, MemberType::Relation, |id, columns, error| {
+        let info = if attributes {
+            read_attributes(columns, error)?
+        } else {
+            None
+        };
+        let invalid = || error("invalid members column");
+        let json = columns.next().flatten().ok_or_else(invalid)?;
+        let mut members = Vec::new();
+        let mut reader = Reader(&json);
+        reader
+            .json_array(|reader| {
+                let (mut ty, mut id, mut role) = (None, None, None);
+                reader.json_object(|reader, key| {
+                    match key.as_str() {
+                        "type" => {
+                            ty = Some(match reader.json_string()?.as_str() {
+                                "N" => MemberType::Node,
+                                "W" => MemberType::Way,
+                                "R" => MemberType::Relation,
+                                _ => return None,
+                            })
+                        }
+                        "ref" => id = Some(Id(reader.json_integer()?)),
+                        "role" => role = Some(reader.json_string()?),
+                        _ => return None,
+                    }
+                    Some(())
+                })?;
+                members.push(Member {
+                    id: id?,
+                    ty: ty?,
+                    role: role.filter(|role| !role.is_empty()).map(Into::into),
+                });
+                Some(())
+            })
+            .and_then(|()| reader.end())
+            .ok_or_else(invalid)?;
+        Ok(Relation {
+            id,
+            tags: read_tags(columns, error)?,
+            info,
+            members: members.into_iter().collect(),
+        })
+    })
+}
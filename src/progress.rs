@@ -0,0 +1,142 @@
+//! Progress and metrics reporting for readers
+//!
+//! Readers report what they consume to an [Observer], e.g. to render a progress bar in a CLI
+//! or to export metrics from a service. [Counters] keeps running totals that can be read
+//! from another thread while reading is in progress.
+
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::pipeline::Source;
+use crate::Element;
+
+/// Receives progress from a reader
+///
+/// All methods do nothing by default, so implementations only override what they need.
+pub trait Observer {
+    /// Called after `bytes` more bytes of input were read
+    fn bytes_read(&mut self, bytes: u64) {
+        let _ = bytes;
+    }
+
+    /// Called after a block of input was decoded, e.g. a PBF blob
+    fn block_decoded(&mut self) {}
+
+    /// Called for each element read
+    fn element_read(&mut self, element: &Element) {
+        let _ = element;
+    }
+}
+
+/// [Observer] that ignores all progress
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl Observer for NoProgress {}
+
+impl<O: Observer + ?Sized> Observer for &mut O {
+    fn bytes_read(&mut self, bytes: u64) {
+        (**self).bytes_read(bytes)
+    }
+
+    fn block_decoded(&mut self) {
+        (**self).block_decoded()
+    }
+
+    fn element_read(&mut self, element: &Element) {
+        (**self).element_read(element)
+    }
+}
+
+/// Running totals of reader progress
+#[derive(Debug, Default)]
+pub struct Counters {
+    pub bytes: AtomicU64,
+    pub blocks: AtomicU64,
+    pub nodes: AtomicU64,
+    pub ways: AtomicU64,
+    pub relations: AtomicU64,
+}
+
+impl Counters {
+    pub fn elements(&self) -> u64 {
+        self.nodes.load(Ordering::Relaxed)
+            + self.ways.load(Ordering::Relaxed)
+            + self.relations.load(Ordering::Relaxed)
+    }
+}
+
+impl Observer for &Counters {
+    fn bytes_read(&mut self, bytes: u64) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn block_decoded(&mut self) {
+        self.blocks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn element_read(&mut self, element: &Element) {
+        let counter = match element {
+            Element::Node(_) => &self.nodes,
+            Element::Way(_) => &self.ways,
+            Element::Relation(_) => &self.relations,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Reports bytes passing through an [io::Read] to an [Observer]
+///
+/// Wrapping the input of a reader reports bytes read even if the reader itself does not.
+#[derive(Debug)]
+pub struct ObservedRead<R, O> {
+    inner: R,
+    observer: O,
+}
+
+impl<R: Read, O: Observer> ObservedRead<R, O> {
+    pub fn new(inner: R, observer: O) -> Self {
+        Self { inner, observer }
+    }
+
+    pub fn into_inner(self) -> (R, O) {
+        (self.inner, self.observer)
+    }
+}
+
+impl<R: Read, O: Observer> Read for ObservedRead<R, O> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.observer.bytes_read(read as u64);
+        Ok(read)
+    }
+}
+
+/// Reports elements produced by a [Source] to an [Observer]
+#[derive(Debug)]
+pub struct ObservedSource<S, O> {
+    inner: S,
+    observer: O,
+}
+
+impl<S: Source, O: Observer> ObservedSource<S, O> {
+    pub fn new(inner: S, observer: O) -> Self {
+        Self { inner, observer }
+    }
+
+    pub fn into_inner(self) -> (S, O) {
+        (self.inner, self.observer)
+    }
+}
+
+impl<S: Source, O: Observer> Iterator for ObservedSource<S, O> {
+    type Item = Result<Element, S::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let element = self.inner.read()?;
+        if let Ok(element) = &element {
+            self.observer.element_read(element);
+        }
+        Some(element)
+    }
+}
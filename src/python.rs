@@ -0,0 +1,300 @@
+//! Python bindings
+//!
+//! Elements are exposed as the `Node`, `Way`, and `Relation` classes, which share an `Element`
+//! base class. Tags are accessed like a `dict` on the element itself, e.g. `way["highway"]`.
+//!
+//! Only elements are exposed so far; the crate does not yet have stores or readers to wrap.
+//!
+//! To build the extension module, enable the `pyo3` feature and build a `cdylib`, e.g. with
+//! [maturin](https://www.maturin.rs).
+
+use std::collections::HashMap;
+
+use pyo3::exceptions::{PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyIterator, PyList};
+
+use crate::scalar::{Scalar, ScalarExt};
+use crate::{Element, Id, Member, MemberType, Node, Relation, TagString, Tags, Way};
+
+/// Base class of `Node`, `Way`, and `Relation`
+#[pyclass(name = "Element", module = "osm_types", subclass, skip_from_py_object)]
+#[derive(Debug, Clone)]
+pub struct PyElement(pub Element);
+
+#[pymethods]
+impl PyElement {
+    #[getter]
+    fn id(&self) -> i64 {
+        self.0.id().0
+    }
+
+    /// Version of the element, or `None` if it has no metadata
+    #[getter]
+    fn version(&self) -> Option<i32> {
+        self.0.info().map(|info| info.version)
+    }
+
+    /// Copy of the tags as a `dict`
+    #[getter]
+    fn tags<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        for (key, value) in self.0.tags() {
+            dict.set_item(key.as_str(), value.as_str())?;
+        }
+        Ok(dict)
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.tags().len()
+    }
+
+    fn __contains__(&self, key: &str) -> bool {
+        self.0.tags().contains_key(key)
+    }
+
+    fn __getitem__(&self, key: &str) -> PyResult<String> {
+        self.0
+            .tags()
+            .get(key)
+            .map(|value| value.to_string())
+            .ok_or_else(|| PyKeyError::new_err(key.to_string()))
+    }
+
+    fn __setitem__(&mut self, key: &str, value: &str) {
+        self.0
+            .tags_mut()
+            .insert(TagString::from_ref(key), TagString::from_ref(value));
+    }
+
+    fn __delitem__(&mut self, key: &str) -> PyResult<()> {
+        self.0
+            .tags_mut()
+            .remove(key)
+            .map(|_| ())
+            .ok_or_else(|| PyKeyError::new_err(key.to_string()))
+    }
+
+    /// Iterates over tag keys, like a `dict`
+    fn __iter__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyIterator>> {
+        PyList::new(py, self.keys())?.try_iter()
+    }
+
+    #[pyo3(signature = (key, default=None))]
+    fn get(&self, key: &str, default: Option<String>) -> Option<String> {
+        self.0
+            .tags()
+            .get(key)
+            .map(|value| value.to_string())
+            .or(default)
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.0.tags().keys().map(|key| key.to_string()).collect()
+    }
+
+    fn values(&self) -> Vec<String> {
+        self.0
+            .tags()
+            .values()
+            .map(|value| value.to_string())
+            .collect()
+    }
+
+    fn items(&self) -> Vec<(String, String)> {
+        self.0
+            .tags()
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn __eq__(&self, other: &PyElement) -> bool {
+        self.0 == other.0
+    }
+
+    fn __repr__(&self) -> String {
+        let ty = match &self.0 {
+            Element::Node(_) => "Node",
+            Element::Way(_) => "Way",
+            Element::Relation(_) => "Relation",
+        };
+        format!("{ty}(id={}, tags={})", self.0.id().0, self.0.tags().len())
+    }
+}
+
+impl PyElement {
+    /// Wraps an element in its matching Python class
+    pub fn into_py_object(element: Element, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        let init = PyClassInitializer::from(PyElement(element.clone()));
+        Ok(match element {
+            Element::Node(_) => Py::new(py, init.add_subclass(PyNode))?.into_any(),
+            Element::Way(_) => Py::new(py, init.add_subclass(PyWay))?.into_any(),
+            Element::Relation(_) => Py::new(py, init.add_subclass(PyRelation))?.into_any(),
+        })
+    }
+}
+
+fn decimal(value: f64) -> PyResult<Scalar> {
+    Scalar::try_from_f64(value)
+        .map(|value| value.round_places(7).normalized())
+        .ok_or_else(|| PyValueError::new_err("coordinate must be finite"))
+}
+
+fn member_type_from_name(name: &str) -> PyResult<MemberType> {
+    match name {
+        "node" => Ok(MemberType::Node),
+        "way" => Ok(MemberType::Way),
+        "relation" => Ok(MemberType::Relation),
+        _ => Err(PyValueError::new_err(format!(
+            "unknown member type {name:?}"
+        ))),
+    }
+}
+
+#[pyclass(name = "Node", module = "osm_types", extends = PyElement)]
+#[derive(Debug)]
+pub struct PyNode;
+
+#[pymethods]
+impl PyNode {
+    #[new]
+    #[pyo3(signature = (id, lat, lon, tags=None))]
+    fn new(
+        id: i64,
+        lat: f64,
+        lon: f64,
+        tags: Option<HashMap<String, String>>,
+    ) -> PyResult<PyClassInitializer<Self>> {
+        let node = Node {
+            id: Id(id),
+            tags: tags_from(tags),
+            info: None,
+            lat: decimal(lat)?,
+            lon: decimal(lon)?,
+        };
+        Ok(PyClassInitializer::from(PyElement(Element::Node(node))).add_subclass(PyNode))
+    }
+
+    #[getter]
+    fn lat(slf: PyRef<'_, Self>) -> f64 {
+        node(&slf).lat.as_f64()
+    }
+
+    #[getter]
+    fn lon(slf: PyRef<'_, Self>) -> f64 {
+        node(&slf).lon.as_f64()
+    }
+}
+
+fn node<'a>(slf: &'a PyRef<'_, PyNode>) -> &'a Node {
+    slf.as_super()
+        .0
+        .as_node()
+        .expect("Node class always wraps a node")
+}
+
+#[pyclass(name = "Way", module = "osm_types", extends = PyElement)]
+#[derive(Debug)]
+pub struct PyWay;
+
+#[pymethods]
+impl PyWay {
+    #[new]
+    #[pyo3(signature = (id, refs, tags=None))]
+    fn new(
+        id: i64,
+        refs: Vec<i64>,
+        tags: Option<HashMap<String, String>>,
+    ) -> PyClassInitializer<Self> {
+        let way = Way {
+            id: Id(id),
+            tags: tags_from(tags),
+            info: None,
+            refs: refs.into_iter().map(Id).collect(),
+        };
+        PyClassInitializer::from(PyElement(Element::Way(way))).add_subclass(PyWay)
+    }
+
+    /// Ids of the nodes of the way, in order
+    #[getter]
+    fn refs(slf: PyRef<'_, Self>) -> Vec<i64> {
+        let Some(way) = slf.as_super().0.as_way() else {
+            return vec![];
+        };
+        way.refs.iter().map(|id| id.0).collect()
+    }
+}
+
+#[pyclass(name = "Relation", module = "osm_types", extends = PyElement)]
+#[derive(Debug)]
+pub struct PyRelation;
+
+#[pymethods]
+impl PyRelation {
+    /// Members are given as `(type, id, role)` tuples, where type is `"node"`, `"way"`, or
+    /// `"relation"` and role may be `None`
+    #[new]
+    #[pyo3(signature = (id, members, tags=None))]
+    fn new(
+        id: i64,
+        members: Vec<(String, i64, Option<String>)>,
+        tags: Option<HashMap<String, String>>,
+    ) -> PyResult<PyClassInitializer<Self>> {
+        let members = members
+            .into_iter()
+            .map(|(ty, id, role)| {
+                Ok(Member {
+                    id: Id(id),
+                    ty: member_type_from_name(&ty)?,
+                    role: role.map(TagString::from),
+                })
+            })
+            .collect::<PyResult<_>>()?;
+        let relation = Relation {
+            id: Id(id),
+            tags: tags_from(tags),
+            info: None,
+            members,
+        };
+        Ok(
+            PyClassInitializer::from(PyElement(Element::Relation(relation)))
+                .add_subclass(PyRelation),
+        )
+    }
+
+    /// Members as `(type, id, role)` tuples
+    #[getter]
+    fn members(slf: PyRef<'_, Self>) -> Vec<(&'static str, i64, Option<String>)> {
+        let Some(relation) = slf.as_super().0.as_relation() else {
+            return vec![];
+        };
+        relation
+            .members
+            .iter()
+            .map(|member| {
+                (
+                    member.ty.as_str(),
+                    member.id.0,
+                    member.role.as_ref().map(|role| role.to_string()),
+                )
+            })
+            .collect()
+    }
+}
+
+fn tags_from(tags: Option<HashMap<String, String>>) -> Tags {
+    tags.into_iter()
+        .flatten()
+        .map(|(key, value)| (TagString::from(key), TagString::from(value)))
+        .collect()
+}
+
+#[pymodule]
+fn osm_types(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyElement>()?;
+    module.add_class::<PyNode>()?;
+    module.add_class::<PyWay>()?;
+    module.add_class::<PyRelation>()?;
+    Ok(())
+}
@@ -0,0 +1,191 @@
+//! Offline lookup of the country or region containing a coordinate
+//!
+//! A [RegionIndex] holds boundaries keyed by their
+//! [ISO 3166](https://wiki.openstreetmap.org/wiki/ISO_3166) code, e.g. `DE` for a country or
+//! `US-CA` for a subdivision, so that country-specific defaults like the driving side or
+//! speed limits can be chosen without a geocoding service. No boundaries are bundled: load
+//! them from a [boundary](https://wiki.openstreetmap.org/wiki/Tag:boundary%3Dadministrative)
+//! extract or a simplified dataset matching the accuracy needed.
+
+use fnv::FnvHashMap as HashMap;
+
+use crate::geom::{ring_contains, signed_area, LatLon};
+use crate::scalar::{Scalar, ScalarExt};
+use crate::{Node, TagString};
+
+/// Size of the cells of the index in degrees
+const CELL_SIZE: f64 = 1.;
+
+/// Area with an ISO 3166 code, made of rings of `(lat, lon)` coordinates
+///
+/// Points are inside if they are enclosed by an odd number of rings, so inner rings cut holes
+/// into outer rings and a region may have several outer rings. Rings must not cross the
+/// antimeridian, see [crate::bbox::split_at_antimeridian]. Closing a ring by repeating its
+/// first coordinate is optional.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Region {
+    pub code: TagString,
+    rings: Vec<Vec<(f64, f64)>>,
+    /// Minimum and maximum latitude and longitude
+    bounds: [f64; 4],
+    /// Planar area in square degrees, only used to rank nested regions
+    area: f64,
+}
+
+impl Region {
+    pub fn new(code: TagString, rings: impl IntoIterator<Item = Vec<LatLon>>) -> Self {
+        let rings: Vec<Vec<_>> = rings
+            .into_iter()
+            .map(|ring| {
+                ring.into_iter()
+                    .map(|(lat, lon)| (lat.as_f64(), lon.as_f64()))
+                    .collect()
+            })
+            .filter(|ring: &Vec<_>| ring.len() >= 3)
+            .collect();
+        let mut bounds = [
+            f64::INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NEG_INFINITY,
+        ];
+        for &(lat, lon) in rings.iter().flatten() {
+            bounds[0] = bounds[0].min(lat);
+            bounds[1] = bounds[1].min(lon);
+            bounds[2] = bounds[2].max(lat);
+            bounds[3] = bounds[3].max(lon);
+        }
+        let area = rings.iter().map(|ring| signed_area(ring).abs()).sum();
+        Self {
+            code,
+            rings,
+            bounds,
+            area,
+        }
+    }
+
+    /// Whether this is a country, with an ISO 3166-1 code like `DE` rather than an ISO 3166-2
+    /// code like `DE-BY`
+    pub fn is_country(&self) -> bool {
+        !self.code.contains('-')
+    }
+
+    /// ISO 3166-1 code of the country, e.g. `US` for `US-CA`
+    pub fn country_code(&self) -> &str {
+        self.code.split('-').next().unwrap_or_default()
+    }
+
+    pub fn contains(&self, lat: Scalar, lon: Scalar) -> bool {
+        let (lat, lon) = (lat.as_f64(), lon.as_f64());
+        let [min_lat, min_lon, max_lat, max_lon] = self.bounds;
+        if lat < min_lat || lat > max_lat || lon < min_lon || lon > max_lon {
+            return false;
+        }
+        let crossings = self
+            .rings
+            .iter()
+            .filter(|ring| ring_contains(ring, lat, lon))
+            .count();
+        crossings % 2 == 1
+    }
+}
+
+/// [Region]s indexed by a grid of cells for fast lookups
+#[derive(Debug, Clone, Default)]
+pub struct RegionIndex {
+    regions: Vec<Region>,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl RegionIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, region: Region) {
+        let index = self.regions.len();
+        let [min_lat, min_lon, max_lat, max_lon] = region.bounds;
+        if min_lat <= max_lat {
+            let (min_cell, max_cell) = (cell(min_lat, min_lon), cell(max_lat, max_lon));
+            for row in min_cell.0..=max_cell.0 {
+                for column in min_cell.1..=max_cell.1 {
+                    self.cells.entry((row, column)).or_default().push(index);
+                }
+            }
+        }
+        self.regions.push(region);
+    }
+
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// Regions containing the coordinate, in the order they were inserted
+    pub fn regions_at(&self, lat: Scalar, lon: Scalar) -> impl Iterator<Item = &Region> {
+        self.cells
+            .get(&cell(lat.as_f64(), lon.as_f64()))
+            .into_iter()
+            .flatten()
+            .map(|&index| &self.regions[index])
+            .filter(move |region| region.contains(lat, lon))
+    }
+
+    /// Most specific region containing the coordinate, e.g. `US-CA` rather than `US`
+    ///
+    /// Of overlapping regions, the one with the smallest area is returned.
+    pub fn region_at(&self, lat: Scalar, lon: Scalar) -> Option<&Region> {
+        self.regions_at(lat, lon)
+            .min_by(|a, b| a.area.total_cmp(&b.area))
+    }
+
+    /// ISO 3166-1 code of the country containing the coordinate
+    ///
+    /// This also finds the country of a subdivision when only subdivisions were inserted.
+    pub fn country_at(&self, lat: Scalar, lon: Scalar) -> Option<&str> {
+        let mut regions = self.regions_at(lat, lon).peekable();
+        let first = regions.peek().copied();
+        regions
+            .find(|region| region.is_country())
+            .or(first)
+            .map(Region::country_code)
+    }
+}
+
+fn cell(lat: f64, lon: f64) -> (i32, i32) {
+    (
+        (lat / CELL_SIZE).floor() as i32,
+        (lon / CELL_SIZE).floor() as i32,
+    )
+}
+
+impl Extend<Region> for RegionIndex {
+    fn extend<I: IntoIterator<Item = Region>>(&mut self, regions: I) {
+        for region in regions {
+            self.insert(region);
+        }
+    }
+}
+
+impl FromIterator<Region> for RegionIndex {
+    fn from_iter<I: IntoIterator<Item = Region>>(regions: I) -> Self {
+        let mut index = Self::new();
+        index.extend(regions);
+        index
+    }
+}
+
+impl Node {
+    /// ISO 3166-1 code of the country the node lies in, see [RegionIndex::country_at]
+    pub fn country<'a>(&self, index: &'a RegionIndex) -> Option<&'a str> {
+        index.country_at(self.lat, self.lon)
+    }
+
+    /// Most specific region the node lies in, see [RegionIndex::region_at]
+    pub fn region<'a>(&self, index: &'a RegionIndex) -> Option<&'a Region> {
+        index.region_at(self.lat, self.lon)
+    }
+}
@@ -0,0 +1,115 @@
+//! Renumbering of elements to dense positive ids, like
+//! [osmium renumber](https://docs.osmcode.org/osmium/latest/osmium-renumber.html)
+//!
+//! Each [MemberType] counts up on its own, and every id gets its new id the first time it is
+//! seen, whether as an element or a reference. Way refs and relation members thus stay
+//! consistent with the elements they point to, even when they come first, e.g. a relation
+//! with a relation member further down the file.
+
+use fnv::FnvHashMap as HashMap;
+
+use crate::store::ElementStore;
+use crate::{Element, Id, MemberType};
+
+/// Assigns new ids and remembers them, so that several collections can be renumbered
+/// consistently
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Renumberer {
+    /// Next id of nodes, ways, and relations
+    next: [i64; 3],
+    ids: [HashMap<Id, Id>; 3],
+}
+
+impl Default for Renumberer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn index(ty: &MemberType) -> usize {
+    match ty {
+        MemberType::Node => 0,
+        MemberType::Way => 1,
+        MemberType::Relation => 2,
+    }
+}
+
+impl Renumberer {
+    /// Renumberer starting at 1 for each type
+    pub fn new() -> Self {
+        Self::starting_at(1, 1, 1)
+    }
+
+    /// Renumberer starting at the given ids, e.g. above the ids of another dataset
+    pub fn starting_at(node: i64, way: i64, relation: i64) -> Self {
+        Self {
+            next: [node, way, relation],
+            ids: Default::default(),
+        }
+    }
+
+    /// New id of an element, assigning the next one if it has none yet
+    pub fn id(&mut self, ty: &MemberType, id: Id) -> Id {
+        let index = index(ty);
+        let next = &mut self.next[index];
+        *self.ids[index].entry(id).or_insert_with(|| {
+            let new = Id(*next);
+            *next += 1;
+            new
+        })
+    }
+
+    /// New id of an element, [None] if it has not been seen
+    pub fn get(&self, ty: &MemberType, id: Id) -> Option<Id> {
+        self.ids[index(ty)].get(&id).copied()
+    }
+
+    /// Old ids of the elements of a type to their new ids
+    pub fn mapping(&self, ty: &MemberType) -> &HashMap<Id, Id> {
+        &self.ids[index(ty)]
+    }
+
+    /// Rewrites the id of the element and its way refs or relation members
+    pub fn renumber(&mut self, element: &mut Element) {
+        match element {
+            Element::Node(node) => node.id = self.id(&MemberType::Node, node.id),
+            Element::Way(way) => {
+                way.id = self.id(&MemberType::Way, way.id);
+                for id in way.refs.iter_mut() {
+                    *id = self.id(&MemberType::Node, *id);
+                }
+            }
+            Element::Relation(relation) => {
+                relation.id = self.id(&MemberType::Relation, relation.id);
+                for member in relation.members.iter_mut() {
+                    member.id = self.id(&member.ty, member.id);
+                }
+            }
+        }
+    }
+
+    /// See [Renumberer::renumber]
+    pub fn renumber_all<'a>(&mut self, elements: impl IntoIterator<Item = &'a mut Element>) {
+        for element in elements {
+            self.renumber(element);
+        }
+    }
+}
+
+impl ElementStore {
+    /// Copy of the store renumbered in the order of [ElementStore::iter_sorted]
+    ///
+    /// Ids referenced but missing from the store still take up a number, so the ids of the
+    /// elements have gaps where references dangle.
+    pub fn renumbered(&self) -> (ElementStore, Renumberer) {
+        let mut renumberer = Renumberer::new();
+        let store = self
+            .iter_sorted()
+            .map(|mut element| {
+                renumberer.renumber(&mut element);
+                element
+            })
+            .collect();
+        (store, renumberer)
+    }
+}
@@ -0,0 +1,188 @@
+//! [Replication](https://wiki.openstreetmap.org/wiki/Planet.osm/diffs) of osmChange diffs
+//!
+//! Each diff of a replication stream has a sequence number, and is published at a path derived
+//! from it, e.g. `replication/minute/005/804/287.osc.gz`, next to a `state.txt` with the time
+//! of the newest change it holds. The `state.txt` at the root of the stream names the latest
+//! diff. Updaters keep the [ReplicationState] they applied last, and find the diffs to apply
+//! next from it.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::error::{Error, Result};
+use crate::{timestamp_from_utc, timestamp_to_utc, Timestamp};
+
+/// Address of the replication streams on the main OSM server
+pub const DEFAULT_BASE: &str = "https://planet.openstreetmap.org/replication";
+
+/// Contents of a `state.txt`
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct ReplicationState {
+    pub sequence_number: u64,
+    /// Time of the newest change in the diff
+    pub timestamp: Timestamp,
+}
+
+impl ReplicationState {
+    /// Reads the properties of a `state.txt`, skipping comments and other keys
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut sequence_number = None;
+        let mut timestamp = None;
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once(['=', ':']) else {
+                continue;
+            };
+            // Java properties escape the colons of the timestamp
+            let value = value.trim().replace('\\', "");
+            match key.trim() {
+                "sequenceNumber" => {
+                    sequence_number = Some(value.parse().map_err(|_| {
+                        Error::decode(None, format!("invalid sequenceNumber {value}"))
+                    })?);
+                }
+                "timestamp" => {
+                    let time = DateTime::parse_from_rfc3339(&value)
+                        .map_err(|_| Error::decode(None, format!("invalid timestamp {value}")))?;
+                    timestamp = Some(timestamp_from_utc(time.with_timezone(&Utc)));
+                }
+                _ => {}
+            }
+        }
+        Ok(Self {
+            sequence_number: sequence_number
+                .ok_or_else(|| Error::decode(None, "missing sequenceNumber"))?,
+            timestamp: timestamp.ok_or_else(|| Error::decode(None, "missing timestamp"))?,
+        })
+    }
+
+    /// Path of the diff, e.g. `005/804/287.osc.gz`
+    pub fn diff_path(&self) -> String {
+        diff_path(self.sequence_number)
+    }
+
+    /// Path of the state of the diff, e.g. `005/804/287.state.txt`
+    pub fn state_path(&self) -> String {
+        state_path(self.sequence_number)
+    }
+}
+
+impl FromStr for ReplicationState {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        Self::parse(text)
+    }
+}
+
+/// Writes the state in the format of `state.txt`
+impl fmt::Display for ReplicationState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let timestamp = timestamp_to_utc(self.timestamp).format("%Y-%m-%dT%H\\:%M\\:%SZ");
+        writeln!(f, "sequenceNumber={}", self.sequence_number)?;
+        writeln!(f, "timestamp={timestamp}")
+    }
+}
+
+/// Period of the diffs of a replication stream
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Interval {
+    Minute,
+    Hour,
+    Day,
+}
+
+impl Interval {
+    /// Name of the stream, e.g. `minute`
+    pub fn name(self) -> &'static str {
+        match self {
+            Interval::Minute => "minute",
+            Interval::Hour => "hour",
+            Interval::Day => "day",
+        }
+    }
+
+    pub fn duration(self) -> TimeDelta {
+        match self {
+            Interval::Minute => TimeDelta::minutes(1),
+            Interval::Hour => TimeDelta::hours(1),
+            Interval::Day => TimeDelta::days(1),
+        }
+    }
+
+    /// URL of the stream below a base such as [DEFAULT_BASE], without a trailing `/`
+    pub fn url(self, base: &str) -> String {
+        format!("{}/{}", base.trim_end_matches('/'), self.name())
+    }
+}
+
+/// Path of a sequence number without extension, e.g. `005/804/287` for 5804287
+///
+/// Numbers beyond 999999999 lengthen the first directory, as osmosis does.
+pub fn sequence_path(sequence_number: u64) -> String {
+    let digits = format!("{sequence_number:09}");
+    let (top, rest) = digits.split_at(digits.len() - 6);
+    format!("{top}/{}/{}", &rest[..3], &rest[3..])
+}
+
+/// Path of the diff of a sequence number, e.g. `005/804/287.osc.gz`
+pub fn diff_path(sequence_number: u64) -> String {
+    format!("{}.osc.gz", sequence_path(sequence_number))
+}
+
+/// Path of the state of a sequence number, e.g. `005/804/287.state.txt`
+pub fn state_path(sequence_number: u64) -> String {
+    format!("{}.state.txt", sequence_path(sequence_number))
+}
+
+/// Sequence number of a path or URL ending in one, e.g. `minute/005/804/287.osc.gz`
+pub fn parse_sequence_path(path: &str) -> Option<u64> {
+    let mut parts = path.trim_end_matches('/').rsplit('/');
+    let low = parts.next()?;
+    let low = low.split_once('.').map_or(low, |(low, _)| low);
+    let middle = parts.next()?;
+    let top = parts.next()?;
+    if low.len() != 3 || middle.len() != 3 || top.len() < 3 {
+        return None;
+    }
+    [top, middle, low]
+        .iter()
+        .all(|part| part.bytes().all(|b| b.is_ascii_digit()))
+        .then(|| format!("{top}{middle}{low}").parse().ok())
+        .flatten()
+}
+
+/// Estimated sequence number of the newest diff at or before a time
+///
+/// Diffs are assumed to follow each other at the interval from a known state, e.g. the latest
+/// one. Outages of the server shift the numbers, so the state of the estimate should be
+/// fetched and the estimate refined with it when precision matters.
+pub fn sequence_for_timestamp(
+    reference: &ReplicationState,
+    interval: Interval,
+    timestamp: Timestamp,
+) -> u64 {
+    let elapsed = timestamp_to_utc(timestamp) - timestamp_to_utc(reference.timestamp);
+    let steps = elapsed
+        .num_seconds()
+        .div_euclid(interval.duration().num_seconds());
+    reference.sequence_number.saturating_add_signed(steps)
+}
+
+/// Estimated time of the newest change in a diff, under the assumptions of
+/// [sequence_for_timestamp]
+pub fn timestamp_for_sequence(
+    reference: &ReplicationState,
+    interval: Interval,
+    sequence_number: u64,
+) -> Timestamp {
+    let steps = sequence_number as i64 - reference.sequence_number as i64;
+    timestamp_from_utc(
+        timestamp_to_utc(reference.timestamp)
+            + TimeDelta::seconds(interval.duration().num_seconds() * steps),
+    )
+}
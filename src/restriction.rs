@@ -0,0 +1,284 @@
+//! [Turn restrictions](https://wiki.openstreetmap.org/wiki/Relation:restriction)
+//!
+//! A restriction relation forbids or requires the turn from its `from` way over a `via` node or
+//! ways onto its `to` way. [TurnRestriction::from_relation] checks the members against the
+//! roles the wiki documents, so that consumers can trust the shape of what it returns.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::access::TransportMode;
+use crate::role::{FROM, TO, VIA};
+use crate::{Id, MemberType, Relation, TagString};
+
+/// Value of `restriction`
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RestrictionKind {
+    NoLeftTurn,
+    NoRightTurn,
+    NoStraightOn,
+    NoUTurn,
+    /// No entry onto the `to` way from any of several `from` ways
+    NoEntry,
+    /// No exit from the `from` way onto any of several `to` ways
+    NoExit,
+    OnlyLeftTurn,
+    OnlyRightTurn,
+    OnlyStraightOn,
+    OnlyUTurn,
+}
+
+impl RestrictionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RestrictionKind::NoLeftTurn => "no_left_turn",
+            RestrictionKind::NoRightTurn => "no_right_turn",
+            RestrictionKind::NoStraightOn => "no_straight_on",
+            RestrictionKind::NoUTurn => "no_u_turn",
+            RestrictionKind::NoEntry => "no_entry",
+            RestrictionKind::NoExit => "no_exit",
+            RestrictionKind::OnlyLeftTurn => "only_left_turn",
+            RestrictionKind::OnlyRightTurn => "only_right_turn",
+            RestrictionKind::OnlyStraightOn => "only_straight_on",
+            RestrictionKind::OnlyUTurn => "only_u_turn",
+        }
+    }
+
+    /// Whether the turn must be taken, for the `only_*` values
+    pub fn is_mandatory(&self) -> bool {
+        matches!(
+            self,
+            RestrictionKind::OnlyLeftTurn
+                | RestrictionKind::OnlyRightTurn
+                | RestrictionKind::OnlyStraightOn
+                | RestrictionKind::OnlyUTurn
+        )
+    }
+}
+
+impl fmt::Display for RestrictionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RestrictionKind {
+    type Err = RestrictionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "no_left_turn" => RestrictionKind::NoLeftTurn,
+            "no_right_turn" => RestrictionKind::NoRightTurn,
+            "no_straight_on" => RestrictionKind::NoStraightOn,
+            "no_u_turn" => RestrictionKind::NoUTurn,
+            "no_entry" => RestrictionKind::NoEntry,
+            "no_exit" => RestrictionKind::NoExit,
+            "only_left_turn" => RestrictionKind::OnlyLeftTurn,
+            "only_right_turn" => RestrictionKind::OnlyRightTurn,
+            "only_straight_on" => RestrictionKind::OnlyStraightOn,
+            "only_u_turn" => RestrictionKind::OnlyUTurn,
+            other => return Err(RestrictionError::UnknownKind(other.to_string())),
+        })
+    }
+}
+
+/// Node or ways between the `from` and `to` ways
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Via {
+    Node(Id),
+    /// Ways in the order of the members, leading from `from` to `to`
+    Ways(Vec<Id>),
+}
+
+/// Why a relation is not a valid turn restriction
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum RestrictionError {
+    /// The relation is not tagged `type=restriction`
+    NotRestriction,
+    /// Neither `restriction` nor a `restriction:<mode>` key is tagged
+    MissingKind,
+    UnknownKind(String),
+    MissingFrom,
+    MissingVia,
+    MissingTo,
+    /// More than one `from` way, only allowed for [RestrictionKind::NoEntry]
+    MultipleFrom,
+    /// More than one `to` way, only allowed for [RestrictionKind::NoExit]
+    MultipleTo,
+    /// More than one `via` node, or both nodes and ways
+    InvalidVia,
+    /// Member at `index` with a role or type the relation type does not allow
+    InvalidMember {
+        index: usize,
+    },
+}
+
+impl fmt::Display for RestrictionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RestrictionError::NotRestriction => write!(f, "relation is not a restriction"),
+            RestrictionError::MissingKind => write!(f, "restriction has no restriction tag"),
+            RestrictionError::UnknownKind(kind) => write!(f, "unknown restriction {kind}"),
+            RestrictionError::MissingFrom => write!(f, "restriction has no from way"),
+            RestrictionError::MissingVia => write!(f, "restriction has no via member"),
+            RestrictionError::MissingTo => write!(f, "restriction has no to way"),
+            RestrictionError::MultipleFrom => write!(f, "restriction has more than one from way"),
+            RestrictionError::MultipleTo => write!(f, "restriction has more than one to way"),
+            RestrictionError::InvalidVia => {
+                write!(f, "via members must be one node or one or more ways")
+            }
+            RestrictionError::InvalidMember { index } => {
+                write!(f, "member {index} has an invalid role or type")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RestrictionError {}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TurnRestriction {
+    /// Id of the relation
+    pub id: Id,
+    pub kind: RestrictionKind,
+    /// Mode of a `restriction:<mode>` key, [None] for plain `restriction`
+    pub mode: Option<TransportMode>,
+    /// Ways the turn starts on, one unless [RestrictionKind::NoEntry]
+    pub from: Vec<Id>,
+    pub via: Via,
+    /// Ways the turn ends on, one unless [RestrictionKind::NoExit]
+    pub to: Vec<Id>,
+    /// Modes exempt by `except`, e.g. `psv;bicycle`, ignoring unknown keys
+    pub except: Vec<TransportMode>,
+}
+
+impl TurnRestriction {
+    /// Restriction of the relation from `restriction`, or else the first `restriction:<mode>`
+    /// key by the order of [TransportMode]
+    pub fn from_relation(relation: &Relation) -> Result<Self, RestrictionError> {
+        if !relation.tags.has("type", "restriction") {
+            return Err(RestrictionError::NotRestriction);
+        }
+        let (value, mode) = match relation.tags.get("restriction") {
+            Some(value) => (value, None),
+            None => relation
+                .tags
+                .iter()
+                .filter_map(|(key, value)| {
+                    let mode = TransportMode::from_key(key.strip_prefix("restriction:")?)?;
+                    Some((value, Some(mode)))
+                })
+                .min_by_key(|(_, mode)| mode.map(|mode| mode as u8))
+                .ok_or(RestrictionError::MissingKind)?,
+        };
+        Self::with_value(relation, value, mode)
+    }
+
+    /// Restriction of the relation applying to `mode`, [None] if there is none or `mode` is
+    /// exempt
+    ///
+    /// The most specific `restriction:<mode>` key in [TransportMode::hierarchy] wins over
+    /// `restriction`, which like `oneway` does not apply to pedestrians.
+    pub fn for_mode(
+        relation: &Relation,
+        mode: TransportMode,
+    ) -> Option<Result<Self, RestrictionError>> {
+        if !relation.tags.has("type", "restriction") {
+            return None;
+        }
+        let (value, specific) = mode
+            .hierarchy()
+            .filter(|m| *m != TransportMode::All)
+            .find_map(|m| {
+                let value = relation
+                    .tags
+                    .get(format!("restriction:{}", m.key()).as_str())?;
+                Some((value, Some(m)))
+            })
+            .or_else(|| {
+                (mode != TransportMode::Foot)
+                    .then(|| relation.tags.get("restriction").map(|value| (value, None)))
+                    .flatten()
+            })?;
+        let restriction = Self::with_value(relation, value, specific);
+        match restriction {
+            Ok(restriction) if !restriction.applies_to(mode) => None,
+            restriction => Some(restriction),
+        }
+    }
+
+    /// Whether `mode` and its ancestors are not exempt by `except`
+    pub fn applies_to(&self, mode: TransportMode) -> bool {
+        !mode
+            .hierarchy()
+            .any(|m| m != TransportMode::All && self.except.contains(&m))
+    }
+
+    fn with_value(
+        relation: &Relation,
+        value: &TagString,
+        mode: Option<TransportMode>,
+    ) -> Result<Self, RestrictionError> {
+        let kind: RestrictionKind = value.parse()?;
+        let (mut from, mut via_nodes, mut via_ways, mut to) = (vec![], vec![], vec![], vec![]);
+        for (index, member) in relation.members.iter().enumerate() {
+            let list = match (member.role.as_deref(), &member.ty) {
+                (Some(FROM), MemberType::Way) => &mut from,
+                (Some(VIA), MemberType::Node) => &mut via_nodes,
+                (Some(VIA), MemberType::Way) => &mut via_ways,
+                (Some(TO), MemberType::Way) => &mut to,
+                // Signs and other members that routers ignore
+                (Some("location_hint"), MemberType::Node) => continue,
+                _ => return Err(RestrictionError::InvalidMember { index }),
+            };
+            list.push(member.id);
+        }
+        if from.is_empty() {
+            return Err(RestrictionError::MissingFrom);
+        }
+        if to.is_empty() {
+            return Err(RestrictionError::MissingTo);
+        }
+        if from.len() > 1 && kind != RestrictionKind::NoEntry {
+            return Err(RestrictionError::MultipleFrom);
+        }
+        if to.len() > 1 && kind != RestrictionKind::NoExit {
+            return Err(RestrictionError::MultipleTo);
+        }
+        let via = match (&via_nodes[..], via_ways.is_empty()) {
+            ([], true) => return Err(RestrictionError::MissingVia),
+            ([node], true) => Via::Node(*node),
+            ([], false) => Via::Ways(via_ways),
+            _ => return Err(RestrictionError::InvalidVia),
+        };
+        let except = relation
+            .tags
+            .get("except")
+            .map(|except| {
+                except
+                    .split(';')
+                    .filter_map(|key| TransportMode::from_key(key.trim()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Self {
+            id: relation.id,
+            kind,
+            mode,
+            from,
+            via,
+            to,
+            except,
+        })
+    }
+}
+
+impl Relation {
+    /// See [TurnRestriction::from_relation]
+    pub fn turn_restriction(&self) -> Result<TurnRestriction, RestrictionError> {
+        TurnRestriction::from_relation(self)
+    }
+}
@@ -0,0 +1,208 @@
+//! Types and roles of the [Member]s of relations as strings
+//!
+//! [MemberType] is written as in the XML and JSON formats, e.g. `way`.
+//! [Role] names the [roles](https://wiki.openstreetmap.org/wiki/Relation#Roles) that are
+//! common across relation types, falling back to [Role::Other] for the rest. Relations can be
+//! filtered by either, e.g. with [Relation::members_with_role] or [Relation::way_members].
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::id::{NodeId, RelationId, WayId};
+use crate::{Member, MemberType, Relation, TagString};
+
+pub const OUTER: &str = "outer";
+pub const INNER: &str = "inner";
+pub const STOP: &str = "stop";
+pub const PLATFORM: &str = "platform";
+pub const FROM: &str = "from";
+pub const VIA: &str = "via";
+pub const TO: &str = "to";
+pub const FORWARD: &str = "forward";
+pub const BACKWARD: &str = "backward";
+
+impl MemberType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MemberType::Node => "node",
+            MemberType::Way => "way",
+            MemberType::Relation => "relation",
+        }
+    }
+}
+
+impl fmt::Display for MemberType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Error returned when parsing a [MemberType] fails
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseMemberTypeError;
+
+impl fmt::Display for ParseMemberTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid member type")
+    }
+}
+
+impl std::error::Error for ParseMemberTypeError {}
+
+impl FromStr for MemberType {
+    type Err = ParseMemberTypeError;
+
+    /// Parses `node`, `way`, or `relation`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "node" => Ok(MemberType::Node),
+            "way" => Ok(MemberType::Way),
+            "relation" => Ok(MemberType::Relation),
+            _ => Err(ParseMemberTypeError),
+        }
+    }
+}
+
+/// Role of a [Member]
+///
+/// Parsing is case-sensitive like the roles themselves, so `Outer` is [Role::Other].
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "TagString", into = "TagString")
+)]
+pub enum Role {
+    /// Way forming part of an outer ring of a
+    /// [multipolygon](https://wiki.openstreetmap.org/wiki/Relation:multipolygon)
+    Outer,
+    /// Way forming part of a hole of a multipolygon
+    Inner,
+    /// Position where a vehicle stops on a
+    /// [public transport route](https://wiki.openstreetmap.org/wiki/Public_transport)
+    Stop,
+    /// Place where passengers wait on a public transport route
+    Platform,
+    /// Way where a [turn restriction](https://wiki.openstreetmap.org/wiki/Relation:restriction)
+    /// starts
+    From,
+    /// Node or ways passed through by a turn restriction
+    Via,
+    /// Way where a turn restriction ends
+    To,
+    /// Way of a [route](https://wiki.openstreetmap.org/wiki/Relation:route) followed only in
+    /// its direction
+    Forward,
+    /// Way of a route followed only against its direction
+    Backward,
+    /// Any other non-empty role, e.g. `admin_centre`
+    Other(TagString),
+}
+
+impl Role {
+    pub fn parse(role: &str) -> Self {
+        match role {
+            OUTER => Role::Outer,
+            INNER => Role::Inner,
+            STOP => Role::Stop,
+            PLATFORM => Role::Platform,
+            FROM => Role::From,
+            VIA => Role::Via,
+            TO => Role::To,
+            FORWARD => Role::Forward,
+            BACKWARD => Role::Backward,
+            other => Role::Other(TagString::from_ref(other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Role::Outer => OUTER,
+            Role::Inner => INNER,
+            Role::Stop => STOP,
+            Role::Platform => PLATFORM,
+            Role::From => FROM,
+            Role::Via => VIA,
+            Role::To => TO,
+            Role::Forward => FORWARD,
+            Role::Backward => BACKWARD,
+            Role::Other(role) => role,
+        }
+    }
+}
+
+impl From<&str> for Role {
+    fn from(role: &str) -> Self {
+        Self::parse(role)
+    }
+}
+
+impl From<TagString> for Role {
+    fn from(role: TagString) -> Self {
+        match Self::parse(&role) {
+            Role::Other(_) => Role::Other(role),
+            known => known,
+        }
+    }
+}
+
+impl From<Role> for TagString {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::Other(role) => role,
+            known => TagString::from_ref(known.as_str()),
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Member {
+    /// [Member::role] parsed, [None] if the member has no role
+    pub fn typed_role(&self) -> Option<Role> {
+        self.role.as_deref().map(Role::parse)
+    }
+
+    /// Sets [Member::role], clearing it for [None] or an empty [Role::Other]
+    pub fn set_role(&mut self, role: Option<Role>) {
+        self.role = role.map(TagString::from).filter(|role| !role.is_empty());
+    }
+}
+
+impl Relation {
+    /// Members of a type, in order
+    pub fn members_of_type(&self, ty: MemberType) -> impl Iterator<Item = &Member> {
+        self.members.iter().filter(move |member| member.ty == ty)
+    }
+
+    /// Members with a role, in order, e.g. [OUTER]
+    ///
+    /// An empty role matches the members without a role.
+    pub fn members_with_role<'a>(&'a self, role: &'a str) -> impl Iterator<Item = &'a Member> {
+        self.members
+            .iter()
+            .filter(move |member| member.role.as_deref().unwrap_or("") == role)
+    }
+
+    /// Node members with their ids, in order
+    pub fn node_members(&self) -> impl Iterator<Item = (NodeId, &Member)> {
+        self.members_of_type(MemberType::Node)
+            .map(|member| (member.id.into(), member))
+    }
+
+    /// Way members with their ids, in order
+    pub fn way_members(&self) -> impl Iterator<Item = (WayId, &Member)> {
+        self.members_of_type(MemberType::Way)
+            .map(|member| (member.id.into(), member))
+    }
+
+    /// Relation members with their ids, in order
+    pub fn relation_members(&self) -> impl Iterator<Item = (RelationId, &Member)> {
+        self.members_of_type(MemberType::Relation)
+            .map(|member| (member.id.into(), member))
+    }
+}
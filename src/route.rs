@@ -0,0 +1,322 @@
+//! [Public transport routes](https://wiki.openstreetmap.org/wiki/Public_transport) in the
+//! PTv2 scheme
+//!
+//! A route relation lists its stops and platforms in the order they are served, followed by
+//! the ways travelled, in order from the first stop to the last. Route masters group the
+//! variants of a line, e.g. both directions of a bus line. [Route::check] reports where a
+//! route breaks these rules.
+
+use std::fmt;
+
+use fnv::{FnvHashMap as HashMap, FnvHashSet as HashSet};
+
+use crate::role::{BACKWARD, FORWARD, PLATFORM, STOP};
+use crate::{Id, MemberType, Relation, TagString, Way};
+
+/// Whether a stop is where vehicles halt on the way, or where passengers wait beside it
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StopKind {
+    Stop,
+    Platform,
+}
+
+/// Whether passengers may board or alight, from the `_entry_only` and `_exit_only` roles
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Boarding {
+    Both,
+    EntryOnly,
+    ExitOnly,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stop {
+    pub ty: MemberType,
+    pub id: Id,
+    pub kind: StopKind,
+    pub boarding: Boarding,
+    /// Position of the member in [Relation::members]
+    pub index: usize,
+}
+
+impl Stop {
+    fn parse(role: &str) -> Option<(StopKind, Boarding)> {
+        let (kind, rest) = if let Some(rest) = role.strip_prefix(STOP) {
+            (StopKind::Stop, rest)
+        } else {
+            (StopKind::Platform, role.strip_prefix(PLATFORM)?)
+        };
+        let boarding = match rest {
+            "" => Boarding::Both,
+            "_entry_only" => Boarding::EntryOnly,
+            "_exit_only" => Boarding::ExitOnly,
+            _ => return None,
+        };
+        Some((kind, boarding))
+    }
+}
+
+/// Problem with the members of a [Route]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RouteIssue {
+    /// Member with a role that routes do not use, e.g. a node without a role
+    UnknownRole { index: usize },
+    /// Stop or platform listed after the first way
+    StopAfterWay { index: usize },
+    /// Way that is not in the store
+    MissingWay { id: Id },
+    /// Way that does not continue from the end of the way before it, index into [Route::ways]
+    Gap { index: usize },
+    /// Stop node that no way of the route passes
+    StopNotOnRoute { index: usize },
+    /// Stop node that the route passes only before the stop listed ahead of it
+    StopOutOfOrder { index: usize },
+}
+
+impl fmt::Display for RouteIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RouteIssue::UnknownRole { index } => write!(f, "member {index} has an unknown role"),
+            RouteIssue::StopAfterWay { index } => {
+                write!(f, "stop member {index} comes after the ways")
+            }
+            RouteIssue::MissingWay { id } => write!(f, "way {} is missing", id.0),
+            RouteIssue::Gap { index } => write!(f, "gap before way {index} of the route"),
+            RouteIssue::StopNotOnRoute { index } => {
+                write!(f, "stop member {index} is not on the route")
+            }
+            RouteIssue::StopOutOfOrder { index } => {
+                write!(f, "stop member {index} is out of order")
+            }
+        }
+    }
+}
+
+/// Relation tagged `type=route`
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Route {
+    pub id: Id,
+    /// Value of `route`, e.g. `bus` or `tram`
+    pub mode: Option<TagString>,
+    /// Value of `ref`, e.g. the line number
+    pub reference: Option<TagString>,
+    pub name: Option<TagString>,
+    pub from: Option<TagString>,
+    pub to: Option<TagString>,
+    /// Whether tagged `public_transport:version=2`
+    pub ptv2: bool,
+    /// Stops and platforms in the order they are served
+    pub stops: Vec<Stop>,
+    /// Ways in the order travelled
+    pub ways: Vec<Id>,
+    /// Problems with the roles and order of the members, a part of [Route::check]
+    pub issues: Vec<RouteIssue>,
+}
+
+impl Route {
+    /// Route of a relation tagged `type=route`, [None] for other relations
+    pub fn from_relation(relation: &Relation) -> Option<Self> {
+        if !relation.tags.has("type", "route") {
+            return None;
+        }
+        let get = |key: &str| relation.tags.get(key).cloned();
+        let mut route = Self {
+            id: relation.id,
+            mode: get("route"),
+            reference: get("ref"),
+            name: get("name"),
+            from: get("from"),
+            to: get("to"),
+            ptv2: relation.tags.has("public_transport:version", "2"),
+            stops: vec![],
+            ways: vec![],
+            issues: vec![],
+        };
+        for (index, member) in relation.members.iter().enumerate() {
+            let role = member.role.as_deref().unwrap_or("");
+            if let Some((kind, boarding)) = Stop::parse(role) {
+                if !route.ways.is_empty() {
+                    route.issues.push(RouteIssue::StopAfterWay { index });
+                }
+                route.stops.push(Stop {
+                    ty: member.ty.clone(),
+                    id: member.id,
+                    kind,
+                    boarding,
+                    index,
+                });
+            } else if member.ty == MemberType::Way
+                && matches!(role, "" | FORWARD | BACKWARD | "hail_and_ride")
+            {
+                route.ways.push(member.id);
+            } else {
+                route.issues.push(RouteIssue::UnknownRole { index });
+            }
+        }
+        Some(route)
+    }
+
+    /// Problems with the order of members, the connectivity of the ways, and the order of stop
+    /// nodes along them, looking up ways with `way`
+    ///
+    /// Ways are oriented by how they connect to the ways before and after them, and closed
+    /// ways such as roundabouts may be left at any of their nodes.
+    pub fn check<'a>(&self, mut way: impl FnMut(Id) -> Option<&'a Way>) -> Vec<RouteIssue> {
+        let mut issues = self.issues.clone();
+        // Nodes passed in order, and the nodes the route may continue from
+        let mut passed: Vec<Id> = vec![];
+        let mut ends: Vec<Id> = vec![];
+        // First node of the last way and where its nodes start in `passed`, if its direction
+        // was guessed because nothing connects to it from before
+        let mut guessed: Option<(Id, usize)> = None;
+        for (index, &id) in self.ways.iter().enumerate() {
+            let Some(way) = way(id).filter(|way| !way.refs.is_empty()) else {
+                issues.push(RouteIssue::MissingWay { id });
+                ends.clear();
+                guessed = None;
+                continue;
+            };
+            let (first, last) = (way.refs[0], way.refs[way.refs.len() - 1]);
+            let closed = way.is_closed();
+            let entry = ends.iter().copied().find(|&end| {
+                if closed {
+                    way.refs.contains(&end)
+                } else {
+                    end == first || end == last
+                }
+            });
+            if entry.is_none() && !ends.is_empty() {
+                issues.push(RouteIssue::Gap { index });
+            }
+            if let Some((start, offset)) = guessed.take() {
+                if entry == Some(start) {
+                    passed[offset..].reverse();
+                }
+            }
+            let offset = passed.len();
+            if closed {
+                passed.extend(way.refs.iter().copied());
+                ends = way.refs.to_vec();
+            } else if entry == Some(last) {
+                passed.extend(way.refs.iter().rev().copied());
+                ends = vec![first];
+            } else {
+                passed.extend(way.refs.iter().copied());
+                ends = vec![last];
+                if entry.is_none() {
+                    ends.push(first);
+                    guessed = Some((first, offset));
+                }
+            }
+        }
+
+        let mut position = 0;
+        for stop in &self.stops {
+            if stop.kind != StopKind::Stop || stop.ty != MemberType::Node {
+                continue;
+            }
+            match passed[position..].iter().position(|&node| node == stop.id) {
+                Some(offset) => position += offset,
+                None if passed.contains(&stop.id) => {
+                    issues.push(RouteIssue::StopOutOfOrder { index: stop.index })
+                }
+                None => issues.push(RouteIssue::StopNotOnRoute { index: stop.index }),
+            }
+        }
+        issues
+    }
+}
+
+/// Relation tagged `type=route_master`, grouping the [Route]s of one line
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RouteMaster {
+    pub id: Id,
+    /// Value of `route_master`, e.g. `bus`
+    pub mode: Option<TagString>,
+    pub reference: Option<TagString>,
+    pub name: Option<TagString>,
+    /// Ids of the member relations
+    pub routes: Vec<Id>,
+}
+
+impl RouteMaster {
+    /// Route master of a relation tagged `type=route_master`, [None] for other relations
+    pub fn from_relation(relation: &Relation) -> Option<Self> {
+        if !relation.tags.has("type", "route_master") {
+            return None;
+        }
+        let get = |key: &str| relation.tags.get(key).cloned();
+        Some(Self {
+            id: relation.id,
+            mode: get("route_master"),
+            reference: get("ref"),
+            name: get("name"),
+            routes: relation
+                .members
+                .iter()
+                .filter(|member| member.ty == MemberType::Relation)
+                .map(|member| member.id)
+                .collect(),
+        })
+    }
+}
+
+/// Routes grouped by their route masters, see [group_routes]
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct RouteGroups {
+    /// Route masters in the order found, with their routes in member order
+    pub masters: Vec<(RouteMaster, Vec<Route>)>,
+    /// Routes that no route master lists
+    pub ungrouped: Vec<Route>,
+}
+
+/// Groups the routes among `relations` under the route masters among them
+///
+/// Members of a route master missing from `relations` are skipped, and a route listed by
+/// several route masters appears under each.
+pub fn group_routes<'a>(relations: impl IntoIterator<Item = &'a Relation>) -> RouteGroups {
+    let mut routes = HashMap::default();
+    let mut masters = vec![];
+    let mut order = vec![];
+    for relation in relations {
+        if let Some(route) = Route::from_relation(relation) {
+            order.push(route.id);
+            routes.insert(route.id, route);
+        } else if let Some(master) = RouteMaster::from_relation(relation) {
+            masters.push(master);
+        }
+    }
+    let grouped: HashSet<Id> = masters
+        .iter()
+        .flat_map(|master| master.routes.iter().copied())
+        .collect();
+    let masters = masters
+        .into_iter()
+        .map(|master| {
+            let members = master
+                .routes
+                .iter()
+                .filter_map(|id| routes.get(id).cloned())
+                .collect();
+            (master, members)
+        })
+        .collect();
+    let ungrouped = order
+        .into_iter()
+        .filter(|id| !grouped.contains(id))
+        .filter_map(|id| routes.remove(&id))
+        .collect();
+    RouteGroups { masters, ungrouped }
+}
+
+impl Relation {
+    /// See [Route::from_relation]
+    pub fn route(&self) -> Option<Route> {
+        Route::from_relation(self)
+    }
+}
@@ -0,0 +1,137 @@
+//! [S2](http://s2geometry.io) cell ids
+//!
+//! Cell ids are returned as the raw `u64` used by most S2 implementations and data platforms,
+//! so they can be joined against ids computed elsewhere. See [token] for the string form.
+
+use ::s2::cellid::CellID;
+use ::s2::cellunion::CellUnion;
+use ::s2::latlng::LatLng;
+use ::s2::rect::Rect;
+use ::s2::region::RegionCoverer;
+
+use crate::bbox::Bbox;
+use crate::geom::LatLon;
+use crate::scalar::ScalarExt;
+use crate::Node;
+
+/// Level of the smallest cells, about 1 cm across
+pub const MAX_LEVEL: u8 = 30;
+
+fn lat_lng((lat, lon): LatLon) -> LatLng {
+    LatLng::from_degrees(lat.as_f64(), lon.as_f64())
+}
+
+/// Id of the cell at `level` containing the coordinate
+///
+/// # Panics
+///
+/// If `level` is greater than [MAX_LEVEL].
+pub fn cell_id(at: LatLon, level: u8) -> u64 {
+    assert!(level <= MAX_LEVEL, "S2 level must be at most {MAX_LEVEL}");
+    CellID::from(lat_lng(at)).parent(level.into()).0
+}
+
+/// Compact hex form of a cell id, e.g. `89c25` for a cell around New York
+pub fn token(id: u64) -> String {
+    CellID(id).to_token()
+}
+
+/// Level of a cell id
+pub fn level(id: u64) -> u8 {
+    CellID(id).level() as u8
+}
+
+/// Approximates areas with a limited number of cells
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Coverer {
+    pub min_level: u8,
+    pub max_level: u8,
+    /// Desired maximum number of cells, which may be exceeded to respect `min_level`
+    pub max_cells: usize,
+}
+
+impl Default for Coverer {
+    /// Defaults of the S2 library: any level with up to 8 cells
+    fn default() -> Self {
+        Self {
+            min_level: 0,
+            max_level: MAX_LEVEL,
+            max_cells: 8,
+        }
+    }
+}
+
+impl Coverer {
+    /// Covering at exactly `level`, e.g. for joining on a single level
+    pub fn at_level(level: u8) -> Self {
+        Self {
+            min_level: level,
+            max_level: level,
+            max_cells: usize::MAX,
+        }
+    }
+
+    fn coverer(&self) -> RegionCoverer {
+        RegionCoverer {
+            min_level: self.min_level.min(MAX_LEVEL),
+            max_level: self.max_level.min(MAX_LEVEL),
+            level_mod: 1,
+            max_cells: self.max_cells,
+        }
+    }
+
+    /// Sorted cell ids covering the box
+    pub fn cover_bbox(&self, bbox: &Bbox) -> Vec<u64> {
+        let rect = Rect::from_degrees(
+            bbox.min_lat.as_f64(),
+            bbox.min_lon.as_f64(),
+            bbox.max_lat.as_f64(),
+            bbox.max_lon.as_f64(),
+        );
+        ids(self.coverer().covering(&rect))
+    }
+
+    /// Sorted cell ids covering a resolved line, e.g. the coordinates of a way
+    ///
+    /// Each segment is covered by the covering of its bounding rectangle, so long diagonal
+    /// segments are covered generously.
+    pub fn cover_line(&self, line: &[LatLon]) -> Vec<u64> {
+        let coverer = self.coverer();
+        let mut cells = vec![];
+        match line {
+            [] => {}
+            [only] => cells.extend(coverer.covering(&Rect::from(lat_lng(*only))).0),
+            _ => {
+                for pair in line.windows(2) {
+                    let rect = Rect::from_point_pair(&lat_lng(pair[0]), &lat_lng(pair[1]));
+                    cells.extend(coverer.covering(&rect).0);
+                }
+            }
+        }
+        // Drop duplicates and cells inside others without merging siblings, which would
+        // produce cells below `min_level`
+        cells.sort_unstable_by_key(|cell| {
+            (cell.range_min().0, std::cmp::Reverse(cell.range_max().0))
+        });
+        let mut covering: Vec<CellID> = vec![];
+        for cell in cells {
+            if covering.last().is_none_or(|last| !last.contains(&cell)) {
+                covering.push(cell);
+            }
+        }
+        ids(CellUnion(covering))
+    }
+}
+
+fn ids(union: CellUnion) -> Vec<u64> {
+    let mut ids: Vec<_> = union.0.into_iter().map(|id| id.0).collect();
+    ids.sort_unstable();
+    ids
+}
+
+impl Node {
+    /// See [cell_id]
+    pub fn s2_cell_id(&self, level: u8) -> u64 {
+        cell_id((self.lat, self.lon), level)
+    }
+}
@@ -0,0 +1,143 @@
+//! Number type of coordinates and measurements
+//!
+//! [Scalar] is a [rust_decimal::Decimal] with the default `decimal` feature, which keeps
+//! coordinates exactly as written in OSM data. Without it, [Scalar] is an [f64] and
+//! `rust_decimal` is not a dependency at all, trading exact decimal semantics for speed.
+//!
+//! [ScalarExt] offers the operations the crate needs from either type, so code using it
+//! works with both. With [f64], [crate::Node] and other types holding coordinates no longer
+//! implement [Eq] and [Hash].
+
+#[cfg(feature = "decimal")]
+pub type Scalar = rust_decimal::Decimal;
+#[cfg(not(feature = "decimal"))]
+pub type Scalar = f64;
+
+/// Conversions and rounding for [Scalar]
+pub trait ScalarExt: Sized + Copy {
+    const ZERO: Self;
+
+    fn from_int(value: i64) -> Self;
+
+    /// `mantissa * 10^-scale`, e.g. `with_scale(5, 1)` for `0.5`
+    fn with_scale(mantissa: i64, scale: u32) -> Self;
+
+    /// Returns [None] for values that are not finite or out of range
+    fn try_from_f64(value: f64) -> Option<Self>;
+
+    fn as_f64(self) -> f64;
+
+    /// Integer part, or [None] if out of range of [i64]
+    fn to_int(self) -> Option<i64>;
+
+    /// Parses a plain decimal number, e.g. `-12.5`, without exponents or special values
+    fn parse_decimal(s: &str) -> Option<Self>;
+
+    /// Rounds to `decimal_places`, with halfway values going to the even neighbor
+    fn round_places(self, decimal_places: u32) -> Self;
+
+    /// Rounds to an integer, with halfway values going away from zero
+    fn round_half_away(self) -> Self;
+
+    fn is_integer(self) -> bool;
+
+    /// Same value with a canonical [std::fmt::Display], e.g. `1.5` instead of `1.50`
+    fn normalized(self) -> Self;
+}
+
+#[cfg(feature = "decimal")]
+impl ScalarExt for rust_decimal::Decimal {
+    const ZERO: Self = rust_decimal::Decimal::ZERO;
+
+    fn from_int(value: i64) -> Self {
+        value.into()
+    }
+
+    fn with_scale(mantissa: i64, scale: u32) -> Self {
+        Self::new(mantissa, scale)
+    }
+
+    fn try_from_f64(value: f64) -> Option<Self> {
+        rust_decimal::prelude::FromPrimitive::from_f64(value)
+    }
+
+    fn as_f64(self) -> f64 {
+        rust_decimal::prelude::ToPrimitive::to_f64(&self).unwrap_or(f64::NAN)
+    }
+
+    fn to_int(self) -> Option<i64> {
+        rust_decimal::prelude::ToPrimitive::to_i64(&self.trunc())
+    }
+
+    fn parse_decimal(s: &str) -> Option<Self> {
+        s.parse().ok()
+    }
+
+    fn round_places(self, decimal_places: u32) -> Self {
+        self.round_dp(decimal_places)
+    }
+
+    fn round_half_away(self) -> Self {
+        self.round_dp_with_strategy(0, rust_decimal::RoundingStrategy::MidpointAwayFromZero)
+    }
+
+    fn is_integer(self) -> bool {
+        self.fract().is_zero()
+    }
+
+    fn normalized(self) -> Self {
+        self.normalize()
+    }
+}
+
+#[cfg(not(feature = "decimal"))]
+impl ScalarExt for f64 {
+    const ZERO: Self = 0.;
+
+    fn from_int(value: i64) -> Self {
+        value as f64
+    }
+
+    fn with_scale(mantissa: i64, scale: u32) -> Self {
+        mantissa as f64 / 10f64.powi(scale as i32)
+    }
+
+    fn try_from_f64(value: f64) -> Option<Self> {
+        value.is_finite().then_some(value)
+    }
+
+    fn as_f64(self) -> f64 {
+        self
+    }
+
+    fn to_int(self) -> Option<i64> {
+        let value = self.trunc();
+        (value >= i64::MIN as f64 && value < i64::MAX as f64).then_some(value as i64)
+    }
+
+    fn parse_decimal(s: &str) -> Option<Self> {
+        let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+            return None;
+        }
+        s.parse().ok()
+    }
+
+    fn round_places(self, decimal_places: u32) -> Self {
+        let factor = 10f64.powi(decimal_places as i32);
+        (self * factor).round_ties_even() / factor
+    }
+
+    fn round_half_away(self) -> Self {
+        self.round()
+    }
+
+    fn is_integer(self) -> bool {
+        self.fract() == 0.
+    }
+
+    /// Turns `-0` into `0`
+    fn normalized(self) -> Self {
+        self + 0.
+    }
+}
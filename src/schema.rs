@@ -0,0 +1,182 @@
+//! Versioned envelope for data serialized by this crate
+//!
+//! Every file starts with a [Header] naming the [Kind] of data and the version of its layout.
+//! Readers only accept the current version, and [Migrations] upgrades files written by older
+//! releases, so long-lived caches can be carried across crate upgrades instead of rebuilt.
+
+use std::io::{self, Read, Write};
+
+use fnv::FnvHashMap as HashMap;
+
+/// Identifies files written by this crate
+pub const MAGIC: [u8; 8] = *b"OSMTYPES";
+
+/// Kind of data following a [Header]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Kind {
+    /// [crate::locations::NodeLocations]
+    NodeLocations,
+}
+
+impl Kind {
+    /// Layout version written by this release
+    pub fn current_version(&self) -> u16 {
+        match self {
+            Kind::NodeLocations => 1,
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            Kind::NodeLocations => 1,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(Kind::NodeLocations),
+            _ => None,
+        }
+    }
+}
+
+/// Start of a versioned file
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Header {
+    pub kind: Kind,
+    pub version: u16,
+}
+
+impl Header {
+    pub const ENCODED_LEN: usize = MAGIC.len() + 3;
+
+    /// Header for the current version of `kind`
+    pub fn current(kind: Kind) -> Self {
+        Self {
+            kind,
+            version: kind.current_version(),
+        }
+    }
+
+    pub fn is_current(&self) -> bool {
+        self.version == self.kind.current_version()
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[self.kind.to_byte()])?;
+        writer.write_all(&self.version.to_le_bytes())
+    }
+
+    /// Fails with [io::ErrorKind::InvalidData] if `reader` does not start with a header
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut bytes = [0; Self::ENCODED_LEN];
+        reader.read_exact(&mut bytes)?;
+        if bytes[..MAGIC.len()] != MAGIC {
+            return Err(invalid_data("missing osm-types header"));
+        }
+        let kind = Kind::from_byte(bytes[MAGIC.len()])
+            .ok_or_else(|| invalid_data("unknown kind of data"))?;
+        let version = u16::from_le_bytes([bytes[MAGIC.len() + 1], bytes[MAGIC.len() + 2]]);
+        if version == 0 || version > kind.current_version() {
+            return Err(invalid_data(format!(
+                "unsupported version {version} of {kind:?}"
+            )));
+        }
+        Ok(Self { kind, version })
+    }
+
+    /// Reads a header and checks that it is the current version of `kind`
+    pub(crate) fn expect<R: Read>(reader: R, kind: Kind) -> io::Result<()> {
+        let header = Self::read_from(reader)?;
+        if header.kind != kind {
+            return Err(invalid_data(format!(
+                "expected {kind:?} but found {:?}",
+                header.kind
+            )));
+        }
+        if !header.is_current() {
+            return Err(invalid_data(format!(
+                "version {} of {kind:?} must be migrated to version {}",
+                header.version,
+                kind.current_version()
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Upgrades the body of a file from one version to the next
+pub type Step = fn(&mut dyn Read, &mut dyn Write) -> io::Result<()>;
+
+/// Registry of upgrade steps between versions
+///
+/// [Migrations::default] contains the steps for every older version written by a release of
+/// this crate. Steps are applied one version at a time until the current version is reached.
+#[derive(Debug, Clone)]
+pub struct Migrations {
+    steps: HashMap<(Kind, u16), Step>,
+}
+
+impl Migrations {
+    /// Registry without any steps
+    pub fn empty() -> Self {
+        Self {
+            steps: HashMap::default(),
+        }
+    }
+
+    /// Registers the step that upgrades `kind` from version `from` to `from + 1`
+    pub fn register(&mut self, kind: Kind, from: u16, step: Step) -> &mut Self {
+        self.steps.insert((kind, from), step);
+        self
+    }
+
+    /// Copies a versioned file from `reader` to `writer`, upgrading it to the current version
+    ///
+    /// Returns the header of the original file. Fails with [io::ErrorKind::InvalidData] if a
+    /// step is missing.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn migrate<R: Read, W: Write>(&self, mut reader: R, mut writer: W) -> io::Result<Header> {
+        let original = Header::read_from(&mut reader)?;
+        Header::current(original.kind).write_to(&mut writer)?;
+        if original.is_current() {
+            io::copy(&mut reader, &mut writer)?;
+            return Ok(original);
+        }
+        let mut body = vec![];
+        reader.read_to_end(&mut body)?;
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            kind = ?original.kind,
+            from = original.version,
+            to = original.kind.current_version(),
+            bytes = body.len(),
+            "migrating"
+        );
+        for version in original.version..original.kind.current_version() {
+            let step = self.steps.get(&(original.kind, version)).ok_or_else(|| {
+                invalid_data(format!(
+                    "no migration from version {version} of {:?}",
+                    original.kind
+                ))
+            })?;
+            let mut upgraded = vec![];
+            step(&mut body.as_slice(), &mut upgraded)?;
+            body = upgraded;
+        }
+        writer.write_all(&body)?;
+        Ok(original)
+    }
+}
+
+impl Default for Migrations {
+    fn default() -> Self {
+        // Every kind is still at its first version
+        Self::empty()
+    }
+}
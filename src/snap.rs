@@ -0,0 +1,150 @@
+//! Snapping of coordinates to a regular grid
+//!
+//! Imports often carry more precision than their source data justifies. Snapping
+//! to a grid removes it and lets nodes that become identical be merged.
+
+use fnv::FnvHashMap as HashMap;
+
+use crate::scalar::{Scalar, ScalarExt};
+use crate::{Id, MemberType, Node, Relation, Way};
+
+/// Square grid with cells of [Grid::step] degrees
+///
+/// Coordinates that snap to the same grid point are considered equal, which unlike
+/// [Node::approx_eq] is transitive and can be used as a hash map key via [Grid::key].
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "decimal", derive(Eq, Hash))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Grid {
+    pub step: Scalar,
+}
+
+impl Grid {
+    /// Creates a grid with spacing `step` degrees, e.g. `0.0000001`
+    ///
+    /// # Panics
+    ///
+    /// If `step` is not positive.
+    pub fn new(step: Scalar) -> Self {
+        assert!(step > Scalar::ZERO, "grid step must be positive");
+        Self { step }
+    }
+
+    /// Grid with spacing of `10^-decimal_places` degrees
+    pub fn with_decimal_places(decimal_places: u32) -> Self {
+        Self::new(Scalar::with_scale(1, decimal_places))
+    }
+
+    /// Index of the grid point nearest to `degrees`, rounding halfway values away from zero
+    fn index(&self, degrees: Scalar) -> Scalar {
+        (degrees / self.step).round_half_away()
+    }
+
+    /// Nearest grid coordinate to `degrees`
+    pub fn snap(&self, degrees: Scalar) -> Scalar {
+        (self.index(degrees) * self.step).normalized()
+    }
+
+    /// Indices of the grid point nearest to the node's latitude and longitude
+    ///
+    /// Returns [None] if an index does not fit into an [i64], which requires an unreasonably small step.
+    pub fn key(&self, node: &Node) -> Option<(i64, i64)> {
+        Some((
+            self.index(node.lat).to_int()?,
+            self.index(node.lon).to_int()?,
+        ))
+    }
+
+    /// Whether two nodes snap to the same grid point
+    pub fn same_point(&self, a: &Node, b: &Node) -> bool {
+        self.index(a.lat) == self.index(b.lat) && self.index(a.lon) == self.index(b.lon)
+    }
+
+    /// Moves the node to the nearest grid point
+    pub fn snap_node(&self, node: &mut Node) {
+        node.lat = self.snap(node.lat);
+        node.lon = self.snap(node.lon);
+    }
+}
+
+/// Nodes after snapping with [snap_and_merge]
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub struct Snapped {
+    /// Remaining nodes, sorted by id
+    pub nodes: Vec<Node>,
+    /// Id of each merged node to the id of the node it was merged into
+    pub merged: HashMap<Id, Id>,
+}
+
+impl Snapped {
+    /// Replaces merged node ids in a way, dropping consecutive duplicates
+    pub fn rewrite_way(&self, way: &mut Way) {
+        for id in way.refs.iter_mut() {
+            if let Some(replacement) = self.merged.get(id) {
+                *id = *replacement;
+            }
+        }
+        way.refs.dedup();
+    }
+
+    /// Replaces merged node ids in a relation's node members
+    pub fn rewrite_relation(&self, relation: &mut Relation) {
+        for member in relation.members.iter_mut() {
+            if member.ty == MemberType::Node {
+                if let Some(replacement) = self.merged.get(&member.id) {
+                    member.id = *replacement;
+                }
+            }
+        }
+    }
+}
+
+/// Snaps nodes to `grid` and merges those that end up at the same grid point
+///
+/// Nodes are merged into the one with the lowest id, combining their tags. Nodes
+/// with conflicting values for a tag are never merged. Use [Snapped::rewrite_way] and
+/// [Snapped::rewrite_relation] to update references to merged nodes afterwards.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(step = %grid.step)))]
+pub fn snap_and_merge(grid: &Grid, nodes: impl IntoIterator<Item = Node>) -> Snapped {
+    let mut nodes: Vec<_> = nodes.into_iter().collect();
+    nodes.sort_by_key(|node| node.id);
+
+    let mut snapped = Snapped::default();
+    let mut at_point: HashMap<(i64, i64), Vec<usize>> = HashMap::default();
+    for mut node in nodes {
+        grid.snap_node(&mut node);
+        let Some(key) = grid.key(&node) else {
+            snapped.nodes.push(node);
+            continue;
+        };
+        let candidates = at_point.entry(key).or_default();
+        let compatible = candidates.iter().copied().find(|i| {
+            let survivor = &snapped.nodes[*i];
+            node.tags.iter().all(|(key, value)| {
+                survivor
+                    .tags
+                    .get(key)
+                    .is_none_or(|existing| existing == value)
+            })
+        });
+        match compatible {
+            Some(i) => {
+                let survivor = &mut snapped.nodes[i];
+                snapped.merged.insert(node.id, survivor.id);
+                survivor.tags.extend(node.tags);
+            }
+            None => {
+                candidates.push(snapped.nodes.len());
+                snapped.nodes.push(node);
+            }
+        }
+    }
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        nodes = snapped.nodes.len(),
+        merged = snapped.merged.len(),
+        "snapped nodes"
+    );
+    snapped
+}
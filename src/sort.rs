@@ -0,0 +1,45 @@
+//! Canonical order of elements
+//!
+//! Planet files, extracts, and history files list nodes, then ways, then relations, each by
+//! ascending id and then by version. Writers and merges such as
+//! [apply_sorted](crate::change::apply_sorted) expect that order. [Element] does not
+//! implement [Ord] since equality compares all of its fields, and [SortKey] only the ones
+//! that decide the order.
+
+use std::cmp::Ordering;
+
+use crate::{Element, Id, MemberType};
+
+/// Fields of an [Element] that decide its position in the canonical order
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub struct SortKey {
+    pub ty: MemberType,
+    pub id: Id,
+    /// 0 without [Info](crate::Info)
+    pub version: i32,
+}
+
+impl Element {
+    pub fn sort_key(&self) -> SortKey {
+        SortKey {
+            ty: self.member_type(),
+            id: self.id(),
+            version: self.info().map_or(0, |info| info.version),
+        }
+    }
+}
+
+/// Compares elements by their [SortKey]
+pub fn compare(a: &Element, b: &Element) -> Ordering {
+    a.sort_key().cmp(&b.sort_key())
+}
+
+/// Sorts elements into the canonical order, keeping the order of elements with equal keys
+pub fn sort_elements(elements: &mut [Element]) {
+    elements.sort_by(compare);
+}
+
+/// Whether elements are in the canonical order
+pub fn is_sorted(elements: &[Element]) -> bool {
+    elements.is_sorted_by(|a, b| compare(a, b).is_le())
+}
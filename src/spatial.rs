@@ -0,0 +1,115 @@
+//! Spatial index over an [ElementStore], backed by an [R-tree](rstar)
+//!
+//! Nodes are indexed by their coordinates and ways by the box around their nodes, so that
+//! geometric operations can look up what lies in an area instead of scanning the store.
+
+use rstar::primitives::{GeomWithData, Rectangle};
+use rstar::{RTree, AABB};
+
+use crate::bbox::Bbox;
+use crate::geom::{LatLon, EARTH_RADIUS};
+use crate::id::ElementId;
+use crate::scalar::ScalarExt;
+use crate::store::ElementStore;
+use crate::{Id, Node};
+
+/// Index of the nodes and ways of a store, see [ElementStore::build_spatial_index]
+///
+/// The index does not follow later changes to the store.
+pub struct SpatialIndex {
+    /// Nodes as points and ways as boxes, in degrees of longitude and latitude
+    boxes: RTree<GeomWithData<Rectangle<[f64; 2]>, ElementId>>,
+    /// Nodes on the unit sphere, where the straight line between points orders them like the
+    /// distance along the surface
+    nodes: RTree<GeomWithData<[f64; 3], Id>>,
+}
+
+fn to_unit_sphere((lat, lon): LatLon) -> [f64; 3] {
+    let (lat, lon) = (lat.as_f64().to_radians(), lon.as_f64().to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+/// Distance in meters along the surface for a straight line through the unit sphere
+fn chord_to_meters(squared_chord: f64) -> f64 {
+    2. * EARTH_RADIUS * (squared_chord.sqrt() / 2.).min(1.).asin()
+}
+
+fn envelope(bbox: &Bbox) -> [[f64; 2]; 2] {
+    [
+        [bbox.min_lon.as_f64(), bbox.min_lat.as_f64()],
+        [bbox.max_lon.as_f64(), bbox.max_lat.as_f64()],
+    ]
+}
+
+impl SpatialIndex {
+    pub fn new(store: &ElementStore) -> Self {
+        let mut boxes = vec![];
+        let mut nodes = vec![];
+        for node in store.nodes() {
+            let point = [node.lon.as_f64(), node.lat.as_f64()];
+            let id = node.node_id().into();
+            boxes.push(GeomWithData::new(Rectangle::from_corners(point, point), id));
+            nodes.push(GeomWithData::new(to_unit_sphere(node.lat_lon()), node.id));
+        }
+        for way in store.ways() {
+            let Some(bbox) = way.bbox(|id| store.get_node(id).map(Node::lat_lon)) else {
+                continue;
+            };
+            let (east, west) = bbox.parts();
+            for part in [Some(east), west].iter().flatten() {
+                let [min, max] = envelope(part);
+                let id = way.way_id().into();
+                boxes.push(GeomWithData::new(Rectangle::from_corners(min, max), id));
+            }
+        }
+        Self {
+            boxes: RTree::bulk_load(boxes),
+            nodes: RTree::bulk_load(nodes),
+        }
+    }
+
+    /// Nodes inside `bbox` and ways whose box intersects it, with edges included
+    ///
+    /// Ways are matched by their box, so a way may be returned though none of its segments
+    /// pass through `bbox`. A box crossing the antimeridian is searched in both of its
+    /// [Bbox::parts].
+    pub fn query(&self, bbox: &Bbox) -> impl Iterator<Item = ElementId> + '_ {
+        let (east, west) = bbox.parts();
+        [Some(east), west]
+            .into_iter()
+            .flatten()
+            .flat_map(move |part| {
+                let [min, max] = envelope(&part);
+                self.boxes
+                    .locate_in_envelope_intersecting(&AABB::from_corners(min, max))
+                    .map(|entry| entry.data)
+            })
+    }
+
+    /// Closest node to the coordinate and its [crate::geom::distance] in meters
+    pub fn nearest_node(&self, coordinate: LatLon) -> Option<(Id, f64)> {
+        self.nodes
+            .nearest_neighbor_iter_with_distance_2(&to_unit_sphere(coordinate))
+            .next()
+            .map(|(entry, squared_chord)| (entry.data, chord_to_meters(squared_chord)))
+    }
+
+    /// Nodes within `distance` meters of the coordinate, with their distances, nearest first
+    pub fn nearest_nodes_within(
+        &self,
+        coordinate: LatLon,
+        distance: f64,
+    ) -> impl Iterator<Item = (Id, f64)> + '_ {
+        self.nodes
+            .nearest_neighbor_iter_with_distance_2(&to_unit_sphere(coordinate))
+            .map(|(entry, squared_chord)| (entry.data, chord_to_meters(squared_chord)))
+            .take_while(move |(_, meters)| *meters <= distance)
+    }
+}
+
+impl ElementStore {
+    /// See [SpatialIndex]
+    pub fn build_spatial_index(&self) -> SpatialIndex {
+        SpatialIndex::new(self)
+    }
+}
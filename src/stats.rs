@@ -0,0 +1,346 @@
+//! Frequencies of tags in the style of [taginfo](https://taginfo.openstreetmap.org)
+//!
+//! [TagStats] counts how many elements of each type use each key, each key and value, and
+//! each pair of keys. The counts can be read directly, ranked with [TagStats::top_keys] and
+//! friends, or written as CSV or JSON for dashboards. Keys and values are [TagString]s cloned
+//! from the elements, so with interned strings the tables share storage with the input.
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use fnv::FnvHashMap as HashMap;
+
+use crate::{Element, MemberType, TagString};
+
+/// Number of elements of each type
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeCounts {
+    pub nodes: u64,
+    pub ways: u64,
+    pub relations: u64,
+}
+
+impl TypeCounts {
+    /// Elements of all types
+    pub fn all(&self) -> u64 {
+        self.nodes + self.ways + self.relations
+    }
+
+    /// Elements of the type
+    pub fn get(&self, ty: &MemberType) -> u64 {
+        match ty {
+            MemberType::Node => self.nodes,
+            MemberType::Way => self.ways,
+            MemberType::Relation => self.relations,
+        }
+    }
+
+    fn add(&mut self, ty: &MemberType) {
+        match ty {
+            MemberType::Node => self.nodes += 1,
+            MemberType::Way => self.ways += 1,
+            MemberType::Relation => self.relations += 1,
+        }
+    }
+}
+
+/// Tag frequencies over a stream of elements
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct TagStats {
+    /// Elements counted, with or without tags
+    pub elements: TypeCounts,
+    /// Elements with any tags
+    pub tagged: TypeCounts,
+    /// Elements with each key
+    pub keys: HashMap<TagString, TypeCounts>,
+    /// Elements with each key and value
+    pub values: HashMap<(TagString, TagString), TypeCounts>,
+    /// Elements with both of each pair of keys, the lesser key first
+    pub combinations: HashMap<(TagString, TagString), TypeCounts>,
+}
+
+impl TagStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts the tags of the element
+    ///
+    /// Combinations grow with the square of the number of tags, which stays small for the
+    /// elements of real data.
+    pub fn add(&mut self, element: &Element) {
+        let ty = element.member_type();
+        self.elements.add(&ty);
+        let tags = element.tags();
+        if tags.is_empty() {
+            return;
+        }
+        self.tagged.add(&ty);
+        let mut keys: Vec<&TagString> = Vec::with_capacity(tags.len());
+        for (key, value) in tags.iter() {
+            self.keys.entry(key.clone()).or_default().add(&ty);
+            self.values
+                .entry((key.clone(), value.clone()))
+                .or_default()
+                .add(&ty);
+            keys.push(key);
+        }
+        keys.sort_unstable();
+        for (i, a) in keys.iter().enumerate() {
+            for b in &keys[i + 1..] {
+                self.combinations
+                    .entry(((*a).clone(), (*b).clone()))
+                    .or_default()
+                    .add(&ty);
+            }
+        }
+    }
+
+    /// Adds the counts of another [TagStats], e.g. of another part of a planet file
+    pub fn merge(&mut self, other: &TagStats) {
+        let add = |counts: &mut TypeCounts, other: &TypeCounts| {
+            counts.nodes += other.nodes;
+            counts.ways += other.ways;
+            counts.relations += other.relations;
+        };
+        add(&mut self.elements, &other.elements);
+        add(&mut self.tagged, &other.tagged);
+        for (key, counts) in &other.keys {
+            add(self.keys.entry(key.clone()).or_default(), counts);
+        }
+        for (tag, counts) in &other.values {
+            add(self.values.entry(tag.clone()).or_default(), counts);
+        }
+        for (pair, counts) in &other.combinations {
+            add(self.combinations.entry(pair.clone()).or_default(), counts);
+        }
+    }
+
+    /// Number of distinct values of the key
+    pub fn distinct_values(&self, key: &str) -> usize {
+        self.values.keys().filter(|(k, _)| k == key).count()
+    }
+
+    /// Keys used most often, most frequent first and then by key
+    pub fn top_keys(&self, n: usize) -> Vec<(&TagString, TypeCounts)> {
+        top(self.keys.iter(), n)
+    }
+
+    /// Values of the key used most often, most frequent first and then by value
+    pub fn top_values(&self, key: &str, n: usize) -> Vec<(&TagString, TypeCounts)> {
+        let values = self
+            .values
+            .iter()
+            .filter(|((k, _), _)| k == key)
+            .map(|((_, value), counts)| (value, counts));
+        top(values, n)
+    }
+
+    /// Keys used most often together with the key, most frequent first and then by key
+    pub fn top_combinations(&self, key: &str, n: usize) -> Vec<(&TagString, TypeCounts)> {
+        let others = self.combinations.iter().filter_map(|((a, b), counts)| {
+            if a == key {
+                Some((b, counts))
+            } else if b == key {
+                Some((a, counts))
+            } else {
+                None
+            }
+        });
+        top(others, n)
+    }
+
+    /// Writes `key,count_all,count_nodes,count_ways,count_relations,values` lines with a
+    /// header, most frequent key first
+    pub fn write_keys_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(
+            writer,
+            "key,count_all,count_nodes,count_ways,count_relations,values\r"
+        )?;
+        let distinct = self.distinct_per_key();
+        for (key, counts) in self.top_keys(usize::MAX) {
+            write_csv_field(&mut writer, key)?;
+            write_csv_counts(&mut writer, &counts)?;
+            writeln!(writer, ",{}\r", distinct.get(key.as_str()).unwrap_or(&0))?;
+        }
+        Ok(())
+    }
+
+    /// Writes `key,value,count_all,count_nodes,count_ways,count_relations` lines with a
+    /// header, by key and then most frequent value first
+    pub fn write_values_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(
+            writer,
+            "key,value,count_all,count_nodes,count_ways,count_relations\r"
+        )?;
+        for ((key, value), counts) in sorted(&self.values) {
+            write_csv_field(&mut writer, key)?;
+            writer.write_all(b",")?;
+            write_csv_field(&mut writer, value)?;
+            write_csv_counts(&mut writer, counts)?;
+            writer.write_all(b"\r\n")?;
+        }
+        Ok(())
+    }
+
+    /// Writes `key1,key2,count_all,count_nodes,count_ways,count_relations` lines with a
+    /// header, by the first key and then most frequent pair first
+    pub fn write_combinations_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(
+            writer,
+            "key1,key2,count_all,count_nodes,count_ways,count_relations\r"
+        )?;
+        for ((a, b), counts) in sorted(&self.combinations) {
+            write_csv_field(&mut writer, a)?;
+            writer.write_all(b",")?;
+            write_csv_field(&mut writer, b)?;
+            write_csv_counts(&mut writer, counts)?;
+            writer.write_all(b"\r\n")?;
+        }
+        Ok(())
+    }
+
+    fn distinct_per_key(&self) -> HashMap<&str, usize> {
+        let mut distinct: HashMap<&str, usize> = HashMap::default();
+        for (key, _) in self.values.keys() {
+            *distinct.entry(key.as_str()).or_default() += 1;
+        }
+        distinct
+    }
+
+    /// Writes all counts as one JSON object, with arrays ordered as in the CSV files
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut out = String::from("{\"elements\":");
+        json_counts(&mut out, &self.elements);
+        out.push_str(",\"tagged\":");
+        json_counts(&mut out, &self.tagged);
+        out.push_str(",\"keys\":[");
+        let distinct = self.distinct_per_key();
+        for (i, (key, counts)) in self.top_keys(usize::MAX).into_iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str("{\"key\":");
+            json_string(&mut out, key);
+            out.push(',');
+            json_fields(&mut out, &counts);
+            let values = distinct.get(key.as_str()).unwrap_or(&0);
+            let _ = write!(out, ",\"values\":{values}}}");
+        }
+        for (name, first, second, table) in [
+            ("values", "key", "value", &self.values),
+            ("combinations", "key1", "key2", &self.combinations),
+        ] {
+            let _ = write!(out, "],\"{name}\":[");
+            for (i, ((a, b), counts)) in sorted(table).into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                let _ = write!(out, "{{\"{first}\":");
+                json_string(&mut out, a);
+                let _ = write!(out, ",\"{second}\":");
+                json_string(&mut out, b);
+                out.push(',');
+                json_fields(&mut out, counts);
+                out.push('}');
+            }
+        }
+        out.push_str("]}");
+        writer.write_all(out.as_bytes())
+    }
+}
+
+impl<'a> Extend<&'a Element> for TagStats {
+    fn extend<T: IntoIterator<Item = &'a Element>>(&mut self, iter: T) {
+        for element in iter {
+            self.add(element);
+        }
+    }
+}
+
+impl<'a> FromIterator<&'a Element> for TagStats {
+    fn from_iter<T: IntoIterator<Item = &'a Element>>(iter: T) -> Self {
+        let mut stats = Self::new();
+        stats.extend(iter);
+        stats
+    }
+}
+
+fn top<'a>(
+    counts: impl Iterator<Item = (&'a TagString, &'a TypeCounts)>,
+    n: usize,
+) -> Vec<(&'a TagString, TypeCounts)> {
+    let mut top: Vec<_> = counts.map(|(key, counts)| (key, *counts)).collect();
+    top.sort_unstable_by(|a, b| b.1.all().cmp(&a.1.all()).then_with(|| a.0.cmp(b.0)));
+    top.truncate(n);
+    top
+}
+
+/// Entries by their first string, then most frequent first, then by their second string
+fn sorted(
+    table: &HashMap<(TagString, TagString), TypeCounts>,
+) -> Vec<(&(TagString, TagString), &TypeCounts)> {
+    let mut sorted: Vec<_> = table.iter().collect();
+    sorted.sort_unstable_by(|a, b| {
+        (a.0 .0.cmp(&b.0 .0))
+            .then_with(|| b.1.all().cmp(&a.1.all()))
+            .then_with(|| a.0 .1.cmp(&b.0 .1))
+    });
+    sorted
+}
+
+/// Writes a field following [RFC 4180](https://datatracker.ietf.org/doc/html/rfc4180)
+fn write_csv_field<W: Write>(mut writer: W, field: &str) -> io::Result<()> {
+    if field.contains([',', '"', '\n', '\r']) {
+        write!(writer, "\"{}\"", field.replace('"', "\"\""))
+    } else {
+        writer.write_all(field.as_bytes())
+    }
+}
+
+fn write_csv_counts<W: Write>(mut writer: W, counts: &TypeCounts) -> io::Result<()> {
+    write!(
+        writer,
+        ",{},{},{},{}",
+        counts.all(),
+        counts.nodes,
+        counts.ways,
+        counts.relations
+    )
+}
+
+fn json_fields(out: &mut String, counts: &TypeCounts) {
+    let _ = write!(
+        out,
+        "\"count_all\":{},\"count_nodes\":{},\"count_ways\":{},\"count_relations\":{}",
+        counts.all(),
+        counts.nodes,
+        counts.ways,
+        counts.relations
+    );
+}
+
+fn json_counts(out: &mut String, counts: &TypeCounts) {
+    out.push('{');
+    json_fields(out, counts);
+    out.push('}');
+}
+
+fn json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if u32::from(c) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
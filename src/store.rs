@@ -0,0 +1,813 @@
+//! In-memory collections of elements, and node coordinates that can also live in a file
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::mem::size_of;
+use std::path::Path;
+
+use fnv::{FnvHashMap as HashMap, FnvHashSet as HashSet};
+use memmap2::MmapMut;
+
+use crate::change::{Action, ConflictKind, OsmChange};
+use crate::error::{ElementContext, Error, Result};
+use crate::geom::LatLon;
+use crate::locations::{from_fixed, to_fixed};
+use crate::{
+    Element, ElementId, Id, Info, MemberType, Node, NodeId, Relation, RelationId, Tags, Way, WayId,
+};
+
+/// Tag maps with at most this many tags are deduplicated by default
+///
+/// Larger maps usually contain unique tags like `name` and are rarely identical.
+pub const DEFAULT_DEDUP_MAX_TAGS: usize = 4;
+
+/// Nodes, ways, and relations, each keyed by id
+///
+/// Identical tag maps are shared between elements on insert, see [ElementStore::stats] for
+/// the memory this saves.
+#[derive(Debug, Clone)]
+pub struct ElementStore {
+    nodes: HashMap<Id, Node>,
+    ways: HashMap<Id, Way>,
+    relations: HashMap<Id, Relation>,
+    tag_pool: Option<TagPool>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct TagPool {
+    max_tags: usize,
+    maps: HashSet<Tags>,
+    deduplicated: u64,
+    saved_bytes: u64,
+}
+
+impl TagPool {
+    fn dedup(&mut self, tags: &mut Tags) {
+        if tags.is_empty() || tags.len() > self.max_tags {
+            return;
+        }
+        match self.maps.get(tags) {
+            Some(shared) if !shared.ptr_eq(tags) => {
+                if !tags.is_shared() {
+                    self.saved_bytes += tags.estimated_heap_size() as u64;
+                }
+                self.deduplicated += 1;
+                *tags = shared.clone();
+            }
+            Some(_) => {}
+            None => {
+                self.maps.insert(tags.clone());
+            }
+        }
+    }
+
+    /// Whether `tags` are a map of the pool
+    fn holds(&self, tags: &Tags) -> bool {
+        self.maps
+            .get(tags)
+            .is_some_and(|shared| shared.ptr_eq(tags))
+    }
+
+    /// Estimated heap bytes of the pool, its table and the maps it shares
+    fn heap_size(&self) -> usize {
+        self.maps.capacity() * (size_of::<Tags>() + 1)
+            + self
+                .maps
+                .iter()
+                .map(Tags::estimated_heap_size)
+                .sum::<usize>()
+    }
+}
+
+/// Counts returned by [ElementStore::stats]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StoreStats {
+    pub nodes: usize,
+    pub ways: usize,
+    pub relations: usize,
+    /// Distinct tag maps available for sharing
+    pub pooled_tag_maps: usize,
+    /// Inserted elements whose tags were replaced by a shared map
+    pub deduplicated_tag_maps: u64,
+    /// Estimated heap bytes freed by sharing tag maps, counting the map table and strings
+    pub saved_bytes: u64,
+    /// Estimated bytes of the nodes, their table in the store and their allocations
+    pub node_bytes: usize,
+    /// Estimated bytes of the ways, their table in the store and their allocations
+    pub way_bytes: usize,
+    /// Estimated bytes of the relations, their table in the store and their allocations
+    pub relation_bytes: usize,
+    /// Estimated bytes of the pooled tag maps, which are left out of the bytes of elements
+    /// sharing them
+    pub tag_pool_bytes: usize,
+}
+
+impl StoreStats {
+    /// Estimated bytes of the whole store
+    pub fn total_bytes(&self) -> usize {
+        self.node_bytes + self.way_bytes + self.relation_bytes + self.tag_pool_bytes
+    }
+}
+
+/// Outcome of [ElementStore::apply_change]
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ApplyReport {
+    pub created: usize,
+    pub modified: usize,
+    pub deleted: usize,
+    /// Changes that were skipped, in the order of the document
+    pub conflicts: Vec<ChangeConflict>,
+}
+
+impl ApplyReport {
+    /// Number of changes applied
+    pub fn applied(&self) -> usize {
+        self.created + self.modified + self.deleted
+    }
+}
+
+/// Change skipped by [ElementStore::apply_change]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ChangeConflict {
+    pub kind: ConflictKind,
+    /// Position of the change in [OsmChange::changes]
+    pub index: usize,
+    pub element: ElementContext,
+    /// Version of the change, if it has [Info]
+    pub version: Option<i32>,
+    /// Version of the element in the store, if it exists and has [Info]
+    pub base_version: Option<i32>,
+}
+
+/// Levels of nested relations [ElementStore::resolve_relation_recursive] follows by default
+///
+/// Route masters and boundary hierarchies rarely nest more than a few levels.
+pub const DEFAULT_MAX_DEPTH: usize = 16;
+
+/// Members of a relation and of the relations nested in it, see [ElementStore::descendants]
+///
+/// Each element is listed once, in the order it is first reached. Nodes of member ways are not
+/// included.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Descendants {
+    pub nodes: Vec<Id>,
+    pub ways: Vec<Id>,
+    /// Nested relations, without the relation itself
+    pub relations: Vec<Id>,
+    /// Members not in the store
+    pub missing: Vec<ElementId>,
+    /// Relations that are members of their own descendants, which were not followed again
+    pub cycles: Vec<Id>,
+}
+
+impl Default for ElementStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ElementStore {
+    /// Empty store deduplicating tag maps of up to [DEFAULT_DEDUP_MAX_TAGS] tags
+    pub fn new() -> Self {
+        Self::with_tag_dedup(DEFAULT_DEDUP_MAX_TAGS)
+    }
+
+    /// Empty store deduplicating tag maps of up to `max_tags` tags
+    pub fn with_tag_dedup(max_tags: usize) -> Self {
+        Self {
+            tag_pool: Some(TagPool {
+                max_tags,
+                ..TagPool::default()
+            }),
+            ..Self::without_tag_dedup()
+        }
+    }
+
+    /// Empty store that keeps tag maps as inserted
+    pub fn without_tag_dedup() -> Self {
+        Self {
+            nodes: HashMap::default(),
+            ways: HashMap::default(),
+            relations: HashMap::default(),
+            tag_pool: None,
+        }
+    }
+
+    /// Inserts an element, returning the element of the same type and id it replaces
+    pub fn insert(&mut self, mut element: Element) -> Option<Element> {
+        if let Some(pool) = &mut self.tag_pool {
+            pool.dedup(element.tags_mut());
+        }
+        match element {
+            Element::Node(node) => self.nodes.insert(node.id, node).map(Element::Node),
+            Element::Way(way) => self.ways.insert(way.id, way).map(Element::Way),
+            Element::Relation(relation) => self
+                .relations
+                .insert(relation.id, relation)
+                .map(Element::Relation),
+        }
+    }
+
+    /// Removes and returns the element of a type and id
+    pub fn remove(&mut self, ty: &MemberType, id: Id) -> Option<Element> {
+        match ty {
+            MemberType::Node => self.nodes.remove(&id).map(Element::Node),
+            MemberType::Way => self.ways.remove(&id).map(Element::Way),
+            MemberType::Relation => self.relations.remove(&id).map(Element::Relation),
+        }
+    }
+
+    /// Applies the changes of a document in order, e.g. to keep an extract up to date with
+    /// [replication diffs](https://wiki.openstreetmap.org/wiki/Planet.osm/diffs)
+    ///
+    /// Changes that conflict with the store are skipped and reported: creating an element that
+    /// exists, modifying or deleting one that does not, and versions that are not newer than
+    /// the stored one. Versions are only compared if both elements have [Info]. Modifications
+    /// of elements outside an extract show up as [ConflictKind::MissingBase].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(changes = change.changes.len())))]
+    pub fn apply_change(&mut self, change: &OsmChange) -> ApplyReport {
+        let mut report = ApplyReport::default();
+        for (index, change) in change.changes.iter().enumerate() {
+            let element = &change.element;
+            let ty = element.member_type();
+            let id = element.id();
+            let version = element.info().map(|info| info.version);
+            // Outer option for whether the element exists
+            let base = self.info(&ty, id).map(|info| info.map(|info| info.version));
+
+            let kind = match (base, change.action) {
+                (None, Action::Create) => None,
+                (None, _) => Some(ConflictKind::MissingBase),
+                (Some(_), Action::Create) => Some(ConflictKind::AlreadyExists),
+                (Some(Some(old)), _) if version.is_some_and(|new| new <= old) => {
+                    Some(ConflictKind::Stale)
+                }
+                (Some(_), _) => None,
+            };
+            if let Some(kind) = kind {
+                report.conflicts.push(ChangeConflict {
+                    kind,
+                    index,
+                    element: ElementContext { ty, id },
+                    version,
+                    base_version: base.flatten(),
+                });
+                continue;
+            }
+            match change.action {
+                Action::Create => report.created += 1,
+                Action::Modify => report.modified += 1,
+                Action::Delete => report.deleted += 1,
+            }
+            if change.action == Action::Delete {
+                self.remove(&ty, id);
+            } else {
+                self.insert(element.clone());
+            }
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            applied = report.applied(),
+            conflicts = report.conflicts.len(),
+            "applied change"
+        );
+        report
+    }
+
+    /// Info of an element, if it exists
+    fn info(&self, ty: &MemberType, id: Id) -> Option<Option<&Info>> {
+        match ty {
+            MemberType::Node => self.nodes.get(&id).map(|node| node.info.as_ref()),
+            MemberType::Way => self.ways.get(&id).map(|way| way.info.as_ref()),
+            MemberType::Relation => self
+                .relations
+                .get(&id)
+                .map(|relation| relation.info.as_ref()),
+        }
+    }
+
+    pub fn get_node(&self, id: impl Into<NodeId>) -> Option<&Node> {
+        self.nodes.get(&id.into().into())
+    }
+
+    pub fn get_way(&self, id: impl Into<WayId>) -> Option<&Way> {
+        self.ways.get(&id.into().into())
+    }
+
+    pub fn get_relation(&self, id: impl Into<RelationId>) -> Option<&Relation> {
+        self.relations.get(&id.into().into())
+    }
+
+    pub fn contains(&self, id: ElementId) -> bool {
+        match id {
+            ElementId::Node(id) => self.nodes.contains_key(&id.into()),
+            ElementId::Way(id) => self.ways.contains_key(&id.into()),
+            ElementId::Relation(id) => self.relations.contains_key(&id.into()),
+        }
+    }
+
+    /// Nodes of a way in order, [None] for those not in the store
+    pub fn way_nodes<'a>(&'a self, way: &'a Way) -> impl Iterator<Item = Option<&'a Node>> + 'a {
+        way.refs.iter().map(|&id| self.get_node(id))
+    }
+
+    /// Whether all nodes of a way or all members of a relation are in the store
+    ///
+    /// Nested relations are not checked for their own members.
+    pub fn is_complete(&self, element: &Element) -> bool {
+        match element {
+            Element::Node(_) => true,
+            Element::Way(way) => way.refs.iter().all(|&id| self.nodes.contains_key(&id)),
+            Element::Relation(relation) => relation
+                .members
+                .iter()
+                .all(|member| self.contains(member.element_id())),
+        }
+    }
+
+    /// Ways that refer to a node, found by scanning all ways
+    pub fn ways_of_node(&self, id: impl Into<NodeId>) -> impl Iterator<Item = &Way> {
+        let id = Id::from(id.into());
+        self.ways.values().filter(move |way| way.refs.contains(&id))
+    }
+
+    /// Relations that have an element as a member, found by scanning all relations
+    pub fn relations_of(&self, id: ElementId) -> impl Iterator<Item = &Relation> {
+        self.relations.values().filter(move |relation| {
+            relation
+                .members
+                .iter()
+                .any(|member| member.element_id() == id)
+        })
+    }
+
+    /// Members of a relation, following nested relations at any depth
+    ///
+    /// Each relation is visited once, so cycles end and are listed in [Descendants::cycles].
+    pub fn descendants(&self, relation: &Relation) -> Descendants {
+        self.walk_relations(relation, usize::MAX).0
+    }
+
+    /// Members of a relation, failing if relations are nested more than `max_depth` levels
+    /// below it, e.g. [DEFAULT_MAX_DEPTH]
+    pub fn resolve_relation_recursive(
+        &self,
+        relation: &Relation,
+        max_depth: usize,
+    ) -> Result<Descendants> {
+        match self.walk_relations(relation, max_depth) {
+            (descendants, None) => Ok(descendants),
+            (_, Some(id)) => Err(Error::Validation {
+                element: Some(ElementContext {
+                    ty: MemberType::Relation,
+                    id,
+                }),
+                message: format!("relations nested more than {max_depth} levels deep"),
+            }),
+        }
+    }
+
+    /// Walks the members depth first, stopping at the first relation below `max_depth` and
+    /// returning its id
+    fn walk_relations(&self, root: &Relation, max_depth: usize) -> (Descendants, Option<Id>) {
+        let mut descendants = Descendants::default();
+        let mut seen = HashSet::default();
+        let mut on_path = HashSet::default();
+        on_path.insert(root.id);
+        seen.insert(ElementId::Relation(root.id.into()));
+        let mut stack = vec![(root, 0)];
+        while let Some((relation, index)) = stack.last_mut() {
+            let relation: &Relation = relation;
+            let Some(member) = relation.members.get(*index) else {
+                on_path.remove(&relation.id);
+                stack.pop();
+                continue;
+            };
+            *index += 1;
+            let id = member.element_id();
+            if member.ty == MemberType::Relation && on_path.contains(&member.id) {
+                if !descendants.cycles.contains(&member.id) {
+                    descendants.cycles.push(member.id);
+                }
+                continue;
+            }
+            if !seen.insert(id) {
+                continue;
+            }
+            if !self.contains(id) {
+                descendants.missing.push(id);
+                continue;
+            }
+            match member.ty {
+                MemberType::Node => descendants.nodes.push(member.id),
+                MemberType::Way => descendants.ways.push(member.id),
+                MemberType::Relation => {
+                    if stack.len() > max_depth {
+                        return (descendants, Some(member.id));
+                    }
+                    descendants.relations.push(member.id);
+                    on_path.insert(member.id);
+                    stack.extend(self.relations.get(&member.id).map(|child| (child, 0)));
+                }
+            }
+        }
+        (descendants, None)
+    }
+
+    /// Copies of all elements sorted by type (nodes, ways, then relations) and then by id,
+    /// the order of planet files
+    pub fn iter_sorted(&self) -> impl Iterator<Item = Element> + '_ {
+        fn sorted<T>(map: &HashMap<Id, T>) -> Vec<&T> {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_unstable_by_key(|(id, _)| **id);
+            entries.into_iter().map(|(_, value)| value).collect()
+        }
+        let nodes = sorted(&self.nodes).into_iter().cloned().map(Element::Node);
+        let ways = sorted(&self.ways).into_iter().cloned().map(Element::Way);
+        let relations = sorted(&self.relations)
+            .into_iter()
+            .cloned()
+            .map(Element::Relation);
+        nodes.chain(ways).chain(relations)
+    }
+
+    /// Nodes in no particular order
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.nodes.values()
+    }
+
+    /// Ways in no particular order
+    pub fn ways(&self) -> impl Iterator<Item = &Way> {
+        self.ways.values()
+    }
+
+    /// Relations in no particular order
+    pub fn relations(&self) -> impl Iterator<Item = &Relation> {
+        self.relations.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len() + self.ways.len() + self.relations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forgets pooled tag maps that no element uses anymore, e.g. after modifying tags
+    pub fn compact_tag_pool(&mut self) {
+        if let Some(pool) = &mut self.tag_pool {
+            pool.maps.retain(Tags::is_shared);
+        }
+    }
+
+    /// Counts of elements and tag maps, along with estimates of the memory they use
+    ///
+    /// Estimating memory takes a pass over all elements, see [crate::memory] for what is
+    /// counted.
+    pub fn stats(&self) -> StoreStats {
+        let pool = self.tag_pool.as_ref();
+        // Pooled maps are counted once in `tag_pool_bytes`
+        let pooled = |tags: &Tags| match pool {
+            Some(pool) if pool.holds(tags) => tags.estimated_heap_size(),
+            _ => 0,
+        };
+        StoreStats {
+            nodes: self.nodes.len(),
+            ways: self.ways.len(),
+            relations: self.relations.len(),
+            pooled_tag_maps: pool.map_or(0, |pool| pool.maps.len()),
+            deduplicated_tag_maps: pool.map_or(0, |pool| pool.deduplicated),
+            saved_bytes: pool.map_or(0, |pool| pool.saved_bytes),
+            node_bytes: table_size(&self.nodes)
+                + (self.nodes.values())
+                    .map(|node| node.estimated_heap_size() - pooled(&node.tags))
+                    .sum::<usize>(),
+            way_bytes: table_size(&self.ways)
+                + (self.ways.values())
+                    .map(|way| way.estimated_heap_size() - pooled(&way.tags))
+                    .sum::<usize>(),
+            relation_bytes: table_size(&self.relations)
+                + (self.relations.values())
+                    .map(|relation| relation.estimated_heap_size() - pooled(&relation.tags))
+                    .sum::<usize>(),
+            tag_pool_bytes: pool.map_or(0, TagPool::heap_size),
+        }
+    }
+}
+
+/// Estimated heap bytes of the table of a map, with one control byte per entry
+fn table_size<V>(map: &HashMap<Id, V>) -> usize {
+    map.capacity() * (size_of::<(Id, V)>() + 1)
+}
+
+impl Extend<Element> for ElementStore {
+    fn extend<I: IntoIterator<Item = Element>>(&mut self, elements: I) {
+        for element in elements {
+            self.insert(element);
+        }
+    }
+}
+
+impl FromIterator<Element> for ElementStore {
+    fn from_iter<I: IntoIterator<Item = Element>>(elements: I) -> Self {
+        let mut store = Self::new();
+        store.extend(elements);
+        store
+    }
+}
+
+/// Coordinate of missing nodes in a [DenseNodeStore], as in osmium
+const UNDEFINED: i32 = i32::MAX;
+
+/// Flatnodes files grow by at least this many nodes at a time
+const FLATNODES_GROWTH: u64 = 1 << 16;
+
+/// Node coordinates by id, to resolve the geometry of ways in large extracts or planets
+///
+/// Untagged nodes with positive ids, the vast majority, only keep their coordinates, as
+/// 32-bit fixed point with 7 decimal places in a flat array indexed by id. That takes 8 bytes
+/// per id up to the largest one, instead of a whole [Node] plus a hash table entry per node.
+/// Their [Info] is dropped. Other nodes are kept whole in a map.
+///
+/// The array is held in memory or in a
+/// [flatnodes](https://osm2pgsql.org/doc/manual.html#flat-node-store) file in the layout of
+/// osmium and osm2pgsql on little-endian machines: longitude, then latitude, with [i32::MAX]
+/// for missing nodes. A file can be reused to resolve ways of later runs or diffs. It is mapped
+/// into memory, so lookups are as cheap as with the array in memory once its pages are cached.
+#[derive(Debug)]
+pub struct DenseNodeStore {
+    coordinates: Coordinates,
+    tagged: HashMap<Id, Node>,
+}
+
+#[derive(Debug)]
+enum Coordinates {
+    /// Longitude and latitude of each id
+    Memory(Vec<[i32; 2]>),
+    File(FlatNodes),
+}
+
+/// Flatnodes file mapped into memory, so lookups index into it without a system call
+#[derive(Debug)]
+struct FlatNodes {
+    file: File,
+    /// Whole file, [None] while it is empty since empty files cannot be mapped
+    map: Option<MmapMut>,
+}
+
+impl FlatNodes {
+    fn open(file: File) -> io::Result<Self> {
+        let mut flatnodes = Self { file, map: None };
+        flatnodes.remap()?;
+        Ok(flatnodes)
+    }
+
+    /// Number of ids in the file
+    fn len(&self) -> u64 {
+        self.map.as_ref().map_or(0, |map| map.len() as u64 / 8)
+    }
+
+    fn remap(&mut self) -> io::Result<()> {
+        self.map = None;
+        if self.file.metadata()?.len() > 0 {
+            // SAFETY: the mapping is only changed through the store, which holds the file open
+            // for the lifetime of the map. Like osmium and osm2pgsql, other processes are
+            // expected not to truncate or write a flatnodes file while it is in use.
+            self.map = Some(unsafe { MmapMut::map_mut(&self.file)? });
+        }
+        Ok(())
+    }
+
+    fn read(&self, index: u64) -> [i32; 2] {
+        let Some(map) = &self.map else {
+            return [UNDEFINED; 2];
+        };
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| map.get(index.checked_mul(8)?..)?.first_chunk::<8>())
+            .map_or([UNDEFINED; 2], |bytes| decode(*bytes))
+    }
+
+    fn write(&mut self, index: u64, coordinates: [i32; 2]) -> io::Result<()> {
+        if index >= self.len() {
+            if coordinates == [UNDEFINED; 2] {
+                return Ok(());
+            }
+            self.grow(index + 1)?;
+        }
+        let map = self.map.as_mut().expect("grown file is mapped");
+        let start = index as usize * 8;
+        map[start..start + 8].copy_from_slice(&encode(coordinates));
+        Ok(())
+    }
+
+    /// Fills the file with missing nodes up to at least `len` ids
+    fn grow(&mut self, len: u64) -> io::Result<()> {
+        let old = self.len();
+        let len = len.max(old + FLATNODES_GROWTH);
+        let bytes = len
+            .checked_mul(8)
+            .filter(|&bytes| usize::try_from(bytes).is_ok());
+        let Some(bytes) = bytes else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "node id is too large for a flatnodes file",
+            ));
+        };
+        self.file.set_len(bytes)?;
+        self.remap()?;
+        let map = self.map.as_mut().expect("grown file is mapped");
+        for node in map[old as usize * 8..].chunks_exact_mut(8) {
+            node.copy_from_slice(&encode([UNDEFINED; 2]));
+        }
+        Ok(())
+    }
+}
+
+fn encode([lon, lat]: [i32; 2]) -> [u8; 8] {
+    let mut bytes = [0; 8];
+    bytes[..4].copy_from_slice(&lon.to_le_bytes());
+    bytes[4..].copy_from_slice(&lat.to_le_bytes());
+    bytes
+}
+
+fn decode(bytes: [u8; 8]) -> [i32; 2] {
+    [
+        i32::from_le_bytes(bytes[..4].try_into().unwrap()),
+        i32::from_le_bytes(bytes[4..].try_into().unwrap()),
+    ]
+}
+
+impl Default for DenseNodeStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DenseNodeStore {
+    /// Empty store holding coordinates in memory
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Empty store with memory for the coordinates of ids below `max_id`
+    pub fn with_capacity(max_id: usize) -> Self {
+        Self {
+            coordinates: Coordinates::Memory(Vec::with_capacity(max_id)),
+            tagged: HashMap::default(),
+        }
+    }
+
+    /// Store holding coordinates in a flatnodes file, created if it does not exist
+    ///
+    /// Coordinates already in the file are kept. Tagged nodes are only held in memory.
+    pub fn with_flatnodes(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let bytes = file.metadata()?.len();
+        if bytes % 8 != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "flatnodes file is not a whole number of nodes",
+            ));
+        }
+        Ok(Self {
+            coordinates: Coordinates::File(FlatNodes::open(file)?),
+            tagged: HashMap::default(),
+        })
+    }
+
+    /// Inserts a node, replacing one with the same id
+    ///
+    /// Fails if an untagged node has a coordinate that does not fit in 32-bit fixed point with
+    /// 7 decimal places, or if the file cannot be written.
+    pub fn insert(&mut self, node: Node) -> Result<()> {
+        let Ok(index) = u64::try_from(node.id.0) else {
+            self.tagged.insert(node.id, node);
+            return Ok(());
+        };
+        if !node.tags.is_empty() {
+            self.set(index, [UNDEFINED; 2])?;
+            self.tagged.insert(node.id, node);
+            return Ok(());
+        }
+        let coordinates =
+            to_fixed(node.lon)
+                .zip(to_fixed(node.lat))
+                .ok_or_else(|| Error::Validation {
+                    element: Some(ElementContext {
+                        ty: MemberType::Node,
+                        id: node.id,
+                    }),
+                    message: "coordinates do not fit in 32-bit fixed point".to_string(),
+                })?;
+        self.tagged.remove(&node.id);
+        self.set(index, [coordinates.0, coordinates.1])
+    }
+
+    fn set(&mut self, index: u64, coordinates: [i32; 2]) -> Result<()> {
+        match &mut self.coordinates {
+            Coordinates::Memory(array) => {
+                let index = usize::try_from(index).map_err(|_| Error::Validation {
+                    element: None,
+                    message: format!("node id {index} is too large for memory"),
+                })?;
+                if index >= array.len() {
+                    if coordinates == [UNDEFINED; 2] {
+                        return Ok(());
+                    }
+                    array.resize(index + 1, [UNDEFINED; 2]);
+                }
+                array[index] = coordinates;
+                Ok(())
+            }
+            Coordinates::File(file) => Ok(file.write(index, coordinates)?),
+        }
+    }
+
+    /// Location of a node, tagged or not
+    ///
+    /// Does not fail, since a flatnodes file is read through its memory map.
+    pub fn location(&self, id: impl Into<NodeId>) -> Result<Option<LatLon>> {
+        let id = Id::from(id.into());
+        if let Some(node) = self.tagged.get(&id) {
+            return Ok(Some((node.lat, node.lon)));
+        }
+        let Ok(index) = u64::try_from(id.0) else {
+            return Ok(None);
+        };
+        let [lon, lat] = match &self.coordinates {
+            Coordinates::Memory(array) => usize::try_from(index)
+                .ok()
+                .and_then(|index| array.get(index))
+                .copied()
+                .unwrap_or([UNDEFINED; 2]),
+            Coordinates::File(file) => file.read(index),
+        };
+        Ok((lon != UNDEFINED).then(|| (from_fixed(lat), from_fixed(lon))))
+    }
+
+    /// Locations of the nodes of a way in order, [None] for those not in the store
+    pub fn way_locations<'a>(
+        &'a self,
+        way: &'a Way,
+    ) -> impl Iterator<Item = Result<Option<LatLon>>> + 'a {
+        way.refs.iter().map(|&id| self.location(id))
+    }
+
+    /// Node with tags or a negative id, which is kept whole
+    pub fn get_tagged(&self, id: impl Into<NodeId>) -> Option<&Node> {
+        self.tagged.get(&id.into().into())
+    }
+
+    /// Nodes kept whole, in no particular order
+    pub fn tagged_nodes(&self) -> impl Iterator<Item = &Node> {
+        self.tagged.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scalar::{Scalar, ScalarExt};
+
+    fn node(id: i64, lat: i64, lon: i64) -> Node {
+        Node::builder(Id(id))
+            .lat(Scalar::from_int(lat))
+            .lon(Scalar::from_int(lon))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn flatnodes_are_kept_between_runs() {
+        let path = std::env::temp_dir().join(format!("osm-types-{}.flatnodes", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut store = DenseNodeStore::with_flatnodes(&path).unwrap();
+        assert_eq!(store.location(NodeId(1)).unwrap(), None);
+        store.insert(node(1, 10, 20)).unwrap();
+        store.insert(node(100_000, -45, 170)).unwrap();
+        assert_eq!(
+            store.location(NodeId(1)).unwrap(),
+            Some((Scalar::from_int(10), Scalar::from_int(20)))
+        );
+        assert_eq!(store.location(NodeId(2)).unwrap(), None);
+        drop(store);
+
+        let store = DenseNodeStore::with_flatnodes(&path).unwrap();
+        assert_eq!(
+            store.location(NodeId(100_000)).unwrap(),
+            Some((Scalar::from_int(-45), Scalar::from_int(170)))
+        );
+        assert_eq!(store.location(NodeId(1 << 40)).unwrap(), None);
+        drop(store);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,159 @@
+//! String type of tags, roles, and user names
+//!
+//! [TagString] is a [kstring::KString] by default, which stores short strings inline and
+//! borrows static ones. Workloads with different tradeoffs can pick another representation with a feature:
+//!
+//! - `arc-str`: [`Arc<str>`](std::sync::Arc), for cheap clones of strings shared between
+//!   many elements
+//! - `box-str`: [`Box<str>`], for the smallest allocation per string
+//! - `compact-str`: [CompactString](https://docs.rs/compact_str), which stores strings of up
+//!   to 24 bytes inline
+//!
+//! The representations have the same API, so code written against [TagString] works with
+//! any of them. If several features are enabled, the first in the list above wins.
+
+#[cfg(not(any(feature = "arc-str", feature = "box-str", feature = "compact-str")))]
+pub use kstring::KString as TagString;
+
+#[cfg(any(feature = "arc-str", feature = "box-str", feature = "compact-str"))]
+pub use repr::TagString;
+
+#[cfg(any(feature = "arc-str", feature = "box-str", feature = "compact-str"))]
+mod repr {
+    use alloc::borrow::ToOwned;
+    use alloc::boxed::Box;
+    use alloc::string::String;
+    use core::borrow::Borrow;
+    use core::fmt;
+    use core::ops::Deref;
+
+    #[cfg(feature = "arc-str")]
+    type Repr = alloc::sync::Arc<str>;
+    #[cfg(all(feature = "box-str", not(feature = "arc-str")))]
+    type Repr = Box<str>;
+    #[cfg(all(
+        feature = "compact-str",
+        not(any(feature = "arc-str", feature = "box-str"))
+    ))]
+    type Repr = compact_str::CompactString;
+
+    /// String with the same API as [kstring::KString]
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Default)]
+    pub struct TagString(Repr);
+
+    impl TagString {
+        pub fn from_ref(s: &str) -> Self {
+            Self(s.into())
+        }
+
+        pub fn from_static(s: &'static str) -> Self {
+            Self(s.into())
+        }
+
+        pub fn from_string(s: String) -> Self {
+            Self(s.into())
+        }
+
+        // A no-op with `box-str`
+        #[allow(clippy::useless_conversion)]
+        pub fn from_boxed(s: Box<str>) -> Self {
+            Self(s.into())
+        }
+
+        pub fn as_str(&self) -> &str {
+            &self.0
+        }
+
+        pub fn into_string(self) -> String {
+            self.as_str().to_owned()
+        }
+    }
+
+    impl Deref for TagString {
+        type Target = str;
+
+        fn deref(&self) -> &str {
+            &self.0
+        }
+    }
+
+    impl AsRef<str> for TagString {
+        fn as_ref(&self) -> &str {
+            &self.0
+        }
+    }
+
+    impl Borrow<str> for TagString {
+        fn borrow(&self) -> &str {
+            &self.0
+        }
+    }
+
+    impl fmt::Debug for TagString {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            fmt::Debug::fmt(self.as_str(), f)
+        }
+    }
+
+    impl fmt::Display for TagString {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            fmt::Display::fmt(self.as_str(), f)
+        }
+    }
+
+    impl From<&str> for TagString {
+        fn from(s: &str) -> Self {
+            Self::from_ref(s)
+        }
+    }
+
+    impl From<&String> for TagString {
+        fn from(s: &String) -> Self {
+            Self::from_ref(s)
+        }
+    }
+
+    impl From<String> for TagString {
+        fn from(s: String) -> Self {
+            Self::from_string(s)
+        }
+    }
+
+    impl From<Box<str>> for TagString {
+        fn from(s: Box<str>) -> Self {
+            Self::from_boxed(s)
+        }
+    }
+
+    impl PartialEq<str> for TagString {
+        fn eq(&self, other: &str) -> bool {
+            self.as_str() == other
+        }
+    }
+
+    impl PartialEq<&str> for TagString {
+        fn eq(&self, other: &&str) -> bool {
+            self.as_str() == *other
+        }
+    }
+
+    impl PartialEq<String> for TagString {
+        fn eq(&self, other: &String) -> bool {
+            self.as_str() == other
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl serde::Serialize for TagString {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(self)
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl<'de> serde::Deserialize<'de> for TagString {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            String::deserialize(deserializer).map(Self::from_string)
+        }
+    }
+}
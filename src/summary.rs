@@ -0,0 +1,148 @@
+//! Summaries of edits for changeset review
+//!
+//! [EditSummary::new] takes the changes of a changeset, e.g. from
+//! `/api/0.6/changeset/#id/download`, along with the previous version of each changed element.
+
+use fnv::FnvHashMap as HashMap;
+
+use crate::bbox::Bbox;
+use crate::change::{Action, Change};
+use crate::geom;
+use crate::{Element, TagString};
+
+/// Number of elements of one type by action
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ActionCounts {
+    pub created: u64,
+    pub modified: u64,
+    pub deleted: u64,
+}
+
+impl ActionCounts {
+    pub fn total(&self) -> u64 {
+        self.created + self.modified + self.deleted
+    }
+
+    fn add(&mut self, action: Action) {
+        match action {
+            Action::Create => self.created += 1,
+            Action::Modify => self.modified += 1,
+            Action::Delete => self.deleted += 1,
+        }
+    }
+}
+
+/// Structured overview of a set of changes
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EditSummary {
+    pub nodes: ActionCounts,
+    pub ways: ActionCounts,
+    pub relations: ActionCounts,
+    /// Number of elements each key was added to, including created elements
+    pub keys_added: HashMap<TagString, u64>,
+    /// Number of elements each key was removed from, including deleted elements
+    pub keys_removed: HashMap<TagString, u64>,
+    /// Number of elements where the value of each key changed
+    pub keys_changed: HashMap<TagString, u64>,
+    /// Box around the old and new positions of all changed nodes
+    ///
+    /// Ways and relations only extend it through their nodes included in the changes.
+    pub bbox: Option<Bbox>,
+    /// Number of modified nodes with a new position
+    pub moved_nodes: u64,
+    /// Total distance in meters between the old and new positions of moved nodes
+    pub node_movement: f64,
+    /// Changes that could not be compared because their previous version was not given
+    pub missing_previous: u64,
+}
+
+impl EditSummary {
+    /// Summarizes changes along with the previous version of each modified or deleted element
+    pub fn new<'a>(edits: impl IntoIterator<Item = (&'a Change, Option<&'a Element>)>) -> Self {
+        let mut summary = Self::default();
+        for (change, previous) in edits {
+            summary.add(change, previous);
+        }
+        summary
+    }
+
+    fn add(&mut self, change: &Change, previous: Option<&Element>) {
+        let counts = match change.element {
+            Element::Node(_) => &mut self.nodes,
+            Element::Way(_) => &mut self.ways,
+            Element::Relation(_) => &mut self.relations,
+        };
+        counts.add(change.action);
+
+        if change.action != Action::Create && previous.is_none() {
+            self.missing_previous += 1;
+        }
+        let old_tags = previous.map(Element::tags);
+        let new_tags = (change.action != Action::Delete).then(|| change.element.tags());
+        for (key, value) in new_tags.into_iter().flatten() {
+            match old_tags.and_then(|tags| tags.get(key)) {
+                None => *self.keys_added.entry(key.clone()).or_default() += 1,
+                Some(old) if old != value => {
+                    *self.keys_changed.entry(key.clone()).or_default() += 1
+                }
+                Some(_) => {}
+            }
+        }
+        for key in old_tags.into_iter().flat_map(|tags| tags.keys()) {
+            if new_tags.is_none_or(|tags| !tags.contains_key(key)) {
+                *self.keys_removed.entry(key.clone()).or_default() += 1;
+            }
+        }
+
+        let old_node = previous.and_then(Element::as_node);
+        let new_node = change
+            .element
+            .as_node()
+            .filter(|_| change.action != Action::Delete);
+        for node in old_node.into_iter().chain(new_node) {
+            self.extend_bbox(node.lat_lon());
+        }
+        if let (Some(old), Some(new)) = (old_node, new_node) {
+            if old.lat_lon() != new.lat_lon() {
+                self.moved_nodes += 1;
+                self.node_movement += old.distance_to(new);
+            }
+        }
+    }
+
+    fn extend_bbox(&mut self, (lat, lon): geom::LatLon) {
+        match &mut self.bbox {
+            None => self.bbox = Some(Bbox::point(lat, lon)),
+            Some(bbox) => bbox.extend(lat, lon),
+        }
+    }
+
+    /// Elements changed in total
+    pub fn total(&self) -> u64 {
+        self.nodes.total() + self.ways.total() + self.relations.total()
+    }
+
+    /// Keys added most often, most frequent first and then by key
+    pub fn top_added(&self, n: usize) -> Vec<(&TagString, u64)> {
+        top(&self.keys_added, n)
+    }
+
+    /// Keys removed most often, most frequent first and then by key
+    pub fn top_removed(&self, n: usize) -> Vec<(&TagString, u64)> {
+        top(&self.keys_removed, n)
+    }
+
+    /// Keys changed most often, most frequent first and then by key
+    pub fn top_changed(&self, n: usize) -> Vec<(&TagString, u64)> {
+        top(&self.keys_changed, n)
+    }
+}
+
+fn top(counts: &HashMap<TagString, u64>, n: usize) -> Vec<(&TagString, u64)> {
+    let mut top: Vec<_> = counts.iter().map(|(key, count)| (key, *count)).collect();
+    top.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    top.truncate(n);
+    top
+}
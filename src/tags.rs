@@ -0,0 +1,215 @@
+//! Tags of an element
+//!
+//! <https://wiki.openstreetmap.org/wiki/Tags>
+//!
+//! [Tags] dereferences to a [TagMap], so lookups like `tags.get("highway")` are those of the
+//! map. It adds accessors for common conventions, e.g. [Tags::is_truthy] and [Tags::name].
+
+use alloc::format;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{BuildHasherDefault, Hash, Hasher};
+use core::ops::{Deref, DerefMut};
+use core::str::FromStr;
+
+use fnv::{FnvBuildHasher, FnvHasher};
+#[cfg(not(feature = "std"))]
+use hashbrown::hash_map;
+#[cfg(feature = "std")]
+use std::collections::hash_map;
+
+use crate::TagString;
+use language::LanguageTag;
+
+pub mod language;
+pub mod values;
+
+/// Map from keys to values
+///
+/// This is a [std::collections::HashMap], or a `hashbrown::HashMap` without the `std` feature.
+pub type TagMap = hash_map::HashMap<TagString, TagString, FnvBuildHasher>;
+
+/// [TagMap] that can be shared between elements
+///
+/// Clones share the same map, which is copied on the first modification through
+/// [DerefMut]. This lets many elements with identical tags, e.g. `building=yes`, hold a single
+/// map, see [crate::store::ElementStore]. Empty tags do not allocate.
+#[derive(Clone, Default)]
+pub struct Tags(Option<Arc<TagMap>>);
+
+static EMPTY: TagMap = TagMap::with_hasher(BuildHasherDefault::new());
+
+impl Tags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the map is shared with other [Tags]
+    pub fn is_shared(&self) -> bool {
+        self.0
+            .as_ref()
+            .is_some_and(|map| Arc::strong_count(map) > 1)
+    }
+
+    /// Whether both are the same shared map, which is cheaper than comparing them
+    pub fn ptr_eq(&self, other: &Tags) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether `key` has exactly `value`
+    pub fn has(&self, key: &str, value: &str) -> bool {
+        self.get(key).is_some_and(|v| v == value)
+    }
+
+    /// Whether `key` is `yes`, `true`, or `1`
+    ///
+    /// Other values, e.g. `-1` for [oneway](crate::oneway), are not truthy.
+    pub fn is_truthy(&self, key: &str) -> bool {
+        self.get(key).and_then(|value| values::parse_bool(value)) == Some(true)
+    }
+
+    /// Whether `key` is `no`, `false`, or `0`
+    pub fn is_falsy(&self, key: &str) -> bool {
+        self.get(key).and_then(|value| values::parse_bool(value)) == Some(false)
+    }
+
+    /// Value of `key` parsed as e.g. a [values::Length], [None] if absent or invalid
+    pub fn parse<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.get(key)?.parse().ok()
+    }
+
+    /// Primary name
+    ///
+    /// <https://wiki.openstreetmap.org/wiki/Key:name>
+    pub fn name(&self) -> Option<&str> {
+        self.get("name").map(TagString::as_str)
+    }
+
+    /// Name in the language with code `lang`, e.g. `de` for `name:de`, falling back to
+    /// [Tags::name]
+    ///
+    /// <https://wiki.openstreetmap.org/wiki/Multilingual_names>
+    pub fn name_localized(&self, lang: &str) -> Option<&str> {
+        self.get(format!("name:{lang}").as_str())
+            .map(TagString::as_str)
+            .or_else(|| self.name())
+    }
+
+    /// Name in the first of `languages`, in order of preference, that the element has
+    ///
+    /// Each language falls back from the most to the least specific key, see
+    /// [LanguageTag::fallbacks], e.g. `name:sr-Latn` to `name:sr`. Without a match, this is
+    /// `int_name` and then [Tags::name].
+    pub fn localized_name(&self, languages: &[LanguageTag]) -> Option<&str> {
+        languages
+            .iter()
+            .flat_map(LanguageTag::fallbacks)
+            .find_map(|tag| self.get(format!("name:{tag}").as_str()))
+            .or_else(|| self.get("int_name"))
+            .map(TagString::as_str)
+            .or_else(|| self.name())
+    }
+
+    /// Tags ordered by key, so that output does not depend on the order of the map
+    pub fn iter_sorted(&self) -> alloc::vec::IntoIter<(&TagString, &TagString)> {
+        let mut tags: Vec<_> = self.iter().collect();
+        tags.sort_unstable();
+        tags.into_iter()
+    }
+
+    /// Unwraps the map, copying it if shared
+    pub fn into_map(self) -> TagMap {
+        self.0.map(Arc::unwrap_or_clone).unwrap_or_default()
+    }
+}
+
+impl Deref for Tags {
+    type Target = TagMap;
+
+    fn deref(&self) -> &TagMap {
+        self.0.as_deref().unwrap_or(&EMPTY)
+    }
+}
+
+impl DerefMut for Tags {
+    /// Copies the map first if it is shared
+    fn deref_mut(&mut self) -> &mut TagMap {
+        Arc::make_mut(self.0.get_or_insert_with(Arc::default))
+    }
+}
+
+impl From<TagMap> for Tags {
+    fn from(map: TagMap) -> Self {
+        Self((!map.is_empty()).then(|| Arc::new(map)))
+    }
+}
+
+impl FromIterator<(TagString, TagString)> for Tags {
+    fn from_iter<I: IntoIterator<Item = (TagString, TagString)>>(iter: I) -> Self {
+        TagMap::from_iter(iter).into()
+    }
+}
+
+impl IntoIterator for Tags {
+    type Item = (TagString, TagString);
+    type IntoIter = hash_map::IntoIter<TagString, TagString>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_map().into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Tags {
+    type Item = (&'a TagString, &'a TagString);
+    type IntoIter = hash_map::Iter<'a, TagString, TagString>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl fmt::Debug for Tags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl PartialEq for Tags {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other) || **self == **other
+    }
+}
+
+impl Eq for Tags {}
+
+impl Hash for Tags {
+    /// Hashes independently of the iteration order of the map
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let combined = self.iter().fold(0u64, |combined, tag| {
+            let mut hasher = FnvHasher::default();
+            tag.hash(&mut hasher);
+            combined.wrapping_add(hasher.finish())
+        });
+        state.write_usize(self.len());
+        state.write_u64(combined);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Tags {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Tags {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        TagMap::deserialize(deserializer).map(Self::from)
+    }
+}
@@ -0,0 +1,101 @@
+//! Languages of [multilingual names](https://wiki.openstreetmap.org/wiki/Multilingual_names)
+//!
+//! Keys such as `name:sr-Latn` carry a [BCP 47](https://www.rfc-editor.org/info/bcp47) tag,
+//! of which [LanguageTag] keeps the language, script, and region subtags.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt;
+use core::str::FromStr;
+
+/// Error returned when parsing a [LanguageTag] fails
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseLanguageTagError;
+
+impl fmt::Display for ParseLanguageTagError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid language tag")
+    }
+}
+
+impl core::error::Error for ParseLanguageTagError {}
+
+/// Language with an optional script and region, e.g. `zh-Hant-TW`
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LanguageTag {
+    /// Two or three lowercase letters, e.g. `sr`
+    pub language: String,
+    /// Four letters in title case, e.g. `Latn`
+    pub script: Option<String>,
+    /// Two uppercase letters or three digits, e.g. `TW` or `419`
+    pub region: Option<String>,
+}
+
+impl LanguageTag {
+    /// Tags to look up for this language, from the most to the least specific
+    ///
+    /// `zh-Hant-TW` gives `zh-Hant-TW`, `zh-Hant`, `zh-TW`, and `zh`.
+    pub fn fallbacks(&self) -> Vec<String> {
+        let mut tags = vec![];
+        if let (Some(script), Some(region)) = (&self.script, &self.region) {
+            tags.push(format!("{}-{script}-{region}", self.language));
+        }
+        if let Some(script) = &self.script {
+            tags.push(format!("{}-{script}", self.language));
+        }
+        if let Some(region) = &self.region {
+            tags.push(format!("{}-{region}", self.language));
+        }
+        tags.push(self.language.clone());
+        tags
+    }
+}
+
+impl FromStr for LanguageTag {
+    type Err = ParseLanguageTagError;
+
+    /// Parses a tag, ignoring case and any subtags after the region, e.g. variants
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut subtags = s.trim().split(['-', '_']).peekable();
+        let language = subtags
+            .next()
+            .filter(|language| {
+                (2..=3).contains(&language.len())
+                    && language.bytes().all(|b| b.is_ascii_alphabetic())
+            })
+            .ok_or(ParseLanguageTagError)?
+            .to_ascii_lowercase();
+        let script = subtags
+            .next_if(|script| script.len() == 4 && script.bytes().all(|b| b.is_ascii_alphabetic()))
+            .map(|script| {
+                let (first, rest) = script.split_at(1);
+                first.to_ascii_uppercase() + &rest.to_ascii_lowercase()
+            });
+        let region = subtags
+            .next_if(|region| {
+                (region.len() == 2 && region.bytes().all(|b| b.is_ascii_alphabetic()))
+                    || (region.len() == 3 && region.bytes().all(|b| b.is_ascii_digit()))
+            })
+            .map(str::to_ascii_uppercase);
+        Ok(Self {
+            language,
+            script,
+            region,
+        })
+    }
+}
+
+impl fmt::Display for LanguageTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.language)?;
+        if let Some(script) = &self.script {
+            write!(f, "-{script}")?;
+        }
+        if let Some(region) = &self.region {
+            write!(f, "-{region}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,270 @@
+//! Typed values of common keys, with their units
+//!
+//! Values are free text, so each parser accepts the spellings documented on the wiki and a few
+//! common variants, e.g. `3,5` for `3.5`. Quantities keep the unit they were tagged in and
+//! convert to SI units on request, e.g. [Length::meters].
+
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::str::FromStr;
+
+use crate::scalar::{Scalar, ScalarExt};
+
+/// Error returned when parsing a value fails
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseValueError;
+
+impl fmt::Display for ParseValueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid tag value")
+    }
+}
+
+impl core::error::Error for ParseValueError {}
+
+/// Parses `yes`, `true`, and `1` as true and `no`, `false`, and `0` as false
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim() {
+        "yes" | "true" | "1" => Some(true),
+        "no" | "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parses a value of the [layer](https://wiki.openstreetmap.org/wiki/Key:layer) key
+pub fn parse_layer(value: &str) -> Option<i8> {
+    value.trim().parse().ok()
+}
+
+/// Non-negative number followed by what remains of the value
+fn number(value: &str) -> Option<(Scalar, &str)> {
+    let value = value.trim_start();
+    let end = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+        .unwrap_or(value.len());
+    let number = Scalar::parse_decimal(&value[..end].replace(',', "."))?;
+    Some((number, value[end..].trim()))
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SpeedUnit {
+    KilometersPerHour,
+    MilesPerHour,
+    Knots,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "decimal", derive(Eq, Hash))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Speed {
+    pub value: Scalar,
+    pub unit: SpeedUnit,
+}
+
+impl Speed {
+    pub fn kilometers_per_hour(&self) -> Scalar {
+        let factor = match self.unit {
+            SpeedUnit::KilometersPerHour => Scalar::from_int(1),
+            SpeedUnit::MilesPerHour => Scalar::with_scale(1_609_344, 6),
+            SpeedUnit::Knots => Scalar::with_scale(1_852, 3),
+        };
+        (self.value * factor).normalized()
+    }
+}
+
+impl FromStr for Speed {
+    type Err = ParseValueError;
+
+    /// Parses a number in km/h, or followed by `mph` or `knots`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, unit) = number(s).ok_or(ParseValueError)?;
+        let unit = match unit {
+            "" | "km/h" | "kmh" | "kph" => SpeedUnit::KilometersPerHour,
+            "mph" => SpeedUnit::MilesPerHour,
+            "knots" | "kn" => SpeedUnit::Knots,
+            _ => return Err(ParseValueError),
+        };
+        Ok(Self { value, unit })
+    }
+}
+
+/// Value of the [maxspeed](https://wiki.openstreetmap.org/wiki/Key:maxspeed) key
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq, Hash))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Maxspeed {
+    Limit(Speed),
+    /// No limit, as on parts of German motorways
+    None,
+    /// Walking speed
+    Walk,
+    /// Shown on variable signs
+    Signals,
+    /// Limit implied by a zone, e.g. `DE:urban` or `FR:rural`
+    Zone(String),
+}
+
+impl Maxspeed {
+    /// Limit in km/h, [None] if there is no number
+    pub fn kilometers_per_hour(&self) -> Option<Scalar> {
+        match self {
+            Maxspeed::Limit(speed) => Some(speed.kilometers_per_hour()),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Maxspeed {
+    type Err = ParseValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Ok(match s {
+            "none" => Maxspeed::None,
+            "walk" => Maxspeed::Walk,
+            "signals" => Maxspeed::Signals,
+            _ => match s.split_once(':') {
+                Some((country, zone))
+                    if country.len() == 2
+                        && country.bytes().all(|b| b.is_ascii_uppercase())
+                        && !zone.is_empty() =>
+                {
+                    Maxspeed::Zone(s.to_string())
+                }
+                _ => Maxspeed::Limit(s.parse()?),
+            },
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LengthUnit {
+    Millimeters,
+    Centimeters,
+    Meters,
+    Kilometers,
+    Inches,
+    Feet,
+    Miles,
+    NauticalMiles,
+}
+
+impl LengthUnit {
+    fn meters(self) -> Scalar {
+        match self {
+            LengthUnit::Millimeters => Scalar::with_scale(1, 3),
+            LengthUnit::Centimeters => Scalar::with_scale(1, 2),
+            LengthUnit::Meters => Scalar::from_int(1),
+            LengthUnit::Kilometers => Scalar::from_int(1000),
+            LengthUnit::Inches => Scalar::with_scale(254, 4),
+            LengthUnit::Feet => Scalar::with_scale(3048, 4),
+            LengthUnit::Miles => Scalar::with_scale(1_609_344, 3),
+            LengthUnit::NauticalMiles => Scalar::from_int(1852),
+        }
+    }
+}
+
+/// Value of keys such as `width`, `height`, `length`, `maxheight`, and `distance`
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "decimal", derive(Eq, Hash))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Length {
+    pub value: Scalar,
+    pub unit: LengthUnit,
+}
+
+impl Length {
+    pub fn meters(&self) -> Scalar {
+        (self.value * self.unit.meters()).normalized()
+    }
+}
+
+impl FromStr for Length {
+    type Err = ParseValueError;
+
+    /// Parses a number in meters, or followed by a unit such as `km`, `ft`, or `mi`
+    ///
+    /// Feet and inches may be combined as in `12'6"`, which is 12.5 [LengthUnit::Feet].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, rest) = number(s).ok_or(ParseValueError)?;
+        let unit = match rest {
+            "" | "m" => LengthUnit::Meters,
+            "mm" => LengthUnit::Millimeters,
+            "cm" => LengthUnit::Centimeters,
+            "km" => LengthUnit::Kilometers,
+            "in" | "\"" => LengthUnit::Inches,
+            "ft" | "'" => LengthUnit::Feet,
+            "mi" => LengthUnit::Miles,
+            "nmi" => LengthUnit::NauticalMiles,
+            _ => {
+                let inches = rest
+                    .strip_prefix('\'')
+                    .and_then(|rest| rest.strip_suffix('"'))
+                    .and_then(number)
+                    .filter(|(_, rest)| rest.is_empty())
+                    .ok_or(ParseValueError)?
+                    .0;
+                return Ok(Self {
+                    value: (value + inches / Scalar::from_int(12)).normalized(),
+                    unit: LengthUnit::Feet,
+                });
+            }
+        };
+        Ok(Self { value, unit })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WeightUnit {
+    Kilograms,
+    /// Metric tons
+    Tonnes,
+    /// US tons of 2000 pounds, `st`
+    ShortTons,
+    /// Imperial tons of 2240 pounds, `lt`
+    LongTons,
+    Pounds,
+}
+
+/// Value of keys such as `maxweight` and `maxaxleload`
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "decimal", derive(Eq, Hash))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Weight {
+    pub value: Scalar,
+    pub unit: WeightUnit,
+}
+
+impl Weight {
+    pub fn tonnes(&self) -> Scalar {
+        let factor = match self.unit {
+            WeightUnit::Kilograms => Scalar::with_scale(1, 3),
+            WeightUnit::Tonnes => Scalar::from_int(1),
+            WeightUnit::ShortTons => Scalar::with_scale(90_718_474, 8),
+            WeightUnit::LongTons => Scalar::with_scale(10_160_469_088, 10),
+            WeightUnit::Pounds => Scalar::with_scale(45_359_237, 11),
+        };
+        (self.value * factor).normalized()
+    }
+}
+
+impl FromStr for Weight {
+    type Err = ParseValueError;
+
+    /// Parses a number in tonnes, or followed by `kg`, `st`, `lt`, or `lbs`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, unit) = number(s).ok_or(ParseValueError)?;
+        let unit = match unit {
+            "" | "t" => WeightUnit::Tonnes,
+            "kg" => WeightUnit::Kilograms,
+            "st" => WeightUnit::ShortTons,
+            "lt" => WeightUnit::LongTons,
+            "lbs" | "lb" => WeightUnit::Pounds,
+            _ => return Err(ParseValueError),
+        };
+        Ok(Self { value, unit })
+    }
+}
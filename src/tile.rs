@@ -0,0 +1,173 @@
+//! [Slippy map tiles](https://wiki.openstreetmap.org/wiki/Slippy_map_tilenames) in
+//! [Web Mercator](https://en.wikipedia.org/wiki/Web_Mercator_projection)
+//!
+//! Tile x grows to the east and y to the south, starting at the antimeridian and the northern
+//! edge of the projection at [MAX_LATITUDE]. [Tile::covering] lists the tiles of a box, e.g. to
+//! expire the tiles touched by a diff.
+
+use std::f64::consts::PI;
+use std::fmt;
+
+use crate::bbox::Bbox;
+use crate::geom::{from_radians, LatLon};
+use crate::scalar::{Scalar, ScalarExt};
+use crate::Node;
+
+/// Deepest zoom that fits x and y in a [u32]
+pub const MAX_ZOOM: u8 = 31;
+
+/// Latitude of the northern edge of the projection, which is square
+pub const MAX_LATITUDE: f64 = 85.051_128_779_806_59;
+
+/// Radius of the sphere of the projection in meters, the semi-major axis of WGS 84
+const RADIUS: f64 = 6_378_137.;
+
+/// Projects a coordinate to Web Mercator (EPSG:3857) x and y in meters
+///
+/// Latitudes are clamped to [MAX_LATITUDE].
+pub fn to_web_mercator((lat, lon): LatLon) -> (f64, f64) {
+    let lat = lat.as_f64().clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+    let x = RADIUS * lon.as_f64().to_radians();
+    let y = RADIUS * (PI / 4. + lat / 2.).tan().ln();
+    (x, y)
+}
+
+/// Coordinate of Web Mercator x and y in meters, rounded to 7 decimal places
+pub fn from_web_mercator(x: f64, y: f64) -> LatLon {
+    let lat = 2. * (y / RADIUS).exp().atan() - PI / 2.;
+    from_radians(lat, x / RADIUS)
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tile {
+    pub z: u8,
+    pub x: u32,
+    pub y: u32,
+}
+
+/// Fractional tile x and y of a coordinate
+fn position(lat: Scalar, lon: Scalar, zoom: u8) -> (f64, f64) {
+    let tiles = (1u64 << zoom) as f64;
+    let lat = lat.as_f64().clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+    let x = (lon.as_f64() + 180.) / 360. * tiles;
+    let y = (1. - lat.tan().asinh() / PI) / 2. * tiles;
+    (x, y)
+}
+
+impl Tile {
+    /// Tile if `x` and `y` are within the tiles of zoom `z`
+    pub fn new(z: u8, x: u32, y: u32) -> Option<Self> {
+        let tiles = 1u64 << z.min(MAX_ZOOM);
+        (z <= MAX_ZOOM && u64::from(x) < tiles && u64::from(y) < tiles).then_some(Self { z, x, y })
+    }
+
+    /// Tile containing a coordinate, with latitudes clamped to [MAX_LATITUDE]
+    ///
+    /// Coordinates on an edge belong to the tile to the east or south, except at the eastern
+    /// and southern edges of the map. `zoom` is clamped to [MAX_ZOOM].
+    pub fn from_lat_lon(lat: Scalar, lon: Scalar, zoom: u8) -> Self {
+        let z = zoom.min(MAX_ZOOM);
+        let (x, y) = position(lat, lon, z);
+        let max = (1u64 << z) as f64 - 1.;
+        Self {
+            z,
+            x: x.floor().clamp(0., max) as u32,
+            y: y.floor().clamp(0., max) as u32,
+        }
+    }
+
+    /// Box of the tile, with coordinates rounded to 7 decimal places
+    pub fn bbox(&self) -> Bbox {
+        let tiles = (1u64 << self.z) as f64;
+        let lon = |x: u32| (x as f64 / tiles * 2. - 1.) * PI;
+        let lat = |y: u32| (PI * (1. - 2. * y as f64 / tiles)).sinh().atan();
+        let (max_lat, min_lon) = from_radians(lat(self.y), lon(self.x));
+        let (min_lat, max_lon) = from_radians(lat(self.y + 1), lon(self.x + 1));
+        Bbox::new(min_lat, min_lon, max_lat, max_lon)
+    }
+
+    /// Tile one zoom out containing this one, [None] at zoom 0
+    pub fn parent(&self) -> Option<Self> {
+        let z = self.z.checked_sub(1)?;
+        Some(Self {
+            z,
+            x: self.x / 2,
+            y: self.y / 2,
+        })
+    }
+
+    /// Tiles one zoom in, in the order of [Tile::quadkey] digits, [None] at [MAX_ZOOM]
+    pub fn children(&self) -> Option<[Self; 4]> {
+        if self.z >= MAX_ZOOM {
+            return None;
+        }
+        let child = |dx, dy| Self {
+            z: self.z + 1,
+            x: self.x * 2 + dx,
+            y: self.y * 2 + dy,
+        };
+        Some([child(0, 0), child(1, 0), child(0, 1), child(1, 1)])
+    }
+
+    /// [Quadkey](https://learn.microsoft.com/en-us/bingmaps/articles/bing-maps-tile-system)
+    /// with one digit per zoom level, empty at zoom 0
+    pub fn quadkey(&self) -> String {
+        (1..=self.z)
+            .rev()
+            .map(|level| {
+                let mask = 1 << (level - 1);
+                let digit = u8::from(self.x & mask != 0) + 2 * u8::from(self.y & mask != 0);
+                char::from(b'0' + digit)
+            })
+            .collect()
+    }
+
+    /// Tile of a quadkey, [None] if it has other digits than `0` to `3` or is too long
+    pub fn from_quadkey(quadkey: &str) -> Option<Self> {
+        let z = u8::try_from(quadkey.len())
+            .ok()
+            .filter(|&z| z <= MAX_ZOOM)?;
+        let (mut x, mut y) = (0, 0);
+        for digit in quadkey.bytes() {
+            let digit = digit.checked_sub(b'0').filter(|&digit| digit < 4)?;
+            x = x << 1 | u32::from(digit & 1);
+            y = y << 1 | u32::from(digit >> 1);
+        }
+        Some(Self { z, x, y })
+    }
+
+    /// Tiles at `zoom` that intersect a box, row by row
+    ///
+    /// Boxes crossing the antimeridian cover the tiles on both sides of it.
+    pub fn covering(bbox: &Bbox, zoom: u8) -> Vec<Self> {
+        let (east, west) = bbox.parts();
+        let mut tiles = vec![];
+        for part in [Some(east), west].into_iter().flatten() {
+            let north_west = Self::from_lat_lon(part.max_lat, part.min_lon, zoom);
+            let south_east = Self::from_lat_lon(part.min_lat, part.max_lon, zoom);
+            for y in north_west.y..=south_east.y {
+                tiles.extend((north_west.x..=south_east.x).map(|x| Self {
+                    z: north_west.z,
+                    x,
+                    y,
+                }));
+            }
+        }
+        tiles
+    }
+}
+
+/// Formats the tile as `z/x/y`, as in tile URLs
+impl fmt::Display for Tile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}/{}", self.z, self.x, self.y)
+    }
+}
+
+impl Node {
+    /// See [Tile::from_lat_lon]
+    pub fn tile(&self, zoom: u8) -> Tile {
+        Tile::from_lat_lon(self.lat, self.lon, zoom)
+    }
+}
@@ -0,0 +1,208 @@
+//! [Diff uploads](https://wiki.openstreetmap.org/wiki/API_v0.6#Diff_upload:_POST_/api/0.6/changeset/#id/upload)
+//! to the API
+//!
+//! [ChangeBuilder] writes the osmChange payload of `POST /api/0.6/changeset/#id/upload`, and
+//! [DiffResult] reads the response, which gives the ids and versions assigned by the server.
+//! New elements have [placeholder ids](crate::placeholder) until then.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use crate::change::{Action, Change, OsmChange};
+use crate::error::{Error, Result};
+use crate::placeholder::remap_ids;
+use crate::xml::{optional, required, TagKind, Tokenizer};
+use crate::{Element, ElementId, Id, Info, MemberType};
+
+/// Collects the changes of an upload into a single changeset
+///
+/// The server applies changes in the order of the payload, so they are written in an order
+/// that it accepts regardless of the order they were added in: creations of nodes, ways, and
+/// then relations, modifications in the same order, and deletions in reverse.
+/// Changes of the same type keep the order they were added in, e.g. so that a relation that is
+/// a member of another new relation can be created first.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub struct ChangeBuilder {
+    changeset: i64,
+    changes: Vec<Change>,
+    if_unused: bool,
+}
+
+impl ChangeBuilder {
+    /// Uploads to the open changeset `changeset`
+    pub fn new(changeset: i64) -> Self {
+        Self {
+            changeset,
+            changes: vec![],
+            if_unused: false,
+        }
+    }
+
+    /// Adds a new element, which should have a placeholder id
+    pub fn create(mut self, element: Element) -> Self {
+        self.push(Action::Create, element);
+        self
+    }
+
+    /// Adds an update of an element, with the version it is based on
+    pub fn modify(mut self, element: Element) -> Self {
+        self.push(Action::Modify, element);
+        self
+    }
+
+    /// Adds a deletion of an element, with the version it is based on
+    pub fn delete(mut self, element: Element) -> Self {
+        self.push(Action::Delete, element);
+        self
+    }
+
+    /// Whether the server should skip deletions of elements that are still used, instead of
+    /// failing the upload
+    pub fn if_unused(mut self, if_unused: bool) -> Self {
+        self.if_unused = if_unused;
+        self
+    }
+
+    pub fn push(&mut self, action: Action, element: Element) {
+        self.changes.push(Change { action, element });
+    }
+
+    /// Document of the changes in upload order, with only the `version` and `changeset` of
+    /// their [Info]
+    ///
+    /// Fails if a modification or deletion has no version.
+    pub fn build(&self) -> Result<OsmChange> {
+        let mut changes = self.changes.clone();
+        changes.sort_by_key(|change| {
+            let rank = match change.element.member_type() {
+                MemberType::Node => 0,
+                MemberType::Way => 1,
+                MemberType::Relation => 2,
+            };
+            match change.action {
+                Action::Create => rank,
+                Action::Modify => 3 + rank,
+                Action::Delete => 8 - rank,
+            }
+        });
+        for change in &mut changes {
+            let version = match change.element.info() {
+                Some(info) => info.version,
+                None if change.action == Action::Create => 0,
+                None => {
+                    return Err(Error::Validation {
+                        element: None,
+                        message: format!("{} without a version", change.action.as_str()),
+                    }
+                    .with_element(change.element.member_type(), change.element.id()))
+                }
+            };
+            let info = Info {
+                version,
+                ..Info::builder().changeset(self.changeset).build()
+            };
+            match &mut change.element {
+                Element::Node(node) => node.info = Some(info),
+                Element::Way(way) => way.info = Some(info),
+                Element::Relation(relation) => relation.info = Some(info),
+            }
+        }
+        Ok(OsmChange {
+            changes,
+            ..OsmChange::new()
+        })
+    }
+
+    /// Writes the payload of the upload
+    pub fn write(&self, output: impl Write) -> Result<()> {
+        self.build()?.write_with(output, self.if_unused)
+    }
+}
+
+/// Response to an upload
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct DiffResult {
+    pub version: Option<String>,
+    pub generator: Option<String>,
+    /// Elements in the order of the upload
+    pub entries: Vec<DiffEntry>,
+}
+
+/// Outcome of the change to a single element
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct DiffEntry {
+    /// Id in the upload, which is a placeholder for new elements
+    pub old_id: ElementId,
+    /// Id on the server, [None] if the element was deleted
+    pub new_id: Option<Id>,
+    /// Version on the server, [None] if the element was deleted
+    pub new_version: Option<i32>,
+}
+
+impl DiffResult {
+    /// Reads a `diffResult` document
+    pub fn read(input: impl BufRead) -> Result<Self> {
+        let mut tokenizer = Tokenizer::new(input);
+        let mut result = Self::default();
+        while let Some(tag) = tokenizer.next_tag()? {
+            let ty = match tag.name.as_str() {
+                _ if tag.kind == TagKind::End => continue,
+                "diffResult" => {
+                    result.version = tag.attribute("version").map(str::to_string);
+                    result.generator = tag.attribute("generator").map(str::to_string);
+                    continue;
+                }
+                "node" => MemberType::Node,
+                "way" => MemberType::Way,
+                "relation" => MemberType::Relation,
+                _ => continue,
+            };
+            let error = |message| Error::decode(Some(tokenizer.tag_offset), message);
+            let old_id = required(&tag, "old_id").map_err(error)?;
+            result.entries.push(DiffEntry {
+                old_id: ElementId::new(ty, Id(old_id)),
+                new_id: optional(&tag, "new_id").map_err(error)?.map(Id),
+                new_version: optional(&tag, "new_version").map_err(error)?,
+            });
+        }
+        Ok(result)
+    }
+
+    /// New ids by old id, for [remap_ids]
+    pub fn ids(&self) -> HashMap<ElementId, Id> {
+        self.entries
+            .iter()
+            .filter_map(|entry| Some((entry.old_id, entry.new_id?)))
+            .collect()
+    }
+
+    /// Updates uploaded elements to their state on the server
+    ///
+    /// Placeholders are replaced, including references to them, and versions are set.
+    /// Deleted elements are left as they are.
+    pub fn apply<'a>(&self, elements: impl IntoIterator<Item = &'a mut Element>) {
+        let versions = self
+            .entries
+            .iter()
+            .filter_map(|entry| Some((entry.old_id, entry.new_version?)))
+            .collect::<HashMap<_, _>>();
+        let mut elements = elements.into_iter().collect::<Vec<_>>();
+        for element in &mut elements {
+            if let Some(&version) = versions.get(&element.element_id()) {
+                set_version(element, version);
+            }
+        }
+        remap_ids(elements, &self.ids());
+    }
+}
+
+/// Sets the version, adding [Info] if there is none
+fn set_version(element: &mut Element, version: i32) {
+    let info = match element {
+        Element::Node(node) => &mut node.info,
+        Element::Way(way) => &mut way.info,
+        Element::Relation(relation) => &mut relation.info,
+    };
+    info.get_or_insert_with(|| Info::builder().build()).version = version;
+}
@@ -0,0 +1,330 @@
+//! Public details of [users](https://wiki.openstreetmap.org/wiki/API_v0.6#Details_of_a_user:_GET_/api/0.6/user/#id)
+//! of the API, e.g. `api/0.6/user/123` or `api/0.6/users?users=1,2`
+//!
+//! [Reader] streams them from XML. With the `serde` feature, [User] has the schema of the JSON
+//! API and [Document] is the body of a response. Users are matched to the versions they edited
+//! by [Info::uid](crate::Info::uid), since display names can change.
+
+use std::io::BufRead;
+
+use crate::error::{Error, Result};
+use crate::xml::{optional, required, TagKind, Tokenizer, XmlTag};
+use crate::{Info, TagString, Timestamp};
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "json::UserRepr", into = "json::UserRepr")
+)]
+pub struct User {
+    pub id: i32,
+    pub display_name: TagString,
+    pub account_created: Option<Timestamp>,
+    /// Profile text in Markdown
+    pub description: String,
+    /// Whether the user agreed to the contributor terms, [None] if not given
+    pub contributor_terms: Option<bool>,
+    /// URL of the profile picture
+    pub image: Option<String>,
+    /// Roles such as `moderator`, `administrator`, or `importer`
+    pub roles: Vec<String>,
+    pub changesets: u32,
+    /// Number of uploaded GPS traces
+    pub traces: u32,
+    pub blocks_received: Blocks,
+    /// Only given for moderators
+    pub blocks_issued: Option<Blocks>,
+}
+
+impl User {
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    /// Whether the user is blocked from editing
+    pub fn is_blocked(&self) -> bool {
+        self.blocks_received.active > 0
+    }
+
+    /// Whether a version was made by this user
+    pub fn is_author_of(&self, info: &Info) -> bool {
+        info.uid == Some(self.id)
+    }
+}
+
+/// Number of blocks, as received or issued by a [User]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Blocks {
+    pub count: u32,
+    /// Blocks that have not expired yet
+    pub active: u32,
+}
+
+/// Body of a JSON response, with `user` for a single user and `users` for several
+#[cfg(feature = "serde")]
+#[derive(Debug, PartialEq, Eq, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Document {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<User>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty", with = "json::entries")]
+    pub users: Vec<User>,
+}
+
+#[cfg(feature = "serde")]
+impl Document {
+    pub fn into_users(self) -> impl Iterator<Item = User> {
+        self.user.into_iter().chain(self.users)
+    }
+}
+
+/// Reads the `user`s of an XML document
+#[derive(Debug)]
+pub struct Reader<R> {
+    tokenizer: Tokenizer<R>,
+    done: bool,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            tokenizer: Tokenizer::new(input),
+            done: false,
+        }
+    }
+}
+
+impl<R> Reader<R> {
+    pub fn into_inner(self) -> R {
+        self.tokenizer.input
+    }
+}
+
+impl<R: BufRead> Reader<R> {
+    fn read_user(&mut self) -> Result<Option<User>> {
+        while let Some(tag) = self.tokenizer.next_tag()? {
+            if tag.name != "user" || tag.kind == TagKind::End {
+                continue;
+            }
+            let offset = self.tokenizer.tag_offset;
+            let mut user =
+                start_user(&tag).map_err(|message| Error::decode(Some(offset), message))?;
+            if tag.kind == TagKind::Start {
+                self.read_children(&mut user)?;
+            }
+            return Ok(Some(user));
+        }
+        Ok(None)
+    }
+
+    /// Reads the details up to the end of the user
+    fn read_children(&mut self, user: &mut User) -> Result<()> {
+        let mut parent = String::new();
+        while let Some(tag) = self.tokenizer.next_tag()? {
+            let offset = self.tokenizer.tag_offset;
+            let error = |message: String| Error::decode(Some(offset), message);
+            let count = |tag: &XmlTag, name| {
+                optional(tag, name)
+                    .map(Option::unwrap_or_default)
+                    .map_err(error)
+            };
+            match (tag.kind, tag.name.as_str()) {
+                (TagKind::End, "user") => return Ok(()),
+                (TagKind::Start, name @ ("roles" | "blocks")) => parent = name.to_string(),
+                (TagKind::End, "roles" | "blocks") => parent.clear(),
+                (TagKind::End, "description") => user.description = self.tokenizer.text()?,
+                (_, "contributor-terms") if tag.kind != TagKind::End => {
+                    user.contributor_terms = optional(&tag, "agreed").map_err(error)?;
+                }
+                (_, "img") if tag.kind != TagKind::End => {
+                    user.image = tag.attribute("href").map(str::to_string);
+                }
+                (_, "changesets") if tag.kind != TagKind::End => {
+                    user.changesets = count(&tag, "count")?;
+                }
+                (_, "traces") if tag.kind != TagKind::End => user.traces = count(&tag, "count")?,
+                (_, name @ ("received" | "issued")) if tag.kind != TagKind::End => {
+                    let blocks = Blocks {
+                        count: count(&tag, "count")?,
+                        active: count(&tag, "active")?,
+                    };
+                    if name == "received" {
+                        user.blocks_received = blocks;
+                    } else {
+                        user.blocks_issued = Some(blocks);
+                    }
+                }
+                (TagKind::Start | TagKind::Empty, role) if parent == "roles" => {
+                    user.roles.push(role.to_string());
+                }
+                _ => {}
+            }
+        }
+        Err(Error::decode(
+            Some(self.tokenizer.offset),
+            "unexpected end of file",
+        ))
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<User>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let user = self.read_user().transpose();
+        if !matches!(user, Some(Ok(_))) {
+            self.done = true;
+        }
+        user
+    }
+}
+
+/// User from the attributes of a `user`
+fn start_user(tag: &XmlTag) -> std::result::Result<User, String> {
+    Ok(User {
+        id: required(tag, "id")?,
+        display_name: tag
+            .attribute("display_name")
+            .map(TagString::from_ref)
+            .ok_or("missing display_name attribute")?,
+        account_created: tag
+            .attribute("account_created")
+            .map(|value| {
+                crate::parse_timestamp(value)
+                    .ok_or_else(|| "invalid account_created attribute".to_string())
+            })
+            .transpose()?,
+        ..User::default()
+    })
+}
+
+/// JSON representation of users, which nests counts in objects
+#[cfg(feature = "serde")]
+mod json {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    pub struct UserRepr {
+        id: i32,
+        display_name: TagString,
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            with = "crate::json::timestamp"
+        )]
+        account_created: Option<Timestamp>,
+        #[serde(default)]
+        description: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        contributor_terms: Option<Agreed>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        img: Option<Image>,
+        #[serde(default)]
+        roles: Vec<String>,
+        #[serde(default)]
+        changesets: Count,
+        #[serde(default)]
+        traces: Count,
+        #[serde(default)]
+        blocks: BlocksRepr,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Agreed {
+        agreed: bool,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Image {
+        href: String,
+    }
+
+    #[derive(Serialize, Deserialize, Default)]
+    struct Count {
+        count: u32,
+    }
+
+    #[derive(Serialize, Deserialize, Default)]
+    struct BlocksRepr {
+        #[serde(default)]
+        received: Blocks,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        issued: Option<Blocks>,
+    }
+
+    impl From<UserRepr> for User {
+        fn from(user: UserRepr) -> Self {
+            User {
+                id: user.id,
+                display_name: user.display_name,
+                account_created: user.account_created,
+                description: user.description,
+                contributor_terms: user.contributor_terms.map(|terms| terms.agreed),
+                image: user.img.map(|img| img.href),
+                roles: user.roles,
+                changesets: user.changesets.count,
+                traces: user.traces.count,
+                blocks_received: user.blocks.received,
+                blocks_issued: user.blocks.issued,
+            }
+        }
+    }
+
+    impl From<User> for UserRepr {
+        fn from(user: User) -> Self {
+            UserRepr {
+                id: user.id,
+                display_name: user.display_name,
+                account_created: user.account_created,
+                description: user.description,
+                contributor_terms: user.contributor_terms.map(|agreed| Agreed { agreed }),
+                img: user.image.map(|href| Image { href }),
+                roles: user.roles,
+                changesets: Count {
+                    count: user.changesets,
+                },
+                traces: Count { count: user.traces },
+                blocks: BlocksRepr {
+                    received: user.blocks_received,
+                    issued: user.blocks_issued,
+                },
+            }
+        }
+    }
+
+    /// `users` as a list of objects with a `user` each
+    pub mod entries {
+        use super::*;
+
+        #[derive(Serialize, Deserialize)]
+        struct Entry<U> {
+            user: U,
+        }
+
+        pub fn serialize<S: Serializer>(
+            users: &[User],
+            serializer: S,
+        ) -> std::result::Result<S::Ok, S::Error> {
+            serializer.collect_seq(users.iter().map(|user| Entry { user }))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> std::result::Result<Vec<User>, D::Error> {
+            Ok(Vec::<Entry<User>>::deserialize(deserializer)?
+                .into_iter()
+                .map(|entry| entry.user)
+                .collect())
+        }
+    }
+}
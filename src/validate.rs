@@ -0,0 +1,203 @@
+//! Checks of elements against the rules of OSM data
+//!
+//! [Node::validate], [Way::validate], and [Relation::validate] check the
+//! [limits of the API](https://wiki.openstreetmap.org/wiki/API_v0.6#Limits), and
+//! [check_references] finds dangling references, which clipping an extract commonly leaves.
+
+use std::fmt;
+
+use crate::scalar::{Scalar, ScalarExt};
+use crate::store::ElementStore;
+use crate::{ElementId, Id, Node, Relation, TagString, Tags, Way};
+
+/// Fewest nodes a way may have
+pub const MIN_WAY_NODES: usize = 2;
+/// Most nodes a way may have
+pub const MAX_WAY_NODES: usize = 2000;
+/// Most members a relation may have
+pub const MAX_RELATION_MEMBERS: usize = 32000;
+/// Most characters in a key or value
+pub const MAX_TAG_LENGTH: usize = 255;
+
+/// Rule broken by a single element
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub enum ValidationError {
+    /// Latitude outside of ±90°
+    LatitudeOutOfRange(Scalar),
+    /// Longitude outside of ±180°
+    LongitudeOutOfRange(Scalar),
+    /// Way with fewer than [MIN_WAY_NODES] nodes
+    TooFewNodes(usize),
+    /// Way with more than [MAX_WAY_NODES] nodes
+    TooManyNodes(usize),
+    /// Relation with more than [MAX_RELATION_MEMBERS] members
+    TooManyMembers(usize),
+    EmptyKey,
+    EmptyValue {
+        key: TagString,
+    },
+    /// Key longer than [MAX_TAG_LENGTH] characters
+    KeyTooLong {
+        key: TagString,
+    },
+    /// Value longer than [MAX_TAG_LENGTH] characters
+    ValueTooLong {
+        key: TagString,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::LatitudeOutOfRange(lat) => write!(f, "latitude {lat} is out of range"),
+            ValidationError::LongitudeOutOfRange(lon) => {
+                write!(f, "longitude {lon} is out of range")
+            }
+            ValidationError::TooFewNodes(count) => {
+                write!(f, "{count} nodes are fewer than {MIN_WAY_NODES}")
+            }
+            ValidationError::TooManyNodes(count) => {
+                write!(f, "{count} nodes are more than {MAX_WAY_NODES}")
+            }
+            ValidationError::TooManyMembers(count) => {
+                write!(f, "{count} members are more than {MAX_RELATION_MEMBERS}")
+            }
+            ValidationError::EmptyKey => write!(f, "key is empty"),
+            ValidationError::EmptyValue { key } => write!(f, "value of {key} is empty"),
+            ValidationError::KeyTooLong { key } => {
+                write!(f, "key {key} is longer than {MAX_TAG_LENGTH} characters")
+            }
+            ValidationError::ValueTooLong { key } => {
+                write!(
+                    f,
+                    "value of {key} is longer than {MAX_TAG_LENGTH} characters"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl Node {
+    /// Every rule the node breaks, none if it is valid
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = vec![];
+        if self.lat.abs() > Scalar::from_int(90) {
+            errors.push(ValidationError::LatitudeOutOfRange(self.lat));
+        }
+        if self.lon.abs() > Scalar::from_int(180) {
+            errors.push(ValidationError::LongitudeOutOfRange(self.lon));
+        }
+        validate_tags(&self.tags, &mut errors);
+        errors
+    }
+}
+
+impl Way {
+    /// Every rule the way breaks, none if it is valid
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = vec![];
+        let count = self.refs.len();
+        if count < MIN_WAY_NODES {
+            errors.push(ValidationError::TooFewNodes(count));
+        } else if count > MAX_WAY_NODES {
+            errors.push(ValidationError::TooManyNodes(count));
+        }
+        validate_tags(&self.tags, &mut errors);
+        errors
+    }
+}
+
+impl Relation {
+    /// Every rule the relation breaks, none if it is valid
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = vec![];
+        if self.members.len() > MAX_RELATION_MEMBERS {
+            errors.push(ValidationError::TooManyMembers(self.members.len()));
+        }
+        validate_tags(&self.tags, &mut errors);
+        errors
+    }
+}
+
+/// Adds errors of the tags, sorted by key so that they are stable
+fn validate_tags(tags: &Tags, errors: &mut Vec<ValidationError>) {
+    for (key, value) in tags.iter_sorted() {
+        if key.is_empty() {
+            errors.push(ValidationError::EmptyKey);
+        } else if key.chars().count() > MAX_TAG_LENGTH {
+            errors.push(ValidationError::KeyTooLong { key: key.clone() });
+        }
+        if value.is_empty() {
+            errors.push(ValidationError::EmptyValue { key: key.clone() });
+        } else if value.chars().count() > MAX_TAG_LENGTH {
+            errors.push(ValidationError::ValueTooLong { key: key.clone() });
+        }
+    }
+}
+
+/// Reference that does not resolve within an [ElementStore]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub enum ReferenceError {
+    /// Way refers to a node that is not in the store
+    MissingNode { way: Id, node: Id },
+    /// Relation has a member that is not in the store
+    MissingMember { relation: Id, member: ElementId },
+    /// Relation has itself as a member
+    SelfReference { relation: Id },
+}
+
+impl fmt::Display for ReferenceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReferenceError::MissingNode { way, node } => {
+                write!(f, "way {} refers to missing node {}", way.0, node.0)
+            }
+            ReferenceError::MissingMember { relation, member } => {
+                write!(f, "relation {} has missing member {member}", relation.0)
+            }
+            ReferenceError::SelfReference { relation } => {
+                write!(f, "relation {} is a member of itself", relation.0)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReferenceError {}
+
+/// Finds references of ways and relations that do not resolve in `store`
+///
+/// Each reference is reported once per element, sorted by kind of error, element, and
+/// referenced id.
+pub fn check_references(store: &ElementStore) -> Vec<ReferenceError> {
+    let mut errors = vec![];
+    for way in store.ways() {
+        errors.extend(
+            way.refs
+                .iter()
+                .filter(|&&node| store.get_node(node).is_none())
+                .map(|&node| ReferenceError::MissingNode { way: way.id, node }),
+        );
+    }
+    for relation in store.relations() {
+        let itself = ElementId::Relation(relation.relation_id());
+        for member in &relation.members {
+            let member = member.element_id();
+            if member == itself {
+                errors.push(ReferenceError::SelfReference {
+                    relation: relation.id,
+                });
+            } else if !store.contains(member) {
+                errors.push(ReferenceError::MissingMember {
+                    relation: relation.id,
+                    member,
+                });
+            }
+        }
+    }
+    errors.sort_unstable();
+    errors.dedup();
+    errors
+}
@@ -0,0 +1,196 @@
+//! Vertical position of features relative to each other and the ground
+//!
+//! <https://wiki.openstreetmap.org/wiki/Key:layer>
+//! <https://wiki.openstreetmap.org/wiki/Key:level>
+
+use fnv::FnvHashMap as HashMap;
+
+use crate::{Element, TagString};
+
+/// Type of a bridge
+///
+/// <https://wiki.openstreetmap.org/wiki/Key:bridge>
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Bridge {
+    Yes,
+    Viaduct,
+    Aqueduct,
+    Boardwalk,
+    Cantilever,
+    Covered,
+    LowWaterCrossing,
+    Movable,
+    Trestle,
+    /// Any value other than `no` not listed above
+    Unknown,
+}
+
+impl Bridge {
+    /// Parses a value of the `bridge` key
+    ///
+    /// Returns [None] for `no`.
+    pub fn parse(value: &str) -> Option<Self> {
+        Some(match value {
+            "no" => return None,
+            "yes" => Bridge::Yes,
+            "viaduct" => Bridge::Viaduct,
+            "aqueduct" => Bridge::Aqueduct,
+            "boardwalk" => Bridge::Boardwalk,
+            "cantilever" => Bridge::Cantilever,
+            "covered" => Bridge::Covered,
+            "low_water_crossing" => Bridge::LowWaterCrossing,
+            "movable" => Bridge::Movable,
+            "trestle" => Bridge::Trestle,
+            _ => Bridge::Unknown,
+        })
+    }
+}
+
+/// Type of a tunnel
+///
+/// <https://wiki.openstreetmap.org/wiki/Key:tunnel>
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Tunnel {
+    Yes,
+    BuildingPassage,
+    Culvert,
+    Flooded,
+    AvalancheProtector,
+    /// Any value other than `no` not listed above
+    Unknown,
+}
+
+impl Tunnel {
+    /// Parses a value of the `tunnel` key
+    ///
+    /// Returns [None] for `no`.
+    pub fn parse(value: &str) -> Option<Self> {
+        Some(match value {
+            "no" => return None,
+            "yes" => Tunnel::Yes,
+            "building_passage" => Tunnel::BuildingPassage,
+            "culvert" => Tunnel::Culvert,
+            "flooded" => Tunnel::Flooded,
+            "avalanche_protector" => Tunnel::AvalancheProtector,
+            _ => Tunnel::Unknown,
+        })
+    }
+}
+
+/// Position of a feature relative to the ground
+///
+/// <https://wiki.openstreetmap.org/wiki/Key:location>
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Location {
+    Underground,
+    Underwater,
+    Overground,
+    Overhead,
+    Roof,
+    Rooftop,
+    Indoor,
+    Outdoor,
+    Platform,
+    Surface,
+    /// Any value not listed above
+    Unknown,
+}
+
+impl Location {
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "underground" => Location::Underground,
+            "underwater" => Location::Underwater,
+            "overground" => Location::Overground,
+            "overhead" => Location::Overhead,
+            "roof" => Location::Roof,
+            "rooftop" => Location::Rooftop,
+            "indoor" => Location::Indoor,
+            "outdoor" => Location::Outdoor,
+            "platform" => Location::Platform,
+            "surface" => Location::Surface,
+            _ => Location::Unknown,
+        }
+    }
+}
+
+/// Relative vertical ordering of crossing features
+///
+/// Untagged features are conventionally treated as layer 0.
+/// Returns [None] if absent or not an integer in the range -128 to 127.
+pub fn layer(tags: &HashMap<TagString, TagString>) -> Option<i8> {
+    crate::tags::values::parse_layer(tags.get("layer")?)
+}
+
+/// Floor levels of a feature inside a building
+///
+/// Multiple levels are separated by `;` (`0;1`) and integer ranges
+/// are expanded (`-1-2` is `[-1, 0, 1, 2]`). Returns [None] if absent or malformed.
+pub fn level(tags: &HashMap<TagString, TagString>) -> Option<Vec<f64>> {
+    parse_levels(tags.get("level")?)
+}
+
+/// Parses a `level` value
+///
+/// See [level] for details.
+pub fn parse_levels(value: &str) -> Option<Vec<f64>> {
+    let mut levels = vec![];
+    for part in value.split(';').map(str::trim) {
+        if let Ok(level) = part.parse::<f64>() {
+            levels.push(level);
+            continue;
+        }
+        // The separator is the first '-' that isn't a sign
+        let separator = part
+            .char_indices()
+            .skip(1)
+            .find(|(_, c)| *c == '-')
+            .map(|(i, _)| i)?;
+        let start: i32 = part[..separator].trim().parse().ok()?;
+        let end: i32 = part[separator + 1..].trim().parse().ok()?;
+        if start > end {
+            return None;
+        }
+        levels.extend((start..=end).map(f64::from));
+    }
+    Some(levels)
+}
+
+pub fn bridge(tags: &HashMap<TagString, TagString>) -> Option<Bridge> {
+    Bridge::parse(tags.get("bridge")?)
+}
+
+pub fn tunnel(tags: &HashMap<TagString, TagString>) -> Option<Tunnel> {
+    Tunnel::parse(tags.get("tunnel")?)
+}
+
+pub fn location(tags: &HashMap<TagString, TagString>) -> Option<Location> {
+    tags.get("location").map(|value| Location::parse(value))
+}
+
+impl Element {
+    /// See [layer]
+    pub fn layer(&self) -> Option<i8> {
+        layer(self.tags())
+    }
+
+    /// See [level]
+    pub fn level(&self) -> Option<Vec<f64>> {
+        level(self.tags())
+    }
+
+    pub fn bridge(&self) -> Option<Bridge> {
+        bridge(self.tags())
+    }
+
+    pub fn tunnel(&self) -> Option<Tunnel> {
+        tunnel(self.tags())
+    }
+
+    pub fn location(&self) -> Option<Location> {
+        location(self.tags())
+    }
+}
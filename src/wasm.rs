@@ -0,0 +1,289 @@
+//! Conversions to and from JavaScript values, for use with [wasm_bindgen]
+//!
+//! Elements become plain objects in the schema of [OSM JSON](https://wiki.openstreetmap.org/wiki/OSM_JSON),
+//! e.g. `{type: "node", id: 1, lat: 52.5, lon: 13.4, tags: {amenity: "cafe"}}`, so that
+//! scripts in the browser never see [Scalar] or [TagString]. Coordinates are numbers, which
+//! are exact to the 7 decimal places that OSM stores.
+//!
+//! The values are built with [js_sys] directly rather than through JSON text, and only work
+//! on a `wasm32` target: elsewhere the functions panic.
+
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::{JsError, JsValue};
+
+use crate::scalar::{Scalar, ScalarExt};
+use crate::{Element, Id, Info, Member, MemberType, Node, Relation, TagString, Tags, Way};
+
+/// Decimal places of coordinates, which OSM stores as 100 nanodegrees
+const COORDINATE_PLACES: u32 = 7;
+
+fn set(object: &Object, key: &str, value: impl Into<JsValue>) {
+    // Setting a property of a plain object cannot fail
+    let _ = Reflect::set(object, &key.into(), &value.into());
+}
+
+/// Property of an object, [None] if missing, `undefined`, or `null`
+fn get(object: &JsValue, key: &str) -> Option<JsValue> {
+    Reflect::get(object, &key.into())
+        .ok()
+        .filter(|value| !value.is_undefined() && !value.is_null())
+}
+
+fn number(object: &JsValue, key: &str) -> Result<Option<f64>, JsError> {
+    get(object, key)
+        .map(|value| {
+            value
+                .as_f64()
+                .ok_or_else(|| JsError::new(&format!("{key} is not a number")))
+        })
+        .transpose()
+}
+
+fn integer(object: &JsValue, key: &str) -> Result<Option<i64>, JsError> {
+    match number(object, key)? {
+        Some(value) if value.fract() != 0. || value.abs() > (1u64 << 53) as f64 => {
+            Err(JsError::new(&format!("{key} is not an integer")))
+        }
+        value => Ok(value.map(|value| value as i64)),
+    }
+}
+
+fn string(object: &JsValue, key: &str) -> Result<Option<String>, JsError> {
+    get(object, key)
+        .map(|value| {
+            value
+                .as_string()
+                .ok_or_else(|| JsError::new(&format!("{key} is not a string")))
+        })
+        .transpose()
+}
+
+fn coordinate(object: &JsValue, key: &str) -> Result<Scalar, JsError> {
+    number(object, key)?
+        .and_then(Scalar::try_from_f64)
+        .map(|value| value.round_places(COORDINATE_PLACES).normalized())
+        .ok_or_else(|| JsError::new(&format!("missing or invalid {key}")))
+}
+
+fn tags_to_js(tags: &Tags) -> Object {
+    let object = Object::new();
+    for (key, value) in tags.iter_sorted() {
+        set(&object, key, value.as_str());
+    }
+    object
+}
+
+fn tags_from_js(value: Option<JsValue>) -> Result<Tags, JsError> {
+    let Some(value) = value else {
+        return Ok(Tags::new());
+    };
+    let object = Object::try_from(&value).ok_or_else(|| JsError::new("tags is not an object"))?;
+    Object::entries(object)
+        .iter()
+        .map(|entry| {
+            let entry = Array::from(&entry);
+            let key = entry.get(0).as_string();
+            let value = entry.get(1).as_string();
+            match (key, value) {
+                (Some(key), Some(value)) => Ok((TagString::from(key), TagString::from(value))),
+                _ => Err(JsError::new("tag values must be strings")),
+            }
+        })
+        .collect()
+}
+
+fn info_to_js(object: &Object, info: &Info) {
+    // Version 0 means unknown, as in the XML formats
+    if info.version != 0 {
+        set(object, "version", info.version);
+    }
+    if let Some(timestamp) = info.timestamp {
+        set(
+            object,
+            "timestamp",
+            crate::format_timestamp(timestamp).to_string(),
+        );
+    }
+    if let Some(changeset) = info.changeset {
+        set(object, "changeset", changeset as f64);
+    }
+    if let Some(user) = &info.user {
+        set(object, "user", user.as_str());
+    }
+    if let Some(uid) = info.uid {
+        set(object, "uid", uid);
+    }
+    if let Some(visible) = info.visible {
+        set(object, "visible", visible);
+    }
+}
+
+fn info_from_js(object: &JsValue) -> Result<Option<Info>, JsError> {
+    const KEYS: [&str; 6] = [
+        "version",
+        "timestamp",
+        "changeset",
+        "user",
+        "uid",
+        "visible",
+    ];
+    if !KEYS.iter().any(|key| get(object, key).is_some()) {
+        return Ok(None);
+    }
+    let timestamp = string(object, "timestamp")?
+        .map(|timestamp| {
+            crate::parse_timestamp(&timestamp).ok_or_else(|| JsError::new("invalid timestamp"))
+        })
+        .transpose()?;
+    let int = |key| -> Result<Option<i32>, JsError> {
+        integer(object, key)?
+            .map(|value| {
+                i32::try_from(value).map_err(|_| JsError::new(&format!("{key} is out of range")))
+            })
+            .transpose()
+    };
+    let visible = get(object, "visible")
+        .map(|value| {
+            value
+                .as_bool()
+                .ok_or_else(|| JsError::new("visible is not a boolean"))
+        })
+        .transpose()?;
+    Ok(Some(Info {
+        version: int("version")?.unwrap_or_default(),
+        timestamp,
+        changeset: integer(object, "changeset")?,
+        uid: int("uid")?,
+        user: string(object, "user")?.map(TagString::from),
+        visible,
+    }))
+}
+
+/// Element as an OSM JSON object
+pub fn element_to_js(element: &Element) -> JsValue {
+    let object = Object::new();
+    set(&object, "type", element.member_type().as_str());
+    set(&object, "id", element.id().0 as f64);
+    match element {
+        Element::Node(node) => {
+            set(&object, "lat", node.lat.as_f64());
+            set(&object, "lon", node.lon.as_f64());
+        }
+        Element::Way(way) => {
+            let nodes: Array = way
+                .refs
+                .iter()
+                .map(|id| JsValue::from(id.0 as f64))
+                .collect();
+            set(&object, "nodes", nodes);
+        }
+        Element::Relation(relation) => {
+            let members: Array = relation
+                .members
+                .iter()
+                .map(|member| {
+                    let object = Object::new();
+                    set(&object, "type", member.ty.as_str());
+                    set(&object, "ref", member.id.0 as f64);
+                    set(&object, "role", member.role.as_deref().unwrap_or(""));
+                    JsValue::from(object)
+                })
+                .collect();
+            set(&object, "members", members);
+        }
+    }
+    if !element.tags().is_empty() {
+        set(&object, "tags", tags_to_js(element.tags()));
+    }
+    if let Some(info) = element.info() {
+        info_to_js(&object, info);
+    }
+    object.into()
+}
+
+/// Element from an OSM JSON object, such as one from [element_to_js]
+pub fn element_from_js(value: &JsValue) -> Result<Element, JsError> {
+    let id = Id(integer(value, "id")?.ok_or_else(|| JsError::new("missing id"))?);
+    let tags = tags_from_js(get(value, "tags"))?;
+    let info = info_from_js(value)?;
+    let ty = string(value, "type")?.ok_or_else(|| JsError::new("missing type"))?;
+    let ty: MemberType = ty
+        .parse()
+        .map_err(|_| JsError::new(&format!("unknown type {ty}")))?;
+    Ok(match ty {
+        MemberType::Node => Element::Node(Node {
+            id,
+            tags,
+            info,
+            lat: coordinate(value, "lat")?,
+            lon: coordinate(value, "lon")?,
+        }),
+        MemberType::Way => {
+            let refs = match get(value, "nodes") {
+                Some(nodes) => Array::from(&nodes)
+                    .iter()
+                    .map(|node| match node.as_f64() {
+                        Some(id) if id.fract() == 0. => Ok(Id(id as i64)),
+                        _ => Err(JsError::new("way nodes must be integers")),
+                    })
+                    .collect::<Result<_, _>>()?,
+                None => Default::default(),
+            };
+            Element::Way(Way {
+                id,
+                tags,
+                info,
+                refs,
+            })
+        }
+        MemberType::Relation => {
+            let members = match get(value, "members") {
+                Some(members) => Array::from(&members)
+                    .iter()
+                    .map(|member| {
+                        let ty = string(&member, "type")?
+                            .ok_or_else(|| JsError::new("member is missing its type"))?;
+                        let id = integer(&member, "ref")?
+                            .ok_or_else(|| JsError::new("member is missing its ref"))?;
+                        Ok(Member {
+                            id: Id(id),
+                            ty: ty
+                                .parse()
+                                .map_err(|_| JsError::new(&format!("unknown member type {ty}")))?,
+                            role: string(&member, "role")?
+                                .filter(|role| !role.is_empty())
+                                .map(TagString::from),
+                        })
+                    })
+                    .collect::<Result<_, JsError>>()?,
+                None => Default::default(),
+            };
+            Element::Relation(Relation {
+                id,
+                tags,
+                info,
+                members,
+            })
+        }
+    })
+}
+
+/// Array of OSM JSON objects
+pub fn elements_to_js<'a>(elements: impl IntoIterator<Item = &'a Element>) -> Array {
+    elements.into_iter().map(element_to_js).collect()
+}
+
+/// Elements of an array of OSM JSON objects, or of the `elements` of an API response
+pub fn elements_from_js(value: &JsValue) -> Result<Vec<Element>, JsError> {
+    let array = match get(value, "elements") {
+        Some(elements) if !Array::is_array(value) => elements,
+        _ => value.clone(),
+    };
+    if !Array::is_array(&array) {
+        return Err(JsError::new("expected an array of elements"));
+    }
+    Array::from(&array)
+        .iter()
+        .map(|element| element_from_js(&element))
+        .collect()
+}
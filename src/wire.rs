@@ -0,0 +1,290 @@
+//! Stable serde representation of elements for caches and binary formats
+//!
+//! The derived serde implementations follow the types of the crate: tags are a map in hash
+//! order, coordinates are strings with the `decimal` feature and floats without it, and
+//! timestamps depend on the `utc` feature. They can change between releases, so data
+//! serialized with them should not outlive the program that wrote it.
+//!
+//! [Compact] instead serializes an [Element] the same way regardless of features, compact
+//! enough for formats like [bincode](https://docs.rs/bincode) or
+//! [postcard](https://docs.rs/postcard). Each element is a tuple of
+//!
+//! 1. the type as a `u8`: 0 for nodes, 1 for ways, 2 for relations
+//! 2. the id as an `i64`
+//! 3. the tags as a sequence of key and value pairs, sorted by key
+//! 4. the [Info] as an optional struct of version, timestamp in whole seconds since the epoch,
+//!    changeset, uid, user, and visible, each optional except for the version
+//! 5. the body of the type:
+//!     - for nodes, latitude and longitude as `i32` in fixed point with 7 decimal places
+//!     - for ways, the node ids as differences to the previous one, starting from 0
+//!     - for relations, the members as a sequence of type, id, and optional role
+//!
+//! Elements without info, e.g. after [Element::strip_info], only take a byte for it in
+//! binary formats. Changes to the layout increment [FORMAT_VERSION], which caches can store
+//! to detect stale data.
+
+use std::borrow::Borrow;
+use std::fmt;
+
+use chrono::DateTime;
+use serde::de::{self, SeqAccess, Visitor};
+use serde::ser::{self, SerializeTuple};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::locations::{from_fixed, to_fixed};
+use crate::{
+    timestamp_from_utc, timestamp_to_utc, Element, Id, Info, Member, MemberType, Node, Refs,
+    Relation, TagString, Tags, Way,
+};
+
+/// Version of the layout of [Compact]
+pub const FORMAT_VERSION: u32 = 1;
+
+/// [Element] with the stable representation described in the [module](self) docs
+///
+/// Serializing accepts a reference, e.g. `Compact(&element)`, to avoid a copy.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Compact<E>(pub E);
+
+impl<E> Compact<E> {
+    pub fn into_inner(self) -> E {
+        self.0
+    }
+}
+
+impl From<Element> for Compact<Element> {
+    fn from(element: Element) -> Self {
+        Self(element)
+    }
+}
+
+impl From<Compact<Element>> for Element {
+    fn from(compact: Compact<Element>) -> Self {
+        compact.0
+    }
+}
+
+/// [Info] in order, generic over the kind of string
+#[derive(Serialize, Deserialize)]
+struct InfoRepr<S> {
+    version: i32,
+    timestamp: Option<i64>,
+    changeset: Option<i64>,
+    uid: Option<i32>,
+    user: Option<S>,
+    visible: Option<bool>,
+}
+
+/// Member type, id, and role
+#[derive(Serialize, Deserialize)]
+struct MemberRepr<S>(u8, i64, Option<S>);
+
+fn member_type_index(ty: &MemberType) -> u8 {
+    match ty {
+        MemberType::Node => 0,
+        MemberType::Way => 1,
+        MemberType::Relation => 2,
+    }
+}
+
+fn member_type_from_index<E: de::Error>(index: u8) -> Result<MemberType, E> {
+    match index {
+        0 => Ok(MemberType::Node),
+        1 => Ok(MemberType::Way),
+        2 => Ok(MemberType::Relation),
+        _ => Err(E::invalid_value(
+            de::Unexpected::Unsigned(index.into()),
+            &"0, 1, or 2",
+        )),
+    }
+}
+
+impl<E: Borrow<Element>> Serialize for Compact<E> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let element = self.0.borrow();
+        let tags: Vec<_> = element
+            .tags()
+            .iter_sorted()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        let info = element.info().map(|info| InfoRepr {
+            version: info.version,
+            timestamp: info
+                .timestamp
+                .map(|timestamp| timestamp_to_utc(timestamp).timestamp()),
+            changeset: info.changeset,
+            uid: info.uid,
+            user: info.user.as_deref(),
+            visible: info.visible,
+        });
+
+        let mut tuple = serializer.serialize_tuple(5)?;
+        tuple.serialize_element(&member_type_index(&element.member_type()))?;
+        tuple.serialize_element(&element.id().0)?;
+        tuple.serialize_element(&tags)?;
+        tuple.serialize_element(&info)?;
+        match element {
+            Element::Node(node) => {
+                let fixed = |degrees| {
+                    to_fixed(degrees).ok_or_else(|| {
+                        ser::Error::custom(format!(
+                            "coordinates of node {} do not fit in 32-bit fixed point",
+                            node.id.0
+                        ))
+                    })
+                };
+                tuple.serialize_element(&(fixed(node.lat)?, fixed(node.lon)?))?;
+            }
+            Element::Way(way) => {
+                let mut previous = 0i64;
+                let deltas: Vec<_> = way
+                    .refs
+                    .iter()
+                    .map(|id| {
+                        let delta = id.0.wrapping_sub(previous);
+                        previous = id.0;
+                        delta
+                    })
+                    .collect();
+                tuple.serialize_element(&deltas)?;
+            }
+            Element::Relation(relation) => {
+                let members: Vec<_> = relation
+                    .members
+                    .iter()
+                    .map(|member| {
+                        MemberRepr(
+                            member_type_index(&member.ty),
+                            member.id.0,
+                            member.role.as_deref(),
+                        )
+                    })
+                    .collect();
+                tuple.serialize_element(&members)?;
+            }
+        }
+        tuple.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Compact<Element> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_tuple(5, CompactVisitor)
+    }
+}
+
+struct CompactVisitor;
+
+impl<'de> Visitor<'de> for CompactVisitor {
+    type Value = Compact<Element>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a compact element")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        fn next<'de, T: Deserialize<'de>, A: SeqAccess<'de>>(
+            seq: &mut A,
+            index: usize,
+        ) -> Result<T, A::Error> {
+            seq.next_element()?
+                .ok_or_else(|| de::Error::invalid_length(index, &"5 fields"))
+        }
+
+        let ty = member_type_from_index(next(&mut seq, 0)?)?;
+        let id = Id(next(&mut seq, 1)?);
+        let tags: Vec<(String, String)> = next(&mut seq, 2)?;
+        let tags: Tags = tags
+            .into_iter()
+            .map(|(key, value)| (TagString::from_string(key), TagString::from_string(value)))
+            .collect();
+        let info = next::<Option<InfoRepr<String>>, _>(&mut seq, 3)?
+            .map(|info| {
+                let timestamp = info
+                    .timestamp
+                    .map(|seconds| {
+                        DateTime::from_timestamp(seconds, 0)
+                            .map(timestamp_from_utc)
+                            .ok_or_else(|| {
+                                de::Error::custom(format!("timestamp {seconds} is out of range"))
+                            })
+                    })
+                    .transpose()?;
+                Ok(Info {
+                    version: info.version,
+                    timestamp,
+                    changeset: info.changeset,
+                    uid: info.uid,
+                    user: info.user.map(TagString::from_string),
+                    visible: info.visible,
+                })
+            })
+            .transpose()?;
+
+        let element = match ty {
+            MemberType::Node => {
+                let (lat, lon): (i32, i32) = next(&mut seq, 4)?;
+                Element::Node(Node {
+                    id,
+                    tags,
+                    info,
+                    lat: from_fixed(lat),
+                    lon: from_fixed(lon),
+                })
+            }
+            MemberType::Way => {
+                let deltas: Vec<i64> = next(&mut seq, 4)?;
+                let mut previous = 0i64;
+                let refs: Refs = deltas
+                    .into_iter()
+                    .map(|delta| {
+                        previous = previous.wrapping_add(delta);
+                        Id(previous)
+                    })
+                    .collect();
+                Element::Way(Way {
+                    id,
+                    tags,
+                    info,
+                    refs,
+                })
+            }
+            MemberType::Relation => {
+                let members: Vec<MemberRepr<String>> = next(&mut seq, 4)?;
+                let members = members
+                    .into_iter()
+                    .map(|MemberRepr(ty, id, role)| {
+                        Ok(Member {
+                            id: Id(id),
+                            ty: member_type_from_index(ty)?,
+                            role: role.map(TagString::from_string),
+                        })
+                    })
+                    .collect::<Result<_, A::Error>>()?;
+                Element::Relation(Relation {
+                    id,
+                    tags,
+                    info,
+                    members,
+                })
+            }
+        };
+        Ok(Compact(element))
+    }
+}
+
+/// [Compact] for fields, e.g. `#[serde(with = "osm_types::wire::compact")]`
+pub mod compact {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::Compact;
+    use crate::Element;
+
+    pub fn serialize<S: Serializer>(element: &Element, serializer: S) -> Result<S::Ok, S::Error> {
+        Compact(element).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Element, D::Error> {
+        Compact::deserialize(deserializer).map(Compact::into_inner)
+    }
+}
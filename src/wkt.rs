@@ -0,0 +1,231 @@
+//! [Well-known text and binary](https://en.wikipedia.org/wiki/Well-known_text_representation_of_geometry)
+//! geometries of nodes, ways, and areas
+//!
+//! WKT keeps coordinates exactly as stored, so it suits comparisons in tests, while WKB is
+//! what PostGIS and most geometry libraries read fastest, e.g. with `ST_GeomFromWKB(…, 4326)`.
+//! WKB is little-endian and carries no SRID, unlike the EWKB of [crate::postgres].
+//!
+//! Both order coordinates as longitude and latitude. Rings of polygons are written with the
+//! outer ring counterclockwise and holes clockwise, as the OGC simple features specification
+//! requires, whatever their orientation in the [Polygon].
+
+use std::fmt::{self, Write};
+
+use crate::area::Area;
+use crate::geom::{signed_area, LatLon, MissingNode};
+use crate::multipolygon::{MultiPolygon, Polygon};
+use crate::scalar::ScalarExt;
+use crate::{Id, Node, Way};
+
+const POINT: u32 = 1;
+const LINE_STRING: u32 = 2;
+const POLYGON: u32 = 3;
+const MULTI_POLYGON: u32 = 6;
+
+/// Ring with the orientation required for an outer ring or a hole
+fn oriented(ring: &[LatLon], counterclockwise: bool) -> Vec<LatLon> {
+    let degrees: Vec<(f64, f64)> = ring
+        .iter()
+        .map(|(lat, lon)| (lat.as_f64(), lon.as_f64()))
+        .collect();
+    let mut ring = ring.to_vec();
+    if (signed_area(&degrees) > 0.) != counterclockwise {
+        ring.reverse();
+    }
+    ring
+}
+
+/// Rings of a polygon, outer ring first, oriented as required, none for an empty polygon
+fn rings(polygon: &Polygon) -> Vec<Vec<LatLon>> {
+    if polygon.outer.is_empty() {
+        return vec![];
+    }
+    let mut rings = vec![oriented(&polygon.outer, true)];
+    rings.extend(polygon.inners.iter().map(|inner| oriented(inner, false)));
+    rings
+}
+
+fn write_coordinates(out: &mut String, coordinates: &[LatLon]) -> fmt::Result {
+    out.push('(');
+    for (i, (lat, lon)) in coordinates.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write!(out, "{} {}", lon.normalized(), lat.normalized())?;
+    }
+    out.push(')');
+    Ok(())
+}
+
+fn write_rings(out: &mut String, rings: &[Vec<LatLon>]) -> fmt::Result {
+    out.push('(');
+    for (i, ring) in rings.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_coordinates(out, ring)?;
+    }
+    out.push(')');
+    Ok(())
+}
+
+/// `POINT(lon lat)`
+pub fn point_wkt((lat, lon): LatLon) -> String {
+    format!("POINT({} {})", lon.normalized(), lat.normalized())
+}
+
+/// `LINESTRING(…)`, or `LINESTRING EMPTY` without coordinates
+pub fn line_string_wkt(line: &[LatLon]) -> String {
+    if line.is_empty() {
+        return "LINESTRING EMPTY".to_string();
+    }
+    let mut out = String::from("LINESTRING");
+    let _ = write_coordinates(&mut out, line);
+    out
+}
+
+/// `POLYGON(…)`, or `POLYGON EMPTY` without an outer ring
+pub fn polygon_wkt(polygon: &Polygon) -> String {
+    if polygon.outer.is_empty() {
+        return "POLYGON EMPTY".to_string();
+    }
+    let mut out = String::from("POLYGON");
+    let _ = write_rings(&mut out, &rings(polygon));
+    out
+}
+
+/// `MULTIPOLYGON(…)`, or `MULTIPOLYGON EMPTY` without polygons
+pub fn multi_polygon_wkt(multipolygon: &MultiPolygon) -> String {
+    if multipolygon.polygons.is_empty() {
+        return "MULTIPOLYGON EMPTY".to_string();
+    }
+    let mut out = String::from("MULTIPOLYGON(");
+    for (i, polygon) in multipolygon.polygons.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        let _ = write_rings(&mut out, &rings(polygon));
+    }
+    out.push(')');
+    out
+}
+
+fn header(bytes: &mut Vec<u8>, ty: u32) {
+    bytes.push(1);
+    bytes.extend_from_slice(&ty.to_le_bytes());
+}
+
+fn coordinate(bytes: &mut Vec<u8>, (lat, lon): LatLon) {
+    bytes.extend_from_slice(&lon.as_f64().to_le_bytes());
+    bytes.extend_from_slice(&lat.as_f64().to_le_bytes());
+}
+
+fn count(bytes: &mut Vec<u8>, n: usize) {
+    bytes.extend_from_slice(&(n as u32).to_le_bytes());
+}
+
+fn polygon_body(bytes: &mut Vec<u8>, polygon: &Polygon) {
+    let rings = rings(polygon);
+    count(bytes, rings.len());
+    for ring in &rings {
+        count(bytes, ring.len());
+        ring.iter().for_each(|at| coordinate(bytes, *at));
+    }
+}
+
+pub fn point_wkb(at: LatLon) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(21);
+    header(&mut bytes, POINT);
+    coordinate(&mut bytes, at);
+    bytes
+}
+
+pub fn line_string_wkb(line: &[LatLon]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(9 + line.len() * 16);
+    header(&mut bytes, LINE_STRING);
+    count(&mut bytes, line.len());
+    line.iter().for_each(|at| coordinate(&mut bytes, *at));
+    bytes
+}
+
+pub fn polygon_wkb(polygon: &Polygon) -> Vec<u8> {
+    let mut bytes = vec![];
+    header(&mut bytes, POLYGON);
+    polygon_body(&mut bytes, polygon);
+    bytes
+}
+
+pub fn multi_polygon_wkb(multipolygon: &MultiPolygon) -> Vec<u8> {
+    let mut bytes = vec![];
+    header(&mut bytes, MULTI_POLYGON);
+    count(&mut bytes, multipolygon.polygons.len());
+    for polygon in &multipolygon.polygons {
+        header(&mut bytes, POLYGON);
+        polygon_body(&mut bytes, polygon);
+    }
+    bytes
+}
+
+impl Node {
+    /// See [point_wkt]
+    pub fn wkt(&self) -> String {
+        point_wkt(self.lat_lon())
+    }
+
+    /// See [point_wkb]
+    pub fn wkb(&self) -> Vec<u8> {
+        point_wkb(self.lat_lon())
+    }
+}
+
+impl Way {
+    /// Line through the nodes of the way, looked up with `node`, see [line_string_wkt]
+    pub fn wkt<'a>(&self, node: impl FnMut(Id) -> Option<&'a Node>) -> Result<String, MissingNode> {
+        self.resolve(node).map(|line| line_string_wkt(&line))
+    }
+
+    /// Line through the nodes of the way, looked up with `node`, see [line_string_wkb]
+    pub fn wkb<'a>(
+        &self,
+        node: impl FnMut(Id) -> Option<&'a Node>,
+    ) -> Result<Vec<u8>, MissingNode> {
+        self.resolve(node).map(|line| line_string_wkb(&line))
+    }
+}
+
+impl Polygon {
+    /// See [polygon_wkt]
+    pub fn wkt(&self) -> String {
+        polygon_wkt(self)
+    }
+
+    /// See [polygon_wkb]
+    pub fn wkb(&self) -> Vec<u8> {
+        polygon_wkb(self)
+    }
+}
+
+impl MultiPolygon {
+    /// See [multi_polygon_wkt]
+    pub fn wkt(&self) -> String {
+        multi_polygon_wkt(self)
+    }
+
+    /// See [multi_polygon_wkb]
+    pub fn wkb(&self) -> Vec<u8> {
+        multi_polygon_wkb(self)
+    }
+}
+
+impl Area {
+    /// Polygons of the area as a `MULTIPOLYGON`, even for a single polygon, so that areas of
+    /// ways and relations share a column type
+    pub fn wkt(&self) -> String {
+        self.multipolygon().wkt()
+    }
+
+    /// See [Area::wkt]
+    pub fn wkb(&self) -> Vec<u8> {
+        self.multipolygon().wkb()
+    }
+}
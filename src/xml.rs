@@ -0,0 +1,1013 @@
+//! [OSM XML](https://wiki.openstreetmap.org/wiki/OSM_XML) files
+//!
+//! [Reader] streams [Element]s from `.osm` files, e.g. API responses or extracts converted with
+//! [osmium](https://osmcode.org/osmium-tool/), without holding the whole file in memory.
+//! [Writer] does the opposite, so the types can be used for round trips between files.
+//! With the `tokio` feature, [AsyncWriter] writes to a [tokio::io::AsyncWrite], and
+//! [crate::feed::read] reads from a [tokio::io::AsyncRead] with a [Decoder].
+//! The parser understands the subset of XML used by OSM files, so document type definitions
+//! and namespaces are not supported.
+
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+use crate::bbox::Bbox;
+use crate::error::{Error, Result};
+use crate::feed::{Buffer, Decode};
+use crate::parse::{ParseOptions, Recovery};
+use crate::pipeline::Sink;
+use crate::progress::{NoProgress, Observer};
+use crate::scalar::{Scalar, ScalarExt};
+use crate::{
+    Element, Id, Info, Member, MemberType, Members, Node, Refs, Relation, TagString, Tags, Way,
+};
+
+/// Attributes of the root `osm` element and its `bounds`
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "decimal", derive(Eq))]
+pub struct Header {
+    /// Version of the format, `0.6` for current files
+    pub version: Option<String>,
+    /// Program that wrote the file
+    pub generator: Option<String>,
+    /// Area the file covers, for extracts and API responses
+    pub bounds: Option<Bbox>,
+}
+
+/// Generator written by [Writer::new]
+pub const DEFAULT_GENERATOR: &str = concat!("osm-types ", env!("CARGO_PKG_VERSION"));
+
+impl Header {
+    /// Header of version `0.6` from [DEFAULT_GENERATOR]
+    pub fn new() -> Self {
+        Self {
+            version: Some("0.6".to_string()),
+            generator: Some(DEFAULT_GENERATOR.to_string()),
+            bounds: None,
+        }
+    }
+}
+
+/// Streaming reader of [Element]s in OSM XML
+///
+/// Elements are read in the order of the file. Other elements, e.g. changesets and notes, are
+/// skipped. Nodes without coordinates, which history files contain for deleted versions, are
+/// placed at `0, 0`. [Info] is filled if any of its attributes is present, with a version of
+/// 0 if the element has none, e.g. for new elements in files written by editors. Malformed
+/// elements end reading unless [ParseOptions] say otherwise.
+#[derive(Debug)]
+pub struct Reader<R, O = NoProgress> {
+    tokenizer: Tokenizer<R>,
+    observer: O,
+    header: Header,
+    recovery: Recovery,
+    reported: u64,
+    done: bool,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            tokenizer: Tokenizer::new(input),
+            observer: NoProgress,
+            header: Header::default(),
+            recovery: Recovery::default(),
+            reported: 0,
+            done: false,
+        }
+    }
+}
+
+impl<R, O> Reader<R, O> {
+    /// Reports bytes and elements read to `observer`
+    pub fn with_observer<P: Observer>(self, observer: P) -> Reader<R, P> {
+        Reader {
+            tokenizer: self.tokenizer,
+            observer,
+            header: self.header,
+            recovery: self.recovery,
+            reported: self.reported,
+            done: self.done,
+        }
+    }
+
+    /// Sets how malformed elements are handled
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.recovery.options = options;
+        self
+    }
+
+    /// Attributes of the file, which are available once the first element was read
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Errors of the malformed elements skipped so far with [ErrorPolicy::Warn]
+    ///
+    /// [ErrorPolicy::Warn]: crate::parse::ErrorPolicy::Warn
+    pub fn warnings(&self) -> &[Error] {
+        &self.recovery.warnings
+    }
+
+    /// Takes the [Reader::warnings], e.g. to report them while reading a large file
+    pub fn take_warnings(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.recovery.warnings)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.tokenizer.input
+    }
+}
+
+impl<R: BufRead, O: Observer> Reader<R, O> {
+    fn read_element(&mut self) -> Result<Option<Element>> {
+        while let Some(tag) = self.tokenizer.next_tag_or_malformed()? {
+            let offset = Some(self.tokenizer.tag_offset);
+            let line = self.tokenizer.tag_line;
+            let tag = match tag {
+                Ok(tag) => tag,
+                Err(err) => {
+                    self.recovery.skip(err.at_line(line))?;
+                    continue;
+                }
+            };
+            match (tag.kind, tag.name.as_str()) {
+                (TagKind::End, _) => {}
+                (_, "node" | "way" | "relation") => {
+                    let element = read_element(&mut self.tokenizer, &tag, false)?
+                        .and_then(|element| self.recovery.check(&element).map(|()| element));
+                    match element {
+                        Ok(element) => return Ok(Some(element)),
+                        Err(err) => self.recovery.skip(err.at_line(line))?,
+                    }
+                }
+                (_, "osm") => {
+                    self.header.version = tag.attribute("version").map(str::to_string);
+                    self.header.generator = tag.attribute("generator").map(str::to_string);
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(generator = ?self.header.generator, "read osm header");
+                }
+                (_, "bounds") => match bounds(&tag) {
+                    Ok(bbox) => self.header.bounds = Some(bbox),
+                    Err(message) => self
+                        .recovery
+                        .skip(Error::decode(offset, message).at_line(line))?,
+                },
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Reads the element started by `tag` along with its children
+///
+/// Nodes of `deleted` elements may lack coordinates, as in the `delete` section of osmChange.
+/// A malformed element is read to its end before its error is returned in the inner result,
+/// so that reading can go on with the next element. The outer result fails if that is not
+/// possible.
+pub(crate) fn read_element<R: BufRead>(
+    tokenizer: &mut Tokenizer<R>,
+    tag: &XmlTag,
+    deleted: bool,
+) -> Result<Result<Element>> {
+    let mut element = start_element(tag, deleted).map_err(|message| {
+        let err = Error::decode(Some(tokenizer.tag_offset), message);
+        match tag.attribute("id").and_then(|id| id.parse().ok()) {
+            Some(id) => err.with_element(member_type(&tag.name), Id(id)),
+            None => err,
+        }
+    });
+    if tag.kind == TagKind::Empty {
+        return Ok(element);
+    }
+    let context = |element: &Result<Element>, err: Error| match element {
+        Ok(element) => err.with_element(element.member_type(), element.id()),
+        Err(_) => err,
+    };
+    // Overpass nests the geometry of members in them, e.g. for `out geom`
+    let mut in_member = false;
+    while let Some(tag) = tokenizer.next_tag_or_malformed()? {
+        let offset = Some(tokenizer.tag_offset);
+        let tag = match tag {
+            Ok(tag) => tag,
+            Err(err) => {
+                if element.is_ok() {
+                    element = Err(context(&element, err));
+                }
+                continue;
+            }
+        };
+        match (tag.kind, tag.name.as_str()) {
+            (TagKind::End, "node" | "way" | "relation") => return Ok(element),
+            (TagKind::End, "member") => in_member = false,
+            (TagKind::End, _) => {}
+            (_, "nd") if in_member => {}
+            (_, "node" | "way" | "relation") => {
+                return Err(context(&element, Error::decode(offset, "nested element")));
+            }
+            (_, "tag" | "nd" | "member") => {
+                if let Ok(built) = &mut element {
+                    if let Err(message) = add_child(built, &tag) {
+                        element = Err(context(&element, Error::decode(offset, message)));
+                    }
+                }
+                in_member = tag.name == "member" && tag.kind == TagKind::Start;
+            }
+            _ => {}
+        }
+    }
+    Err(context(
+        &element,
+        Error::decode(Some(tokenizer.offset), "unexpected end of file"),
+    ))
+}
+
+impl<R: BufRead, O: Observer> Iterator for Reader<R, O> {
+    type Item = Result<Element>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let element = self
+            .read_element()
+            .map_err(|err| err.at_line(self.tokenizer.tag_line));
+        self.observer
+            .bytes_read(self.tokenizer.offset - self.reported);
+        self.reported = self.tokenizer.offset;
+        match &element {
+            Ok(Some(element)) => self.observer.element_read(element),
+            Ok(None) | Err(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(bytes = self.tokenizer.offset, "finished reading osm xml");
+                self.done = true;
+            }
+        }
+        element.transpose()
+    }
+}
+
+/// [Decode]r of OSM XML pushed in chunks, which yields the same as a [Reader]
+///
+/// Each element is decoded once all of it was pushed, so the input buffered is about the size
+/// of the largest element.
+#[derive(Debug)]
+pub struct Decoder {
+    reader: Reader<Buffer>,
+    scanner: Scanner,
+    ended: bool,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self {
+            reader: Reader::new(Buffer::default()),
+            scanner: Scanner::default(),
+            ended: false,
+        }
+    }
+
+    /// Attributes of the file, which are available once the first element was decoded
+    pub fn header(&self) -> &Header {
+        self.reader.header()
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decode for Decoder {
+    type Item = Element;
+
+    fn push(&mut self, data: &[u8]) {
+        let buffer = &mut self.reader.tokenizer.input;
+        buffer.push(data);
+        self.scanner.scan(buffer);
+    }
+
+    fn end(&mut self) {
+        self.ended = true;
+    }
+
+    fn decode(&mut self) -> Option<Result<Element>> {
+        if !self.ended {
+            self.scanner.ready = self.scanner.ready.checked_sub(1)?;
+        }
+        self.reader.next()
+    }
+}
+
+/// Writer of [Element]s in OSM XML
+///
+/// The header is written with the first element, and [Writer::finish] closes the root. Each
+/// element is written with a single call to the output, which should still be buffered if
+/// it is a file. [Info] with a version of 0 is written without a version attribute.
+#[derive(Debug)]
+pub struct Writer<W> {
+    output: W,
+    header: Header,
+    buf: String,
+    state: WriterState,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum WriterState {
+    Empty,
+    Open,
+    Finished,
+}
+
+impl<W: Write> Writer<W> {
+    /// Writer with the header from [Header::new]
+    pub fn new(output: W) -> Self {
+        Self::with_header(output, Header::new())
+    }
+
+    pub fn with_header(output: W, header: Header) -> Self {
+        Self {
+            output,
+            header,
+            buf: String::new(),
+            state: WriterState::Empty,
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = element.id().0)))]
+    pub fn write(&mut self, element: &Element) -> Result<()> {
+        self.buf.clear();
+        self.start()?;
+        write_element(&mut self.buf, element, 1);
+        self.output.write_all(self.buf.as_bytes())?;
+        Ok(())
+    }
+
+    /// Closes the root element, writing the header first if no element was written
+    pub fn finish(&mut self) -> Result<()> {
+        self.buf.clear();
+        self.start()?;
+        self.buf.push_str("</osm>\n");
+        self.output.write_all(self.buf.as_bytes())?;
+        self.output.flush()?;
+        self.state = WriterState::Finished;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    /// Adds the header to the buffer if not written yet
+    fn start(&mut self) -> Result<()> {
+        match self.state {
+            WriterState::Empty => {}
+            WriterState::Open => return Ok(()),
+            WriterState::Finished => {
+                return Err(Error::Validation {
+                    element: None,
+                    message: "writer is already finished".to_string(),
+                })
+            }
+        }
+        self.buf
+            .push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<osm");
+        if let Some(version) = &self.header.version {
+            attribute(&mut self.buf, "version", version);
+        }
+        if let Some(generator) = &self.header.generator {
+            attribute(&mut self.buf, "generator", generator);
+        }
+        self.buf.push_str(">\n");
+        if let Some(bounds) = &self.header.bounds {
+            self.buf.push_str(" <bounds");
+            attribute(&mut self.buf, "minlat", bounds.min_lat.normalized());
+            attribute(&mut self.buf, "minlon", bounds.min_lon.normalized());
+            attribute(&mut self.buf, "maxlat", bounds.max_lat.normalized());
+            attribute(&mut self.buf, "maxlon", bounds.max_lon.normalized());
+            self.buf.push_str("/>\n");
+        }
+        self.state = WriterState::Open;
+        Ok(())
+    }
+}
+
+impl<W: Write> Sink for Writer<W> {
+    type Error = Error;
+
+    fn write(&mut self, element: Element) -> Result<()> {
+        Writer::write(self, &element)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        Writer::finish(self)
+    }
+}
+
+/// [Writer] to a [tokio::io::AsyncWrite], e.g. a socket
+///
+/// Each element is written to the output once it is encoded, so the output need not be
+/// buffered.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct AsyncWriter<W> {
+    output: W,
+    writer: Writer<Vec<u8>>,
+}
+
+#[cfg(feature = "tokio")]
+impl<W: tokio::io::AsyncWrite + Unpin> AsyncWriter<W> {
+    /// Writer with the header from [Header::new]
+    pub fn new(output: W) -> Self {
+        Self::with_header(output, Header::new())
+    }
+
+    pub fn with_header(output: W, header: Header) -> Self {
+        Self {
+            output,
+            writer: Writer::with_header(vec![], header),
+        }
+    }
+
+    pub async fn write(&mut self, element: &Element) -> Result<()> {
+        self.writer.write(element)?;
+        self.write_encoded().await
+    }
+
+    /// Closes the root element, writing the header first if no element was written
+    pub async fn finish(&mut self) -> Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        self.writer.finish()?;
+        self.write_encoded().await?;
+        self.output.flush().await?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    async fn write_encoded(&mut self) -> Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        self.output.write_all(&self.writer.output).await?;
+        self.writer.output.clear();
+        Ok(())
+    }
+}
+
+/// Appends an element indented by `depth` spaces, and its children by one more
+pub(crate) fn write_element(buf: &mut String, element: &Element, depth: usize) {
+    let name = match element {
+        Element::Node(_) => "node",
+        Element::Way(_) => "way",
+        Element::Relation(_) => "relation",
+    };
+    let _ = write!(buf, "{:depth$}<{name} id=\"{}\"", "", element.id().0);
+    if let Some(info) = element.info() {
+        if let Some(visible) = info.visible {
+            attribute(buf, "visible", visible);
+        }
+        if info.version != 0 {
+            attribute(buf, "version", info.version);
+        }
+        if let Some(changeset) = info.changeset {
+            attribute(buf, "changeset", changeset);
+        }
+        if let Some(timestamp) = info.timestamp {
+            attribute(buf, "timestamp", crate::format_timestamp(timestamp));
+        }
+        if let Some(user) = &info.user {
+            attribute(buf, "user", user);
+        }
+        if let Some(uid) = info.uid {
+            attribute(buf, "uid", uid);
+        }
+    }
+    if let Element::Node(node) = element {
+        attribute(buf, "lat", node.lat.normalized());
+        attribute(buf, "lon", node.lon.normalized());
+    }
+
+    let tags = element.tags();
+    let empty = tags.is_empty()
+        && match element {
+            Element::Node(_) => true,
+            Element::Way(way) => way.refs.is_empty(),
+            Element::Relation(relation) => relation.members.is_empty(),
+        };
+    if empty {
+        buf.push_str("/>\n");
+        return;
+    }
+    buf.push_str(">\n");
+    match element {
+        Element::Node(_) => {}
+        Element::Way(way) => {
+            for id in &way.refs {
+                let _ = writeln!(buf, "{:depth$} <nd ref=\"{}\"/>", "", id.0);
+            }
+        }
+        Element::Relation(relation) => {
+            for member in &relation.members {
+                let ty = member.ty.as_str();
+                let _ = write!(
+                    buf,
+                    "{:depth$} <member type=\"{ty}\" ref=\"{}\"",
+                    "", member.id.0
+                );
+                attribute(buf, "role", member.role.as_deref().unwrap_or_default());
+                buf.push_str("/>\n");
+            }
+        }
+    }
+    for (key, value) in tags.iter_sorted() {
+        let _ = write!(buf, "{:depth$} <tag", "");
+        attribute(buf, "k", key);
+        attribute(buf, "v", value);
+        buf.push_str("/>\n");
+    }
+    let _ = writeln!(buf, "{:depth$}</{name}>", "");
+}
+
+/// Appends ` name="value"` with the value escaped
+pub(crate) fn attribute(buf: &mut String, name: &str, value: impl std::fmt::Display) {
+    let _ = write!(buf, " {name}=\"");
+    let start = buf.len();
+    let _ = write!(buf, "{value}");
+    let value = buf.split_off(start);
+    escape(buf, &value);
+    buf.push('"');
+}
+
+/// Escapes markup and whitespace that attribute value normalization would replace
+pub(crate) fn escape(buf: &mut String, value: &str) {
+    for c in value.chars() {
+        match c {
+            '&' => buf.push_str("&amp;"),
+            '<' => buf.push_str("&lt;"),
+            '>' => buf.push_str("&gt;"),
+            '"' => buf.push_str("&quot;"),
+            '\t' => buf.push_str("&#9;"),
+            '\n' => buf.push_str("&#10;"),
+            '\r' => buf.push_str("&#13;"),
+            c => buf.push(c),
+        }
+    }
+}
+
+fn member_type(name: &str) -> MemberType {
+    match name {
+        "node" => MemberType::Node,
+        "way" => MemberType::Way,
+        _ => MemberType::Relation,
+    }
+}
+
+/// Element from the attributes of a `node`, `way`, or `relation`
+fn start_element(tag: &XmlTag, deleted: bool) -> std::result::Result<Element, String> {
+    let id = Id(required(tag, "id")?);
+    let info = info(tag)?;
+    let tags = Tags::new();
+    Ok(match tag.name.as_str() {
+        "node" => {
+            let deleted = deleted || info.as_ref().and_then(|info| info.visible) == Some(false);
+            let coordinate = |name| match tag.attribute(name) {
+                None if deleted => Ok(Scalar::ZERO),
+                None => Err(format!("missing {name} attribute")),
+                Some(value) => {
+                    Scalar::parse_decimal(value).ok_or_else(|| format!("invalid {name} attribute"))
+                }
+            };
+            Element::Node(Node {
+                id,
+                tags,
+                info,
+                lat: coordinate("lat")?,
+                lon: coordinate("lon")?,
+            })
+        }
+        "way" => Element::Way(Way {
+            id,
+            tags,
+            info,
+            refs: Refs::new(),
+        }),
+        _ => Element::Relation(Relation {
+            id,
+            tags,
+            info,
+            members: Members::new(),
+        }),
+    })
+}
+
+/// Adds a `tag`, `nd`, or `member` to its element
+fn add_child(element: &mut Element, tag: &XmlTag) -> std::result::Result<(), String> {
+    match (tag.name.as_str(), element) {
+        ("tag", element) => {
+            let key = tag.attribute("k").ok_or("missing k attribute")?;
+            let value = tag.attribute("v").ok_or("missing v attribute")?;
+            element
+                .tags_mut()
+                .insert(TagString::from_ref(key), TagString::from_ref(value));
+        }
+        ("nd", Element::Way(way)) => way.refs.push(Id(required(tag, "ref")?)),
+        ("member", Element::Relation(relation)) => {
+            let ty = match tag.attribute("type") {
+                Some("node") => MemberType::Node,
+                Some("way") => MemberType::Way,
+                Some("relation") => MemberType::Relation,
+                Some(_) => return Err("invalid type attribute".to_string()),
+                None => return Err("missing type attribute".to_string()),
+            };
+            relation.members.push(Member {
+                id: Id(required(tag, "ref")?),
+                ty,
+                role: tag
+                    .attribute("role")
+                    .filter(|role| !role.is_empty())
+                    .map(TagString::from_ref),
+            });
+        }
+        (name, _) => return Err(format!("unexpected {name}")),
+    }
+    Ok(())
+}
+
+fn info(tag: &XmlTag) -> std::result::Result<Option<Info>, String> {
+    const ATTRIBUTES: [&str; 6] = [
+        "version",
+        "timestamp",
+        "changeset",
+        "uid",
+        "user",
+        "visible",
+    ];
+    if !ATTRIBUTES.iter().any(|name| tag.attribute(name).is_some()) {
+        return Ok(None);
+    }
+    let timestamp = match tag.attribute("timestamp") {
+        Some(value) => Some(crate::parse_timestamp(value).ok_or("invalid timestamp attribute")?),
+        None => None,
+    };
+    Ok(Some(Info {
+        version: optional(tag, "version")?.unwrap_or(0),
+        timestamp,
+        changeset: optional(tag, "changeset")?,
+        uid: optional(tag, "uid")?,
+        user: tag.attribute("user").map(TagString::from_ref),
+        visible: optional(tag, "visible")?,
+    }))
+}
+
+fn bounds(tag: &XmlTag) -> std::result::Result<Bbox, String> {
+    let coordinate = |name| {
+        tag.attribute(name)
+            .and_then(Scalar::parse_decimal)
+            .ok_or_else(|| format!("missing or invalid {name} attribute"))
+    };
+    Ok(Bbox::new(
+        coordinate("minlat")?,
+        coordinate("minlon")?,
+        coordinate("maxlat")?,
+        coordinate("maxlon")?,
+    ))
+}
+
+pub(crate) fn optional<T: FromStr>(
+    tag: &XmlTag,
+    name: &str,
+) -> std::result::Result<Option<T>, String> {
+    tag.attribute(name)
+        .map(|value| value.parse())
+        .transpose()
+        .map_err(|_| format!("invalid {name} attribute"))
+}
+
+pub(crate) fn required<T: FromStr>(tag: &XmlTag, name: &str) -> std::result::Result<T, String> {
+    optional(tag, name)?.ok_or_else(|| format!("missing {name} attribute"))
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum TagKind {
+    /// `<name>`
+    Start,
+    /// `</name>`
+    End,
+    /// `<name/>`
+    Empty,
+}
+
+/// XML tag with unescaped attribute values
+#[derive(Debug)]
+pub(crate) struct XmlTag {
+    pub kind: TagKind,
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+}
+
+impl XmlTag {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Splits XML into tags, skipping text, comments, CDATA, and declarations
+#[derive(Debug)]
+pub(crate) struct Tokenizer<R> {
+    pub input: R,
+    buf: Vec<u8>,
+    /// Text before the last tag
+    text: Vec<u8>,
+    /// Bytes consumed so far
+    pub offset: u64,
+    /// Position of the last tag returned
+    pub tag_offset: u64,
+    /// Line breaks consumed so far
+    lines: u64,
+    /// Line of the last tag returned, counted from 1
+    pub tag_line: u64,
+}
+
+impl<R: BufRead> Tokenizer<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            input,
+            buf: vec![],
+            text: vec![],
+            offset: 0,
+            tag_offset: 0,
+            lines: 0,
+            tag_line: 1,
+        }
+    }
+
+    pub fn next_tag(&mut self) -> Result<Option<XmlTag>> {
+        self.next_tag_or_malformed()?.transpose()
+    }
+
+    /// Reads the next tag like [Tokenizer::next_tag], with the error of a malformed tag in the
+    /// inner result, after which reading can go on
+    pub fn next_tag_or_malformed(&mut self) -> Result<Option<Result<XmlTag>>> {
+        loop {
+            self.text.clear();
+            let read = self.input.read_until(b'<', &mut self.text)?;
+            self.offset += read as u64;
+            self.lines += line_breaks(&self.text);
+            if self.text.pop() != Some(b'<') {
+                return Ok(None);
+            }
+            self.tag_offset = self.offset - 1;
+            self.tag_line = self.lines + 1;
+
+            self.buf.clear();
+            self.read_to_gt()?;
+            let skipped = [
+                (&b"!--"[..], &b"-->"[..]),
+                (b"![CDATA[", b"]]>"),
+                (b"?", b"?>"),
+                (b"!", b">"),
+            ];
+            match skipped
+                .into_iter()
+                .find(|(prefix, _)| self.buf.starts_with(prefix))
+            {
+                Some((prefix, terminator)) => {
+                    while !self.buf.ends_with(terminator)
+                        || self.buf.len() < prefix.len() + terminator.len()
+                    {
+                        self.read_to_gt()?;
+                    }
+                }
+                None => {
+                    while in_quotes(&self.buf) {
+                        self.read_to_gt()?;
+                    }
+                    return Ok(Some(self.parse()));
+                }
+            }
+        }
+    }
+
+    /// Unescaped text between the last tag and the one before it
+    ///
+    /// Text before a comment or CDATA section is lost.
+    pub fn text(&self) -> Result<String> {
+        std::str::from_utf8(&self.text)
+            .ok()
+            .and_then(|text| decode(text, false))
+            .ok_or_else(|| Error::decode(Some(self.tag_offset), "invalid text"))
+    }
+
+    /// Appends input up to and including the next `>`
+    fn read_to_gt(&mut self) -> Result<()> {
+        let start = self.buf.len();
+        let read = self.input.read_until(b'>', &mut self.buf)?;
+        self.offset += read as u64;
+        self.lines += line_breaks(&self.buf[start..]);
+        if read == 0 {
+            return Err(Error::decode(Some(self.offset), "unexpected end of file"));
+        }
+        Ok(())
+    }
+
+    fn parse(&self) -> Result<XmlTag> {
+        let error = |message| Error::decode(Some(self.tag_offset), message);
+        let text = std::str::from_utf8(&self.buf[..self.buf.len() - 1])
+            .map_err(|_| error("invalid UTF-8"))?;
+        let (kind, text) = if let Some(text) = text.strip_prefix('/') {
+            (TagKind::End, text)
+        } else if let Some(text) = text.strip_suffix('/') {
+            (TagKind::Empty, text)
+        } else {
+            (TagKind::Start, text)
+        };
+        let name_end = text
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(text.len());
+        if name_end == 0 {
+            return Err(error("missing tag name"));
+        }
+        let mut tag = XmlTag {
+            kind,
+            name: text[..name_end].to_string(),
+            attributes: vec![],
+        };
+
+        let mut rest = text[name_end..].trim_start();
+        while !rest.is_empty() {
+            let (name, value) = rest
+                .split_once('=')
+                .ok_or_else(|| error("attribute without value"))?;
+            let value = value.trim_start();
+            let quote = value
+                .chars()
+                .next()
+                .filter(|c| *c == '"' || *c == '\'')
+                .ok_or_else(|| error("unquoted attribute value"))?;
+            let (value, remaining) = value[1..]
+                .split_once(quote)
+                .ok_or_else(|| error("unterminated attribute value"))?;
+            let value = unescape(value).ok_or_else(|| error("invalid entity"))?;
+            tag.attributes.push((name.trim_end().to_string(), value));
+            rest = remaining.trim_start();
+        }
+        Ok(tag)
+    }
+}
+
+/// Finds the elements of input that is still arriving that were pushed completely, so that a
+/// [Tokenizer] reading them never runs out of input in the middle of one
+#[derive(Debug, Default)]
+pub(crate) struct Scanner {
+    /// Offset up to which the input was scanned
+    offset: u64,
+    /// Whether the input scanned ends inside an element
+    in_element: bool,
+    /// Complete elements that were not read yet
+    pub ready: usize,
+}
+
+impl Scanner {
+    /// Scans the input pushed since the last call
+    pub fn scan(&mut self, buffer: &Buffer) {
+        self.offset = self.offset.max(buffer.consumed());
+        while let Some((len, tag)) = scan_tag(buffer.unconsumed_after(self.offset)) {
+            self.offset += len as u64;
+            let Some((kind, b"node" | b"way" | b"relation")) = tag else {
+                continue;
+            };
+            match (kind, self.in_element) {
+                (TagKind::Start, false) => self.in_element = true,
+                (TagKind::End, false) => {}
+                (TagKind::Empty, false) => self.ready += 1,
+                (TagKind::End, true) => {
+                    self.in_element = false;
+                    self.ready += 1;
+                }
+                // Nested elements are errors, which the reader reports once it gets to them
+                (TagKind::Start | TagKind::Empty, true) => self.ready += 1,
+            }
+        }
+    }
+}
+
+/// Kind and name of a tag found by [Scanner]
+type ScannedTag<'a> = (TagKind, &'a [u8]);
+
+/// Length of the text or the tag at the start of `data`, with the kind and name of the tag,
+/// or [None] if `data` ends before them, skipping comments and declarations like [Tokenizer]
+fn scan_tag(data: &[u8]) -> Option<(usize, Option<ScannedTag<'_>>)> {
+    let find_gt = |from: usize| {
+        data[from..]
+            .iter()
+            .position(|&byte| byte == b'>')
+            .map(|i| from + i)
+    };
+    match data.iter().position(|&byte| byte == b'<') {
+        None if data.is_empty() => return None,
+        None => return Some((data.len(), None)),
+        Some(0) => {}
+        Some(start) => return Some((start, None)),
+    }
+    let mut end = find_gt(1)?;
+    let skipped = [
+        (&b"!--"[..], &b"-->"[..]),
+        (b"![CDATA[", b"]]>"),
+        (b"?", b"?>"),
+        (b"!", b">"),
+    ];
+    if let Some((prefix, terminator)) = skipped
+        .into_iter()
+        .find(|(prefix, _)| data[1..=end].starts_with(prefix))
+    {
+        while !data[1..=end].ends_with(terminator) || end < prefix.len() + terminator.len() {
+            end = find_gt(end + 1)?;
+        }
+        return Some((end + 1, None));
+    }
+    while in_quotes(&data[1..end]) {
+        end = find_gt(end + 1)?;
+    }
+    let text = &data[1..end];
+    let (kind, text) = if let Some(text) = text.strip_prefix(b"/") {
+        (TagKind::End, text)
+    } else if let Some(text) = text.strip_suffix(b"/") {
+        (TagKind::Empty, text)
+    } else {
+        (TagKind::Start, text)
+    };
+    let name_end = text
+        .iter()
+        .position(u8::is_ascii_whitespace)
+        .unwrap_or(text.len());
+    Some((end + 1, Some((kind, &text[..name_end]))))
+}
+
+fn line_breaks(data: &[u8]) -> u64 {
+    data.iter().filter(|&&byte| byte == b'\n').count() as u64
+}
+
+/// Whether the tag read so far ends inside a quoted attribute value
+fn in_quotes(tag: &[u8]) -> bool {
+    let mut quote = None;
+    for &byte in tag {
+        match quote {
+            None if byte == b'"' || byte == b'\'' => quote = Some(byte),
+            Some(open) if open == byte => quote = None,
+            _ => {}
+        }
+    }
+    quote.is_some()
+}
+
+/// Replaces entities and normalizes whitespace of an attribute value
+pub(crate) fn unescape(value: &str) -> Option<String> {
+    decode(value, true)
+}
+
+/// Replaces entities, and whitespace with spaces if `normalize`
+fn decode(value: &str, normalize: bool) -> Option<String> {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut rest = value;
+    loop {
+        let (text, entity) = match rest.split_once('&') {
+            Some((text, entity)) => (text, Some(entity)),
+            None => (rest, None),
+        };
+        unescaped.extend(text.chars().map(|c| {
+            if normalize && matches!(c, '\t' | '\n' | '\r') {
+                ' '
+            } else {
+                c
+            }
+        }));
+        let Some(entity) = entity else {
+            return Some(unescaped);
+        };
+        let (name, remaining) = entity.split_once(';')?;
+        unescaped.push(match name {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = match name.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => name.strip_prefix('#')?.parse().ok()?,
+                };
+                char::from_u32(code)?
+            }
+        });
+        rest = remaining;
+    }
+}